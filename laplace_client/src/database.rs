@@ -0,0 +1,265 @@
+use anyhow::anyhow;
+use laplace_common::api::{SqlQuery, SqlQueryResult};
+use laplace_common::lapp::Lapp as CommonLapp;
+use serde_json::Value;
+use wasm_web_helpers::fetch::JsonFetcher;
+use web_sys::HtmlTextAreaElement;
+use yew::{html, Context, Html};
+use yew_mdc_widgets::{dom, Button};
+
+use crate::i18n::label::*;
+use crate::{i18n, json_callback, Msg, Root};
+
+type Lapp = CommonLapp<String>;
+
+const PAGE_SIZE: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlRequest {
+    Tables,
+    RowCounts,
+    Rows,
+    Query,
+}
+
+#[derive(Debug)]
+pub enum DatabaseMsg {
+    Fetched(SqlRequest, SqlQueryResult),
+    SelectTable(String),
+    SwitchPage(usize),
+    RunQuery,
+}
+
+/// The read-only view of the lapp database, built on the admin SQL endpoint.
+pub struct DatabaseBrowser {
+    lapp_name: String,
+    tables: Vec<(String, Option<u64>)>,
+    table: Option<String>,
+    page: usize,
+    rows: Option<SqlQueryResult>,
+    query_result: Option<SqlQueryResult>,
+}
+
+impl DatabaseBrowser {
+    pub fn open(ctx: &Context<Root>, lapp_name: impl Into<String>) -> Self {
+        let browser = Self {
+            lapp_name: lapp_name.into(),
+            tables: Vec::new(),
+            table: None,
+            page: 0,
+            rows: None,
+            query_result: None,
+        };
+        browser.send_query(
+            ctx,
+            SqlRequest::Tables,
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        );
+        browser
+    }
+
+    pub fn update(&mut self, ctx: &Context<Root>, msg: DatabaseMsg) -> bool {
+        match msg {
+            DatabaseMsg::Fetched(SqlRequest::Tables, result) => {
+                self.tables = result
+                    .rows
+                    .into_iter()
+                    .filter_map(|row| row.into_iter().next())
+                    .filter_map(|name| name.as_str().map(|name| (name.to_string(), None)))
+                    .collect();
+
+                if !self.tables.is_empty() {
+                    let sql = self
+                        .tables
+                        .iter()
+                        .map(|(table, _)| {
+                            format!("SELECT {}, COUNT(*) FROM {}", quote_literal(table), quote_ident(table))
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" UNION ALL ");
+                    self.send_query(ctx, SqlRequest::RowCounts, sql);
+                }
+                true
+            },
+            DatabaseMsg::Fetched(SqlRequest::RowCounts, result) => {
+                for row in result.rows {
+                    if let [Value::String(table), count] = row.as_slice() {
+                        if let Some((_, table_count)) = self.tables.iter_mut().find(|(name, _)| name == table) {
+                            *table_count = count.as_u64();
+                        }
+                    }
+                }
+                true
+            },
+            DatabaseMsg::Fetched(SqlRequest::Rows, result) => {
+                self.rows = Some(result);
+                true
+            },
+            DatabaseMsg::Fetched(SqlRequest::Query, result) => {
+                self.query_result = Some(result);
+                true
+            },
+            DatabaseMsg::SelectTable(table) => {
+                self.table = Some(table);
+                self.page = 0;
+                self.fetch_rows(ctx);
+                false
+            },
+            DatabaseMsg::SwitchPage(page) => {
+                self.page = page;
+                self.fetch_rows(ctx);
+                false
+            },
+            DatabaseMsg::RunQuery => {
+                let sql = dom::existing::get_element_by_id::<HtmlTextAreaElement>("database-query").value();
+                if !sql.trim().is_empty() {
+                    self.send_query(ctx, SqlRequest::Query, sql);
+                }
+                false
+            },
+        }
+    }
+
+    pub fn view(&self, ctx: &Context<Root>) -> Html {
+        let i18n = i18n::load();
+
+        let tables = self
+            .tables
+            .iter()
+            .map(|(table, count)| {
+                let select_button = Button::new().label(table.as_str()).on_click({
+                    let table = table.clone();
+                    ctx.link()
+                        .callback(move |_| Msg::Database(DatabaseMsg::SelectTable(table.clone())))
+                });
+
+                html! {
+                    <div class = "lapps-table-row">
+                        <div class = "lapps-table-col">{ select_button }</div>
+                        <div class = "lapps-table-col">
+                            { count.map(|count| format!("{count} {}", i18n.text(ROWS))).unwrap_or_default() }
+                        </div>
+                    </div>
+                }
+            })
+            .collect::<Html>();
+
+        let rows = match (&self.table, &self.rows) {
+            (Some(table), Some(rows)) => {
+                let mut prev_button = Button::new().label(i18n.text(PREVIOUS)).on_click({
+                    let page = self.page.saturating_sub(1);
+                    ctx.link()
+                        .callback(move |_| Msg::Database(DatabaseMsg::SwitchPage(page)))
+                });
+                if self.page == 0 {
+                    prev_button = prev_button.disabled();
+                }
+
+                let mut next_button = Button::new().label(i18n.text(NEXT)).on_click({
+                    let page = self.page + 1;
+                    ctx.link()
+                        .callback(move |_| Msg::Database(DatabaseMsg::SwitchPage(page)))
+                });
+                if rows.rows.len() < PAGE_SIZE {
+                    next_button = next_button.disabled();
+                }
+
+                html! {
+                    <>
+                        <h2 class = "mdc-typography--headline6">
+                            { format!("{table}, {} {}", i18n.text(PAGE), self.page + 1) }
+                        </h2>
+                        { view_result(rows) }
+                        { prev_button }
+                        { next_button }
+                    </>
+                }
+            },
+            _ => html! {},
+        };
+
+        let query_button = Button::new()
+            .label(i18n.text(RUN_QUERY))
+            .on_click(ctx.link().callback(|_| Msg::Database(DatabaseMsg::RunQuery)));
+
+        html! {
+            <>
+                <h1 class = "title mdc-typography--headline5">
+                    { format!("{}: {}", i18n.text(DATABASE), self.lapp_name) }
+                </h1>
+                <div class = "lapps-table">{ tables }</div>
+                { rows }
+
+                <h2 class = "mdc-typography--headline6">{ i18n.text(READ_ONLY_QUERY) }</h2>
+                <div>
+                    <textarea id = "database-query" rows = "4" cols = "80" placeholder = "SELECT * FROM ..."></textarea>
+                </div>
+                { query_button }
+                { self.query_result.as_ref().map(view_result).unwrap_or_default() }
+            </>
+        }
+    }
+
+    fn fetch_rows(&self, ctx: &Context<Root>) {
+        if let Some(table) = &self.table {
+            let sql = format!(
+                "SELECT * FROM {} LIMIT {PAGE_SIZE} OFFSET {}",
+                quote_ident(table),
+                self.page * PAGE_SIZE
+            );
+            self.send_query(ctx, SqlRequest::Rows, sql);
+        }
+    }
+
+    fn send_query(&self, ctx: &Context<Root>, request: SqlRequest, sql: impl Into<String>) {
+        let query = SqlQuery {
+            sql: sql.into(),
+            read_only: true,
+        };
+
+        match serde_json::to_string(&query) {
+            Ok(body) => {
+                let callback = json_callback(ctx, move |result| Msg::Database(DatabaseMsg::Fetched(request, result)));
+                JsonFetcher::send_post_json(
+                    Lapp::main_uri(format!("lapp/{}/sql", self.lapp_name)),
+                    body,
+                    move |response_result| callback.emit(response_result),
+                );
+            },
+            Err(err) => ctx
+                .link()
+                .send_message(Msg::Error(anyhow!("Serialize query error: {err}"))),
+        }
+    }
+}
+
+fn view_result(result: &SqlQueryResult) -> Html {
+    html! {
+        <table class = "database-table">
+            <tr>
+                { result.columns.iter().map(|column| html! { <th>{ column }</th> }).collect::<Html>() }
+            </tr>
+            { result.rows.iter().map(|row| html! {
+                <tr>
+                    { row.iter().map(|value| html! { <td>{ view_value(value) }</td> }).collect::<Html>() }
+                </tr>
+            }).collect::<Html>() }
+        </table>
+    }
+}
+
+fn view_value(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".into(),
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
@@ -16,7 +16,86 @@ lazy_static! {
 pub mod label {
     pub const SETTINGS: &str = "Settings";
     pub const APPLICATIONS: &str = "Applications";
+    pub const LAUNCHER: &str = "Launcher";
+    pub const MANAGE: &str = "Manage";
+    pub const NO_LAPPS: &str = "There are no enabled applications";
     pub const ADD_LAPP: &str = "Add lapp";
+    pub const UPGRADE_LAPP: &str = "Upgrade lapp";
+    pub const QUARANTINED: &str = "Quarantined after repeated failures";
+    pub const ADDED_PERMISSIONS: &str = "Added permissions";
+    pub const REMOVED_PERMISSIONS: &str = "Removed permissions";
+    pub const NO_PERMISSION_CHANGES: &str = "No permission changes";
+    pub const PENDING_PERMISSION: &str = "pending";
+    pub const NETWORK: &str = "Network";
+    pub const NO_P2P_NODES: &str = "There are no started P2P nodes";
+    pub const PEER_ID: &str = "Peer ID";
+    pub const LISTEN_ADDRESSES: &str = "Listen addresses";
+    pub const TOPIC: &str = "Topic";
+    pub const PEERS: &str = "Peers";
+    pub const PEER_ID_OR_ADDRESS: &str = "Peer ID or address";
+    pub const DIAL: &str = "Dial";
+    pub const LABEL: &str = "Label";
+    pub const BAN: &str = "Ban";
+    pub const UNBAN: &str = "Unban";
+    pub const BANNED: &str = "Banned";
+    pub const CONNECTED: &str = "Connected";
+    pub const DISCOVERED: &str = "Discovered";
+    pub const DATABASE: &str = "Database";
+    pub const ROWS: &str = "rows";
+    pub const PAGE: &str = "page";
+    pub const PREVIOUS: &str = "Previous";
+    pub const NEXT: &str = "Next";
+    pub const READ_ONLY_QUERY: &str = "Read-only query";
+    pub const RUN_QUERY: &str = "Run";
+    pub const JOBS: &str = "Jobs";
+    pub const NO_JOBS: &str = "There are no scheduled jobs";
+    pub const RUN_NOW: &str = "Run now";
+    pub const NEXT_RUN: &str = "Next run";
+    pub const LAST_RUN: &str = "Last run";
+    pub const STORE: &str = "Store";
+    pub const NO_REGISTRY_LAPPS: &str = "There are no lapps in the registry";
+    pub const LATEST_VERSION: &str = "Latest version";
+    pub const INSTALLED_VERSION: &str = "Installed version";
+    pub const VERSION: &str = "Version";
+    pub const AUTHOR: &str = "Author";
+    pub const LICENSE: &str = "License";
+    pub const HOMEPAGE: &str = "Homepage";
+    pub const SERVER_SETTINGS: &str = "Server settings";
+    pub const CONFIG_FILE: &str = "Config file";
+    pub const HOT_SETTINGS: &str = "Applied without restart";
+    pub const EFFECTIVE_CONFIG: &str = "Effective configuration";
+    pub const RESTART_REQUIRED: &str =
+        "Other settings are changed in the config file and applied after the server restart";
+    pub const VALIDATE: &str = "Validate";
+    pub const APPLY: &str = "Apply";
+    pub const SETTINGS_VALID: &str = "Settings are valid";
+    pub const SETTINGS_APPLIED: &str = "Settings are applied";
+    pub const TASKS: &str = "Tasks";
+    pub const NO_TASKS: &str = "There are no tasks";
+    pub const CANCEL: &str = "Cancel";
+    pub const TASK_INSTALL: &str = "Install";
+    pub const TASK_UPGRADE: &str = "Upgrade";
+    pub const TASK_IMPORT: &str = "Import database of";
+    pub const TASK_RESTORE: &str = "Restore";
+    pub const TASK_RUNNING: &str = "Running";
+    pub const TASK_COMPLETED: &str = "Completed";
+    pub const TASK_FAILED: &str = "Failed";
+    pub const TASK_CANCELLED: &str = "Cancelled";
+    pub const USERS: &str = "Users";
+    pub const NO_USERS: &str = "There are no users";
+    pub const USER_NAME: &str = "User name";
+    pub const CREATE_USER: &str = "Create user";
+    pub const MAKE: &str = "Make";
+    pub const RESET_CREDENTIALS: &str = "Reset credentials";
+    pub const DELETE: &str = "Delete";
+    pub const CREATED: &str = "Created";
+    pub const SESSIONS: &str = "Sessions";
+    pub const NO_SESSIONS: &str = "There are no sessions since the server start";
+    pub const USER_AGENT: &str = "User agent";
+    pub const STARTED: &str = "Started";
+    pub const LAST_SEEN: &str = "Last seen";
+    pub const NEW_ACCESS_LINK: &str = "Access link of";
+    pub const SHOWN_ONCE: &str = "Copy the link now, it will not be shown again";
 }
 
 pub fn default_translations() -> HashMap<String, TextMap> {
@@ -25,7 +104,97 @@ pub fn default_translations() -> HashMap<String, TextMap> {
         [
             (label::SETTINGS.into(), "Settings".into()),
             (label::APPLICATIONS.into(), "Applications".into()),
+            (label::LAUNCHER.into(), "Launcher".into()),
+            (label::MANAGE.into(), "Manage".into()),
+            (label::NO_LAPPS.into(), "There are no enabled applications".into()),
             (label::ADD_LAPP.into(), "Add lapp".into()),
+            (label::UPGRADE_LAPP.into(), "Upgrade lapp".into()),
+            (label::QUARANTINED.into(), "Quarantined after repeated failures".into()),
+            (label::ADDED_PERMISSIONS.into(), "Added permissions".into()),
+            (label::REMOVED_PERMISSIONS.into(), "Removed permissions".into()),
+            (label::NO_PERMISSION_CHANGES.into(), "No permission changes".into()),
+            (label::PENDING_PERMISSION.into(), "pending".into()),
+            (label::NETWORK.into(), "Network".into()),
+            (label::NO_P2P_NODES.into(), "There are no started P2P nodes".into()),
+            (label::PEER_ID.into(), "Peer ID".into()),
+            (label::LISTEN_ADDRESSES.into(), "Listen addresses".into()),
+            (label::TOPIC.into(), "Topic".into()),
+            (label::PEERS.into(), "Peers".into()),
+            (label::PEER_ID_OR_ADDRESS.into(), "Peer ID or address".into()),
+            (label::DIAL.into(), "Dial".into()),
+            (label::LABEL.into(), "Label".into()),
+            (label::BAN.into(), "Ban".into()),
+            (label::UNBAN.into(), "Unban".into()),
+            (label::BANNED.into(), "Banned".into()),
+            (label::CONNECTED.into(), "Connected".into()),
+            (label::DISCOVERED.into(), "Discovered".into()),
+            (label::DATABASE.into(), "Database".into()),
+            (label::ROWS.into(), "rows".into()),
+            (label::PAGE.into(), "page".into()),
+            (label::PREVIOUS.into(), "Previous".into()),
+            (label::NEXT.into(), "Next".into()),
+            (label::READ_ONLY_QUERY.into(), "Read-only query".into()),
+            (label::RUN_QUERY.into(), "Run".into()),
+            (label::JOBS.into(), "Jobs".into()),
+            (label::NO_JOBS.into(), "There are no scheduled jobs".into()),
+            (label::RUN_NOW.into(), "Run now".into()),
+            (label::NEXT_RUN.into(), "Next run".into()),
+            (label::LAST_RUN.into(), "Last run".into()),
+            (label::STORE.into(), "Store".into()),
+            (
+                label::NO_REGISTRY_LAPPS.into(),
+                "There are no lapps in the registry".into(),
+            ),
+            (label::LATEST_VERSION.into(), "Latest version".into()),
+            (label::INSTALLED_VERSION.into(), "Installed version".into()),
+            (label::VERSION.into(), "Version".into()),
+            (label::AUTHOR.into(), "Author".into()),
+            (label::LICENSE.into(), "License".into()),
+            (label::HOMEPAGE.into(), "Homepage".into()),
+            (label::SERVER_SETTINGS.into(), "Server settings".into()),
+            (label::CONFIG_FILE.into(), "Config file".into()),
+            (label::HOT_SETTINGS.into(), "Applied without restart".into()),
+            (label::EFFECTIVE_CONFIG.into(), "Effective configuration".into()),
+            (
+                label::RESTART_REQUIRED.into(),
+                "Other settings are changed in the config file and applied after the server restart".into(),
+            ),
+            (label::VALIDATE.into(), "Validate".into()),
+            (label::APPLY.into(), "Apply".into()),
+            (label::SETTINGS_VALID.into(), "Settings are valid".into()),
+            (label::SETTINGS_APPLIED.into(), "Settings are applied".into()),
+            (label::TASKS.into(), "Tasks".into()),
+            (label::NO_TASKS.into(), "There are no tasks".into()),
+            (label::CANCEL.into(), "Cancel".into()),
+            (label::TASK_INSTALL.into(), "Install".into()),
+            (label::TASK_UPGRADE.into(), "Upgrade".into()),
+            (label::TASK_IMPORT.into(), "Import database of".into()),
+            (label::TASK_RESTORE.into(), "Restore".into()),
+            (label::TASK_RUNNING.into(), "Running".into()),
+            (label::TASK_COMPLETED.into(), "Completed".into()),
+            (label::TASK_FAILED.into(), "Failed".into()),
+            (label::TASK_CANCELLED.into(), "Cancelled".into()),
+            (label::USERS.into(), "Users".into()),
+            (label::NO_USERS.into(), "There are no users".into()),
+            (label::USER_NAME.into(), "User name".into()),
+            (label::CREATE_USER.into(), "Create user".into()),
+            (label::MAKE.into(), "Make".into()),
+            (label::RESET_CREDENTIALS.into(), "Reset credentials".into()),
+            (label::DELETE.into(), "Delete".into()),
+            (label::CREATED.into(), "Created".into()),
+            (label::SESSIONS.into(), "Sessions".into()),
+            (
+                label::NO_SESSIONS.into(),
+                "There are no sessions since the server start".into(),
+            ),
+            (label::USER_AGENT.into(), "User agent".into()),
+            (label::STARTED.into(), "Started".into()),
+            (label::LAST_SEEN.into(), "Last seen".into()),
+            (label::NEW_ACCESS_LINK.into(), "Access link of".into()),
+            (
+                label::SHOWN_ONCE.into(),
+                "Copy the link now, it will not be shown again".into(),
+            ),
         ]
         .into(),
     )]
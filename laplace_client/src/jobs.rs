@@ -0,0 +1,71 @@
+use laplace_common::api::JobInfo;
+use yew::{html, Context, Html};
+use yew_mdc_widgets::{Button, IconButton};
+
+use crate::i18n::label::*;
+use crate::{i18n, Msg, Root};
+
+pub fn view(ctx: &Context<Root>, jobs: &[JobInfo]) -> Html {
+    let i18n = i18n::load();
+
+    let refresh_button = IconButton::new()
+        .icon("refresh")
+        .on_click(ctx.link().callback(|_| Msg::FetchJobs));
+
+    let jobs = if jobs.is_empty() {
+        html! { <p>{ i18n.text(NO_JOBS) }</p> }
+    } else {
+        jobs.iter().map(|job| view_job(ctx, job)).collect::<Html>()
+    };
+
+    html! {
+        <>
+            <h1 class = "title mdc-typography--headline5">{ i18n.text(JOBS) } { refresh_button }</h1>
+            <div class = "lapps-table">{ jobs }</div>
+        </>
+    }
+}
+
+fn view_job(ctx: &Context<Root>, job: &JobInfo) -> Html {
+    let i18n = i18n::load();
+
+    let run_button = Button::new().label(i18n.text(RUN_NOW)).on_click({
+        let lapp_name = job.lapp_name.clone();
+        let job_name = job.name.clone();
+        ctx.link()
+            .callback(move |_| Msg::RunJob(lapp_name.clone(), job_name.clone()))
+    });
+
+    let last_result = match &job.last_result {
+        Some(result) => html! {
+            <span class = { if result.success { "status-success" } else { "status-failure" } }>
+                { &result.message }
+            </span>
+        },
+        None => html! {},
+    };
+
+    html! {
+        <>
+            <div class = "lapps-table-row">
+                <div class = "lapps-table-col">
+                    <big>{ format!("{} / {}", job.lapp_name, job.name) }</big>
+                </div>
+                <div class = "lapps-table-col"><code>{ &job.schedule }</code></div>
+                <div class = "lapps-table-col">{ run_button }</div>
+            </div>
+            <div class = "lapps-table-row">
+                <div class = "lapps-table-col">
+                    { format!("{}: {}", i18n.text(NEXT_RUN), job.next_run.as_deref().unwrap_or("-")) }
+                </div>
+                <div class = "lapps-table-col">
+                    { format!("{}: {}", i18n.text(LAST_RUN), job.last_run.as_deref().unwrap_or("-")) }
+                </div>
+            </div>
+            <div class = "lapps-table-row">
+                <div class = "lapps-table-col">{ last_result }</div>
+            </div>
+            <br />
+        </>
+    }
+}
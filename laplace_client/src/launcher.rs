@@ -0,0 +1,69 @@
+use laplace_common::lapp::{Lapp as CommonLapp, LappSettings};
+use yew::{html, Context, Html};
+use yew_mdc_widgets::Button;
+
+use crate::i18n::label::*;
+use crate::{i18n, lapp_ref, Msg, Page, Root};
+
+type Lapp = CommonLapp<String>;
+
+pub fn view(ctx: &Context<Root>, lapps: &[LappSettings]) -> Html {
+    let i18n = i18n::load();
+    let cards = lapps
+        .iter()
+        .filter(|lapp_settings| lapp_settings.enabled())
+        .map(view_card)
+        .collect::<Vec<_>>();
+
+    let content = if cards.is_empty() {
+        html! { <p>{ i18n.text(NO_LAPPS) }</p> }
+    } else {
+        html! { <div class = "launcher-grid">{ for cards }</div> }
+    };
+
+    html! {
+        <>
+            { view_tabs(ctx, &Page::Launcher) }
+            { content }
+        </>
+    }
+}
+
+/// The tabs switching between the launcher and the lapps management table.
+pub fn view_tabs(ctx: &Context<Root>, page: &Page) -> Html {
+    let i18n = i18n::load();
+    let tab = |label, tab_page: Page| {
+        let mut button = Button::new().label(i18n.text(label));
+        if *page == tab_page {
+            button = button.unelevated();
+        }
+        button.on_click(ctx.link().callback(move |_| Msg::SwitchPage(tab_page.clone())))
+    };
+
+    html! {
+        <div class = "launcher-tabs">
+            { tab(LAUNCHER, Page::Launcher) }
+            { tab(MANAGE, Page::Lapps) }
+        </div>
+    }
+}
+
+fn view_card(lapp_settings: &LappSettings) -> Html {
+    let icon = if lapp_settings.application.icon.is_some() {
+        let icon_uri = Lapp::main_uri(format!("lapp/{}/icon", lapp_settings.name()));
+        html! { <img class = "launcher-icon" src = { icon_uri } alt = "" /> }
+    } else {
+        let letter = lapp_settings.title().chars().next().unwrap_or_default().to_uppercase();
+        html! { <div class = "launcher-icon launcher-letter">{ letter.to_string() }</div> }
+    };
+
+    html! {
+        <a class = "launcher-card mdc-card" href = { lapp_ref(lapp_settings) }>
+            { icon }
+            <div class = "launcher-title mdc-typography--subtitle1">{ lapp_settings.title() }</div>
+            if let Some(description) = &lapp_settings.application.description {
+                <div class = "launcher-description mdc-typography--body2">{ description }</div>
+            }
+        </a>
+    }
+}
@@ -3,7 +3,9 @@ use std::collections::HashMap;
 use std::convert::TryFrom;
 
 use anyhow::{anyhow, Context as _, Error};
-use laplace_common::api::{Response as CommonLappResponse, UpdateQuery};
+use laplace_common::api::{
+    JobInfo, P2pAction, P2pStats, RegistryIndex, Response as CommonLappResponse, UpdateQuery, UpgradeDiff,
+};
 use laplace_common::lapp::{Lapp as CommonLapp, LappSettings, Permission};
 use laplace_yew::error::{Errors, ErrorsMsg, MsgError};
 use wasm_web_helpers::error::Result;
@@ -20,16 +22,50 @@ use yew_mdc_widgets::{
     ListItem, MdcWidget, Switch, TopAppBar,
 };
 
+use self::database::{DatabaseBrowser, DatabaseMsg};
 use self::i18n::label::*;
+use self::settings::{SettingsMsg, SettingsPage};
+use self::tasks::{TasksMsg, TasksPanel};
+use self::users::{UsersMsg, UsersPage};
 
+mod database;
 mod i18n;
+mod jobs;
+mod launcher;
+mod network;
+mod settings;
+mod store;
+mod tasks;
+mod users;
 
 type ErrorsLink = Scope<Errors<Root>>;
 type Lapp = CommonLapp<String>;
 type LappResponse = CommonLappResponse<'static, Cow<'static, LappSettings>>;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Page {
+    Launcher,
+    Lapps,
+    Network,
+    Jobs,
+    Store,
+    Settings,
+    Users,
+    Database(String),
+}
+
 struct Root {
+    page: Page,
     lapps: Vec<LappSettings>,
+    p2p_stats: Vec<P2pStats>,
+    jobs: Vec<JobInfo>,
+    registry: RegistryIndex,
+    database: Option<DatabaseBrowser>,
+    settings: Option<SettingsPage>,
+    users: Option<UsersPage>,
+    tasks: TasksPanel,
+    upgrade: Option<UpgradeDiff>,
+    should_open_upgrade: bool,
     errors_link: Option<ErrorsLink>,
 }
 
@@ -67,10 +103,27 @@ impl PermissionUpdate {
 #[derive(Debug)]
 enum Msg {
     Fetch(LappResponse),
+    FetchP2pStats,
+    P2pStatsFetched(Vec<P2pStats>),
+    ApplyP2pAction(String, P2pAction),
+    FetchJobs,
+    JobsFetched(Vec<JobInfo>),
+    RunJob(String, String),
+    FetchRegistry,
+    RegistryFetched(RegistryIndex),
+    InstallFromRegistry(String),
+    SwitchPage(Page),
+    Database(DatabaseMsg),
+    Settings(SettingsMsg),
+    Users(UsersMsg),
+    Tasks(TasksMsg),
     SwitchLapp(String),
     SwitchAutoload(String),
     UpdatePermission(PermissionUpdate),
     AddLar,
+    UploadUpgrade,
+    ConfirmUpgrade(String),
+    CancelUpgrade(String),
     Error(Error),
     SetErrorsLink(ErrorsLink),
 }
@@ -94,7 +147,17 @@ impl Component for Root {
     fn create(ctx: &Context<Self>) -> Self {
         Self::send_get(ctx, Lapp::main_uri("lapps"));
         Self {
+            page: Page::Launcher,
             lapps: vec![],
+            p2p_stats: vec![],
+            jobs: vec![],
+            registry: RegistryIndex::default(),
+            database: None,
+            settings: None,
+            users: None,
+            tasks: TasksPanel::connect(ctx),
+            upgrade: None,
+            should_open_upgrade: false,
             errors_link: None,
         }
     }
@@ -109,6 +172,11 @@ impl Component for Root {
                         .collect();
                     true
                 },
+                LappResponse::Upgrade { upgrade } => {
+                    self.upgrade = Some(upgrade);
+                    self.should_open_upgrade = true;
+                    true
+                },
                 LappResponse::Updated { updated } => {
                     if let Some(lapp_settings) = self
                         .lapps
@@ -139,6 +207,11 @@ impl Component for Root {
                             should_render = lapp_settings.permissions.deny(permission);
                         }
 
+                        // The profile may change several permissions, so the lapps are fetched again
+                        if updated.permission_profile.is_some() {
+                            Self::send_get(ctx, Lapp::main_uri("lapps"));
+                        }
+
                         should_render
                     } else {
                         console::error!(&format!("Unknown lapp name: {}", updated.lapp_name));
@@ -146,6 +219,92 @@ impl Component for Root {
                     }
                 },
             },
+            Msg::FetchP2pStats => {
+                let callback = json_callback(ctx, Msg::P2pStatsFetched);
+                JsonFetcher::send_get(Lapp::main_uri("p2p"), move |response_result| {
+                    callback.emit(response_result)
+                });
+                false
+            },
+            Msg::P2pStatsFetched(stats) => {
+                self.p2p_stats = stats;
+                true
+            },
+            Msg::ApplyP2pAction(lapp_name, action) => {
+                if let Ok(body) = serde_json::to_string(&action)
+                    .context("Serialize P2P action error")
+                    .msg_error_map(ctx.link())
+                {
+                    let callback = json_callback(ctx, Msg::P2pStatsFetched);
+                    JsonFetcher::send_post_json(
+                        Lapp::main_uri(format!("p2p/{lapp_name}")),
+                        body,
+                        move |response_result| callback.emit(response_result),
+                    );
+                }
+                false
+            },
+            Msg::FetchJobs => {
+                let callback = json_callback(ctx, Msg::JobsFetched);
+                JsonFetcher::send_get(Lapp::main_uri("jobs"), move |response_result| {
+                    callback.emit(response_result)
+                });
+                false
+            },
+            Msg::JobsFetched(jobs) => {
+                self.jobs = jobs;
+                true
+            },
+            Msg::RunJob(lapp_name, job_name) => {
+                let callback = json_callback(ctx, Msg::JobsFetched);
+                JsonFetcher::send_post_json(
+                    Lapp::main_uri(format!("jobs/{lapp_name}/{job_name}/run")),
+                    "",
+                    move |response_result| callback.emit(response_result),
+                );
+                false
+            },
+            Msg::FetchRegistry => {
+                let callback = json_callback(ctx, Msg::RegistryFetched);
+                JsonFetcher::send_get(Lapp::main_uri("registry"), move |response_result| {
+                    callback.emit(response_result)
+                });
+                false
+            },
+            Msg::RegistryFetched(registry) => {
+                self.registry = registry;
+                true
+            },
+            Msg::InstallFromRegistry(lapp_name) => {
+                Self::send_post_json(ctx, Lapp::main_uri(format!("registry/{lapp_name}/install")), "");
+                false
+            },
+            Msg::SwitchPage(page) => {
+                match &page {
+                    Page::Launcher | Page::Lapps => {},
+                    Page::Network => ctx.link().send_message(Msg::FetchP2pStats),
+                    Page::Jobs => ctx.link().send_message(Msg::FetchJobs),
+                    Page::Store => ctx.link().send_message(Msg::FetchRegistry),
+                    Page::Settings => self.settings = Some(SettingsPage::open(ctx)),
+                    Page::Users => self.users = Some(UsersPage::open(ctx)),
+                    Page::Database(lapp_name) => self.database = Some(DatabaseBrowser::open(ctx, lapp_name)),
+                }
+                self.page = page;
+                true
+            },
+            Msg::Database(msg) => match self.database.as_mut() {
+                Some(database) => database.update(ctx, msg),
+                None => false,
+            },
+            Msg::Settings(msg) => match self.settings.as_mut() {
+                Some(settings) => settings.update(ctx, msg),
+                None => false,
+            },
+            Msg::Users(msg) => match self.users.as_mut() {
+                Some(users) => users.update(ctx, msg),
+                None => false,
+            },
+            Msg::Tasks(msg) => self.tasks.update(ctx, msg),
             Msg::SwitchLapp(name) => {
                 if let Some(lapp_settings) = self.lapps.iter_mut().find(|lapp| lapp.name() == name) {
                     lapp_settings.switch_enabled();
@@ -206,7 +365,17 @@ impl Component for Root {
                 }
                 false
             },
-            Msg::AddLar => false,
+            Msg::AddLar | Msg::UploadUpgrade => false,
+            Msg::ConfirmUpgrade(lapp_name) => {
+                self.upgrade = None;
+                Self::send_post_json(ctx, Lapp::main_uri(format!("lapp/{lapp_name}/upgrade/confirm")), "");
+                true
+            },
+            Msg::CancelUpgrade(lapp_name) => {
+                self.upgrade = None;
+                Self::send_post_json(ctx, Lapp::main_uri(format!("lapp/{lapp_name}/upgrade/cancel")), "");
+                true
+            },
             Msg::Error(error) => {
                 let error = error.to_string();
                 console::error!(&error);
@@ -230,6 +399,66 @@ impl Component for Root {
             .title(html! { <h3 tabindex = 0>{ i18n.text(SETTINGS) }</h3> })
             .content(
                 List::ul()
+                    .item(
+                        ListItem::new()
+                            .icon("apps")
+                            .text(i18n.text(APPLICATIONS))
+                            .attr("tabindex", "0")
+                            .on_click(ctx.link().callback(|_| {
+                                close_drawer();
+                                Msg::SwitchPage(Page::Launcher)
+                            })),
+                    )
+                    .item(
+                        ListItem::new()
+                            .icon("hub")
+                            .text(i18n.text(NETWORK))
+                            .attr("tabindex", "0")
+                            .on_click(ctx.link().callback(|_| {
+                                close_drawer();
+                                Msg::SwitchPage(Page::Network)
+                            })),
+                    )
+                    .item(
+                        ListItem::new()
+                            .icon("schedule")
+                            .text(i18n.text(JOBS))
+                            .attr("tabindex", "0")
+                            .on_click(ctx.link().callback(|_| {
+                                close_drawer();
+                                Msg::SwitchPage(Page::Jobs)
+                            })),
+                    )
+                    .item(
+                        ListItem::new()
+                            .icon("storefront")
+                            .text(i18n.text(STORE))
+                            .attr("tabindex", "0")
+                            .on_click(ctx.link().callback(|_| {
+                                close_drawer();
+                                Msg::SwitchPage(Page::Store)
+                            })),
+                    )
+                    .item(
+                        ListItem::new()
+                            .icon("settings")
+                            .text(i18n.text(SERVER_SETTINGS))
+                            .attr("tabindex", "0")
+                            .on_click(ctx.link().callback(|_| {
+                                close_drawer();
+                                Msg::SwitchPage(Page::Settings)
+                            })),
+                    )
+                    .item(
+                        ListItem::new()
+                            .icon("people")
+                            .text(i18n.text(USERS))
+                            .attr("tabindex", "0")
+                            .on_click(ctx.link().callback(|_| {
+                                close_drawer();
+                                Msg::SwitchPage(Page::Users)
+                            })),
+                    )
                     .divider()
                     .item(
                         ListItem::new()
@@ -237,9 +466,7 @@ impl Component for Root {
                             .text(i18n.text(ADD_LAPP))
                             .attr("tabindex", "0")
                             .on_click(|_| {
-                                dom::existing::get_element_by_id::<Element>("app-drawer")
-                                    .get("MDCDrawer")
-                                    .set("open", false);
+                                close_drawer();
                                 Dialog::open_existing("add-lapp-dialog");
                             }),
                     )
@@ -251,6 +478,7 @@ impl Component for Root {
             .id("top-app-bar")
             .title("laplace")
             .navigation_item(IconButton::new().icon("menu"))
+            .action_item(self.tasks.view_button(ctx))
             .enable_shadow_when_scroll_window()
             .on_navigation(|_| {
                 let drawer = dom::existing::get_element_by_id::<Element>("app-drawer").get("MDCDrawer");
@@ -297,6 +525,32 @@ impl Component for Root {
                 })
             }));
 
+        let content = match self.page {
+            Page::Launcher => launcher::view(ctx, &self.lapps),
+            Page::Lapps => html! {
+                <>
+                    { launcher::view_tabs(ctx, &self.page) }
+                    <div class = "lapps-table">
+                        { self.lapps.iter().map(|lapp| self.view_lapp(ctx, lapp)).collect::<Html>() }
+                    </div>
+                </>
+            },
+            Page::Network => network::view(ctx, &self.p2p_stats),
+            Page::Jobs => jobs::view(ctx, &self.jobs),
+            Page::Store => store::view(ctx, &self.registry, &self.lapps),
+            Page::Settings => self
+                .settings
+                .as_ref()
+                .map(|settings| settings.view(ctx))
+                .unwrap_or_default(),
+            Page::Users => self.users.as_ref().map(|users| users.view(ctx)).unwrap_or_default(),
+            Page::Database(_) => self
+                .database
+                .as_ref()
+                .map(|database| database.view(ctx))
+                .unwrap_or_default(),
+        };
+
         html! {
             <>
                 { drawer }
@@ -305,13 +559,12 @@ impl Component for Root {
                 <div class = { classes!("app-content", Drawer::APP_CONTENT_CLASS) }>
                     { top_app_bar }
                     { add_lapp_dialog }
+                    { self.view_upgrade_dialog(ctx) }
+                    { self.tasks.view(ctx) }
 
                     <div class = "mdc-top-app-bar--fixed-adjust">
                         <div class = "content-container">
-                            <h1 class = "title mdc-typography--headline5">{ i18n.text(APPLICATIONS) }</h1>
-                            <div class = "lapps-table">
-                                { self.lapps.iter().map(|lapp| self.view_lapp(ctx, lapp)).collect::<Html>() }
-                            </div>
+                            { content }
                         </div>
                     </div>
                 </div>
@@ -322,6 +575,12 @@ impl Component for Root {
 
     fn rendered(&mut self, _ctx: &Context<Self>, _first_render: bool) {
         auto_init();
+        self.tasks.rendered();
+
+        if self.should_open_upgrade {
+            self.should_open_upgrade = false;
+            Dialog::open_existing("upgrade-lapp-dialog");
+        }
     }
 }
 
@@ -336,7 +595,72 @@ impl Root {
         JsonFetcher::send_post_json(uri, body, move |response_result| callback.emit(response_result));
     }
 
+    fn view_upgrade_dialog(&self, ctx: &Context<Self>) -> Html {
+        let i18n = i18n::load();
+        let Some(upgrade) = &self.upgrade else {
+            return html! {};
+        };
+
+        let permissions_list = |label, permissions: &[Permission]| {
+            if permissions.is_empty() {
+                html! {}
+            } else {
+                html! {
+                    <div>
+                        <h3>{ i18n.text(label) }</h3>
+                        { ChipSet::new().chips(permissions.iter().map(|permission| Chip::simple().text(permission.as_str()))) }
+                    </div>
+                }
+            }
+        };
+
+        let versions = match (&upgrade.current_version, &upgrade.upgrade_version) {
+            (None, None) => html! {},
+            (current, new) => html! {
+                <p>{ format!("{} → {}", current.as_deref().unwrap_or("?"), new.as_deref().unwrap_or("?")) }</p>
+            },
+        };
+
+        let content = if upgrade.is_empty() {
+            html! {
+                <>
+                    { versions }
+                    <p>{ i18n.text(NO_PERMISSION_CHANGES) }</p>
+                </>
+            }
+        } else {
+            html! {
+                <>
+                    { versions }
+                    { permissions_list(ADDED_PERMISSIONS, &upgrade.added_permissions) }
+                    { permissions_list(REMOVED_PERMISSIONS, &upgrade.removed_permissions) }
+                </>
+            }
+        };
+
+        Dialog::new()
+            .id("upgrade-lapp-dialog")
+            .title(html! { <h2 tabindex = 0> { format!("{}: {}", i18n.text(UPGRADE_LAPP), upgrade.lapp_name) } </h2> })
+            .content(content)
+            .action(Button::new().label("Cancel").class(Dialog::BUTTON_CLASS).on_click({
+                let lapp_name = upgrade.lapp_name.clone();
+                ctx.link().callback(move |_| {
+                    Dialog::close_existing("upgrade-lapp-dialog");
+                    Msg::CancelUpgrade(lapp_name.clone())
+                })
+            }))
+            .action(Button::new().label("Upgrade").class(Dialog::BUTTON_CLASS).on_click({
+                let lapp_name = upgrade.lapp_name.clone();
+                ctx.link().callback(move |_| {
+                    Dialog::close_existing("upgrade-lapp-dialog");
+                    Msg::ConfirmUpgrade(lapp_name.clone())
+                })
+            }))
+            .into()
+    }
+
     fn view_lapp(&self, ctx: &Context<Self>, lapp_settings: &LappSettings) -> Html {
+        let i18n = i18n::load();
         let lapp_name = lapp_settings.name().to_string();
 
         let enable_switch = Switch::new()
@@ -356,10 +680,15 @@ impl Root {
             .id(format!("{}--permissions", lapp_settings.name()))
             .filter()
             .chips(lapp_settings.permissions.required().map(|permission| {
+                let text = if lapp_settings.permissions.is_pending(permission) {
+                    format!("{} ({})", permission.as_str(), i18n.text(PENDING_PERMISSION))
+                } else {
+                    permission.as_str().to_string()
+                };
                 Chip::simple()
                     .id(format!("{}--{}", lapp_settings.name(), permission.as_str()))
                     .checkmark()
-                    .text(permission.as_str())
+                    .text(text)
                     .select(lapp_settings.permissions.is_allowed(permission))
             }))
             .on_selection(ctx.link().callback(|event: CustomEvent| {
@@ -368,21 +697,99 @@ impl Root {
                     .unwrap_or_else(Msg::Error)
             }));
 
-        let lapp_ref = if let Some(access_token) = lapp_settings.application.access_token.as_deref() {
-            format!("{}?access_token={access_token}", lapp_settings.name())
+        let upgrade_selector_id = format!("{}--upgrade", lapp_settings.name());
+        let upgrade_button = Button::new().label(i18n.text(UPGRADE_LAPP)).on_click({
+            let upgrade_selector_id = upgrade_selector_id.clone();
+            move |_| dom::existing::get_element_by_id::<HtmlInputElement>(&upgrade_selector_id).click()
+        });
+        let upgrade_selector = html! {
+            <input id = { upgrade_selector_id.clone() } type = "file" accept = ".lar, .zip" style = "display: none" onchange = {
+                let send_lar_callback = callback(ctx);
+                let lapp_name = lapp_settings.name().to_string();
+
+                ctx.link().callback(move |_| {
+                    let input = dom::existing::get_element_by_id::<HtmlInputElement>(&upgrade_selector_id);
+                    if let Some(file) = input.files().and_then(|files| files.get(0)) {
+                        let form_data = match FormData::new() {
+                            Ok(form_data) => form_data,
+                            Err(err) => return Msg::Error(anyhow!("Creation form data error: {:?}", err)),
+                        };
+                        if let Err(err) = form_data.append_with_blob("lar", &file) {
+                            return Msg::Error(anyhow!("Append file to form data error: {:?}", err));
+                        }
+
+                        let callback = send_lar_callback.clone();
+                        JsonFetcher::send_post(
+                            Lapp::main_uri(format!("lapp/{lapp_name}/upgrade")),
+                            form_data,
+                            move |response_result| callback.emit(response_result),
+                        );
+                    }
+                    input.set_value("");
+                    Msg::UploadUpgrade
+                })
+            } />
+        };
+
+        let database_button = if lapp_settings.permissions.is_allowed(Permission::Database) {
+            let lapp_name = lapp_settings.name().to_string();
+            Button::new()
+                .label(i18n.text(DATABASE))
+                .on_click(
+                    ctx.link()
+                        .callback(move |_| Msg::SwitchPage(Page::Database(lapp_name.clone()))),
+                )
+                .into()
+        } else {
+            html! {}
+        };
+
+        let application = &lapp_settings.application;
+        let metadata = [
+            (VERSION, application.version.as_deref()),
+            (AUTHOR, application.author.as_deref()),
+            (LICENSE, application.license.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| {
+            value.map(|value| html! { <span>{ format!("{}: {value} ", i18n.text(label)) }</span> })
+        });
+        let homepage = match application.homepage_url() {
+            Some(homepage) => {
+                html! { <a href = { homepage.to_string() } target = "_blank" rel = "noopener noreferrer">{ i18n.text(HOMEPAGE) }</a> }
+            },
+            None => html! {},
+        };
+
+        let quarantined = if lapp_settings.is_quarantined() {
+            html! {
+                <div class = "lapps-table-col status-failure">
+                    { i18n.text(QUARANTINED) }
+                </div>
+            }
         } else {
-            lapp_settings.name().to_string()
+            html! {}
         };
 
         html! {
             <>
                 <div class = "lapps-table-row">
                     <div class = "lapps-table-col">
-                        <big><a href = { lapp_ref }>{ lapp_settings.title() }</a></big>
+                        <big><a href = { lapp_ref(lapp_settings) }>{ lapp_settings.title() }</a></big>
                     </div>
                     <div class = "lapps-table-col">
                         { enable_switch }
                     </div>
+                    { quarantined }
+                </div>
+                <div class = "lapps-table-row">
+                    <div class = "lapps-table-col mdc-typography--body2">
+                        if let Some(description) = &application.description {
+                            <div>{ description }</div>
+                        }
+                        { for metadata }
+                        { homepage }
+                    </div>
                 </div>
                 <div class = "lapps-table-row">
                     <div class = "lapps-table-col">
@@ -396,6 +803,13 @@ impl Root {
                         { permissions }
                     </div>
                 </div>
+                <div class = "lapps-table-row">
+                    <div class = "lapps-table-col">
+                        { upgrade_button }
+                        { upgrade_selector }
+                        { database_button }
+                    </div>
+                </div>
                 <br />
             </>
         }
@@ -419,6 +833,41 @@ fn callback(ctx: &Context<Root>) -> Callback<Result<(Response, Result<LappRespon
         })
 }
 
+/// Returns the lapp link, with the access token if the lapp has it.
+fn lapp_ref(lapp_settings: &LappSettings) -> String {
+    if let Some(access_token) = lapp_settings.application.access_token.as_deref() {
+        format!("{}?access_token={access_token}", lapp_settings.name())
+    } else {
+        lapp_settings.name().to_string()
+    }
+}
+
+fn json_callback<T: 'static>(
+    ctx: &Context<Root>,
+    into_msg: impl Fn(T) -> Msg + 'static,
+) -> Callback<Result<(Response, Result<T>)>> {
+    ctx.link()
+        .callback(move |response_result: Result<(Response, Result<T>)>| {
+            response_result
+                .map(|(response, body)| {
+                    body.map(&into_msg).unwrap_or_else(|err| {
+                        Msg::Error(anyhow!(
+                            "Parse response body error: {:?}, for request {}",
+                            err,
+                            response.url(),
+                        ))
+                    })
+                })
+                .unwrap_or_else(|err| Msg::Error(err.into()))
+        })
+}
+
+fn close_drawer() {
+    dom::existing::get_element_by_id::<Element>("app-drawer")
+        .get("MDCDrawer")
+        .set("open", false);
+}
+
 fn main() {
     let root = dom::existing::get_element_by_id("root");
     yew::Renderer::<Root>::with_root(root).render();
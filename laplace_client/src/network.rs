@@ -0,0 +1,154 @@
+use laplace_common::api::{P2pAction, P2pStats, PeerStats};
+use web_sys::HtmlInputElement;
+use yew::{html, Context, Html};
+use yew_mdc_widgets::{dom, Button, IconButton};
+
+use crate::i18n::label::*;
+use crate::{i18n, Msg, Root};
+
+pub fn view(ctx: &Context<Root>, stats: &[P2pStats]) -> Html {
+    let i18n = i18n::load();
+
+    let refresh_button = IconButton::new()
+        .icon("refresh")
+        .on_click(ctx.link().callback(|_| Msg::FetchP2pStats));
+
+    let nodes = if stats.is_empty() {
+        html! { <p>{ i18n.text(NO_P2P_NODES) }</p> }
+    } else {
+        stats.iter().map(|stats| view_node(ctx, stats)).collect::<Html>()
+    };
+
+    html! {
+        <>
+            <h1 class = "title mdc-typography--headline5">{ i18n.text(NETWORK) } { refresh_button }</h1>
+            { nodes }
+        </>
+    }
+}
+
+fn view_node(ctx: &Context<Root>, stats: &P2pStats) -> Html {
+    let i18n = i18n::load();
+    let dial_input_id = format!("{}--dial", stats.lapp_name);
+
+    let dial_button = Button::new().label(i18n.text(DIAL)).on_click({
+        let lapp_name = stats.lapp_name.clone();
+        let dial_input_id = dial_input_id.clone();
+
+        ctx.link().callback(move |_| {
+            let input = dom::existing::get_element_by_id::<HtmlInputElement>(&dial_input_id);
+            let address = input.value();
+            input.set_value("");
+            Msg::ApplyP2pAction(lapp_name.clone(), P2pAction::Dial { address })
+        })
+    });
+
+    html! {
+        <div class = "p2p-node">
+            <h2 class = "mdc-typography--headline6">{ &stats.lapp_name }</h2>
+            <div class = "lapps-table">
+                <div class = "lapps-table-row">
+                    <div class = "lapps-table-col">{ i18n.text(PEER_ID) }</div>
+                    <div class = "lapps-table-col"><code>{ &stats.peer_id }</code></div>
+                </div>
+                <div class = "lapps-table-row">
+                    <div class = "lapps-table-col">{ i18n.text(LISTEN_ADDRESSES) }</div>
+                    <div class = "lapps-table-col">
+                        { stats.listen_addresses.iter().map(|address| html! { <div><code>{ address }</code></div> }).collect::<Html>() }
+                    </div>
+                </div>
+                { stats.topics.iter().map(|topic| html! {
+                    <div class = "lapps-table-row">
+                        <div class = "lapps-table-col">{ i18n.text(TOPIC) } { " " } <code>{ &topic.topic }</code></div>
+                        <div class = "lapps-table-col">
+                            { format!(
+                                "in: {} ({:.1}/min), out: {} ({:.1}/min)",
+                                topic.messages_in, topic.messages_in_per_min, topic.messages_out, topic.messages_out_per_min,
+                            ) }
+                        </div>
+                    </div>
+                }).collect::<Html>() }
+                <div class = "lapps-table-row">
+                    <div class = "lapps-table-col">
+                        <input id = { dial_input_id } type = "text" placeholder = { i18n.text(PEER_ID_OR_ADDRESS).to_string() } />
+                    </div>
+                    <div class = "lapps-table-col">{ dial_button }</div>
+                </div>
+            </div>
+
+            <h3 class = "mdc-typography--subtitle1">{ i18n.text(PEERS) }</h3>
+            <div class = "lapps-table">
+                { stats.peers.iter().map(|peer| view_peer(ctx, &stats.lapp_name, peer)).collect::<Html>() }
+            </div>
+            <br />
+        </div>
+    }
+}
+
+fn view_peer(ctx: &Context<Root>, lapp_name: &str, peer: &PeerStats) -> Html {
+    let i18n = i18n::load();
+    let label_input_id = format!("{lapp_name}--{}--label", peer.peer_id);
+
+    let label_button = Button::new().label(i18n.text(LABEL)).on_click({
+        let lapp_name = lapp_name.to_string();
+        let peer_id = peer.peer_id.clone();
+        let label_input_id = label_input_id.clone();
+
+        ctx.link().callback(move |_| {
+            let label = dom::existing::get_element_by_id::<HtmlInputElement>(&label_input_id).value();
+            Msg::ApplyP2pAction(lapp_name.clone(), P2pAction::Label {
+                peer_id: peer_id.clone(),
+                label: Some(label),
+            })
+        })
+    });
+
+    let ban_button = Button::new()
+        .label(i18n.text(if peer.banned { UNBAN } else { BAN }))
+        .on_click({
+            let lapp_name = lapp_name.to_string();
+            let peer_id = peer.peer_id.clone();
+            let banned = peer.banned;
+
+            ctx.link().callback(move |_| {
+                let peer_id = peer_id.clone();
+                let action = if banned {
+                    P2pAction::Unban { peer_id }
+                } else {
+                    P2pAction::Ban { peer_id }
+                };
+                Msg::ApplyP2pAction(lapp_name.clone(), action)
+            })
+        });
+
+    let status = if peer.banned {
+        i18n.text(BANNED)
+    } else if peer.connected {
+        i18n.text(CONNECTED)
+    } else {
+        i18n.text(DISCOVERED)
+    };
+
+    html! {
+        <>
+            <div class = "lapps-table-row">
+                <div class = "lapps-table-col">
+                    <b>{ peer.label.as_deref().unwrap_or_default() }</b> { " " } <code>{ &peer.peer_id }</code>
+                </div>
+                <div class = "lapps-table-col">{ status }</div>
+            </div>
+            <div class = "lapps-table-row">
+                <div class = "lapps-table-col">
+                    { peer.addresses.iter().map(|address| html! { <div><code>{ address }</code></div> }).collect::<Html>() }
+                </div>
+            </div>
+            <div class = "lapps-table-row">
+                <div class = "lapps-table-col">
+                    <input id = { label_input_id } type = "text" value = { peer.label.clone().unwrap_or_default() } />
+                    { label_button }
+                </div>
+                <div class = "lapps-table-col">{ ban_button }</div>
+            </div>
+        </>
+    }
+}
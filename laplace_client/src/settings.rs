@@ -0,0 +1,152 @@
+use anyhow::anyhow;
+use laplace_common::api::{HotSettings, ServerSettings, SettingsValidation};
+use laplace_common::lapp::Lapp as CommonLapp;
+use serde::de::DeserializeOwned;
+use wasm_web_helpers::fetch::JsonFetcher;
+use web_sys::HtmlInputElement;
+use yew::{html, Context, Html};
+use yew_mdc_widgets::{dom, Button};
+
+use crate::i18n::label::*;
+use crate::{i18n, json_callback, Msg, Root};
+
+type Lapp = CommonLapp<String>;
+
+#[derive(Debug)]
+pub enum SettingsMsg {
+    Fetched(ServerSettings),
+    Validate,
+    Validated(SettingsValidation),
+    Apply,
+    Saved,
+    Reloaded(ServerSettings),
+}
+
+/// The server settings editor: the hot settings are validated by the server, saved to the config file
+/// and applied by the config reload.
+pub struct SettingsPage {
+    settings: Option<ServerSettings>,
+    validation: Option<SettingsValidation>,
+    applied: bool,
+}
+
+impl SettingsPage {
+    pub fn open(ctx: &Context<Root>) -> Self {
+        let callback = json_callback(ctx, |settings| Msg::Settings(SettingsMsg::Fetched(settings)));
+        JsonFetcher::send_get(Lapp::main_uri("settings"), move |response_result| {
+            callback.emit(response_result)
+        });
+
+        Self {
+            settings: None,
+            validation: None,
+            applied: false,
+        }
+    }
+
+    pub fn update(&mut self, ctx: &Context<Root>, msg: SettingsMsg) -> bool {
+        match msg {
+            SettingsMsg::Fetched(settings) => {
+                self.settings = Some(settings);
+                true
+            },
+            SettingsMsg::Validate => {
+                send_hot_settings(ctx, Lapp::main_uri("settings/validate"), |validation| {
+                    Msg::Settings(SettingsMsg::Validated(validation))
+                });
+                false
+            },
+            SettingsMsg::Validated(validation) => {
+                self.validation = Some(validation);
+                self.applied = false;
+                true
+            },
+            SettingsMsg::Apply => {
+                send_hot_settings(ctx, Lapp::main_uri("settings"), |_: ServerSettings| {
+                    Msg::Settings(SettingsMsg::Saved)
+                });
+                false
+            },
+            SettingsMsg::Saved => {
+                let callback = json_callback(ctx, |settings| Msg::Settings(SettingsMsg::Reloaded(settings)));
+                JsonFetcher::send_post_json(Lapp::main_uri("settings/reload"), "", move |response_result| {
+                    callback.emit(response_result)
+                });
+                false
+            },
+            SettingsMsg::Reloaded(settings) => {
+                self.settings = Some(settings);
+                self.validation = None;
+                self.applied = true;
+                true
+            },
+        }
+    }
+
+    pub fn view(&self, ctx: &Context<Root>) -> Html {
+        let i18n = i18n::load();
+        let Some(settings) = &self.settings else {
+            return html! {};
+        };
+
+        let validation = match &self.validation {
+            Some(validation) if validation.is_valid() => {
+                html! { <p class = "status-success">{ i18n.text(SETTINGS_VALID) }</p> }
+            },
+            Some(validation) => validation
+                .errors
+                .iter()
+                .map(|error| html! { <p class = "status-failure">{ error }</p> })
+                .collect(),
+            None if self.applied => html! { <p class = "status-success">{ i18n.text(SETTINGS_APPLIED) }</p> },
+            None => html! {},
+        };
+
+        html! {
+            <>
+                <h1 class = "title mdc-typography--headline5">{ i18n.text(SERVER_SETTINGS) }</h1>
+                <p>{ format!("{}: {}", i18n.text(CONFIG_FILE), settings.config_path.as_deref().unwrap_or("-")) }</p>
+
+                <h2 class = "mdc-typography--headline6">{ i18n.text(HOT_SETTINGS) }</h2>
+                <div class = "settings-field">
+                    <label for = "settings-log-spec">{ "log.spec" }</label>
+                    <input id = "settings-log-spec" type = "text" value = { settings.hot.log_spec.clone() } />
+                </div>
+                <div class = "settings-field">
+                    <input id = "settings-read-only" type = "checkbox" checked = { settings.hot.read_only } />
+                    <label for = "settings-read-only">{ "lapps.read_only" }</label>
+                </div>
+                { validation }
+                <div>
+                    { Button::new().label(i18n.text(VALIDATE)).on_click(ctx.link().callback(|_| Msg::Settings(SettingsMsg::Validate))) }
+                    { Button::new().label(i18n.text(APPLY)).on_click(ctx.link().callback(|_| Msg::Settings(SettingsMsg::Apply))) }
+                </div>
+
+                <h2 class = "mdc-typography--headline6">{ i18n.text(EFFECTIVE_CONFIG) }</h2>
+                <p>{ i18n.text(RESTART_REQUIRED) }</p>
+                <pre class = "settings-config">{ serde_json::to_string_pretty(&settings.config).unwrap_or_default() }</pre>
+            </>
+        }
+    }
+}
+
+fn send_hot_settings<T: DeserializeOwned + 'static>(
+    ctx: &Context<Root>,
+    uri: String,
+    into_msg: impl Fn(T) -> Msg + 'static,
+) {
+    let hot = HotSettings {
+        log_spec: dom::existing::get_element_by_id::<HtmlInputElement>("settings-log-spec").value(),
+        read_only: dom::existing::get_element_by_id::<HtmlInputElement>("settings-read-only").checked(),
+    };
+
+    match serde_json::to_string(&hot) {
+        Ok(body) => {
+            let callback = json_callback(ctx, into_msg);
+            JsonFetcher::send_post_json(uri, body, move |response_result| callback.emit(response_result));
+        },
+        Err(err) => ctx
+            .link()
+            .send_message(Msg::Error(anyhow!("Serialize settings error: {err}"))),
+    }
+}
@@ -0,0 +1,83 @@
+use laplace_common::api::{RegistryIndex, RegistryLapp};
+use laplace_common::lapp::LappSettings;
+use yew::{html, Context, Html};
+use yew_mdc_widgets::{Button, IconButton};
+
+use crate::i18n::label::*;
+use crate::{i18n, Msg, Root};
+
+pub fn view(ctx: &Context<Root>, index: &RegistryIndex, lapps: &[LappSettings]) -> Html {
+    let i18n = i18n::load();
+
+    let refresh_button = IconButton::new()
+        .icon("refresh")
+        .on_click(ctx.link().callback(|_| Msg::FetchRegistry));
+
+    let registry_lapps = if index.lapps.is_empty() {
+        html! { <p>{ i18n.text(NO_REGISTRY_LAPPS) }</p> }
+    } else {
+        index
+            .lapps
+            .iter()
+            .map(|registry_lapp| {
+                let installed = lapps.iter().find(|lapp| lapp.name() == registry_lapp.name);
+                view_registry_lapp(ctx, registry_lapp, installed)
+            })
+            .collect::<Html>()
+    };
+
+    html! {
+        <>
+            <h1 class = "title mdc-typography--headline5">{ i18n.text(STORE) } { refresh_button }</h1>
+            <div class = "lapps-table">{ registry_lapps }</div>
+        </>
+    }
+}
+
+fn view_registry_lapp(ctx: &Context<Root>, registry_lapp: &RegistryLapp, installed: Option<&LappSettings>) -> Html {
+    let i18n = i18n::load();
+
+    let latest_version = registry_lapp.latest().map(|latest| latest.version.as_str());
+    let installed_version = installed.map(|lapp| lapp.application.version.as_deref().unwrap_or("-"));
+    let action_label = match installed_version {
+        None => Some(TASK_INSTALL),
+        Some(installed_version) if latest_version.is_some_and(|latest| latest != installed_version) => {
+            Some(TASK_UPGRADE)
+        },
+        Some(_) => None,
+    };
+    let action_button = match action_label {
+        Some(label) => {
+            let lapp_name = registry_lapp.name.clone();
+            let button = Button::new().label(i18n.text(label)).on_click(
+                ctx.link()
+                    .callback(move |_| Msg::InstallFromRegistry(lapp_name.clone())),
+            );
+            html! { { button } }
+        },
+        None => html! {},
+    };
+
+    html! {
+        <>
+            <div class = "lapps-table-row">
+                <div class = "lapps-table-col">
+                    <big>{ &registry_lapp.title }</big> { " " } <code>{ &registry_lapp.name }</code>
+                </div>
+                <div class = "lapps-table-col">{ action_button }</div>
+            </div>
+            <div class = "lapps-table-row">
+                <div class = "lapps-table-col">{ registry_lapp.description.as_deref().unwrap_or_default() }</div>
+            </div>
+            <div class = "lapps-table-row">
+                <div class = "lapps-table-col">
+                    { format!("{}: {}", i18n.text(LATEST_VERSION), latest_version.unwrap_or("-")) }
+                </div>
+                <div class = "lapps-table-col">
+                    { format!("{}: {}", i18n.text(INSTALLED_VERSION), installed_version.unwrap_or("-")) }
+                </div>
+            </div>
+            <br />
+        </>
+    }
+}
@@ -0,0 +1,247 @@
+use anyhow::anyhow;
+use laplace_common::api::{TaskInfo, TaskKind, TaskState};
+use laplace_common::lapp::Lapp as CommonLapp;
+use wasm_web_helpers::fetch::JsonFetcher;
+use wasm_web_helpers::websocket::{self, WebSocketError, WebSocketService};
+use yew::{classes, html, Context, Html};
+use yew_mdc_widgets::{dom, Button, IconButton, MdcWidget, Snackbar};
+
+use crate::i18n::label::*;
+use crate::{i18n, json_callback, Msg, Root};
+
+type Lapp = CommonLapp<String>;
+
+const TASKS_SNACKBAR_ID: &str = "tasks-snackbar";
+
+#[derive(Debug)]
+pub enum TasksMsg {
+    Received(TaskInfo),
+    Fetched(Vec<TaskInfo>),
+    Toggle,
+    Cancel(u64),
+    Disconnected,
+}
+
+/// The panel of the long-running server operations, updated by the tasks websocket.
+pub struct TasksPanel {
+    tasks: Vec<TaskInfo>,
+    opened: bool,
+    toast: Option<String>,
+    should_open_toast: bool,
+    _ws: Option<WebSocketService>,
+}
+
+impl TasksPanel {
+    pub fn connect(ctx: &Context<Root>) -> Self {
+        let location = dom::existing::location();
+        let protocol = location.protocol().unwrap_or_default();
+        let host = location.host().unwrap_or_default();
+        let url = format!(
+            "{}//{host}{}",
+            protocol.replace("http", "ws"),
+            Lapp::main_uri("tasks/ws")
+        );
+
+        let send_callback = ctx.link().batch_callback(|send_result: Result<(), WebSocketError>| {
+            send_result.err().map(|err| Msg::Error(anyhow!("{}", err)))
+        });
+        let receive_callback =
+            ctx.link().callback(
+                |receive_result: Result<websocket::Message, WebSocketError>| match receive_result {
+                    Ok(msg) => match match msg {
+                        websocket::Message::Text(text) => serde_json::from_str(&text),
+                        websocket::Message::Bytes(bytes) => serde_json::from_slice(&bytes),
+                    } {
+                        Ok(task) => Msg::Tasks(TasksMsg::Received(task)),
+                        Err(err) => Msg::Error(err.into()),
+                    },
+                    Err(err) => Msg::Error(anyhow!("{}", err)),
+                },
+            );
+        let close_callback = ctx.link().callback(|_| Msg::Tasks(TasksMsg::Disconnected));
+
+        let ws = WebSocketService::open(
+            &url,
+            move |send_result| send_callback.emit(send_result),
+            move |receive_result| receive_callback.emit(receive_result),
+            {
+                let close_callback = close_callback.clone();
+                move || close_callback.emit(())
+            },
+            move || close_callback.emit(()),
+        )
+        .map_err(|err| {
+            ctx.link()
+                .send_message(Msg::Error(anyhow!("Tasks websocket error: {err:?}")))
+        })
+        .ok();
+
+        Self {
+            tasks: Vec::new(),
+            opened: false,
+            toast: None,
+            should_open_toast: false,
+            _ws: ws,
+        }
+    }
+
+    pub fn update(&mut self, ctx: &Context<Root>, msg: TasksMsg) -> bool {
+        match msg {
+            TasksMsg::Received(task) => {
+                match self.tasks.iter_mut().find(|current| current.id == task.id) {
+                    Some(current) => {
+                        if !current.state.is_finished() && task.state.is_finished() {
+                            self.toast = Some(task_toast(&task));
+                            self.should_open_toast = true;
+                        }
+                        *current = task;
+                    },
+                    None => self.tasks.push(task),
+                }
+                true
+            },
+            TasksMsg::Fetched(tasks) => {
+                self.tasks = tasks;
+                true
+            },
+            TasksMsg::Toggle => {
+                self.opened = !self.opened;
+                true
+            },
+            TasksMsg::Cancel(id) => {
+                let callback = json_callback(ctx, |tasks| Msg::Tasks(TasksMsg::Fetched(tasks)));
+                JsonFetcher::send_post_json(
+                    Lapp::main_uri(format!("tasks/{id}/cancel")),
+                    "",
+                    move |response_result| callback.emit(response_result),
+                );
+                false
+            },
+            TasksMsg::Disconnected => {
+                self._ws = None;
+                ctx.link()
+                    .send_message(Msg::Error(anyhow!("Tasks websocket connection close")));
+                false
+            },
+        }
+    }
+
+    pub fn rendered(&mut self) {
+        if self.should_open_toast {
+            self.should_open_toast = false;
+            Snackbar::open_existing(TASKS_SNACKBAR_ID);
+        }
+    }
+
+    pub fn view_button(&self, ctx: &Context<Root>) -> Html {
+        let running = self.tasks.iter().filter(|task| !task.state.is_finished()).count();
+        let icon = if running > 0 { "pending" } else { "task_alt" };
+
+        IconButton::new()
+            .icon(icon)
+            .attr("title", i18n::load().text(TASKS))
+            .on_click(ctx.link().callback(|_| Msg::Tasks(TasksMsg::Toggle)))
+            .into()
+    }
+
+    pub fn view(&self, ctx: &Context<Root>) -> Html {
+        let i18n = i18n::load();
+
+        let snackbar = Snackbar::new()
+            .id(TASKS_SNACKBAR_ID)
+            .label(self.toast.clone().unwrap_or_default());
+        if !self.opened {
+            return snackbar.into();
+        }
+
+        let tasks = if self.tasks.is_empty() {
+            html! { <p>{ i18n.text(NO_TASKS) }</p> }
+        } else {
+            self.tasks
+                .iter()
+                .rev()
+                .map(|task| view_task(ctx, task))
+                .collect::<Html>()
+        };
+
+        html! {
+            <>
+                <div class = "tasks-panel mdc-elevation--z4">
+                    <h2 class = "mdc-typography--headline6">{ i18n.text(TASKS) }</h2>
+                    { tasks }
+                </div>
+                { snackbar }
+            </>
+        }
+    }
+}
+
+fn view_task(ctx: &Context<Root>, task: &TaskInfo) -> Html {
+    let i18n = i18n::load();
+
+    let progress = match (task.state, task.progress) {
+        (TaskState::Running, Some(progress)) => html! { <progress max = "1" value = { progress.to_string() } /> },
+        (TaskState::Running, None) => html! { <progress /> },
+        _ => html! {},
+    };
+
+    let cancel_button = if task.cancellable && !task.state.is_finished() {
+        let id = task.id;
+        Button::new()
+            .label(i18n.text(CANCEL))
+            .on_click(ctx.link().callback(move |_| Msg::Tasks(TasksMsg::Cancel(id))))
+            .into()
+    } else {
+        html! {}
+    };
+
+    html! {
+        <div class = { classes!("task", task_state_class(task.state)) }>
+            <div>{ task_title(task) }</div>
+            <div>
+                { progress }
+                <span>{ i18n.text(task_state_label(task.state)) }</span>
+                { cancel_button }
+            </div>
+            if let Some(message) = &task.message {
+                <div class = "task-message">{ message }</div>
+            }
+        </div>
+    }
+}
+
+fn task_title(task: &TaskInfo) -> String {
+    let kind = match task.kind {
+        TaskKind::Install => TASK_INSTALL,
+        TaskKind::Upgrade => TASK_UPGRADE,
+        TaskKind::Import => TASK_IMPORT,
+        TaskKind::Restore => TASK_RESTORE,
+    };
+    format!("{} {}", i18n::load().text(kind), task.lapp_name)
+}
+
+fn task_toast(task: &TaskInfo) -> String {
+    format!(
+        "{}: {}",
+        task_title(task),
+        i18n::load().text(task_state_label(task.state))
+    )
+}
+
+fn task_state_label(state: TaskState) -> &'static str {
+    match state {
+        TaskState::Running => TASK_RUNNING,
+        TaskState::Completed => TASK_COMPLETED,
+        TaskState::Failed => TASK_FAILED,
+        TaskState::Cancelled => TASK_CANCELLED,
+    }
+}
+
+fn task_state_class(state: TaskState) -> &'static str {
+    match state {
+        TaskState::Running => "task-running",
+        TaskState::Completed => "task-completed",
+        TaskState::Failed => "task-failed",
+        TaskState::Cancelled => "task-cancelled",
+    }
+}
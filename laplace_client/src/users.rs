@@ -0,0 +1,238 @@
+use anyhow::anyhow;
+use laplace_common::api::{Role, UserCredentials, UserInfo, UserRequest};
+use laplace_common::lapp::Lapp as CommonLapp;
+use serde::de::DeserializeOwned;
+use wasm_web_helpers::fetch::JsonFetcher;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::{html, Context, Html};
+use yew_mdc_widgets::{dom, Button};
+
+use crate::i18n::label::*;
+use crate::{i18n, json_callback, Msg, Root};
+
+type Lapp = CommonLapp<String>;
+
+#[derive(Debug)]
+pub enum UsersMsg {
+    Fetched(Vec<UserInfo>),
+    Create,
+    Created(UserCredentials),
+    SwitchRole(String, Role),
+    Updated(UserInfo),
+    ResetCredentials(String),
+    Delete(String),
+}
+
+/// The users of the admin UI with their roles and sessions.
+pub struct UsersPage {
+    users: Vec<UserInfo>,
+
+    /// The credentials of the last created or reset user, the server does not show them again.
+    credentials: Option<UserCredentials>,
+}
+
+impl UsersPage {
+    pub fn open(ctx: &Context<Root>) -> Self {
+        fetch_users(ctx);
+
+        Self {
+            users: Vec::new(),
+            credentials: None,
+        }
+    }
+
+    pub fn update(&mut self, ctx: &Context<Root>, msg: UsersMsg) -> bool {
+        match msg {
+            UsersMsg::Fetched(users) => {
+                self.users = users;
+                true
+            },
+            UsersMsg::Create => {
+                let request = UserRequest {
+                    name: dom::existing::get_element_by_id::<HtmlInputElement>("user-name").value(),
+                    role: parse_role(&dom::existing::get_element_by_id::<HtmlSelectElement>("user-role").value()),
+                };
+                send_user_request(ctx, Lapp::main_uri("users"), &request, |credentials| {
+                    Msg::Users(UsersMsg::Created(credentials))
+                });
+                false
+            },
+            UsersMsg::Created(credentials) => {
+                self.credentials = Some(credentials);
+                fetch_users(ctx);
+                true
+            },
+            UsersMsg::SwitchRole(name, role) => {
+                let uri = Lapp::main_uri(format!("users/{name}"));
+                send_user_request(ctx, uri, &UserRequest { name, role }, |user| {
+                    Msg::Users(UsersMsg::Updated(user))
+                });
+                false
+            },
+            UsersMsg::Updated(updated) => {
+                if let Some(user) = self.users.iter_mut().find(|user| user.name == updated.name) {
+                    *user = updated;
+                }
+                true
+            },
+            UsersMsg::ResetCredentials(name) => {
+                let callback = json_callback(ctx, |credentials| Msg::Users(UsersMsg::Created(credentials)));
+                JsonFetcher::send_post_json(
+                    Lapp::main_uri(format!("users/{name}/reset")),
+                    "",
+                    move |response_result| callback.emit(response_result),
+                );
+                false
+            },
+            UsersMsg::Delete(name) => {
+                let callback = json_callback(ctx, |users| Msg::Users(UsersMsg::Fetched(users)));
+                JsonFetcher::send_post_json(
+                    Lapp::main_uri(format!("users/{name}/delete")),
+                    "",
+                    move |response_result| callback.emit(response_result),
+                );
+                false
+            },
+        }
+    }
+
+    pub fn view(&self, ctx: &Context<Root>) -> Html {
+        let i18n = i18n::load();
+
+        let credentials = match &self.credentials {
+            Some(credentials) => {
+                let location = dom::existing::location();
+                let origin = location.origin().unwrap_or_default();
+                let link = format!("{origin}/?access_token={}", credentials.access_token);
+                html! {
+                    <div class = "user-credentials">
+                        <p>{ format!("{} {}:", i18n.text(NEW_ACCESS_LINK), credentials.name) }</p>
+                        <pre>{ link }</pre>
+                        <p>{ i18n.text(SHOWN_ONCE) }</p>
+                    </div>
+                }
+            },
+            None => html! {},
+        };
+
+        let users = if self.users.is_empty() {
+            html! { <p>{ i18n.text(NO_USERS) }</p> }
+        } else {
+            self.users.iter().map(|user| view_user(ctx, user)).collect()
+        };
+
+        html! {
+            <>
+                <h1 class = "title mdc-typography--headline5">{ i18n.text(USERS) }</h1>
+                <div class = "settings-field">
+                    <input id = "user-name" type = "text" placeholder = { i18n.text(USER_NAME) } />
+                    <select id = "user-role">
+                        <option value = { Role::Viewer.as_str() } selected = true>{ Role::Viewer.as_str() }</option>
+                        <option value = { Role::Admin.as_str() }>{ Role::Admin.as_str() }</option>
+                    </select>
+                    { Button::new().label(i18n.text(CREATE_USER)).on_click(ctx.link().callback(|_| Msg::Users(UsersMsg::Create))) }
+                </div>
+                { credentials }
+                { users }
+            </>
+        }
+    }
+}
+
+fn view_user(ctx: &Context<Root>, user: &UserInfo) -> Html {
+    let i18n = i18n::load();
+
+    let switch_role_button = {
+        let name = user.name.clone();
+        let role = match user.role {
+            Role::Admin => Role::Viewer,
+            Role::Viewer => Role::Admin,
+        };
+        Button::new()
+            .label(format!("{} {}", i18n.text(MAKE), role.as_str()))
+            .on_click(
+                ctx.link()
+                    .callback(move |_| Msg::Users(UsersMsg::SwitchRole(name.clone(), role))),
+            )
+    };
+    let reset_button = {
+        let name = user.name.clone();
+        Button::new().label(i18n.text(RESET_CREDENTIALS)).on_click(
+            ctx.link()
+                .callback(move |_| Msg::Users(UsersMsg::ResetCredentials(name.clone()))),
+        )
+    };
+    let delete_button = {
+        let name = user.name.clone();
+        Button::new()
+            .label(i18n.text(DELETE))
+            .on_click(ctx.link().callback(move |_| Msg::Users(UsersMsg::Delete(name.clone()))))
+    };
+
+    let sessions = if user.sessions.is_empty() {
+        html! { <p>{ i18n.text(NO_SESSIONS) }</p> }
+    } else {
+        html! {
+            <table class = "database-table">
+                <tr>
+                    <th>{ i18n.text(USER_AGENT) }</th>
+                    <th>{ i18n.text(STARTED) }</th>
+                    <th>{ i18n.text(LAST_SEEN) }</th>
+                </tr>
+                { for user.sessions.iter().map(|session| html! {
+                    <tr>
+                        <td>{ session.user_agent.as_deref().unwrap_or("-") }</td>
+                        <td>{ session.started_at.clone() }</td>
+                        <td>{ session.last_seen.clone() }</td>
+                    </tr>
+                }) }
+            </table>
+        }
+    };
+
+    html! {
+        <div class = "user mdc-card">
+            <h2 class = "mdc-typography--headline6">{ format!("{} ({})", user.name, user.role.as_str()) }</h2>
+            <p>{ format!("{}: {}", i18n.text(CREATED), user.created_at) }</p>
+            <div>
+                { switch_role_button }
+                { reset_button }
+                { delete_button }
+            </div>
+            <h3 class = "mdc-typography--subtitle1">{ i18n.text(SESSIONS) }</h3>
+            { sessions }
+        </div>
+    }
+}
+
+fn fetch_users(ctx: &Context<Root>) {
+    let callback = json_callback(ctx, |users| Msg::Users(UsersMsg::Fetched(users)));
+    JsonFetcher::send_get(Lapp::main_uri("users"), move |response_result| {
+        callback.emit(response_result)
+    });
+}
+
+fn parse_role(role: &str) -> Role {
+    if role == Role::Admin.as_str() {
+        Role::Admin
+    } else {
+        Role::Viewer
+    }
+}
+
+fn send_user_request<T: DeserializeOwned + 'static>(
+    ctx: &Context<Root>,
+    uri: String,
+    request: &UserRequest,
+    into_msg: impl Fn(T) -> Msg + 'static,
+) {
+    match serde_json::to_string(request) {
+        Ok(body) => {
+            let callback = json_callback(ctx, into_msg);
+            JsonFetcher::send_post_json(uri, body, move |response_result| callback.emit(response_result));
+        },
+        Err(err) => ctx
+            .link()
+            .send_message(Msg::Error(anyhow!("Serialize user error: {err}"))),
+    }
+}
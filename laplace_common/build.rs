@@ -0,0 +1,8 @@
+fn main() {
+    #[cfg(feature = "proto")]
+    {
+        println!("cargo:rerun-if-changed=proto");
+        prost_build::compile_protos(&["proto/laplace/api.proto"], &["proto"])
+            .expect("Protobuf schema should be compiled");
+    }
+}
@@ -1,5 +1,37 @@
+pub use self::audit::*;
+pub use self::db_backup::*;
+pub use self::dependencies::*;
+pub use self::info::*;
+pub use self::jobs::*;
+pub use self::logs::*;
+pub use self::migrations::*;
 pub use self::p2p::*;
+pub use self::registry::*;
+pub use self::reload::*;
+pub use self::settings::*;
+pub use self::sql::*;
+pub use self::tasks::*;
 pub use self::update::*;
+pub use self::upgrade::*;
+pub use self::usage::*;
+pub use self::users::*;
+pub use self::ws::*;
 
+pub mod audit;
+pub mod db_backup;
+pub mod dependencies;
+pub mod info;
+pub mod jobs;
+pub mod logs;
+pub mod migrations;
 pub mod p2p;
+pub mod registry;
+pub mod reload;
+pub mod settings;
+pub mod sql;
+pub mod tasks;
 pub mod update;
+pub mod upgrade;
+pub mod usage;
+pub mod users;
+pub mod ws;
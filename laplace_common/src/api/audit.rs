@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+use crate::lapp::{Permission, PermissionsSettings};
+
+/// The state of the lapp permission before and after the change.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionState {
+    Allowed,
+    Pending,
+    Denied,
+}
+
+impl PermissionState {
+    pub fn of(permissions: &PermissionsSettings, permission: Permission) -> Self {
+        if permissions.is_allowed(permission) {
+            Self::Allowed
+        } else if permissions.is_pending(permission) {
+            Self::Pending
+        } else {
+            Self::Denied
+        }
+    }
+
+    /// The permissions whose state differs in the old and new settings, with the old and new states.
+    pub fn changes(
+        old_permissions: &PermissionsSettings,
+        new_permissions: &PermissionsSettings,
+    ) -> Vec<(Permission, Self, Self)> {
+        let mut changes: Vec<(Permission, Self, Self)> = Vec::new();
+        for settings in [old_permissions, new_permissions] {
+            for permission in settings.required().chain(settings.allowed()).chain(settings.pending()) {
+                let old_state = Self::of(old_permissions, permission);
+                let new_state = Self::of(new_permissions, permission);
+                let is_listed = changes.iter().any(|(changed, ..)| *changed == permission);
+                if old_state != new_state && !is_listed {
+                    changes.push((permission, old_state, new_state));
+                }
+            }
+        }
+        changes
+    }
+}
+
+/// The permission change made by the lapp update, the time is in RFC 3339 format.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PermissionAuditEntry {
+    pub lapp_name: String,
+    pub time: String,
+
+    /// The name of the user who made the change, or `laplace` for the Laplace access token.
+    pub actor: String,
+    pub permission: Permission,
+    pub old_state: PermissionState,
+    pub new_state: PermissionState,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permission_states() {
+        let permissions = PermissionsSettings {
+            required: vec![Permission::Http, Permission::Database, Permission::Sleep],
+            allowed: vec![Permission::Http],
+            pending: vec![Permission::Sleep],
+        };
+
+        assert_eq!(
+            PermissionState::of(&permissions, Permission::Http),
+            PermissionState::Allowed
+        );
+        assert_eq!(
+            PermissionState::of(&permissions, Permission::Sleep),
+            PermissionState::Pending
+        );
+        assert_eq!(
+            PermissionState::of(&permissions, Permission::Database),
+            PermissionState::Denied
+        );
+    }
+
+    #[test]
+    fn permission_changes() {
+        let old_permissions = PermissionsSettings {
+            required: vec![Permission::Http, Permission::Database, Permission::Sleep],
+            allowed: vec![Permission::Http],
+            pending: vec![Permission::Sleep],
+        };
+        let new_permissions = PermissionsSettings {
+            allowed: vec![Permission::Database],
+            pending: vec![],
+            ..old_permissions.clone()
+        };
+
+        assert_eq!(PermissionState::changes(&old_permissions, &new_permissions), [
+            (Permission::Http, PermissionState::Allowed, PermissionState::Denied),
+            (Permission::Database, PermissionState::Denied, PermissionState::Allowed),
+            (Permission::Sleep, PermissionState::Pending, PermissionState::Denied),
+        ]);
+        assert!(PermissionState::changes(&old_permissions, &old_permissions).is_empty());
+    }
+}
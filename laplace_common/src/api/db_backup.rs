@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// The online backup of the lapp database written to the backup directory of the lapp.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DatabaseBackup {
+    pub lapp_name: String,
+
+    /// The timestamped file name in the backup directory.
+    pub file_name: String,
+    pub size_bytes: u64,
+}
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::lapp::{check_dependencies, load_order, DependencySettings, LappSettings};
+
+/// The dependency graph of the installed lapps, the nodes are in the load order.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DependencyGraph {
+    pub lapps: Vec<DependencyNode>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DependencyNode {
+    pub lapp_name: String,
+    pub version: Option<String>,
+    pub enabled: bool,
+    pub dependencies: Vec<DependencySettings>,
+
+    /// The unsatisfied dependencies, the lapp can't be enabled until they are resolved.
+    pub errors: Vec<String>,
+}
+
+impl DependencyGraph {
+    pub fn new(lapps: &HashMap<String, LappSettings>) -> Self {
+        let (order, unresolved) = load_order(lapps);
+        let lapps = order
+            .into_iter()
+            .chain(unresolved)
+            .map(|lapp_name| {
+                let settings = &lapps[&lapp_name];
+                DependencyNode {
+                    version: settings.application.version.clone(),
+                    enabled: settings.enabled(),
+                    dependencies: settings.dependencies().to_vec(),
+                    errors: check_dependencies(&lapp_name, lapps)
+                        .into_iter()
+                        .map(|err| err.to_string())
+                        .collect(),
+                    lapp_name,
+                }
+            })
+            .collect();
+
+        Self { lapps }
+    }
+}
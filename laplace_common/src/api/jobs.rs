@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// The state of the lapp scheduled job, the times are in RFC 3339 format.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct JobInfo {
+    pub lapp_name: String,
+    pub name: String,
+    pub schedule: String,
+    pub path: Option<String>,
+    pub next_run: Option<String>,
+    pub last_run: Option<String>,
+    pub last_result: Option<JobResult>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct JobResult {
+    pub success: bool,
+    pub message: String,
+}
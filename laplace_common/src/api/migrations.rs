@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// The SQL migrations of the lapp database, in the order of their file names.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LappMigrations {
+    pub lapp_name: String,
+    pub migrations: Vec<MigrationStatus>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MigrationStatus {
+    /// The file name of the migration in the `migrations` directory of the lapp.
+    pub name: String,
+
+    /// The time of the migration applying in milliseconds since the UNIX epoch, the migration is pending if missing.
+    pub applied_at: Option<i64>,
+}
@@ -5,3 +5,52 @@ pub struct Peer {
     pub peer_id: Vec<u8>,
     pub keypair: Vec<u8>,
 }
+
+/// The state of the lapp P2P node exposed by the admin API.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq)]
+pub struct P2pStats {
+    pub lapp_name: String,
+    pub peer_id: String,
+    pub listen_addresses: Vec<String>,
+    pub uptime_sec: u64,
+    pub topics: Vec<TopicStats>,
+    pub peers: Vec<PeerStats>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq)]
+pub struct TopicStats {
+    pub topic: String,
+    pub messages_in: u64,
+    pub messages_out: u64,
+    pub messages_in_per_min: f64,
+    pub messages_out_per_min: f64,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PeerStats {
+    pub peer_id: String,
+    pub addresses: Vec<String>,
+    pub connected: bool,
+    pub banned: bool,
+    pub label: Option<String>,
+}
+
+/// The admin action on the lapp P2P node.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum P2pAction {
+    /// Dial the known peer by the peer ID or the multiaddress.
+    Dial {
+        address: String,
+    },
+    Ban {
+        peer_id: String,
+    },
+    Unban {
+        peer_id: String,
+    },
+    Label {
+        peer_id: String,
+        label: Option<String>,
+    },
+}
@@ -0,0 +1,91 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+/// The index of the lapps available in the remote registry.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RegistryIndex {
+    pub lapps: Vec<RegistryLapp>,
+}
+
+impl RegistryIndex {
+    pub fn lapp(&self, name: &str) -> Option<&RegistryLapp> {
+        self.lapps.iter().find(|lapp| lapp.name == name)
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RegistryLapp {
+    pub name: String,
+    pub title: String,
+
+    #[serde(default)]
+    pub description: Option<String>,
+    pub versions: Vec<RegistryVersion>,
+}
+
+impl RegistryLapp {
+    /// The highest semver version of the lapp, the versions that are not semver are never the latest.
+    pub fn latest(&self) -> Option<&RegistryVersion> {
+        self.versions
+            .iter()
+            .filter_map(|version| Version::parse(&version.version).ok().map(|semver| (semver, version)))
+            .max_by(|(semver_a, _), (semver_b, _)| semver_a.cmp(semver_b))
+            .map(|(_, version)| version)
+    }
+
+    /// The given version of the lapp or the latest one.
+    pub fn version(&self, version: Option<&str>) -> Option<&RegistryVersion> {
+        match version {
+            Some(version) => self
+                .versions
+                .iter()
+                .find(|registry_version| registry_version.version == version),
+            None => self.latest(),
+        }
+    }
+}
+
+/// The lapp package of the version, the URL may be relative to the index URL.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RegistryVersion {
+    pub version: String,
+    pub url: String,
+
+    /// The hex-encoded SHA-256 of the package, it is checked after the download if present.
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(version: &str) -> RegistryVersion {
+        RegistryVersion {
+            version: version.into(),
+            url: format!("notes-{version}.lar"),
+            sha256: None,
+        }
+    }
+
+    #[test]
+    fn select_version() {
+        let lapp = RegistryLapp {
+            name: "notes".into(),
+            title: "Notes".into(),
+            description: None,
+            versions: vec![
+                version("0.9.0"),
+                version("0.10.1"),
+                version("latest"),
+                version("0.10.0"),
+            ],
+        };
+
+        assert_eq!(lapp.latest(), Some(&version("0.10.1")));
+        assert_eq!(lapp.version(None), Some(&version("0.10.1")));
+        assert_eq!(lapp.version(Some("0.9.0")), Some(&version("0.9.0")));
+        assert_eq!(lapp.version(Some("latest")), Some(&version("latest")));
+        assert_eq!(lapp.version(Some("1.0.0")), None);
+    }
+}
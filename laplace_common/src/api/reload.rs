@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+use crate::lapp::{LappSettings, Permission};
+
+/// The changes of the lapp settings re-read from its config.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SettingsReload {
+    pub lapp_name: String,
+    pub allowed_permissions: Vec<Permission>,
+    pub denied_permissions: Vec<Permission>,
+
+    /// The settings used by the lapp instance are changed, not only the lapp metadata.
+    pub instance_changed: bool,
+
+    /// The running lapp is re-instantiated with the reloaded settings.
+    pub reinstantiated: bool,
+}
+
+impl SettingsReload {
+    pub fn new(current: &LappSettings, reloaded: &LappSettings) -> Self {
+        Self {
+            lapp_name: reloaded.name().into(),
+            allowed_permissions: reloaded
+                .permissions
+                .allowed()
+                .filter(|&permission| !current.permissions.is_allowed(permission))
+                .collect(),
+            denied_permissions: current
+                .permissions
+                .allowed()
+                .filter(|&permission| !reloaded.permissions.is_allowed(permission))
+                .collect(),
+            instance_changed: is_instance_changed(current, reloaded),
+            reinstantiated: false,
+        }
+    }
+}
+
+/// Whether the settings used by the lapp instance are changed, e.g. the allowed permissions that define the imports of
+/// the instance.
+pub fn is_instance_changed(current: &LappSettings, updated: &LappSettings) -> bool {
+    instance_settings(current) != instance_settings(updated)
+}
+
+/// The settings without the lapp metadata and the options that are applied without the re-instantiation.
+fn instance_settings(settings: &LappSettings) -> serde_json::Value {
+    let mut settings = settings.clone();
+    let application = &mut settings.application;
+    application.title = String::new();
+    application.version = None;
+    application.autoload = false;
+    application.description = None;
+    application.author = None;
+    application.homepage = None;
+    application.license = None;
+    application.icon = None;
+    application.tags = None;
+
+    serde_json::to_value(settings).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lapp::PermissionsSettings;
+
+    #[test]
+    fn reload_changes() {
+        let current = LappSettings {
+            lapp_name: "notes".into(),
+            permissions: PermissionsSettings {
+                required: vec![Permission::Http, Permission::Database, Permission::Sleep],
+                allowed: vec![Permission::Http, Permission::Database],
+                pending: vec![],
+            },
+            ..Default::default()
+        };
+
+        let mut reloaded = current.clone();
+        reloaded.application.title = "Notes".into();
+        reloaded.application.description = Some("Simple notes".into());
+        assert_eq!(SettingsReload::new(&current, &reloaded), SettingsReload {
+            lapp_name: "notes".into(),
+            ..Default::default()
+        });
+
+        reloaded.permissions.allowed = vec![Permission::Http, Permission::Sleep];
+        assert_eq!(SettingsReload::new(&current, &reloaded), SettingsReload {
+            lapp_name: "notes".into(),
+            allowed_permissions: vec![Permission::Sleep],
+            denied_permissions: vec![Permission::Database],
+            instance_changed: true,
+            reinstantiated: false,
+        });
+
+        let mut reloaded = current.clone();
+        reloaded.resources.get_or_insert_with(Default::default).max_memory_mb = Some(64);
+        assert!(SettingsReload::new(&current, &reloaded).instance_changed);
+    }
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The effective server configuration for the settings editor, the secrets are redacted.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ServerSettings {
+    pub config_path: Option<String>,
+    pub config: Value,
+    pub hot: HotSettings,
+}
+
+/// The server settings that are applied by the config reload, without the server restart.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct HotSettings {
+    pub log_spec: String,
+    pub read_only: bool,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SettingsValidation {
+    pub errors: Vec<String>,
+}
+
+impl SettingsValidation {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
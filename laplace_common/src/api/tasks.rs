@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+/// The long-running server operation, it is sent to the admin UI on every change.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct TaskInfo {
+    pub id: u64,
+    pub kind: TaskKind,
+    pub lapp_name: String,
+
+    /// The completed part of the task from 0 to 1, `None` when the progress is unknown.
+    pub progress: Option<f32>,
+    pub state: TaskState,
+    pub message: Option<String>,
+    pub cancellable: bool,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    Install,
+    Upgrade,
+    Import,
+    Restore,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl TaskState {
+    pub fn is_finished(self) -> bool {
+        self != Self::Running
+    }
+}
@@ -4,6 +4,7 @@ use std::ops::Deref;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
+use crate::api::UpgradeDiff;
 use crate::lapp::{LappSettings, Permission};
 
 #[skip_serializing_none]
@@ -14,6 +15,10 @@ pub struct UpdateQuery {
     pub autoload: Option<bool>,
     pub allow_permission: Option<Permission>,
     pub deny_permission: Option<Permission>,
+
+    /// The name of the permission profile from the server settings, the lapp is allowed exactly the required
+    /// permissions of the profile.
+    pub permission_profile: Option<String>,
 }
 
 impl UpdateQuery {
@@ -31,8 +36,13 @@ impl UpdateQuery {
             autoload,
             allow_permission,
             deny_permission,
+            permission_profile,
         } = self;
-        enabled.is_some() || autoload.is_some() || allow_permission.is_some() || deny_permission.is_some()
+        enabled.is_some()
+            || autoload.is_some()
+            || allow_permission.is_some()
+            || deny_permission.is_some()
+            || permission_profile.is_some()
     }
 
     pub fn enabled(mut self, enabled: impl Into<Option<bool>>) -> Self {
@@ -55,6 +65,11 @@ impl UpdateQuery {
         self
     }
 
+    pub fn permission_profile(mut self, permission_profile: impl Into<Option<String>>) -> Self {
+        self.permission_profile = permission_profile.into();
+        self
+    }
+
     pub fn update_permission(self, permission: impl Into<Permission>, allow: bool) -> Self {
         if allow {
             self.allow_permission(permission.into())
@@ -108,6 +123,10 @@ pub enum Response<'a, LS: Deref<Target = LappSettings> + 'a> {
     Updated {
         updated: UpdateQuery,
     },
+
+    Upgrade {
+        upgrade: UpgradeDiff,
+    },
 }
 
 impl<'a, LS: Deref<Target = LappSettings> + 'a> Response<'a, LS> {
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+use crate::lapp::{Permission, PermissionsSettings};
+
+/// The difference between the required permissions of the installed lapp and of its uploaded upgrade.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UpgradeDiff {
+    pub lapp_name: String,
+    pub added_permissions: Vec<Permission>,
+    pub removed_permissions: Vec<Permission>,
+
+    #[serde(default)]
+    pub current_version: Option<String>,
+
+    #[serde(default)]
+    pub upgrade_version: Option<String>,
+}
+
+impl UpgradeDiff {
+    pub fn new(lapp_name: impl Into<String>, current: &PermissionsSettings, upgrade: &PermissionsSettings) -> Self {
+        Self {
+            lapp_name: lapp_name.into(),
+            added_permissions: upgrade
+                .required()
+                .filter(|permission| !current.required.contains(permission))
+                .collect(),
+            removed_permissions: current
+                .required()
+                .filter(|permission| !upgrade.required.contains(permission))
+                .collect(),
+            current_version: None,
+            upgrade_version: None,
+        }
+    }
+
+    pub fn with_versions(mut self, current: Option<String>, upgrade: Option<String>) -> Self {
+        self.current_version = current;
+        self.upgrade_version = upgrade;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_permissions.is_empty() && self.removed_permissions.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_required_permissions() {
+        let current = PermissionsSettings {
+            required: vec![Permission::Http, Permission::Database],
+            allowed: vec![Permission::Http],
+            pending: vec![],
+        };
+        let upgrade = PermissionsSettings {
+            required: vec![Permission::Http, Permission::Websocket],
+            allowed: vec![],
+            pending: vec![],
+        };
+
+        let diff = UpgradeDiff::new("test", &current, &upgrade);
+        assert_eq!(diff, UpgradeDiff {
+            lapp_name: "test".into(),
+            added_permissions: vec![Permission::Websocket],
+            removed_permissions: vec![Permission::Database],
+            current_version: None,
+            upgrade_version: None,
+        });
+        assert!(!diff.is_empty());
+        assert!(UpgradeDiff::new("test", &current, &current).is_empty());
+    }
+}
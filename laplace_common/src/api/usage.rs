@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// The disk usage of the lapp data directory and database.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DiskUsage {
+    pub lapp_name: String,
+    pub used_bytes: u64,
+
+    /// The effective disk quota of the lapp, the usage is not limited if missing.
+    pub quota_bytes: Option<u64>,
+}
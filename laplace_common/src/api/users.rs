@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Full access to the admin UI and API.
+    Admin,
+
+    /// Read-only access to the admin UI and API.
+    #[default]
+    Viewer,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Admin => "admin",
+            Self::Viewer => "viewer",
+        }
+    }
+}
+
+/// The user of the admin UI, the times are in RFC 3339 format.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UserInfo {
+    pub name: String,
+    pub role: Role,
+    pub created_at: String,
+    pub sessions: Vec<SessionInfo>,
+}
+
+/// The client that has used the user credentials since the server start.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SessionInfo {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub started_at: String,
+    pub last_seen: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UserRequest {
+    pub name: String,
+    pub role: Role,
+}
+
+/// The new access token of the user, it is shown once after the user creation or the credentials reset.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UserCredentials {
+    pub name: String,
+    pub access_token: String,
+}
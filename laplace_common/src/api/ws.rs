@@ -0,0 +1,127 @@
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+/// The websocket framing negotiated by the `Sec-WebSocket-Protocol` header. JSON envelopes are sent
+/// as text frames and CBOR envelopes are sent as binary frames.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WsFraming {
+    #[default]
+    Json,
+    Cbor,
+}
+
+impl WsFraming {
+    pub const JSON_PROTOCOL: &'static str = "laplace.json";
+    pub const CBOR_PROTOCOL: &'static str = "laplace.cbor";
+
+    /// Supported subprotocols in order of preference.
+    pub const PROTOCOLS: [&'static str; 2] = [Self::CBOR_PROTOCOL, Self::JSON_PROTOCOL];
+
+    pub const fn protocol(self) -> &'static str {
+        match self {
+            Self::Json => Self::JSON_PROTOCOL,
+            Self::Cbor => Self::CBOR_PROTOCOL,
+        }
+    }
+
+    pub fn from_protocol(protocol: &str) -> Option<Self> {
+        match protocol {
+            Self::JSON_PROTOCOL => Some(Self::Json),
+            Self::CBOR_PROTOCOL => Some(Self::Cbor),
+            _ => None,
+        }
+    }
+}
+
+/// The common envelope of websocket messages. The message kind is a tag for the payload, so
+/// the payload schema can evolve with new kinds without breaking old clients.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Envelope<T> {
+    pub id: Option<String>,
+    pub kind: String,
+    pub payload: T,
+}
+
+impl<T> Envelope<T> {
+    pub fn new(kind: impl Into<String>, payload: T) -> Self {
+        Self {
+            id: None,
+            kind: kind.into(),
+            payload,
+        }
+    }
+
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+}
+
+impl<T: Serialize> Envelope<T> {
+    pub fn to_json(&self) -> Result<String, EnvelopeError> {
+        serde_json::to_string(self).map_err(EnvelopeError::Json)
+    }
+
+    pub fn to_cbor(&self) -> Result<Vec<u8>, EnvelopeError> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(self, &mut bytes).map_err(|err| EnvelopeError::Cbor(err.to_string()))?;
+        Ok(bytes)
+    }
+}
+
+impl<T: DeserializeOwned> Envelope<T> {
+    pub fn from_json(text: &str) -> Result<Self, EnvelopeError> {
+        serde_json::from_str(text).map_err(EnvelopeError::Json)
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, EnvelopeError> {
+        ciborium::from_reader(bytes).map_err(|err| EnvelopeError::Cbor(err.to_string()))
+    }
+}
+
+#[derive(Debug)]
+pub enum EnvelopeError {
+    Json(serde_json::Error),
+    Cbor(String),
+}
+
+impl fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(err) => write!(f, "JSON envelope error: {err}"),
+            Self::Cbor(err) => write!(f, "CBOR envelope error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for EnvelopeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope_roundtrip() {
+        let envelope = Envelope::new("message", vec!["hello".to_string()]).with_id("1");
+
+        let json = envelope.to_json().unwrap();
+        assert_eq!(json, r#"{"id":"1","kind":"message","payload":["hello"]}"#);
+        assert_eq!(Envelope::from_json(&json).unwrap(), envelope);
+
+        let cbor = envelope.to_cbor().unwrap();
+        assert!(cbor.len() < json.len());
+        assert_eq!(Envelope::from_cbor(&cbor).unwrap(), envelope);
+    }
+
+    #[test]
+    fn framing_protocols() {
+        for framing in [WsFraming::Json, WsFraming::Cbor] {
+            assert_eq!(WsFraming::from_protocol(framing.protocol()), Some(framing));
+        }
+        assert_eq!(WsFraming::from_protocol("unknown"), None);
+    }
+}
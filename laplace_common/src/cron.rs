@@ -0,0 +1,140 @@
+//! Parsing of the five-field cron expressions used by the lapp scheduled jobs.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronError(pub String);
+
+impl fmt::Display for CronError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Wrong cron expression: {}", self.0)
+    }
+}
+
+impl std::error::Error for CronError {}
+
+/// The cron schedule: minute, hour, day of month, month and day of week fields.
+/// Each field supports `*`, numbers, ranges `a-b`, steps `*/n` or `a-b/n` and lists `a,b,c`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// Checks the time, `weekday` is counted from Sunday as 0.
+    pub fn matches(&self, minute: u32, hour: u32, day: u32, month: u32, weekday: u32) -> bool {
+        let is_set = |field: u64, value: u32| value < 64 && field & (1 << value) != 0;
+
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => is_set(self.weekdays, weekday),
+            (false, true) => is_set(self.days, day),
+            // Like the classic cron, the day matches by any of the restricted day fields
+            (false, false) => is_set(self.days, day) || is_set(self.weekdays, weekday),
+        };
+
+        is_set(self.minutes, minute) && is_set(self.hours, hour) && is_set(self.months, month) && day_matches
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = CronError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<_> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields.as_slice() else {
+            return Err(CronError(format!("expected 5 fields in \"{expression}\"")));
+        };
+
+        let mut weekday_bits = parse_field(weekdays, 0, 7)?;
+        // Both 0 and 7 are Sunday
+        if weekday_bits & (1 << 7) != 0 {
+            weekday_bits |= 1;
+        }
+
+        Ok(Self {
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            weekdays: weekday_bits,
+            any_day: *days == "*",
+            any_weekday: *weekdays == "*",
+        })
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, CronError> {
+    let mut bits = 0;
+
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, parse_number(step)?),
+            None => (item, 1),
+        };
+        if step == 0 {
+            return Err(CronError(format!("zero step in \"{item}\"")));
+        }
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_number(start)?, parse_number(end)?)
+        } else {
+            let start = parse_number(range)?;
+            (start, if step > 1 { max } else { start })
+        };
+        if start < min || end > max || start > end {
+            return Err(CronError(format!("\"{item}\" is out of range {min}-{max}")));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+fn parse_number(value: &str) -> Result<u32, CronError> {
+    value
+        .parse()
+        .map_err(|_| CronError(format!("\"{value}\" is not a number")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_match() {
+        let schedule: CronSchedule = "*/15 9-17 * * 1-5".parse().unwrap();
+        assert!(schedule.matches(0, 9, 10, 6, 1));
+        assert!(schedule.matches(45, 17, 10, 6, 5));
+        assert!(!schedule.matches(10, 9, 10, 6, 1));
+        assert!(!schedule.matches(0, 8, 10, 6, 1));
+        assert!(!schedule.matches(0, 9, 10, 6, 0));
+
+        let schedule: CronSchedule = "30 2 1,15 * *".parse().unwrap();
+        assert!(schedule.matches(30, 2, 15, 3, 4));
+        assert!(!schedule.matches(30, 2, 14, 3, 4));
+
+        let schedule: CronSchedule = "0 0 * * 7".parse().unwrap();
+        assert!(schedule.matches(0, 0, 5, 1, 0));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("* * * *".parse::<CronSchedule>().is_err());
+        assert!("60 * * * *".parse::<CronSchedule>().is_err());
+        assert!("*/0 * * * *".parse::<CronSchedule>().is_err());
+        assert!("a * * * *".parse::<CronSchedule>().is_err());
+    }
+}
@@ -3,9 +3,13 @@ use std::path::Path;
 use serde::{Deserialize, Serialize};
 
 pub use self::access::*;
+pub use self::dependency::*;
+pub use self::manifest::*;
 pub use self::settings::*;
 
 pub mod access;
+pub mod dependency;
+pub mod manifest;
 pub mod settings;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
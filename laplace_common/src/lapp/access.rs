@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use strum::{AsRefStr, EnumString, IntoStaticStr};
 
-#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, AsRefStr, IntoStaticStr, EnumString)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, AsRefStr, IntoStaticStr, EnumString)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum Permission {
@@ -10,11 +10,19 @@ pub enum Permission {
     ClientHttp,
     Http,
     Websocket,
+    WebsocketClient,
     Tcp,
     Database,
+    DatabaseRead,
     Sleep,
     LappsIncoming,
     LappsOutgoing,
+    Webdav,
+    Graphql,
+    Mqtt,
+    Threads,
+    Crypto,
+    Mail,
 }
 
 impl Permission {
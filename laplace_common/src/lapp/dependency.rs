@@ -0,0 +1,214 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use semver::{Version, VersionReq};
+
+use super::{DependencySettings, LappSettings};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencyError {
+    Missing(String),
+    Disabled(String),
+    WrongRequirement {
+        lapp_name: String,
+        requirement: String,
+        error: String,
+    },
+    VersionMismatch {
+        lapp_name: String,
+        requirement: String,
+        version: Option<String>,
+    },
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for DependencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(lapp_name) => write!(f, "dependency '{lapp_name}' is not installed"),
+            Self::Disabled(lapp_name) => write!(f, "dependency '{lapp_name}' is disabled"),
+            Self::WrongRequirement {
+                lapp_name,
+                requirement,
+                error,
+            } => write!(
+                f,
+                "wrong version requirement '{requirement}' of dependency '{lapp_name}': {error}"
+            ),
+            Self::VersionMismatch {
+                lapp_name,
+                requirement,
+                version,
+            } => write!(
+                f,
+                "dependency '{lapp_name}' version {} does not match '{requirement}'",
+                version.as_deref().unwrap_or("<none>")
+            ),
+            Self::Cycle(lapp_names) => write!(f, "dependencies form a cycle between {}", lapp_names.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for DependencyError {}
+
+impl DependencySettings {
+    /// Checks that the installed dependency lapp is enabled and its version matches the requirement.
+    pub fn check(&self, dependency: Option<&LappSettings>) -> Result<(), DependencyError> {
+        let dependency = dependency.ok_or_else(|| DependencyError::Missing(self.lapp_name.clone()))?;
+        if !dependency.enabled() {
+            return Err(DependencyError::Disabled(self.lapp_name.clone()));
+        }
+
+        if let Some(requirement) = &self.version {
+            let version_req = VersionReq::parse(requirement).map_err(|err| DependencyError::WrongRequirement {
+                lapp_name: self.lapp_name.clone(),
+                requirement: requirement.clone(),
+                error: err.to_string(),
+            })?;
+
+            let version = dependency.application.version.as_deref();
+            let is_matched = version
+                .and_then(|version| Version::parse(version).ok())
+                .is_some_and(|version| version_req.matches(&version));
+            if !is_matched {
+                return Err(DependencyError::VersionMismatch {
+                    lapp_name: self.lapp_name.clone(),
+                    requirement: requirement.clone(),
+                    version: version.map(Into::into),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks the direct dependencies of the lapp and that it is not a part of a dependency cycle.
+pub fn check_dependencies(lapp_name: &str, lapps: &HashMap<String, LappSettings>) -> Vec<DependencyError> {
+    let Some(settings) = lapps.get(lapp_name) else {
+        return Vec::new();
+    };
+
+    let mut errors: Vec<_> = settings
+        .dependencies()
+        .iter()
+        .filter_map(|dependency| dependency.check(lapps.get(&dependency.lapp_name)).err())
+        .collect();
+
+    if !settings.dependencies().is_empty() {
+        let (_, unresolved) = load_order(lapps);
+        if unresolved.iter().any(|name| name == lapp_name) {
+            errors.push(DependencyError::Cycle(unresolved));
+        }
+    }
+
+    errors
+}
+
+/// Orders the lapp names so that every lapp follows its installed dependencies. The lapps of the dependency
+/// cycles and the lapps that depend on them can't be ordered, they are returned separately.
+pub fn load_order(lapps: &HashMap<String, LappSettings>) -> (Vec<String>, Vec<String>) {
+    let mut pending: BTreeMap<&str, BTreeSet<&str>> = lapps
+        .iter()
+        .map(|(name, settings)| {
+            let dependencies = settings
+                .dependencies()
+                .iter()
+                .map(|dependency| dependency.lapp_name.as_str())
+                .filter(|dependency_name| lapps.contains_key(*dependency_name))
+                .collect();
+            (name.as_str(), dependencies)
+        })
+        .collect();
+
+    let mut order = Vec::with_capacity(lapps.len());
+    loop {
+        let ready: Vec<&str> = pending
+            .iter()
+            .filter(|(_, dependencies)| dependencies.iter().all(|name| !pending.contains_key(name)))
+            .map(|(&name, _)| name)
+            .collect();
+        if ready.is_empty() {
+            break;
+        }
+
+        for name in ready {
+            pending.remove(name);
+            order.push(name.to_string());
+        }
+    }
+
+    (order, pending.into_keys().map(Into::into).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lapp(version: &str, enabled: bool, dependencies: &[(&str, Option<&str>)]) -> LappSettings {
+        let mut settings = LappSettings::default();
+        settings.application.version = Some(version.into());
+        settings.application.enabled = enabled;
+        settings.dependencies = Some(
+            dependencies
+                .iter()
+                .map(|&(lapp_name, version)| DependencySettings {
+                    lapp_name: lapp_name.into(),
+                    version: version.map(Into::into),
+                })
+                .collect(),
+        );
+        settings
+    }
+
+    #[test]
+    fn order_by_dependencies() {
+        let lapps = HashMap::from([
+            (
+                "app".to_string(),
+                lapp("1.0.0", true, &[("chat", None), ("store", None)]),
+            ),
+            ("chat".to_string(), lapp("0.3.1", true, &[("store", None)])),
+            ("store".to_string(), lapp("0.2.0", true, &[])),
+            ("first".to_string(), lapp("0.1.0", true, &[("second", None)])),
+            ("second".to_string(), lapp("0.1.0", true, &[("first", None)])),
+        ]);
+
+        let (order, unresolved) = load_order(&lapps);
+        assert_eq!(order, ["store", "chat", "app"]);
+        assert_eq!(unresolved, ["first", "second"]);
+        assert!(check_dependencies("app", &lapps).is_empty());
+        assert_eq!(check_dependencies("first", &lapps), [DependencyError::Cycle(vec![
+            "first".into(),
+            "second".into()
+        ])]);
+    }
+
+    #[test]
+    fn check_dependency_state_and_version() {
+        let lapps = HashMap::from([
+            (
+                "app".to_string(),
+                lapp("1.0.0", true, &[
+                    ("chat", Some(">=0.2, <0.4")),
+                    ("store", Some("^0.3")),
+                    ("notes", None),
+                    ("missing", None),
+                ]),
+            ),
+            ("chat".to_string(), lapp("0.3.1", true, &[])),
+            ("store".to_string(), lapp("0.2.0", true, &[])),
+            ("notes".to_string(), lapp("1.0.0", false, &[])),
+        ]);
+
+        assert_eq!(check_dependencies("app", &lapps), [
+            DependencyError::VersionMismatch {
+                lapp_name: "store".into(),
+                requirement: "^0.3".into(),
+                version: Some("0.2.0".into()),
+            },
+            DependencyError::Disabled("notes".into()),
+            DependencyError::Missing("missing".into()),
+        ]);
+    }
+}
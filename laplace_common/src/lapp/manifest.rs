@@ -0,0 +1,263 @@
+use std::fmt;
+use std::path::Component;
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use super::{Lapp, LappSettings, Permission};
+
+/// The entry point of the lapp found in the lapp directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryPoint {
+    /// The `{lapp_name}_server.wasm` server module.
+    ServerModule,
+
+    /// The `static/index.html` page.
+    StaticIndex,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ManifestError {
+    Config { error: String },
+    WrongName { name: String },
+    MissingTitle,
+    MissingVersion,
+    WrongVersion { version: String, error: String },
+    WrongHomepage { homepage: String },
+    DuplicatedPermission { permission: Permission },
+    NotRequiredPermission { permission: Permission },
+    WrongMountPath { guest_path: String },
+    WrongMountHostPath { path: String },
+    NoEntryPoint,
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config { error } => write!(f, "wrong config: {error}"),
+            Self::WrongName { name } => write!(f, "wrong name '{name}', only letters, digits, '_' and '-' are allowed"),
+            Self::MissingTitle => write!(f, "application title is missing"),
+            Self::MissingVersion => write!(f, "application version is missing"),
+            Self::WrongVersion { version, error } => write!(f, "wrong version '{version}': {error}"),
+            Self::WrongHomepage { homepage } => write!(f, "homepage '{homepage}' is not an HTTP URL"),
+            Self::DuplicatedPermission { permission } => {
+                write!(f, "permission '{}' is required twice", permission.as_str())
+            },
+            Self::NotRequiredPermission { permission } => {
+                write!(f, "permission '{}' is allowed but not required", permission.as_str())
+            },
+            Self::WrongMountPath { guest_path } => {
+                write!(f, "mount path '{guest_path}' is not absolute or is mounted twice")
+            },
+            Self::WrongMountHostPath { path } => write!(f, "mount host path '{path}' contains '..'"),
+            Self::NoEntryPoint => write!(f, "neither server module nor static index page is found"),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+/// The lapp that is not loaded because of the manifest errors.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct InvalidLapp {
+    pub lapp_name: String,
+    pub errors: Vec<ManifestError>,
+}
+
+/// The validated required fields of the lapp config and the entry points of the lapp.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LappManifest {
+    pub name: String,
+    pub version: String,
+    pub required_permissions: Vec<Permission>,
+    pub entry_points: Vec<EntryPoint>,
+}
+
+impl LappManifest {
+    /// Validates the lapp settings, the entry points are the ones found in the lapp directory.
+    pub fn new(settings: &LappSettings, entry_points: Vec<EntryPoint>) -> Result<Self, Vec<ManifestError>> {
+        let mut errors = Vec::new();
+
+        let name = settings.name();
+        let is_valid_name = !name.is_empty()
+            && !Lapp::<()>::is_main(name)
+            && name
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-');
+        if !is_valid_name {
+            errors.push(ManifestError::WrongName { name: name.into() });
+        }
+
+        if settings.title().trim().is_empty() {
+            errors.push(ManifestError::MissingTitle);
+        }
+
+        let version = settings.application.version.as_deref();
+        match version.map(Version::parse) {
+            Some(Ok(_)) => (),
+            Some(Err(err)) => errors.push(ManifestError::WrongVersion {
+                version: version.unwrap_or_default().into(),
+                error: err.to_string(),
+            }),
+            None => errors.push(ManifestError::MissingVersion),
+        }
+
+        if let Some(homepage) = &settings.application.homepage {
+            if settings.application.homepage_url().is_none() {
+                errors.push(ManifestError::WrongHomepage {
+                    homepage: homepage.clone(),
+                });
+            }
+        }
+
+        let mut required = Vec::new();
+        for &permission in &settings.permissions.required {
+            if required.contains(&permission) {
+                errors.push(ManifestError::DuplicatedPermission { permission });
+            } else {
+                required.push(permission);
+            }
+        }
+        for &permission in &settings.permissions.allowed {
+            if !required.contains(&permission) {
+                errors.push(ManifestError::NotRequiredPermission { permission });
+            }
+        }
+
+        let mut guest_paths = Vec::new();
+        for mount in settings.mounts.iter().flatten() {
+            let guest_path = mount.guest_path.trim_end_matches('/');
+            if !mount.guest_path.starts_with('/') || guest_paths.contains(&guest_path) {
+                errors.push(ManifestError::WrongMountPath {
+                    guest_path: mount.guest_path.clone(),
+                });
+            }
+            guest_paths.push(guest_path);
+
+            // The host path is resolved on the instantiation, it must not step out of the lapp or allowed directory
+            if mount
+                .path
+                .components()
+                .any(|component| component == Component::ParentDir)
+            {
+                errors.push(ManifestError::WrongMountHostPath {
+                    path: mount.path.display().to_string(),
+                });
+            }
+        }
+
+        if entry_points.is_empty() {
+            errors.push(ManifestError::NoEntryPoint);
+        }
+
+        match version {
+            Some(version) if errors.is_empty() => Ok(Self {
+                name: name.into(),
+                version: version.into(),
+                required_permissions: settings.permissions.required.clone(),
+                entry_points,
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lapp::MountSettings;
+
+    fn settings(name: &str, version: Option<&str>) -> LappSettings {
+        let mut settings = LappSettings {
+            lapp_name: name.into(),
+            ..LappSettings::default()
+        };
+        settings.application.title = "Notes".into();
+        settings.application.version = version.map(Into::into);
+        settings.permissions.required = vec![Permission::Database, Permission::ClientHttp];
+        settings.permissions.allowed = vec![Permission::Database];
+        settings
+    }
+
+    #[test]
+    fn valid_manifest() {
+        let manifest = LappManifest::new(&settings("notes", Some("0.2.1")), vec![EntryPoint::ServerModule]).unwrap();
+        assert_eq!(manifest.name, "notes");
+        assert_eq!(manifest.version, "0.2.1");
+        assert_eq!(manifest.required_permissions, [
+            Permission::Database,
+            Permission::ClientHttp
+        ]);
+    }
+
+    #[test]
+    fn manifest_errors() {
+        let mut wrong = settings("../notes", Some("0.2"));
+        wrong.permissions.allowed.push(Permission::Http);
+        let mount = |guest_path: &str| MountSettings {
+            path: "shared".into(),
+            guest_path: guest_path.into(),
+            read: true,
+            write: false,
+            create: false,
+        };
+        let outside_mount = MountSettings {
+            path: "data/../../shared".into(),
+            ..mount("/shared")
+        };
+        wrong.mounts = Some(vec![
+            mount("/"),
+            mount("shared"),
+            mount("/data"),
+            mount("/data/"),
+            outside_mount,
+        ]);
+
+        let errors = LappManifest::new(&wrong, vec![]).unwrap_err();
+        assert_eq!(errors.len(), 7);
+        assert_eq!(errors[0], ManifestError::WrongName {
+            name: "../notes".into()
+        });
+        assert!(matches!(errors[1], ManifestError::WrongVersion { .. }));
+        assert_eq!(errors[2], ManifestError::NotRequiredPermission {
+            permission: Permission::Http
+        });
+        assert_eq!(errors[3], ManifestError::WrongMountPath {
+            guest_path: "shared".into()
+        });
+        assert_eq!(errors[4], ManifestError::WrongMountPath {
+            guest_path: "/data/".into()
+        });
+        assert_eq!(errors[5], ManifestError::WrongMountHostPath {
+            path: "data/../../shared".into()
+        });
+        assert_eq!(errors[6], ManifestError::NoEntryPoint);
+
+        assert_eq!(
+            LappManifest::new(&settings("notes", None), vec![EntryPoint::StaticIndex]),
+            Err(vec![ManifestError::MissingVersion])
+        );
+    }
+
+    #[test]
+    fn homepage_errors() {
+        let mut lapp_settings = settings("notes", Some("0.2.1"));
+        lapp_settings.application.homepage = Some("https://example.com/notes".into());
+        assert!(LappManifest::new(&lapp_settings, vec![EntryPoint::ServerModule]).is_ok());
+
+        for homepage in [
+            "javascript:alert(1)",
+            " JavaScript:alert(1)",
+            "data:text/html,notes",
+            "example.com",
+        ] {
+            lapp_settings.application.homepage = Some(homepage.into());
+            let errors = LappManifest::new(&lapp_settings, vec![EntryPoint::ServerModule]).unwrap_err();
+            assert_eq!(errors, [ManifestError::WrongHomepage {
+                homepage: homepage.into()
+            }]);
+        }
+    }
+}
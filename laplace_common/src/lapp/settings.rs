@@ -1,7 +1,10 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::path::{Path, PathBuf};
 
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use url::Url;
 
 use super::Permission;
 
@@ -9,14 +12,48 @@ use super::Permission;
 #[serde(default)]
 pub struct ApplicationSettings {
     pub title: String,
+
+    /// The lapp version in the semver format, it is checked against the version requirements of the dependent lapps.
+    pub version: Option<String>,
     pub enabled: bool,
     pub autoload: bool,
     pub description: Option<String>,
+    pub author: Option<String>,
+
+    /// The URL of the lapp home page.
+    pub homepage: Option<String>,
+
+    /// The SPDX license expression of the lapp, e.g. `MIT OR Apache-2.0`.
+    pub license: Option<String>,
+
+    /// The icon file path relative to the lapp static directory, it is shown in the launcher.
+    pub icon: Option<PathBuf>,
     pub tags: Option<Vec<String>>,
     pub access_token: Option<String>,
     pub additional_static_dirs: Vec<PathBuf>,
     #[serde(default = "default_data_dir")]
     pub data_dir: PathBuf,
+
+    /// Restore the server module instance from the snapshot taken after its `init`, instead of the initialization.
+    pub snapshot: bool,
+
+    /// Record the inbound requests and the host call results of the server module to the trace file.
+    pub record_trace: bool,
+
+    /// Pass the inbound requests to the server module without the body, the module reads the body by frames.
+    pub stream_request_body: bool,
+
+    /// The lapp is disabled by the server after the repeated failures, enabling the lapp lifts the quarantine.
+    pub quarantined: bool,
+}
+
+impl ApplicationSettings {
+    /// The home page URL if it is an `http` or `https` one, the other URLs (e.g. `javascript:`) are not linked.
+    pub fn homepage_url(&self) -> Option<&str> {
+        let homepage = self.homepage.as_deref()?;
+        let url = Url::parse(homepage).ok()?;
+        matches!(url.scheme(), "http" | "https").then_some(homepage)
+    }
 }
 
 fn default_data_dir() -> PathBuf {
@@ -28,6 +65,9 @@ fn default_data_dir() -> PathBuf {
 pub struct PermissionsSettings {
     pub required: Vec<Permission>,
     pub allowed: Vec<Permission>,
+
+    /// The permissions requested by the lapp update that wait for the approval.
+    pub pending: Vec<Permission>,
 }
 
 impl PermissionsSettings {
@@ -35,23 +75,88 @@ impl PermissionsSettings {
         self.allowed.contains(&permission)
     }
 
+    pub fn is_pending(&self, permission: Permission) -> bool {
+        self.pending.contains(&permission)
+    }
+
+    /// Allows the permission, the pending permission is approved.
     pub fn allow(&mut self, permission: Permission) -> bool {
+        let is_pending = self.remove_pending(permission);
         if !self.is_allowed(permission) {
             self.allowed.push(permission);
             true
         } else {
-            false
+            is_pending
         }
     }
 
+    /// Denies the permission, the pending permission is rejected.
     pub fn deny(&mut self, permission: Permission) -> bool {
+        let is_pending = self.remove_pending(permission);
         let index = self.allowed.iter().position(|allowed| *allowed == permission);
         if let Some(index) = index {
             self.allowed.remove(index);
             true
         } else {
-            false
+            is_pending
+        }
+    }
+
+    /// Moves the permissions that are newly required or allowed by the updated settings compared to the previous
+    /// ones to the pending permissions, so the update does not grant them without the approval. Returns whether the
+    /// settings are changed.
+    pub fn hold_new(&mut self, previous: &PermissionsSettings) -> bool {
+        let before = (self.allowed.clone(), self.pending.clone());
+
+        for &permission in &previous.pending {
+            if !self.is_pending(permission) {
+                self.pending.push(permission);
+            }
+        }
+        for permission in self.required.clone() {
+            if !previous.required.contains(&permission)
+                && !previous.is_allowed(permission)
+                && !self.is_pending(permission)
+            {
+                self.pending.push(permission);
+            }
+        }
+        for permission in self.allowed.clone() {
+            if !previous.is_allowed(permission) {
+                self.allowed.retain(|allowed| *allowed != permission);
+                if !self.is_pending(permission) {
+                    self.pending.push(permission);
+                }
+            }
         }
+
+        let (allowed, required) = (&self.allowed, &self.required);
+        self.pending
+            .retain(|permission| required.contains(permission) && !allowed.contains(permission));
+
+        before != (self.allowed.clone(), self.pending.clone())
+    }
+
+    /// Allows exactly the required permissions of the profile, the pending permissions are approved or rejected by
+    /// the profile. Returns whether the settings are changed.
+    pub fn apply_profile(&mut self, profile: &[Permission]) -> bool {
+        let allowed: Vec<_> = self
+            .required()
+            .filter(|permission| profile.contains(permission))
+            .collect();
+        let is_changed = !self.pending.is_empty()
+            || allowed.len() != self.allowed.len()
+            || allowed.iter().any(|permission| !self.is_allowed(*permission));
+
+        self.allowed = allowed;
+        self.pending.clear();
+        is_changed
+    }
+
+    fn remove_pending(&mut self, permission: Permission) -> bool {
+        let len = self.pending.len();
+        self.pending.retain(|pending| *pending != permission);
+        self.pending.len() != len
     }
 
     pub fn required(&self) -> impl Iterator<Item = Permission> + '_ {
@@ -61,17 +166,35 @@ impl PermissionsSettings {
     pub fn allowed(&self) -> impl Iterator<Item = Permission> + '_ {
         self.allowed.iter().copied()
     }
+
+    pub fn pending(&self) -> impl Iterator<Item = Permission> + '_ {
+        self.pending.iter().copied()
+    }
 }
 
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct DatabaseSettings {
     pub path: Option<PathBuf>,
+
+    /// The FTS5 full-text search tables created in the lapp database on the instantiation.
+    pub fts_tables: Vec<FtsTableSettings>,
+
+    /// The directory of the online database backups, relative to the lapp directory. It is `backups` if missing.
+    pub backup_dir: Option<PathBuf>,
 }
 
 impl DatabaseSettings {
     pub const fn new() -> Self {
-        Self { path: None }
+        Self {
+            path: None,
+            fts_tables: Vec::new(),
+            backup_dir: None,
+        }
+    }
+
+    pub fn backup_dir(&self) -> &Path {
+        self.backup_dir.as_deref().unwrap_or_else(|| Path::new("backups"))
     }
 
     pub fn path(&self) -> &Path {
@@ -83,11 +206,58 @@ impl DatabaseSettings {
     }
 }
 
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FtsTableSettings {
+    pub name: String,
+    pub columns: Vec<String>,
+
+    /// The table indexed by the external content FTS table, the lapp keeps the index in sync, e.g. by the triggers.
+    pub content: Option<String>,
+
+    /// The integer primary key of the content table, `rowid` by default.
+    pub content_rowid: Option<String>,
+
+    /// The FTS5 tokenizer, e.g. `porter unicode61`.
+    pub tokenize: Option<String>,
+}
+
+impl FtsTableSettings {
+    /// Returns the statement creating the table if it does not exist, the names and the options are quoted.
+    pub fn create_table_sql(&self) -> String {
+        let mut args: Vec<_> = self.columns.iter().map(|column| quote_identifier(column)).collect();
+        if let Some(content) = &self.content {
+            args.push(format!("content={}", quote_literal(content)));
+        }
+        if let Some(content_rowid) = &self.content_rowid {
+            args.push(format!("content_rowid={}", quote_literal(content_rowid)));
+        }
+        if let Some(tokenize) = &self.tokenize {
+            args.push(format!("tokenize={}", quote_literal(tokenize)));
+        }
+
+        format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS {} USING fts5({})",
+            quote_identifier(&self.name),
+            args.join(", ")
+        )
+    }
+}
+
+pub fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn quote_literal(literal: &str) -> String {
+    format!("'{}'", literal.replace('\'', "''"))
+}
+
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct NetworkSettings {
     pub http: Option<HttpSettings>,
     pub gossipsub: Option<GossipsubSettings>,
+    pub mqtt: Option<MqttSettings>,
 }
 
 impl NetworkSettings {
@@ -95,6 +265,7 @@ impl NetworkSettings {
         Self {
             http: None,
             gossipsub: None,
+            mqtt: None,
         }
     }
 
@@ -117,6 +288,12 @@ impl NetworkSettings {
     pub fn into_gossipsub(self) -> GossipsubSettings {
         self.gossipsub.unwrap_or_default()
     }
+
+    pub fn mqtt(&self) -> &MqttSettings {
+        static DEFAULT: MqttSettings = MqttSettings::new();
+
+        self.mqtt.as_ref().unwrap_or(&DEFAULT)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -124,6 +301,10 @@ impl NetworkSettings {
 pub struct HttpSettings {
     pub methods: HttpMethods,
     pub hosts: HttpHosts,
+
+    /// The patterns of the allowed request URLs: `host`, `*.domain` or `scheme://host[:port]/path-prefix`. When the
+    /// list is not empty, the request URL must match one of the patterns in addition to the `hosts`.
+    pub allow: Vec<String>,
     #[serde(default = "http_timeout_ms")]
     pub timeout_ms: u64,
 }
@@ -137,9 +318,57 @@ impl HttpSettings {
         Self {
             methods: HttpMethods::new(),
             hosts: HttpHosts::new(),
+            allow: Vec::new(),
             timeout_ms: http_timeout_ms(),
         }
     }
+
+    /// Checks the request URL parts against the `allow` patterns, any URL is allowed by the empty list.
+    pub fn is_url_allowed(&self, scheme: &str, host: &str, port: Option<u16>, path: &str) -> bool {
+        self.allow.is_empty()
+            || self
+                .allow
+                .iter()
+                .any(|pattern| url_pattern_matches(pattern, scheme, host, port, path))
+    }
+}
+
+/// The host pattern matches the host on any scheme and port. The URL pattern matches the scheme, the host pattern,
+/// the port (the default port of the scheme if missing) and the path prefix, the trailing `*` of the prefix is
+/// optional.
+fn url_pattern_matches(pattern: &str, scheme: &str, host: &str, port: Option<u16>, path: &str) -> bool {
+    let Some((pattern_scheme, rest)) = pattern.split_once("://") else {
+        return host_pattern_matches(pattern, host);
+    };
+    let (authority, path_prefix) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let (pattern_host, pattern_port) = match authority.rsplit_once(':').filter(|(_, port)| !port.ends_with(']')) {
+        Some((pattern_host, pattern_port)) => match pattern_port.parse::<u16>() {
+            Ok(pattern_port) => (pattern_host, Some(pattern_port)),
+            Err(_) => return false,
+        },
+        None => (authority, None),
+    };
+    let default_port = match scheme.to_ascii_lowercase().as_str() {
+        "http" => Some(80),
+        "https" => Some(443),
+        _ => None,
+    };
+
+    pattern_scheme.eq_ignore_ascii_case(scheme)
+        && host_pattern_matches(pattern_host, host)
+        && port.or(default_port) == pattern_port.or(default_port)
+        && path.starts_with(path_prefix.trim_end_matches('*'))
+}
+
+/// The `*.domain` pattern matches the subdomains of the domain, but not the domain itself.
+fn host_pattern_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .to_ascii_lowercase()
+            .strip_suffix(&domain.to_ascii_lowercase())
+            .is_some_and(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.')),
+        None => pattern.eq_ignore_ascii_case(host),
+    }
 }
 
 impl Default for HttpSettings {
@@ -165,6 +394,17 @@ impl HttpMethods {
     pub const fn new() -> Self {
         Self::All
     }
+
+    /// Checks the request method name, e.g. `GET`.
+    pub fn is_allowed(&self, method: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::List(methods) => methods.iter().any(|item| match item {
+                HttpMethod::Get => method.eq_ignore_ascii_case("get"),
+                HttpMethod::Post => method.eq_ignore_ascii_case("post"),
+            }),
+        }
+    }
 }
 
 impl Default for HttpMethods {
@@ -311,6 +551,23 @@ impl GossipsubSettings {
     }
 }
 
+/// MQTT topic filters of the lapp, the wildcards `+` and `#` are allowed.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MqttSettings {
+    pub subscribe: Vec<String>,
+    pub publish: Vec<String>,
+}
+
+impl MqttSettings {
+    pub const fn new() -> Self {
+        Self {
+            subscribe: Vec::new(),
+            publish: Vec::new(),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct LappIncomingRequestSettings {
@@ -333,6 +590,333 @@ pub struct LappRequestsSettings {
     pub outgoing: Option<Vec<LappOutgoingRequestSettings>>,
 }
 
+impl LappRequestsSettings {
+    /// Checks the request of the lapp to the `lapp_name` lapp, any request is allowed when `outgoing` is missing.
+    pub fn is_outgoing_allowed(&self, method: &str, path: &str) -> bool {
+        self.outgoing.as_ref().is_none_or(|outgoing| {
+            outgoing
+                .iter()
+                .any(|rule| rule.methods.is_allowed(method) && request_pattern_matches(&rule.request, path))
+        })
+    }
+
+    /// Checks the request of the `lapp_name` lapp to the lapp, any request is allowed when `incoming` is missing.
+    pub fn is_incoming_allowed(&self, method: &str, path: &str) -> bool {
+        self.incoming.as_ref().is_none_or(|incoming| {
+            incoming
+                .iter()
+                .any(|rule| rule.methods.is_allowed(method) && request_pattern_matches(&rule.request, path))
+        })
+    }
+}
+
+/// The request pattern is the path prefix relative to the lapp root, the trailing `*` or `.*` is optional.
+fn request_pattern_matches(pattern: &str, path: &str) -> bool {
+    let prefix = pattern
+        .strip_suffix(".*")
+        .or_else(|| pattern.strip_suffix('*'))
+        .unwrap_or(pattern);
+    path.trim_start_matches('/').starts_with(prefix.trim_start_matches('/'))
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct JobSettings {
+    pub name: String,
+
+    /// The five-field cron expression, e.g. `*/10 * * * *`.
+    pub schedule: String,
+
+    /// The lapp HTTP route requested with the POST method on each job run. The job without the path calls
+    /// the `on_schedule` export of the lapp server module with the job name.
+    pub path: Option<String>,
+}
+
+/// The host directory mounted to the lapp server module. The access flags are granted only along with the
+/// `file_read` and `file_write` permissions, the missing host directory is created when any access is granted.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MountSettings {
+    /// The host directory, relative to the lapp directory if not absolute.
+    pub path: PathBuf,
+
+    /// The absolute path of the directory seen by the lapp, e.g. `/shared`.
+    pub guest_path: String,
+
+    /// Read the directory entries and the files, requires `file_read`.
+    #[serde(default)]
+    pub read: bool,
+
+    /// Write to the existing files, requires `file_write`.
+    #[serde(default)]
+    pub write: bool,
+
+    /// Create, rename and remove the directory entries, requires `file_write`.
+    #[serde(default)]
+    pub create: bool,
+}
+
+/// Limits of the threads spawned by the lapp compiled with the wasm threads proposal.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ThreadsSettings {
+    /// The maximum number of the simultaneously running threads of the lapp.
+    pub max_threads: u32,
+
+    /// The CPU time in milliseconds the lapp threads may spend per minute, unlimited if missing.
+    pub cpu_quota_ms: Option<u64>,
+}
+
+impl ThreadsSettings {
+    pub const fn new() -> Self {
+        Self {
+            max_threads: 4,
+            cpu_quota_ms: None,
+        }
+    }
+}
+
+impl Default for ThreadsSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Limits of the resources used by the lapp server module instance.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ResourcesSettings {
+    /// The maximum size in MiB the linear memory of the lapp may grow to, limited by the server if missing.
+    pub max_memory_mb: Option<u64>,
+
+    /// The time in milliseconds a single call of the lapp server module may take, limited by the server if missing.
+    pub call_time_limit_ms: Option<u64>,
+
+    /// The number of the lapp server module instances processing the HTTP requests in parallel, one if missing.
+    pub instances: Option<u32>,
+
+    /// The maximum size in MiB of the lapp data directory and database, limited by the server if missing.
+    pub disk_quota_mb: Option<u64>,
+
+    /// The maximum size in MiB of the HTTP request body passed to the lapp, limited by the server if missing.
+    pub max_body_mb: Option<u64>,
+
+    /// The time in milliseconds the lapp may process an HTTP request, limited by the server if missing.
+    pub http_timeout_ms: Option<u64>,
+}
+
+impl ResourcesSettings {
+    pub const fn new() -> Self {
+        Self {
+            max_memory_mb: None,
+            call_time_limit_ms: None,
+            instances: None,
+            disk_quota_mb: None,
+            max_body_mb: None,
+            http_timeout_ms: None,
+        }
+    }
+
+    pub fn instances(&self) -> u32 {
+        self.instances.unwrap_or(1).max(1)
+    }
+}
+
+/// The restart policy of the lapp instance that trapped or panicked during the call.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Keep the failed instance running.
+    #[default]
+    Never,
+
+    /// Re-instantiate the failed instance.
+    OnFailure,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StaticCacheSettings {
+    /// The time in seconds the browser uses the static file without revalidation, by default the browser revalidates
+    /// the file by its ETag on every use.
+    pub max_age_secs: u64,
+
+    /// The patterns of the static file paths which never change, e.g. the fingerprinted assets `assets/*.js`. The `*`
+    /// matches any characters, the path is relative to the static directory.
+    pub immutable: Vec<String>,
+}
+
+impl StaticCacheSettings {
+    /// The year the immutable files are cached for.
+    pub const IMMUTABLE_MAX_AGE_SECS: u64 = 365 * 24 * 60 * 60;
+
+    pub const fn new() -> Self {
+        Self {
+            max_age_secs: 0,
+            immutable: Vec::new(),
+        }
+    }
+
+    /// The `Cache-Control` header value of the static file.
+    pub fn cache_control(&self, file_path: &str) -> String {
+        let file_path = file_path.trim_start_matches('/');
+        if self
+            .immutable
+            .iter()
+            .any(|pattern| wildcard_matches(pattern.trim_start_matches('/'), file_path))
+        {
+            format!("public, max-age={}, immutable", Self::IMMUTABLE_MAX_AGE_SECS)
+        } else if self.max_age_secs > 0 {
+            format!("public, max-age={}", self.max_age_secs)
+        } else {
+            "no-cache".into()
+        }
+    }
+}
+
+/// The protective headers of the responses. The missing header is inherited from the server settings, and the empty one
+/// is not sent.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SecurityHeadersSettings {
+    /// The `Content-Security-Policy` header, e.g. `default-src 'self'; script-src 'self' 'wasm-unsafe-eval'`.
+    pub content_security_policy: Option<String>,
+
+    /// The `X-Frame-Options` header, e.g. `SAMEORIGIN` or `DENY`.
+    pub frame_options: Option<String>,
+
+    /// The `Referrer-Policy` header, e.g. `no-referrer`.
+    pub referrer_policy: Option<String>,
+
+    /// The `Strict-Transport-Security` header, it is sent over TLS only.
+    pub strict_transport_security: Option<String>,
+}
+
+impl SecurityHeadersSettings {
+    /// The headers sent unless they are overridden: the pages are not framed by the other origins, the full URL is not
+    /// leaked to the other origins and the browser keeps using HTTPS for a year. The content policy depends on the lapp
+    /// too much to be set by default.
+    pub fn recommended() -> Self {
+        Self {
+            content_security_policy: None,
+            frame_options: Some("SAMEORIGIN".into()),
+            referrer_policy: Some("strict-origin-when-cross-origin".into()),
+            strict_transport_security: Some("max-age=31536000".into()),
+        }
+    }
+
+    /// Returns the settings with the headers replaced by the set headers of the overrides.
+    pub fn merge(&self, overrides: &Self) -> Self {
+        let choose = |value: &Option<String>, override_value: &Option<String>| {
+            override_value.as_ref().or(value.as_ref()).cloned()
+        };
+
+        Self {
+            content_security_policy: choose(&self.content_security_policy, &overrides.content_security_policy),
+            frame_options: choose(&self.frame_options, &overrides.frame_options),
+            referrer_policy: choose(&self.referrer_policy, &overrides.referrer_policy),
+            strict_transport_security: choose(&self.strict_transport_security, &overrides.strict_transport_security),
+        }
+    }
+
+    /// Returns the names and the values of the headers to send, the missing and the empty headers are skipped.
+    pub fn headers(&self, is_tls: bool) -> Vec<(&'static str, &str)> {
+        [
+            ("content-security-policy", &self.content_security_policy),
+            ("x-frame-options", &self.frame_options),
+            ("referrer-policy", &self.referrer_policy),
+            (
+                "strict-transport-security",
+                if is_tls { &self.strict_transport_security } else { &None },
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value.as_deref().filter(|value| !value.is_empty())?)))
+        .collect()
+    }
+}
+
+/// Matches the text by the pattern, where `*` matches any characters.
+fn wildcard_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(first) = parts.next() else {
+        return text.is_empty();
+    };
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<_> = parts.collect();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ProxyRouteSettings {
+    /// The path prefix of the lapp route, e.g. `/api`.
+    pub path: String,
+
+    /// The URL of the upstream HTTP service, e.g. `http://127.0.0.1:8080/v1`.
+    pub upstream: String,
+}
+
+impl ProxyRouteSettings {
+    /// Returns the upstream URL of the lapp path if the path is within the route, the route prefix is replaced by
+    /// the upstream URL.
+    pub fn upstream_url(&self, lapp_path: &str) -> Option<String> {
+        let tail = lapp_path.strip_prefix(self.path.trim_end_matches('/'))?;
+        if !tail.is_empty() && !tail.starts_with('/') {
+            return None;
+        }
+        Some(format!("{}{tail}", self.upstream.trim_end_matches('/')))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RestartSettings {
+    pub policy: RestartPolicy,
+
+    /// The maximum number of the restarts in a row, the counter is reset by the call without failure.
+    pub max_retries: u32,
+
+    /// The delay in milliseconds before the first restart, it doubles with every next restart in a row.
+    pub backoff_ms: u64,
+}
+
+impl RestartSettings {
+    pub const fn new() -> Self {
+        Self {
+            policy: RestartPolicy::Never,
+            max_retries: 3,
+            backoff_ms: 1000,
+        }
+    }
+}
+
+impl Default for RestartSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct DependencySettings {
+    pub lapp_name: String,
+
+    /// The semver requirement of the dependency version, e.g. `>=0.2, <0.4`. Any version is accepted if missing.
+    pub version: Option<String>,
+}
+
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct LappSettings {
@@ -343,6 +927,32 @@ pub struct LappSettings {
     pub database: Option<DatabaseSettings>,
     pub network: Option<NetworkSettings>,
     pub lapp_requests: Option<Vec<LappRequestsSettings>>,
+    pub jobs: Option<Vec<JobSettings>>,
+    pub dependencies: Option<Vec<DependencySettings>>,
+
+    /// The per-route rate limits, e.g. `POST /api/send = 5/min per client`.
+    pub rate_limits: Option<Vec<String>>,
+    pub threads: Option<ThreadsSettings>,
+    pub resources: Option<ResourcesSettings>,
+    pub restart: Option<RestartSettings>,
+
+    /// The environment variables of the lapp server module, e.g. API keys and configuration.
+    pub env: Option<BTreeMap<String, String>>,
+
+    /// The browser caching of the lapp static files.
+    pub static_cache: Option<StaticCacheSettings>,
+
+    /// The routes forwarded to the upstream services instead of the lapp server module.
+    pub proxy: Option<Vec<ProxyRouteSettings>>,
+
+    /// The overrides of the server-wide security headers of the lapp responses.
+    pub security_headers: Option<SecurityHeadersSettings>,
+
+    /// The host directories mounted to the lapp server module, the data directory is mounted to `/` if missing.
+    pub mounts: Option<Vec<MountSettings>>,
+
+    /// The lapp-specific settings read by the lapp server module, the server does not interpret them.
+    pub custom: Option<serde_json::Map<String, serde_json::Value>>,
 }
 
 impl LappSettings {
@@ -364,6 +974,20 @@ impl LappSettings {
     #[inline]
     pub fn set_enabled(&mut self, enabled: bool) {
         self.application.enabled = enabled;
+        if enabled {
+            self.application.quarantined = false;
+        }
+    }
+
+    #[inline]
+    pub fn is_quarantined(&self) -> bool {
+        self.application.quarantined
+    }
+
+    /// Disables the failing lapp until it is enabled again.
+    pub fn quarantine(&mut self) {
+        self.application.enabled = false;
+        self.application.quarantined = true;
     }
 
     #[inline]
@@ -415,4 +1039,324 @@ impl LappSettings {
     pub fn into_lapp_requests(self) -> Vec<LappRequestsSettings> {
         self.lapp_requests.unwrap_or_default()
     }
+
+    /// The requests settings of the lapp with the given name.
+    pub fn lapp_requests_of(&self, lapp_name: &str) -> Option<&LappRequestsSettings> {
+        self.lapp_requests()
+            .iter()
+            .find(|requests| requests.lapp_name == lapp_name)
+    }
+
+    pub fn jobs(&self) -> &[JobSettings] {
+        self.jobs.as_deref().unwrap_or_default()
+    }
+
+    pub fn dependencies(&self) -> &[DependencySettings] {
+        self.dependencies.as_deref().unwrap_or_default()
+    }
+
+    pub fn rate_limits(&self) -> &[String] {
+        self.rate_limits.as_deref().unwrap_or_default()
+    }
+
+    pub fn threads(&self) -> &ThreadsSettings {
+        static DEFAULT: ThreadsSettings = ThreadsSettings::new();
+
+        self.threads.as_ref().unwrap_or(&DEFAULT)
+    }
+
+    pub fn resources(&self) -> &ResourcesSettings {
+        static DEFAULT: ResourcesSettings = ResourcesSettings::new();
+
+        self.resources.as_ref().unwrap_or(&DEFAULT)
+    }
+
+    pub fn restart(&self) -> &RestartSettings {
+        static DEFAULT: RestartSettings = RestartSettings::new();
+
+        self.restart.as_ref().unwrap_or(&DEFAULT)
+    }
+
+    pub fn static_cache(&self) -> &StaticCacheSettings {
+        static DEFAULT: StaticCacheSettings = StaticCacheSettings::new();
+
+        self.static_cache.as_ref().unwrap_or(&DEFAULT)
+    }
+
+    pub fn security_headers(&self) -> Option<&SecurityHeadersSettings> {
+        self.security_headers.as_ref()
+    }
+
+    pub fn proxy(&self) -> &[ProxyRouteSettings] {
+        self.proxy.as_deref().unwrap_or_default()
+    }
+
+    /// Returns the upstream URL of the proxy route with the longest prefix matching the lapp path.
+    pub fn proxy_upstream_url(&self, lapp_path: &str) -> Option<String> {
+        self.proxy()
+            .iter()
+            .filter(|route| route.upstream_url(lapp_path).is_some())
+            .max_by_key(|route| route.path.trim_end_matches('/').len())
+            .and_then(|route| route.upstream_url(lapp_path))
+    }
+
+    /// The `application` and `custom` sections passed to the lapp server module. The access token is omitted, it is
+    /// the secret of the lapp clients.
+    pub fn guest_settings_json(&self) -> serde_json::Value {
+        let mut application = serde_json::to_value(&self.application).unwrap_or_default();
+        if let Some(application) = application.as_object_mut() {
+            application.remove("access_token");
+        }
+
+        serde_json::json!({
+            "application": application,
+            "custom": self.custom.clone().unwrap_or_default(),
+        })
+    }
+
+    /// The configured mount points or the data directory mounted to `/` with all access allowed by the permissions.
+    pub fn mounts(&self) -> Cow<'_, [MountSettings]> {
+        match &self.mounts {
+            Some(mounts) => Cow::Borrowed(mounts),
+            None => Cow::Owned(vec![MountSettings {
+                path: self.application.data_dir.clone(),
+                guest_path: "/".into(),
+                read: true,
+                write: true,
+                create: true,
+            }]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_allow_patterns() {
+        let settings = HttpSettings {
+            allow: vec![
+                "example.com".into(),
+                "*.cdn.net".into(),
+                "https://api.service.io/v1/*".into(),
+                "http://localhost:8080".into(),
+            ],
+            ..HttpSettings::new()
+        };
+
+        assert!(settings.is_url_allowed("https", "example.com", None, "/any"));
+        assert!(settings.is_url_allowed("http", "EXAMPLE.com", Some(8000), "/"));
+        assert!(!settings.is_url_allowed("https", "www.example.com", None, "/"));
+
+        assert!(settings.is_url_allowed("https", "img.cdn.net", None, "/a.png"));
+        assert!(!settings.is_url_allowed("https", "cdn.net", None, "/a.png"));
+        assert!(!settings.is_url_allowed("https", "evilcdn.net", None, "/a.png"));
+
+        assert!(settings.is_url_allowed("https", "api.service.io", None, "/v1/users"));
+        assert!(settings.is_url_allowed("https", "api.service.io", Some(443), "/v1/"));
+        assert!(!settings.is_url_allowed("https", "api.service.io", None, "/v2/users"));
+        assert!(!settings.is_url_allowed("http", "api.service.io", None, "/v1/users"));
+        assert!(!settings.is_url_allowed("https", "api.service.io", Some(8443), "/v1/users"));
+
+        assert!(settings.is_url_allowed("http", "localhost", Some(8080), "/"));
+        assert!(!settings.is_url_allowed("http", "localhost", None, "/"));
+
+        assert!(HttpSettings::new().is_url_allowed("http", "any.host", None, "/"));
+    }
+
+    #[test]
+    fn lapp_requests_patterns() {
+        let settings: LappSettings = serde_json::from_value(serde_json::json!({
+            "lapp_requests": [
+                {
+                    "lapp_name": "sowa",
+                    "outgoing": [
+                        { "methods": ["get"], "request": "account/.*" },
+                        { "methods": ["post"], "request": "/transfer/*" },
+                    ],
+                },
+                { "lapp_name": "notes" },
+            ],
+        }))
+        .unwrap();
+
+        let sowa = settings.lapp_requests_of("sowa").unwrap();
+        assert!(sowa.is_outgoing_allowed("GET", "/account/1"));
+        assert!(!sowa.is_outgoing_allowed("POST", "account/1"));
+        assert!(sowa.is_outgoing_allowed("post", "transfer/2"));
+        assert!(!sowa.is_outgoing_allowed("GET", "/accounts"));
+        assert!(sowa.is_incoming_allowed("POST", "/any"));
+
+        assert!(settings
+            .lapp_requests_of("notes")
+            .unwrap()
+            .is_outgoing_allowed("POST", "/any"));
+        assert!(settings.lapp_requests_of("chat").is_none());
+    }
+
+    #[test]
+    fn fts_table_sql() {
+        let settings: LappSettings = serde_json::from_value(serde_json::json!({
+            "database": {
+                "fts_tables": [
+                    { "name": "notes_fts", "columns": ["title", "body"], "content": "notes", "content_rowid": "id" },
+                    { "name": "odd\"name", "columns": ["text"], "tokenize": "porter 'unicode61'" },
+                ],
+            },
+        }))
+        .unwrap();
+
+        let tables = &settings.database().fts_tables;
+        assert_eq!(
+            tables[0].create_table_sql(),
+            r#"CREATE VIRTUAL TABLE IF NOT EXISTS "notes_fts" USING fts5("title", "body", content='notes', content_rowid='id')"#
+        );
+        assert_eq!(
+            tables[1].create_table_sql(),
+            r#"CREATE VIRTUAL TABLE IF NOT EXISTS "odd""name" USING fts5("text", tokenize='porter ''unicode61''')"#
+        );
+    }
+
+    #[test]
+    fn static_cache_control() {
+        let settings: LappSettings = serde_json::from_value(serde_json::json!({
+            "static_cache": { "max_age_secs": 600, "immutable": ["assets/*", "*.wasm", "/fonts/*.*.woff2"] },
+        }))
+        .unwrap();
+
+        let static_cache = settings.static_cache();
+        let immutable = "public, max-age=31536000, immutable";
+        assert_eq!(static_cache.cache_control("assets/app.3f2a.js"), immutable);
+        assert_eq!(static_cache.cache_control("/chat_bg.wasm"), immutable);
+        assert_eq!(static_cache.cache_control("fonts/inter.a1b2.woff2"), immutable);
+        assert_eq!(static_cache.cache_control("fonts/inter.woff2"), "public, max-age=600");
+        assert_eq!(static_cache.cache_control("index.html"), "public, max-age=600");
+        assert_eq!(
+            LappSettings::default().static_cache().cache_control("app.js"),
+            "no-cache"
+        );
+
+        assert!(wildcard_matches("*", ""));
+        assert!(wildcard_matches("a*b*c", "abc"));
+        assert!(!wildcard_matches("a*b*c", "acb"));
+        assert!(!wildcard_matches("app.js", "app.json"));
+    }
+
+    #[test]
+    fn guest_settings_json() {
+        let settings: LappSettings = serde_json::from_value(serde_json::json!({
+            "application": { "title": "Notes", "access_token": "secret" },
+            "custom": { "page_size": 20, "feeds": { "default": "news" } },
+        }))
+        .unwrap();
+
+        let json = settings.guest_settings_json();
+        assert_eq!(json["application"]["title"], "Notes");
+        assert!(json["application"].get("access_token").is_none());
+        assert_eq!(json["custom"]["page_size"], 20);
+        assert_eq!(json["custom"]["feeds"]["default"], "news");
+
+        assert_eq!(
+            LappSettings::default().guest_settings_json()["custom"],
+            serde_json::json!({})
+        );
+    }
+
+    #[test]
+    fn hold_new_permissions() {
+        let previous = PermissionsSettings {
+            required: vec![Permission::Http, Permission::Database],
+            allowed: vec![Permission::Http],
+            pending: vec![],
+        };
+        let mut updated = PermissionsSettings {
+            required: vec![
+                Permission::Http,
+                Permission::Database,
+                Permission::Websocket,
+                Permission::Sleep,
+            ],
+            allowed: vec![Permission::Http, Permission::Sleep],
+            pending: vec![],
+        };
+
+        assert!(updated.hold_new(&previous));
+        assert_eq!(updated.allowed, [Permission::Http]);
+        assert_eq!(updated.pending, [Permission::Websocket, Permission::Sleep]);
+        assert!(!updated.clone().hold_new(&updated));
+
+        assert!(updated.allow(Permission::Sleep));
+        assert!(updated.deny(Permission::Websocket));
+        assert_eq!(updated.allowed, [Permission::Http, Permission::Sleep]);
+        assert!(updated.pending.is_empty());
+        assert!(!updated.deny(Permission::Database));
+    }
+
+    #[test]
+    fn apply_permission_profile() {
+        let mut permissions = PermissionsSettings {
+            required: vec![Permission::Http, Permission::Database, Permission::Sleep],
+            allowed: vec![Permission::Http],
+            pending: vec![Permission::Sleep],
+        };
+
+        assert!(permissions.apply_profile(&[Permission::Database, Permission::Sleep, Permission::FileRead]));
+        assert_eq!(permissions.allowed, [Permission::Database, Permission::Sleep]);
+        assert!(permissions.pending.is_empty());
+        assert!(!permissions.apply_profile(&[Permission::Sleep, Permission::Database]));
+    }
+
+    #[test]
+    fn proxy_upstream_url() {
+        let settings: LappSettings = serde_json::from_value(serde_json::json!({
+            "proxy": [
+                { "path": "/api", "upstream": "http://127.0.0.1:8080/v1/" },
+                { "path": "/api/admin/", "upstream": "http://127.0.0.1:9090" },
+                { "path": "/", "upstream": "http://127.0.0.1:3000" },
+            ],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            settings.proxy_upstream_url("/api/users").as_deref(),
+            Some("http://127.0.0.1:8080/v1/users")
+        );
+        assert_eq!(
+            settings.proxy_upstream_url("/api").as_deref(),
+            Some("http://127.0.0.1:8080/v1")
+        );
+        assert_eq!(
+            settings.proxy_upstream_url("/api/admin/stats").as_deref(),
+            Some("http://127.0.0.1:9090/stats")
+        );
+        assert_eq!(
+            settings.proxy_upstream_url("/apidocs").as_deref(),
+            Some("http://127.0.0.1:3000/apidocs")
+        );
+        assert_eq!(LappSettings::default().proxy_upstream_url("/api"), None);
+    }
+
+    #[test]
+    fn security_headers_overrides() {
+        let server = SecurityHeadersSettings::recommended().merge(&SecurityHeadersSettings {
+            referrer_policy: Some("no-referrer".into()),
+            ..Default::default()
+        });
+        let settings: LappSettings = serde_json::from_value(serde_json::json!({
+            "security_headers": { "content_security_policy": "default-src 'self'", "frame_options": "" },
+        }))
+        .unwrap();
+        let headers = server.merge(settings.security_headers().unwrap());
+
+        assert_eq!(headers.headers(false), vec![
+            ("content-security-policy", "default-src 'self'"),
+            ("referrer-policy", "no-referrer"),
+        ]);
+        assert_eq!(
+            headers.headers(true).last(),
+            Some(&("strict-transport-security", "max-age=31536000"))
+        );
+        assert_eq!(server.headers(false).len(), 2);
+    }
 }
@@ -1,2 +1,6 @@
 pub mod api;
+pub mod cron;
 pub mod lapp;
+#[cfg(feature = "proto")]
+pub mod proto;
+pub mod rate_limit;
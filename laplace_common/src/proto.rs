@@ -0,0 +1,348 @@
+//! Protobuf messages generated from `proto/laplace/api.proto` and conversions to the common API types.
+
+use std::fmt;
+use std::ops::Deref;
+
+use serde_json::Value;
+
+use crate::api;
+use crate::lapp::{self, LappSettings};
+
+#[allow(clippy::derive_partial_eq_without_eq)]
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/laplace.api.rs"));
+}
+
+pub use self::generated::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownPermission(pub i32);
+
+impl fmt::Display for UnknownPermission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown permission value: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownPermission {}
+
+impl From<lapp::Permission> for Permission {
+    fn from(permission: lapp::Permission) -> Self {
+        match permission {
+            lapp::Permission::FileRead => Self::FileRead,
+            lapp::Permission::FileWrite => Self::FileWrite,
+            lapp::Permission::ClientHttp => Self::ClientHttp,
+            lapp::Permission::Http => Self::Http,
+            lapp::Permission::Websocket => Self::Websocket,
+            lapp::Permission::WebsocketClient => Self::WebsocketClient,
+            lapp::Permission::Tcp => Self::Tcp,
+            lapp::Permission::Database => Self::Database,
+            lapp::Permission::DatabaseRead => Self::DatabaseRead,
+            lapp::Permission::Sleep => Self::Sleep,
+            lapp::Permission::LappsIncoming => Self::LappsIncoming,
+            lapp::Permission::LappsOutgoing => Self::LappsOutgoing,
+            lapp::Permission::Webdav => Self::Webdav,
+            lapp::Permission::Graphql => Self::Graphql,
+            lapp::Permission::Mqtt => Self::Mqtt,
+            lapp::Permission::Threads => Self::Threads,
+            lapp::Permission::Crypto => Self::Crypto,
+            lapp::Permission::Mail => Self::Mail,
+        }
+    }
+}
+
+impl TryFrom<i32> for lapp::Permission {
+    type Error = UnknownPermission;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match Permission::try_from(value).map_err(|_| UnknownPermission(value))? {
+            Permission::Unspecified => Err(UnknownPermission(value)),
+            Permission::FileRead => Ok(Self::FileRead),
+            Permission::FileWrite => Ok(Self::FileWrite),
+            Permission::ClientHttp => Ok(Self::ClientHttp),
+            Permission::Http => Ok(Self::Http),
+            Permission::Websocket => Ok(Self::Websocket),
+            Permission::WebsocketClient => Ok(Self::WebsocketClient),
+            Permission::Tcp => Ok(Self::Tcp),
+            Permission::Database => Ok(Self::Database),
+            Permission::DatabaseRead => Ok(Self::DatabaseRead),
+            Permission::Sleep => Ok(Self::Sleep),
+            Permission::LappsIncoming => Ok(Self::LappsIncoming),
+            Permission::LappsOutgoing => Ok(Self::LappsOutgoing),
+            Permission::Webdav => Ok(Self::Webdav),
+            Permission::Graphql => Ok(Self::Graphql),
+            Permission::Mqtt => Ok(Self::Mqtt),
+            Permission::Threads => Ok(Self::Threads),
+            Permission::Crypto => Ok(Self::Crypto),
+            Permission::Mail => Ok(Self::Mail),
+        }
+    }
+}
+
+fn permission_value(permission: lapp::Permission) -> i32 {
+    Permission::from(permission).into()
+}
+
+impl From<api::Info> for Info {
+    fn from(info: api::Info) -> Self {
+        let api::Info { version, url } = info;
+        Self { version, url }
+    }
+}
+
+impl From<Info> for api::Info {
+    fn from(info: Info) -> Self {
+        let Info { version, url } = info;
+        Self { version, url }
+    }
+}
+
+impl From<&LappSettings> for Lapp {
+    fn from(settings: &LappSettings) -> Self {
+        Self {
+            lapp_name: settings.lapp_name.clone(),
+            title: settings.application.title.clone(),
+            enabled: settings.application.enabled,
+            autoload: settings.application.autoload,
+            quarantined: settings.application.quarantined,
+            description: settings.application.description.clone(),
+            version: settings.application.version.clone(),
+            author: settings.application.author.clone(),
+            homepage: settings.application.homepage.clone(),
+            license: settings.application.license.clone(),
+            tags: settings.application.tags.clone().unwrap_or_default(),
+            required_permissions: settings.permissions.required().map(permission_value).collect(),
+            allowed_permissions: settings.permissions.allowed().map(permission_value).collect(),
+            pending_permissions: settings.permissions.pending().map(permission_value).collect(),
+            icon: settings
+                .application
+                .icon
+                .as_ref()
+                .map(|icon| icon.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+impl From<api::UpdateQuery> for UpdateQuery {
+    fn from(query: api::UpdateQuery) -> Self {
+        let api::UpdateQuery {
+            lapp_name,
+            enabled,
+            autoload,
+            allow_permission,
+            deny_permission,
+            permission_profile,
+        } = query;
+
+        Self {
+            lapp_name,
+            enabled,
+            autoload,
+            allow_permission: allow_permission.map(permission_value),
+            deny_permission: deny_permission.map(permission_value),
+            permission_profile,
+        }
+    }
+}
+
+impl TryFrom<UpdateQuery> for api::UpdateQuery {
+    type Error = UnknownPermission;
+
+    fn try_from(query: UpdateQuery) -> Result<Self, Self::Error> {
+        let UpdateQuery {
+            lapp_name,
+            enabled,
+            autoload,
+            allow_permission,
+            deny_permission,
+            permission_profile,
+        } = query;
+
+        Ok(Self {
+            lapp_name,
+            enabled,
+            autoload,
+            allow_permission: allow_permission.map(TryInto::try_into).transpose()?,
+            deny_permission: deny_permission.map(TryInto::try_into).transpose()?,
+            permission_profile,
+        })
+    }
+}
+
+impl From<api::UpdateRequest> for UpdateRequest {
+    fn from(request: api::UpdateRequest) -> Self {
+        Self {
+            update: Some(request.update.into()),
+        }
+    }
+}
+
+impl TryFrom<UpdateRequest> for api::UpdateRequest {
+    type Error = UnknownPermission;
+
+    fn try_from(request: UpdateRequest) -> Result<Self, Self::Error> {
+        let update = request.update.unwrap_or_default().try_into()?;
+        Ok(Self { update })
+    }
+}
+
+impl<'a, LS: Deref<Target = LappSettings> + 'a> From<api::Response<'a, LS>> for Response {
+    fn from(response: api::Response<'a, LS>) -> Self {
+        let response = match response {
+            api::Response::Lapps { lapps, .. } => response::Response::Lapps(Lapps {
+                lapps: lapps.iter().map(|settings| Lapp::from(settings.deref())).collect(),
+            }),
+            api::Response::Updated { updated } => response::Response::Updated(updated.into()),
+            api::Response::Upgrade { upgrade } => response::Response::Upgrade(upgrade.into()),
+        };
+
+        Self {
+            response: Some(response),
+        }
+    }
+}
+
+impl From<api::UpgradeDiff> for UpgradeDiff {
+    fn from(diff: api::UpgradeDiff) -> Self {
+        let api::UpgradeDiff {
+            lapp_name,
+            added_permissions,
+            removed_permissions,
+            current_version,
+            upgrade_version,
+        } = diff;
+
+        Self {
+            lapp_name,
+            added_permissions: added_permissions.into_iter().map(permission_value).collect(),
+            removed_permissions: removed_permissions.into_iter().map(permission_value).collect(),
+            current_version,
+            upgrade_version,
+        }
+    }
+}
+
+impl From<api::Peer> for Peer {
+    fn from(peer: api::Peer) -> Self {
+        let api::Peer { peer_id, keypair } = peer;
+        Self { peer_id, keypair }
+    }
+}
+
+impl From<Peer> for api::Peer {
+    fn from(peer: Peer) -> Self {
+        let Peer { peer_id, keypair } = peer;
+        Self { peer_id, keypair }
+    }
+}
+
+impl From<api::SqlQuery> for SqlQuery {
+    fn from(query: api::SqlQuery) -> Self {
+        let api::SqlQuery { sql, read_only } = query;
+        Self { sql, read_only }
+    }
+}
+
+impl From<SqlQuery> for api::SqlQuery {
+    fn from(query: SqlQuery) -> Self {
+        let SqlQuery { sql, read_only } = query;
+        Self { sql, read_only }
+    }
+}
+
+impl From<Value> for SqlValue {
+    fn from(value: Value) -> Self {
+        let value = match value {
+            Value::Null => sql_value::Value::Null(true),
+            Value::Bool(value) => sql_value::Value::Integer(value as _),
+            Value::Number(number) => match number.as_i64() {
+                Some(number) => sql_value::Value::Integer(number),
+                None => sql_value::Value::Real(number.as_f64().unwrap_or_default()),
+            },
+            Value::String(value) => sql_value::Value::Text(value),
+            Value::Array(values) => sql_value::Value::Blob(
+                values
+                    .iter()
+                    .map(|value| value.as_u64().unwrap_or_default() as u8)
+                    .collect(),
+            ),
+            value @ Value::Object(_) => sql_value::Value::Text(value.to_string()),
+        };
+
+        Self { value: Some(value) }
+    }
+}
+
+impl From<SqlValue> for Value {
+    fn from(value: SqlValue) -> Self {
+        match value.value {
+            None | Some(sql_value::Value::Null(_)) => Value::Null,
+            Some(sql_value::Value::Integer(value)) => value.into(),
+            Some(sql_value::Value::Real(value)) => value.into(),
+            Some(sql_value::Value::Text(value)) => value.into(),
+            Some(sql_value::Value::Blob(value)) => value.into(),
+        }
+    }
+}
+
+impl From<api::SqlQueryResult> for SqlQueryResult {
+    fn from(result: api::SqlQueryResult) -> Self {
+        Self {
+            columns: result.columns,
+            rows: result
+                .rows
+                .into_iter()
+                .map(|values| SqlRow {
+                    values: values.into_iter().map(Into::into).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<SqlQueryResult> for api::SqlQueryResult {
+    fn from(result: SqlQueryResult) -> Self {
+        Self {
+            columns: result.columns,
+            rows: result
+                .rows
+                .into_iter()
+                .map(|row| row.values.into_iter().map(Into::into).collect())
+                .collect(),
+        }
+    }
+}
+
+impl From<api::Envelope<Vec<u8>>> for Envelope {
+    fn from(envelope: api::Envelope<Vec<u8>>) -> Self {
+        let api::Envelope { id, kind, payload } = envelope;
+        Self { id, kind, payload }
+    }
+}
+
+impl From<Envelope> for api::Envelope<Vec<u8>> {
+    fn from(envelope: Envelope) -> Self {
+        let Envelope { id, kind, payload } = envelope;
+        Self { id, kind, payload }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+
+    #[test]
+    fn update_query_roundtrip() {
+        let query = api::UpdateQuery::new("test").allow_permission(lapp::Permission::Database);
+
+        let bytes = UpdateQuery::from(query).encode_to_vec();
+        let decoded = api::UpdateQuery::try_from(UpdateQuery::decode(bytes.as_slice()).unwrap()).unwrap();
+
+        assert_eq!(
+            decoded,
+            api::UpdateQuery::new("test").allow_permission(lapp::Permission::Database)
+        );
+    }
+}
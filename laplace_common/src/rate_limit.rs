@@ -0,0 +1,167 @@
+//! Parsing of the per-route rate limits declared by lapps.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitError(pub String);
+
+impl fmt::Display for RateLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Wrong rate limit: {}", self.0)
+    }
+}
+
+impl std::error::Error for RateLimitError {}
+
+/// The rate limit of the lapp route in the form `[METHOD] PATH = N/UNIT [burst M] [per client]`, e.g.
+/// `POST /api/send = 5/min per client`. The path is relative to the lapp root, the trailing `*` matches any tail,
+/// the rule without the method matches all methods. The unit is `sec`, `min`, `hour` or `day`. The `burst` is the
+/// number of the requests allowed at once after the idle time, it is `N` by default.
+/// Without `per client` the limit is shared by all clients of the lapp.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitRule {
+    pub method: Option<String>,
+    pub path: String,
+    pub requests: u32,
+    pub period: Duration,
+    pub burst: Option<u32>,
+    pub per_client: bool,
+}
+
+impl RateLimitRule {
+    /// Checks the request method and the path relative to the lapp root.
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        let method_matches = self
+            .method
+            .as_deref()
+            .is_none_or(|rule_method| rule_method.eq_ignore_ascii_case(method));
+        let path_matches = match self.path.strip_suffix('*') {
+            Some(prefix) => path.starts_with(prefix),
+            None => path == self.path,
+        };
+
+        method_matches && path_matches
+    }
+
+    /// The number of the requests allowed at once.
+    pub fn capacity(&self) -> u32 {
+        self.burst.unwrap_or(self.requests)
+    }
+
+    /// The route of the rule, e.g. `POST /api/send`.
+    pub fn route(&self) -> String {
+        match &self.method {
+            Some(method) => format!("{method} {}", self.path),
+            None => self.path.clone(),
+        }
+    }
+}
+
+impl FromStr for RateLimitRule {
+    type Err = RateLimitError;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let Some((route, limit)) = rule.split_once('=') else {
+            return Err(RateLimitError(format!("expected \"ROUTE = LIMIT\" in \"{rule}\"")));
+        };
+
+        let (method, path) = match route.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [path] => (None, *path),
+            [method, path] => (Some(method.to_ascii_uppercase()), *path),
+            _ => return Err(RateLimitError(format!("wrong route \"{}\"", route.trim()))),
+        };
+        if !path.starts_with('/') {
+            return Err(RateLimitError(format!("the path \"{path}\" should start with '/'")));
+        }
+
+        let (rate, burst, per_client) = match limit.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [rate] => (*rate, None, false),
+            [rate, "per", "client"] => (*rate, None, true),
+            [rate, "burst", burst] => (*rate, Some(*burst), false),
+            [rate, "burst", burst, "per", "client"] => (*rate, Some(*burst), true),
+            _ => return Err(RateLimitError(format!("wrong limit \"{}\"", limit.trim()))),
+        };
+        let burst = burst
+            .map(|burst| {
+                burst
+                    .parse()
+                    .ok()
+                    .filter(|&burst| burst > 0)
+                    .ok_or_else(|| RateLimitError(format!("wrong burst \"{burst}\"")))
+            })
+            .transpose()?;
+
+        let (requests, unit) = rate
+            .split_once('/')
+            .ok_or_else(|| RateLimitError(format!("expected \"N/UNIT\" in \"{rate}\"")))?;
+        let requests = requests
+            .parse()
+            .ok()
+            .filter(|&requests| requests > 0)
+            .ok_or_else(|| RateLimitError(format!("wrong number of requests \"{requests}\"")))?;
+        let period = match unit {
+            "s" | "sec" | "second" => Duration::from_secs(1),
+            "m" | "min" | "minute" => Duration::from_secs(60),
+            "h" | "hour" => Duration::from_secs(60 * 60),
+            "d" | "day" => Duration::from_secs(24 * 60 * 60),
+            _ => return Err(RateLimitError(format!("unknown unit \"{unit}\""))),
+        };
+
+        Ok(Self {
+            method,
+            path: path.into(),
+            requests,
+            period,
+            burst,
+            per_client,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_match() {
+        let rule: RateLimitRule = "POST /api/send = 5/min per client".parse().unwrap();
+        assert_eq!(rule, RateLimitRule {
+            method: Some("POST".into()),
+            path: "/api/send".into(),
+            requests: 5,
+            period: Duration::from_secs(60),
+            burst: None,
+            per_client: true,
+        });
+        assert!(rule.matches("POST", "/api/send"));
+        assert!(!rule.matches("GET", "/api/send"));
+        assert!(!rule.matches("POST", "/api/send/1"));
+
+        let rule: RateLimitRule = "/api/* = 100/hour".parse().unwrap();
+        assert!(!rule.per_client);
+        assert_eq!(rule.route(), "/api/*");
+        assert!(rule.matches("GET", "/api/notes"));
+        assert!(rule.matches("delete", "/api/notes/1"));
+        assert!(!rule.matches("GET", "/static/app.js"));
+
+        let rule: RateLimitRule = "GET /search = 2/sec burst 10 per client".parse().unwrap();
+        assert_eq!(rule.burst, Some(10));
+        assert_eq!(rule.capacity(), 10);
+        assert!(rule.per_client);
+        assert_eq!("/api = 3/sec burst 5".parse::<RateLimitRule>().unwrap().capacity(), 5);
+        assert_eq!("/api = 3/sec".parse::<RateLimitRule>().unwrap().capacity(), 3);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("POST /api/send".parse::<RateLimitRule>().is_err());
+        assert!("POST api/send = 5/min".parse::<RateLimitRule>().is_err());
+        assert!("/api = 0/min".parse::<RateLimitRule>().is_err());
+        assert!("/api = 5/week".parse::<RateLimitRule>().is_err());
+        assert!("/api = 5/min per lapp".parse::<RateLimitRule>().is_err());
+        assert!("/api = 5/min burst 0".parse::<RateLimitRule>().is_err());
+        assert!("/api = 5/min burst".parse::<RateLimitRule>().is_err());
+    }
+}
@@ -0,0 +1,119 @@
+//! Benchmarks of the host call layer with the `bench` test lapp.
+//!
+//! Build the lapp before running: `cargo make bench_lapp`, or set `BENCH_LAPP_WASM` to the built module path.
+
+use std::path::PathBuf;
+use std::{env, fs};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use laplace_server::lapps::Lapp;
+use laplace_wasm::http;
+use reqwest::Client;
+use tempfile::TempDir;
+use tokio::runtime::Runtime;
+
+const LAPP_NAME: &str = "bench";
+
+const TRANSFER_SIZES: &[usize] = &[16, 1024, 64 * 1024, 1024 * 1024];
+
+struct BenchLapp {
+    lapp: Lapp,
+    _lapps_dir: TempDir,
+}
+
+impl BenchLapp {
+    fn new() -> Self {
+        let lapps_dir = TempDir::new().expect("Temp dir should be created");
+        let lapp_dir = lapps_dir.path().join(LAPP_NAME);
+        fs::create_dir(&lapp_dir).expect("Lapp dir should be created");
+
+        let source_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../tests/lapps/bench");
+        fs::copy(
+            source_dir.join(Lapp::config_file_name()),
+            Lapp::settings_path(&lapp_dir),
+        )
+        .expect("Lapp config should be copied");
+
+        let wasm_path = env::var_os("BENCH_LAPP_WASM").map(PathBuf::from).unwrap_or_else(|| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("../target/wasm32-unknown-unknown/release")
+                .join(format!("{LAPP_NAME}_server.wasm"))
+        });
+        let settings = Lapp::load_settings(LAPP_NAME, &lapp_dir).expect("Lapp settings should be loaded");
+        let lapp = Lapp::new(LAPP_NAME, lapp_dir, settings);
+        fs::copy(&wasm_path, lapp.server_module_file())
+            .unwrap_or_else(|err| panic!("Bench lapp module {} should be built: {err}", wasm_path.display()));
+
+        Self {
+            lapp,
+            _lapps_dir: lapps_dir,
+        }
+    }
+}
+
+fn request(path: &str, body: Vec<u8>) -> http::Request {
+    let mut request = http::Request::new(body);
+    request.uri = path.parse().expect("Path should be valid URI");
+    request
+}
+
+fn process_http(runtime: &Runtime, lapp: &mut Lapp, request: http::Request) -> http::Response {
+    let response = runtime
+        .block_on(lapp.process_http(request))
+        .expect("Request should be processed");
+    assert!(
+        response.status.is_success(),
+        "Unexpected response status {}",
+        response.status
+    );
+    response
+}
+
+fn bench_instantiate(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("Tokio runtime should be created");
+    let mut bench_lapp = BenchLapp::new();
+    let client = Client::new();
+
+    c.bench_function("instantiate", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(bench_lapp.lapp.instantiate(client.clone()))
+                .expect("Lapp should be instantiated");
+            bench_lapp.lapp.take_instance()
+        })
+    });
+}
+
+fn bench_process_http(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("Tokio runtime should be created");
+    let mut bench_lapp = BenchLapp::new();
+    runtime
+        .block_on(bench_lapp.lapp.instantiate(Client::new()))
+        .expect("Lapp should be instantiated");
+
+    c.bench_function("process_http", |b| {
+        b.iter(|| process_http(&runtime, &mut bench_lapp.lapp, request("/bench/noop", Vec::new())))
+    });
+
+    let mut group = c.benchmark_group("db");
+    group.bench_function("query_row", |b| {
+        b.iter(|| process_http(&runtime, &mut bench_lapp.lapp, request("/bench/db/row", Vec::new())))
+    });
+    group.bench_function("query", |b| {
+        b.iter(|| process_http(&runtime, &mut bench_lapp.lapp, request("/bench/db/rows", Vec::new())))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("memory_transfer");
+    for &size in TRANSFER_SIZES {
+        // The body is moved to the wasm memory and back
+        group.throughput(Throughput::Bytes(2 * size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+            b.iter(|| process_http(&runtime, &mut bench_lapp.lapp, request("/bench/echo", vec![1; size])))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_instantiate, bench_process_http);
+criterion_main!(benches);
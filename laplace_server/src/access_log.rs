@@ -0,0 +1,226 @@
+//! The access log of the HTTP server.
+//!
+//! Every request is written as a line of the `combined` format of the common web servers or as a JSON object to the
+//! `access.log` file of the `access_log.dir` directory. The files are rotated daily like the server log. With the
+//! `per_lapp` setting the requests to the lapps are written to the `{lapp_name}.access.log` files instead. The access
+//! tokens passed in the query are not logged.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use axum::extract::{ConnectInfo, State};
+use axum::http::{header, HeaderMap, HeaderName, Request, Uri};
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::Utc;
+use flexi_logger::writers::{FileLogWriter, LogWriter};
+use flexi_logger::{Age, Cleanup, Criterion, DeferredNow, FileSpec, FlexiLoggerError, Naming};
+use laplace_wasm::http::REQUEST_ID_HEADER;
+use log::Record;
+use serde_json::json;
+
+use crate::lapps::LappsProvider;
+use crate::settings::{AccessLogFormat, AccessLogSettings};
+
+const COMMON_LOG_NAME: &str = "access";
+
+pub struct AccessLog {
+    dir: PathBuf,
+    format: AccessLogFormat,
+    per_lapp: bool,
+    keep_log_for_days: usize,
+    common_writer: FileLogWriter,
+    lapp_writers: Mutex<HashMap<String, Arc<FileLogWriter>>>,
+}
+
+impl AccessLog {
+    /// Creates the access log if its directory is configured.
+    pub fn new(settings: &AccessLogSettings) -> Result<Option<Self>, FlexiLoggerError> {
+        let Some(dir) = settings.dir.clone() else {
+            return Ok(None);
+        };
+        let common_writer = file_writer(&dir, COMMON_LOG_NAME, settings.keep_log_for_days)?;
+
+        Ok(Some(Self {
+            dir,
+            format: settings.format,
+            per_lapp: settings.per_lapp,
+            keep_log_for_days: settings.keep_log_for_days,
+            common_writer,
+            lapp_writers: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    fn write(&self, lapp_name: Option<&str>, line: &str) {
+        let result = match lapp_name.and_then(|lapp_name| self.lapp_writer(lapp_name)) {
+            Some(lapp_writer) => write_line(&lapp_writer, line),
+            None => write_line(&self.common_writer, line),
+        };
+        if let Err(err) = result {
+            log::error!("Cannot write access log: {err}");
+        }
+    }
+
+    fn lapp_writer(&self, lapp_name: &str) -> Option<Arc<FileLogWriter>> {
+        let mut lapp_writers = self
+            .lapp_writers
+            .lock()
+            .expect("Access log lock should not be poisoned");
+        if let Some(lapp_writer) = lapp_writers.get(lapp_name) {
+            return Some(Arc::clone(lapp_writer));
+        }
+
+        match file_writer(
+            &self.dir,
+            &format!("{lapp_name}.{COMMON_LOG_NAME}"),
+            self.keep_log_for_days,
+        ) {
+            Ok(lapp_writer) => {
+                let lapp_writer = Arc::new(lapp_writer);
+                lapp_writers.insert(lapp_name.into(), Arc::clone(&lapp_writer));
+                Some(lapp_writer)
+            },
+            Err(err) => {
+                log::error!("Cannot create access log of lapp '{lapp_name}': {err}");
+                None
+            },
+        }
+    }
+
+    pub fn flush(&self) {
+        let lapp_writers = self
+            .lapp_writers
+            .lock()
+            .expect("Access log lock should not be poisoned");
+        for writer in lapp_writers.values().map(AsRef::as_ref).chain([&self.common_writer]) {
+            if let Err(err) = LogWriter::flush(writer) {
+                log::error!("Cannot flush access log: {err}");
+            }
+        }
+    }
+}
+
+fn file_writer(dir: &Path, name: &str, keep_log_for_days: usize) -> Result<FileLogWriter, FlexiLoggerError> {
+    FileLogWriter::builder(
+        FileSpec::default()
+            .directory(dir)
+            .basename(name)
+            .suppress_timestamp()
+            .suffix("log"),
+    )
+    .rotate(
+        Criterion::Age(Age::Day),
+        Naming::Timestamps,
+        Cleanup::KeepLogFiles(keep_log_for_days),
+    )
+    .append()
+    .format(line_format)
+    .try_build()
+}
+
+fn write_line(writer: &FileLogWriter, line: &str) -> io::Result<()> {
+    LogWriter::write(
+        writer,
+        &mut DeferredNow::new(),
+        &Record::builder().args(format_args!("{line}")).build(),
+    )
+}
+
+/// Writes the already formatted access log line as is.
+fn line_format(writer: &mut dyn Write, _now: &mut DeferredNow, record: &Record) -> io::Result<()> {
+    write!(writer, "{}", record.args())
+}
+
+pub async fn log_request<B>(
+    State((access_log, lapps_provider)): State<(Arc<AccessLog>, LappsProvider)>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let started = Instant::now();
+    let time = Utc::now();
+    let client_ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_else(|| "-".into());
+    let method = request.method().clone();
+    let uri = redact_access_token(request.uri());
+    let version = request.version();
+    let referer = header_value(request.headers(), header::REFERER);
+    let user_agent = header_value(request.headers(), header::USER_AGENT);
+
+    let lapp_name = match request.uri().path().split('/').find(|chunk| !chunk.is_empty()) {
+        Some(lapp_name) if access_log.per_lapp => {
+            let manager = lapps_provider.read_manager().await;
+            manager.lapp_settings(lapp_name).ok().map(|_| lapp_name.to_string())
+        },
+        _ => None,
+    };
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16();
+    let request_id = header_value(response.headers(), HeaderName::from_static(REQUEST_ID_HEADER));
+    let body_size = response
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok()?.parse::<u64>().ok());
+
+    let line = match access_log.format {
+        AccessLogFormat::Combined => format!(
+            "{client_ip} - - [{}] \"{method} {uri} {version:?}\" {status} {} \"{}\" \"{}\"",
+            time.format("%d/%b/%Y:%H:%M:%S %z"),
+            body_size.map_or_else(|| "-".into(), |body_size| body_size.to_string()),
+            escape_quotes(referer.as_deref()),
+            escape_quotes(user_agent.as_deref()),
+        ),
+        AccessLogFormat::Json => json!({
+            "time": time.to_rfc3339(),
+            "client": client_ip,
+            "lapp": lapp_name,
+            "method": method.as_str(),
+            "uri": uri,
+            "version": format!("{version:?}"),
+            "status": status,
+            "body_size": body_size,
+            "referer": referer,
+            "user_agent": user_agent,
+            "request_id": request_id,
+            "duration_ms": started.elapsed().as_secs_f64() * 1000.0,
+        })
+        .to_string(),
+    };
+    access_log.write(lapp_name.as_deref(), &line);
+
+    response
+}
+
+fn header_value(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers.get(name).and_then(|value| value.to_str().ok()).map(Into::into)
+}
+
+/// Escapes the quoted field of the combined format, the missing field is written as `-`.
+fn escape_quotes(value: Option<&str>) -> String {
+    value.map_or_else(|| "-".into(), |value| value.replace('"', "\\\""))
+}
+
+/// Replaces the value of the `access_token` query parameter, so the tokens do not leak to the log files.
+fn redact_access_token(uri: &Uri) -> String {
+    let path = uri.path();
+    let Some(query) = uri.query() else {
+        return path.into();
+    };
+
+    let query: Vec<_> = query
+        .split('&')
+        .map(|param| match param.split_once('=') {
+            Some(("access_token", _)) => "access_token=-",
+            _ => param,
+        })
+        .collect();
+    format!("{path}?{}", query.join("&"))
+}
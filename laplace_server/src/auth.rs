@@ -1,27 +1,51 @@
 use std::fs;
-use std::io::{BufReader, Write};
+use std::io::{self, BufReader, Write};
 use std::path::Path;
 
 use rcgen::{Certificate, CertificateParams, DistinguishedName, DnType};
 use ring::rand;
 use rustls::PrivateKey;
-use rustls_pemfile::{certs, pkcs8_private_keys};
+use rustls_pemfile::{certs, read_all, Item};
 
 use crate::error::{AppError, AppResult};
 
 pub mod middleware;
+pub mod users;
 
-pub fn prepare_access_token(maybe_access_token: Option<String>) -> AppResult<&'static str> {
+/// Uses the configured access token, or the token generated at the previous run and saved into the state dir.
+pub fn prepare_access_token(maybe_access_token: Option<String>, state_dir: &Path) -> AppResult<&'static str> {
     let access_token = if let Some(access_token) = maybe_access_token {
         access_token
     } else {
-        generate_token()?
+        let access_token_path = state_dir.join("access_token");
+        match fs::read_to_string(&access_token_path) {
+            Ok(access_token) if !access_token.trim().is_empty() => access_token.trim().to_string(),
+            _ => {
+                let access_token = generate_token()?;
+                fs::create_dir_all(state_dir)?;
+                write_private_file(&access_token_path, access_token.as_bytes())?;
+                access_token
+            },
+        }
     };
 
     // todo: use `String::leak` when its stabilized
     Ok(Box::leak(access_token.into_boxed_str()))
 }
 
+/// Writes the file readable by the owner only.
+pub fn write_private_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents)
+}
+
 pub fn generate_token() -> AppResult<String> {
     let buf: [u8; 32] = rand::generate(&rand::SystemRandom::new())
         .map_err(|_| AppError::TokenGenerationFail)?
@@ -29,38 +53,49 @@ pub fn generate_token() -> AppResult<String> {
     Ok(bs58::encode(&buf).into_string())
 }
 
+/// Loads the configured certificate chain and private key, the self-signed certificate is generated if both files are
+/// missing. The private key may be in the PKCS#8, PKCS#1 (RSA) or SEC1 (EC) PEM format.
 pub fn prepare_certificates(
     certificate_path: &Path,
     private_key_path: &Path,
     host: impl Into<String>,
 ) -> AppResult<(Vec<rustls::Certificate>, PrivateKey)> {
-    if !certificate_path.exists() && !private_key_path.exists() {
-        log::info!("Generate SSL certificate");
-        let certificate = generate_self_signed_certificate(vec![host.into()])?;
+    match (certificate_path.exists(), private_key_path.exists()) {
+        (true, true) => (),
+        (true, false) => return Err(AppError::MissingSslFile(private_key_path.into())),
+        (false, true) => return Err(AppError::MissingSslFile(certificate_path.into())),
+        (false, false) => {
+            log::info!("Generate SSL certificate");
+            let certificate = generate_self_signed_certificate(vec![host.into()])?;
 
-        if let Some(parent) = private_key_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-        if let Some(parent) = certificate_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
+            if let Some(parent) = private_key_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if let Some(parent) = certificate_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
 
-        fs::File::create(private_key_path)?.write_all(certificate.serialize_private_key_pem().as_bytes())?;
-        fs::File::create(certificate_path)?.write_all(certificate.serialize_pem()?.as_bytes())?;
+            write_private_file(private_key_path, certificate.serialize_private_key_pem().as_bytes())?;
+            fs::File::create(certificate_path)?.write_all(certificate.serialize_pem()?.as_bytes())?;
+        },
     }
 
     log::info!("Bind SSL");
-    let certificates = certs(&mut BufReader::new(fs::File::open(certificate_path)?))?
+    let certificates: Vec<_> = certs(&mut BufReader::new(fs::File::open(certificate_path)?))?
         .into_iter()
         .map(rustls::Certificate)
         .collect();
+    if certificates.is_empty() {
+        return Err(AppError::MissingCertificate);
+    }
 
-    let private_key = PrivateKey(
-        pkcs8_private_keys(&mut BufReader::new(fs::File::open(private_key_path)?))?
-            .into_iter()
-            .next()
-            .ok_or(AppError::MissingPrivateKey)?,
-    );
+    let private_key = read_all(&mut BufReader::new(fs::File::open(private_key_path)?))?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or(AppError::MissingPrivateKey)?;
 
     Ok((certificates, private_key))
 }
@@ -1,21 +1,37 @@
 use std::fmt::Debug;
 
 use axum::extract::State;
-use axum::http::{header, Request, StatusCode};
+use axum::http::{header, Method, Request, StatusCode};
 use axum::middleware::Next;
 use axum::response::{IntoResponse, Redirect, Response};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use cookie::time::Duration;
 use cookie::Cookie;
+use laplace_common::api::Role;
 
+use crate::auth::users::Users;
 use crate::lapps::{Lapp, LappsProvider};
+use crate::vhost::LappHost;
+use crate::web_api::graphql::GRAPHQL_PATH;
+use crate::web_api::webdav::DAV_PATH;
 use crate::web_api::{err_into_json_response, ResultResponse};
 
+/// The name of the user who makes the request to the Laplace API, it is `laplace` for the Laplace access token.
+#[derive(Debug, Clone)]
+pub struct Actor(pub String);
+
 pub async fn check_access<B: Debug>(
-    State((lapps_provider, laplace_access_token)): State<(LappsProvider, &'static str)>,
+    State((lapps_provider, laplace_access_token, users, is_graphql_enabled)): State<(
+        LappsProvider,
+        &'static str,
+        Users,
+        bool,
+    )>,
     request: Request<B>,
     next: Next<B>,
 ) -> ResultResponse<Response> {
-    let request = match query_access_token_redirect(request) {
+    let mut request = match query_access_token_redirect(request) {
         Ok(response) => return Ok(response),
         Err(request) => request,
     };
@@ -28,7 +44,21 @@ pub async fn check_access<B: Debug>(
         .unwrap_or_default()
         .to_string();
 
-    if lapp_name.is_empty() || lapp_name == "static" || lapp_name == "favicon.ico" {
+    // The deploy webhook is verified by the webhook secret instead of the access token
+    let is_deploy_webhook = request.uri().path().starts_with(&Lapp::main_uri("deploy/"));
+
+    // The GraphQL gateway checks the access tokens of the queried lapps itself, but its schema is served to the
+    // authenticated users only
+    let is_graphql = lapp_name == GRAPHQL_PATH;
+    let is_graphql_gateway =
+        is_graphql_enabled && request.method() == Method::POST && request.uri().path() == format!("/{GRAPHQL_PATH}");
+
+    if lapp_name.is_empty()
+        || lapp_name == "static"
+        || lapp_name == "favicon.ico"
+        || is_deploy_webhook
+        || is_graphql_gateway
+    {
         Ok(next.run(request).await)
     } else {
         let access_token = request
@@ -38,10 +68,29 @@ pub async fn check_access<B: Debug>(
             .filter_map(|cookie_value| Cookie::parse(cookie_value.to_str().ok()?).ok())
             .find(|cookie| cookie.name() == "access_token")
             .map(|cookie| cookie.value().to_string())
+            .or_else(|| basic_auth_access_token(&request))
             .unwrap_or_default();
 
-        if lapp_name == Lapp::main_name() {
-            if access_token == laplace_access_token {
+        if lapp_name == Lapp::main_name() || is_graphql {
+            let actor = if access_token == laplace_access_token {
+                Some(Actor(Lapp::main_name().into()))
+            } else {
+                let user_agent = request
+                    .headers()
+                    .get(header::USER_AGENT)
+                    .and_then(|user_agent| user_agent.to_str().ok());
+                let is_read = (request.method() == Method::GET || request.method() == Method::HEAD)
+                    && is_viewer_route(request.uri().path());
+
+                match users.authenticate(&access_token, user_agent) {
+                    Some((user_name, Role::Admin)) => Some(Actor(user_name)),
+                    Some((user_name, Role::Viewer)) if is_read => Some(Actor(user_name)),
+                    _ => None,
+                }
+            };
+
+            if let Some(actor) = actor {
+                request.extensions_mut().insert(actor);
                 Ok(next.run(request).await)
             } else {
                 let mut response = Response::default();
@@ -61,9 +110,7 @@ pub async fn check_access<B: Debug>(
                             access_token
                         );
 
-                        let mut response = Response::default();
-                        *response.status_mut() = StatusCode::FORBIDDEN;
-                        Ok(response)
+                        Ok(access_denied_response(&request, &lapp_name))
                     }
                 },
                 Err(err) => Err(err_into_json_response(err)),
@@ -72,6 +119,60 @@ pub async fn check_access<B: Debug>(
     }
 }
 
+/// Checks that the management API route is the read of the non-sensitive data allowed for the viewers. The lapp
+/// backups, database exports, logs, permission audits, server settings, users and replication changes are not listed.
+fn is_viewer_route(path: &str) -> bool {
+    let chunks: Vec<_> = path.split('/').filter(|chunk| !chunk.is_empty()).collect();
+    match chunks.as_slice() {
+        [GRAPHQL_PATH, "schema"] => true,
+        [GRAPHQL_PATH, ..] | [] => false,
+        [_, static_dir, ..] if *static_dir == Lapp::static_dir_name() => true,
+        [_, route @ ..] => matches!(
+            route,
+            [] | ["info"]
+                | ["lapps"]
+                | ["lapps", "dependencies" | "invalid"]
+                | ["permission-profiles"]
+                | ["lapp", _, "icon" | "compare" | "disk" | "migrations"]
+                | ["jobs"]
+                | ["p2p"]
+                | ["tasks"]
+                | ["tasks", "ws"]
+                | ["registry"]
+                | ["registry", _]
+        ),
+    }
+}
+
+/// Returns the password of the HTTP Basic authorization as the access token, it is used by the WebDAV clients.
+fn basic_auth_access_token<B>(request: &Request<B>) -> Option<String> {
+    let credentials = request
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let credentials = String::from_utf8(BASE64.decode(credentials.trim()).ok()?).ok()?;
+    let (_user, password) = credentials.split_once(':')?;
+
+    Some(password.to_string())
+}
+
+fn access_denied_response<B>(request: &Request<B>, lapp_name: &str) -> Response {
+    let mut response = Response::default();
+    let is_dav_request = request.uri().path().split('/').filter(|chunk| !chunk.is_empty()).nth(1) == Some(DAV_PATH);
+
+    if is_dav_request {
+        *response.status_mut() = StatusCode::UNAUTHORIZED;
+        if let Ok(authenticate) = format!(r#"Basic realm="{lapp_name}""#).try_into() {
+            response.headers_mut().insert(header::WWW_AUTHENTICATE, authenticate);
+        }
+    } else {
+        *response.status_mut() = StatusCode::FORBIDDEN;
+    }
+    response
+}
+
 pub fn query_access_token_redirect<B>(request: Request<B>) -> Result<Response, Request<B>> {
     let uri = request.uri().clone();
     let query = uri.query().unwrap_or_default();
@@ -96,14 +197,21 @@ pub fn query_access_token_redirect<B>(request: Request<B>) -> Result<Response, R
             .find(|chunk| !chunk.is_empty())
             .unwrap_or(Lapp::main_name());
 
+        // The lapp served from its subdomain owns the whole origin, so the cookie and the redirect use its own paths
+        let lapp_host = request.extensions().get::<LappHost>();
+        let (cookie_path, path) = match lapp_host {
+            Some(lapp_host) => ("/".to_string(), lapp_host.host_path(uri.path())),
+            None => (format!("/{lapp_name}"), uri.path()),
+        };
+
         let access_token_cookie = Cookie::build(("access_token", access_token))
             .domain(uri.host().unwrap_or(""))
-            .path(format!("/{}", lapp_name))
+            .path(cookie_path)
             .http_only(true)
             .max_age(Duration::days(365 * 10)) // 10 years
             .build();
 
-        let mut response = Redirect::to(&format!("{}{}", uri.path(), new_query)).into_response();
+        let mut response = Redirect::to(&format!("{path}{new_query}")).into_response();
         response.headers_mut().insert(
             header::SET_COOKIE,
             access_token_cookie.to_string().try_into().map_err(|_| request)?,
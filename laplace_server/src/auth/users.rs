@@ -0,0 +1,276 @@
+//! Users of the admin UI.
+//!
+//! Every user has a role and an own access token, which is accepted by the admin UI along with the Laplace
+//! access token. Only the token hashes are stored in the `users.json` file of the state dir. Sessions are
+//! the distinct clients that have used the user token since the server start, they are kept in memory.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use axum::extract::Path as UrlPath;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use laplace_common::api::{Role, SessionInfo, UserCredentials, UserInfo, UserRequest};
+use ring::digest;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::generate_token;
+use crate::error::{ServerError, ServerResult};
+use crate::lapps::{Lapp, LappsProvider};
+use crate::web_api::ResponseFormat;
+
+pub mod handler;
+
+pub fn router(users: Users) -> Router<LappsProvider> {
+    Router::new()
+        .route(
+            &Lapp::main_uri("users"),
+            get({
+                let users = users.clone();
+                move |format: ResponseFormat| handler::get_users(format, users.clone())
+            })
+            .post({
+                let users = users.clone();
+                move |format: ResponseFormat, request: Json<UserRequest>| {
+                    handler::create_user(format, users.clone(), request)
+                }
+            }),
+        )
+        .route(
+            &Lapp::main_uri("users/:name"),
+            post({
+                let users = users.clone();
+                move |format: ResponseFormat, name: UrlPath<String>, request: Json<UserRequest>| {
+                    handler::update_user(format, users.clone(), name, request)
+                }
+            }),
+        )
+        .route(
+            &Lapp::main_uri("users/:name/reset"),
+            post({
+                let users = users.clone();
+                move |format: ResponseFormat, name: UrlPath<String>| {
+                    handler::reset_credentials(format, users.clone(), name)
+                }
+            }),
+        )
+        .route(
+            &Lapp::main_uri("users/:name/delete"),
+            post(move |format: ResponseFormat, name: UrlPath<String>| {
+                handler::delete_user(format, users.clone(), name)
+            }),
+        )
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct StoredUser {
+    name: String,
+    role: Role,
+    token_hash: String,
+
+    /// The creation time in RFC 3339 format.
+    created_at: String,
+}
+
+#[derive(Debug)]
+struct Session {
+    user_name: String,
+    user_agent: Option<String>,
+    started_at: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct UsersInner {
+    users: Vec<StoredUser>,
+    sessions: HashMap<String, Session>,
+}
+
+#[derive(Clone)]
+pub struct Users {
+    path: PathBuf,
+    inner: Arc<Mutex<UsersInner>>,
+}
+
+impl Users {
+    pub fn load(state_dir: &Path) -> ServerResult<Self> {
+        let path = state_dir.join("users.json");
+        let users = match fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self {
+            path,
+            inner: Arc::new(Mutex::new(UsersInner {
+                users,
+                sessions: HashMap::new(),
+            })),
+        })
+    }
+
+    pub fn list(&self) -> Vec<UserInfo> {
+        let inner = self.lock();
+        inner
+            .users
+            .iter()
+            .map(|user| user_info(user, &inner.sessions))
+            .collect()
+    }
+
+    pub fn create(&self, request: UserRequest) -> ServerResult<UserCredentials> {
+        check_user_name(&request.name)?;
+
+        let mut inner = self.lock();
+        if inner.users.iter().any(|user| user.name == request.name) {
+            return Err(ServerError::UserAlreadyExists(request.name));
+        }
+
+        let access_token = new_token()?;
+        inner.users.push(StoredUser {
+            name: request.name.clone(),
+            role: request.role,
+            token_hash: token_hash(&access_token),
+            created_at: Utc::now().to_rfc3339(),
+        });
+        self.save(&inner)?;
+
+        Ok(UserCredentials {
+            name: request.name,
+            access_token,
+        })
+    }
+
+    pub fn update(&self, name: &str, request: UserRequest) -> ServerResult<UserInfo> {
+        let mut guard = self.lock();
+        let inner = &mut *guard;
+        let user = find_user(&mut inner.users, name)?;
+        user.role = request.role;
+
+        let info = user_info(user, &inner.sessions);
+        self.save(inner)?;
+        Ok(info)
+    }
+
+    /// Replaces the user access token, the sessions of the previous token are closed.
+    pub fn reset_credentials(&self, name: &str) -> ServerResult<UserCredentials> {
+        let mut inner = self.lock();
+        let access_token = new_token()?;
+        find_user(&mut inner.users, name)?.token_hash = token_hash(&access_token);
+        inner.sessions.retain(|_, session| session.user_name != name);
+        self.save(&inner)?;
+
+        Ok(UserCredentials {
+            name: name.into(),
+            access_token,
+        })
+    }
+
+    pub fn delete(&self, name: &str) -> ServerResult<()> {
+        let mut inner = self.lock();
+        let len = inner.users.len();
+        inner.users.retain(|user| user.name != name);
+        if inner.users.len() == len {
+            return Err(ServerError::UserNotFound(name.into()));
+        }
+        inner.sessions.retain(|_, session| session.user_name != name);
+        self.save(&inner)
+    }
+
+    /// Returns the name and the role of the user with the access token and records the client session.
+    pub fn authenticate(&self, access_token: &str, user_agent: Option<&str>) -> Option<(String, Role)> {
+        if access_token.is_empty() {
+            return None;
+        }
+
+        let hash = token_hash(access_token);
+        let mut inner = self.lock();
+        let user = inner.users.iter().find(|user| user.token_hash == hash)?;
+        let (user_name, role) = (user.name.clone(), user.role);
+
+        let now = Utc::now();
+        let session_id = session_id(&hash, user_agent);
+        inner
+            .sessions
+            .entry(session_id)
+            .and_modify(|session| session.last_seen = now)
+            .or_insert_with(|| Session {
+                user_name: user_name.clone(),
+                user_agent: user_agent.map(Into::into),
+                started_at: now,
+                last_seen: now,
+            });
+
+        Some((user_name, role))
+    }
+
+    fn save(&self, inner: &UsersInner) -> ServerResult<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(&inner.users)?)?;
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, UsersInner> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+fn check_user_name(name: &str) -> ServerResult<()> {
+    let is_valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '-' || ch == '.');
+
+    if is_valid {
+        Ok(())
+    } else {
+        Err(ServerError::WrongUserName(name.into()))
+    }
+}
+
+fn find_user<'a>(users: &'a mut [StoredUser], name: &str) -> ServerResult<&'a mut StoredUser> {
+    users
+        .iter_mut()
+        .find(|user| user.name == name)
+        .ok_or_else(|| ServerError::UserNotFound(name.into()))
+}
+
+fn user_info(user: &StoredUser, sessions: &HashMap<String, Session>) -> UserInfo {
+    let mut user_sessions: Vec<_> = sessions
+        .iter()
+        .filter(|(_, session)| session.user_name == user.name)
+        .map(|(id, session)| SessionInfo {
+            id: id.clone(),
+            user_agent: session.user_agent.clone(),
+            started_at: session.started_at.to_rfc3339(),
+            last_seen: session.last_seen.to_rfc3339(),
+        })
+        .collect();
+    user_sessions.sort_unstable_by(|a, b| b.last_seen.cmp(&a.last_seen));
+
+    UserInfo {
+        name: user.name.clone(),
+        role: user.role,
+        created_at: user.created_at.clone(),
+        sessions: user_sessions,
+    }
+}
+
+fn new_token() -> ServerResult<String> {
+    generate_token().map_err(|err| ServerError::UsersError(err.to_string()))
+}
+
+fn token_hash(access_token: &str) -> String {
+    hex::encode(digest::digest(&digest::SHA256, access_token.as_bytes()))
+}
+
+fn session_id(token_hash: &str, user_agent: Option<&str>) -> String {
+    let key = format!("{token_hash}\n{}", user_agent.unwrap_or_default());
+    hex::encode(&digest::digest(&digest::SHA256, key.as_bytes()).as_ref()[..8])
+}
@@ -0,0 +1,54 @@
+use axum::extract::Path;
+use axum::response::IntoResponse;
+use axum::Json;
+use laplace_common::api::{UserCredentials, UserInfo, UserRequest};
+
+use crate::auth::users::Users;
+use crate::web_api::{err_into_json_response, Negotiated, ResponseFormat, ResultResponse};
+
+pub async fn get_users(format: ResponseFormat, users: Users) -> impl IntoResponse {
+    Negotiated(format, users.list())
+}
+
+pub async fn create_user(
+    format: ResponseFormat,
+    users: Users,
+    Json(request): Json<UserRequest>,
+) -> ResultResponse<Negotiated<UserCredentials>> {
+    users
+        .create(request)
+        .map(|credentials| Negotiated(format, credentials))
+        .map_err(err_into_json_response)
+}
+
+pub async fn update_user(
+    format: ResponseFormat,
+    users: Users,
+    Path(name): Path<String>,
+    Json(request): Json<UserRequest>,
+) -> ResultResponse<Negotiated<UserInfo>> {
+    users
+        .update(&name, request)
+        .map(|user| Negotiated(format, user))
+        .map_err(err_into_json_response)
+}
+
+pub async fn reset_credentials(
+    format: ResponseFormat,
+    users: Users,
+    Path(name): Path<String>,
+) -> ResultResponse<Negotiated<UserCredentials>> {
+    users
+        .reset_credentials(&name)
+        .map(|credentials| Negotiated(format, credentials))
+        .map_err(err_into_json_response)
+}
+
+pub async fn delete_user(
+    format: ResponseFormat,
+    users: Users,
+    Path(name): Path<String>,
+) -> ResultResponse<Negotiated<Vec<UserInfo>>> {
+    users.delete(&name).map_err(err_into_json_response)?;
+    Ok(Negotiated(format, users.list()))
+}
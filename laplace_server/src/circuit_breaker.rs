@@ -0,0 +1,77 @@
+//! Quarantine of the repeatedly failing lapps.
+//!
+//! The lapp services report the failed instantiations, the trapped or panicked calls and the calls without failure.
+//! After `lapps.quarantine_threshold` failures in a row the lapp service is stopped and the lapp is disabled with
+//! the `quarantined` flag in its config, so it is not started again until the administrator enables it.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tokio::sync::mpsc;
+
+use crate::lapps::LappsProvider;
+
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    failures: Arc<Mutex<HashMap<String, u32>>>,
+    tripped_in: mpsc::UnboundedSender<String>,
+    tripped: Arc<Mutex<Option<mpsc::UnboundedReceiver<String>>>>,
+}
+
+impl CircuitBreaker {
+    /// Creates the breaker that trips after `threshold` failures in a row, the zero threshold never trips.
+    pub fn new(threshold: u32) -> Self {
+        let (tripped_in, tripped) = mpsc::unbounded_channel();
+
+        Self {
+            threshold,
+            failures: Default::default(),
+            tripped_in,
+            tripped: Arc::new(Mutex::new(Some(tripped))),
+        }
+    }
+
+    /// Quarantines the lapps for which the breaker is tripped.
+    pub fn run(&self, lapps_provider: LappsProvider) {
+        let Some(mut tripped) = self
+            .tripped
+            .lock()
+            .expect("Circuit breaker lock should not be poisoned")
+            .take()
+        else {
+            log::warn!("Circuit breaker is already run");
+            return;
+        };
+
+        tokio::spawn(async move {
+            while let Some(lapp_name) = tripped.recv().await {
+                match lapps_provider.write_manager().await.quarantine_lapp(&lapp_name) {
+                    Ok(()) => log::error!("Lapp '{lapp_name}' is quarantined after the repeated failures"),
+                    Err(err) => log::error!("Quarantine lapp '{lapp_name}' error: {err}"),
+                }
+            }
+        });
+    }
+
+    pub fn record_failure(&self, lapp_name: &str) {
+        let mut failures = self.failures();
+        let count = failures.entry(lapp_name.to_string()).or_default();
+        *count += 1;
+
+        if self.threshold > 0 && *count == self.threshold {
+            self.tripped_in.send(lapp_name.to_string()).ok();
+        }
+    }
+
+    /// Forgets the failures of the lapp after the call without failure or when the lapp is enabled.
+    pub fn reset(&self, lapp_name: &str) {
+        self.failures().remove(lapp_name);
+    }
+
+    fn failures(&self) -> MutexGuard<'_, HashMap<String, u32>> {
+        self.failures
+            .lock()
+            .expect("Circuit breaker lock should not be poisoned")
+    }
+}
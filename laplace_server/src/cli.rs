@@ -2,6 +2,64 @@ use std::path::PathBuf;
 
 #[derive(clap::Parser)]
 pub struct Opts {
-    #[clap(short, long, default_value = "config.toml")]
-    pub config: PathBuf,
+    /// Config file path, by default `config.toml` in the working directory or in the platform config dir
+    #[clap(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Run the server in the background as a daemon process
+    #[cfg(unix)]
+    #[clap(long)]
+    pub daemon: bool,
+
+    /// Write the daemon process PID to the file
+    #[cfg(unix)]
+    #[clap(long, requires = "daemon")]
+    pub pid_file: Option<PathBuf>,
+
+    /// Write logs to the file, overrides the `log.path` setting
+    #[clap(long)]
+    pub log_file: Option<PathBuf>,
+
+    /// Dump the lapp database to the line-delimited JSON and exit
+    #[clap(long, value_name = "LAPP_NAME", conflicts_with = "import_db")]
+    pub export_db: Option<String>,
+
+    /// Load the lapp database from the line-delimited JSON dump and exit
+    #[clap(long, value_name = "LAPP_NAME")]
+    pub import_db: Option<String>,
+
+    /// The dump file for `--export-db` and `--import-db`, stdout or stdin by default
+    #[clap(long)]
+    pub dump_file: Option<PathBuf>,
+
+    /// Replay the trace recorded by the lapp with the `record_trace` setting and exit
+    #[clap(long, value_name = "LAPP_NAME", conflicts_with_all = ["export_db", "import_db"])]
+    pub replay_trace: Option<String>,
+
+    /// The trace file for `--replay-trace`, `{lapp_name}_server.trace` in the lapp directory by default
+    #[clap(long, requires = "replay_trace")]
+    pub trace_file: Option<PathBuf>,
+
+    /// Sign the lapp package with the `--signing-key`, print the publisher public key and exit
+    #[clap(long, value_name = "PACKAGE", requires = "signing_key")]
+    pub sign_package: Option<PathBuf>,
+
+    /// The file with the hex-encoded Ed25519 seed of the publisher for `--sign-package`
+    #[clap(long, requires = "sign_package")]
+    pub signing_key: Option<PathBuf>,
+
+    /// Run the server under the Windows service control manager
+    #[cfg(windows)]
+    #[clap(long)]
+    pub service: bool,
+
+    /// Install the server as a Windows service with the current config
+    #[cfg(windows)]
+    #[clap(long, conflicts_with_all = ["service", "uninstall_service"])]
+    pub install_service: bool,
+
+    /// Stop and remove the installed Windows service
+    #[cfg(windows)]
+    #[clap(long, conflicts_with = "service")]
+    pub uninstall_service: bool,
 }
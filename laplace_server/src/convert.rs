@@ -1,19 +1,47 @@
-use axum::body::Body;
-use axum::http::Request;
+use axum::body::{Body, HttpBody};
+use axum::http::{header, Request};
 use hyper::body;
 use laplace_wasm::http;
 
-use crate::error::ServerResult;
+use crate::error::{ServerError, ServerResult};
 
-pub async fn to_wasm_http_request(request: Request<Body>) -> ServerResult<http::Request> {
-    let (parts, body) = request.into_parts();
-    let body = body::to_bytes(body).await?;
+/// Reads the request with the body of `max_body_size` bytes at most, the oversized body is refused by its
+/// `Content-Length` before it is read, or as soon as the read chunks exceed the limit.
+pub async fn to_wasm_http_request(
+    lapp_name: &str,
+    request: Request<Body>,
+    max_body_size: Option<u64>,
+) -> ServerResult<http::Request> {
+    let (parts, mut body) = request.into_parts();
+    let body = match max_body_size {
+        Some(max_body_size) => {
+            let body_too_large = || ServerError::LappRequestBodyTooLarge(lapp_name.into(), max_body_size);
+            let content_length = parts
+                .headers
+                .get(header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok()?.parse::<u64>().ok());
+            if content_length.is_some_and(|content_length| content_length > max_body_size) {
+                return Err(body_too_large());
+            }
+
+            let mut bytes = Vec::new();
+            while let Some(chunk) = body.data().await {
+                let chunk = chunk?;
+                if (bytes.len() + chunk.len()) as u64 > max_body_size {
+                    return Err(body_too_large());
+                }
+                bytes.extend_from_slice(&chunk);
+            }
+            bytes
+        },
+        None => body::to_bytes(body).await?.into(),
+    };
 
     Ok(http::Request {
         method: parts.method,
         uri: parts.uri,
         version: parts.version,
         headers: parts.headers,
-        body: body.into(),
+        body,
     })
 }
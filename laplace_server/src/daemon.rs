@@ -0,0 +1,15 @@
+use std::path::Path;
+use std::{env, io};
+
+use daemonize::Daemonize;
+
+pub fn daemonize(pid_file: Option<&Path>) -> io::Result<()> {
+    let mut daemonize = Daemonize::new().working_directory(env::current_dir()?);
+    if let Some(pid_file) = pid_file {
+        daemonize = daemonize.pid_file(pid_file);
+    }
+
+    daemonize
+        .start()
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))
+}
@@ -0,0 +1,246 @@
+//! Deployment of lapps from git repositories.
+//!
+//! The configured branch is checked out into the cache dir, built with the configured command and installed
+//! into the lapp directory, then the lapp is reloaded. The deployment is triggered at startup, by polling
+//! the branch and by the webhook.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::HeaderMap;
+use ring::hmac;
+use tokio::sync::Mutex;
+use zip::ZipArchive;
+
+use crate::error::{ServerError, ServerResult};
+use crate::lapps::{extract_package, is_regenerated_file, LappsProvider};
+use crate::settings::{GitLappSettings, Settings};
+
+#[derive(Clone)]
+pub struct Deployer {
+    lapps_provider: LappsProvider,
+    checkouts_dir: PathBuf,
+    git_lapps: Arc<[GitLappSettings]>,
+
+    /// Deployments are run one at a time, so the webhook and polling do not race for the same checkout.
+    lock: Arc<Mutex<()>>,
+}
+
+impl Deployer {
+    pub fn new(lapps_provider: LappsProvider, settings: &Settings) -> Self {
+        Self {
+            lapps_provider,
+            checkouts_dir: settings.paths.cache.join("git"),
+            git_lapps: settings.lapps.git.clone().into(),
+            lock: Default::default(),
+        }
+    }
+
+    pub fn git_lapp(&self, lapp_name: &str) -> Option<&GitLappSettings> {
+        self.git_lapps.iter().find(|git_lapp| git_lapp.name == lapp_name)
+    }
+
+    /// Deploys every git lapp and starts polling of the lapps branches.
+    pub fn run(&self) {
+        for git_lapp in self.git_lapps.iter().cloned() {
+            let deployer = self.clone();
+
+            tokio::spawn(async move {
+                deployer.deploy_logged(&git_lapp).await;
+
+                if let Some(poll_interval_sec) = git_lapp.poll_interval_sec {
+                    let mut interval = tokio::time::interval(Duration::from_secs(poll_interval_sec.max(1)));
+                    interval.tick().await;
+
+                    loop {
+                        interval.tick().await;
+                        deployer.deploy_logged(&git_lapp).await;
+                    }
+                }
+            });
+        }
+    }
+
+    pub async fn deploy_logged(&self, git_lapp: &GitLappSettings) {
+        if let Err(err) = self.deploy(git_lapp).await {
+            log::error!("Deploy lapp '{}' from {} error: {err}", git_lapp.name, git_lapp.url);
+        }
+    }
+
+    /// Updates the lapp from the repository, returns `false` when there are no new commits.
+    pub async fn deploy(&self, git_lapp: &GitLappSettings) -> ServerResult<bool> {
+        let _lock = self.lock.lock().await;
+
+        let manager = self.lapps_provider.read_manager().await;
+        manager.check_writable()?;
+        let lapp_dir = manager.lapp_dir(&git_lapp.name).0;
+        drop(manager);
+
+        let checkout_dir = self.checkouts_dir.join(&git_lapp.name);
+        let deployed = tokio::task::spawn_blocking({
+            let git_lapp = git_lapp.clone();
+            move || -> ServerResult<bool> {
+                let is_changed = sync_repository(&git_lapp, &checkout_dir)?;
+                if !is_changed && lapp_dir.exists() {
+                    return Ok(false);
+                }
+
+                if let Some(build) = &git_lapp.build {
+                    run_build(build, &checkout_dir)?;
+                }
+                install_package(&checkout_dir.join(git_lapp.package.unwrap_or_default()), &lapp_dir)?;
+
+                Ok(true)
+            }
+        })
+        .await
+        .map_err(|err| ServerError::DeployError(err.to_string()))??;
+
+        if deployed {
+            log::info!("Lapp '{}' is deployed from {}", git_lapp.name, git_lapp.url);
+            let reload = self.lapps_provider.write_manager().await.reload_lapp(&git_lapp.name)?;
+            reload.await?;
+        }
+
+        Ok(deployed)
+    }
+}
+
+/// Checks the webhook request by the GitHub/Gitea HMAC signature or the GitLab token.
+pub fn verify_webhook(secret: &str, headers: &HeaderMap, body: &[u8]) -> bool {
+    if let Some(signature) = headers
+        .get("x-hub-signature-256")
+        .and_then(|signature| signature.to_str().ok())
+        .and_then(|signature| signature.strip_prefix("sha256="))
+    {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        return hex::decode(signature)
+            .map(|signature| hmac::verify(&key, body, &signature).is_ok())
+            .unwrap_or(false);
+    }
+
+    // The tokens are compared by their HMAC tags in constant time
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let secret_tag = hmac::sign(&key, secret.as_bytes());
+    headers
+        .get("x-gitlab-token")
+        .map(|token| hmac::verify(&key, token.as_bytes(), secret_tag.as_ref()).is_ok())
+        .unwrap_or(false)
+}
+
+/// Clones or fetches the branch into the checkout dir, returns `true` if there are new commits.
+fn sync_repository(git_lapp: &GitLappSettings, checkout_dir: &Path) -> ServerResult<bool> {
+    if !checkout_dir.join(".git").exists() {
+        if let Some(parent) = checkout_dir.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        git(None, [
+            OsStr::new("clone"),
+            OsStr::new("--depth=1"),
+            OsStr::new("--branch"),
+            OsStr::new(&git_lapp.branch),
+            OsStr::new(&git_lapp.url),
+            checkout_dir.as_os_str(),
+        ])?;
+        return Ok(true);
+    }
+
+    git(Some(checkout_dir), [
+        "fetch",
+        "--depth=1",
+        git_lapp.url.as_str(),
+        git_lapp.branch.as_str(),
+    ])?;
+    let head = git(Some(checkout_dir), ["rev-parse", "HEAD"])?;
+    let fetched = git(Some(checkout_dir), ["rev-parse", "FETCH_HEAD"])?;
+    if head == fetched {
+        return Ok(false);
+    }
+
+    git(Some(checkout_dir), ["reset", "--hard", "FETCH_HEAD"])?;
+    Ok(true)
+}
+
+fn git(dir: Option<&Path>, args: impl IntoIterator<Item = impl AsRef<OsStr>>) -> ServerResult<String> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command
+        .args(args)
+        .output()
+        .map_err(|err| ServerError::DeployError(format!("cannot run git: {err}")))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(ServerError::DeployError(format!(
+            "git failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+fn run_build(build: &str, checkout_dir: &Path) -> ServerResult<()> {
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    };
+    #[cfg(not(windows))]
+    let mut command = {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+
+    let output = command
+        .arg(build)
+        .current_dir(checkout_dir)
+        .output()
+        .map_err(|err| ServerError::DeployError(format!("cannot run build command: {err}")))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(ServerError::DeployError(format!(
+            "build command `{build}` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// Installs the lapp package over the lapp directory, so the lapp data is kept.
+fn install_package(package_path: &Path, lapp_dir: &Path) -> ServerResult<()> {
+    if package_path.is_file() {
+        let mut archive = ZipArchive::new(fs::File::open(package_path)?)?;
+        extract_package(&mut archive, lapp_dir, |_| Ok(()))?;
+    } else {
+        copy_dir(package_path, lapp_dir)?;
+    }
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> ServerResult<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_name() == ".git" || is_regenerated_file(&entry.path()) {
+            continue;
+        }
+
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,197 @@
+//! Dump of lapps databases to the line-delimited JSON and loading it back.
+//!
+//! Every table is written as a schema line `{"table": ..., "schema": ...}` followed by a line
+//! `{"table": ..., "row": {...}}` per row. Blob values are written as arrays of bytes.
+//! Internal Laplace tables are not dumped.
+
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::{ServerError, ServerResult};
+use crate::lapps::Lapp;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Record {
+    Schema { table: String, schema: String },
+    Row { table: String, row: Map<String, Value> },
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DumpResult {
+    pub tables: usize,
+    pub rows: usize,
+}
+
+/// Returns the database path of the installed lapp without starting the lapps manager.
+pub fn lapp_database_path(lapps_path: impl AsRef<Path>, lapp_name: &str) -> ServerResult<PathBuf> {
+    let lapp_path = lapps_path.as_ref().join(lapp_name);
+    let settings =
+        Lapp::load_settings(lapp_name, &lapp_path).ok_or_else(|| ServerError::LappNotFound(lapp_name.into()))?;
+    Ok(Lapp::database_path(lapp_path, &settings))
+}
+
+pub fn export(connection: &Connection, mut writer: impl Write) -> ServerResult<DumpResult> {
+    let mut result = DumpResult::default();
+    let mut stmt = connection.prepare(
+        "SELECT name, sql FROM sqlite_master
+        WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name NOT LIKE '\\_laplace\\_%' ESCAPE '\\'
+        ORDER BY rowid",
+    )?;
+    let tables: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+    for (table, schema) in tables {
+        write_record(&mut writer, &Record::Schema {
+            table: table.clone(),
+            schema,
+        })?;
+        result.tables += 1;
+
+        let mut stmt = connection.prepare(&format!("SELECT * FROM {}", quote_identifier(&table)))?;
+        let columns: Vec<String> = stmt.column_names().into_iter().map(Into::into).collect();
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let mut values = Map::new();
+            for (idx, column) in columns.iter().enumerate() {
+                values.insert(column.clone(), to_json_value(row.get(idx)?));
+            }
+
+            write_record(&mut writer, &Record::Row {
+                table: table.clone(),
+                row: values,
+            })?;
+            result.rows += 1;
+        }
+    }
+
+    writer.flush()?;
+    Ok(result)
+}
+
+/// Loads the dump in a single transaction. Tables that do not exist are created by the dumped schema,
+/// rows with the conflicting keys are replaced. The schema must be a single `CREATE TABLE` statement of its table,
+/// and the rows are loaded only into the tables of the dump schema.
+pub fn import(connection: &mut Connection, reader: impl BufRead) -> ServerResult<DumpResult> {
+    import_with_progress(connection, reader, |_| Ok(()))
+}
+
+/// Loads the dump like [`import`], calling `on_progress` with the number of the read bytes after every line.
+/// The import is rolled back if `on_progress` returns an error.
+pub fn import_with_progress(
+    connection: &mut Connection,
+    reader: impl BufRead,
+    mut on_progress: impl FnMut(usize) -> ServerResult<()>,
+) -> ServerResult<DumpResult> {
+    let mut result = DumpResult::default();
+    let mut read_bytes = 0;
+    let mut tables = HashSet::new();
+    let transaction = connection.transaction()?;
+
+    for line in reader.lines() {
+        let line = line?;
+        read_bytes += line.len() + 1;
+        on_progress(read_bytes)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str(&line)? {
+            Record::Schema { table, schema } => {
+                let definition = schema
+                    .strip_prefix("CREATE TABLE ")
+                    .and_then(|definition| strip_table_name(definition.trim_start(), &table))
+                    .ok_or_else(|| ServerError::DumpInvalid(format!("wrong schema of table '{table}'")))?;
+
+                // Only the first statement is executed, so the schema cannot run the other SQL
+                transaction.execute(
+                    &format!("CREATE TABLE IF NOT EXISTS {} {definition}", quote_identifier(&table)),
+                    [],
+                )?;
+                tables.insert(table);
+                result.tables += 1;
+            },
+            Record::Row { table, row } => {
+                if !tables.contains(&table) {
+                    return Err(ServerError::DumpInvalid(format!("no schema of table '{table}'")));
+                }
+
+                let columns: Vec<_> = row.keys().map(|column| quote_identifier(column)).collect();
+                let placeholders = vec!["?"; columns.len()];
+                transaction.execute(
+                    &format!(
+                        "INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+                        quote_identifier(&table),
+                        columns.join(", "),
+                        placeholders.join(", ")
+                    ),
+                    params_from_iter(row.values().map(to_sql_value)),
+                )?;
+                result.rows += 1;
+            },
+        }
+    }
+
+    transaction.commit()?;
+    Ok(result)
+}
+
+/// Returns the columns definition following the table name, which may be quoted in any SQLite way.
+fn strip_table_name<'a>(definition: &'a str, table: &str) -> Option<&'a str> {
+    let names = [
+        quote_identifier(table),
+        format!("[{table}]"),
+        format!("`{table}`"),
+        table.to_string(),
+    ];
+    names.iter().find_map(|name| {
+        let columns = definition.strip_prefix(name.as_str())?.trim_start();
+        columns.starts_with('(').then_some(columns)
+    })
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!(r#""{}""#, name.replace('"', r#""""#))
+}
+
+fn write_record(writer: &mut impl Write, record: &Record) -> ServerResult<()> {
+    serde_json::to_writer(&mut *writer, record)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+pub fn to_json_value(value: SqlValue) -> Value {
+    match value {
+        SqlValue::Null => Value::Null,
+        SqlValue::Integer(value) => value.into(),
+        SqlValue::Real(value) => value.into(),
+        SqlValue::Text(value) => value.into(),
+        SqlValue::Blob(value) => value.into(),
+    }
+}
+
+fn to_sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(value) => SqlValue::Integer(*value as _),
+        Value::Number(number) => match number.as_i64() {
+            Some(number) => SqlValue::Integer(number),
+            None => SqlValue::Real(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(value) => SqlValue::Text(value.clone()),
+        Value::Array(values) => SqlValue::Blob(
+            values
+                .iter()
+                .map(|value| value.as_u64().unwrap_or_default() as u8)
+                .collect(),
+        ),
+        value => SqlValue::Text(value.to_string()),
+    }
+}
@@ -1,5 +1,6 @@
 use std::io;
 use std::net::AddrParseError;
+use std::path::PathBuf;
 
 use flexi_logger::FlexiLoggerError;
 use laplace_common::lapp::Permission;
@@ -7,7 +8,7 @@ use rcgen::RcgenError;
 use rusqlite::Error as SqlError;
 use thiserror::Error;
 
-use crate::lapps::{LappInstanceError, LappSettingsError};
+use crate::lapps::{LappInstanceError, LappSettingsError, ResourceLimitExceeded};
 use crate::service::gossipsub;
 
 pub type AppResult<T> = Result<T, AppError>;
@@ -35,8 +36,20 @@ pub enum AppError {
     #[error("Missing private key")]
     MissingPrivateKey,
 
+    #[error("Missing certificate")]
+    MissingCertificate,
+
+    #[error(
+        "Missing SSL file '{}', both the certificate and the private key should be configured",
+        .0.display()
+    )]
+    MissingSslFile(PathBuf),
+
     #[error("Error while generate token")]
     TokenGenerationFail,
+
+    #[error("Users loading error: {0}")]
+    UsersLoadingFail(String),
 }
 
 pub type ServerResult<T> = Result<T, ServerError>;
@@ -61,6 +74,9 @@ pub enum ServerError {
     #[error("Zip error: {0}")]
     ZipError(#[from] zip::result::ZipError),
 
+    #[error("CSV error: {0}")]
+    CsvError(#[from] csv::Error),
+
     #[error("Lapps manager poisoned lock: another task failed inside")]
     LappsManagerNotLock,
 
@@ -73,18 +89,96 @@ pub enum ServerError {
     #[error("Lapp '{0}' is not enabled")]
     LappNotEnabled(String),
 
+    #[error("Permission profile '{0}' does not exist")]
+    PermissionProfileNotFound(String),
+
+    #[error("Lapp '{0}' dependencies are not satisfied: {1}")]
+    LappDependenciesNotSatisfied(String, String),
+
     #[error("Lapp '{0}' is not loaded")]
     LappNotLoaded(String),
 
+    #[error("Lapp '{0}' is unloading")]
+    LappUnloading(String),
+
+    #[error("Rate limit of route '{1}' of lapp '{0}' is exceeded")]
+    LappRateLimitExceeded(String, String),
+
+    #[error("Request body of lapp '{0}' exceeds {1} bytes")]
+    LappRequestBodyTooLarge(String, u64),
+
+    #[error("Proxy route of lapp '{0}' failed: {1}")]
+    LappProxyError(String, String),
+
     #[error("Lapp '{0}' already exists")]
     LappAlreadyExists(String),
 
+    #[error("Lapp '{0}' is required by lapps: {1}")]
+    LappHasDependents(String, String),
+
+    #[error("Manifest of lapp '{0}' is invalid: {1}")]
+    LappManifestInvalid(String, String),
+
+    #[error("Wrong package of lapp '{0}': {1}")]
+    WrongLappPackage(String, String),
+
+    #[error("Signature of lapp '{0}' is not valid: {1}")]
+    LappSignatureInvalid(String, String),
+
+    #[error("File '{1}' of lapp '{0}' does not exist")]
+    LappFileNotFound(String, String),
+
+    #[error("Lapp '{0}' has no icon")]
+    LappIconNotFound(String),
+
+    #[error("Job '{1}' of lapp '{0}' does not exist")]
+    JobNotFound(String, String),
+
+    #[error("Job error: {0}")]
+    JobFail(String),
+
+    #[error("Task {0} does not exist")]
+    TaskNotFound(u64),
+
+    #[error("Task {0} cannot be cancelled")]
+    TaskNotCancellable(u64),
+
+    #[error("Task {0} is cancelled")]
+    TaskCancelled(u64),
+
+    #[error("P2P is not started for lapp '{0}'")]
+    P2pNotStarted(String),
+
+    #[error("Upgrade of lapp '{0}' is not uploaded")]
+    LappUpgradeNotFound(String),
+
+    #[error("Previous version of lapp '{0}' is not found")]
+    LappPreviousVersionNotFound(String),
+
+    #[error("Upgrade of lapp '{0}' is rolled back: {1}")]
+    LappUpgradeRolledBack(String, String),
+
+    #[error("Health check of staged upgrade of lapp '{0}' is failed: {1}")]
+    LappHealthCheckFailed(String, String),
+
+    #[error("Mount path '{1}' of lapp '{0}' is outside of the lapp directory and the allowed mount paths")]
+    LappMountNotAllowed(String, String),
+
+    #[error("Restored path '{1}' of lapp '{0}' is outside of the lapp directory")]
+    LappRestorePathNotAllowed(String, String),
+
     #[error("Path '{0}' is not lapp directory")]
     WrongLappDirectory(String),
 
     #[error("Unknown lapp name")]
     UnknownLappName,
 
+    #[error("Laplace is running in read-only mode, state mutations are not allowed")]
+    ReadOnlyMode,
+
+    #[error("SQL statement is not read-only")]
+    SqlNotReadOnly,
+
     #[error("Permission '{}' denied for lapp '{0}'", .1.as_str())]
     LappPermissionDenied(String, Permission),
 
@@ -101,14 +195,68 @@ pub enum ServerError {
     ResultNotParsed,
 
     #[error("Lapp instance operation error: {0}")]
-    LappInstanceFail(#[from] LappInstanceError),
+    LappInstanceFail(LappInstanceError),
+
+    #[error("Lapp resource limit error: {0}")]
+    LappResourceLimit(ResourceLimitExceeded),
 
     #[error("Lapp database operation error: {0:?}")]
     LappDatabaseError(#[from] SqlError),
 
+    #[error("Lapp database backup error: {0}")]
+    DatabaseBackupError(String),
+
     #[error("Lapp initialization error: {0:?}")]
     LappInitError(String),
 
+    #[error("Trace replay diverged: {0}")]
+    TraceReplayDiverged(String),
+
     #[error("Fail to send lapp service for lapp '{0}'")]
     LappServiceSendError(String),
+
+    #[error("Replication error: {0}")]
+    ReplicationError(String),
+
+    #[error("Dump is not valid: {0}")]
+    DumpInvalid(String),
+
+    #[error("Lapp deploy error: {0}")]
+    DeployError(String),
+
+    #[error("Lapps registry error: {0}")]
+    RegistryError(String),
+
+    #[error("Lapp '{0}' is not found in the registry")]
+    RegistryLappNotFound(String),
+
+    #[error("Version '{1}' of lapp '{0}' is not found in the registry")]
+    RegistryVersionNotFound(String, String),
+
+    #[error("User '{0}' does not exist")]
+    UserNotFound(String),
+
+    #[error("User '{0}' already exists")]
+    UserAlreadyExists(String),
+
+    #[error("Wrong user name '{0}', only letters, digits, '_', '-' and '.' are allowed")]
+    WrongUserName(String),
+
+    #[error("Users error: {0}")]
+    UsersError(String),
+
+    #[error("Settings error: {0}")]
+    SettingsError(String),
+
+    #[error("Settings are not valid: {0}")]
+    SettingsInvalid(String),
+}
+
+impl From<LappInstanceError> for ServerError {
+    fn from(err: LappInstanceError) -> Self {
+        match err {
+            LappInstanceError::ResourceLimit(exceeded) => Self::LappResourceLimit(exceeded),
+            err => Self::LappInstanceFail(err),
+        }
+    }
 }
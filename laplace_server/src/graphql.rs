@@ -0,0 +1,480 @@
+//! GraphQL gateway over the schema fragments of lapps.
+//!
+//! The enabled lapps with the allowed `graphql` permission export the SDL of their schema fragment and the
+//! resolver. The root `Query` and `Mutation` fields of the fragments are merged into one schema: the gateway
+//! splits the operation by the root fields, sends every lapp the operation with its own fields only and merges
+//! the responses. Each lapp is authorized by its access token from the `access_tokens` request extension, the
+//! Laplace access token under the `laplace` key authorizes all of them.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use futures::future;
+use graphql_parser::query::{
+    self, Definition, Directive, Document, OperationDefinition, Selection, SelectionSet, Value as QueryValue,
+};
+use graphql_parser::schema;
+use laplace_common::lapp::Permission;
+use laplace_wasm::graphql;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::lapps::{Lapp, LappsProvider};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphqlRequest {
+    pub query: String,
+    pub operation_name: Option<String>,
+    #[serde(default)]
+    pub variables: Value,
+    #[serde(default)]
+    pub extensions: GraphqlExtensions,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct GraphqlExtensions {
+    #[serde(default)]
+    pub access_tokens: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct GraphqlResponse {
+    pub data: Option<Map<String, Value>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<Value>,
+}
+
+impl GraphqlResponse {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            data: None,
+            errors: vec![json!({ "message": message.into() })],
+        }
+    }
+
+    fn field_error(&mut self, response_key: &str, message: impl Into<String>, lapp_name: &str) {
+        if let Some(data) = &mut self.data {
+            data.insert(response_key.into(), Value::Null);
+        }
+        self.errors.push(json!({
+            "message": message.into(),
+            "path": [response_key],
+            "extensions": { "lapp": lapp_name },
+        }));
+    }
+}
+
+struct LappSchema {
+    lapp_name: String,
+    sdl: String,
+    query_fields: Vec<String>,
+    mutation_fields: Vec<String>,
+}
+
+impl LappSchema {
+    fn new(lapp_name: String, sdl: String) -> Result<Self, schema::ParseError> {
+        let document = schema::parse_schema::<String>(&sdl)?;
+
+        let mut query_type = "Query".to_string();
+        let mut mutation_type = "Mutation".to_string();
+        for definition in &document.definitions {
+            if let schema::Definition::SchemaDefinition(schema) = definition {
+                query_type = schema.query.clone().unwrap_or(query_type);
+                mutation_type = schema.mutation.clone().unwrap_or(mutation_type);
+            }
+        }
+
+        let mut query_fields = Vec::new();
+        let mut mutation_fields = Vec::new();
+        for definition in &document.definitions {
+            let (type_name, fields) = match definition {
+                schema::Definition::TypeDefinition(schema::TypeDefinition::Object(object)) => {
+                    (&object.name, &object.fields)
+                },
+                schema::Definition::TypeExtension(schema::TypeExtension::Object(object)) => {
+                    (&object.name, &object.fields)
+                },
+                _ => continue,
+            };
+
+            let field_names = fields.iter().map(|field| field.name.clone());
+            if *type_name == query_type {
+                query_fields.extend(field_names);
+            } else if *type_name == mutation_type {
+                mutation_fields.extend(field_names);
+            }
+        }
+
+        Ok(Self {
+            lapp_name,
+            sdl,
+            query_fields,
+            mutation_fields,
+        })
+    }
+
+    fn has_root_field(&self, is_mutation: bool, field_name: &str) -> bool {
+        let fields = if is_mutation {
+            &self.mutation_fields
+        } else {
+            &self.query_fields
+        };
+        fields.iter().any(|name| name == field_name)
+    }
+}
+
+/// Returns the schema fragments of the GraphQL lapps, every fragment is prefixed with the lapp name comment.
+pub async fn merged_schema(lapps_provider: &LappsProvider) -> String {
+    lapp_schemas(lapps_provider)
+        .await
+        .into_iter()
+        .map(|schema| format!("# lapp: {}\n{}\n", schema.lapp_name, schema.sdl.trim()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub async fn execute(
+    lapps_provider: &LappsProvider,
+    laplace_access_token: &str,
+    request: GraphqlRequest,
+) -> GraphqlResponse {
+    let document = match query::parse_query::<String>(&request.query) {
+        Ok(document) => document,
+        Err(err) => return GraphqlResponse::error(format!("Query parse error: {err}")),
+    };
+    let Some(operation) = find_operation(&document, request.operation_name.as_deref()) else {
+        return GraphqlResponse::error("Operation is not found");
+    };
+    let is_mutation = match operation {
+        OperationDefinition::SelectionSet(_) | OperationDefinition::Query(_) => false,
+        OperationDefinition::Mutation(_) => true,
+        OperationDefinition::Subscription(_) => return GraphqlResponse::error("Subscriptions are not supported"),
+    };
+
+    let schemas = lapp_schemas(lapps_provider).await;
+    let mut response = GraphqlResponse {
+        data: Some(Map::new()),
+        errors: Vec::new(),
+    };
+
+    let mut lapp_fields: BTreeMap<&str, Vec<Selection<String>>> = BTreeMap::new();
+    for selection in &selection_set(operation).items {
+        let Selection::Field(field) = selection else {
+            response
+                .errors
+                .push(json!({ "message": "Fragments in the root selection are not supported" }));
+            continue;
+        };
+
+        let response_key = field.alias.as_ref().unwrap_or(&field.name);
+        if field.name == "__typename" {
+            let type_name = if is_mutation { "Mutation" } else { "Query" };
+            if let Some(data) = &mut response.data {
+                data.insert(response_key.clone(), type_name.into());
+            }
+            continue;
+        }
+
+        match schemas
+            .iter()
+            .find(|schema| schema.has_root_field(is_mutation, &field.name))
+        {
+            Some(schema) => lapp_fields
+                .entry(schema.lapp_name.as_str())
+                .or_default()
+                .push(selection.clone()),
+            None => response.errors.push(json!({
+                "message": format!("Cannot query root field \"{}\"", field.name),
+                "path": [response_key],
+            })),
+        }
+    }
+
+    let manager = lapps_provider.read_manager().await;
+    let mut resolves = Vec::new();
+    for (lapp_name, fields) in lapp_fields {
+        let response_keys: Vec<String> = fields.iter().filter_map(response_key).collect();
+
+        let lapp_access_token = manager
+            .lapp_settings(lapp_name)
+            .ok()
+            .and_then(|settings| settings.application.access_token.clone())
+            .unwrap_or_default();
+        if !is_authorized(
+            &request.extensions.access_tokens,
+            lapp_name,
+            &lapp_access_token,
+            laplace_access_token,
+        ) {
+            log::warn!("GraphQL access denied for lapp \"{lapp_name}\"");
+            for response_key in &response_keys {
+                response.field_error(
+                    response_key,
+                    format!("Access denied for lapp \"{lapp_name}\""),
+                    lapp_name,
+                );
+            }
+            continue;
+        }
+
+        let lapp_request = graphql::Request {
+            query: lapp_query(&document, operation, fields),
+            operation_name: request.operation_name.clone(),
+            variables_json: request.variables.to_string(),
+        };
+        let lapp_name = lapp_name.to_string();
+        let resolve = manager.process_graphql(lapp_name.clone(), lapp_request);
+        resolves.push(async move { (lapp_name, response_keys, resolve.await) });
+    }
+    drop(manager);
+
+    for (lapp_name, response_keys, result) in future::join_all(resolves).await {
+        let lapp_response = result
+            .map_err(|err| err.to_string())
+            .and_then(|json| serde_json::from_str::<Map<String, Value>>(&json).map_err(|err| err.to_string()));
+
+        match lapp_response {
+            Ok(mut lapp_response) => {
+                match (lapp_response.remove("data"), &mut response.data) {
+                    (Some(Value::Object(lapp_data)), Some(data)) => data.extend(lapp_data),
+                    _ => {
+                        for response_key in &response_keys {
+                            if let Some(data) = &mut response.data {
+                                data.insert(response_key.clone(), Value::Null);
+                            }
+                        }
+                    },
+                }
+
+                if let Some(Value::Array(errors)) = lapp_response.remove("errors") {
+                    response
+                        .errors
+                        .extend(errors.into_iter().map(|error| with_lapp_extension(error, &lapp_name)));
+                }
+            },
+            Err(err) => {
+                log::error!("GraphQL resolve error of lapp \"{lapp_name}\": {err}");
+                for response_key in &response_keys {
+                    response.field_error(response_key, format!("Lapp resolve error: {err}"), &lapp_name);
+                }
+            },
+        }
+    }
+
+    response
+}
+
+/// Collects the schema fragments of the enabled lapps with the allowed `graphql` permission.
+async fn lapp_schemas(lapps_provider: &LappsProvider) -> Vec<LappSchema> {
+    let manager = lapps_provider.read_manager().await;
+    let mut lapp_names: Vec<_> = manager
+        .lapp_settings_iter()
+        .filter(|(name, settings)| {
+            !Lapp::is_main(name) && settings.enabled() && settings.permissions.is_allowed(Permission::Graphql)
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    lapp_names.sort_unstable();
+
+    let schema_futures: Vec<_> = lapp_names
+        .into_iter()
+        .map(|lapp_name| {
+            let schema = manager.graphql_schema(lapp_name.clone());
+            async move { (lapp_name, schema.await) }
+        })
+        .collect();
+    drop(manager);
+
+    let mut schemas = Vec::new();
+    for (lapp_name, result) in future::join_all(schema_futures).await {
+        match result {
+            Ok(Some(sdl)) => match LappSchema::new(lapp_name.clone(), sdl) {
+                Ok(schema) => schemas.push(schema),
+                Err(err) => log::error!("Wrong GraphQL schema of lapp \"{lapp_name}\": {err}"),
+            },
+            Ok(None) => log::warn!("Lapp \"{lapp_name}\" has the graphql permission but does not export the schema"),
+            Err(err) => log::error!("Get GraphQL schema of lapp \"{lapp_name}\" error: {err}"),
+        }
+    }
+    schemas
+}
+
+fn is_authorized(
+    access_tokens: &HashMap<String, String>,
+    lapp_name: &str,
+    lapp_access_token: &str,
+    laplace_access_token: &str,
+) -> bool {
+    access_tokens.get(Lapp::main_name()).map(String::as_str) == Some(laplace_access_token)
+        || access_tokens.get(lapp_name).map(String::as_str) == Some(lapp_access_token)
+}
+
+fn find_operation<'a>(
+    document: &'a Document<'a, String>,
+    operation_name: Option<&str>,
+) -> Option<&'a OperationDefinition<'a, String>> {
+    let mut operations = document.definitions.iter().filter_map(|definition| match definition {
+        Definition::Operation(operation) => Some(operation),
+        Definition::Fragment(_) => None,
+    });
+
+    match operation_name {
+        Some(operation_name) => operations.find(|operation| {
+            let name = match operation {
+                OperationDefinition::SelectionSet(_) => None,
+                OperationDefinition::Query(query) => query.name.as_deref(),
+                OperationDefinition::Mutation(mutation) => mutation.name.as_deref(),
+                OperationDefinition::Subscription(subscription) => subscription.name.as_deref(),
+            };
+            name == Some(operation_name)
+        }),
+        None => {
+            let operation = operations.next();
+            operation.filter(|_| operations.next().is_none())
+        },
+    }
+}
+
+fn selection_set<'a, 'b>(operation: &'b OperationDefinition<'a, String>) -> &'b SelectionSet<'a, String> {
+    match operation {
+        OperationDefinition::SelectionSet(selection_set) => selection_set,
+        OperationDefinition::Query(query) => &query.selection_set,
+        OperationDefinition::Mutation(mutation) => &mutation.selection_set,
+        OperationDefinition::Subscription(subscription) => &subscription.selection_set,
+    }
+}
+
+fn response_key(selection: &Selection<String>) -> Option<String> {
+    match selection {
+        Selection::Field(field) => Some(field.alias.clone().unwrap_or_else(|| field.name.clone())),
+        _ => None,
+    }
+}
+
+/// Prints the operation with the lapp root fields, the variables and the fragments it uses.
+fn lapp_query<'a>(
+    document: &Document<'a, String>,
+    operation: &OperationDefinition<'a, String>,
+    fields: Vec<Selection<'a, String>>,
+) -> String {
+    let fragments: HashMap<&str, _> = document
+        .definitions
+        .iter()
+        .filter_map(|definition| match definition {
+            Definition::Fragment(fragment) => Some((fragment.name.as_str(), fragment)),
+            Definition::Operation(_) => None,
+        })
+        .collect();
+
+    let mut operation = operation.clone();
+    let mut usage = Usage::default();
+    match &mut operation {
+        OperationDefinition::SelectionSet(selection_set) => {
+            selection_set.items = fields;
+            usage.selection_set(selection_set, &fragments);
+        },
+        OperationDefinition::Query(query) => {
+            query.selection_set.items = fields;
+            usage.directives(&query.directives);
+            usage.selection_set(&query.selection_set, &fragments);
+            query
+                .variable_definitions
+                .retain(|variable| usage.variables.contains(&variable.name));
+        },
+        OperationDefinition::Mutation(mutation) => {
+            mutation.selection_set.items = fields;
+            usage.directives(&mutation.directives);
+            usage.selection_set(&mutation.selection_set, &fragments);
+            mutation
+                .variable_definitions
+                .retain(|variable| usage.variables.contains(&variable.name));
+        },
+        OperationDefinition::Subscription(subscription) => {
+            subscription.selection_set.items = fields;
+            usage.directives(&subscription.directives);
+            usage.selection_set(&subscription.selection_set, &fragments);
+            subscription
+                .variable_definitions
+                .retain(|variable| usage.variables.contains(&variable.name));
+        },
+    }
+
+    let mut definitions = vec![Definition::Operation(operation)];
+    definitions.extend(document.definitions.iter().filter_map(|definition| match definition {
+        Definition::Fragment(fragment) if usage.fragments.contains(&fragment.name) => {
+            Some(Definition::Fragment(fragment.clone()))
+        },
+        _ => None,
+    }));
+
+    Document { definitions }.to_string()
+}
+
+/// The variables and fragments used by the selection, some resolvers reject the unused ones.
+#[derive(Default)]
+struct Usage {
+    variables: HashSet<String>,
+    fragments: HashSet<String>,
+}
+
+impl Usage {
+    fn selection_set<'a>(
+        &mut self,
+        selection_set: &SelectionSet<'a, String>,
+        fragments: &HashMap<&str, &query::FragmentDefinition<'a, String>>,
+    ) {
+        for selection in &selection_set.items {
+            match selection {
+                Selection::Field(field) => {
+                    for (_, value) in &field.arguments {
+                        self.value(value);
+                    }
+                    self.directives(&field.directives);
+                    self.selection_set(&field.selection_set, fragments);
+                },
+                Selection::FragmentSpread(spread) => {
+                    self.directives(&spread.directives);
+                    if self.fragments.insert(spread.fragment_name.clone()) {
+                        if let Some(fragment) = fragments.get(spread.fragment_name.as_str()) {
+                            self.directives(&fragment.directives);
+                            self.selection_set(&fragment.selection_set, fragments);
+                        }
+                    }
+                },
+                Selection::InlineFragment(inline) => {
+                    self.directives(&inline.directives);
+                    self.selection_set(&inline.selection_set, fragments);
+                },
+            }
+        }
+    }
+
+    fn directives(&mut self, directives: &[Directive<String>]) {
+        for directive in directives {
+            for (_, value) in &directive.arguments {
+                self.value(value);
+            }
+        }
+    }
+
+    fn value(&mut self, value: &QueryValue<String>) {
+        match value {
+            QueryValue::Variable(name) => {
+                self.variables.insert(name.clone());
+            },
+            QueryValue::List(values) => values.iter().for_each(|value| self.value(value)),
+            QueryValue::Object(fields) => fields.values().for_each(|value| self.value(value)),
+            _ => (),
+        }
+    }
+}
+
+fn with_lapp_extension(mut error: Value, lapp_name: &str) -> Value {
+    if let Value::Object(error) = &mut error {
+        let extensions = error.entry("extensions").or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(extensions) = extensions {
+            extensions.insert("lapp".into(), lapp_name.into());
+        }
+    }
+    error
+}
@@ -0,0 +1,208 @@
+//! Calls of the lapp APIs by other lapps.
+//!
+//! The calling lapp lists the called lapps in its `lapp_requests` settings and needs the `lapps_outgoing` permission,
+//! the called lapp needs the `lapps_incoming` permission. The server mints the capability token of every listed pair
+//! when the calling lapp is instantiated and keeps it in the host context of the instance, the lapp never sees it.
+//! The `invoke_lapp` host call routes the request with the token, so the request is processed only if the token of
+//! the pair is actual and the settings of both lapps allow it. The tokens of the lapp are revoked when it is
+//! re-instantiated, so the tokens of the instances with the previous settings are not accepted.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use laplace_common::lapp::Permission;
+use laplace_wasm::http::{self, HeaderValue, Uri};
+use laplace_wasm::lapps::{CallError, CallResult};
+use ring::rand::{self, SecureRandom};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::lapps::{LappsManager, LappsProvider};
+
+/// The header with the name of the calling lapp, set by the server on the lapp calls only.
+pub const CALLER_HEADER: &str = "x-laplace-caller";
+
+pub struct LappCall {
+    pub caller: String,
+    pub lapp_name: String,
+    pub token: String,
+    pub request: http::Request,
+    pub response_out: oneshot::Sender<CallResult<http::Response>>,
+}
+
+#[derive(Clone)]
+pub struct LappCalls {
+    tokens: Arc<Mutex<HashMap<(String, String), String>>>,
+    calls_in: mpsc::UnboundedSender<LappCall>,
+    calls: Arc<Mutex<Option<mpsc::UnboundedReceiver<LappCall>>>>,
+}
+
+impl LappCalls {
+    pub fn new() -> Self {
+        let (calls_in, calls) = mpsc::unbounded_channel();
+
+        Self {
+            tokens: Default::default(),
+            calls_in,
+            calls: Arc::new(Mutex::new(Some(calls))),
+        }
+    }
+
+    /// Routes the lapp calls to the called lapps.
+    pub fn run(&self, lapps_provider: LappsProvider) {
+        let Some(mut calls) = self
+            .calls
+            .lock()
+            .expect("Lapp calls lock should not be poisoned")
+            .take()
+        else {
+            log::warn!("Lapp calls are already run");
+            return;
+        };
+
+        let tokens = self.tokens.clone();
+        tokio::spawn(async move {
+            while let Some(call) = calls.recv().await {
+                let is_valid_token = lock_tokens(&tokens)
+                    .get(&(call.caller.clone(), call.lapp_name.clone()))
+                    .is_some_and(|token| *token == call.token);
+                let lapps_provider = lapps_provider.clone();
+
+                tokio::spawn(async move {
+                    let LappCall {
+                        caller,
+                        lapp_name,
+                        request,
+                        response_out,
+                        ..
+                    } = call;
+
+                    let result = if is_valid_token {
+                        process_call(lapps_provider, caller, lapp_name, request).await
+                    } else {
+                        Err(CallError::ForbiddenLapp(lapp_name))
+                    };
+                    response_out.send(result).ok();
+                });
+            }
+        });
+    }
+
+    /// Mints the capability token of the calling lapp to the called lapp, the pooled instances of the lapp share the
+    /// actual token of the pair.
+    pub fn mint_token(&self, caller: &str, lapp_name: &str) -> Option<String> {
+        let mut tokens = lock_tokens(&self.tokens);
+        if let Some(token) = tokens.get(&(caller.to_string(), lapp_name.to_string())) {
+            return Some(token.clone());
+        }
+
+        let mut buf = [0; 32];
+        if let Err(err) = rand::SystemRandom::new().fill(&mut buf) {
+            log::error!("Mint capability token of lapp '{caller}' to lapp '{lapp_name}' error: {err}");
+            return None;
+        }
+
+        let token = bs58::encode(&buf).into_string();
+        tokens.insert((caller.into(), lapp_name.into()), token.clone());
+        Some(token)
+    }
+
+    /// Revokes the tokens of the calling lapp.
+    pub fn revoke_tokens(&self, caller: &str) {
+        lock_tokens(&self.tokens).retain(|(token_caller, _), _| token_caller != caller);
+    }
+
+    pub async fn call(
+        &self,
+        caller: &str,
+        lapp_name: String,
+        token: String,
+        request: http::Request,
+    ) -> CallResult<http::Response> {
+        let (response_out, response_in) = oneshot::channel();
+        let call = LappCall {
+            caller: caller.into(),
+            lapp_name,
+            token,
+            request,
+            response_out,
+        };
+
+        self.calls_in
+            .send(call)
+            .map_err(|_| CallError::FailCall("lapp calls are not run".into()))?;
+        response_in
+            .await
+            .map_err(|_| CallError::FailCall("lapp call is dropped".into()))?
+    }
+}
+
+impl Default for LappCalls {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn process_call(
+    lapps_provider: LappsProvider,
+    caller: String,
+    lapp_name: String,
+    mut request: http::Request,
+) -> CallResult<http::Response> {
+    let path = request.uri.path().trim_start_matches('/').to_string();
+    let manager = lapps_provider.read_manager().await;
+    check_call(&manager, &caller, &lapp_name, request.method.as_str(), &path)?;
+
+    let query = request.uri.query().map(|query| format!("?{query}")).unwrap_or_default();
+    request.uri = format!("/{lapp_name}/{path}{query}")
+        .parse::<Uri>()
+        .map_err(|err| CallError::FailCall(format!("wrong request path '{path}': {err}")))?;
+    request.headers.remove(CALLER_HEADER);
+    if let Ok(caller) = HeaderValue::from_str(&caller) {
+        request.headers.insert(CALLER_HEADER, caller);
+    }
+
+    let process_http_fut = manager.process_http(lapp_name, request);
+    drop(manager);
+
+    process_http_fut
+        .await
+        .map_err(|err| CallError::FailCall(err.to_string()))
+}
+
+fn check_call(manager: &LappsManager, caller: &str, lapp_name: &str, method: &str, path: &str) -> CallResult<()> {
+    let forbidden_lapp = || CallError::ForbiddenLapp(lapp_name.into());
+    if caller == lapp_name {
+        return Err(forbidden_lapp());
+    }
+
+    manager
+        .check_enabled_and_allow_permissions(caller, &[Permission::LappsOutgoing])
+        .map_err(|_| forbidden_lapp())?;
+    manager
+        .check_enabled_and_allow_permissions(lapp_name, &[Permission::LappsIncoming])
+        .map_err(|_| forbidden_lapp())?;
+
+    let outgoing = manager
+        .lapp_settings(caller)
+        .ok()
+        .and_then(|settings| settings.lapp_requests_of(lapp_name))
+        .ok_or_else(forbidden_lapp)?;
+    let is_incoming_allowed = manager
+        .lapp_settings(lapp_name)
+        .ok()
+        .and_then(|settings| settings.lapp_requests_of(caller))
+        .is_none_or(|incoming| incoming.is_incoming_allowed(method, path));
+
+    if outgoing.is_outgoing_allowed(method, path) && is_incoming_allowed {
+        Ok(())
+    } else {
+        Err(CallError::ForbiddenRequest(
+            lapp_name.into(),
+            format!("{method} {path}"),
+        ))
+    }
+}
+
+fn lock_tokens(tokens: &Mutex<HashMap<(String, String), String>>) -> MutexGuard<'_, HashMap<(String, String), String>> {
+    tokens.lock().expect("Lapp tokens lock should not be poisoned")
+}
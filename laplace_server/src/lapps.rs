@@ -1,12 +1,42 @@
+pub use self::audit::*;
+pub use self::backup::*;
+pub use self::component::*;
+pub use self::connection_pool::*;
+pub use self::disk_quota::*;
+pub use self::duplicate::*;
 pub use self::instance::*;
+pub use self::job_queue::*;
 pub use self::lapp::*;
+pub use self::logs::*;
 pub use self::manager::*;
+pub use self::migrations::*;
+pub use self::module_cache::*;
+pub use self::pool::*;
 pub use self::provider::*;
 pub use self::settings::*;
+pub use self::signature::*;
+pub use self::snapshot::*;
+pub use self::trace::*;
+pub use self::upgrade::*;
 
+mod audit;
+mod backup;
+mod component;
+mod connection_pool;
+mod disk_quota;
+mod duplicate;
 mod instance;
+mod job_queue;
 mod lapp;
+mod logs;
 mod manager;
+mod migrations;
+mod module_cache;
+mod pool;
 mod provider;
 mod settings;
+mod signature;
+mod snapshot;
+mod trace;
+mod upgrade;
 mod wasm_interop;
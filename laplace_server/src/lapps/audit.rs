@@ -0,0 +1,78 @@
+//! Audit log of the lapp permission changes.
+//!
+//! Every permission allowed or denied by the lapp update is appended as a JSON line to the
+//! `permissions_audit.jsonl` file of the state dir. The log is never rewritten by the server and is kept after the
+//! lapp is uninstalled.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use laplace_common::api::{PermissionAuditEntry, PermissionState};
+use laplace_common::lapp::Permission;
+
+use crate::error::ServerResult;
+
+#[derive(Debug, Clone)]
+pub struct PermissionAudit {
+    path: PathBuf,
+}
+
+impl PermissionAudit {
+    pub fn new(state_dir: &Path) -> Self {
+        Self {
+            path: state_dir.join("permissions_audit.jsonl"),
+        }
+    }
+
+    pub fn record(
+        &self,
+        lapp_name: &str,
+        actor: &str,
+        permission: Permission,
+        old_state: PermissionState,
+        new_state: PermissionState,
+    ) -> ServerResult<()> {
+        let entry = PermissionAuditEntry {
+            lapp_name: lapp_name.into(),
+            time: Utc::now().to_rfc3339(),
+            actor: actor.into(),
+            permission,
+            old_state,
+            new_state,
+        };
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&line)?;
+        Ok(())
+    }
+
+    /// Returns the recorded changes of the lapp permissions, the oldest first.
+    pub fn entries(&self, lapp_name: &str) -> ServerResult<Vec<PermissionAuditEntry>> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            match serde_json::from_str::<PermissionAuditEntry>(&line) {
+                Ok(entry) if entry.lapp_name == lapp_name => entries.push(entry),
+                Ok(_) => {},
+                Err(err) => log::warn!("Wrong permissions audit entry '{line}': {err}"),
+            }
+        }
+        Ok(entries)
+    }
+}
@@ -0,0 +1,193 @@
+//! Backup of the lapp to a single archive and its restoring on another server.
+//!
+//! The archive has the layout of the lapp package: the files of the lapp directory with its config are in the root.
+//! The database and the data directory are stored under `.backup/` regardless of their paths, because they may be
+//! configured outside the lapp directory. The database is copied by `VACUUM INTO`, so the backup of the running
+//! lapp is consistent. The files regenerated by the server (snapshots, traces, compiled modules) and the previous
+//! and staged versions kept by the upgrade are not backed up.
+//!
+//! Besides, the database alone is copied to the backup directory of the lapp by the SQLite online backup.
+
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+
+use chrono::Utc;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use tempfile::NamedTempFile;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::error::{ServerError, ServerResult};
+use crate::lapps::{package_settings, Lapp, LappSettings, PackageVerifier, STAGING_DIR};
+
+pub const BACKUP_DIR: &str = ".backup";
+const BACKUP_DATABASE: &str = ".backup/database.sqlite";
+const BACKUP_DATA_DIR: &str = ".backup/data";
+
+/// The lapp subdirectories and the file extensions that are not backed up and not duplicated.
+pub const EXCLUDED_DIRS: &[&str] = &[".previous", STAGING_DIR];
+pub const EXCLUDED_EXTENSIONS: &[&str] = &["snapshot", "trace", "cwasm", "tmp"];
+
+/// Whether the file is regenerated by the server, e.g. the compiled module. Such files are not taken from the packages
+/// and backups, so they cannot be planted into the lapp directory.
+pub fn is_regenerated_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| EXCLUDED_EXTENSIONS.contains(&extension))
+}
+
+pub fn backup_lapp(lapp_dir: &Path, settings: &LappSettings, writer: impl Write + Seek) -> ServerResult<()> {
+    let database_path = Lapp::database_path(lapp_dir, settings);
+    let data_dir = Lapp::data_dir_path(lapp_dir, settings);
+    let excluded: Vec<PathBuf> = ["", "-wal", "-shm", "-journal"]
+        .into_iter()
+        .map(|suffix| PathBuf::from(format!("{}{suffix}", database_path.display())))
+        .chain([data_dir.clone(), Lapp::database_backup_dir(lapp_dir, settings)])
+        .chain(EXCLUDED_DIRS.iter().map(|dir| lapp_dir.join(dir)))
+        .collect();
+
+    let mut zip = ZipWriter::new(writer);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let is_excluded = |path: &Path| excluded.iter().any(|excluded| excluded == path) || is_regenerated_file(path);
+    add_dir(&mut zip, options, lapp_dir, "", &is_excluded)?;
+
+    if database_path.is_file() {
+        let database_copy = NamedTempFile::new()?;
+        Connection::open(&database_path)?.execute("VACUUM INTO ?1", [database_copy.path().to_string_lossy()])?;
+        zip.start_file(BACKUP_DATABASE, options)?;
+        io::copy(&mut fs::File::open(database_copy.path())?, &mut zip)?;
+    }
+
+    if data_dir.is_dir() {
+        zip.add_directory(BACKUP_DATA_DIR, options)?;
+        add_dir(&mut zip, options, &data_dir, BACKUP_DATA_DIR, &is_excluded)?;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// Copies the database to the timestamped file of the backup directory by the SQLite online backup, so the database
+/// may be used while it is copied. Returns the path of the backup file.
+pub fn backup_database(database_path: &Path, backup_dir: &Path) -> ServerResult<PathBuf> {
+    let source = Connection::open_with_flags(
+        database_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+    )?;
+
+    fs::create_dir_all(backup_dir)?;
+    let stem = database_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("database");
+    let backup_path = backup_dir.join(format!("{stem}-{}.sqlite", Utc::now().format("%Y%m%dT%H%M%S%.3fZ")));
+
+    // The unfinished backup is left in the temporary file, which is not taken for the backup
+    let tmp_path = backup_path.with_extension("tmp");
+    if let Err(err) = source.backup(DatabaseName::Main, &tmp_path, None) {
+        fs::remove_file(&tmp_path).ok();
+        return Err(err.into());
+    }
+    fs::rename(&tmp_path, &backup_path)?;
+
+    Ok(backup_path)
+}
+
+/// Restores the backed up lapp to the lapp directory that has no lapp installed. The lapp files are verified like
+/// the installed package, and the database and the data are restored only inside the lapp directory.
+pub fn restore_lapp<R: Read + Seek>(
+    lapp_name: &str,
+    archive: &mut ZipArchive<R>,
+    lapp_dir: &Path,
+    package_verifier: &PackageVerifier,
+    mut on_progress: impl FnMut(f32) -> ServerResult<()>,
+) -> ServerResult<()> {
+    let settings = package_settings(lapp_name, archive)?;
+    if Lapp::settings_path(lapp_dir).exists() {
+        return Err(ServerError::LappAlreadyExists(lapp_name.into()));
+    }
+    package_verifier.verify_backup(lapp_name, archive)?;
+
+    for path in [settings.database().path(), settings.application.data_dir.as_path()] {
+        let is_enclosed = path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !is_enclosed {
+            return Err(ServerError::LappRestorePathNotAllowed(
+                lapp_name.into(),
+                path.display().to_string(),
+            ));
+        }
+    }
+
+    let database_path = Lapp::database_path(lapp_dir, &settings);
+    let data_dir = Lapp::data_dir_path(lapp_dir, &settings);
+
+    let len = archive.len();
+    for idx in 0..len {
+        on_progress(idx as f32 / len as f32)?;
+
+        let mut entry = archive.by_index(idx)?;
+        let Some(entry_path) = entry.enclosed_name().map(Path::to_path_buf) else {
+            continue;
+        };
+        if is_regenerated_file(&entry_path) {
+            continue;
+        }
+
+        let path = if entry_path == Path::new(BACKUP_DATABASE) {
+            database_path.clone()
+        } else if let Ok(data_path) = entry_path.strip_prefix(BACKUP_DATA_DIR) {
+            data_dir.join(data_path)
+        } else if entry_path.starts_with(BACKUP_DIR) {
+            continue;
+        } else {
+            lapp_dir.join(entry_path)
+        };
+
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut entry, &mut fs::File::create(&path)?)?;
+        }
+    }
+    on_progress(1.0)
+}
+
+fn add_dir<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
+    options: FileOptions,
+    dir: &Path,
+    prefix: &str,
+    is_excluded: &dyn Fn(&Path) -> bool,
+) -> ServerResult<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if is_excluded(&path) {
+            continue;
+        }
+
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let name = if prefix.is_empty() {
+            file_name.to_string()
+        } else {
+            format!("{prefix}/{file_name}")
+        };
+
+        if path.is_dir() {
+            zip.add_directory(name.as_str(), options)?;
+            add_dir(zip, options, &path, &name, is_excluded)?;
+        } else {
+            zip.start_file(name, options)?;
+            io::copy(&mut fs::File::open(&path)?, zip)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,255 @@
+//! The server modules built as the WASM components.
+//!
+//! The component implements the `lapp-component` world of `wit/lapp.wit` instead of the `u64` slice ABI of the core
+//! modules: it exports the typed HTTP handler and imports the typed `db`, `kv` and `http-client` interfaces besides
+//! WASI preview2. The imports are linked for every component and checked by the lapp permissions on the call. The
+//! component lapp processes the HTTP requests only, it has no instance pool, snapshot and trace.
+
+use std::str::FromStr;
+
+use laplace_wasm::database::Value;
+use laplace_wasm::http::{self as wasm_http, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri, Version};
+use rusqlite::OptionalExtension;
+use wasmtime::component::{Component, Linker};
+use wasmtime::Store;
+
+use self::bindings::laplace::lapp::{db, http_client, kv, types};
+use self::bindings::LappComponent;
+use crate::lapps::wasm_interop::database;
+use crate::lapps::wasm_interop::http::do_invoke_http;
+use crate::lapps::{Ctx, DiskQuota, LappInstanceError, LappInstanceResult};
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        path: "wit",
+        world: "lapp-component",
+        async: true,
+    });
+}
+
+const KV_TABLE: &str = "_laplace_kv";
+
+/// Checks the layer field of the WASM binary header, which is `1` for the components and `0` for the core modules.
+pub fn is_component(wasm_bytes: &[u8]) -> bool {
+    wasm_bytes.starts_with(b"\0asm") && wasm_bytes.get(6..8) == Some(&[1, 0])
+}
+
+pub struct ComponentInstance {
+    bindings: LappComponent,
+    store: Store<Ctx>,
+
+    /// The call of the instance trapped since the last check, so its state may be inconsistent.
+    pub is_failed: bool,
+}
+
+impl ComponentInstance {
+    pub async fn instantiate(component: &Component, mut store: Store<Ctx>) -> LappInstanceResult<Self> {
+        let mut linker = Linker::new(store.engine());
+        wasmtime_wasi::preview2::command::add_to_linker(&mut linker)?;
+        LappComponent::add_to_linker(&mut linker, |ctx| ctx)?;
+
+        store.data_mut().start_call();
+        let result = LappComponent::instantiate_async(&mut store, component, &linker).await;
+        store.data_mut().finish_call();
+        let (bindings, _) = result.map_err(LappInstanceError::from_call)?;
+
+        Ok(Self {
+            bindings,
+            store,
+            is_failed: false,
+        })
+    }
+
+    pub async fn process_http(&mut self, request: wasm_http::Request) -> LappInstanceResult<wasm_http::Response> {
+        let request_id = request.request_id().map(Into::into);
+        let request = types::Request {
+            method: request.method.to_string(),
+            uri: request.uri.to_string(),
+            headers: from_header_map(&request.headers),
+            body: request.body,
+        };
+
+        self.store.data_mut().request_id = request_id;
+        self.store.data_mut().start_http_call();
+        let result = self
+            .bindings
+            .laplace_lapp_http_handler()
+            .call_handle(&mut self.store, &request)
+            .await;
+        self.store.data_mut().finish_call();
+        self.store.data_mut().request_id = None;
+
+        let response = result.map_err(|err| {
+            self.is_failed = true;
+            LappInstanceError::from_call(err)
+        })?;
+        Ok(wasm_http::Response {
+            status: StatusCode::from_u16(response.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            version: Version::HTTP_11,
+            headers: to_header_map(response.headers),
+            body: response.body,
+        })
+    }
+}
+
+impl types::Host for Ctx {}
+
+#[async_trait::async_trait]
+impl db::Host for Ctx {
+    async fn execute(&mut self, sql: String) -> wasmtime::Result<Result<u64, String>> {
+        if let Some(Err(exceeded)) = self.disk_quota.as_ref().map(DiskQuota::check) {
+            return Ok(Err(exceeded.to_string()));
+        }
+        Ok(self
+            .with_connection(|connection| database::do_execute(connection, sql))
+            .await)
+    }
+
+    async fn query(&mut self, sql: String) -> wasmtime::Result<Result<Vec<db::Row>, String>> {
+        let rows = self
+            .with_connection(|connection| database::do_query(connection, sql))
+            .await;
+        Ok(rows.map(|rows| rows.into_iter().map(|row| to_row(row.into_values())).collect()))
+    }
+
+    async fn query_row(&mut self, sql: String) -> wasmtime::Result<Result<Option<db::Row>, String>> {
+        let row = self
+            .with_connection(|connection| database::do_query_row(connection, sql))
+            .await;
+        Ok(row.map(|row| row.map(|row| to_row(row.into_values()))))
+    }
+}
+
+#[async_trait::async_trait]
+impl kv::Host for Ctx {
+    async fn get(&mut self, key: String) -> wasmtime::Result<Result<Option<Vec<u8>>, String>> {
+        Ok(self
+            .with_connection(|connection| {
+                let is_table_exists = connection
+                    .query_row(
+                        "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                        [KV_TABLE],
+                        |_| Ok(()),
+                    )
+                    .optional()?
+                    .is_some();
+                if !is_table_exists {
+                    return Ok(None);
+                }
+
+                connection
+                    .query_row(&format!("SELECT value FROM {KV_TABLE} WHERE key = ?1"), [&key], |row| {
+                        row.get(0)
+                    })
+                    .optional()
+            })
+            .await)
+    }
+
+    async fn set(&mut self, key: String, value: Vec<u8>) -> wasmtime::Result<Result<(), String>> {
+        if let Some(Err(exceeded)) = self.disk_quota.as_ref().map(DiskQuota::check) {
+            return Ok(Err(exceeded.to_string()));
+        }
+        Ok(self
+            .with_connection(|connection| {
+                connection.execute(
+                    &format!("CREATE TABLE IF NOT EXISTS {KV_TABLE} (key TEXT PRIMARY KEY, value BLOB NOT NULL)"),
+                    [],
+                )?;
+                connection.execute(
+                    &format!("INSERT OR REPLACE INTO {KV_TABLE} (key, value) VALUES (?1, ?2)"),
+                    rusqlite::params![key, value],
+                )?;
+                Ok(())
+            })
+            .await)
+    }
+
+    async fn delete(&mut self, key: String) -> wasmtime::Result<Result<bool, String>> {
+        Ok(self
+            .with_connection(|connection| {
+                match connection.execute(&format!("DELETE FROM {KV_TABLE} WHERE key = ?1"), [&key]) {
+                    Ok(deleted_rows) => Ok(deleted_rows > 0),
+                    Err(err) if err.to_string().contains("no such table") => Ok(false),
+                    Err(err) => Err(err),
+                }
+            })
+            .await)
+    }
+}
+
+#[async_trait::async_trait]
+impl http_client::Host for Ctx {
+    async fn send(&mut self, request: types::Request) -> wasmtime::Result<Result<types::Response, String>> {
+        let Some(http_ctx) = self.http.as_ref() else {
+            return Ok(Err(wasm_http::InvokeError::EmptyContext.to_string()));
+        };
+
+        let request = match to_wasm_request(request) {
+            Ok(request) => request,
+            Err(err) => return Ok(Err(err)),
+        };
+        let response = do_invoke_http(http_ctx, request)
+            .await
+            .map(|response| types::Response {
+                status: response.status.as_u16(),
+                headers: from_header_map(&response.headers),
+                body: response.body,
+            })
+            .map_err(|err| err.to_string());
+        Ok(response)
+    }
+}
+
+impl Ctx {
+    async fn with_connection<T, E: ToString>(
+        &self,
+        fun: impl FnOnce(&rusqlite::Connection) -> Result<T, E>,
+    ) -> Result<T, String> {
+        match self.database.as_ref() {
+            Some(database_ctx) => {
+                let connection = database_ctx.connection.lock().await;
+                fun(&connection).map_err(|err| err.to_string())
+            },
+            None => Err("Database context not found".to_string()),
+        }
+    }
+}
+
+fn to_wasm_request(request: types::Request) -> Result<wasm_http::Request, String> {
+    Ok(wasm_http::Request {
+        method: Method::from_str(&request.method).map_err(|err| err.to_string())?,
+        uri: Uri::from_str(&request.uri).map_err(|err| err.to_string())?,
+        version: Version::HTTP_11,
+        headers: to_header_map(request.headers),
+        body: request.body,
+    })
+}
+
+fn from_header_map(headers: &HeaderMap) -> types::Headers {
+    headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+/// Converts the headers of the component, the invalid headers are skipped.
+fn to_header_map(headers: types::Headers) -> HeaderMap {
+    headers
+        .into_iter()
+        .filter_map(|(name, value)| Some((HeaderName::from_str(&name).ok()?, HeaderValue::from_str(&value).ok()?)))
+        .collect()
+}
+
+fn to_row(values: Vec<Value>) -> db::Row {
+    values
+        .into_iter()
+        .map(|value| match value {
+            Value::Null => db::Value::Null,
+            Value::Integer(val) => db::Value::Integer(val),
+            Value::Real(val) => db::Value::Real(val),
+            Value::Text(val) => db::Value::Text(val),
+            Value::Blob(val) => db::Value::Blob(val),
+        })
+        .collect()
+}
@@ -0,0 +1,124 @@
+//! The pool of the lapp database connections.
+//!
+//! Every lapp instance takes its own connection from the pool of the lapp, so the pooled instances do not serialize
+//! on one SQLite handle, and the transactions and the prepared statements of an instance stay on its connection. The
+//! connection of the dropped instance returns to the pool with its statement cache, so the restarted instances do not
+//! open the database again. The connection with the unfinished transaction is closed instead. The pool is replaced
+//! when the database path or the database permissions of the lapp change.
+
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::lapps::wasm_interop::database;
+
+#[derive(Clone)]
+pub struct ConnectionPool {
+    inner: Arc<ConnectionPoolInner>,
+}
+
+struct ConnectionPoolInner {
+    database_path: PathBuf,
+    read_only: bool,
+    is_allow_write: bool,
+
+    /// The number of the idle connections kept by the pool, the extra returned connections are closed.
+    max_idle: usize,
+    idle: Mutex<Vec<Connection>>,
+}
+
+impl ConnectionPool {
+    pub fn new(database_path: PathBuf, read_only: bool, is_allow_write: bool, max_idle: usize) -> Self {
+        Self {
+            inner: Arc::new(ConnectionPoolInner {
+                database_path,
+                read_only,
+                is_allow_write,
+                max_idle,
+                idle: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Whether the pool opens the connections of the same database with the same access.
+    pub fn is_same(&self, database_path: &Path, read_only: bool, is_allow_write: bool) -> bool {
+        self.inner.database_path == database_path
+            && self.inner.read_only == read_only
+            && self.inner.is_allow_write == is_allow_write
+    }
+
+    pub fn database_path(&self) -> &Path {
+        &self.inner.database_path
+    }
+
+    /// Takes the idle connection or opens the new one, the writes are denied if they are not allowed by the pool.
+    pub fn acquire(&self) -> rusqlite::Result<PooledConnection> {
+        let connection = match self.idle().pop() {
+            Some(connection) => connection,
+            None => self.open()?,
+        };
+
+        Ok(PooledConnection {
+            connection: Some(connection),
+            pool: self.clone(),
+        })
+    }
+
+    fn open(&self) -> rusqlite::Result<Connection> {
+        let connection = if self.inner.read_only {
+            Connection::open_with_flags(
+                &self.inner.database_path,
+                OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+            )?
+        } else {
+            Connection::open(&self.inner.database_path)?
+        };
+        if !self.inner.is_allow_write {
+            database::deny_writes(&connection);
+        }
+        Ok(connection)
+    }
+
+    fn idle(&self) -> MutexGuard<'_, Vec<Connection>> {
+        self.inner
+            .idle
+            .lock()
+            .expect("Connection pool lock should not be poisoned")
+    }
+}
+
+pub struct PooledConnection {
+    connection: Option<Connection>,
+    pool: ConnectionPool,
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.connection
+            .as_ref()
+            .expect("Pooled connection should be present until dropped")
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.connection
+            .as_mut()
+            .expect("Pooled connection should be present until dropped")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.take().filter(Connection::is_autocommit) {
+            let mut idle = self.pool.idle();
+            if idle.len() < self.pool.inner.max_idle {
+                idle.push(connection);
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Disk quota of the lapp data.
+//!
+//! The quota covers the lapp data directory and the database files. The usage is measured by walking the data
+//! directory, so the measured usage is reused for a second by the checks of the frequent writes. Once the usage
+//! exceeds the quota, `db_execute` returns the quota error and the WASI writes to the files fail with `EDQUOT`. The
+//! write in progress is not interrupted, so the usage may exceed the quota by the size of the last write.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{fs, io};
+
+use anyhow::Context as _;
+use wasmtime::{Caller, Extern, Linker, Store};
+
+use crate::lapps::{Ctx, ResourceLimitExceeded};
+
+const USAGE_TTL: Duration = Duration::from_secs(1);
+const WASI_MODULE: &str = "wasi_snapshot_preview1";
+const STDERR_FD: i32 = 2;
+const ERRNO_DQUOT: i32 = 19;
+
+#[derive(Clone)]
+pub struct DiskQuota {
+    disk_quota: u64,
+    data_dir: PathBuf,
+    database_path: PathBuf,
+    measured_usage: Arc<Mutex<Option<(Instant, u64)>>>,
+}
+
+impl DiskQuota {
+    pub fn new(disk_quota_mb: u64, data_dir: impl Into<PathBuf>, database_path: impl Into<PathBuf>) -> Self {
+        Self {
+            disk_quota: disk_quota_mb.saturating_mul(1 << 20),
+            data_dir: data_dir.into(),
+            database_path: database_path.into(),
+            measured_usage: Default::default(),
+        }
+    }
+
+    /// Checks the usage measured at most a second ago against the quota.
+    pub fn check(&self) -> Result<(), ResourceLimitExceeded> {
+        let mut measured_usage = self
+            .measured_usage
+            .lock()
+            .expect("Disk usage lock should not be poisoned");
+        let disk_usage = match *measured_usage {
+            Some((measured_at, disk_usage)) if measured_at.elapsed() < USAGE_TTL => disk_usage,
+            _ => {
+                let disk_usage = disk_usage(&self.data_dir, &self.database_path);
+                *measured_usage = Some((Instant::now(), disk_usage));
+                disk_usage
+            },
+        };
+
+        if disk_usage > self.disk_quota {
+            Err(ResourceLimitExceeded::Disk {
+                disk_quota: self.disk_quota,
+                disk_usage,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The size in bytes of the lapp data directory and database files.
+pub fn disk_usage(data_dir: &Path, database_path: &Path) -> u64 {
+    let database_size: u64 = ["", "-wal", "-shm", "-journal"]
+        .into_iter()
+        .filter_map(|suffix| fs::metadata(format!("{}{suffix}", database_path.display())).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+
+    // The database may be configured inside the data directory, it is counted once
+    let data_size = dir_size(data_dir, database_path).unwrap_or_default();
+    database_size + data_size
+}
+
+fn dir_size(dir: &Path, database_path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+
+        if metadata.is_dir() {
+            size += dir_size(&path, database_path)?;
+        } else if !path
+            .as_os_str()
+            .to_string_lossy()
+            .starts_with(&*database_path.to_string_lossy())
+        {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
+
+/// Shadows the WASI `fd_write` of the linker, so the writes to the files of the instance with the exceeded disk quota
+/// fail with `EDQUOT`. The writes to stdout and stderr are not limited.
+pub fn limit_fd_write(linker: &mut Linker<Ctx>, store: &mut Store<Ctx>) -> anyhow::Result<()> {
+    let fd_write = linker
+        .get(&mut *store, WASI_MODULE, "fd_write")
+        .and_then(Extern::into_func)
+        .context("WASI fd_write is not defined")?
+        .typed::<(i32, i32, i32, i32), i32>(&*store)?;
+
+    linker.allow_shadowing(true);
+    linker.func_wrap4_async(
+        WASI_MODULE,
+        "fd_write",
+        move |mut caller: Caller<'_, Ctx>, fd: i32, iovs: i32, iovs_len: i32, nwritten: i32| {
+            Box::new(async move {
+                let is_exceeded = fd > STDERR_FD
+                    && caller
+                        .data()
+                        .disk_quota
+                        .as_ref()
+                        .is_some_and(|disk_quota| disk_quota.check().is_err());
+                if is_exceeded {
+                    return Ok(ERRNO_DQUOT);
+                }
+                fd_write.call_async(&mut caller, (fd, iovs, iovs_len, nwritten)).await
+            })
+        },
+    )?;
+    linker.allow_shadowing(false);
+    Ok(())
+}
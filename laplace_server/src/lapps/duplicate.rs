@@ -0,0 +1,98 @@
+//! Duplication of the installed lapp under a new name.
+//!
+//! The duplicate gets the lapp files with the server module renamed after the new name and the lapp settings with a
+//! new access token. The data directory and the database are not copied, so the duplicate starts with the empty data.
+//! The data paths configured outside the lapp directory are moved into the duplicate directory, so the instances do
+//! not share the data. The files regenerated by the server, the package signature and the versions kept by the
+//! upgrade are not copied.
+
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use crate::auth::generate_token;
+use crate::error::{ServerError, ServerResult};
+use crate::lapps::settings::FileSettings;
+use crate::lapps::{is_regenerated_file, Lapp, LappSettings, EXCLUDED_DIRS, MANIFEST_FILE, SIGNATURE_FILE};
+
+/// Copies the lapp files to the directory of the new lapp and returns the settings of the duplicate.
+pub fn duplicate_lapp_dir(
+    lapp_name: &str,
+    lapp_dir: &Path,
+    settings: &LappSettings,
+    new_lapp_name: &str,
+    new_lapp_dir: &Path,
+) -> ServerResult<LappSettings> {
+    if new_lapp_dir.exists() {
+        return Err(ServerError::LappAlreadyExists(new_lapp_name.into()));
+    }
+
+    let database_path = Lapp::database_path(lapp_dir, settings);
+    let excluded: Vec<PathBuf> = ["", "-wal", "-shm", "-journal"]
+        .into_iter()
+        .map(|suffix| PathBuf::from(format!("{}{suffix}", database_path.display())))
+        .chain([Lapp::data_dir_path(lapp_dir, settings)])
+        .chain(EXCLUDED_DIRS.iter().map(|dir| lapp_dir.join(dir)))
+        .chain([MANIFEST_FILE, SIGNATURE_FILE].map(|file| lapp_dir.join(file)))
+        .collect();
+    let is_excluded = |path: &Path| excluded.iter().any(|excluded| excluded == path) || is_regenerated_file(path);
+
+    let mut new_settings = settings.clone();
+    new_settings.lapp_name = new_lapp_name.into();
+    new_settings.application.access_token = Some(generate_token().map_err(|err| io::Error::other(err.to_string()))?);
+    if let Some(data_dir) = relative_data_path(&new_settings.application.data_dir) {
+        new_settings.application.data_dir = data_dir;
+    }
+    if let Some(database) = new_settings.database.as_mut() {
+        if let Some(path) = database.path.as_deref().and_then(relative_data_path) {
+            database.path = Some(path);
+        }
+    }
+
+    let result = copy_dir(lapp_dir, new_lapp_dir, &is_excluded)
+        .and_then(|()| rename_server_module(new_lapp_dir, lapp_name, new_lapp_name))
+        .map_err(ServerError::from)
+        .and_then(|()| Ok(new_settings.save(Lapp::settings_path(new_lapp_dir))?));
+    if result.is_err() {
+        fs::remove_dir_all(new_lapp_dir).ok();
+    }
+
+    result.map(|()| new_settings)
+}
+
+fn rename_server_module(lapp_dir: &Path, lapp_name: &str, new_lapp_name: &str) -> io::Result<()> {
+    let server_module = lapp_dir.join(format!("{lapp_name}_server.wasm"));
+    if server_module.is_file() {
+        fs::rename(server_module, lapp_dir.join(format!("{new_lapp_name}_server.wasm")))
+    } else {
+        Ok(())
+    }
+}
+
+/// The path inside the lapp directory for the data path configured outside it.
+fn relative_data_path(path: &Path) -> Option<PathBuf> {
+    if path.is_absolute() {
+        path.file_name().map(PathBuf::from)
+    } else {
+        None
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path, is_excluded: &dyn Fn(&Path) -> bool) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let path = entry?.path();
+        if is_excluded(&path) {
+            continue;
+        }
+        let Some(file_name) = path.file_name() else {
+            continue;
+        };
+
+        if path.is_dir() {
+            copy_dir(&path, &to.join(file_name), is_excluded)?;
+        } else {
+            fs::copy(&path, to.join(file_name))?;
+        }
+    }
+    Ok(())
+}
@@ -1,18 +1,36 @@
-use std::io;
 use std::ops::Deref;
 use std::string::FromUtf8Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{fmt, io};
 
 use borsh::BorshDeserialize;
+use laplace_wasm::health::HealthResult;
+use laplace_wasm::jobs::JobResult;
 use laplace_wasm::route::{gossipsub, websocket, Route};
-use laplace_wasm::{http, WasmSlice};
+use laplace_wasm::schedule::ScheduleResult;
+use laplace_wasm::{graphql, http, mqtt, WasmSlice};
 use thiserror::Error;
-use wasmtime::{Instance, Store};
+use wasmtime::{Instance, ResourceLimiter, Store, TypedFunc, UpdateDeadline, WasmBacktrace, WasmParams, WasmResults};
 use wasmtime_wasi::preview2::preview1::{WasiPreview1Adapter, WasiPreview1View};
 use wasmtime_wasi::preview2::{Table, WasiCtx, WasiView};
 
+use crate::lapps::wasm_interop::blob::BlobsCtx;
 use crate::lapps::wasm_interop::database::DatabaseCtx;
-use crate::lapps::wasm_interop::http::HttpCtx;
+use crate::lapps::wasm_interop::http::{HttpBodyCtx, HttpCtx};
+use crate::lapps::wasm_interop::jobs::JobsCtx;
+use crate::lapps::wasm_interop::lapps::LappsCtx;
+use crate::lapps::wasm_interop::logging::LogCtx;
+use crate::lapps::wasm_interop::mail::MailCtx;
+use crate::lapps::wasm_interop::mqtt::MqttCtx;
+use crate::lapps::wasm_interop::permission::PermissionCtx;
+use crate::lapps::wasm_interop::template::TemplateCtx;
+use crate::lapps::wasm_interop::threads::ThreadsCtx;
+use crate::lapps::wasm_interop::timer::TimersCtx;
+use crate::lapps::wasm_interop::ws_client::WsClientCtx;
 use crate::lapps::wasm_interop::{MemoryManagementError, MemoryManagementHostData};
+use crate::lapps::{DiskQuota, Trace, TraceEvent};
+use crate::service::websocket::WsClients;
 
 #[derive(Debug, Error)]
 pub enum LappInstanceError {
@@ -22,6 +40,9 @@ pub enum LappInstanceError {
     #[error("Wasm error: {0}")]
     WasmError(#[from] anyhow::Error),
 
+    #[error("Wasm trap: {trap}{}", display_backtrace(.backtrace))]
+    WasmTrap { trap: String, backtrace: Option<String> },
+
     #[error("Can't deserialize string: {0:?}")]
     DeserializeStringError(#[from] FromUtf8Error),
 
@@ -30,36 +51,199 @@ pub enum LappInstanceError {
 
     #[error("Wrong memory operation: {0}")]
     MemoryManagementError(#[from] MemoryManagementError),
+
+    #[error("{0}")]
+    ResourceLimit(#[from] ResourceLimitExceeded),
+}
+
+impl LappInstanceError {
+    /// Decodes the wasm backtrace of the failed wasm function call.
+    pub fn from_call(err: anyhow::Error) -> Self {
+        if let Some(exceeded) = err.downcast_ref::<ResourceLimitExceeded>() {
+            return Self::ResourceLimit(exceeded.clone());
+        }
+
+        match err.downcast_ref::<WasmBacktrace>() {
+            Some(backtrace) => Self::WasmTrap {
+                trap: err.root_cause().to_string(),
+                backtrace: Some(backtrace.to_string()),
+            },
+            None => Self::WasmError(err),
+        }
+    }
+}
+
+fn display_backtrace(backtrace: &Option<String>) -> String {
+    backtrace
+        .as_ref()
+        .map(|backtrace| format!("\n{backtrace}"))
+        .unwrap_or_default()
 }
 
 pub type LappInstanceResult<T> = Result<T, LappInstanceError>;
 
+/// The resource limit exceeded by the lapp instance.
+#[derive(Debug, Clone)]
+pub enum ResourceLimitExceeded {
+    /// The instance tried to grow its memory beyond the limit.
+    Memory { max_memory: usize, desired_memory: usize },
+
+    /// The lapp call is not finished within the time limit.
+    CallTime { time_limit: Duration },
+
+    /// The HTTP request is not processed within the timeout.
+    HttpTimeout { timeout: Duration },
+
+    /// The lapp data directory and database exceed the disk quota.
+    Disk { disk_quota: u64, disk_usage: u64 },
+}
+
+impl fmt::Display for ResourceLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Memory {
+                max_memory,
+                desired_memory,
+            } => write!(
+                f,
+                "memory limit of {} MiB is exceeded, {} MiB is requested",
+                max_memory >> 20,
+                desired_memory.div_ceil(1 << 20)
+            ),
+            Self::CallTime { time_limit } => write!(f, "call time limit of {} ms is exceeded", time_limit.as_millis()),
+            Self::HttpTimeout { timeout } => write!(f, "HTTP timeout of {} ms is exceeded", timeout.as_millis()),
+            Self::Disk { disk_quota, disk_usage } => write!(
+                f,
+                "disk quota of {} MiB is exceeded, {} MiB is used",
+                disk_quota >> 20,
+                disk_usage.div_ceil(1 << 20)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ResourceLimitExceeded {}
+
+/// Limits the linear memory of the lapp instance. Growing beyond the limit traps the instance instead of
+/// returning the failure to the lapp, so the request fails with the resource limit error.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryLimiter {
+    max_memory: Option<usize>,
+}
+
+impl MemoryLimiter {
+    pub fn new(max_memory_mb: Option<u64>) -> Self {
+        Self {
+            max_memory: max_memory_mb
+                .map(|max_memory_mb| usize::try_from(max_memory_mb.saturating_mul(1 << 20)).unwrap_or(usize::MAX)),
+        }
+    }
+}
+
+impl ResourceLimiter for MemoryLimiter {
+    fn memory_growing(&mut self, _current: usize, desired: usize, maximum: Option<usize>) -> anyhow::Result<bool> {
+        if let Some(max_memory) = self.max_memory.filter(|&max_memory| desired > max_memory) {
+            return Err(ResourceLimitExceeded::Memory {
+                max_memory,
+                desired_memory: desired,
+            }
+            .into());
+        }
+        Ok(maximum.is_none_or(|maximum| desired <= maximum))
+    }
+
+    fn table_growing(&mut self, _current: u32, desired: u32, maximum: Option<u32>) -> anyhow::Result<bool> {
+        Ok(maximum.is_none_or(|maximum| desired <= maximum))
+    }
+}
+
 pub struct LappInstance {
     pub instance: Instance,
     pub memory_management: MemoryManagementHostData,
     pub store: Store<Ctx>,
+
+    /// The call of the instance trapped since the last check, so its state may be inconsistent.
+    pub is_failed: bool,
 }
 
 impl LappInstance {
-    pub async fn process_http(&mut self, request: http::Request) -> LappInstanceResult<http::Response> {
+    /// Calls the lapp function within the call time limit of the instance.
+    async fn call<Params, Results>(
+        &mut self,
+        func: TypedFunc<Params, Results>,
+        params: Params,
+    ) -> LappInstanceResult<Results>
+    where
+        Params: WasmParams + Send + Sync,
+        Results: WasmResults + Send + Sync,
+    {
+        self.call_within(func, params, Ctx::start_call).await
+    }
+
+    /// Calls the lapp function within the deadline set by `start_call`.
+    async fn call_within<Params, Results>(
+        &mut self,
+        func: TypedFunc<Params, Results>,
+        params: Params,
+        start_call: fn(&mut Ctx),
+    ) -> LappInstanceResult<Results>
+    where
+        Params: WasmParams + Send + Sync,
+        Results: WasmResults + Send + Sync,
+    {
+        start_call(self.store.data_mut());
+        let result = func.call_async(&mut self.store, params).await;
+        self.store.data_mut().finish_call();
+        if let Some(database_ctx) = self.store.data().database.as_ref() {
+            database_ctx.rollback_unfinished().await;
+        }
+
+        result.map_err(|err| {
+            self.is_failed = true;
+            LappInstanceError::from_call(err)
+        })
+    }
+
+    /// Processes the request by the `process_http` export. The streamed request body is kept on the host and the
+    /// written response frames are appended to the returned response body.
+    pub async fn process_http(&mut self, mut request: http::Request) -> LappInstanceResult<http::Response> {
         let process_http_fn = self
             .instance
             .get_typed_func::<u64, u64>(&mut self.store, "process_http")?;
 
+        self.store.data_mut().record(TraceEvent::Http(borsh::to_vec(&request)?));
+        self.store.data_mut().http_body.start_request(&mut request);
         let bytes = borsh::to_vec(&request)?;
         let arg = self.bytes_to_wasm_slice(&bytes).await?;
 
-        let slice = process_http_fn.call_async(&mut self.store, arg.into()).await?;
-        let bytes = self.wasm_slice_to_vec(slice).await?;
-
-        Ok(BorshDeserialize::deserialize(&mut bytes.as_slice())?)
+        self.store.data_mut().request_id = request.request_id().map(Into::into);
+        let call_result = self
+            .call_within(process_http_fn, arg.into(), Ctx::start_http_call)
+            .await;
+        self.store.data_mut().request_id = None;
+        let response_frames = self.store.data_mut().http_body.finish_request();
+        let bytes = self.wasm_slice_to_vec(call_result?).await?;
+
+        let mut response: http::Response = BorshDeserialize::deserialize(&mut bytes.as_slice())?;
+        response.body.extend(response_frames);
+        Ok(response)
     }
 
-    pub async fn route_ws(&mut self, msg: &websocket::MessageIn) -> LappInstanceResult<Vec<Route>> {
+    /// Routes the message of the WebSocket client, the lapp gets the client id by the `ws_current_client` host call.
+    pub async fn route_ws(
+        &mut self,
+        client_id: Option<u64>,
+        msg: &websocket::MessageIn,
+    ) -> LappInstanceResult<Vec<Route>> {
         let route_ws_fn = self.instance.get_typed_func::<u64, u64>(&mut self.store, "route_ws")?;
-        let arg = self.bytes_to_wasm_slice(&borsh::to_vec(&msg)?).await?;
+        let bytes = borsh::to_vec(&msg)?;
+        self.store.data_mut().record(TraceEvent::Ws(bytes.clone()));
+        let arg = self.bytes_to_wasm_slice(&bytes).await?;
 
-        let response_slice = route_ws_fn.call_async(&mut self.store, arg.into()).await?;
+        self.store.data_mut().ws_current_client = client_id;
+        let response_slice = self.call(route_ws_fn, arg.into()).await;
+        self.store.data_mut().ws_current_client = None;
+        let response_slice = response_slice?;
         let bytes = self.wasm_slice_to_vec(response_slice).await?;
 
         Ok(BorshDeserialize::try_from_slice(&bytes)?)
@@ -69,14 +253,142 @@ impl LappInstance {
         let route_gossipsub = self
             .instance
             .get_typed_func::<u64, u64>(&mut self.store, "route_gossipsub")?;
-        let arg = self.bytes_to_wasm_slice(&borsh::to_vec(&msg)?).await?;
+        let bytes = borsh::to_vec(&msg)?;
+        self.store.data_mut().record(TraceEvent::Gossipsub(bytes.clone()));
+        let arg = self.bytes_to_wasm_slice(&bytes).await?;
 
-        let response_slice = route_gossipsub.call_async(&mut self.store, arg.into()).await?;
+        let response_slice = self.call(route_gossipsub, arg.into()).await?;
         let bytes = self.wasm_slice_to_vec(response_slice).await?;
 
         Ok(BorshDeserialize::try_from_slice(&bytes)?)
     }
 
+    pub async fn handle_mqtt(&mut self, msg: &mqtt::Message) -> LappInstanceResult<Vec<Route>> {
+        let handle_mqtt_fn = self
+            .instance
+            .get_typed_func::<u64, u64>(&mut self.store, "handle_mqtt")?;
+        let bytes = borsh::to_vec(msg)?;
+        self.store.data_mut().record(TraceEvent::Mqtt(bytes.clone()));
+        let arg = self.bytes_to_wasm_slice(&bytes).await?;
+
+        let response_slice = self.call(handle_mqtt_fn, arg.into()).await?;
+        let bytes = self.wasm_slice_to_vec(response_slice).await?;
+
+        Ok(BorshDeserialize::try_from_slice(&bytes)?)
+    }
+
+    /// Returns the error page rendered by the lapp, `None` if the module does not export `error_page`.
+    pub async fn error_page(&mut self, request: &http::ErrorPageRequest) -> LappInstanceResult<Option<String>> {
+        let Ok(error_page_fn) = self.instance.get_typed_func::<u64, u64>(&mut self.store, "error_page") else {
+            return Ok(None);
+        };
+        let bytes = borsh::to_vec(request)?;
+        self.store.data_mut().record(TraceEvent::ErrorPage(bytes.clone()));
+        let arg = self.bytes_to_wasm_slice(&bytes).await?;
+
+        let slice = self.call(error_page_fn, arg.into()).await?;
+        let bytes = self.wasm_slice_to_vec(slice).await?;
+
+        Ok(BorshDeserialize::try_from_slice(&bytes)?)
+    }
+
+    /// Returns the SDL of the lapp GraphQL schema fragment, `None` if the module does not export it.
+    pub async fn graphql_schema(&mut self) -> LappInstanceResult<Option<String>> {
+        let Ok(graphql_schema_fn) = self
+            .instance
+            .get_typed_func::<(), u64>(&mut self.store, "graphql_schema")
+        else {
+            return Ok(None);
+        };
+
+        let slice = self.call(graphql_schema_fn, ()).await?;
+        Ok(Some(self.wasm_slice_to_string(slice).await?))
+    }
+
+    pub async fn graphql_resolve(&mut self, request: &graphql::Request) -> LappInstanceResult<String> {
+        let graphql_resolve_fn = self
+            .instance
+            .get_typed_func::<u64, u64>(&mut self.store, "graphql_resolve")?;
+        let bytes = borsh::to_vec(request)?;
+        self.store.data_mut().record(TraceEvent::Graphql(bytes.clone()));
+        let arg = self.bytes_to_wasm_slice(&bytes).await?;
+
+        let slice = self.call(graphql_resolve_fn, arg.into()).await?;
+        self.wasm_slice_to_string(slice).await
+    }
+
+    /// Runs the scheduled job by the `on_schedule` export, `None` if the module does not export it.
+    pub async fn on_schedule(&mut self, job_name: &str) -> LappInstanceResult<Option<ScheduleResult>> {
+        let Ok(on_schedule_fn) = self.instance.get_typed_func::<u64, u64>(&mut self.store, "on_schedule") else {
+            return Ok(None);
+        };
+        self.store
+            .data_mut()
+            .record(TraceEvent::Schedule(job_name.as_bytes().to_vec()));
+        let arg = self.bytes_to_wasm_slice(job_name).await?;
+
+        let slice = self.call(on_schedule_fn, arg.into()).await?;
+        let bytes = self.wasm_slice_to_vec(slice).await?;
+
+        Ok(Some(BorshDeserialize::try_from_slice(&bytes)?))
+    }
+
+    /// Passes the message of the bus to the `on_lapp_message` export, `false` if the module does not export it.
+    pub async fn on_lapp_message(&mut self, sender: &str, payload: &[u8]) -> LappInstanceResult<bool> {
+        let Ok(on_lapp_message_fn) = self
+            .instance
+            .get_typed_func::<(u64, u64), ()>(&mut self.store, "on_lapp_message")
+        else {
+            return Ok(false);
+        };
+        self.store.data_mut().record(TraceEvent::LappMessage {
+            sender: sender.into(),
+            payload: payload.into(),
+        });
+        let sender_arg = self.bytes_to_wasm_slice(sender).await?;
+        let payload_arg = self.bytes_to_wasm_slice(payload).await?;
+
+        self.call(on_lapp_message_fn, (sender_arg.into(), payload_arg.into()))
+            .await?;
+        Ok(true)
+    }
+
+    /// Runs the background job by the `on_job` export, `None` if the module does not export it.
+    pub async fn on_job(&mut self, payload: &[u8]) -> LappInstanceResult<Option<JobResult>> {
+        let Ok(on_job_fn) = self.instance.get_typed_func::<u64, u64>(&mut self.store, "on_job") else {
+            return Ok(None);
+        };
+        self.store.data_mut().record(TraceEvent::Job(payload.to_vec()));
+        let arg = self.bytes_to_wasm_slice(payload).await?;
+
+        let slice = self.call(on_job_fn, arg.into()).await?;
+        let bytes = self.wasm_slice_to_vec(slice).await?;
+
+        Ok(Some(BorshDeserialize::try_from_slice(&bytes)?))
+    }
+
+    /// Calls the `on_timer` export with the id of the fired timer, nothing is called if the module does not export it.
+    pub async fn on_timer(&mut self, id: u64) -> LappInstanceResult<()> {
+        let Ok(on_timer_fn) = self.instance.get_typed_func::<u64, ()>(&mut self.store, "on_timer") else {
+            return Ok(());
+        };
+        self.store.data_mut().record(TraceEvent::Timer(id));
+
+        self.call(on_timer_fn, id).await
+    }
+
+    /// Runs the smoke test by the `health` export, `None` if the module does not export it.
+    pub async fn health(&mut self) -> LappInstanceResult<Option<HealthResult>> {
+        let Ok(health_fn) = self.instance.get_typed_func::<(), u64>(&mut self.store, "health") else {
+            return Ok(None);
+        };
+
+        let slice = self.call(health_fn, ()).await?;
+        let bytes = self.wasm_slice_to_vec(slice).await?;
+
+        Ok(Some(BorshDeserialize::try_from_slice(&bytes)?))
+    }
+
     pub async fn copy_to_memory(&mut self, src_bytes: &[u8]) -> LappInstanceResult<u32> {
         Ok(self
             .memory_management
@@ -133,6 +445,30 @@ pub struct Ctx {
     pub memory_data: Option<MemoryManagementHostData>,
     pub database: Option<DatabaseCtx>,
     pub http: Option<HttpCtx>,
+    pub http_body: HttpBodyCtx,
+    pub template: Option<TemplateCtx>,
+    pub mqtt: Option<MqttCtx>,
+    pub mail: Option<MailCtx>,
+    pub blobs: Option<BlobsCtx>,
+    pub ws_push: Option<WsClients>,
+    pub ws_current_client: Option<u64>,
+
+    /// The id of the HTTP request being processed, it is attached to the lapp log records.
+    pub request_id: Option<String>,
+    pub ws_client: Option<WsClientCtx>,
+    pub permission: Option<PermissionCtx>,
+    pub lapps: Option<LappsCtx>,
+    pub jobs: Option<JobsCtx>,
+    pub log: Option<LogCtx>,
+    pub settings_json: Option<String>,
+    pub threads: Option<Arc<ThreadsCtx>>,
+    pub timers: Option<TimersCtx>,
+    pub trace: Option<Trace>,
+    pub limiter: MemoryLimiter,
+    pub call_time_limit: Option<Duration>,
+    pub http_timeout: Option<Duration>,
+    pub disk_quota: Option<DiskQuota>,
+    call_deadline: Option<(Instant, ResourceLimitExceeded)>,
 }
 
 impl Ctx {
@@ -144,9 +480,69 @@ impl Ctx {
             memory_data: None,
             database: None,
             http: None,
+            http_body: HttpBodyCtx::default(),
+            template: None,
+            mqtt: None,
+            mail: None,
+            blobs: None,
+            ws_push: None,
+            ws_current_client: None,
+            request_id: None,
+            ws_client: None,
+            permission: None,
+            lapps: None,
+            jobs: None,
+            log: None,
+            settings_json: None,
+            threads: None,
+            timers: None,
+            trace: None,
+            limiter: MemoryLimiter::default(),
+            call_time_limit: None,
+            http_timeout: None,
+            disk_quota: None,
+            call_deadline: None,
+        }
+    }
+
+    /// Interrupts the lapp call running after its deadline. The store should be created by the engine with the
+    /// epoch interruption, the deadline is checked on every epoch tick.
+    pub fn interrupt_on_deadline(store: &mut Store<Self>) {
+        store.set_epoch_deadline(1);
+        store.epoch_deadline_callback(|store| match &store.data().call_deadline {
+            Some((deadline, exceeded)) if Instant::now() >= *deadline => Err(exceeded.clone().into()),
+            _ => Ok(UpdateDeadline::Continue(1)),
+        });
+    }
+
+    /// Starts the time limit of the lapp call.
+    pub fn start_call(&mut self) {
+        self.call_deadline = self.call_time_limit.map(|time_limit| {
+            (Instant::now() + time_limit, ResourceLimitExceeded::CallTime {
+                time_limit,
+            })
+        });
+    }
+
+    /// Starts the time limit of the HTTP request processing, the earlier of the call time limit and the HTTP timeout.
+    pub fn start_http_call(&mut self) {
+        self.start_call();
+        if let Some(timeout) = self.http_timeout {
+            let deadline = Instant::now() + timeout;
+            if self
+                .call_deadline
+                .as_ref()
+                .map_or(true, |(call_deadline, _)| deadline < *call_deadline)
+            {
+                self.call_deadline = Some((deadline, ResourceLimitExceeded::HttpTimeout { timeout }));
+            }
         }
     }
 
+    pub fn finish_call(&mut self) {
+        self.call_deadline = None;
+    }
+
     pub fn memory_data(&self) -> &MemoryManagementHostData {
         self.memory_data.as_ref().expect("Memory data is empty")
     }
@@ -0,0 +1,148 @@
+//! Background jobs of the lapps.
+//!
+//! The jobs enqueued by the `job_enqueue` host call are kept in the `_laplace_jobs` table of the lapp database, so they
+//! survive the server restarts. The lapp service passes the due jobs to the `on_job` export of the main instance and
+//! deletes the job only after the export succeeds, the failed job is retried with the backoff. So every job is
+//! delivered at least once and the lapp handler should be idempotent.
+
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+
+pub const JOBS_TABLE: &str = "_laplace_jobs";
+
+/// The number of the job runs after which the failing job is dropped.
+const MAX_ATTEMPTS: u32 = 10;
+
+/// The maximum delay before the retry of the failed job.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub struct Job {
+    pub id: i64,
+    pub payload: Vec<u8>,
+    pub attempts: u32,
+}
+
+pub struct JobQueue {
+    connection: Connection,
+}
+
+impl JobQueue {
+    /// Opens the queue in the lapp database, the database and the jobs table are created by the enqueueing side only.
+    /// Returns `None` if the lapp has not enqueued jobs yet.
+    pub fn open(database_path: impl AsRef<Path>, create: bool) -> rusqlite::Result<Option<Self>> {
+        let database_path = database_path.as_ref();
+        if !create && !database_path.exists() {
+            return Ok(None);
+        }
+
+        let connection = if create {
+            Connection::open(database_path)?
+        } else {
+            Connection::open_with_flags(
+                database_path,
+                OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+            )?
+        };
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+
+        if create {
+            connection.execute(
+                &format!(
+                    "CREATE TABLE IF NOT EXISTS {JOBS_TABLE} (
+                        id INTEGER PRIMARY KEY AUTOINCREMENT,
+                        payload BLOB NOT NULL,
+                        attempts INTEGER NOT NULL DEFAULT 0,
+                        run_at INTEGER NOT NULL
+                    )"
+                ),
+                [],
+            )?;
+        } else {
+            let is_table_exists = connection
+                .query_row(
+                    "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                    [JOBS_TABLE],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if !is_table_exists {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(Self { connection }))
+    }
+
+    /// Adds the job due immediately, returns its id.
+    pub fn push(&self, payload: &[u8]) -> rusqlite::Result<i64> {
+        self.connection.execute(
+            &format!("INSERT INTO {JOBS_TABLE} (payload, run_at) VALUES (?1, ?2)"),
+            params![payload, now_millis()],
+        )?;
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    /// Returns the earliest due job.
+    pub fn next_due(&self) -> rusqlite::Result<Option<Job>> {
+        self.connection
+            .query_row(
+                &format!(
+                    "SELECT id, payload, attempts FROM {JOBS_TABLE} WHERE run_at <= ?1 ORDER BY run_at, id LIMIT 1"
+                ),
+                [now_millis()],
+                |row| {
+                    Ok(Job {
+                        id: row.get(0)?,
+                        payload: row.get(1)?,
+                        attempts: row.get(2)?,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    /// Returns the time until the next postponed job is due.
+    pub fn next_delay(&self) -> rusqlite::Result<Option<Duration>> {
+        let run_at: Option<i64> =
+            self.connection
+                .query_row(&format!("SELECT MIN(run_at) FROM {JOBS_TABLE}"), [], |row| row.get(0))?;
+        Ok(run_at.map(|run_at| Duration::from_millis(run_at.saturating_sub(now_millis()).max(0) as u64)))
+    }
+
+    pub fn complete(&self, job: &Job) -> rusqlite::Result<()> {
+        self.connection
+            .execute(&format!("DELETE FROM {JOBS_TABLE} WHERE id = ?1"), [job.id])?;
+        Ok(())
+    }
+
+    /// Postpones the failed job with the exponential backoff, the job is dropped after the maximum attempts.
+    /// Returns `false` if the job is dropped.
+    pub fn fail(&self, job: &Job) -> rusqlite::Result<bool> {
+        let attempts = job.attempts + 1;
+        if attempts >= MAX_ATTEMPTS {
+            self.complete(job)?;
+            return Ok(false);
+        }
+
+        let backoff = Duration::from_secs(1)
+            .saturating_mul(2_u32.saturating_pow(attempts))
+            .min(MAX_RETRY_BACKOFF);
+        self.connection.execute(
+            &format!("UPDATE {JOBS_TABLE} SET attempts = ?1, run_at = ?2 WHERE id = ?3"),
+            params![attempts, now_millis() + backoff.as_millis() as i64, job.id],
+        )?;
+        Ok(true)
+    }
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as i64)
+}
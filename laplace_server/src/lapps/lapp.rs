@@ -1,26 +1,58 @@
 use std::fs;
 use std::ops::Deref;
-use std::path::{Path, PathBuf};
+use std::path::{self, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 use borsh::BorshDeserialize;
 use cap_std::fs::Dir;
 use derive_more::{Deref, DerefMut};
 pub use laplace_common::api::{UpdateQuery, UpdateRequest as LappUpdateRequest};
 pub use laplace_common::lapp::access::*;
-use laplace_wasm::http::{Request, Response};
+use laplace_common::lapp::{EntryPoint, LappManifest, ManifestError, MountSettings};
+use laplace_wasm::graphql;
+use laplace_wasm::health::HealthResult;
+use laplace_wasm::http::{ErrorPageRequest, Request, Response};
+use laplace_wasm::jobs::JobResult;
+use laplace_wasm::schedule::ScheduleResult;
 use reqwest::Client;
-use rusqlite::Connection;
+use rumqttc::AsyncClient;
 use serde::{Serialize, Serializer};
-use wasmtime::{Config, Engine, Linker, Module, Store};
+use tokio::sync::{mpsc, Notify};
+use wasmtime::component::Component;
+use wasmtime::{Config, Engine, ExternType, Linker, Module, SharedMemory, Store};
 use wasmtime_wasi::preview2::preview1::add_to_linker_async;
-use wasmtime_wasi::preview2::{DirPerms, FilePerms, Table, WasiCtxBuilder};
+use wasmtime_wasi::preview2::{DirPerms, FilePerms, Table, WasiCtx, WasiCtxBuilder};
 
 use crate::error::{ServerError, ServerResult};
+use crate::lapp_calls::LappCalls;
 use crate::lapps::settings::{FileSettings, LappSettings, LappSettingsResult};
+use crate::lapps::wasm_interop::blob::BlobsCtx;
 use crate::lapps::wasm_interop::database::DatabaseCtx;
-use crate::lapps::wasm_interop::http::HttpCtx;
-use crate::lapps::wasm_interop::{database, http, sleep, MemoryManagementHostData};
-use crate::lapps::{Ctx, LappInstance, LappInstanceError};
+use crate::lapps::wasm_interop::http::{HttpBodyCtx, HttpCtx};
+use crate::lapps::wasm_interop::jobs::JobsCtx;
+use crate::lapps::wasm_interop::lapps::LappsCtx;
+use crate::lapps::wasm_interop::logging::LogCtx;
+use crate::lapps::wasm_interop::mail::MailCtx;
+use crate::lapps::wasm_interop::mqtt::MqttCtx;
+use crate::lapps::wasm_interop::permission::PermissionCtx;
+use crate::lapps::wasm_interop::template::TemplateCtx;
+use crate::lapps::wasm_interop::threads::{ThreadPool, ThreadsCtx};
+use crate::lapps::wasm_interop::timer::{TimersCtx, MAX_TIMERS};
+use crate::lapps::wasm_interop::ws_client::WsClientCtx;
+use crate::lapps::wasm_interop::{
+    blob, crypto, database, http, jobs, lapp_settings, lapps, logging, mail, mqtt, permission, sleep, template,
+    threads, time, timer, ws_client, ws_push, MemoryManagementHostData,
+};
+use crate::lapps::{
+    apply_migrations, create_fts_tables, is_component, limit_fd_write, ComponentInstance, ConnectionPool, Ctx,
+    DiskQuota, InstancePool, InstanceSnapshot, LappInstance, LappInstanceError, LappLogs, MemoryLimiter, ModuleCache,
+    ModuleCacheKey, PackageVerifier, PooledConnection, Trace, MIGRATIONS_DIR,
+};
+use crate::mail::Mailer;
+use crate::permission_requests::PermissionRequests;
+use crate::service::bus::LappBus;
+use crate::service::websocket::WsClients;
 
 lazy_static::lazy_static! {
     static ref ENGINE: Engine = {
@@ -28,11 +60,26 @@ lazy_static::lazy_static! {
         config.wasm_backtrace_details(wasmtime::WasmBacktraceDetails::Enable);
         config.wasm_component_model(true);
         config.async_support(true);
+        config.wasm_threads(true);
+        config.epoch_interruption(true);
 
-        Engine::new(&config).expect("Failed create engine")
+        let engine = Engine::new(&config).expect("Failed create engine");
+        let ticker = engine.clone();
+        std::thread::Builder::new()
+            .name("wasm-epoch-ticker".into())
+            .spawn(move || loop {
+                std::thread::sleep(EPOCH_TICK);
+                ticker.increment_epoch();
+            })
+            .expect("Failed spawn epoch ticker");
+
+        engine
     };
 }
 
+/// The period of checking the lapp call time limits.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
 pub type CommonLapp = laplace_common::lapp::Lapp<PathBuf>;
 pub type CommonLappResponse<'a> = laplace_common::api::Response<'a, CommonLappGuard<'a>>;
 
@@ -97,6 +144,31 @@ pub struct Lapp {
     #[deref_mut]
     lapp: CommonLapp,
     instance: Option<LappInstance>,
+
+    /// The instance of the server module built as the WASM component, it replaces the core module instance.
+    component: Option<ComponentInstance>,
+    pool: InstancePool,
+    connection_pool: Option<ConnectionPool>,
+    read_only: bool,
+    debug: bool,
+    mqtt_client: Option<AsyncClient>,
+    mailer: Option<Mailer>,
+    thread_pool: ThreadPool,
+    max_memory_mb: Option<u64>,
+    call_time_limit_ms: Option<u64>,
+    http_timeout_ms: Option<u64>,
+    disk_quota_mb: Option<u64>,
+    mount_paths: Vec<PathBuf>,
+    module_cache: bool,
+    module_cache_key: Option<ModuleCacheKey>,
+    package_verifier: PackageVerifier,
+    permission_requests: Option<PermissionRequests>,
+    lapp_calls: Option<LappCalls>,
+    lapp_bus: Option<LappBus>,
+    ws_clients: WsClients,
+    lapp_logs: Option<LappLogs>,
+    timers_out: Option<mpsc::Sender<u64>>,
+    jobs_notify: Arc<Notify>,
 }
 
 impl Lapp {
@@ -104,6 +176,29 @@ impl Lapp {
         Self {
             lapp: CommonLapp::new(name.into(), root_dir.into(), settings),
             instance: None,
+            component: None,
+            pool: InstancePool::default(),
+            connection_pool: None,
+            read_only: false,
+            debug: false,
+            mqtt_client: None,
+            mailer: None,
+            thread_pool: ThreadPool::default(),
+            max_memory_mb: None,
+            call_time_limit_ms: None,
+            http_timeout_ms: None,
+            disk_quota_mb: None,
+            mount_paths: Vec::new(),
+            module_cache: false,
+            module_cache_key: None,
+            package_verifier: PackageVerifier::default(),
+            permission_requests: None,
+            lapp_calls: None,
+            lapp_bus: None,
+            ws_clients: WsClients::default(),
+            lapp_logs: None,
+            timers_out: None,
+            jobs_notify: Arc::new(Notify::new()),
         }
     }
 
@@ -119,6 +214,20 @@ impl Lapp {
         CommonLapp::index_file_name()
     }
 
+    pub const fn templates_dir_name() -> &'static str {
+        "templates"
+    }
+
+    /// The page of the static directory shown to the browser when the lapp route is not found.
+    pub const fn not_found_page_name() -> &'static str {
+        "404.html"
+    }
+
+    /// The page of the static directory shown to the browser on the server errors of the lapp routes.
+    pub const fn server_error_page_name() -> &'static str {
+        "50x.html"
+    }
+
     pub const fn main_name() -> &'static str {
         CommonLapp::main_name()
     }
@@ -151,115 +260,738 @@ impl Lapp {
         }
     }
 
+    /// Loads the lapp settings and validates the lapp manifest.
+    pub fn load_valid_settings(
+        lapp_name: impl AsRef<str>,
+        lapp_path: impl AsRef<Path>,
+    ) -> Result<LappSettings, Vec<ManifestError>> {
+        let lapp_name = lapp_name.as_ref();
+        let lapp_path = lapp_path.as_ref();
+
+        let settings = LappSettings::load(lapp_name, Self::settings_path(lapp_path))
+            .map_err(|err| vec![ManifestError::Config { error: err.to_string() }])?;
+        let entry_points = Self::entry_points(lapp_name, |path| lapp_path.join(path).is_file());
+        LappManifest::new(&settings, entry_points)?;
+
+        Ok(settings)
+    }
+
+    /// The entry points of the lapp, the existence of the file is checked by its path relative to the lapp directory.
+    pub fn entry_points(lapp_name: &str, mut is_file: impl FnMut(&str) -> bool) -> Vec<EntryPoint> {
+        let mut entry_points = Vec::new();
+        if is_file(&format!("{lapp_name}_server.wasm")) {
+            entry_points.push(EntryPoint::ServerModule);
+        }
+        if is_file(&format!("{}/{}", Self::static_dir_name(), Self::index_file_name())) {
+            entry_points.push(EntryPoint::StaticIndex);
+        }
+        entry_points
+    }
+
     pub fn save_settings(&mut self) -> LappSettingsResult<()> {
         self.settings().save(Self::settings_path(self.root_dir()))
     }
 
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn is_debug(&self) -> bool {
+        self.debug
+    }
+
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug = debug;
+    }
+
+    /// Sets the client of the MQTT bridge used by the `mqtt_publish` host call.
+    pub fn set_mqtt_client(&mut self, mqtt_client: Option<AsyncClient>) {
+        self.mqtt_client = mqtt_client;
+    }
+
+    /// Sets the SMTP mailer used by the `send_mail` host call.
+    pub fn set_mailer(&mut self, mailer: Option<Mailer>) {
+        self.mailer = mailer;
+    }
+
+    /// Sets the host threads pool shared by the threads of all lapps.
+    pub fn set_thread_pool(&mut self, thread_pool: ThreadPool) {
+        self.thread_pool = thread_pool;
+    }
+
+    /// Sets the server limit of the lapp memory, the lapp may set the lower one in the `resources` settings.
+    pub fn set_max_memory_mb(&mut self, max_memory_mb: Option<u64>) {
+        self.max_memory_mb = max_memory_mb;
+    }
+
+    /// The effective limit of the lapp memory in MiB.
+    pub fn max_memory_mb(&self) -> Option<u64> {
+        min_limit(self.settings().resources().max_memory_mb, self.max_memory_mb)
+    }
+
+    /// Sets the server time limit of the lapp calls, the lapp may set the lower one in the `resources` settings.
+    pub fn set_call_time_limit_ms(&mut self, call_time_limit_ms: Option<u64>) {
+        self.call_time_limit_ms = call_time_limit_ms;
+    }
+
+    /// The effective time limit of the lapp calls.
+    pub fn call_time_limit(&self) -> Option<Duration> {
+        min_limit(self.settings().resources().call_time_limit_ms, self.call_time_limit_ms).map(Duration::from_millis)
+    }
+
+    /// Sets the server timeout of the HTTP requests, the lapp may set the lower one in the `resources` settings.
+    pub fn set_http_timeout_ms(&mut self, http_timeout_ms: Option<u64>) {
+        self.http_timeout_ms = http_timeout_ms;
+    }
+
+    /// The effective timeout of the HTTP request processing.
+    pub fn http_timeout(&self) -> Option<Duration> {
+        min_limit(self.settings().resources().http_timeout_ms, self.http_timeout_ms).map(Duration::from_millis)
+    }
+
+    /// Sets the server disk quota of the lapp data, the lapp may set the lower one in the `resources` settings.
+    pub fn set_disk_quota_mb(&mut self, disk_quota_mb: Option<u64>) {
+        self.disk_quota_mb = disk_quota_mb;
+    }
+
+    /// The effective disk quota of the lapp data directory and database in MiB.
+    pub fn disk_quota_mb(&self) -> Option<u64> {
+        min_limit(self.settings().resources().disk_quota_mb, self.disk_quota_mb)
+    }
+
+    /// Sets the absolute host directories outside of the lapp directory that the lapp may mount.
+    pub fn set_mount_paths(&mut self, mount_paths: Vec<PathBuf>) {
+        self.mount_paths = mount_paths;
+    }
+
+    /// Sets whether the compiled server module is cached in the lapp directory.
+    pub fn set_module_cache(&mut self, module_cache: bool) {
+        self.module_cache = module_cache;
+    }
+
+    /// Sets the key of the cached module signatures, the module is compiled on each instantiation without it.
+    pub fn set_module_cache_key(&mut self, module_cache_key: Option<ModuleCacheKey>) {
+        self.module_cache_key = module_cache_key;
+    }
+
+    /// Sets the verifier of the lapp files signature checked before the instantiation.
+    pub fn set_package_verifier(&mut self, package_verifier: PackageVerifier) {
+        self.package_verifier = package_verifier;
+    }
+
+    /// Sets the queue of the permissions requested by the lapp at runtime.
+    pub fn set_permission_requests(&mut self, permission_requests: PermissionRequests) {
+        self.permission_requests = Some(permission_requests);
+    }
+
+    /// Sets the router of the lapp calls made by the `invoke_lapp` host call.
+    pub fn set_lapp_calls(&mut self, lapp_calls: LappCalls) {
+        self.lapp_calls = Some(lapp_calls);
+    }
+
+    /// Sets the bus of the messages sent by the `lapp_send` host call.
+    pub fn set_lapp_bus(&mut self, lapp_bus: LappBus) {
+        self.lapp_bus = Some(lapp_bus);
+    }
+
+    /// Sets the log streams of the records sent by the `log` host call.
+    pub fn set_lapp_logs(&mut self, lapp_logs: LappLogs) {
+        self.lapp_logs = Some(lapp_logs);
+    }
+
+    /// Creates the queue of the timers fired by the main instance, the lapp service calls `on_timer` for them.
+    pub fn timers_receiver(&mut self) -> mpsc::Receiver<u64> {
+        let (timers_out, timers_in) = mpsc::channel(MAX_TIMERS);
+        self.timers_out = Some(timers_out);
+        timers_in
+    }
+
+    /// The WebSocket sessions of the lapp clients, the lapp service keeps them and the `ws_push` host call uses them.
+    pub fn ws_clients(&self) -> WsClients {
+        self.ws_clients.clone()
+    }
+
+    /// The notification of the jobs enqueued by the instances, the lapp service runs the due jobs on it.
+    pub fn jobs_notify(&self) -> Arc<Notify> {
+        Arc::clone(&self.jobs_notify)
+    }
+
     pub fn instance_mut(&mut self) -> Option<&mut LappInstance> {
         self.instance.as_mut()
     }
 
+    /// Returns whether the instance call failed since the previous check.
+    pub fn take_instance_failure(&mut self) -> bool {
+        let is_instance_failed = self
+            .instance
+            .as_mut()
+            .is_some_and(|instance| std::mem::take(&mut instance.is_failed));
+        let is_component_failed = self
+            .component
+            .as_mut()
+            .is_some_and(|component| std::mem::take(&mut component.is_failed));
+        is_instance_failed || is_component_failed
+    }
+
     pub fn take_instance(&mut self) -> Option<LappInstance> {
+        self.pool = InstancePool::default();
         self.instance.take()
     }
 
+    /// The additional instances processing the HTTP requests in parallel with the main one.
+    pub fn pool(&self) -> &InstancePool {
+        &self.pool
+    }
+
     pub async fn process_http(&mut self, request: Request) -> ServerResult<Response> {
-        match self.instance_mut() {
-            Some(instance) => Ok(instance.process_http(request).await?),
+        if let Some(component) = self.component.as_mut() {
+            return match component.process_http(request).await {
+                Ok(response) => Ok(response),
+                Err(err) => Err(self.instance_error(err).into()),
+            };
+        }
+
+        match self.instance.as_mut() {
+            Some(instance) => match instance.process_http(request).await {
+                Ok(response) => Ok(response),
+                Err(err) => Err(self.instance_error(err).into()),
+            },
+            None => Err(ServerError::LappNotLoaded(self.name().to_string())),
+        }
+    }
+
+    pub async fn error_page(&mut self, request: ErrorPageRequest) -> ServerResult<Option<String>> {
+        match self.instance.as_mut() {
+            Some(instance) => match instance.error_page(&request).await {
+                Ok(page) => Ok(page),
+                Err(err) => Err(self.instance_error(err).into()),
+            },
+            None => Err(ServerError::LappNotLoaded(self.name().to_string())),
+        }
+    }
+
+    pub async fn graphql_schema(&mut self) -> ServerResult<Option<String>> {
+        match self.instance.as_mut() {
+            Some(instance) => match instance.graphql_schema().await {
+                Ok(schema) => Ok(schema),
+                Err(err) => Err(self.instance_error(err).into()),
+            },
+            None => Err(ServerError::LappNotLoaded(self.name().to_string())),
+        }
+    }
+
+    pub async fn graphql_resolve(&mut self, request: graphql::Request) -> ServerResult<String> {
+        match self.instance.as_mut() {
+            Some(instance) => match instance.graphql_resolve(&request).await {
+                Ok(response) => Ok(response),
+                Err(err) => Err(self.instance_error(err).into()),
+            },
+            None => Err(ServerError::LappNotLoaded(self.name().to_string())),
+        }
+    }
+
+    pub async fn on_schedule(&mut self, job_name: &str) -> ServerResult<Option<ScheduleResult>> {
+        match self.instance.as_mut() {
+            Some(instance) => match instance.on_schedule(job_name).await {
+                Ok(result) => Ok(result),
+                Err(err) => Err(self.instance_error(err).into()),
+            },
+            None => Err(ServerError::LappNotLoaded(self.name().to_string())),
+        }
+    }
+
+    pub async fn on_lapp_message(&mut self, sender: &str, payload: &[u8]) -> ServerResult<bool> {
+        match self.instance.as_mut() {
+            Some(instance) => match instance.on_lapp_message(sender, payload).await {
+                Ok(is_handled) => Ok(is_handled),
+                Err(err) => Err(self.instance_error(err).into()),
+            },
+            None => Err(ServerError::LappNotLoaded(self.name().to_string())),
+        }
+    }
+
+    pub async fn on_job(&mut self, payload: &[u8]) -> ServerResult<Option<JobResult>> {
+        match self.instance.as_mut() {
+            Some(instance) => match instance.on_job(payload).await {
+                Ok(result) => Ok(result),
+                Err(err) => Err(self.instance_error(err).into()),
+            },
+            None => Err(ServerError::LappNotLoaded(self.name().to_string())),
+        }
+    }
+
+    pub async fn on_timer(&mut self, id: u64) -> ServerResult<()> {
+        match self.instance.as_mut() {
+            Some(instance) => match instance.on_timer(id).await {
+                Ok(()) => Ok(()),
+                Err(err) => Err(self.instance_error(err).into()),
+            },
             None => Err(ServerError::LappNotLoaded(self.name().to_string())),
         }
     }
 
+    pub async fn health(&mut self) -> ServerResult<Option<HealthResult>> {
+        match self.instance.as_mut() {
+            Some(instance) => match instance.health().await {
+                Ok(result) => Ok(result),
+                Err(err) => Err(self.instance_error(err).into()),
+            },
+            None => Err(ServerError::LappNotLoaded(self.name().to_string())),
+        }
+    }
+
+    /// Logs the wasm backtrace of the lapp trap, the backtrace is kept in the error in the debug mode only.
+    pub fn instance_error(&self, err: LappInstanceError) -> LappInstanceError {
+        Self::log_instance_error(self.name(), self.debug, err)
+    }
+
+    /// Logs the lapp trap out of the lapp, e.g. the one of the pooled instance.
+    pub fn log_instance_error(lapp_name: &str, debug: bool, err: LappInstanceError) -> LappInstanceError {
+        match err {
+            LappInstanceError::WasmTrap { trap, backtrace } => {
+                if let Some(backtrace) = &backtrace {
+                    log::error!("Lapp '{lapp_name}' trap: {trap}\n{backtrace}");
+                }
+                LappInstanceError::WasmTrap {
+                    trap,
+                    backtrace: backtrace.filter(|_| debug),
+                }
+            },
+            err => err,
+        }
+    }
+
     pub fn server_module_file(&self) -> PathBuf {
         self.root_dir().join(format!("{}_server.wasm", self.name()))
     }
 
-    pub async fn instantiate(&mut self, http_client: Client) -> ServerResult<()> {
-        let wasm_bytes = fs::read(self.server_module_file())?;
-        let module = Module::new(&ENGINE, wasm_bytes)?;
-
-        let mut linker = Linker::new(&ENGINE);
-        add_to_linker_async(&mut linker)?;
+    pub fn snapshot_file(&self) -> PathBuf {
+        self.root_dir().join(format!("{}_server.snapshot", self.name()))
+    }
 
-        let is_allow_read = self.is_allowed_permission(Permission::FileRead);
-        let is_allow_write = self.is_allowed_permission(Permission::FileWrite);
-        let is_allow_db_access = self.is_allowed_permission(Permission::Database);
-        let is_allow_http = self.is_allowed_permission(Permission::Http);
-        let is_allow_sleep = self.is_allowed_permission(Permission::Sleep);
+    pub fn trace_file(&self) -> PathBuf {
+        self.root_dir().join(format!("{}_server.trace", self.name()))
+    }
 
-        let data_dir_path = if self.data_dir().is_absolute() {
-            self.data_dir().to_owned()
+    /// Instantiates the main instance and the pool of the additional ones. The lapp recording the trace has
+    /// no pool, because the trace of the parallel requests could not be replayed.
+    pub async fn instantiate(&mut self, http_client: Client) -> ServerResult<()> {
+        let trace = if self.settings().application.record_trace && !self.read_only {
+            Some(Trace::record(self.trace_file())?)
         } else {
-            self.root_dir().join(self.data_dir())
+            None
         };
-        if !data_dir_path.exists() && (is_allow_read || is_allow_write) {
-            fs::create_dir(&data_dir_path)?;
+        let is_pooled = trace.is_none();
+        self.instantiate_with_trace(http_client.clone(), trace).await?;
+
+        if is_pooled && self.component.is_none() {
+            let mut instances = Vec::new();
+            for _ in 1..self.settings().resources().instances() {
+                instances.push(self.new_instance(http_client.clone(), None, None).await?);
+            }
+            self.pool = InstancePool::new(instances);
         }
+        Ok(())
+    }
 
-        let mut wasi = WasiCtxBuilder::new();
-        wasi.inherit_stdout();
+    /// Instantiates the main instance recording or replaying the trace, the signature of the lapp files is checked
+    /// first. Only the main instance sets the timers, the previous instance timers are dropped with it.
+    pub async fn instantiate_with_trace(&mut self, http_client: Client, trace: Option<Trace>) -> ServerResult<()> {
+        self.package_verifier.verify_dir(self.name(), self.root_dir())?;
+        self.pool = InstancePool::default();
+        if let Some(lapp_calls) = &self.lapp_calls {
+            lapp_calls.revoke_tokens(self.name());
+        }
 
-        if self
-            .settings()
-            .permissions
-            .required()
-            .any(|permission| permission == Permission::FileRead || permission == Permission::FileWrite)
-        {
-            let preopened_dir = Dir::open_ambient_dir(&data_dir_path, cap_std::ambient_authority())?;
-            let mut perms = DirPerms::empty();
-            let mut file_perms = FilePerms::empty();
+        let is_replay = trace.as_ref().is_some_and(Trace::is_replay);
+        if !is_replay && !self.read_only {
+            self.apply_migrations()?;
+        }
+        self.update_connection_pool();
 
-            if is_allow_read {
-                perms |= DirPerms::READ;
-                file_perms |= FilePerms::READ;
+        let wasm_bytes = fs::read(self.server_module_file())?;
+        if is_component(&wasm_bytes) {
+            if trace.is_some() {
+                log::warn!("Lapp '{}' is a component, the trace is not supported", self.name());
             }
+            self.instance = None;
+            let component = self.new_component_instance(http_client, &wasm_bytes).await?;
+            self.component.replace(component);
+            return Ok(());
+        }
 
-            if is_allow_write {
-                perms |= DirPerms::MUTATE;
-                file_perms |= FilePerms::WRITE;
+        let timers = self.timers_out.clone().map(TimersCtx::new);
+        let instance = self.new_instance(http_client, trace, timers).await?;
+        self.component = None;
+        self.instance.replace(instance);
+        Ok(())
+    }
+
+    /// Applies the pending migrations and creates the FTS tables of the lapp with the `database` permission.
+    fn apply_migrations(&self) -> ServerResult<()> {
+        if !self.is_allowed_permission(Permission::Database) {
+            if self.root_dir().join(MIGRATIONS_DIR).is_dir() {
+                log::warn!(
+                    "Migrations of lapp '{}' are skipped without the database permission",
+                    self.name()
+                );
             }
+            return Ok(());
+        }
+
+        let database_path = self.get_database_path();
+        for name in apply_migrations(self.root_dir(), &database_path)? {
+            log::info!("Migration '{name}' of lapp '{}' is applied", self.name());
+        }
+        create_fts_tables(database_path, &self.settings().database().fts_tables)
+    }
+
+    /// Replaces the connection pool if the database path or the database permissions are changed, so the instances
+    /// do not get the connections opened with the previous access.
+    fn update_connection_pool(&mut self) {
+        let database_path = self.get_database_path();
+        let is_allow_db_write = self.is_allowed_permission(Permission::Database);
+        let is_same = self
+            .connection_pool
+            .as_ref()
+            .is_some_and(|pool| pool.is_same(&database_path, self.read_only, is_allow_db_write));
 
-            wasi.preopened_dir(preopened_dir, perms, file_perms, "/");
+        if !is_same {
+            let max_idle = self.settings().resources().instances() as usize;
+            self.connection_pool = Some(ConnectionPool::new(
+                database_path,
+                self.read_only,
+                is_allow_db_write,
+                max_idle,
+            ));
         }
+    }
+
+    /// Creates the instance of the server component. The component gets the database and the HTTP client allowed by
+    /// the lapp permissions, but not the other host calls of the core modules.
+    async fn new_component_instance(&self, http_client: Client, wasm_bytes: &[u8]) -> ServerResult<ComponentInstance> {
+        let component = Component::new(&ENGINE, wasm_bytes)?;
+
+        let is_allow_read = self.is_allowed_permission(Permission::FileRead);
+        let is_allow_write = !self.read_only && self.is_allowed_permission(Permission::FileWrite);
+        let is_allow_db_write = self.is_allowed_permission(Permission::Database);
+        let is_allow_db_access = is_allow_db_write || self.is_allowed_permission(Permission::DatabaseRead);
+        let is_allow_http = self.is_allowed_permission(Permission::Http);
+
+        let wasi = self.wasi_ctx(is_allow_read, is_allow_write)?;
+        let mut store = Store::new(&ENGINE, Ctx::new(wasi, Table::new()));
+        store.data_mut().limiter = MemoryLimiter::new(self.max_memory_mb());
+        store.limiter(|ctx| &mut ctx.limiter);
+        store.data_mut().call_time_limit = self.call_time_limit();
+        store.data_mut().http_timeout = self.http_timeout();
+        Ctx::interrupt_on_deadline(&mut store);
+
+        if is_allow_db_access {
+            store.data_mut().database = Some(DatabaseCtx::new(self.acquire_connection()?));
+        }
+        if is_allow_http {
+            store.data_mut().http = Some(HttpCtx::new(http_client, self.settings().network().http().clone()));
+        }
+        if let Some(disk_quota_mb) = self.disk_quota_mb().filter(|_| !self.read_only) {
+            let data_dir_path = Self::data_dir_path(self.root_dir(), self.settings());
+            store.data_mut().disk_quota = Some(DiskQuota::new(disk_quota_mb, data_dir_path, self.get_database_path()));
+        }
+
+        let instance = ComponentInstance::instantiate(&component, store).await?;
+        log::debug!("Lapp '{}' is instantiated as a component", self.name());
+        Ok(instance)
+    }
+
+    /// Creates the instance of the server module. The recorded and replayed instances are initialized without
+    /// the snapshot, so the trace contains the host calls of `init`.
+    async fn new_instance(
+        &self,
+        http_client: Client,
+        trace: Option<Trace>,
+        timers: Option<TimersCtx>,
+    ) -> ServerResult<LappInstance> {
+        let wasm_bytes = fs::read(self.server_module_file())?;
+        let module = match self.module_cache_key.as_ref().filter(|_| self.module_cache) {
+            Some(key) => ModuleCache::new(self.root_dir(), self.name(), key).load_or_compile(
+                &ENGINE,
+                &wasm_bytes,
+                !self.read_only,
+            )?,
+            None => Module::new(&ENGINE, &wasm_bytes)?,
+        };
+
+        let mut linker = Linker::new(&ENGINE);
+        add_to_linker_async(&mut linker)?;
+
+        let is_allow_read = self.is_allowed_permission(Permission::FileRead);
+        let is_allow_write = !self.read_only && self.is_allowed_permission(Permission::FileWrite);
+        let is_allow_db_write = self.is_allowed_permission(Permission::Database);
+        let is_allow_db_access = is_allow_db_write || self.is_allowed_permission(Permission::DatabaseRead);
+        let is_allow_http = self.is_allowed_permission(Permission::Http);
+        let is_allow_sleep = self.is_allowed_permission(Permission::Sleep);
+        let is_allow_mqtt = self.is_allowed_permission(Permission::Mqtt);
+        let is_allow_threads = self.is_allowed_permission(Permission::Threads);
+        let is_allow_lapps_outgoing = self.is_allowed_permission(Permission::LappsOutgoing);
+        let is_allow_ws_client = self.is_allowed_permission(Permission::WebsocketClient);
+        let is_allow_crypto = self.is_allowed_permission(Permission::Crypto);
+        let is_allow_mail = self.is_allowed_permission(Permission::Mail);
+        let is_allow_websocket = self.is_allowed_permission(Permission::Websocket);
+        let shared_memory_imports: Vec<_> = module
+            .imports()
+            .filter_map(|import| match import.ty() {
+                ExternType::Memory(memory_type) if memory_type.is_shared() => {
+                    Some((import.module().to_string(), import.name().to_string(), memory_type))
+                },
+                _ => None,
+            })
+            .collect();
 
-        let wasi = wasi.build();
+        let snapshot_key = InstanceSnapshot::key(&wasm_bytes, &[
+            is_allow_read,
+            is_allow_write,
+            is_allow_db_access,
+            is_allow_db_write,
+            is_allow_http,
+            is_allow_sleep,
+            is_allow_mqtt,
+            is_allow_threads,
+            is_allow_lapps_outgoing,
+            is_allow_ws_client,
+            is_allow_crypto,
+            is_allow_mail,
+            is_allow_websocket,
+        ]);
+        let is_replay = trace.as_ref().map_or(false, Trace::is_replay);
+        // The snapshot does not support the shared memory
+        let is_snapshot = self.settings().application.snapshot && shared_memory_imports.is_empty();
+        let snapshot = if is_snapshot && trace.is_none() {
+            InstanceSnapshot::load(self.snapshot_file(), &snapshot_key)
+        } else {
+            None
+        };
+
+        let data_dir_path = Self::data_dir_path(self.root_dir(), self.settings());
+        let wasi = self.wasi_ctx(is_allow_read, is_allow_write)?;
         let table = Table::new();
         let ctx = Ctx::new(wasi, table);
         let mut store = Store::new(&ENGINE, ctx);
+        store.data_mut().trace = trace;
+        store.data_mut().limiter = MemoryLimiter::new(self.max_memory_mb());
+        store.limiter(|ctx| &mut ctx.limiter);
+        store.data_mut().call_time_limit = self.call_time_limit();
+        store.data_mut().http_timeout = self.http_timeout();
+        Ctx::interrupt_on_deadline(&mut store);
 
-        if is_allow_db_access {
-            let database_path = self.get_database_path();
-            let connection = Connection::open(database_path)?;
+        for (module_name, name, memory_type) in shared_memory_imports {
+            let memory = SharedMemory::new(&ENGINE, memory_type)?;
+            linker.define(&store, &module_name, &name, memory)?;
+        }
+
+        if is_allow_db_access && !is_replay {
+            let mut database_ctx = DatabaseCtx::new(self.acquire_connection()?);
+            if is_allow_db_write && !self.read_only {
+                database_ctx.set_backup_dir(Self::database_backup_dir(self.root_dir(), self.settings()));
+            }
+            store.data_mut().database = Some(database_ctx);
+        }
 
-            store.data_mut().database = Some(DatabaseCtx::new(connection));
+        if is_allow_db_access {
             linker.func_wrap1_async("env", "db_execute", database::execute)?;
             linker.func_wrap1_async("env", "db_query", database::query)?;
             linker.func_wrap1_async("env", "db_query_row", database::query_row)?;
+            linker.func_wrap0_async("env", "db_begin", database::begin)?;
+            linker.func_wrap0_async("env", "db_commit", database::commit)?;
+            linker.func_wrap0_async("env", "db_rollback", database::rollback)?;
+            linker.func_wrap1_async("env", "db_prepare", database::prepare)?;
+            linker.func_wrap2_async("env", "db_bind", database::bind)?;
+            linker.func_wrap1_async("env", "db_step", database::step)?;
+            linker.func_wrap1_async("env", "db_finalize", database::finalize)?;
+            linker.func_wrap1_async("env", "db_query_open", database::query_open)?;
+            linker.func_wrap1_async("env", "db_query_next", database::query_next)?;
+            linker.func_wrap1_async("env", "db_query_close", database::query_close)?;
+            linker.func_wrap1_async("env", "db_fts_search", database::fts_search)?;
+            linker.func_wrap0_async("env", "db_backup", database::backup)?;
         }
 
         if is_allow_http {
-            store.data_mut().http = Some(HttpCtx::new(http_client, self.lapp.settings().network().http().clone()));
+            if !is_replay {
+                store.data_mut().http = Some(HttpCtx::new(http_client, self.lapp.settings().network().http().clone()));
+            }
             linker.func_wrap1_async("env", "invoke_http", http::invoke_http)?;
+            linker.func_wrap1_async("env", "invoke_http_stream", http::invoke_http_stream)?;
+        }
+
+        if is_allow_ws_client {
+            if !is_replay {
+                store.data_mut().ws_client = Some(WsClientCtx::new(self.settings().network().http().clone()));
+            }
+            linker.func_wrap1_async("env", "ws_connect", ws_client::ws_connect)?;
+            linker.func_wrap2_async("env", "ws_send", ws_client::ws_send)?;
+            linker.func_wrap2_async("env", "ws_receive", ws_client::ws_receive)?;
+            linker.func_wrap1_async("env", "ws_close", ws_client::ws_close)?;
+        }
+
+        if is_allow_crypto {
+            linker.func_wrap1_async("env", "invoke_crypto", crypto::invoke_crypto)?;
         }
 
         if is_allow_sleep {
             linker.func_wrap1_async("env", "invoke_sleep", sleep::invoke_sleep)?;
         }
 
-        let instance = linker.instantiate_async(&mut store, &module).await?;
+        if is_allow_mqtt {
+            if let Some(mqtt_client) = self.mqtt_client.clone().filter(|_| !is_replay) {
+                store.data_mut().mqtt = Some(MqttCtx::new(mqtt_client, self.settings().network().mqtt().clone()));
+            }
+            linker.func_wrap1_async("env", "mqtt_publish", mqtt::mqtt_publish)?;
+        }
+
+        if is_allow_mail {
+            if let Some(mailer) = self.mailer.clone().filter(|_| !is_replay) {
+                store.data_mut().mail = Some(MailCtx::new(self.name(), mailer));
+            }
+            linker.func_wrap1_async("env", "send_mail", mail::send_mail)?;
+        }
+
+        if is_allow_websocket {
+            if !is_replay {
+                store.data_mut().ws_push = Some(self.ws_clients());
+            }
+            linker.func_wrap("env", "ws_current_client", ws_push::ws_current_client)?;
+            linker.func_wrap2_async("env", "ws_push", ws_push::ws_push)?;
+        }
+
+        if is_allow_read || is_allow_write {
+            if !is_replay {
+                store.data_mut().blobs = Some(BlobsCtx::new(&data_dir_path, is_allow_write));
+            }
+            linker.func_wrap1_async("env", "blob_put", blob::blob_put)?;
+            linker.func_wrap1_async("env", "blob_get", blob::blob_get)?;
+            linker.func_wrap1_async("env", "blob_delete", blob::blob_delete)?;
+        }
+
+        let templates_dir = self.root_dir().join(Self::templates_dir_name());
+        if templates_dir.is_dir() {
+            store.data_mut().template = Some(TemplateCtx::new(templates_dir));
+        }
+        linker.func_wrap1_async("env", "render_template", template::render_template)?;
+        store.data_mut().http_body = HttpBodyCtx::new(self.settings().application.stream_request_body);
+        linker.func_wrap2_async("env", "http_read_body", http::http_read_body)?;
+        linker.func_wrap1_async("env", "http_write_body", http::http_write_body)?;
+
+        store.data_mut().settings_json = Some(self.settings().guest_settings_json().to_string());
+        linker.func_wrap0_async("env", "get_settings_json", lapp_settings::get_settings_json)?;
+
+        linker.func_wrap("env", "now_millis", time::now_millis)?;
+        linker.func_wrap("env", "monotonic_nanos", time::monotonic_nanos)?;
+
+        store.data_mut().timers = timers.filter(|_| !is_replay);
+        linker.func_wrap("env", "set_timeout", timer::set_timeout)?;
+        linker.func_wrap("env", "set_interval", timer::set_interval)?;
+        linker.func_wrap("env", "clear_timer", timer::clear_timer)?;
+
+        // The replayed lapp does not repeat the logged records
+        if let Some(lapp_logs) = self.lapp_logs.clone().filter(|_| !is_replay) {
+            store.data_mut().log = Some(LogCtx::new(self.name(), lapp_logs));
+        }
+        linker.func_wrap1_async("env", "log", logging::log)?;
+
+        // The jobs are kept in the lapp database, so they are enqueued only by the lapp allowed to write it
+        if is_allow_db_write && !self.read_only && !is_replay {
+            store.data_mut().jobs = Some(JobsCtx::new(self.get_database_path(), self.jobs_notify()));
+        }
+        linker.func_wrap1_async("env", "job_enqueue", jobs::job_enqueue)?;
+
+        let permission_requests = self
+            .permission_requests
+            .clone()
+            .filter(|_| !self.read_only && !is_replay);
+        store.data_mut().permission = Some(PermissionCtx::new(
+            self.name(),
+            self.settings().permissions.clone(),
+            permission_requests,
+        ));
+        linker.func_wrap1_async("env", "request_permission", permission::request_permission)?;
+
+        if is_allow_lapps_outgoing {
+            if let Some(lapp_calls) = self.lapp_calls.clone().filter(|_| !is_replay) {
+                let lapp_names = self
+                    .settings()
+                    .lapp_requests()
+                    .iter()
+                    .map(|requests| requests.lapp_name.as_str());
+                let lapp_bus = self.lapp_bus.clone();
+                store.data_mut().lapps = Some(LappsCtx::new(self.name(), lapp_names, lapp_calls, lapp_bus));
+            }
+            linker.func_wrap1_async("env", "invoke_lapp", lapps::invoke_lapp)?;
+            linker.func_wrap1_async("env", "lapp_send", lapps::lapp_send)?;
+        }
+
+        if is_allow_threads {
+            linker.func_wrap("wasi", "thread-spawn", threads::thread_spawn)?;
+            let instance_pre = linker.instantiate_pre(&module)?;
+            store.data_mut().threads = Some(Arc::new(ThreadsCtx::new(
+                self.name(),
+                ENGINE.clone(),
+                instance_pre,
+                self.thread_pool.clone(),
+                self.settings().threads().clone(),
+            )));
+        }
+
+        // The threads have no preopened directories, so only the instance writes are limited after the threads
+        // instantiation is prepared
+        if let Some(disk_quota_mb) = self.disk_quota_mb().filter(|_| !self.read_only && !is_replay) {
+            store.data_mut().disk_quota = Some(DiskQuota::new(disk_quota_mb, &data_dir_path, self.get_database_path()));
+            limit_fd_write(&mut linker, &mut store)?;
+        }
+
+        // The module start and the initialization exports are limited as a single call
+        store.data_mut().start_call();
+        let instance = linker
+            .instantiate_async(&mut store, &module)
+            .await
+            .map_err(LappInstanceError::from_call)?;
         let memory_management = MemoryManagementHostData::from_instance(&instance, &mut store)?;
         store.data_mut().memory_data = Some(memory_management.clone());
 
+        if let Some(snapshot) = snapshot {
+            snapshot.restore(&instance, &mut store)?;
+            store.data_mut().finish_call();
+            log::debug!("Lapp '{}' is restored from the snapshot", self.name());
+
+            return Ok(LappInstance {
+                instance,
+                memory_management,
+                store,
+                is_failed: false,
+            });
+        }
+
         if let Some(initialize) = instance.get_func(&mut store, "_initialize") {
-            initialize.call_async(&mut store, &[], &mut Vec::new()).await?;
+            initialize
+                .call_async(&mut store, &[], &mut Vec::new())
+                .await
+                .map_err(|err| self.instance_error(LappInstanceError::from_call(err)))?;
         }
 
         if let Some(start) = instance.get_func(&mut store, "_start") {
-            start.call_async(&mut store, &[], &mut Vec::new()).await?;
+            start
+                .call_async(&mut store, &[], &mut Vec::new())
+                .await
+                .map_err(|err| self.instance_error(LappInstanceError::from_call(err)))?;
         }
 
         if let Some(init) = instance.get_func(&mut store, "init") {
-            let slice = init.typed::<(), u64>(&store)?.call_async(&mut store, ()).await?;
+            let slice = init
+                .typed::<(), u64>(&store)?
+                .call_async(&mut store, ())
+                .await
+                .map_err(|err| self.instance_error(LappInstanceError::from_call(err)))?;
             let mut memory_manager = memory_management.to_manager(&mut store);
             let bytes = memory_manager
                 .wasm_slice_to_vec(slice)
@@ -267,22 +999,147 @@ impl Lapp {
                 .map_err(LappInstanceError::MemoryManagementError)?;
             Result::<(), String>::try_from_slice(&bytes)?.map_err(ServerError::LappInitError)?;
         }
+        store.data_mut().finish_call();
 
-        self.instance.replace(LappInstance {
+        if is_snapshot && !self.read_only && !is_replay {
+            if let Err(err) = InstanceSnapshot::take(snapshot_key, &instance, &mut store)
+                .and_then(|snapshot| snapshot.save(self.snapshot_file()))
+            {
+                log::warn!("Snapshot of lapp '{}' is not saved: {err}", self.name());
+            }
+        }
+
+        Ok(LappInstance {
             instance,
             memory_management,
             store,
-        });
-        Ok(())
+            is_failed: false,
+        })
+    }
+
+    /// Builds the WASI context with the mounts of the lapp allowed by the file permissions.
+    fn wasi_ctx(&self, is_allow_read: bool, is_allow_write: bool) -> ServerResult<WasiCtx> {
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.inherit_stdout();
+        for (key, value) in self.settings().env.iter().flatten() {
+            wasi.env(key, value);
+        }
+
+        for mount in self.settings().mounts().iter() {
+            let mut perms = DirPerms::empty();
+            let mut file_perms = FilePerms::empty();
+            if mount.read && is_allow_read {
+                perms |= DirPerms::READ;
+                file_perms |= FilePerms::READ;
+            }
+            if mount.write && is_allow_write {
+                file_perms |= FilePerms::WRITE;
+            }
+            if mount.create && is_allow_write {
+                perms |= DirPerms::MUTATE;
+            }
+            if perms.is_empty() && file_perms.is_empty() {
+                continue;
+            }
+
+            if let Some(mount_path) = self.mount_path(mount)? {
+                let preopened_dir = Dir::open_ambient_dir(&mount_path, cap_std::ambient_authority())?;
+                wasi.preopened_dir(preopened_dir, perms, file_perms, &mount.guest_path);
+            }
+        }
+
+        Ok(wasi.build())
+    }
+
+    /// Takes the connection of the lapp database from the pool, the writes are denied without the `database`
+    /// permission.
+    fn acquire_connection(&self) -> ServerResult<PooledConnection> {
+        let connection_pool = self
+            .connection_pool
+            .as_ref()
+            .ok_or_else(|| ServerError::LappInitError("database connection pool is not created".into()))?;
+        Ok(connection_pool.acquire()?)
     }
 
     fn get_database_path(&self) -> PathBuf {
-        let database_path = self.settings().database().path();
+        Self::database_path(self.root_dir(), self.settings())
+    }
+
+    pub fn data_dir_path(lapp_path: impl AsRef<Path>, settings: &LappSettings) -> PathBuf {
+        let data_dir = &settings.application.data_dir;
+
+        if data_dir.is_absolute() {
+            data_dir.clone()
+        } else {
+            lapp_path.as_ref().join(data_dir)
+        }
+    }
+
+    /// Resolves the host directory of the mount and creates it if it is missing, `None` is returned for the missing
+    /// directory in the read-only mode. The relative path must stay inside the lapp directory and the absolute one
+    /// inside the mount paths of the server settings, the symlinks are resolved before the check.
+    fn mount_path(&self, mount: &MountSettings) -> ServerResult<Option<PathBuf>> {
+        let (mount_path, allowed_paths) = if mount.path.is_absolute() {
+            (mount.path.clone(), &self.mount_paths[..])
+        } else {
+            (self.root_dir().join(&mount.path), std::slice::from_ref(self.root_dir()))
+        };
+
+        // The missing directories are checked by their existing ancestor, which is resolved before they are created
+        let mut components = mount.path.components();
+        let is_allowed = !components.any(|component| component == path::Component::ParentDir)
+            && mount_path
+                .ancestors()
+                .find(|path| path.exists())
+                .and_then(|existing_path| existing_path.canonicalize().ok())
+                .is_some_and(|existing_path| {
+                    allowed_paths
+                        .iter()
+                        .filter_map(|allowed_path| allowed_path.canonicalize().ok())
+                        .any(|allowed_path| existing_path.starts_with(allowed_path))
+                });
+        if !is_allowed {
+            return Err(ServerError::LappMountNotAllowed(
+                self.name().into(),
+                mount.path.display().to_string(),
+            ));
+        }
+
+        if !mount_path.exists() && !self.read_only {
+            fs::create_dir_all(&mount_path)?;
+        }
+        if mount_path.exists() {
+            Ok(Some(mount_path))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn database_backup_dir(lapp_path: impl AsRef<Path>, settings: &LappSettings) -> PathBuf {
+        let backup_dir = settings.database().backup_dir();
+
+        if backup_dir.is_relative() {
+            lapp_path.as_ref().join(backup_dir)
+        } else {
+            backup_dir.into()
+        }
+    }
+
+    pub fn database_path(lapp_path: impl AsRef<Path>, settings: &LappSettings) -> PathBuf {
+        let database_path = settings.database().path();
 
         if database_path.is_relative() {
-            self.root_dir().join(database_path)
+            lapp_path.as_ref().join(database_path)
         } else {
             database_path.into()
         }
     }
 }
+
+/// The lower of the lapp and the server limits.
+fn min_limit(lapp_limit: Option<u64>, server_limit: Option<u64>) -> Option<u64> {
+    match (lapp_limit, server_limit) {
+        (Some(lapp_limit), Some(server_limit)) => Some(lapp_limit.min(server_limit)),
+        (lapp_limit, server_limit) => lapp_limit.or(server_limit),
+    }
+}
@@ -0,0 +1,68 @@
+//! Log streams of the lapps.
+//!
+//! The records sent by the `log` host call of the lapp server module are written to the server log with the
+//! `lapp::{lapp_name}` target, so the log spec filters them per lapp, e.g. `info,lapp::notes=debug`. The last records
+//! of every lapp are kept in memory and returned by the management API regardless of the log spec. The records written
+//! while the lapp processes an HTTP request carry the `x-request-id` of the request.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::Utc;
+use laplace_common::api::{LappLogRecord, LogLevel};
+
+const MAX_RECORDS: usize = 1000;
+
+#[derive(Debug, Clone, Default)]
+pub struct LappLogs {
+    streams: Arc<Mutex<HashMap<String, VecDeque<LappLogRecord>>>>,
+}
+
+impl LappLogs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&self, lapp_name: &str, level: LogLevel, target: String, message: String, request_id: Option<String>) {
+        let log_level = match level {
+            LogLevel::Error => log::Level::Error,
+            LogLevel::Warn => log::Level::Warn,
+            LogLevel::Info => log::Level::Info,
+            LogLevel::Debug => log::Level::Debug,
+            LogLevel::Trace => log::Level::Trace,
+        };
+        match &request_id {
+            Some(request_id) => log::log!(
+                target: &format!("lapp::{lapp_name}"),
+                log_level,
+                "[{target}] [{request_id}] {message}"
+            ),
+            None => log::log!(target: &format!("lapp::{lapp_name}"), log_level, "[{target}] {message}"),
+        }
+
+        let mut streams = self.lock_streams();
+        let stream = streams.entry(lapp_name.into()).or_default();
+        if stream.len() == MAX_RECORDS {
+            stream.pop_front();
+        }
+        stream.push_back(LappLogRecord {
+            time: Utc::now().to_rfc3339(),
+            level,
+            target,
+            message,
+            request_id,
+        });
+    }
+
+    /// Returns the last records of the lapp, the oldest first.
+    pub fn records(&self, lapp_name: &str) -> Vec<LappLogRecord> {
+        self.lock_streams()
+            .get(lapp_name)
+            .map(|stream| stream.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn lock_streams(&self) -> MutexGuard<'_, HashMap<String, VecDeque<LappLogRecord>>> {
+        self.streams.lock().expect("Lapp logs lock should not be poisoned")
+    }
+}
@@ -1,35 +1,89 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::future::Future;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
 
 use futures::future::{self, Either};
 use futures::{FutureExt, TryFutureExt};
-use laplace_common::api::UpdateQuery;
-use laplace_common::lapp::{LappSettings, Permission};
-use laplace_wasm::http;
+use laplace_common::api::{
+    is_instance_changed, DependencyGraph, DiskUsage, LappLogRecord, LappMigrations, PermissionAuditEntry,
+    PermissionState, SettingsReload, UpdateQuery, UpgradeDiff,
+};
+use laplace_common::lapp::{self, InvalidLapp, LappSettings, ManifestError, Permission};
+use laplace_wasm::schedule::ScheduleResult;
+use laplace_wasm::{graphql, http, mqtt};
 use reqwest::Client;
+use rumqttc::AsyncClient;
+use tempfile::NamedTempFile;
 use tokio::fs;
+use tokio::sync::oneshot;
 use truba::{Context, Sender};
+use zip::ZipArchive;
 
+use crate::circuit_breaker::CircuitBreaker;
 use crate::error::{ServerError, ServerResult};
+use crate::lapp_calls::LappCalls;
 use crate::lapps::settings::FileSettings;
-use crate::lapps::LappDir;
-use crate::service::lapp::LappServiceMessage;
+use crate::lapps::wasm_interop::threads::ThreadPool;
+use crate::lapps::{
+    disk_usage, duplicate_lapp_dir, migrations_status, LappDir, LappLogs, LappUpgrade, ModuleCacheKey, PackageVerifier,
+    PermissionAudit,
+};
+use crate::mail::Mailer;
+use crate::permission_requests::PermissionRequests;
+use crate::rate_limit::RateLimiter;
+use crate::service::bus::LappBus;
+use crate::service::lapp::{LappMessage, LappServiceMessage};
 use crate::service::{Addr, LappService};
 use crate::settings::LappsSettings;
+use crate::tasks::Tasks;
 use crate::Lapp;
 
+/// The maximum time the unloading lapp finishes its requests.
+const UNLOAD_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct LappsManager {
     lapp_settings: HashMap<String, LappSettings>,
+    invalid_lapps: BTreeMap<String, Vec<ManifestError>>,
+    upgrades: HashMap<String, LappUpgrade>,
     lapps_path: PathBuf,
+    read_only: bool,
+    debug: bool,
     http_client: Client,
+    mqtt_client: Option<AsyncClient>,
+    mailer: Option<Mailer>,
+    rate_limiter: RateLimiter,
+    circuit_breaker: CircuitBreaker,
+    thread_pool: ThreadPool,
+    max_memory_mb: Option<u64>,
+    call_time_limit_ms: Option<u64>,
+    disk_quota_mb: Option<u64>,
+    max_body_mb: Option<u64>,
+    http_timeout_ms: Option<u64>,
+    mount_paths: Vec<PathBuf>,
+    module_cache: bool,
+    module_cache_key: Option<ModuleCacheKey>,
+    package_verifier: PackageVerifier,
+    permission_audit: Option<PermissionAudit>,
+    permission_requests: PermissionRequests,
+    permission_profiles: BTreeMap<String, Vec<Permission>>,
+    lapp_calls: LappCalls,
+    lapp_bus: LappBus,
+    lapp_logs: LappLogs,
+
+    /// The lapps whose services are draining before the unload.
+    unloading: Arc<Mutex<HashSet<String>>>,
+    tasks: Tasks,
     ctx: Context<Addr>,
 }
 
 impl LappsManager {
     pub async fn new(settings: &LappsSettings, ctx: Context<Addr>) -> io::Result<Self> {
         let mut lapp_settings = HashMap::new();
+        let mut invalid_lapps = BTreeMap::new();
+        fs::create_dir_all(&settings.path).await?;
         let mut read_dir = fs::read_dir(&settings.path).await?;
 
         while let Some(dir) = read_dir.next_entry().await? {
@@ -44,15 +98,51 @@ impl LappsManager {
                 }
             }
 
-            if let Some(settings) = Lapp::load_settings(&name, dir.path()) {
-                lapp_settings.insert(name, settings);
+            if Lapp::is_main(&name) || !dir.file_type().await?.is_dir() {
+                continue;
+            }
+
+            match Lapp::load_valid_settings(&name, dir.path()) {
+                Ok(settings) => {
+                    lapp_settings.insert(name, settings);
+                },
+                Err(errors) => {
+                    log::error!("Lapp '{name}' is not loaded: {}", join_errors(&errors));
+                    invalid_lapps.insert(name, errors);
+                },
             }
         }
 
         Ok(Self {
             lapp_settings,
+            invalid_lapps,
+            upgrades: HashMap::new(),
             lapps_path: settings.path.clone(),
+            read_only: settings.read_only,
+            debug: settings.debug,
             http_client: Client::new(),
+            mqtt_client: None,
+            mailer: None,
+            rate_limiter: RateLimiter::new(),
+            circuit_breaker: CircuitBreaker::new(settings.quarantine_threshold),
+            thread_pool: ThreadPool::new(settings.threads_pool_size),
+            max_memory_mb: settings.max_memory_mb,
+            call_time_limit_ms: settings.call_time_limit_ms,
+            http_timeout_ms: settings.http_timeout_ms,
+            disk_quota_mb: settings.disk_quota_mb,
+            max_body_mb: settings.max_body_mb,
+            mount_paths: settings.mount_paths.clone(),
+            module_cache: settings.module_cache,
+            module_cache_key: None,
+            package_verifier: PackageVerifier::new(&settings.publisher_keys, settings.allow_unsigned),
+            permission_audit: None,
+            permission_requests: PermissionRequests::new(),
+            permission_profiles: settings.permission_profiles.clone(),
+            lapp_calls: LappCalls::new(),
+            lapp_bus: LappBus::new(),
+            lapp_logs: LappLogs::new(),
+            unloading: Default::default(),
+            tasks: Tasks::new(),
             ctx,
         })
     }
@@ -61,13 +151,172 @@ impl LappsManager {
         &self.ctx
     }
 
-    pub fn insert_lapp_settings(&mut self, lapp_name: impl Into<String>) {
+    pub fn http_client(&self) -> &Client {
+        &self.http_client
+    }
+
+    pub fn package_verifier(&self) -> &PackageVerifier {
+        &self.package_verifier
+    }
+
+    /// Sets the client of the MQTT bridge, the running lapps get it after the reload.
+    pub fn set_mqtt_client(&mut self, mqtt_client: AsyncClient) {
+        self.mqtt_client = Some(mqtt_client);
+    }
+
+    /// Sets the SMTP mailer of the lapp mails, the running lapps get it after the reload.
+    pub fn set_mailer(&mut self, mailer: Mailer) {
+        self.mailer = Some(mailer);
+    }
+
+    /// Sets the key that signs the compiled modules cached in the lapp directories.
+    pub fn set_module_cache_key(&mut self, module_cache_key: ModuleCacheKey) {
+        self.module_cache_key = Some(module_cache_key);
+    }
+
+    /// Sets the audit log of the permission changes made by the lapp updates.
+    pub fn set_permission_audit(&mut self, permission_audit: PermissionAudit) {
+        self.permission_audit = Some(permission_audit);
+    }
+
+    pub fn permission_profiles(&self) -> &BTreeMap<String, Vec<Permission>> {
+        &self.permission_profiles
+    }
+
+    pub fn permission_requests(&self) -> &PermissionRequests {
+        &self.permission_requests
+    }
+
+    pub fn lapp_calls(&self) -> &LappCalls {
+        &self.lapp_calls
+    }
+
+    pub fn lapp_bus(&self) -> &LappBus {
+        &self.lapp_bus
+    }
+
+    /// The last log records of the lapp, the oldest first.
+    pub fn lapp_logs(&self, lapp_name: &str) -> ServerResult<Vec<LappLogRecord>> {
+        self.lapp_settings(lapp_name)?;
+        Ok(self.lapp_logs.records(lapp_name))
+    }
+
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    pub fn circuit_breaker(&self) -> &CircuitBreaker {
+        &self.circuit_breaker
+    }
+
+    pub fn tasks(&self) -> &Tasks {
+        &self.tasks
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Switches the read-only mode of the manager and the running lapps.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+        for lapp_name in self.lapp_settings.keys() {
+            LappService::set_read_only(self.ctx(), &Addr::Lapp(lapp_name.clone()), read_only);
+        }
+    }
+
+    pub fn check_writable(&self) -> ServerResult<()> {
+        if self.read_only {
+            Err(ServerError::ReadOnlyMode)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn new_lapp(&self, lapp_name: impl Into<String>, lapp_settings: LappSettings) -> Lapp {
         let lapp_name = lapp_name.into();
         let lapp_dir = self.lapp_dir(&lapp_name);
+        self.new_lapp_in(lapp_name, lapp_dir, lapp_settings)
+    }
+
+    fn new_lapp_in(&self, lapp_name: String, lapp_dir: impl Into<PathBuf>, lapp_settings: LappSettings) -> Lapp {
+        let mut lapp = Lapp::new(lapp_name, lapp_dir, lapp_settings);
+        lapp.set_read_only(self.read_only);
+        lapp.set_debug(self.debug);
+        lapp.set_mqtt_client(self.mqtt_client.clone());
+        lapp.set_mailer(self.mailer.clone());
+        lapp.set_thread_pool(self.thread_pool.clone());
+        lapp.set_max_memory_mb(self.max_memory_mb);
+        lapp.set_call_time_limit_ms(self.call_time_limit_ms);
+        lapp.set_http_timeout_ms(self.http_timeout_ms);
+        lapp.set_disk_quota_mb(self.disk_quota_mb);
+        lapp.set_mount_paths(self.mount_paths.clone());
+        lapp.set_module_cache(self.module_cache);
+        lapp.set_module_cache_key(self.module_cache_key.clone());
+        lapp.set_package_verifier(self.package_verifier.clone());
+        lapp.set_permission_requests(self.permission_requests.clone());
+        lapp.set_lapp_calls(self.lapp_calls.clone());
+        lapp.set_lapp_bus(self.lapp_bus.clone());
+        lapp.set_lapp_logs(self.lapp_logs.clone());
+        lapp
+    }
+
+    /// Loads and validates the lapp settings. The invalid lapp is unregistered, its errors are kept for the
+    /// management API.
+    pub fn insert_lapp_settings(&mut self, lapp_name: impl Into<String>) -> ServerResult<()> {
+        let lapp_name = lapp_name.into();
+        let lapp_dir = self.lapp_dir(&lapp_name);
+
+        match Lapp::load_valid_settings(&lapp_name, lapp_dir) {
+            Ok(settings) => {
+                self.invalid_lapps.remove(&lapp_name);
+                self.lapp_settings.insert(lapp_name, settings);
+                Ok(())
+            },
+            Err(errors) => {
+                let message = join_errors(&errors);
+                self.lapp_settings.remove(&lapp_name);
+                self.invalid_lapps.insert(lapp_name.clone(), errors);
+                Err(ServerError::LappManifestInvalid(lapp_name, message))
+            },
+        }
+    }
+
+    /// Re-reads the settings of the registered lapp. The permissions newly requested or allowed by the updated config
+    /// are not granted, they are kept pending until the admin allows or denies them.
+    fn reinsert_lapp_settings(&mut self, lapp_name: &str) -> ServerResult<()> {
+        let previous = self
+            .lapp_settings
+            .get(lapp_name)
+            .map(|settings| settings.permissions.clone());
+        self.insert_lapp_settings(lapp_name)?;
 
-        if let Some(settings) = Lapp::load_settings(&lapp_name, lapp_dir) {
-            self.lapp_settings.insert(lapp_name, settings);
+        let Some(previous) = previous else {
+            return Ok(());
+        };
+        let read_only = self.read_only;
+        let settings_path = Lapp::settings_path(self.lapp_dir(lapp_name));
+        let lapp_settings = self.lapp_settings_mut(lapp_name)?;
+        if lapp_settings.permissions.hold_new(&previous) {
+            log::info!(
+                "Lapp '{lapp_name}' permissions {:?} are pending approval",
+                lapp_settings.permissions.pending
+            );
+            if !read_only {
+                lapp_settings.save(settings_path)?;
+            }
         }
+        Ok(())
+    }
+
+    pub fn invalid_lapps(&self) -> Vec<InvalidLapp> {
+        self.invalid_lapps
+            .iter()
+            .map(|(lapp_name, errors)| InvalidLapp {
+                lapp_name: lapp_name.clone(),
+                errors: errors.clone(),
+            })
+            .collect()
     }
 
     pub fn load_lapp_service(
@@ -75,24 +324,405 @@ impl LappsManager {
         lapp_name: impl Into<String>,
         lapp_settings: impl Into<LappSettings>,
     ) -> impl Future<Output = ServerResult<()>> {
+        let lapp_service_addr = Addr::Lapp(lapp_name.into());
+
+        LappService::stop(self.ctx(), &lapp_service_addr);
+
+        let lapp = self.new_lapp(lapp_service_addr.into_lapp_name(), lapp_settings.into());
+        LappService::new(lapp, self.circuit_breaker.clone()).run(self.ctx().clone(), self.http_client.clone())
+    }
+
+    /// Reloads the lapp settings after the lapp files are updated. The returned future restarts the lapp service if it
+    /// was run, it does not borrow the manager.
+    pub fn reload_lapp(&mut self, lapp_name: impl AsRef<str>) -> ServerResult<impl Future<Output = ServerResult<()>>> {
+        let lapp_name = lapp_name.as_ref();
+        let lapp_service_addr = Addr::Lapp(lapp_name.into());
+        let is_run = LappService::is_run(self.ctx(), &lapp_service_addr);
+        LappService::stop(self.ctx(), &lapp_service_addr);
+
+        self.reinsert_lapp_settings(lapp_name)?;
+        let lapp_settings = self.lapp_settings(lapp_name)?.clone();
+
+        if lapp_settings.enabled() && (is_run || lapp_settings.autoload()) {
+            self.check_dependencies(lapp_name)?;
+            let load = self.load_lapp_service(lapp_name.to_string(), lapp_settings);
+            return Ok(Either::Right(load));
+        }
+        Ok(Either::Left(future::ok(())))
+    }
+
+    /// Re-reads the lapp config, the running lapp is re-instantiated only if the settings used by its instance are
+    /// changed. The returned future re-instantiates the lapp, it does not borrow the manager.
+    pub fn reload_lapp_settings(
+        &mut self,
+        lapp_name: impl AsRef<str>,
+    ) -> ServerResult<(SettingsReload, impl Future<Output = ServerResult<()>>)> {
+        let lapp_name = lapp_name.as_ref();
+        let current = self.lapp_settings(lapp_name)?.clone();
+        self.reinsert_lapp_settings(lapp_name)?;
+        let reloaded = self.lapp_settings(lapp_name)?.clone();
+
+        let mut reload = SettingsReload::new(&current, &reloaded);
+        let lapp_service_addr = Addr::Lapp(lapp_name.into());
+        if reload.instance_changed && LappService::is_run(self.ctx(), &lapp_service_addr) {
+            LappService::stop(self.ctx(), &lapp_service_addr);
+
+            if reloaded.enabled() {
+                self.check_dependencies(lapp_name)?;
+                reload.reinstantiated = true;
+                let reinstantiate = self.load_lapp_service(lapp_name.to_string(), reloaded);
+                return Ok((reload, Either::Right(reinstantiate)));
+            }
+        }
+        Ok((reload, Either::Left(future::ok(()))))
+    }
+
+    /// Runs the service of the enabled lapp if it is not run yet.
+    pub fn load_lapp(&self, lapp_name: impl Into<String>) -> impl Future<Output = ServerResult<()>> {
+        let lapp_name = lapp_name.into();
+        let run_lapp_service = self
+            .check_enabled_and_allow_permissions(&lapp_name, &[])
+            .map(|()| self.run_lapp_service_if_needed(lapp_name));
+
+        async move { run_lapp_service?.await.map(drop) }
+    }
+
+    /// Stops the lapp service and frees its instances. The returned future waits for the queued and in-flight
+    /// requests of the lapp to finish, but not longer than the drain timeout, and the new requests fail with the
+    /// unloading error until then. The lapp stays enabled, so the next request after the unload loads it again.
+    pub fn unload_lapp(&self, lapp_name: impl AsRef<str>) -> ServerResult<impl Future<Output = ()>> {
+        let lapp_name = lapp_name.as_ref().to_string();
+        self.lapp_settings(&lapp_name)?;
+
+        let unloading = Arc::clone(&self.unloading);
+        lock_unloading(&unloading).insert(lapp_name.clone());
+        let drained = LappService::drain(self.ctx(), &Addr::Lapp(lapp_name.clone()));
+
+        Ok(async move {
+            if let Some(drained) = drained {
+                if tokio::time::timeout(UNLOAD_DRAIN_TIMEOUT, drained).await.is_err() {
+                    log::warn!("Lapp '{lapp_name}' is unloaded before its requests are finished");
+                }
+            }
+            lock_unloading(&unloading).remove(&lapp_name);
+        })
+    }
+
+    /// Unloads all lapps at once, e.g. on the server shutdown. The returned future waits for the lapp services to
+    /// drain, so the instances and the database connections of the lapps are dropped when it completes.
+    pub fn unload_lapps(&self) -> impl Future<Output = ()> {
+        let unloads: Vec<_> = self
+            .lapp_settings
+            .keys()
+            .filter_map(|lapp_name| self.unload_lapp(lapp_name).ok())
+            .collect();
+
+        future::join_all(unloads).map(drop)
+    }
+
+    /// Keeps the uploaded package of the installed lapp until the upgrade is confirmed and returns the changes of
+    /// the required permissions.
+    pub fn stage_upgrade(&mut self, lapp_name: impl Into<String>, package: NamedTempFile) -> ServerResult<UpgradeDiff> {
+        self.check_writable()?;
+
+        let lapp_name = lapp_name.into();
+        self.package_verifier
+            .verify_package(&lapp_name, &mut ZipArchive::new(package.as_file())?)?;
+        let upgrade = LappUpgrade::new(&lapp_name, package)?;
+        let diff = upgrade.diff(self.lapp_settings(&lapp_name)?);
+
+        self.upgrades.insert(lapp_name, upgrade);
+        Ok(diff)
+    }
+
+    pub fn compare_upgrade(&self, lapp_name: impl AsRef<str>) -> ServerResult<UpgradeDiff> {
+        let lapp_name = lapp_name.as_ref();
+        let upgrade = self
+            .upgrades
+            .get(lapp_name)
+            .ok_or_else(|| ServerError::LappUpgradeNotFound(lapp_name.into()))?;
+
+        Ok(upgrade.diff(self.lapp_settings(lapp_name)?))
+    }
+
+    pub fn cancel_upgrade(&mut self, lapp_name: impl AsRef<str>) -> ServerResult<()> {
+        let lapp_name = lapp_name.as_ref();
+        self.upgrades
+            .remove(lapp_name)
+            .map(drop)
+            .ok_or_else(|| ServerError::LappUpgradeNotFound(lapp_name.into()))
+    }
+
+    /// Installs the confirmed upgrade over the lapp directory. The returned future loads the upgraded lapp if it was
+    /// run, it does not borrow the manager. The lapp that fails to load is restored by `restore_previous_version`, see
+    /// `LappsProvider::apply_upgrade`.
+    pub fn apply_upgrade(
+        &mut self,
+        lapp_name: impl AsRef<str>,
+    ) -> ServerResult<(UpgradeDiff, impl Future<Output = ServerResult<()>>)> {
+        self.check_writable()?;
+
+        let lapp_name = lapp_name.as_ref();
+        let diff = self.compare_upgrade(lapp_name)?;
+        let upgrade = self
+            .upgrades
+            .remove(lapp_name)
+            .ok_or_else(|| ServerError::LappUpgradeNotFound(lapp_name.into()))?;
+        let settings = upgrade.merge_settings(self.lapp_settings(lapp_name)?);
+
+        let lapp_dir = self.lapp_dir(lapp_name);
+        let lapp_service_addr = Addr::Lapp(lapp_name.into());
+        let is_run = LappService::is_run(self.ctx(), &lapp_service_addr);
+        LappService::stop(self.ctx(), &lapp_service_addr);
+
+        upgrade.install(&lapp_dir)?;
+        settings.save(Lapp::settings_path(&lapp_dir))?;
+        self.lapp_settings.insert(lapp_name.into(), settings.clone());
+
+        if settings.enabled() && (is_run || settings.autoload()) {
+            let load = self.load_lapp_service(lapp_name.to_string(), settings);
+            return Ok((diff, Either::Right(load)));
+        }
+        Ok((diff, Either::Left(future::ok(()))))
+    }
+
+    /// Smoke-tests the staged upgrade side by side with the running version: the package is extracted to the staging
+    /// slot, instantiated with the lapp data and checked by the `health` export, if the lapp exports it. The returned
+    /// future does not borrow the manager, so the running version keeps serving the requests meanwhile. The upgrade
+    /// that passed the check is installed by `apply_upgrade`, the failed one stays staged.
+    pub fn check_upgrade_health(
+        &self,
+        lapp_name: impl Into<String>,
+    ) -> ServerResult<impl Future<Output = ServerResult<()>>> {
+        self.check_writable()?;
+
         let lapp_name = lapp_name.into();
+        let upgrade = self
+            .upgrades
+            .get(&lapp_name)
+            .ok_or_else(|| ServerError::LappUpgradeNotFound(lapp_name.clone()))?;
+        let current_settings = self.lapp_settings(&lapp_name)?;
         let lapp_dir = self.lapp_dir(&lapp_name);
-        let lapp_service_addr = Addr::Lapp(lapp_name);
 
+        // The staged instance uses the data of the running version and leaves no traces in the staging slot
+        let mut settings = upgrade.merge_settings(current_settings);
+        settings.application.data_dir = Lapp::data_dir_path(&lapp_dir, current_settings);
+        settings.database.get_or_insert_with(Default::default).path =
+            Some(Lapp::database_path(&lapp_dir, current_settings));
+        settings.application.snapshot = false;
+        settings.application.record_trace = false;
+
+        let staging_dir = upgrade.extract_staging(&lapp_dir)?;
+        let mut staged_lapp = self.new_lapp_in(lapp_name.clone(), staging_dir, settings);
+        let http_client = self.http_client.clone();
+
+        Ok(async move {
+            let health = match staged_lapp.instantiate(http_client).await {
+                Ok(()) => staged_lapp.health().await,
+                Err(err) => Err(err),
+            };
+            drop(staged_lapp);
+            LappUpgrade::remove_staging(&lapp_dir)?;
+
+            match health {
+                Ok(None | Some(Ok(()))) => Ok(()),
+                Ok(Some(Err(reason))) => Err(ServerError::LappHealthCheckFailed(lapp_name, reason)),
+                Err(err) => Err(ServerError::LappHealthCheckFailed(lapp_name, err.to_string())),
+            }
+        })
+    }
+
+    /// Restores the lapp version replaced by the last upgrade. The returned future reloads the lapp, it does not borrow
+    /// the manager.
+    pub fn rollback_lapp(
+        &mut self,
+        lapp_name: impl AsRef<str>,
+    ) -> ServerResult<impl Future<Output = ServerResult<()>>> {
+        self.check_writable()?;
+
+        let lapp_name = lapp_name.as_ref();
+        self.lapp_settings(lapp_name)?;
+
+        let lapp_service_addr = Addr::Lapp(lapp_name.into());
+        let is_run = LappService::is_run(self.ctx(), &lapp_service_addr);
         LappService::stop(self.ctx(), &lapp_service_addr);
 
-        let lapp = Lapp::new(lapp_service_addr.into_lapp_name(), lapp_dir, lapp_settings.into());
-        LappService::new(lapp).run(self.ctx().clone(), self.http_client.clone())
+        self.restore_previous_version(lapp_name, is_run)
+    }
+
+    /// Restores the lapp version replaced by the last upgrade, the returned future loads it if the lapp was run.
+    pub fn restore_previous_version(
+        &mut self,
+        lapp_name: &str,
+        is_run: bool,
+    ) -> ServerResult<impl Future<Output = ServerResult<()>>> {
+        LappUpgrade::rollback(lapp_name, self.lapp_dir(lapp_name))?;
+        self.insert_lapp_settings(lapp_name)?;
+        log::info!("Lapp '{lapp_name}' is rolled back to the previous version");
+
+        let settings = self.lapp_settings(lapp_name)?.clone();
+        if settings.enabled() && (is_run || settings.autoload()) {
+            let load = self.load_lapp_service(lapp_name.to_string(), settings);
+            return Ok(Either::Right(load));
+        }
+        Ok(Either::Left(future::ok(())))
+    }
+
+    /// Copies the installed lapp under the new name with the empty data, so several isolated instances of the same
+    /// application can run side by side. The duplicate is not signed by the publisher, so it is refused when the
+    /// package signatures are checked.
+    pub fn duplicate_lapp(&mut self, lapp_name: impl AsRef<str>, new_lapp_name: impl Into<String>) -> ServerResult<()> {
+        self.check_writable()?;
+
+        let lapp_name = lapp_name.as_ref();
+        let new_lapp_name = new_lapp_name.into();
+        let settings = self.lapp_settings(lapp_name)?;
+        if self.lapp_settings.contains_key(&new_lapp_name) {
+            return Err(ServerError::LappAlreadyExists(new_lapp_name));
+        }
+        if self.package_verifier.is_enabled() {
+            return Err(ServerError::LappSignatureInvalid(
+                new_lapp_name,
+                "the duplicate is not signed by the publisher".into(),
+            ));
+        }
+
+        duplicate_lapp_dir(
+            lapp_name,
+            &self.lapp_dir(lapp_name),
+            settings,
+            &new_lapp_name,
+            &self.lapp_dir(&new_lapp_name),
+        )?;
+        self.insert_lapp_settings(new_lapp_name)
     }
 
+    /// Stops the lapp and removes its files. The data directory and the database are kept unless `purge` is set,
+    /// so the lapp installed again with the same name gets its data back.
+    pub async fn uninstall_lapp(&mut self, lapp_name: impl AsRef<str>, purge: bool) -> ServerResult<()> {
+        self.check_writable()?;
+
+        let lapp_name = lapp_name.as_ref();
+        let settings = self.lapp_settings(lapp_name)?.clone();
+        let dependents: Vec<_> = self
+            .lapp_settings
+            .iter()
+            .filter(|(_, settings)| {
+                settings.enabled()
+                    && settings
+                        .dependencies()
+                        .iter()
+                        .any(|dependency| dependency.lapp_name == lapp_name)
+            })
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if !dependents.is_empty() {
+            return Err(ServerError::LappHasDependents(lapp_name.into(), dependents.join(", ")));
+        }
+
+        LappService::stop(self.ctx(), &Addr::Lapp(lapp_name.into()));
+        self.upgrades.remove(lapp_name);
+
+        let lapp_dir = self.lapp_dir(lapp_name);
+        let data_dir_path = Lapp::data_dir_path(&lapp_dir, &settings);
+        let database_path = Lapp::database_path(&lapp_dir, &settings);
+        let is_kept = |path: &Path| !purge && (data_dir_path.starts_with(path) || database_path.starts_with(path));
+
+        // The data directory and the database may be configured outside of the lapp directory, such paths are
+        // never purged
+        if purge {
+            let lapp_root = fs::canonicalize(lapp_dir.root_dir()).await?;
+            for path in [&data_dir_path, &database_path] {
+                if !path.exists() {
+                    continue;
+                }
+
+                if !is_inside_dir(&lapp_root, path).await {
+                    log::error!(
+                        "Path '{}' of lapp '{lapp_name}' is outside of the lapp directory, it is not purged",
+                        path.display()
+                    );
+                } else if path.is_dir() {
+                    fs::remove_dir_all(path).await?;
+                } else {
+                    fs::remove_file(path).await?;
+                }
+            }
+        }
+
+        let mut is_empty = true;
+        let mut read_dir = fs::read_dir(lapp_dir.root_dir()).await?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            let path = entry.path();
+            if is_kept(&path) {
+                is_empty = false;
+            } else if entry.file_type().await?.is_dir() {
+                fs::remove_dir_all(&path).await?;
+            } else {
+                fs::remove_file(&path).await?;
+            }
+        }
+        if is_empty {
+            fs::remove_dir(lapp_dir.root_dir()).await?;
+        }
+
+        self.lapp_settings.remove(lapp_name);
+        log::info!("Lapp '{lapp_name}' is uninstalled");
+        Ok(())
+    }
+
+    /// Loads the autoload lapps and their dependencies, every lapp is loaded after its dependencies.
     pub async fn autoload_lapps(&self) {
-        for (name, settings) in &self.lapp_settings {
-            if !Lapp::is_main(name) && settings.enabled() && settings.autoload() {
-                log::info!("Autoload lapp '{name}'");
+        let (order, unresolved) = lapp::load_order(&self.lapp_settings);
+        for name in unresolved {
+            log::error!("Lapp '{name}' is not loaded: its dependencies form a cycle");
+        }
 
-                self.load_lapp_service(name, settings.clone())
-                    .await
-                    .expect("Lapp should be loaded");
+        let mut required = HashSet::new();
+        for name in order.iter().rev() {
+            let settings = &self.lapp_settings[name];
+            if !Lapp::is_main(name) && settings.enabled() && (settings.autoload() || required.contains(name.as_str())) {
+                required.insert(name.as_str());
+                required.extend(
+                    settings
+                        .dependencies()
+                        .iter()
+                        .map(|dependency| dependency.lapp_name.as_str()),
+                );
+            }
+        }
+
+        let mut failed = HashSet::new();
+        for name in &order {
+            let settings = &self.lapp_settings[name];
+            if Lapp::is_main(name) || !settings.enabled() || !required.contains(name.as_str()) {
+                continue;
+            }
+
+            let failed_dependency = settings
+                .dependencies()
+                .iter()
+                .find(|dependency| failed.contains(&dependency.lapp_name));
+            if let Some(dependency) = failed_dependency {
+                log::error!(
+                    "Lapp '{name}' is not loaded: dependency '{}' is not loaded",
+                    dependency.lapp_name
+                );
+                failed.insert(name.clone());
+                continue;
+            }
+            if let Err(err) = self.check_dependencies(name) {
+                log::error!("{err}");
+                failed.insert(name.clone());
+                continue;
+            }
+
+            // The failed lapp stays unloaded, and its dependents are not loaded after it
+            log::info!("Autoload lapp '{name}'");
+            if let Err(err) = self.load_lapp_service(name, settings.clone()).await {
+                log::error!("Lapp '{name}' is not loaded: {err}");
+                LappService::stop(self.ctx(), &Addr::Lapp(name.clone()));
+                failed.insert(name.clone());
             }
         }
     }
@@ -102,6 +732,9 @@ impl LappsManager {
         lapp_name: impl Into<String>,
     ) -> impl Future<Output = ServerResult<Sender<LappServiceMessage>>> {
         let lapp_name = lapp_name.into();
+        if lock_unloading(&self.unloading).contains(&lapp_name) {
+            return Either::Left(future::err(ServerError::LappUnloading(lapp_name)));
+        }
         let lapp_settings = match self.lapp_settings(&lapp_name) {
             Ok(lapp_settings) => lapp_settings,
             Err(err) => return Either::Left(future::err(err)),
@@ -111,12 +744,15 @@ impl LappsManager {
         match self.ctx().get_actor_sender::<LappServiceMessage>(&lapp_service_addr) {
             Some(sender) => Either::Left(future::ok(sender)),
             None => {
-                let lapp_name = lapp_service_addr.as_lapp_name();
-                let lapp_dir = self.lapp_dir(lapp_name);
-                let lapp = Lapp::new(lapp_name, lapp_dir, lapp_settings.clone());
+                if let Err(err) = self.check_dependencies(lapp_service_addr.as_lapp_name()) {
+                    return Either::Left(future::err(err));
+                }
+
+                let lapp = self.new_lapp(lapp_service_addr.as_lapp_name(), lapp_settings.clone());
                 let ctx = self.ctx().clone();
 
-                let run_fut = LappService::new(lapp).run(ctx.clone(), self.http_client.clone());
+                let run_fut =
+                    LappService::new(lapp, self.circuit_breaker.clone()).run(ctx.clone(), self.http_client.clone());
                 Either::Right(run_fut.map_ok(move |()| ctx.actor_sender::<LappServiceMessage>(lapp_service_addr)))
             },
         }
@@ -127,8 +763,97 @@ impl LappsManager {
         lapp_name: impl Into<String>,
         request: http::Request,
     ) -> impl Future<Output = ServerResult<http::Response>> {
-        let lapp_name = lapp_name.into();
         let (message, response_in) = LappServiceMessage::new_http(request);
+        self.send_to_lapp_service(lapp_name, message, response_in)
+    }
+
+    /// Renders the error page by the `error_page` export of the lapp if its service is running.
+    pub fn error_page(
+        &self,
+        lapp_name: impl Into<String>,
+        request: http::ErrorPageRequest,
+    ) -> impl Future<Output = ServerResult<Option<String>>> {
+        let lapp_name = lapp_name.into();
+        if !LappService::is_run(self.ctx(), &Addr::Lapp(lapp_name.clone())) {
+            return Either::Left(future::ok(None));
+        }
+
+        let (message, page_in) = LappServiceMessage::new_error_page(request);
+        Either::Right(self.send_to_lapp_service(lapp_name, message, page_in))
+    }
+
+    pub fn graphql_schema(&self, lapp_name: impl Into<String>) -> impl Future<Output = ServerResult<Option<String>>> {
+        let (message, schema_in) = LappServiceMessage::new_graphql_schema();
+        self.send_to_lapp_service(lapp_name, message, schema_in)
+    }
+
+    pub fn process_graphql(
+        &self,
+        lapp_name: impl Into<String>,
+        request: graphql::Request,
+    ) -> impl Future<Output = ServerResult<String>> {
+        let (message, response_in) = LappServiceMessage::new_graphql(request);
+        self.send_to_lapp_service(lapp_name, message, response_in)
+    }
+
+    /// Runs the scheduled job by the `on_schedule` export of the lapp, `None` if the lapp does not export it.
+    pub fn run_schedule(
+        &self,
+        lapp_name: impl Into<String>,
+        job_name: impl Into<String>,
+    ) -> impl Future<Output = ServerResult<Option<ScheduleResult>>> {
+        let (message, result_in) = LappServiceMessage::new_schedule(job_name);
+        self.send_to_lapp_service(lapp_name, message, result_in)
+    }
+
+    /// Sends the MQTT message to the lapp service without waiting for the handling.
+    pub fn send_mqtt(
+        &self,
+        lapp_name: impl Into<String>,
+        message: mqtt::Message,
+    ) -> impl Future<Output = ServerResult<()>> {
+        let lapp_name = lapp_name.into();
+
+        self.run_lapp_service_if_needed(lapp_name.clone())
+            .and_then(move |lapp_service_sender| {
+                let send_result = lapp_service_sender
+                    .send(LappServiceMessage::Mqtt(message))
+                    .map_err(|err| {
+                        log::error!("Error occurs when send to lapp service: {err:?}");
+                        ServerError::LappServiceSendError(lapp_name)
+                    });
+                future::ready(send_result)
+            })
+    }
+
+    /// Queues the message of the bus to the lapp service without waiting for the handling.
+    pub fn send_lapp_message(
+        &self,
+        lapp_name: impl Into<String>,
+        sender: String,
+        payload: Vec<u8>,
+    ) -> impl Future<Output = ServerResult<()>> {
+        let lapp_name = lapp_name.into();
+
+        self.run_lapp_service_if_needed(lapp_name.clone())
+            .and_then(move |lapp_service_sender| {
+                let send_result = lapp_service_sender
+                    .send(LappServiceMessage::LappMessage(LappMessage { sender, payload }))
+                    .map_err(|err| {
+                        log::error!("Error occurs when send to lapp service: {err:?}");
+                        ServerError::LappServiceSendError(lapp_name)
+                    });
+                future::ready(send_result)
+            })
+    }
+
+    fn send_to_lapp_service<T>(
+        &self,
+        lapp_name: impl Into<String>,
+        message: LappServiceMessage,
+        response_in: oneshot::Receiver<ServerResult<T>>,
+    ) -> impl Future<Output = ServerResult<T>> {
+        let lapp_name = lapp_name.into();
 
         self.run_lapp_service_if_needed(lapp_name.clone())
             .and_then(move |lapp_service_sender| {
@@ -152,6 +877,81 @@ impl LappsManager {
         LappDir(self.lapps_path.join(lapp_name.as_ref()))
     }
 
+    pub fn lapp_data_dir_path(&self, lapp_name: impl AsRef<str> + ToString) -> ServerResult<PathBuf> {
+        let lapp_settings = self.lapp_settings(lapp_name.as_ref())?;
+        Ok(Lapp::data_dir_path(self.lapp_dir(lapp_name.as_ref()), lapp_settings))
+    }
+
+    pub fn lapp_database_path(&self, lapp_name: impl AsRef<str> + ToString) -> ServerResult<PathBuf> {
+        let lapp_settings = self.lapp_settings(lapp_name.as_ref())?;
+        Ok(Lapp::database_path(self.lapp_dir(lapp_name.as_ref()), lapp_settings))
+    }
+
+    /// The disk usage of the lapp data with the effective quota, the lower of the lapp and server ones.
+    pub fn lapp_disk_usage(&self, lapp_name: impl AsRef<str> + ToString) -> ServerResult<DiskUsage> {
+        let lapp_settings = self.lapp_settings(lapp_name.as_ref())?;
+        let lapp_dir = self.lapp_dir(lapp_name.as_ref());
+        let used_bytes = disk_usage(
+            &Lapp::data_dir_path(&lapp_dir, lapp_settings),
+            &Lapp::database_path(&lapp_dir, lapp_settings),
+        );
+        let quota_mb = lapp_settings
+            .resources()
+            .disk_quota_mb
+            .into_iter()
+            .chain(self.disk_quota_mb)
+            .min();
+
+        Ok(DiskUsage {
+            lapp_name: lapp_name.to_string(),
+            used_bytes,
+            quota_bytes: quota_mb.map(|quota_mb| quota_mb.saturating_mul(1 << 20)),
+        })
+    }
+
+    pub fn lapp_database_backup_dir(&self, lapp_name: impl AsRef<str> + ToString) -> ServerResult<PathBuf> {
+        let lapp_settings = self.lapp_settings(lapp_name.as_ref())?;
+        Ok(Lapp::database_backup_dir(
+            self.lapp_dir(lapp_name.as_ref()),
+            lapp_settings,
+        ))
+    }
+
+    /// The maximum size in bytes of the HTTP request body of the lapp, the lower of the lapp and server limits.
+    pub fn lapp_max_body_size(&self, lapp_name: impl AsRef<str> + ToString) -> ServerResult<Option<u64>> {
+        let max_body_mb = self
+            .lapp_settings(lapp_name)?
+            .resources()
+            .max_body_mb
+            .into_iter()
+            .chain(self.max_body_mb)
+            .min();
+        Ok(max_body_mb.map(|max_body_mb| max_body_mb.saturating_mul(1 << 20)))
+    }
+
+    /// The timeout of the HTTP request processing of the lapp, the lower of the lapp and server timeouts.
+    pub fn lapp_http_timeout(&self, lapp_name: impl AsRef<str> + ToString) -> ServerResult<Option<Duration>> {
+        let http_timeout_ms = self
+            .lapp_settings(lapp_name)?
+            .resources()
+            .http_timeout_ms
+            .into_iter()
+            .chain(self.http_timeout_ms)
+            .min();
+        Ok(http_timeout_ms.map(Duration::from_millis))
+    }
+
+    pub fn lapp_migrations(&self, lapp_name: impl AsRef<str> + ToString) -> ServerResult<LappMigrations> {
+        let lapp_settings = self.lapp_settings(lapp_name.as_ref())?;
+        let lapp_dir = self.lapp_dir(lapp_name.as_ref());
+        let migrations = migrations_status(&lapp_dir, Lapp::database_path(&lapp_dir, lapp_settings))?;
+
+        Ok(LappMigrations {
+            lapp_name: lapp_name.to_string(),
+            migrations,
+        })
+    }
+
     pub fn lapp_settings(&self, lapp_name: impl AsRef<str> + ToString) -> ServerResult<&LappSettings> {
         let lapp_settings = self
             .lapp_settings
@@ -193,24 +993,142 @@ impl LappsManager {
         Ok(())
     }
 
-    pub async fn update_lapp_settings(&mut self, query: UpdateQuery) -> ServerResult<UpdateQuery> {
+    /// Checks that the dependencies of the lapp are installed, enabled and match the version requirements.
+    pub fn check_dependencies(&self, lapp_name: impl AsRef<str>) -> ServerResult<()> {
+        let lapp_name = lapp_name.as_ref();
+        let errors = lapp::check_dependencies(lapp_name, &self.lapp_settings);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+            Err(ServerError::LappDependenciesNotSatisfied(
+                lapp_name.into(),
+                errors.join("; "),
+            ))
+        }
+    }
+
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        DependencyGraph::new(&self.lapp_settings)
+    }
+
+    /// Updates the lapp settings, the permission changes are recorded to the audit log with the actor. The permission
+    /// profile is applied before the allowed and denied permissions of the query. The returned future re-instantiates
+    /// the running lapp when the settings used by its instance are changed.
+    pub fn update_lapp_settings(
+        &mut self,
+        mut query: UpdateQuery,
+        actor: &str,
+    ) -> ServerResult<(UpdateQuery, impl Future<Output = ServerResult<()>>)> {
+        self.check_writable()?;
+        if query.enabled == Some(true) {
+            self.check_dependencies(&query.lapp_name)?;
+            self.circuit_breaker.reset(&query.lapp_name);
+        }
+        let profile = match &query.permission_profile {
+            Some(profile_name) => Some(
+                self.permission_profiles
+                    .get(profile_name)
+                    .cloned()
+                    .ok_or_else(|| ServerError::PermissionProfileNotFound(profile_name.clone()))?,
+            ),
+            None => None,
+        };
+
         let ctx = self.ctx().clone();
         let lapp_name = query.lapp_name.clone();
         let lapp_dir = self.lapp_dir(&lapp_name);
+        let permission_audit = self.permission_audit.clone();
         let lapp_settings = self.lapp_settings_mut(&lapp_name)?;
 
+        let old_settings = lapp_settings.clone();
+        if let Some(profile) = profile {
+            if !lapp_settings.permissions.apply_profile(&profile) {
+                query.permission_profile = None;
+            }
+        }
         let updated = lapp_settings.update(query, Lapp::settings_path(lapp_dir))?;
 
-        if updated.is_applied() {
-            let lapp_service_actor_id = Addr::Lapp(lapp_name);
-            if LappService::is_run(&ctx, &lapp_service_actor_id) && lapp_settings.enabled() {
-                LappService::stop(&ctx, &lapp_service_actor_id);
-                let lapp_settings = lapp_settings.clone();
-                self.load_lapp_service(lapp_service_actor_id.into_lapp_name(), lapp_settings)
-                    .await?;
+        if let Some(permission_audit) = permission_audit {
+            for (permission, old_state, new_state) in
+                PermissionState::changes(&old_settings.permissions, &lapp_settings.permissions)
+            {
+                permission_audit.record(&lapp_name, actor, permission, old_state, new_state)?;
             }
         }
 
-        Ok(updated)
+        // The allowed permissions define the instance imports, so the running lapp is re-instantiated with them
+        let lapp_service_addr = Addr::Lapp(lapp_name);
+        let is_reinstantiated = is_instance_changed(&old_settings, lapp_settings)
+            && lapp_settings.enabled()
+            && LappService::is_run(&ctx, &lapp_service_addr);
+        if !is_reinstantiated {
+            return Ok((updated, Either::Left(future::ok(()))));
+        }
+
+        LappService::stop(&ctx, &lapp_service_addr);
+        let lapp_settings = lapp_settings.clone();
+        let reinstantiate = self.load_lapp_service(lapp_service_addr.into_lapp_name(), lapp_settings);
+        Ok((updated, Either::Right(reinstantiate)))
+    }
+
+    /// Adds the permission requested by the running lapp to its required and pending permissions, so the admin
+    /// approves or rejects it. The permission that is already required is not requested again.
+    pub fn request_permission(&mut self, lapp_name: &str, permission: Permission) -> ServerResult<()> {
+        self.check_writable()?;
+        let settings_path = Lapp::settings_path(self.lapp_dir(lapp_name));
+        let lapp_settings = self.lapp_settings_mut(lapp_name)?;
+        if lapp_settings.permissions.required.contains(&permission) {
+            return Ok(());
+        }
+
+        lapp_settings.permissions.required.push(permission);
+        lapp_settings.permissions.pending.push(permission);
+        lapp_settings.save(settings_path)?;
+        log::info!(
+            "Lapp '{lapp_name}' requests permission '{}' pending approval",
+            permission.as_str()
+        );
+        Ok(())
     }
+
+    /// Returns the permission changes of the lapp recorded to the audit log, the log of the uninstalled lapp is kept.
+    pub fn permission_audit(&self, lapp_name: &str) -> ServerResult<Vec<PermissionAuditEntry>> {
+        match &self.permission_audit {
+            Some(permission_audit) => permission_audit.entries(lapp_name),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Stops the service of the repeatedly failing lapp and disables the lapp until it is enabled again.
+    pub fn quarantine_lapp(&mut self, lapp_name: impl AsRef<str>) -> ServerResult<()> {
+        let lapp_name = lapp_name.as_ref();
+        LappService::stop(self.ctx(), &Addr::Lapp(lapp_name.into()));
+
+        let read_only = self.read_only;
+        let settings_path = Lapp::settings_path(self.lapp_dir(lapp_name));
+        let lapp_settings = self.lapp_settings_mut(lapp_name)?;
+        lapp_settings.quarantine();
+
+        if !read_only {
+            lapp_settings.save(settings_path)?;
+        }
+        Ok(())
+    }
+}
+
+fn lock_unloading(unloading: &Mutex<HashSet<String>>) -> MutexGuard<'_, HashSet<String>> {
+    unloading.lock().expect("Unloading lapps lock should not be poisoned")
+}
+
+/// Whether the path is inside the directory and is not the directory itself, the symlinks are resolved before the
+/// check.
+async fn is_inside_dir(dir: &Path, path: &Path) -> bool {
+    fs::canonicalize(path)
+        .await
+        .is_ok_and(|path| path != dir && path.starts_with(dir))
+}
+
+fn join_errors(errors: &[ManifestError]) -> String {
+    errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
 }
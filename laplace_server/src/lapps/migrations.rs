@@ -0,0 +1,149 @@
+//! The SQL migrations of the lapp database.
+//!
+//! The lapp ships the `migrations` directory of the `.sql` files, which are applied in the order of their names. The
+//! pending migrations are applied on the instantiation of the lapp in a single transaction, so the failed migration
+//! leaves the database unchanged and fails the instantiation. The applied migrations are recorded in the
+//! `_laplace_migrations` table and are never applied again, so the applied file should not be changed.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use laplace_common::api::MigrationStatus;
+use laplace_common::lapp::FtsTableSettings;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+
+use crate::error::{ServerError, ServerResult};
+
+pub const MIGRATIONS_DIR: &str = "migrations";
+pub const MIGRATIONS_TABLE: &str = "_laplace_migrations";
+
+/// Applies the pending migrations, returns the names of the applied ones.
+pub fn apply_migrations(lapp_dir: impl AsRef<Path>, database_path: impl AsRef<Path>) -> ServerResult<Vec<String>> {
+    let files = migration_files(lapp_dir)?;
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut connection = Connection::open(database_path)?;
+    connection.execute(
+        &format!("CREATE TABLE IF NOT EXISTS {MIGRATIONS_TABLE} (name TEXT PRIMARY KEY, applied_at INTEGER NOT NULL)"),
+        [],
+    )?;
+    let applied = applied_migrations(&connection)?;
+
+    let transaction = connection.transaction()?;
+    let mut applied_names = Vec::new();
+    for (name, path) in files.into_iter().filter(|(name, _)| !applied.contains_key(name)) {
+        let sql = fs::read_to_string(&path)?;
+        transaction
+            .execute_batch(&sql)
+            .map_err(|err| ServerError::LappInitError(format!("Migration '{name}' error: {err}")))?;
+        transaction.execute(
+            &format!("INSERT INTO {MIGRATIONS_TABLE} (name, applied_at) VALUES (?1, ?2)"),
+            params![name, now_millis()],
+        )?;
+        applied_names.push(name);
+    }
+    transaction.commit()?;
+
+    Ok(applied_names)
+}
+
+/// Creates the FTS5 tables of the lapp settings which do not exist yet. The tables are created after the migrations,
+/// so the external content tables may be created by the migrations.
+pub fn create_fts_tables(database_path: impl AsRef<Path>, fts_tables: &[FtsTableSettings]) -> ServerResult<()> {
+    if fts_tables.is_empty() {
+        return Ok(());
+    }
+
+    let connection = Connection::open(database_path)?;
+    for fts_table in fts_tables {
+        connection
+            .execute_batch(&fts_table.create_table_sql())
+            .map_err(|err| ServerError::LappInitError(format!("FTS table '{}' error: {err}", fts_table.name)))?;
+    }
+    Ok(())
+}
+
+/// Returns the migration files with the applied ones, including the applied migrations whose files are removed.
+pub fn migrations_status(
+    lapp_dir: impl AsRef<Path>,
+    database_path: impl AsRef<Path>,
+) -> ServerResult<Vec<MigrationStatus>> {
+    let database_path = database_path.as_ref();
+    let mut applied = if database_path.exists() {
+        let connection = Connection::open_with_flags(
+            database_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+        )?;
+        applied_migrations(&connection)?
+    } else {
+        HashMap::new()
+    };
+
+    let mut migrations: Vec<_> = migration_files(lapp_dir)?
+        .into_iter()
+        .map(|(name, _)| {
+            let applied_at = applied.remove(&name);
+            MigrationStatus { name, applied_at }
+        })
+        .collect();
+    migrations.extend(applied.into_iter().map(|(name, applied_at)| MigrationStatus {
+        name,
+        applied_at: Some(applied_at),
+    }));
+    migrations.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(migrations)
+}
+
+/// Returns the `.sql` files of the migrations directory sorted by name.
+fn migration_files(lapp_dir: impl AsRef<Path>) -> ServerResult<Vec<(String, PathBuf)>> {
+    let migrations_dir = lapp_dir.as_ref().join(MIGRATIONS_DIR);
+    if !migrations_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(migrations_dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|extension| extension == "sql") {
+            if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                files.push((name.to_string(), path.clone()));
+            }
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+fn applied_migrations(connection: &Connection) -> rusqlite::Result<HashMap<String, i64>> {
+    let is_table_exists = connection
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [MIGRATIONS_TABLE],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if !is_table_exists {
+        return Ok(HashMap::new());
+    }
+
+    let mut stmt = connection.prepare(&format!("SELECT name, applied_at FROM {MIGRATIONS_TABLE}"))?;
+    let mut applied = HashMap::new();
+    for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+        let (name, applied_at) = row?;
+        applied.insert(name, applied_at);
+    }
+    Ok(applied)
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as i64)
+}
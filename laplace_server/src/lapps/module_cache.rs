@@ -0,0 +1,145 @@
+//! Cache of the compiled lapp server modules.
+//!
+//! The compilation of a large module takes seconds, so the compiled artifact is saved to the lapp directory as
+//! `{lapp_name}_server.{hash}.cwasm`, where the hash is the SHA-256 of the wasm file. The next instantiation of the
+//! same module loads the artifact instead of compiling the module again. The artifact produced by another wasmtime
+//! version or engine configuration is rejected on load, then the module is compiled and the artifact is replaced.
+//!
+//! The artifact is native code, and the lapp directory is written by the lapp packages, backups and mounts, so the
+//! artifact starts with the HMAC of the module hash and the compiled module. The HMAC key is kept in the server state
+//! directory, so the artifact that is not produced by the server is not loaded.
+
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use ring::rand::{self, SecureRandom};
+use ring::{digest, hmac};
+use wasmtime::{Engine, Module};
+
+use crate::auth::write_private_file;
+
+const ARTIFACT_EXTENSION: &str = "cwasm";
+const KEY_FILE: &str = "module_cache.key";
+const KEY_LEN: usize = 32;
+const TAG_LEN: usize = 32;
+const HASH_LEN: usize = 32;
+
+/// The key of the HMAC that signs the artifacts of the compiled modules.
+#[derive(Clone)]
+pub struct ModuleCacheKey(hmac::Key);
+
+impl ModuleCacheKey {
+    /// Loads the key from the state directory or generates it at the first run.
+    pub fn load_or_generate(state_dir: &Path) -> io::Result<Self> {
+        let key_path = state_dir.join(KEY_FILE);
+        let key = match fs::read(&key_path) {
+            Ok(key) if key.len() == KEY_LEN => key,
+            _ => {
+                let mut key = vec![0; KEY_LEN];
+                rand::SystemRandom::new()
+                    .fill(&mut key)
+                    .map_err(|_| io::Error::other("module cache key is not generated"))?;
+                fs::create_dir_all(state_dir)?;
+                write_private_file(&key_path, &key)?;
+                key
+            },
+        };
+        Ok(Self(hmac::Key::new(hmac::HMAC_SHA256, &key)))
+    }
+}
+
+pub struct ModuleCache<'a> {
+    lapp_dir: &'a Path,
+    lapp_name: &'a str,
+    key: &'a ModuleCacheKey,
+}
+
+impl<'a> ModuleCache<'a> {
+    pub fn new(lapp_dir: &'a Path, lapp_name: &'a str, key: &'a ModuleCacheKey) -> Self {
+        Self {
+            lapp_dir,
+            lapp_name,
+            key,
+        }
+    }
+
+    /// Loads the cached artifact of the module or compiles it, the new artifact is saved if `is_save` is set.
+    pub fn load_or_compile(&self, engine: &Engine, wasm_bytes: &[u8], is_save: bool) -> wasmtime::Result<Module> {
+        let hash = digest::digest(&digest::SHA256, wasm_bytes);
+        let artifact_file = self.artifact_file(hash.as_ref());
+
+        if artifact_file.exists() {
+            match self.load(engine, &artifact_file, hash.as_ref()) {
+                Ok(module) => {
+                    log::debug!("Lapp '{}' module is loaded from the cache", self.lapp_name);
+                    return Ok(module);
+                },
+                Err(err) => log::warn!("Cached module of lapp '{}' is not loaded: {err}", self.lapp_name),
+            }
+        }
+
+        let module = Module::new(engine, wasm_bytes)?;
+        if is_save {
+            if let Err(err) = self.save(&artifact_file, hash.as_ref(), &module) {
+                log::warn!("Compiled module of lapp '{}' is not cached: {err}", self.lapp_name);
+            }
+        }
+        Ok(module)
+    }
+
+    fn artifact_file(&self, hash: &[u8]) -> PathBuf {
+        self.lapp_dir.join(format!(
+            "{}_server.{}.{ARTIFACT_EXTENSION}",
+            self.lapp_name,
+            hex::encode(hash)
+        ))
+    }
+
+    /// Loads the artifact signed by the server for the module with the hash.
+    fn load(&self, engine: &Engine, artifact_file: &Path, hash: &[u8]) -> wasmtime::Result<Module> {
+        let artifact = fs::read(artifact_file)?;
+        if artifact.len() < TAG_LEN + HASH_LEN {
+            wasmtime::bail!("the artifact is truncated");
+        }
+
+        let (tag, signed) = artifact.split_at(TAG_LEN);
+        hmac::verify(&self.key.0, signed, tag).map_err(|_| wasmtime::Error::msg("the artifact is not signed"))?;
+
+        let (artifact_hash, serialized) = signed.split_at(HASH_LEN);
+        if artifact_hash != hash {
+            wasmtime::bail!("the artifact is compiled from another module");
+        }
+
+        // SAFETY: the artifact is signed by the server key, so it is created by `Module::serialize` of the server,
+        // and wasmtime checks that it is compatible with the engine
+        unsafe { Module::deserialize(engine, serialized) }
+    }
+
+    /// Replaces the artifacts of the previous module versions with the signed artifact of the module.
+    fn save(&self, artifact_file: &Path, hash: &[u8], module: &Module) -> wasmtime::Result<()> {
+        let prefix = format!("{}_server.", self.lapp_name);
+        for entry in fs::read_dir(self.lapp_dir)? {
+            let path = entry?.path();
+            let is_artifact = path
+                .extension()
+                .is_some_and(|extension| extension == ARTIFACT_EXTENSION)
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(&prefix));
+            if is_artifact && path != artifact_file {
+                fs::remove_file(path)?;
+            }
+        }
+
+        let mut signed = hash.to_vec();
+        signed.extend(module.serialize()?);
+        let tag = hmac::sign(&self.key.0, &signed);
+
+        // The artifact is written to the temporary file first, so the partially written one is never loaded
+        let temp_file = artifact_file.with_extension("tmp");
+        fs::write(&temp_file, [tag.as_ref(), &signed[..]].concat())?;
+        fs::rename(temp_file, artifact_file)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+//! Additional instances of the lapp server module for the parallel HTTP requests.
+//!
+//! The lapp with `resources.instances` greater than one has the pool of the additional instances besides the main
+//! one. The HTTP request is processed by an idle pooled instance concurrently with the other requests, or by the
+//! main instance when all pooled ones are busy. Other events are processed by the main instance only. The instance
+//! that trapped during the call is not returned to the pool, so the pool shrinks until the lapp is restarted.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::lapps::LappInstance;
+
+#[derive(Clone, Default)]
+pub struct InstancePool {
+    idle: Arc<Mutex<Vec<LappInstance>>>,
+}
+
+impl InstancePool {
+    pub fn new(instances: Vec<LappInstance>) -> Self {
+        Self {
+            idle: Arc::new(Mutex::new(instances)),
+        }
+    }
+
+    /// Takes the idle instance, it returns to the pool when dropped.
+    pub fn try_acquire(&self) -> Option<PooledInstance> {
+        self.idle().pop().map(|instance| PooledInstance {
+            instance: Some(instance),
+            pool: self.clone(),
+        })
+    }
+
+    fn idle(&self) -> MutexGuard<'_, Vec<LappInstance>> {
+        self.idle.lock().expect("Instance pool lock should not be poisoned")
+    }
+}
+
+pub struct PooledInstance {
+    instance: Option<LappInstance>,
+    pool: InstancePool,
+}
+
+impl Deref for PooledInstance {
+    type Target = LappInstance;
+
+    fn deref(&self) -> &Self::Target {
+        self.instance
+            .as_ref()
+            .expect("Pooled instance should be present until dropped")
+    }
+}
+
+impl DerefMut for PooledInstance {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.instance
+            .as_mut()
+            .expect("Pooled instance should be present until dropped")
+    }
+}
+
+impl Drop for PooledInstance {
+    fn drop(&mut self) {
+        if let Some(instance) = self.instance.take().filter(|instance| !instance.is_failed) {
+            self.pool.idle().push(instance);
+        }
+    }
+}
@@ -4,16 +4,19 @@ use std::sync::Arc;
 
 use axum::response::IntoResponse;
 use derive_more::Deref;
+use laplace_common::api::UpgradeDiff;
 use laplace_common::lapp::Permission;
 use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use truba::Context;
 
-use crate::error::ServerResult;
+use crate::error::{ServerError, ServerResult};
 use crate::lapps::LappsManager;
 use crate::service::Addr;
 use crate::settings::LappsSettings;
 use crate::web_api::{err_into_json_response, ResultResponse};
 
+/// The shared lapps manager. The manager methods that call the lapps return the futures that do not borrow the
+/// manager, so the handlers drop the manager guard before awaiting them and do not hold the lock across the lapp calls.
 #[derive(Clone, Deref)]
 #[deref(forward)]
 pub struct LappsProvider(Arc<RwLock<LappsManager>>);
@@ -33,6 +36,21 @@ impl LappsProvider {
         self.0.write().await
     }
 
+    /// Installs the confirmed upgrade and loads the upgraded lapp without holding the manager lock. The lapp that
+    /// fails to load is rolled back to the previous version under the lock taken again.
+    pub async fn apply_upgrade(&self, lapp_name: &str) -> ServerResult<UpgradeDiff> {
+        let (diff, load) = self.write_manager().await.apply_upgrade(lapp_name)?;
+        if let Err(err) = load.await {
+            log::error!("Upgraded lapp '{lapp_name}' is not loaded, roll back to the previous version: {err}");
+
+            // The upgraded lapp was being loaded, so the previous version is loaded back
+            let restore = self.write_manager().await.restore_previous_version(lapp_name, true)?;
+            restore.await?;
+            return Err(ServerError::LappUpgradeRolledBack(lapp_name.into(), err.to_string()));
+        }
+        Ok(diff)
+    }
+
     pub async fn handle<Fut, Res>(self, handler: impl FnOnce(Self) -> Fut) -> ResultResponse<Res>
     where
         Fut: Future<Output = ServerResult<Res>>,
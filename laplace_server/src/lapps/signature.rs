@@ -0,0 +1,238 @@
+//! Ed25519 signatures of the lapp packages.
+//!
+//! The signed package has the `package.sha256` manifest with the SHA-256 of every other package file in the
+//! `sha256sum` format and the `package.sig` file with the hex-encoded Ed25519 signature of the manifest. When the
+//! `lapps.publisher_keys` are set, the package is installed or upgraded only if its manifest is signed by one of the
+//! keys and lists all package files with their hashes. The lapp files are checked again before every instantiation,
+//! except the config, which is changed by the server. The `lapps.allow_unsigned` setting disables the checks.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use ring::digest;
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use tempfile::NamedTempFile;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::error::{ServerError, ServerResult};
+use crate::lapps::{Lapp, BACKUP_DIR};
+
+pub const MANIFEST_FILE: &str = "package.sha256";
+pub const SIGNATURE_FILE: &str = "package.sig";
+
+#[derive(Clone, Default)]
+pub struct PackageVerifier {
+    publisher_keys: Arc<[Vec<u8>]>,
+    is_enabled: bool,
+}
+
+impl PackageVerifier {
+    /// Creates the verifier of the hex-encoded publisher keys. The wrong keys are skipped, but the signatures are
+    /// still required, so the misconfigured server refuses the packages instead of accepting the unsigned ones.
+    pub fn new(publisher_keys: &[String], allow_unsigned: bool) -> Self {
+        let keys = publisher_keys
+            .iter()
+            .filter_map(|key| match hex::decode(key.trim()) {
+                Ok(key) if key.len() == 32 => Some(key),
+                _ => {
+                    log::error!("Publisher key '{key}' is not a hex-encoded Ed25519 public key");
+                    None
+                },
+            })
+            .collect();
+
+        Self {
+            publisher_keys: keys,
+            is_enabled: !publisher_keys.is_empty() && !allow_unsigned,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+
+    /// Checks that the package is signed by a publisher and is not tampered.
+    pub fn verify_package<R: Read + Seek>(&self, lapp_name: &str, archive: &mut ZipArchive<R>) -> ServerResult<()> {
+        self.verify_archive(lapp_name, archive, |_| false)
+    }
+
+    /// Checks that the lapp files of the backup are signed by a publisher and are not tampered. The backed up data
+    /// and the config, which is changed by the server, are not checked.
+    pub fn verify_backup<R: Read + Seek>(&self, lapp_name: &str, archive: &mut ZipArchive<R>) -> ServerResult<()> {
+        self.verify_archive(lapp_name, archive, |name| {
+            name == Lapp::config_file_name() || Path::new(name).starts_with(BACKUP_DIR)
+        })
+    }
+
+    fn verify_archive<R: Read + Seek>(
+        &self,
+        lapp_name: &str,
+        archive: &mut ZipArchive<R>,
+        is_unchecked: impl Fn(&str) -> bool,
+    ) -> ServerResult<()> {
+        if !self.is_enabled {
+            return Ok(());
+        }
+
+        let mut read_entry = |name: &str| -> ServerResult<Vec<u8>> {
+            let mut entry = archive
+                .by_name(name)
+                .map_err(|_| signature_error(lapp_name, format!("'{name}' is missing, the package is not signed")))?;
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            Ok(bytes)
+        };
+        let manifest = read_entry(MANIFEST_FILE)?;
+        let signature = read_entry(SIGNATURE_FILE)?;
+        let mut hashes = self.verify_manifest(lapp_name, &manifest, &signature)?;
+        hashes.retain(|name, _| !is_unchecked(name));
+
+        for idx in 0..archive.len() {
+            let mut entry = archive.by_index(idx)?;
+            let name = entry.name().to_string();
+            if entry.is_dir() || is_signature_file(&name) || is_unchecked(&name) {
+                continue;
+            }
+
+            if hashes.remove(&name) != Some(sha256_hex(&mut entry)?) {
+                return Err(signature_error(lapp_name, format!("file '{name}' is not signed")));
+            }
+        }
+
+        match hashes.keys().next() {
+            Some(name) => Err(signature_error(lapp_name, format!("signed file '{name}' is missing"))),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that the installed lapp files are signed by a publisher and are not tampered.
+    pub fn verify_dir(&self, lapp_name: &str, lapp_dir: &Path) -> ServerResult<()> {
+        if !self.is_enabled {
+            return Ok(());
+        }
+
+        let read_file = |name: &str| {
+            fs::read(lapp_dir.join(name))
+                .map_err(|_| signature_error(lapp_name, format!("'{name}' is missing, the lapp is not signed")))
+        };
+        let manifest = read_file(MANIFEST_FILE)?;
+        let signature = read_file(SIGNATURE_FILE)?;
+
+        for (name, hash) in self.verify_manifest(lapp_name, &manifest, &signature)? {
+            if name == Lapp::config_file_name() {
+                continue;
+            }
+
+            let is_valid = fs::File::open(lapp_dir.join(&name))
+                .and_then(|mut file| sha256_hex(&mut file))
+                .is_ok_and(|file_hash| file_hash == hash);
+            if !is_valid {
+                return Err(signature_error(lapp_name, format!("file '{name}' is changed")));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the signed file hashes by the file names.
+    fn verify_manifest(
+        &self,
+        lapp_name: &str,
+        manifest: &[u8],
+        signature: &[u8],
+    ) -> ServerResult<BTreeMap<String, String>> {
+        let signature = hex::decode(String::from_utf8_lossy(signature).trim())
+            .map_err(|_| signature_error(lapp_name, "wrong signature format".into()))?;
+        let is_signed = self.publisher_keys.iter().any(|key| {
+            UnparsedPublicKey::new(&ED25519, key)
+                .verify(manifest, &signature)
+                .is_ok()
+        });
+        if !is_signed {
+            return Err(signature_error(
+                lapp_name,
+                "signature does not match publisher keys".into(),
+            ));
+        }
+
+        let manifest =
+            std::str::from_utf8(manifest).map_err(|_| signature_error(lapp_name, "wrong manifest format".into()))?;
+        manifest
+            .lines()
+            .map(|line| {
+                line.split_once("  ")
+                    .filter(|(_, name)| {
+                        name.split('/')
+                            .all(|component| component != ".." && !component.is_empty())
+                    })
+                    .map(|(hash, name)| (name.to_string(), hash.to_string()))
+                    .ok_or_else(|| signature_error(lapp_name, format!("wrong manifest line '{line}'")))
+            })
+            .collect()
+    }
+}
+
+/// Adds the manifest and the signature made by the hex-encoded Ed25519 seed to the package, returns the public key.
+pub fn sign_package(package_path: &Path, signing_key: &str) -> ServerResult<String> {
+    let key_pair = hex::decode(signing_key.trim())
+        .ok()
+        .and_then(|seed| Ed25519KeyPair::from_seed_unchecked(&seed).ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "wrong signing key"))?;
+
+    let mut archive = ZipArchive::new(fs::File::open(package_path)?)?;
+    let mut manifest = String::new();
+    let mut hashes = BTreeMap::new();
+    for idx in 0..archive.len() {
+        let mut entry = archive.by_index(idx)?;
+        let name = entry.name().to_string();
+        if !entry.is_dir() && !is_signature_file(&name) {
+            hashes.insert(name, sha256_hex(&mut entry)?);
+        }
+    }
+    for (name, hash) in hashes {
+        manifest.push_str(&format!("{hash}  {name}\n"));
+    }
+    let signature = hex::encode(key_pair.sign(manifest.as_bytes()));
+
+    let signed_package = NamedTempFile::new_in(package_path.parent().unwrap_or(Path::new(".")))?;
+    let mut zip = ZipWriter::new(signed_package.as_file());
+    for idx in 0..archive.len() {
+        let entry = archive.by_index_raw(idx)?;
+        if !is_signature_file(entry.name()) {
+            zip.raw_copy_file(entry)?;
+        }
+    }
+    zip.start_file(MANIFEST_FILE, FileOptions::default())?;
+    zip.write_all(manifest.as_bytes())?;
+    zip.start_file(SIGNATURE_FILE, FileOptions::default())?;
+    zip.write_all(signature.as_bytes())?;
+    zip.finish()?;
+    drop(zip);
+
+    signed_package.persist(package_path).map_err(|err| err.error)?;
+    Ok(hex::encode(key_pair.public_key()))
+}
+
+fn is_signature_file(name: &str) -> bool {
+    name == MANIFEST_FILE || name == SIGNATURE_FILE
+}
+
+fn sha256_hex(reader: &mut impl Read) -> io::Result<String> {
+    let mut context = digest::Context::new(&digest::SHA256);
+    let mut buf = [0; 8192];
+    loop {
+        let len = reader.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        context.update(&buf[..len]);
+    }
+    Ok(hex::encode(context.finish()))
+}
+
+fn signature_error(lapp_name: &str, reason: String) -> ServerError {
+    ServerError::LappSignatureInvalid(lapp_name.into(), reason)
+}
@@ -0,0 +1,135 @@
+//! Snapshots of the initialized lapp instances.
+//!
+//! After the lapp `init` function completes, the linear memory and the exported mutable globals of the instance
+//! are saved to the snapshot file. The next instantiation of the same module with the same permissions restores
+//! them instead of calling the initialization functions. Host state created by `init` (opened files, prepared
+//! statements) is not a part of the snapshot, so it is enabled by the `snapshot` lapp setting only.
+
+use std::fs;
+use std::path::Path;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use ring::digest;
+use wasmtime::{Instance, Mutability, Store, Val};
+
+use crate::lapps::{Ctx, LappInstanceError, LappInstanceResult};
+
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+enum GlobalValue {
+    I32(i32),
+    I64(i64),
+    F32(u32),
+    F64(u64),
+}
+
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct InstanceSnapshot {
+    /// The hash of the module and the instance permissions, the snapshot of other ones is not restored.
+    key: Vec<u8>,
+    memory_pages: u64,
+
+    /// The memory content without the trailing zero bytes.
+    memory: Vec<u8>,
+    globals: Vec<(String, GlobalValue)>,
+}
+
+impl InstanceSnapshot {
+    pub fn key(wasm_bytes: &[u8], permissions: &[bool]) -> Vec<u8> {
+        let mut context = digest::Context::new(&digest::SHA256);
+        context.update(wasm_bytes);
+        context.update(&permissions.iter().map(|&allowed| allowed as u8).collect::<Vec<_>>());
+        context.finish().as_ref().to_vec()
+    }
+
+    pub fn take(key: Vec<u8>, instance: &Instance, store: &mut Store<Ctx>) -> LappInstanceResult<Self> {
+        let memory = instance
+            .get_memory(&mut *store, "memory")
+            .ok_or_else(|| LappInstanceError::WasmFunctionNotFound("memory".into()))?;
+        let data = memory.data(&*store);
+        let len = data.iter().rposition(|&byte| byte != 0).map_or(0, |pos| pos + 1);
+
+        let memory_pages = memory.size(&*store);
+        let memory = data[..len].to_vec();
+        let globals = exported_globals(instance, store)
+            .into_iter()
+            .filter_map(|(name, global)| {
+                let value = match global.get(&mut *store) {
+                    Val::I32(value) => GlobalValue::I32(value),
+                    Val::I64(value) => GlobalValue::I64(value),
+                    Val::F32(value) => GlobalValue::F32(value),
+                    Val::F64(value) => GlobalValue::F64(value),
+                    _ => return None,
+                };
+                Some((name, value))
+            })
+            .collect();
+
+        Ok(Self {
+            key,
+            memory_pages,
+            memory,
+            globals,
+        })
+    }
+
+    pub fn restore(&self, instance: &Instance, store: &mut Store<Ctx>) -> LappInstanceResult<()> {
+        let memory = instance
+            .get_memory(&mut *store, "memory")
+            .ok_or_else(|| LappInstanceError::WasmFunctionNotFound("memory".into()))?;
+        let current_pages = memory.size(&*store);
+        if current_pages < self.memory_pages {
+            memory.grow(&mut *store, self.memory_pages - current_pages)?;
+        }
+
+        let data = memory.data_mut(&mut *store);
+        data[..self.memory.len()].copy_from_slice(&self.memory);
+        data[self.memory.len()..].fill(0);
+
+        for (name, global) in exported_globals(instance, store) {
+            if let Some((_, value)) = self.globals.iter().find(|(global_name, _)| *global_name == name) {
+                let value = match *value {
+                    GlobalValue::I32(value) => Val::I32(value),
+                    GlobalValue::I64(value) => Val::I64(value),
+                    GlobalValue::F32(value) => Val::F32(value),
+                    GlobalValue::F64(value) => Val::F64(value),
+                };
+                global.set(&mut *store, value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads the snapshot if it exists and matches the key.
+    pub fn load(path: impl AsRef<Path>, key: &[u8]) -> Option<Self> {
+        let bytes = fs::read(path.as_ref()).ok()?;
+        match Self::try_from_slice(&bytes) {
+            Ok(snapshot) if snapshot.key == key => Some(snapshot),
+            Ok(_) => None,
+            Err(err) => {
+                log::warn!("Wrong lapp snapshot {}: {err}", path.as_ref().display());
+                None
+            },
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> LappInstanceResult<()> {
+        fs::write(path, borsh::to_vec(self)?)?;
+        Ok(())
+    }
+}
+
+fn exported_globals(instance: &Instance, store: &mut Store<Ctx>) -> Vec<(String, wasmtime::Global)> {
+    let globals: Vec<_> = instance
+        .exports(&mut *store)
+        .filter_map(|export| {
+            let name = export.name().to_string();
+            export.into_global().map(|global| (name, global))
+        })
+        .collect();
+
+    globals
+        .into_iter()
+        .filter(|(_, global)| global.ty(&*store).mutability() == Mutability::Var)
+        .collect()
+}
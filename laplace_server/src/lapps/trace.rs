@@ -0,0 +1,243 @@
+//! Record and replay of the lapp server module execution.
+//!
+//! With the `record_trace` lapp setting the inbound requests of the instance and the results of its host calls are
+//! written to the trace file. The replay instantiates the module with the host calls answered from the trace
+//! and sends it the recorded requests, so the execution is repeated without the database, the network and
+//! the other lapp state. WASI clocks, random and files are not recorded.
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use laplace_wasm::route::{gossipsub, websocket};
+use laplace_wasm::{graphql, http, mqtt};
+use reqwest::Client;
+
+use crate::error::{ServerError, ServerResult};
+use crate::lapps::{Ctx, Lapp, LappInstanceError};
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub enum TraceEvent {
+    Http(Vec<u8>),
+    Ws(Vec<u8>),
+    Gossipsub(Vec<u8>),
+    HostCall { name: String, result: Vec<u8> },
+    Graphql(Vec<u8>),
+    Mqtt(Vec<u8>),
+    ErrorPage(Vec<u8>),
+    Schedule(Vec<u8>),
+    Timer(u64),
+    Job(Vec<u8>),
+    LappMessage { sender: String, payload: Vec<u8> },
+}
+
+impl TraceEvent {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Http(_) => "http",
+            Self::Ws(_) => "ws",
+            Self::Gossipsub(_) => "gossipsub",
+            Self::HostCall { name, .. } => name,
+            Self::Graphql(_) => "graphql",
+            Self::Mqtt(_) => "mqtt",
+            Self::ErrorPage(_) => "error_page",
+            Self::Schedule(_) => "schedule",
+            Self::Timer(_) => "timer",
+            Self::Job(_) => "job",
+            Self::LappMessage { .. } => "lapp_message",
+        }
+    }
+}
+
+pub enum Trace {
+    Record(BufWriter<File>),
+    Replay(VecDeque<TraceEvent>),
+}
+
+impl Trace {
+    /// Starts the new trace file, the previous one is overwritten.
+    pub fn record(path: impl AsRef<Path>) -> ServerResult<Self> {
+        Ok(Self::Record(BufWriter::new(File::create(path)?)))
+    }
+
+    pub fn replay(path: impl AsRef<Path>) -> ServerResult<Self> {
+        let bytes = fs::read(path)?;
+        let mut reader = bytes.as_slice();
+        let mut events = VecDeque::new();
+        while !reader.is_empty() {
+            events.push_back(TraceEvent::deserialize(&mut reader)?);
+        }
+
+        Ok(Self::Replay(events))
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(self, Self::Replay(_))
+    }
+
+    fn write(writer: &mut BufWriter<File>, event: &TraceEvent) -> std::io::Result<()> {
+        borsh::to_writer(&mut *writer, event)?;
+        writer.flush()
+    }
+}
+
+impl Ctx {
+    pub fn is_replay(&self) -> bool {
+        self.trace.as_ref().map_or(false, Trace::is_replay)
+    }
+
+    /// Writes the event to the trace when it is recorded.
+    pub fn record(&mut self, event: TraceEvent) {
+        if let Some(Trace::Record(writer)) = &mut self.trace {
+            if let Err(err) = Trace::write(writer, &event) {
+                log::warn!("Write '{}' trace event error: {err}", event.name());
+            }
+        }
+    }
+
+    /// Returns the recorded result of the host call when the trace is replayed.
+    pub fn replayed_host_call(&mut self, name: &str) -> Option<Vec<u8>> {
+        match self.trace.as_mut()? {
+            Trace::Replay(events) => match events.pop_front() {
+                Some(TraceEvent::HostCall { name: recorded, result }) if recorded == name => Some(result),
+                Some(event) => panic!("Replay diverged: '{name}' host call instead of '{}'", event.name()),
+                None => panic!("Replay diverged: '{name}' host call after the trace end"),
+            },
+            Trace::Record(_) => None,
+        }
+    }
+
+    /// Records the serialized host call result.
+    pub fn record_host_call(&mut self, name: &str, result: &[u8]) {
+        if matches!(self.trace, Some(Trace::Record(_))) {
+            self.record(TraceEvent::HostCall {
+                name: name.into(),
+                result: result.into(),
+            });
+        }
+    }
+
+    fn next_replayed(&mut self) -> Option<TraceEvent> {
+        match self.trace.as_mut()? {
+            Trace::Replay(events) => events.pop_front(),
+            Trace::Record(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ReplayResult {
+    pub http: usize,
+    pub ws: usize,
+    pub gossipsub: usize,
+    pub graphql: usize,
+    pub mqtt: usize,
+    pub error_page: usize,
+    pub schedule: usize,
+    pub timer: usize,
+    pub job: usize,
+    pub lapp_message: usize,
+}
+
+/// Replays the trace recorded by the lapp instance from the lapps directory.
+pub async fn replay_trace(
+    lapps_path: impl AsRef<Path>,
+    lapp_name: &str,
+    trace_file: Option<PathBuf>,
+) -> ServerResult<ReplayResult> {
+    let lapp_path = lapps_path.as_ref().join(lapp_name);
+    let settings =
+        Lapp::load_settings(lapp_name, &lapp_path).ok_or_else(|| ServerError::LappNotFound(lapp_name.into()))?;
+    let mut lapp = Lapp::new(lapp_name, lapp_path, settings);
+    lapp.set_debug(true);
+    let trace = Trace::replay(trace_file.unwrap_or_else(|| lapp.trace_file()))?;
+
+    lapp.instantiate_with_trace(Client::new(), Some(trace)).await?;
+    let instance = lapp
+        .instance_mut()
+        .ok_or_else(|| ServerError::LappNotLoaded(lapp_name.into()))?;
+
+    let mut result = ReplayResult::default();
+    while let Some(event) = instance.store.data_mut().next_replayed() {
+        match event {
+            TraceEvent::Http(bytes) => {
+                let request = http::Request::try_from_slice(&bytes)?;
+                let (method, uri) = (request.method.clone(), request.uri.clone());
+                let response = instance.process_http(request).await?;
+                log::info!("Replayed HTTP {method} {uri}: {}", response.status);
+                result.http += 1;
+            },
+            TraceEvent::Ws(bytes) => {
+                let routes = instance
+                    .route_ws(None, &websocket::MessageIn::try_from_slice(&bytes)?)
+                    .await?;
+                log::info!("Replayed WS message: {} routes", routes.len());
+                result.ws += 1;
+            },
+            TraceEvent::Gossipsub(bytes) => {
+                let routes = instance
+                    .route_gossipsub(&gossipsub::MessageIn::try_from_slice(&bytes)?)
+                    .await?;
+                log::info!("Replayed gossipsub message: {} routes", routes.len());
+                result.gossipsub += 1;
+            },
+            TraceEvent::Graphql(bytes) => {
+                let response = instance
+                    .graphql_resolve(&graphql::Request::try_from_slice(&bytes)?)
+                    .await?;
+                log::info!("Replayed GraphQL request: {} bytes response", response.len());
+                result.graphql += 1;
+            },
+            TraceEvent::Mqtt(bytes) => {
+                let message = mqtt::Message::try_from_slice(&bytes)?;
+                let routes = instance.handle_mqtt(&message).await?;
+                log::info!(
+                    "Replayed MQTT message of topic {}: {} routes",
+                    message.topic,
+                    routes.len()
+                );
+                result.mqtt += 1;
+            },
+            TraceEvent::ErrorPage(bytes) => {
+                let request = http::ErrorPageRequest::try_from_slice(&bytes)?;
+                let page = instance.error_page(&request).await?;
+                log::info!(
+                    "Replayed {} error page: {} bytes",
+                    request.status,
+                    page.map_or(0, |page| page.len())
+                );
+                result.error_page += 1;
+            },
+            TraceEvent::Schedule(bytes) => {
+                let job_name = String::from_utf8(bytes).map_err(LappInstanceError::from)?;
+                let job_result = instance.on_schedule(&job_name).await?;
+                log::info!("Replayed scheduled job '{job_name}': {job_result:?}");
+                result.schedule += 1;
+            },
+            TraceEvent::Timer(id) => {
+                instance.on_timer(id).await?;
+                log::info!("Replayed timer {id}");
+                result.timer += 1;
+            },
+            TraceEvent::Job(payload) => {
+                let job_result = instance.on_job(&payload).await?;
+                log::info!("Replayed job of {} bytes payload: {job_result:?}", payload.len());
+                result.job += 1;
+            },
+            TraceEvent::LappMessage { sender, payload } => {
+                let is_handled = instance.on_lapp_message(&sender, &payload).await?;
+                log::info!("Replayed message of lapp '{sender}': handled {is_handled}");
+                result.lapp_message += 1;
+            },
+            TraceEvent::HostCall { name, .. } => {
+                return Err(ServerError::TraceReplayDiverged(format!(
+                    "'{name}' host call is recorded but not made"
+                )));
+            },
+        }
+    }
+
+    Ok(result)
+}
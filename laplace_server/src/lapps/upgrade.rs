@@ -0,0 +1,218 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use laplace_common::api::UpgradeDiff;
+use laplace_common::lapp::LappManifest;
+use tempfile::NamedTempFile;
+use zip::ZipArchive;
+
+use crate::error::{ServerError, ServerResult};
+use crate::lapps::{is_regenerated_file, Lapp, LappSettings};
+
+/// The lapp subdirectory with the files of the version replaced by the last upgrade.
+const PREVIOUS_DIR: &str = ".previous";
+const PREVIOUS_FILES_DIR: &str = "files";
+const PREVIOUS_ADDED_FILE: &str = "added";
+
+/// The lapp subdirectory with the files of the upgrade version smoke-tested before the deployment.
+pub const STAGING_DIR: &str = ".staging";
+
+/// The uploaded lapp package which waits for the confirmation to replace the installed lapp.
+pub struct LappUpgrade {
+    package: NamedTempFile,
+    settings: LappSettings,
+}
+
+impl LappUpgrade {
+    pub fn new(lapp_name: impl AsRef<str>, package: NamedTempFile) -> ServerResult<Self> {
+        let settings = package_settings(lapp_name.as_ref(), &mut ZipArchive::new(package.as_file())?)?;
+        Ok(Self { package, settings })
+    }
+
+    pub fn settings(&self) -> &LappSettings {
+        &self.settings
+    }
+
+    pub fn diff(&self, current: &LappSettings) -> UpgradeDiff {
+        UpgradeDiff::new(current.name(), &current.permissions, &self.settings.permissions).with_versions(
+            current.application.version.clone(),
+            self.settings.application.version.clone(),
+        )
+    }
+
+    /// Merges the upgrade settings with the current ones: the state and the access token are kept, the permissions
+    /// that remain required stay allowed and the newly required permissions are allowed by the confirmation.
+    pub fn merge_settings(&self, current: &LappSettings) -> LappSettings {
+        let diff = self.diff(current);
+        let mut settings = self.settings.clone();
+
+        settings.application.enabled = current.application.enabled;
+        settings.application.autoload = current.application.autoload;
+        settings.application.access_token = current.application.access_token.clone();
+        settings.permissions.allowed = settings
+            .permissions
+            .required()
+            .filter(|permission| {
+                current.permissions.is_allowed(*permission) || diff.added_permissions.contains(permission)
+            })
+            .collect();
+
+        settings
+    }
+
+    /// Extracts the package over the lapp directory, so the lapp data is kept. The replaced files of the previous
+    /// version are moved to the `.previous` lapp subdirectory to roll back to.
+    pub fn install(&self, lapp_dir: impl AsRef<Path>) -> ServerResult<()> {
+        let lapp_dir = lapp_dir.as_ref();
+        let previous_dir = Self::previous_dir(lapp_dir);
+        let previous_files_dir = previous_dir.join(PREVIOUS_FILES_DIR);
+        if previous_dir.exists() {
+            fs::remove_dir_all(&previous_dir)?;
+        }
+        fs::create_dir_all(&previous_files_dir)?;
+
+        let mut archive = ZipArchive::new(self.package.as_file())?;
+        let mut added = String::new();
+        for idx in 0..archive.len() {
+            let entry = archive.by_index(idx)?;
+            let Some(entry_path) = entry
+                .enclosed_name()
+                .filter(|path| !entry.is_dir() && !is_regenerated_file(path))
+            else {
+                continue;
+            };
+
+            let path = lapp_dir.join(&entry_path);
+            if path.is_file() {
+                let previous_path = previous_files_dir.join(&entry_path);
+                if let Some(parent) = previous_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::rename(&path, previous_path)?;
+            } else {
+                added.push_str(&entry_path.to_string_lossy());
+                added.push('\n');
+            }
+        }
+        fs::write(previous_dir.join(PREVIOUS_ADDED_FILE), added)?;
+
+        extract_package(&mut archive, lapp_dir, |_| Ok(()))
+    }
+
+    /// Extracts the package to the `.staging` lapp subdirectory, so the upgrade version can be instantiated next to
+    /// the running one. Returns the staging directory.
+    pub fn extract_staging(&self, lapp_dir: impl AsRef<Path>) -> ServerResult<PathBuf> {
+        let staging_dir = Self::staging_dir(lapp_dir.as_ref());
+        Self::remove_staging(lapp_dir)?;
+
+        extract_package(&mut ZipArchive::new(self.package.as_file())?, &staging_dir, |_| Ok(()))?;
+        Ok(staging_dir)
+    }
+
+    pub fn remove_staging(lapp_dir: impl AsRef<Path>) -> ServerResult<()> {
+        let staging_dir = Self::staging_dir(lapp_dir.as_ref());
+        if staging_dir.exists() {
+            fs::remove_dir_all(staging_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Restores the files of the version replaced by the last upgrade and removes the files added by it.
+    pub fn rollback(lapp_name: &str, lapp_dir: impl AsRef<Path>) -> ServerResult<()> {
+        let lapp_dir = lapp_dir.as_ref();
+        let previous_dir = Self::previous_dir(lapp_dir);
+        if !previous_dir.is_dir() {
+            return Err(ServerError::LappPreviousVersionNotFound(lapp_name.into()));
+        }
+
+        for added in fs::read_to_string(previous_dir.join(PREVIOUS_ADDED_FILE))?.lines() {
+            let path = lapp_dir.join(added);
+            if path.is_file() {
+                fs::remove_file(path)?;
+            }
+        }
+        move_files(&previous_dir.join(PREVIOUS_FILES_DIR), lapp_dir)?;
+
+        fs::remove_dir_all(previous_dir).map_err(Into::into)
+    }
+
+    fn previous_dir(lapp_dir: &Path) -> PathBuf {
+        lapp_dir.join(PREVIOUS_DIR)
+    }
+
+    fn staging_dir(lapp_dir: &Path) -> PathBuf {
+        lapp_dir.join(STAGING_DIR)
+    }
+}
+
+/// Reads the lapp settings from the package and validates the lapp manifest.
+pub fn package_settings<R: io::Read + io::Seek>(
+    lapp_name: &str,
+    archive: &mut ZipArchive<R>,
+) -> ServerResult<LappSettings> {
+    let wrong_package = |reason: String| ServerError::WrongLappPackage(lapp_name.into(), reason);
+
+    let mut config = String::new();
+    archive
+        .by_name(Lapp::config_file_name())
+        .map_err(|_| wrong_package(format!("'{}' is missing", Lapp::config_file_name())))?
+        .read_to_string(&mut config)?;
+
+    let mut settings: LappSettings =
+        toml::from_str(&config).map_err(|err| wrong_package(format!("wrong config: {err}")))?;
+    settings.lapp_name = lapp_name.into();
+
+    let entry_points = Lapp::entry_points(lapp_name, |path| archive.file_names().any(|name| name == path));
+    LappManifest::new(&settings, entry_points).map_err(|errors| {
+        let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        wrong_package(errors.join("; "))
+    })?;
+
+    Ok(settings)
+}
+
+/// Extracts the package entries to the directory, the progress is reported before each entry. The files regenerated
+/// by the server are skipped, so the package cannot plant the compiled modules.
+pub fn extract_package<R: io::Read + io::Seek>(
+    archive: &mut ZipArchive<R>,
+    dir: &Path,
+    mut on_progress: impl FnMut(f32) -> ServerResult<()>,
+) -> ServerResult<()> {
+    let len = archive.len();
+    for idx in 0..len {
+        on_progress(idx as f32 / len as f32)?;
+
+        let mut entry = archive.by_index(idx)?;
+        let Some(entry_path) = entry.enclosed_name().filter(|path| !is_regenerated_file(path)) else {
+            continue;
+        };
+        let path = dir.join(entry_path);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut entry, &mut fs::File::create(&path)?)?;
+        }
+    }
+    on_progress(1.0)
+}
+
+/// Moves the files of the directory tree over the destination directory.
+fn move_files(from: &Path, to: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let path = to.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            fs::create_dir_all(&path)?;
+            move_files(&entry.path(), &path)?;
+        } else {
+            fs::rename(entry.path(), path)?;
+        }
+    }
+    Ok(())
+}
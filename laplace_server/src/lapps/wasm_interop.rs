@@ -6,11 +6,26 @@ use std::string::FromUtf8Error;
 use anyhow::anyhow;
 use laplace_wasm::WasmSlice;
 use thiserror::Error;
-use wasmtime::{AsContextMut, Instance, Memory, TypedFunc};
+use wasmtime::{AsContext, AsContextMut, Instance, Memory, SharedMemory, TypedFunc};
 
+pub mod blob;
+pub mod crypto;
 pub mod database;
 pub mod http;
+pub mod jobs;
+pub mod lapp_settings;
+pub mod lapps;
+pub mod logging;
+pub mod mail;
+pub mod mqtt;
+pub mod permission;
 pub mod sleep;
+pub mod template;
+pub mod threads;
+pub mod time;
+pub mod timer;
+pub mod ws_client;
+pub mod ws_push;
 
 pub type BoxedSendFuture<'a, T> = Box<dyn Future<Output = T> + Send + 'a>;
 
@@ -28,15 +43,49 @@ pub enum MemoryManagementError {
 
 pub type MemoryManagementResult<T> = Result<T, MemoryManagementError>;
 
+/// The linear memory of the instance, the modules compiled with the threads proposal import the shared one.
+#[derive(Clone)]
+pub enum LappMemory {
+    Local(Memory),
+    Shared(SharedMemory),
+}
+
+impl LappMemory {
+    pub fn data_ptr(&self, store: impl AsContext) -> *mut u8 {
+        match self {
+            Self::Local(memory) => memory.data_ptr(store),
+            Self::Shared(memory) => memory.data().as_ptr() as *mut u8,
+        }
+    }
+
+    pub fn data_size(&self, store: impl AsContext) -> usize {
+        match self {
+            Self::Local(memory) => memory.data_size(store),
+            Self::Shared(memory) => memory.data_size(),
+        }
+    }
+
+    pub async fn grow<S>(&self, store: S, pages: u64) -> anyhow::Result<u64>
+    where
+        S: AsContextMut,
+        S::Data: Send,
+    {
+        match self {
+            Self::Local(memory) => memory.grow_async(store, pages).await,
+            Self::Shared(memory) => memory.grow(pages),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct MemoryManagementHostData {
-    memory: Memory,
+    memory: LappMemory,
     alloc_fn: TypedFunc<u32, u32>,
     dealloc_fn: TypedFunc<(u32, u32), ()>,
 }
 
 impl MemoryManagementHostData {
-    pub fn new(memory: Memory, alloc_fn: TypedFunc<u32, u32>, dealloc_fn: TypedFunc<(u32, u32), ()>) -> Self {
+    pub fn new(memory: LappMemory, alloc_fn: TypedFunc<u32, u32>, dealloc_fn: TypedFunc<(u32, u32), ()>) -> Self {
         Self {
             memory,
             alloc_fn,
@@ -45,16 +94,20 @@ impl MemoryManagementHostData {
     }
 
     pub fn from_instance(instance: &Instance, mut store: impl AsContextMut) -> anyhow::Result<Self> {
-        let memory = instance
-            .get_memory(&mut store, "memory")
-            .ok_or_else(|| anyhow!("Memory is empty"))?;
+        let memory = match instance.get_memory(&mut store, "memory") {
+            Some(memory) => LappMemory::Local(memory),
+            None => instance
+                .get_shared_memory(&mut store, "memory")
+                .map(LappMemory::Shared)
+                .ok_or_else(|| anyhow!("Memory is empty"))?,
+        };
         let alloc_fn = instance.get_typed_func(&mut store, "alloc")?;
         let dealloc_fn = instance.get_typed_func(store, "dealloc")?;
 
         Ok(Self::new(memory, alloc_fn, dealloc_fn))
     }
 
-    pub fn memory(&self) -> &Memory {
+    pub fn memory(&self) -> &LappMemory {
         &self.memory
     }
 
@@ -83,12 +136,12 @@ where
     S: AsContextMut,
     S::Data: Send,
 {
-    pub fn memory(&self) -> &Memory {
+    pub fn memory(&self) -> &LappMemory {
         &self.host_data.memory
     }
 
     pub async fn memory_grow(&mut self, pages: u64) -> anyhow::Result<u64> {
-        self.host_data.memory.grow_async(&mut self.store, pages).await
+        self.host_data.memory.grow(&mut self.store, pages).await
     }
 
     pub fn is_memory_enough(&self, offset: usize, size: usize) -> bool {
@@ -135,7 +188,18 @@ where
             size
         );
 
-        let data = memory.data(&self.store)[offset..(offset + size)].to_vec();
+        if offset
+            .checked_add(size)
+            .is_none_or(|end| end > memory.data_size(&self.store))
+        {
+            return Err(MemoryManagementError::WrongMemorySize);
+        }
+
+        let mut data = vec![0; size];
+        // SAFETY: the range is checked above, the grown shared memory keeps its address
+        unsafe {
+            copy_nonoverlapping(memory.data_ptr(&self.store).add(offset), data.as_mut_ptr(), size);
+        }
         unsafe { self.dealloc(offset as _, size as _).await? };
 
         Ok(data)
@@ -0,0 +1,181 @@
+use std::io;
+use std::path::PathBuf;
+
+use borsh::BorshSerialize;
+use laplace_wasm::blob::is_valid_hash;
+use tokio::fs;
+use wasmtime::Caller;
+
+use crate::lapps::wasm_interop::BoxedSendFuture;
+use crate::lapps::{Ctx, DiskQuota};
+
+/// The subdirectory of the lapp data directory with the blobs.
+pub const BLOBS_DIR: &str = "blobs";
+
+/// The content addressed blobs of the lapp. The blob is stored in the `blobs/<2 hash chars>/<hash>` file of the lapp
+/// data directory, so the same content is stored once. The lapp without the `file_write` permission only reads blobs.
+pub struct BlobsCtx {
+    blobs_dir: PathBuf,
+    is_writable: bool,
+}
+
+impl BlobsCtx {
+    pub fn new(data_dir: impl Into<PathBuf>, is_writable: bool) -> Self {
+        Self {
+            blobs_dir: data_dir.into().join(BLOBS_DIR),
+            is_writable,
+        }
+    }
+
+    pub async fn put(&self, bytes: &[u8]) -> io::Result<String> {
+        self.check_writable()?;
+        let hash = blake3::hash(bytes).to_hex().to_string();
+        let path = self.blob_path(&hash);
+        if fs::try_exists(&path).await? {
+            return Ok(hash);
+        }
+
+        let dir = path.parent().expect("Blob path should have a parent");
+        fs::create_dir_all(dir).await?;
+
+        // The blob is written to the temporary file first, so the reader never gets a partial content
+        let tmp_path = dir.join(format!("{hash}.tmp"));
+        fs::write(&tmp_path, bytes).await?;
+        fs::rename(&tmp_path, &path).await?;
+        Ok(hash)
+    }
+
+    pub async fn get(&self, hash: &str) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.blob_path(hash)).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub async fn delete(&self, hash: &str) -> io::Result<bool> {
+        self.check_writable()?;
+        match fs::remove_file(self.blob_path(hash)).await {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn check_writable(&self) -> io::Result<()> {
+        if self.is_writable {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::PermissionDenied, "blobs are read only"))
+        }
+    }
+
+    fn blob_path(&self, hash: &str) -> PathBuf {
+        self.blobs_dir.join(&hash[..2]).join(hash)
+    }
+}
+
+/// Stores the blob unless the lapp disk quota is exceeded.
+pub fn blob_put(caller: Caller<Ctx>, bytes_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(blob_put_async(caller, bytes_slice))
+}
+
+pub async fn blob_put_async(mut caller: Caller<'_, Ctx>, bytes_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let bytes = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_vec(bytes_slice)
+        .await
+        .map_err(|err| format!("{err:?}"));
+
+    let serialized = match caller.data_mut().replayed_host_call("blob_put") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match (bytes, caller.data().blobs.as_ref()) {
+                (Err(err), _) => Err(err),
+                (Ok(_), None) => Err("Blobs context is empty".to_string()),
+                (Ok(bytes), Some(blobs_ctx)) => match caller.data().disk_quota.as_ref().map(DiskQuota::check) {
+                    Some(Err(exceeded)) => Err(exceeded.to_string()),
+                    _ => blobs_ctx.put(&bytes).await.map_err(|err| err.to_string()),
+                },
+            };
+            record_result(&mut caller, "blob_put", &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+pub fn blob_get(caller: Caller<Ctx>, hash_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(blob_get_async(caller, hash_slice))
+}
+
+pub async fn blob_get_async(mut caller: Caller<'_, Ctx>, hash_slice: u64) -> u64 {
+    let hash = read_hash(&mut caller, hash_slice).await;
+
+    let serialized = match caller.data_mut().replayed_host_call("blob_get") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match (hash, caller.data().blobs.as_ref()) {
+                (Err(err), _) => Err(err),
+                (Ok(_), None) => Err("Blobs context is empty".to_string()),
+                (Ok(hash), Some(blobs_ctx)) => blobs_ctx.get(&hash).await.map_err(|err| err.to_string()),
+            };
+            record_result(&mut caller, "blob_get", &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+pub fn blob_delete(caller: Caller<Ctx>, hash_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(blob_delete_async(caller, hash_slice))
+}
+
+pub async fn blob_delete_async(mut caller: Caller<'_, Ctx>, hash_slice: u64) -> u64 {
+    let hash = read_hash(&mut caller, hash_slice).await;
+
+    let serialized = match caller.data_mut().replayed_host_call("blob_delete") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match (hash, caller.data().blobs.as_ref()) {
+                (Err(err), _) => Err(err),
+                (Ok(_), None) => Err("Blobs context is empty".to_string()),
+                (Ok(hash), Some(blobs_ctx)) => blobs_ctx.delete(&hash).await.map_err(|err| err.to_string()),
+            };
+            record_result(&mut caller, "blob_delete", &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+/// Reads the hash and checks it, so the hash can not point outside the blobs directory.
+async fn read_hash(caller: &mut Caller<'_, Ctx>, hash_slice: u64) -> Result<String, String> {
+    let memory_data = caller.data().memory_data().clone();
+    let hash = memory_data
+        .to_manager(caller)
+        .wasm_slice_to_string(hash_slice)
+        .await
+        .map_err(|err| format!("{err:?}"))?;
+
+    if is_valid_hash(&hash) {
+        Ok(hash)
+    } else {
+        Err(format!("Invalid blob hash '{hash}'"))
+    }
+}
+
+fn record_result<T: BorshSerialize>(caller: &mut Caller<'_, Ctx>, name: &str, result: &Result<T, String>) -> Vec<u8> {
+    let serialized = borsh::to_vec(result).expect("Result should be serializable");
+    caller.data_mut().record_host_call(name, &serialized);
+    serialized
+}
+
+async fn move_to_wasm(mut caller: Caller<'_, Ctx>, serialized: &[u8]) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(serialized)
+        .await
+        .expect("Result should be to move to WASM")
+        .into()
+}
@@ -0,0 +1,123 @@
+use borsh::BorshDeserialize;
+use laplace_wasm::crypto::{AeadAlgorithm, CryptoError, CryptoRequest, CryptoResult, HashAlgorithm};
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, NONCE_LEN};
+use ring::digest;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use wasmtime::Caller;
+
+use crate::lapps::wasm_interop::BoxedSendFuture;
+use crate::lapps::Ctx;
+
+pub fn invoke_crypto(caller: Caller<Ctx>, request_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(invoke_crypto_async(caller, request_slice))
+}
+
+/// Only the seal result is recorded to the trace, the other operations do not depend on the randomness.
+pub async fn invoke_crypto_async(mut caller: Caller<'_, Ctx>, request_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let request = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_vec(request_slice)
+        .await
+        .map_err(|_| CryptoError::CanNotReadWasmData)
+        .and_then(|bytes| CryptoRequest::try_from_slice(&bytes).map_err(|_| CryptoError::FailDeserializeRequest));
+
+    let is_seal = matches!(request, Ok(CryptoRequest::Seal { .. }));
+    let replayed = if is_seal {
+        caller.data_mut().replayed_host_call("invoke_crypto")
+    } else {
+        None
+    };
+    let serialized = match replayed {
+        Some(serialized) => serialized,
+        None => {
+            let result = request.and_then(do_invoke_crypto);
+            let serialized = borsh::to_vec(&result).expect("Result should be serializable");
+            if is_seal {
+                caller.data_mut().record_host_call("invoke_crypto", &serialized);
+            }
+            serialized
+        },
+    };
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(&serialized)
+        .await
+        .expect("Result should be to move to WASM")
+        .into()
+}
+
+fn do_invoke_crypto(request: CryptoRequest) -> CryptoResult<Vec<u8>> {
+    match request {
+        CryptoRequest::Hash { algorithm, data } => Ok(match algorithm {
+            HashAlgorithm::Sha256 => digest::digest(&digest::SHA256, &data).as_ref().to_vec(),
+            HashAlgorithm::Blake3 => blake3::hash(&data).as_bytes().to_vec(),
+        }),
+        CryptoRequest::Ed25519PublicKey { seed } => {
+            let key_pair = Ed25519KeyPair::from_seed_unchecked(&seed).map_err(|_| CryptoError::WrongKey)?;
+            Ok(key_pair.public_key().as_ref().to_vec())
+        },
+        CryptoRequest::Ed25519Sign { seed, message } => {
+            let key_pair = Ed25519KeyPair::from_seed_unchecked(&seed).map_err(|_| CryptoError::WrongKey)?;
+            Ok(key_pair.sign(&message).as_ref().to_vec())
+        },
+        CryptoRequest::Ed25519Verify {
+            public_key,
+            message,
+            signature,
+        } => UnparsedPublicKey::new(&ED25519, public_key)
+            .verify(&message, &signature)
+            .map(|_| Vec::new())
+            .map_err(|_| CryptoError::InvalidSignature),
+        CryptoRequest::Seal {
+            algorithm,
+            key,
+            aad,
+            plaintext,
+        } => {
+            let key = aead_key(algorithm, &key)?;
+            let mut nonce = [0; NONCE_LEN];
+            SystemRandom::new()
+                .fill(&mut nonce)
+                .map_err(|_| CryptoError::FailRandom)?;
+
+            let mut in_out = plaintext;
+            key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(aad), &mut in_out)
+                .map_err(|_| CryptoError::WrongKey)?;
+
+            let mut sealed = nonce.to_vec();
+            sealed.append(&mut in_out);
+            Ok(sealed)
+        },
+        CryptoRequest::Open {
+            algorithm,
+            key,
+            aad,
+            mut sealed,
+        } => {
+            let key = aead_key(algorithm, &key)?;
+            if sealed.len() < NONCE_LEN {
+                return Err(CryptoError::FailOpen);
+            }
+
+            let mut in_out = sealed.split_off(NONCE_LEN);
+            let nonce = Nonce::try_assume_unique_for_key(&sealed).map_err(|_| CryptoError::FailOpen)?;
+            let plaintext = key
+                .open_in_place(nonce, Aad::from(aad), &mut in_out)
+                .map_err(|_| CryptoError::FailOpen)?;
+            Ok(plaintext.to_vec())
+        },
+    }
+}
+
+fn aead_key(algorithm: AeadAlgorithm, key: &[u8]) -> CryptoResult<LessSafeKey> {
+    let algorithm = match algorithm {
+        AeadAlgorithm::Aes256Gcm => &aead::AES_256_GCM,
+        AeadAlgorithm::ChaCha20Poly1305 => &aead::CHACHA20_POLY1305,
+    };
+    UnboundKey::new(algorithm, key)
+        .map(LessSafeKey::new)
+        .map_err(|_| CryptoError::WrongKey)
+}
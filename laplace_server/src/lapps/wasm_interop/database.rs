@@ -1,37 +1,125 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use borsh::BorshSerialize;
-use laplace_wasm::database::{Row, Value};
-use rusqlite::types::ValueRef;
-use rusqlite::{Connection, OptionalExtension};
-use tokio::sync::Mutex;
+use borsh::{BorshDeserialize, BorshSerialize};
+use laplace_common::lapp::quote_identifier;
+use laplace_wasm::database::{FtsMatch, FtsQuery, Row, Value};
+use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+use rusqlite::types::{self, ValueRef};
+use rusqlite::{params_from_iter, Connection, OpenFlags, OptionalExtension};
+use tokio::sync::{mpsc, Mutex};
 use wasmtime::Caller;
 
 use crate::lapps::wasm_interop::BoxedSendFuture;
-use crate::lapps::Ctx;
+use crate::lapps::{backup_database, Ctx, DiskQuota, PooledConnection};
+
+/// The capacity of the connection cache of the compiled statements.
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+
+/// The limit of the statements prepared by the instance and not finalized yet.
+const MAX_STATEMENTS: usize = 256;
+
+/// The limit of the cursors open by the instance at once.
+const MAX_CURSORS: usize = 16;
+
+/// The number of the rows read by the cursor ahead of the lapp.
+const CURSOR_BUFFER: usize = 64;
 
 pub struct DatabaseCtx {
-    pub connection: Arc<Mutex<Connection>>,
+    pub connection: Arc<Mutex<PooledConnection>>,
+    statements: Mutex<Statements>,
+    cursors: Mutex<Cursors>,
+
+    /// The directory of the `db_backup` host call, which is not allowed without it.
+    backup_dir: Option<PathBuf>,
 }
 
 impl DatabaseCtx {
-    pub fn new(connection: Connection) -> Self {
+    pub fn new(connection: PooledConnection) -> Self {
+        connection.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
         Self {
             connection: Arc::new(Mutex::new(connection)),
+            statements: Mutex::new(Statements::default()),
+            cursors: Mutex::new(Cursors::default()),
+            backup_dir: None,
+        }
+    }
+
+    pub fn set_backup_dir(&mut self, backup_dir: PathBuf) {
+        self.backup_dir = Some(backup_dir);
+    }
+
+    /// Rolls back the transaction left open by the finished call, so it does not hold the database lock.
+    pub async fn rollback_unfinished(&self) {
+        let connection = self.connection.lock().await;
+        if !connection.is_autocommit() {
+            log::warn!("The unfinished database transaction is rolled back");
+            if let Err(err) = connection.execute_batch("ROLLBACK") {
+                log::error!("Rollback of the unfinished transaction error: {err}");
+            }
         }
     }
 }
 
+/// The cursors open by the `db_query_open` host call. The rows borrow the connection, so every cursor reads them by
+/// its own read-only connection in the blocking task and sends them to the lapp through the bounded channel. The
+/// cursor does not see the uncommitted changes of the instance connection.
+#[derive(Default)]
+struct Cursors {
+    open: HashMap<u64, mpsc::Receiver<Result<Row, String>>>,
+    next_handle: u64,
+}
+
+/// The statements prepared by the `db_prepare` host call. The compiled statement borrows the connection, so only its
+/// SQL and parameters are kept by the handle, and the compiled statement is taken from the connection cache on run.
+#[derive(Default)]
+struct Statements {
+    prepared: HashMap<u64, PreparedStatement>,
+    next_handle: u64,
+}
+
+struct PreparedStatement {
+    sql: String,
+    params: Vec<Value>,
+
+    /// The rows of the current run, the statement is run by the first step after the binding.
+    rows: Option<VecDeque<Row>>,
+}
+
+/// Denies the statements that change the database, so the lapp with the `database_read` permission only runs queries.
+pub fn deny_writes(connection: &Connection) {
+    connection.authorizer(Some(authorize_read));
+}
+
+fn authorize_read(context: AuthContext<'_>) -> Authorization {
+    match context.action {
+        AuthAction::Select
+        | AuthAction::Read { .. }
+        | AuthAction::Function { .. }
+        | AuthAction::Recursive
+        | AuthAction::Transaction { .. } => Authorization::Allow,
+        _ => Authorization::Deny,
+    }
+}
+
+/// Executes the statement unless the lapp disk quota is exceeded.
 pub fn execute(caller: Caller<Ctx>, sql_query_slice: u64) -> BoxedSendFuture<u64> {
-    Box::new(run(caller, sql_query_slice, do_execute))
+    let disk_quota = caller.data().disk_quota.clone();
+    Box::new(run(caller, "db_execute", sql_query_slice, move |connection, sql| {
+        if let Some(Err(exceeded)) = disk_quota.as_ref().map(DiskQuota::check) {
+            return Err(exceeded.to_string());
+        }
+        do_execute(connection, sql)
+    }))
 }
 
 pub fn query(caller: Caller<Ctx>, sql_query_slice: u64) -> BoxedSendFuture<u64> {
-    Box::new(run(caller, sql_query_slice, do_query))
+    Box::new(run(caller, "db_query", sql_query_slice, do_query))
 }
 
 pub fn query_row(caller: Caller<Ctx>, sql_query_slice: u64) -> BoxedSendFuture<u64> {
-    Box::new(run(caller, sql_query_slice, do_query_row))
+    Box::new(run(caller, "db_query_row", sql_query_slice, do_query_row))
 }
 
 pub fn do_execute(connection: &Connection, sql: String) -> Result<u64, String> {
@@ -60,8 +148,439 @@ pub fn do_query_row(connection: &Connection, sql: String) -> Result<Option<Row>,
         .map_err(|err| format!("{:?}", err))
 }
 
+/// Begins the transaction of the instance connection, the statements are not committed until `db_commit`.
+pub fn begin(caller: Caller<Ctx>) -> BoxedSendFuture<u64> {
+    Box::new(transaction(caller, "db_begin", |connection| {
+        if !connection.is_autocommit() {
+            return Err("Transaction is already begun".to_string());
+        }
+        connection.execute_batch("BEGIN").map_err(|err| format!("{err:?}"))
+    }))
+}
+
+pub fn commit(caller: Caller<Ctx>) -> BoxedSendFuture<u64> {
+    Box::new(transaction(caller, "db_commit", |connection| {
+        if connection.is_autocommit() {
+            return Err("Transaction is not begun".to_string());
+        }
+        connection.execute_batch("COMMIT").map_err(|err| format!("{err:?}"))
+    }))
+}
+
+pub fn rollback(caller: Caller<Ctx>) -> BoxedSendFuture<u64> {
+    Box::new(transaction(caller, "db_rollback", |connection| {
+        if connection.is_autocommit() {
+            return Err("Transaction is not begun".to_string());
+        }
+        connection.execute_batch("ROLLBACK").map_err(|err| format!("{err:?}"))
+    }))
+}
+
+async fn transaction(
+    mut caller: Caller<'_, Ctx>,
+    name: &str,
+    fun: impl FnOnce(&Connection) -> Result<(), String>,
+) -> u64 {
+    let serialized = match caller.data_mut().replayed_host_call(name) {
+        Some(serialized) => serialized,
+        None => {
+            let result = match caller.data().database.as_ref() {
+                Some(database_ctx) => fun(&*database_ctx.connection.lock().await),
+                None => Err("Database context not found".to_string()),
+            };
+            record_result(&mut caller, name, &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+pub fn prepare(caller: Caller<Ctx>, sql_query_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(prepare_async(caller, sql_query_slice))
+}
+
+pub async fn prepare_async(mut caller: Caller<'_, Ctx>, sql_query_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let sql = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_string(sql_query_slice)
+        .await
+        .map_err(|err| format!("{err:?}"));
+
+    let serialized = match caller.data_mut().replayed_host_call("db_prepare") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match (sql, caller.data().database.as_ref()) {
+                (Err(err), _) => Err(err),
+                (Ok(_), None) => Err("Database context not found".to_string()),
+                (Ok(sql), Some(database_ctx)) => do_prepare(database_ctx, sql).await,
+            };
+            record_result(&mut caller, "db_prepare", &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+async fn do_prepare(database_ctx: &DatabaseCtx, sql: String) -> Result<u64, String> {
+    let mut statements = database_ctx.statements.lock().await;
+    if statements.prepared.len() >= MAX_STATEMENTS {
+        return Err(format!("Too many prepared statements, the limit is {MAX_STATEMENTS}"));
+    }
+
+    // The statement is compiled once to check the SQL, then it is reused from the connection cache
+    database_ctx
+        .connection
+        .lock()
+        .await
+        .prepare_cached(&sql)
+        .map_err(|err| format!("{err:?}"))?;
+
+    let handle = statements.next_handle;
+    statements.next_handle += 1;
+    statements.prepared.insert(handle, PreparedStatement {
+        sql,
+        params: Vec::new(),
+        rows: None,
+    });
+    Ok(handle)
+}
+
+/// Binds the positional parameters to the statement and resets its run.
+pub fn bind(caller: Caller<Ctx>, handle: u64, params_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(bind_async(caller, handle, params_slice))
+}
+
+pub async fn bind_async(mut caller: Caller<'_, Ctx>, handle: u64, params_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let params = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_vec(params_slice)
+        .await
+        .map_err(|err| format!("{err:?}"))
+        .and_then(|bytes| Vec::<Value>::try_from_slice(&bytes).map_err(|err| format!("{err:?}")));
+
+    let serialized = match caller.data_mut().replayed_host_call("db_bind") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match (params, caller.data().database.as_ref()) {
+                (Err(err), _) => Err(err),
+                (Ok(_), None) => Err("Database context not found".to_string()),
+                (Ok(params), Some(database_ctx)) => {
+                    let mut statements = database_ctx.statements.lock().await;
+                    match statements.prepared.get_mut(&handle) {
+                        Some(statement) => {
+                            statement.params = params;
+                            statement.rows = None;
+                            Ok(())
+                        },
+                        None => Err(format!("Statement {handle} is not prepared")),
+                    }
+                },
+            };
+            record_result(&mut caller, "db_bind", &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+/// Returns the next row of the statement. The first step runs the statement unless the lapp disk quota is exceeded
+/// for the changing statement, and the statement without rows returns `None` at once.
+pub fn step(caller: Caller<Ctx>, handle: u64) -> BoxedSendFuture<u64> {
+    Box::new(step_async(caller, handle))
+}
+
+pub async fn step_async(mut caller: Caller<'_, Ctx>, handle: u64) -> u64 {
+    let serialized = match caller.data_mut().replayed_host_call("db_step") {
+        Some(serialized) => serialized,
+        None => {
+            let disk_quota = caller.data().disk_quota.clone();
+            let result = match caller.data().database.as_ref() {
+                Some(database_ctx) => do_step(database_ctx, handle, disk_quota.as_ref()).await,
+                None => Err("Database context not found".to_string()),
+            };
+            record_result(&mut caller, "db_step", &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+async fn do_step(
+    database_ctx: &DatabaseCtx,
+    handle: u64,
+    disk_quota: Option<&DiskQuota>,
+) -> Result<Option<Row>, String> {
+    let mut statements = database_ctx.statements.lock().await;
+    let statement = statements
+        .prepared
+        .get_mut(&handle)
+        .ok_or_else(|| format!("Statement {handle} is not prepared"))?;
+
+    if statement.rows.is_none() {
+        let connection = database_ctx.connection.lock().await;
+        let mut stmt = connection
+            .prepare_cached(&statement.sql)
+            .map_err(|err| format!("{err:?}"))?;
+        if !stmt.readonly() {
+            if let Some(Err(exceeded)) = disk_quota.map(DiskQuota::check) {
+                return Err(exceeded.to_string());
+            }
+        }
+
+        let params = params_from_iter(statement.params.iter().map(to_sql_value));
+        let mut rows = VecDeque::new();
+        if stmt.column_count() == 0 {
+            stmt.execute(params).map_err(|err| format!("{err:?}"))?;
+        } else {
+            let mut provider = stmt.query(params).map_err(|err| format!("{err:?}"))?;
+            while let Some(row) = provider.next().map_err(|err| format!("{err:?}"))? {
+                rows.push_back(to_row(row).map_err(|err| format!("{err:?}"))?);
+            }
+        }
+        statement.rows = Some(rows);
+    }
+
+    Ok(statement.rows.as_mut().and_then(VecDeque::pop_front))
+}
+
+pub fn query_open(caller: Caller<Ctx>, sql_query_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(query_open_async(caller, sql_query_slice))
+}
+
+pub async fn query_open_async(mut caller: Caller<'_, Ctx>, sql_query_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let sql = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_string(sql_query_slice)
+        .await
+        .map_err(|err| format!("{err:?}"));
+
+    let serialized = match caller.data_mut().replayed_host_call("db_query_open") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match (sql, caller.data().database.as_ref()) {
+                (Err(err), _) => Err(err),
+                (Ok(_), None) => Err("Database context not found".to_string()),
+                (Ok(sql), Some(database_ctx)) => do_query_open(database_ctx, sql).await,
+            };
+            record_result(&mut caller, "db_query_open", &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+async fn do_query_open(database_ctx: &DatabaseCtx, sql: String) -> Result<u64, String> {
+    let mut cursors = database_ctx.cursors.lock().await;
+    if cursors.open.len() >= MAX_CURSORS {
+        return Err(format!("Too many open cursors, the limit is {MAX_CURSORS}"));
+    }
+
+    // The query is checked by the instance connection, so the cursor fails at once on the wrong SQL
+    let database_path = {
+        let connection = database_ctx.connection.lock().await;
+        connection.prepare_cached(&sql).map_err(|err| format!("{err:?}"))?;
+        connection
+            .path()
+            .map(ToString::to_string)
+            .ok_or_else(|| "Database path not found".to_string())?
+    };
+
+    let (rows_out, rows_in) = mpsc::channel(CURSOR_BUFFER);
+    tokio::task::spawn_blocking(move || {
+        let result = Connection::open_with_flags(
+            database_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+        )
+        .and_then(|connection| {
+            deny_writes(&connection);
+            let mut stmt = connection.prepare(&sql)?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                // The closed cursor drops the receiver
+                if rows_out.blocking_send(Ok(to_row(row)?)).is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            rows_out.blocking_send(Err(format!("{err:?}"))).ok();
+        }
+    });
+
+    let handle = cursors.next_handle;
+    cursors.next_handle += 1;
+    cursors.open.insert(handle, rows_in);
+    Ok(handle)
+}
+
+/// Returns the next row of the cursor, or `None` when the rows are over.
+pub fn query_next(caller: Caller<Ctx>, handle: u64) -> BoxedSendFuture<u64> {
+    Box::new(query_next_async(caller, handle))
+}
+
+pub async fn query_next_async(mut caller: Caller<'_, Ctx>, handle: u64) -> u64 {
+    let serialized = match caller.data_mut().replayed_host_call("db_query_next") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match caller.data().database.as_ref() {
+                Some(database_ctx) => {
+                    let mut cursors = database_ctx.cursors.lock().await;
+                    match cursors.open.get_mut(&handle) {
+                        Some(rows_in) => rows_in.recv().await.transpose(),
+                        None => Err(format!("Cursor {handle} is not open")),
+                    }
+                },
+                None => Err("Database context not found".to_string()),
+            };
+            record_result(&mut caller, "db_query_next", &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+/// Closes the cursor, its reading task stops on the next row.
+pub fn query_close(caller: Caller<Ctx>, handle: u64) -> BoxedSendFuture<()> {
+    Box::new(async move {
+        if let Some(database_ctx) = caller.data().database.as_ref() {
+            database_ctx.cursors.lock().await.open.remove(&handle);
+        }
+    })
+}
+
+/// Searches the FTS5 table, the matches are ordered by the rank, the best first.
+pub fn fts_search(caller: Caller<Ctx>, fts_query_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(fts_search_async(caller, fts_query_slice))
+}
+
+pub async fn fts_search_async(mut caller: Caller<'_, Ctx>, fts_query_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let fts_query = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_vec(fts_query_slice)
+        .await
+        .map_err(|err| format!("{err:?}"))
+        .and_then(|bytes| FtsQuery::try_from_slice(&bytes).map_err(|err| format!("{err:?}")));
+
+    let serialized = match caller.data_mut().replayed_host_call("db_fts_search") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match (fts_query, caller.data().database.as_ref()) {
+                (Err(err), _) => Err(err),
+                (Ok(_), None) => Err("Database context not found".to_string()),
+                (Ok(fts_query), Some(database_ctx)) => do_fts_search(&*database_ctx.connection.lock().await, fts_query),
+            };
+            record_result(&mut caller, "db_fts_search", &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+pub fn do_fts_search(connection: &Connection, fts_query: FtsQuery) -> Result<Vec<FtsMatch>, String> {
+    let FtsQuery {
+        table,
+        query,
+        limit,
+        highlight,
+    } = fts_query;
+    let table = quote_identifier(&table);
+    let (open, close) = highlight.unwrap_or_default();
+
+    connection
+        .prepare_cached(&format!(
+            "SELECT rowid, rank, snippet({table}, -1, ?2, ?3, '…', 16) FROM {table} WHERE {table} MATCH ?1 \
+             ORDER BY rank LIMIT ?4"
+        ))
+        .and_then(|mut stmt| {
+            stmt.query_map(rusqlite::params![query, open, close, limit], |row| {
+                Ok(FtsMatch {
+                    rowid: row.get(0)?,
+                    rank: row.get(1)?,
+                    snippet: row.get(2)?,
+                })
+            })?
+            .collect()
+        })
+        .map_err(|err| format!("{err:?}"))
+}
+
+/// Copies the database to the backup directory, returns the file name of the backup.
+pub fn backup(caller: Caller<Ctx>) -> BoxedSendFuture<u64> {
+    Box::new(backup_async(caller))
+}
+
+pub async fn backup_async(mut caller: Caller<'_, Ctx>) -> u64 {
+    let serialized = match caller.data_mut().replayed_host_call("db_backup") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match caller.data().database.as_ref() {
+                Some(database_ctx) => do_backup(database_ctx, caller.data().disk_quota.as_ref()).await,
+                None => Err("Database context not found".to_string()),
+            };
+            record_result(&mut caller, "db_backup", &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+async fn do_backup(database_ctx: &DatabaseCtx, disk_quota: Option<&DiskQuota>) -> Result<String, String> {
+    let backup_dir = database_ctx
+        .backup_dir
+        .clone()
+        .ok_or_else(|| "Database backup is not allowed".to_string())?;
+    if let Some(Err(exceeded)) = disk_quota.map(DiskQuota::check) {
+        return Err(exceeded.to_string());
+    }
+
+    let database_path = database_ctx
+        .connection
+        .lock()
+        .await
+        .path()
+        .map(PathBuf::from)
+        .ok_or_else(|| "Database path not found".to_string())?;
+
+    let backup_path = tokio::task::spawn_blocking(move || backup_database(&database_path, &backup_dir))
+        .await
+        .map_err(|err| err.to_string())?
+        .map_err(|err| err.to_string())?;
+    Ok(backup_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default())
+}
+
+/// Drops the statement, the compiled statement stays in the connection cache.
+pub fn finalize(caller: Caller<Ctx>, handle: u64) -> BoxedSendFuture<()> {
+    Box::new(async move {
+        if let Some(database_ctx) = caller.data().database.as_ref() {
+            database_ctx.statements.lock().await.prepared.remove(&handle);
+        }
+    })
+}
+
+fn record_result<T: BorshSerialize>(caller: &mut Caller<'_, Ctx>, name: &str, result: &Result<T, String>) -> Vec<u8> {
+    let serialized = borsh::to_vec(result).expect("Result should be serializable");
+    caller.data_mut().record_host_call(name, &serialized);
+    serialized
+}
+
+async fn move_to_wasm(mut caller: Caller<'_, Ctx>, serialized: &[u8]) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(serialized)
+        .await
+        .expect("Result should be to move to WASM")
+        .into()
+}
+
 async fn run<T: BorshSerialize + Send>(
     mut caller: Caller<'_, Ctx>,
+    name: &str,
     sql_query_slice: u64,
     fun: impl Fn(&Connection, String) -> Result<T, String>,
 ) -> u64 {
@@ -73,15 +592,22 @@ async fn run<T: BorshSerialize + Send>(
         .await
         .expect("SQL query should be converted to string");
 
-    let result = match caller.data().database.as_ref() {
-        Some(database_ctx) => {
-            let connection = database_ctx.connection.lock().await;
-            fun(&connection, sql)
+    let serialized = match caller.data_mut().replayed_host_call(name) {
+        Some(serialized) => serialized,
+        None => {
+            let result = match caller.data().database.as_ref() {
+                Some(database_ctx) => {
+                    let connection = database_ctx.connection.lock().await;
+                    fun(&connection, sql)
+                },
+                None => Err("Database context not found".to_string()),
+            };
+
+            let serialized = borsh::to_vec(&result).expect("Result should be serializable");
+            caller.data_mut().record_host_call(name, &serialized);
+            serialized
         },
-        None => Err("Database context not found".to_string()),
     };
-
-    let serialized = borsh::to_vec(&result).expect("Result should be serializable");
     memory_data
         .to_manager(&mut caller)
         .bytes_to_wasm_slice(&serialized)
@@ -97,6 +623,16 @@ fn to_row(source: &rusqlite::Row<'_>) -> rusqlite::Result<Row> {
         .map(Row::new)
 }
 
+fn to_sql_value(value: &Value) -> types::Value {
+    match value {
+        Value::Null => types::Value::Null,
+        Value::Integer(val) => types::Value::Integer(*val),
+        Value::Real(val) => types::Value::Real(*val),
+        Value::Text(val) => types::Value::Text(val.clone()),
+        Value::Blob(val) => types::Value::Blob(val.clone()),
+    }
+}
+
 fn to_value(source: ValueRef<'_>) -> Value {
     match source {
         ValueRef::Null => Value::Null,
@@ -106,3 +642,32 @@ fn to_value(source: ValueRef<'_>) -> Value {
         ValueRef::Blob(val) => Value::Blob(val.into()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_only_connection() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch("CREATE TABLE notes (text TEXT); INSERT INTO notes VALUES ('first');")
+            .unwrap();
+        deny_writes(&connection);
+
+        for sql in [
+            "INSERT INTO notes VALUES ('second')",
+            "UPDATE notes SET text = 'changed'",
+            "DELETE FROM notes",
+            "DROP TABLE notes",
+            "CREATE TABLE other (text TEXT)",
+            "ATTACH DATABASE ':memory:' AS other",
+            "PRAGMA user_version = 1",
+        ] {
+            assert!(do_execute(&connection, sql.into()).is_err(), "{sql}");
+        }
+
+        let rows = do_query(&connection, "SELECT text FROM notes".into()).unwrap();
+        assert_eq!(rows, [Row::new(vec![Value::Text("first".into())])]);
+    }
+}
@@ -1,15 +1,22 @@
+use std::collections::HashMap;
 use std::iter::FromIterator;
 use std::time::Duration;
 
 use borsh::BorshDeserialize;
 use laplace_common::lapp::{HttpHosts, HttpMethod, HttpMethods, HttpSettings};
-use laplace_wasm::http;
-use reqwest::Client;
+use laplace_wasm::http::{self, BodyError, BodyResult, REQUEST_BODY};
+use reqwest::{redirect, Client};
 use wasmtime::Caller;
 
 use crate::lapps::wasm_interop::BoxedSendFuture;
 use crate::lapps::Ctx;
 
+/// The redirect limit of the default reqwest policy.
+const MAX_REDIRECTS: usize = 10;
+
+/// The limit of the outbound response bodies read by frames at once.
+const MAX_BODY_STREAMS: usize = 16;
+
 #[derive(Clone)]
 pub struct HttpCtx {
     pub client: Client,
@@ -17,7 +24,36 @@ pub struct HttpCtx {
 }
 
 impl HttpCtx {
+    /// The client of the lapp with the `allow` patterns follows only the redirects to the allowed URLs.
     pub fn new(client: Client, settings: HttpSettings) -> Self {
+        let client = if settings.allow.is_empty() {
+            client
+        } else {
+            let allow_settings = settings.clone();
+            let redirect_policy = redirect::Policy::custom(move |attempt| {
+                let url = attempt.url();
+                let is_allowed = attempt.previous().len() < MAX_REDIRECTS
+                    && allow_settings.is_url_allowed(
+                        url.scheme(),
+                        url.host_str().unwrap_or(""),
+                        url.port(),
+                        url.path(),
+                    );
+                if is_allowed {
+                    attempt.follow()
+                } else {
+                    attempt.stop()
+                }
+            });
+            Client::builder()
+                .redirect(redirect_policy)
+                .build()
+                .unwrap_or_else(|err| {
+                    log::error!("Build HTTP client with the allowed redirects error: {err}");
+                    client
+                })
+        };
+
         Self { client, settings }
     }
 }
@@ -35,17 +71,24 @@ pub async fn invoke_http_async(mut caller: Caller<'_, Ctx>, request_slice: u64)
         .await
         .map_err(|_| http::InvokeError::CanNotReadWasmData);
 
-    let result = match caller.data().http.as_ref() {
-        Some(http_ctx) => match request_bytes.and_then(|bytes| {
-            BorshDeserialize::try_from_slice(&bytes).map_err(|_| http::InvokeError::FailDeserializeRequest)
-        }) {
-            Ok(request) => do_invoke_http(http_ctx, request).await,
-            Err(err) => Err(err),
+    let serialized = match caller.data_mut().replayed_host_call("invoke_http") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match caller.data().http.as_ref() {
+                Some(http_ctx) => match request_bytes.and_then(|bytes| {
+                    BorshDeserialize::try_from_slice(&bytes).map_err(|_| http::InvokeError::FailDeserializeRequest)
+                }) {
+                    Ok(request) => do_invoke_http(http_ctx, request).await,
+                    Err(err) => Err(err),
+                },
+                None => Err(http::InvokeError::EmptyContext),
+            };
+
+            let serialized = borsh::to_vec(&result).expect("Result should be serializable");
+            caller.data_mut().record_host_call("invoke_http", &serialized);
+            serialized
         },
-        None => Err(http::InvokeError::EmptyContext),
     };
-
-    let serialized = borsh::to_vec(&result).expect("Result should be serializable");
     memory_data
         .to_manager(&mut caller)
         .bytes_to_wasm_slice(&serialized)
@@ -55,6 +98,14 @@ pub async fn invoke_http_async(mut caller: Caller<'_, Ctx>, request_slice: u64)
 }
 
 pub async fn do_invoke_http(ctx: &HttpCtx, request: http::Request) -> http::InvokeResult<http::Response> {
+    let response = send_http(ctx, request).await?;
+    let mut head = response_head(&response);
+    head.body = response.bytes().await.map(|bytes| bytes.to_vec()).unwrap_or_default();
+    log::trace!("Invoke HTTP response body: {}", String::from_utf8_lossy(&head.body));
+    Ok(head)
+}
+
+async fn send_http(ctx: &HttpCtx, request: http::Request) -> http::InvokeResult<reqwest::Response> {
     log::trace!("Invoke HTTP: {request:#?},\n{:#?}", ctx.settings);
     let http::Request {
         method,
@@ -74,6 +125,16 @@ pub async fn do_invoke_http(ctx: &HttpCtx, request: http::Request) -> http::Invo
         return Err(http::InvokeError::ForbiddenHost(uri.host().unwrap_or("").into()));
     }
 
+    let is_url_allowed = ctx.settings.is_url_allowed(
+        uri.scheme_str().unwrap_or(""),
+        uri.host().unwrap_or(""),
+        uri.port_u16(),
+        uri.path(),
+    );
+    if !is_url_allowed {
+        return Err(http::InvokeError::ForbiddenUrl(uri.to_string()));
+    }
+
     match ctx
         .client
         .request(method, uri.to_string())
@@ -86,22 +147,7 @@ pub async fn do_invoke_http(ctx: &HttpCtx, request: http::Request) -> http::Invo
     {
         Ok(response) => {
             log::trace!("Invoke HTTP response: {response:#?}");
-
-            Ok(http::Response {
-                status: response.status(),
-                version: response.version(),
-                headers: http::HeaderMap::from_iter(
-                    response
-                        .headers()
-                        .iter()
-                        .map(|(name, value)| (name.clone(), value.clone())),
-                ),
-                body: {
-                    let body = response.bytes().await.map(|bytes| bytes.to_vec()).unwrap_or_default();
-                    log::trace!("Invoke HTTP response body: {}", String::from_utf8_lossy(&body));
-                    body
-                },
-            })
+            Ok(response)
         },
         Err(err) => Err(http::InvokeError::FailRequest(
             err.status().map(|status| status.as_u16()),
@@ -110,6 +156,21 @@ pub async fn do_invoke_http(ctx: &HttpCtx, request: http::Request) -> http::Invo
     }
 }
 
+/// The response without the body.
+fn response_head(response: &reqwest::Response) -> http::Response {
+    http::Response {
+        status: response.status(),
+        version: response.version(),
+        headers: http::HeaderMap::from_iter(
+            response
+                .headers()
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone())),
+        ),
+        body: Vec::new(),
+    }
+}
+
 fn is_method_allowed(method: &http::Method, methods: &HttpMethods) -> bool {
     match methods {
         HttpMethods::All => true,
@@ -126,3 +187,171 @@ fn is_host_allowed(host: &str, hosts: &HttpHosts) -> bool {
         HttpHosts::List(list) => list.iter().any(|item| item.as_str() == host),
     }
 }
+
+/// The bodies read and written by frames. The inbound request body and the written response frames are kept for
+/// the current `process_http` call only, the outbound response bodies are kept until they are read to the end.
+#[derive(Default)]
+pub struct HttpBodyCtx {
+    stream_request_body: bool,
+    request_body: Option<(Vec<u8>, usize)>,
+    response_frames: Vec<u8>,
+    streams: HashMap<u64, (reqwest::Response, Vec<u8>)>,
+    next_handle: u64,
+}
+
+impl HttpBodyCtx {
+    pub fn new(stream_request_body: bool) -> Self {
+        Self {
+            stream_request_body,
+            next_handle: REQUEST_BODY + 1,
+            ..Default::default()
+        }
+    }
+
+    /// Keeps the body of the inbound request on the host when the lapp reads it by frames, the written response
+    /// frames of the previous call are dropped.
+    pub fn start_request(&mut self, request: &mut http::Request) {
+        self.response_frames.clear();
+        self.request_body = if self.stream_request_body {
+            Some((std::mem::take(&mut request.body), 0))
+        } else {
+            None
+        };
+    }
+
+    /// Returns the written response frames, the unread rest of the request body is dropped.
+    pub fn finish_request(&mut self) -> Vec<u8> {
+        self.request_body = None;
+        std::mem::take(&mut self.response_frames)
+    }
+
+    pub fn write_response_frame(&mut self, frame: &[u8]) {
+        self.response_frames.extend_from_slice(frame);
+    }
+
+    pub fn add_stream(&mut self, response: reqwest::Response) -> BodyResult<u64> {
+        if self.streams.len() >= MAX_BODY_STREAMS {
+            return Err(BodyError::TooManyStreams);
+        }
+
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.streams.insert(handle, (response, Vec::new()));
+        Ok(handle)
+    }
+
+    /// Reads the next frame of at most `max_len` bytes, the stream read to the end is closed.
+    pub async fn read_frame(&mut self, handle: u64, max_len: usize) -> BodyResult<Vec<u8>> {
+        if handle == REQUEST_BODY {
+            let (body, offset) = self.request_body.as_mut().ok_or(BodyError::StreamNotFound(handle))?;
+            let end = body.len().min(*offset + max_len);
+            let frame = body[*offset..end].to_vec();
+            *offset = end;
+            return Ok(frame);
+        }
+
+        let (response, pending) = self.streams.get_mut(&handle).ok_or(BodyError::StreamNotFound(handle))?;
+        if pending.is_empty() {
+            match response.chunk().await {
+                Ok(Some(chunk)) => pending.extend_from_slice(&chunk),
+                Ok(None) => {
+                    self.streams.remove(&handle);
+                    return Ok(Vec::new());
+                },
+                Err(err) => {
+                    self.streams.remove(&handle);
+                    return Err(BodyError::FailRead(err.to_string()));
+                },
+            }
+        }
+
+        let len = pending.len().min(max_len);
+        Ok(pending.drain(..len).collect())
+    }
+}
+
+pub fn invoke_http_stream(caller: Caller<Ctx>, request_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(invoke_http_stream_async(caller, request_slice))
+}
+
+pub async fn invoke_http_stream_async(mut caller: Caller<'_, Ctx>, request_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let request_bytes = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_vec(request_slice)
+        .await
+        .map_err(|_| http::InvokeError::CanNotReadWasmData);
+
+    let serialized = match caller.data_mut().replayed_host_call("invoke_http_stream") {
+        Some(serialized) => serialized,
+        None => {
+            let response = match caller.data().http.as_ref() {
+                Some(http_ctx) => match request_bytes.and_then(|bytes| {
+                    BorshDeserialize::try_from_slice(&bytes).map_err(|_| http::InvokeError::FailDeserializeRequest)
+                }) {
+                    Ok(request) => send_http(http_ctx, request).await,
+                    Err(err) => Err(err),
+                },
+                None => Err(http::InvokeError::EmptyContext),
+            };
+            let result = response.and_then(|response| {
+                let head = response_head(&response);
+                caller
+                    .data_mut()
+                    .http_body
+                    .add_stream(response)
+                    .map(|handle| (head, handle))
+                    .map_err(|err| http::InvokeError::FailRequest(None, err.to_string()))
+            });
+
+            let serialized = borsh::to_vec(&result).expect("Result should be serializable");
+            caller.data_mut().record_host_call("invoke_http_stream", &serialized);
+            serialized
+        },
+    };
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(&serialized)
+        .await
+        .expect("Result should be to move to WASM")
+        .into()
+}
+
+pub fn http_read_body(caller: Caller<Ctx>, handle: u64, max_len: u32) -> BoxedSendFuture<u64> {
+    Box::new(http_read_body_async(caller, handle, max_len))
+}
+
+pub async fn http_read_body_async(mut caller: Caller<'_, Ctx>, handle: u64, max_len: u32) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let serialized = match caller.data_mut().replayed_host_call("http_read_body") {
+        Some(serialized) => serialized,
+        None => {
+            let result = caller.data_mut().http_body.read_frame(handle, max_len as usize).await;
+
+            let serialized = borsh::to_vec(&result).expect("Result should be serializable");
+            caller.data_mut().record_host_call("http_read_body", &serialized);
+            serialized
+        },
+    };
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(&serialized)
+        .await
+        .expect("Result should be to move to WASM")
+        .into()
+}
+
+pub fn http_write_body(caller: Caller<Ctx>, frame_slice: u64) -> BoxedSendFuture<()> {
+    Box::new(http_write_body_async(caller, frame_slice))
+}
+
+pub async fn http_write_body_async(mut caller: Caller<'_, Ctx>, frame_slice: u64) {
+    let memory_data = caller.data().memory_data().clone();
+
+    match memory_data.to_manager(&mut caller).wasm_slice_to_vec(frame_slice).await {
+        Ok(frame) => caller.data_mut().http_body.write_response_frame(&frame),
+        Err(err) => log::error!("Read response body frame from WASM error: {err}"),
+    }
+}
@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+use wasmtime::Caller;
+
+use crate::lapps::wasm_interop::BoxedSendFuture;
+use crate::lapps::{Ctx, DiskQuota, JobQueue};
+
+/// The jobs enqueued by the instance, the queue is opened in the lapp database on the first job.
+pub struct JobsCtx {
+    database_path: PathBuf,
+    queue: Option<JobQueue>,
+    jobs_notify: Arc<Notify>,
+}
+
+impl JobsCtx {
+    pub fn new(database_path: PathBuf, jobs_notify: Arc<Notify>) -> Self {
+        Self {
+            database_path,
+            queue: None,
+            jobs_notify,
+        }
+    }
+
+    /// Persists the job and wakes the lapp service to run it.
+    pub fn enqueue(&mut self, payload: &[u8]) -> Result<u64, String> {
+        let queue = match &mut self.queue {
+            Some(queue) => queue,
+            None => {
+                let queue = JobQueue::open(&self.database_path, true)
+                    .map_err(|err| err.to_string())?
+                    .ok_or_else(|| "jobs queue is not created".to_string())?;
+                self.queue.insert(queue)
+            },
+        };
+
+        let id = queue.push(payload).map_err(|err| err.to_string())?;
+        self.jobs_notify.notify_one();
+        Ok(id as u64)
+    }
+}
+
+pub fn job_enqueue(caller: Caller<Ctx>, payload_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(job_enqueue_async(caller, payload_slice))
+}
+
+pub async fn job_enqueue_async(mut caller: Caller<'_, Ctx>, payload_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let payload = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_vec(payload_slice)
+        .await
+        .map_err(|err| format!("{err:?}"));
+
+    let serialized = match caller.data_mut().replayed_host_call("job_enqueue") {
+        Some(serialized) => serialized,
+        None => {
+            let disk_quota = caller.data().disk_quota.clone();
+            let result = payload.and_then(|payload| {
+                if let Some(Err(exceeded)) = disk_quota.as_ref().map(DiskQuota::check) {
+                    return Err(exceeded.to_string());
+                }
+                match caller.data_mut().jobs.as_mut() {
+                    Some(jobs_ctx) => jobs_ctx.enqueue(&payload),
+                    None => Err("Jobs are not allowed, the lapp database is not writable".to_string()),
+                }
+            });
+
+            let serialized = borsh::to_vec(&result).expect("Result should be serializable");
+            caller.data_mut().record_host_call("job_enqueue", &serialized);
+            serialized
+        },
+    };
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(&serialized)
+        .await
+        .expect("Result should be to move to WASM")
+        .into()
+}
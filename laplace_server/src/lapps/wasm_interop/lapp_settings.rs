@@ -0,0 +1,28 @@
+use wasmtime::Caller;
+
+use crate::lapps::wasm_interop::BoxedSendFuture;
+use crate::lapps::Ctx;
+
+pub fn get_settings_json(caller: Caller<Ctx>) -> BoxedSendFuture<u64> {
+    Box::new(get_settings_json_async(caller))
+}
+
+/// Returns the lapp settings taken at the instantiation, the replayed lapp gets the recorded ones.
+pub async fn get_settings_json_async(mut caller: Caller<'_, Ctx>) -> u64 {
+    let settings_json = match caller.data_mut().replayed_host_call("get_settings_json") {
+        Some(serialized) => serialized,
+        None => {
+            let settings_json = caller.data().settings_json.clone().unwrap_or_default().into_bytes();
+            caller.data_mut().record_host_call("get_settings_json", &settings_json);
+            settings_json
+        },
+    };
+
+    let memory_data = caller.data().memory_data().clone();
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(&settings_json)
+        .await
+        .expect("Settings should be to move to WASM")
+        .into()
+}
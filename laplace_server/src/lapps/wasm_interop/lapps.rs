@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use borsh::BorshDeserialize;
+use laplace_wasm::http;
+use laplace_wasm::lapps::{CallError, CallResult, LappMessage, LappRequest};
+use wasmtime::Caller;
+
+use crate::lapp_calls::LappCalls;
+use crate::lapps::wasm_interop::BoxedSendFuture;
+use crate::lapps::Ctx;
+use crate::service::bus::LappBus;
+
+pub struct LappsCtx {
+    lapp_name: String,
+
+    /// The capability tokens of the lapp by the called lapp names.
+    tokens: HashMap<String, String>,
+    calls: LappCalls,
+    bus: Option<LappBus>,
+}
+
+impl LappsCtx {
+    /// Mints the tokens of the lapps listed in the `lapp_requests` settings.
+    pub fn new<'a>(
+        lapp_name: impl Into<String>,
+        lapp_names: impl IntoIterator<Item = &'a str>,
+        calls: LappCalls,
+        bus: Option<LappBus>,
+    ) -> Self {
+        let lapp_name = lapp_name.into();
+        let tokens = lapp_names
+            .into_iter()
+            .filter_map(|called| {
+                calls
+                    .mint_token(&lapp_name, called)
+                    .map(|token| (called.to_string(), token))
+            })
+            .collect();
+
+        Self {
+            lapp_name,
+            tokens,
+            calls,
+            bus,
+        }
+    }
+
+    pub async fn call(&self, request: LappRequest) -> CallResult<http::Response> {
+        let LappRequest { lapp_name, request } = request;
+        let token = self
+            .tokens
+            .get(&lapp_name)
+            .cloned()
+            .ok_or_else(|| CallError::ForbiddenLapp(lapp_name.clone()))?;
+
+        self.calls.call(&self.lapp_name, lapp_name, token, request).await
+    }
+
+    pub async fn send(&self, message: LappMessage) -> CallResult<()> {
+        let LappMessage { lapp_name, payload } = message;
+        let bus = self.bus.as_ref().ok_or(CallError::EmptyContext)?;
+
+        bus.send(&self.lapp_name, lapp_name, payload).await
+    }
+}
+
+pub fn invoke_lapp(caller: Caller<Ctx>, request_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(invoke_lapp_async(caller, request_slice))
+}
+
+pub async fn invoke_lapp_async(mut caller: Caller<'_, Ctx>, request_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let request_bytes = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_vec(request_slice)
+        .await
+        .map_err(|_| CallError::CanNotReadWasmData);
+
+    let serialized = match caller.data_mut().replayed_host_call("invoke_lapp") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match caller.data().lapps.as_ref() {
+                Some(lapps_ctx) => match request_bytes.and_then(|bytes| {
+                    LappRequest::try_from_slice(&bytes).map_err(|_| CallError::FailDeserializeRequest)
+                }) {
+                    Ok(request) => lapps_ctx.call(request).await,
+                    Err(err) => Err(err),
+                },
+                None => Err(CallError::EmptyContext),
+            };
+
+            let serialized = borsh::to_vec(&result).expect("Result should be serializable");
+            caller.data_mut().record_host_call("invoke_lapp", &serialized);
+            serialized
+        },
+    };
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(&serialized)
+        .await
+        .expect("Result should be to move to WASM")
+        .into()
+}
+
+pub fn lapp_send(caller: Caller<Ctx>, message_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(lapp_send_async(caller, message_slice))
+}
+
+pub async fn lapp_send_async(mut caller: Caller<'_, Ctx>, message_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let message_bytes = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_vec(message_slice)
+        .await
+        .map_err(|_| CallError::CanNotReadWasmData);
+
+    let serialized = match caller.data_mut().replayed_host_call("lapp_send") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match caller.data().lapps.as_ref() {
+                Some(lapps_ctx) => match message_bytes.and_then(|bytes| {
+                    LappMessage::try_from_slice(&bytes).map_err(|_| CallError::FailDeserializeRequest)
+                }) {
+                    Ok(message) => lapps_ctx.send(message).await,
+                    Err(err) => Err(err),
+                },
+                None => Err(CallError::EmptyContext),
+            };
+
+            let serialized = borsh::to_vec(&result).expect("Result should be serializable");
+            caller.data_mut().record_host_call("lapp_send", &serialized);
+            serialized
+        },
+    };
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(&serialized)
+        .await
+        .expect("Result should be to move to WASM")
+        .into()
+}
@@ -0,0 +1,55 @@
+use borsh::BorshDeserialize;
+use laplace_common::api::LogLevel;
+use laplace_wasm::log::{Level, Record};
+use wasmtime::Caller;
+
+use crate::lapps::wasm_interop::BoxedSendFuture;
+use crate::lapps::{Ctx, LappLogs};
+
+pub struct LogCtx {
+    lapp_name: String,
+    logs: LappLogs,
+}
+
+impl LogCtx {
+    pub fn new(lapp_name: impl Into<String>, logs: LappLogs) -> Self {
+        Self {
+            lapp_name: lapp_name.into(),
+            logs,
+        }
+    }
+
+    pub fn log(&self, record: Record, request_id: Option<String>) {
+        let Record { level, target, message } = record;
+        let level = match level {
+            Level::Error => LogLevel::Error,
+            Level::Warn => LogLevel::Warn,
+            Level::Info => LogLevel::Info,
+            Level::Debug => LogLevel::Debug,
+            Level::Trace => LogLevel::Trace,
+        };
+        self.logs.push(&self.lapp_name, level, target, message, request_id);
+    }
+}
+
+pub fn log(caller: Caller<Ctx>, record_slice: u64) -> BoxedSendFuture<()> {
+    Box::new(log_async(caller, record_slice))
+}
+
+pub async fn log_async(mut caller: Caller<'_, Ctx>, record_slice: u64) {
+    let memory_data = caller.data().memory_data().clone();
+
+    let record_bytes = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_vec(record_slice)
+        .await;
+
+    let Some(log_ctx) = caller.data().log.as_ref() else {
+        return;
+    };
+    match record_bytes.map(|bytes| Record::try_from_slice(&bytes)) {
+        Ok(Ok(record)) => log_ctx.log(record, caller.data().request_id.clone()),
+        Ok(Err(err)) => log::error!("Log record deserialization error: {err}"),
+        Err(err) => log::error!("Read log record from WASM error: {err}"),
+    }
+}
@@ -0,0 +1,69 @@
+use borsh::BorshDeserialize;
+use laplace_wasm::mail::Mail;
+use wasmtime::Caller;
+
+use crate::lapps::wasm_interop::BoxedSendFuture;
+use crate::lapps::Ctx;
+use crate::mail::Mailer;
+
+pub struct MailCtx {
+    lapp_name: String,
+    mailer: Mailer,
+}
+
+impl MailCtx {
+    pub fn new(lapp_name: impl Into<String>, mailer: Mailer) -> Self {
+        Self {
+            lapp_name: lapp_name.into(),
+            mailer,
+        }
+    }
+
+    pub async fn send(&self, mail: Mail) -> Result<(), String> {
+        let to = mail.to.clone();
+        let result = self.mailer.send(mail).await;
+        match &result {
+            Ok(()) => log::info!("Lapp '{}' sent mail to '{to}'", self.lapp_name),
+            Err(err) => log::warn!("Lapp '{}' send mail to '{to}' error: {err}", self.lapp_name),
+        }
+        result
+    }
+}
+
+pub fn send_mail(caller: Caller<Ctx>, mail_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(send_mail_async(caller, mail_slice))
+}
+
+pub async fn send_mail_async(mut caller: Caller<'_, Ctx>, mail_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let mail = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_vec(mail_slice)
+        .await
+        .map_err(|err| format!("{err:?}"))
+        .and_then(|bytes| Mail::try_from_slice(&bytes).map_err(|err| format!("{err:?}")));
+
+    let serialized = match caller.data_mut().replayed_host_call("send_mail") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match caller.data().mail.as_ref() {
+                Some(mail_ctx) => match mail {
+                    Ok(mail) => mail_ctx.send(mail).await,
+                    Err(err) => Err(err),
+                },
+                None => Err("Mail is not configured".to_string()),
+            };
+
+            let serialized = borsh::to_vec(&result).expect("Result should be serializable");
+            caller.data_mut().record_host_call("send_mail", &serialized);
+            serialized
+        },
+    };
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(&serialized)
+        .await
+        .expect("Result should be to move to WASM")
+        .into()
+}
@@ -0,0 +1,78 @@
+use borsh::BorshDeserialize;
+use laplace_common::lapp::MqttSettings;
+use laplace_wasm::mqtt::{Message, PublishError, PublishResult};
+use rumqttc::{AsyncClient, QoS};
+use wasmtime::Caller;
+
+use crate::lapps::wasm_interop::BoxedSendFuture;
+use crate::lapps::Ctx;
+
+#[derive(Clone)]
+pub struct MqttCtx {
+    pub client: AsyncClient,
+    pub settings: MqttSettings,
+}
+
+impl MqttCtx {
+    pub fn new(client: AsyncClient, settings: MqttSettings) -> Self {
+        Self { client, settings }
+    }
+
+    pub async fn publish(&self, message: Message) -> PublishResult {
+        let Message { topic, payload, retain } = message;
+
+        let is_allowed = rumqttc::valid_topic(&topic)
+            && self
+                .settings
+                .publish
+                .iter()
+                .any(|filter| rumqttc::valid_filter(filter) && rumqttc::matches(&topic, filter));
+        if !is_allowed {
+            return Err(PublishError::ForbiddenTopic(topic));
+        }
+
+        self.client
+            .publish(topic, QoS::AtLeastOnce, retain, payload)
+            .await
+            .map_err(|err| PublishError::FailPublish(err.to_string()))
+    }
+}
+
+pub fn mqtt_publish(caller: Caller<Ctx>, message_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(mqtt_publish_async(caller, message_slice))
+}
+
+pub async fn mqtt_publish_async(mut caller: Caller<'_, Ctx>, message_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let message_bytes = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_vec(message_slice)
+        .await
+        .map_err(|_| PublishError::CanNotReadWasmData);
+
+    let serialized = match caller.data_mut().replayed_host_call("mqtt_publish") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match caller.data().mqtt.as_ref() {
+                Some(mqtt_ctx) => match message_bytes
+                    .and_then(|bytes| Message::try_from_slice(&bytes).map_err(|_| PublishError::FailDeserializeMessage))
+                {
+                    Ok(message) => mqtt_ctx.publish(message).await,
+                    Err(err) => Err(err),
+                },
+                None => Err(PublishError::EmptyContext),
+            };
+
+            let serialized = borsh::to_vec(&result).expect("Result should be serializable");
+            caller.data_mut().record_host_call("mqtt_publish", &serialized);
+            serialized
+        },
+    };
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(&serialized)
+        .await
+        .expect("Result should be to move to WASM")
+        .into()
+}
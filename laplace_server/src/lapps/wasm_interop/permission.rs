@@ -0,0 +1,79 @@
+use laplace_common::lapp::{Permission, PermissionsSettings};
+use laplace_wasm::permission::{PermissionError, PermissionResult, PermissionStatus};
+use wasmtime::Caller;
+
+use crate::lapps::wasm_interop::BoxedSendFuture;
+use crate::lapps::Ctx;
+use crate::permission_requests::PermissionRequests;
+
+pub struct PermissionCtx {
+    lapp_name: String,
+    permissions: PermissionsSettings,
+
+    /// The requests are not sent by the replayed and the read-only lapps.
+    requests: Option<PermissionRequests>,
+}
+
+impl PermissionCtx {
+    pub fn new(
+        lapp_name: impl Into<String>,
+        permissions: PermissionsSettings,
+        requests: Option<PermissionRequests>,
+    ) -> Self {
+        Self {
+            lapp_name: lapp_name.into(),
+            permissions,
+            requests,
+        }
+    }
+
+    /// The status by the settings of the instance, the permission that is not required yet is requested.
+    pub fn request(&self, permission: Permission) -> PermissionStatus {
+        if self.permissions.is_allowed(permission) {
+            PermissionStatus::Granted
+        } else if self.permissions.is_pending(permission) {
+            PermissionStatus::Pending
+        } else if self.permissions.required.contains(&permission) {
+            PermissionStatus::Denied
+        } else if let Some(requests) = &self.requests {
+            requests.request(&self.lapp_name, permission);
+            PermissionStatus::Pending
+        } else {
+            PermissionStatus::Denied
+        }
+    }
+}
+
+pub fn request_permission(caller: Caller<Ctx>, permission_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(request_permission_async(caller, permission_slice))
+}
+
+pub async fn request_permission_async(mut caller: Caller<'_, Ctx>, permission_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let permission = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_string(permission_slice)
+        .await
+        .map_err(|_| PermissionError::CanNotReadWasmData);
+
+    let result: PermissionResult = permission.and_then(|permission| {
+        let permission =
+            Permission::try_from(permission.as_str()).map_err(|_| PermissionError::UnknownPermission(permission))?;
+        Ok(caller
+            .data()
+            .permission
+            .as_ref()
+            .map_or(PermissionStatus::Denied, |permission_ctx| {
+                permission_ctx.request(permission)
+            }))
+    });
+
+    let serialized = borsh::to_vec(&result).expect("Result should be serializable");
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(&serialized)
+        .await
+        .expect("Result should be to move to WASM")
+        .into()
+}
@@ -0,0 +1,70 @@
+use std::path::Path;
+
+use borsh::BorshDeserialize;
+use laplace_wasm::template::{RenderError, RenderRequest, RenderResult};
+use minijinja::{Environment, ErrorKind};
+use wasmtime::Caller;
+
+use crate::lapps::wasm_interop::BoxedSendFuture;
+use crate::lapps::Ctx;
+
+pub struct TemplateCtx {
+    env: Environment<'static>,
+}
+
+impl TemplateCtx {
+    /// The templates are loaded from the directory on the first use and then cached.
+    pub fn new(templates_dir: impl AsRef<Path>) -> Self {
+        let mut env = Environment::new();
+        env.set_loader(minijinja::path_loader(templates_dir.as_ref()));
+        Self { env }
+    }
+
+    pub fn render(&self, request: RenderRequest) -> RenderResult {
+        let RenderRequest {
+            template_name,
+            context_json,
+        } = request;
+
+        let context: serde_json::Value =
+            serde_json::from_str(&context_json).map_err(|err| RenderError::WrongContext(err.to_string()))?;
+        let template = self.env.get_template(&template_name).map_err(|err| match err.kind() {
+            ErrorKind::TemplateNotFound => RenderError::TemplateNotFound(template_name.clone()),
+            _ => RenderError::FailRender(err.to_string()),
+        })?;
+
+        template
+            .render(context)
+            .map_err(|err| RenderError::FailRender(err.to_string()))
+    }
+}
+
+pub fn render_template(caller: Caller<Ctx>, request_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(render_template_async(caller, request_slice))
+}
+
+/// The rendering depends on the lapp templates only, so it is not recorded to the trace.
+pub async fn render_template_async(mut caller: Caller<'_, Ctx>, request_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let request_bytes = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_vec(request_slice)
+        .await
+        .map_err(|_| RenderError::CanNotReadWasmData);
+
+    let result = match caller.data().template.as_ref() {
+        Some(template_ctx) => request_bytes
+            .and_then(|bytes| RenderRequest::try_from_slice(&bytes).map_err(|_| RenderError::FailDeserializeRequest))
+            .and_then(|request| template_ctx.render(request)),
+        None => Err(RenderError::EmptyContext),
+    };
+
+    let serialized = borsh::to_vec(&result).expect("Result should be serializable");
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(&serialized)
+        .await
+        .expect("Result should be to move to WASM")
+        .into()
+}
@@ -0,0 +1,221 @@
+//! Threads of the lapps compiled with the wasm threads proposal.
+//!
+//! Such a module imports the shared memory and the `wasi::thread-spawn` function. Every spawned thread is a new
+//! instance of the module over the same shared memory, started by the `wasi_thread_start` export on a dedicated
+//! host thread. The host threads of all lapps are bounded by the server pool, the lapp limits the number of its
+//! running threads and the CPU time they spend per minute. The host calls of the threads have no database, HTTP,
+//! MQTT and template contexts, so the threads are intended for computations.
+
+use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use laplace_common::lapp::ThreadsSettings;
+use tokio::runtime::Handle;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use wasmtime::{Caller, Engine, InstancePre, Store};
+use wasmtime_wasi::preview2::{Table, WasiCtxBuilder};
+
+use crate::lapps::wasm_interop::MemoryManagementHostData;
+use crate::lapps::{Ctx, LappInstanceError};
+
+/// The maximum thread ID allowed by the wasi-threads specification.
+const MAX_THREAD_ID: i32 = 0x1FFF_FFFF;
+
+/// The period of the lapp threads CPU quota.
+const CPU_QUOTA_PERIOD: Duration = Duration::from_secs(60);
+
+/// The host threads shared by the threads of all lapps.
+#[derive(Clone)]
+pub struct ThreadPool {
+    permits: Arc<Semaphore>,
+}
+
+impl ThreadPool {
+    pub fn new(size: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(size.max(1))),
+        }
+    }
+}
+
+impl Default for ThreadPool {
+    fn default() -> Self {
+        Self::new(std::thread::available_parallelism().map_or(1, usize::from))
+    }
+}
+
+struct CpuUsage {
+    period_start: Instant,
+    used: Duration,
+}
+
+impl CpuUsage {
+    fn current(&mut self) -> &mut Duration {
+        if self.period_start.elapsed() >= CPU_QUOTA_PERIOD {
+            self.period_start = Instant::now();
+            self.used = Duration::ZERO;
+        }
+        &mut self.used
+    }
+}
+
+pub struct ThreadsCtx {
+    lapp_name: String,
+    engine: Engine,
+    instance_pre: InstancePre<Ctx>,
+    runtime: Handle,
+    pool: ThreadPool,
+    settings: ThreadsSettings,
+    running: AtomicU32,
+    next_id: AtomicI32,
+    cpu_usage: Mutex<CpuUsage>,
+}
+
+impl ThreadsCtx {
+    pub fn new(
+        lapp_name: impl Into<String>,
+        engine: Engine,
+        instance_pre: InstancePre<Ctx>,
+        pool: ThreadPool,
+        settings: ThreadsSettings,
+    ) -> Self {
+        Self {
+            lapp_name: lapp_name.into(),
+            engine,
+            instance_pre,
+            runtime: Handle::current(),
+            pool,
+            settings,
+            running: AtomicU32::new(0),
+            next_id: AtomicI32::new(1),
+            cpu_usage: Mutex::new(CpuUsage {
+                period_start: Instant::now(),
+                used: Duration::ZERO,
+            }),
+        }
+    }
+
+    /// The CPU time spent by the finished threads of the lapp during the current quota period.
+    pub fn cpu_used(&self) -> Duration {
+        *self
+            .cpu_usage
+            .lock()
+            .expect("CPU usage lock should not be poisoned")
+            .current()
+    }
+
+    fn is_cpu_quota_exceeded(&self) -> bool {
+        self.settings
+            .cpu_quota_ms
+            .is_some_and(|quota_ms| self.cpu_used() >= Duration::from_millis(quota_ms))
+    }
+
+    fn account_cpu(&self, time: Duration) {
+        *self
+            .cpu_usage
+            .lock()
+            .expect("CPU usage lock should not be poisoned")
+            .current() += time;
+    }
+
+    fn spawn(self: &Arc<Self>, start_arg: i32) -> Result<i32, String> {
+        if self.is_cpu_quota_exceeded() {
+            return Err("CPU quota of the threads is exceeded".into());
+        }
+
+        let permit = self
+            .pool
+            .permits
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| "host thread pool is exhausted".to_string())?;
+
+        let max_threads = self.settings.max_threads;
+        self.running
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |running| {
+                (running < max_threads).then_some(running + 1)
+            })
+            .map_err(|_| format!("limit of {max_threads} threads is reached"))?;
+
+        let id = self
+            .next_id
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |id| {
+                Some(if id >= MAX_THREAD_ID { 1 } else { id + 1 })
+            })
+            .unwrap_or(1);
+
+        let ctx = Arc::clone(self);
+        std::thread::Builder::new()
+            .name(format!("lapp-{}-{id}", self.lapp_name))
+            .spawn(move || ctx.run(id, start_arg, permit))
+            .map_err(|err| {
+                self.running.fetch_sub(1, Ordering::SeqCst);
+                format!("host thread is not spawned: {err}")
+            })?;
+
+        Ok(id)
+    }
+
+    fn run(self: Arc<Self>, id: i32, start_arg: i32, permit: OwnedSemaphorePermit) {
+        let cpu_start = thread_cpu_time();
+        let started = Instant::now();
+
+        if let Err(err) = self.runtime.block_on(self.start(id, start_arg)) {
+            log::error!("Thread {id} of lapp '{}' failed: {err}", self.lapp_name);
+        }
+
+        let cpu_time = match (cpu_start, thread_cpu_time()) {
+            (Some(start), Some(end)) => end.saturating_sub(start),
+            _ => started.elapsed(),
+        };
+        self.account_cpu(cpu_time);
+        self.running.fetch_sub(1, Ordering::SeqCst);
+        drop(permit);
+    }
+
+    async fn start(self: &Arc<Self>, id: i32, start_arg: i32) -> Result<(), LappInstanceError> {
+        let wasi = WasiCtxBuilder::new().inherit_stdout().build();
+        let mut ctx = Ctx::new(wasi, Table::new());
+        ctx.threads = Some(Arc::clone(self));
+
+        let mut store = Store::new(&self.engine, ctx);
+        // The threads are limited by the CPU quota instead of the call time limit
+        Ctx::interrupt_on_deadline(&mut store);
+        let instance = self.instance_pre.instantiate_async(&mut store).await?;
+        let memory_management = MemoryManagementHostData::from_instance(&instance, &mut store)?;
+        store.data_mut().memory_data = Some(memory_management);
+
+        instance
+            .get_typed_func::<(i32, i32), ()>(&mut store, "wasi_thread_start")?
+            .call_async(&mut store, (id, start_arg))
+            .await
+            .map_err(LappInstanceError::from_call)
+    }
+}
+
+/// Spawns the lapp thread, returns its ID or a negative value if the thread is not spawned.
+pub fn thread_spawn(caller: Caller<'_, Ctx>, start_arg: i32) -> i32 {
+    let Some(threads) = caller.data().threads.clone() else {
+        return -1;
+    };
+
+    threads.spawn(start_arg).unwrap_or_else(|err| {
+        log::warn!("Thread of lapp '{}' is not spawned: {err}", threads.lapp_name);
+        -1
+    })
+}
+
+#[cfg(unix)]
+fn thread_cpu_time() -> Option<Duration> {
+    let mut time = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+
+    // SAFETY: the pointer refers to the valid timespec for the duration of the call
+    let result = unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut time) };
+    (result == 0).then(|| Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+}
+
+#[cfg(not(unix))]
+fn thread_cpu_time() -> Option<Duration> {
+    None
+}
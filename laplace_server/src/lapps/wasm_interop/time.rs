@@ -0,0 +1,36 @@
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use wasmtime::Caller;
+
+use crate::lapps::Ctx;
+
+static MONOTONIC_START: OnceLock<Instant> = OnceLock::new();
+
+pub fn now_millis(caller: Caller<Ctx>) -> u64 {
+    replayed_or_record(caller, "now_millis", || {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis() as u64)
+    })
+}
+
+/// The nanoseconds since the first call of any lapp, so the values are comparable across the instances.
+pub fn monotonic_nanos(caller: Caller<Ctx>) -> u64 {
+    replayed_or_record(caller, "monotonic_nanos", || {
+        MONOTONIC_START.get_or_init(Instant::now).elapsed().as_nanos() as u64
+    })
+}
+
+/// The replayed lapp gets the recorded time, so its calls behave as the recorded ones.
+fn replayed_or_record(mut caller: Caller<Ctx>, name: &str, time: impl FnOnce() -> u64) -> u64 {
+    if let Some(serialized) = caller.data_mut().replayed_host_call(name) {
+        if let Ok(bytes) = serialized.try_into() {
+            return u64::from_le_bytes(bytes);
+        }
+    }
+
+    let time = time();
+    caller.data_mut().record_host_call(name, &time.to_le_bytes());
+    time
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant, MissedTickBehavior};
+use wasmtime::Caller;
+
+use crate::lapps::Ctx;
+
+/// The limit of the timers set by the instance at once, it is also the capacity of the fired timers queue.
+pub const MAX_TIMERS: usize = 64;
+
+/// The shortest period of the interval timer.
+const MIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The timers of the main lapp instance. The ids of the fired timers are sent to the lapp service, which calls
+/// the `on_timer` export, so the interval does not fire again until its previous tick is queued.
+pub struct TimersCtx {
+    fired_out: mpsc::Sender<u64>,
+    timers: HashMap<u64, JoinHandle<()>>,
+}
+
+impl TimersCtx {
+    pub fn new(fired_out: mpsc::Sender<u64>) -> Self {
+        Self {
+            fired_out,
+            timers: HashMap::new(),
+        }
+    }
+
+    /// Sets the timer, the timer with the same id is replaced. Returns `false` if the instance has too many timers.
+    pub fn set(&mut self, id: u64, delay: Duration, is_interval: bool) -> bool {
+        self.timers.retain(|_, timer| !timer.is_finished());
+        if !self.timers.contains_key(&id) && self.timers.len() >= MAX_TIMERS {
+            return false;
+        }
+
+        let fired_out = self.fired_out.clone();
+        let timer = tokio::spawn(async move {
+            if is_interval {
+                let period = delay.max(MIN_INTERVAL);
+                let mut interval = time::interval_at(Instant::now() + period, period);
+                interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                loop {
+                    interval.tick().await;
+                    if fired_out.send(id).await.is_err() {
+                        break;
+                    }
+                }
+            } else {
+                time::sleep(delay).await;
+                fired_out.send(id).await.ok();
+            }
+        });
+
+        if let Some(replaced) = self.timers.insert(id, timer) {
+            replaced.abort();
+        }
+        true
+    }
+
+    pub fn clear(&mut self, id: u64) {
+        if let Some(timer) = self.timers.remove(&id) {
+            timer.abort();
+        }
+    }
+}
+
+impl Drop for TimersCtx {
+    fn drop(&mut self) {
+        for timer in self.timers.values() {
+            timer.abort();
+        }
+    }
+}
+
+pub fn set_timeout(caller: Caller<Ctx>, millis: u64, id: u64) -> u32 {
+    set_timer(caller, "set_timeout", millis, id, false)
+}
+
+pub fn set_interval(caller: Caller<Ctx>, millis: u64, id: u64) -> u32 {
+    set_timer(caller, "set_interval", millis, id, true)
+}
+
+pub fn clear_timer(mut caller: Caller<Ctx>, id: u64) {
+    if let Some(timers_ctx) = caller.data_mut().timers.as_mut() {
+        timers_ctx.clear(id);
+    }
+}
+
+/// The replayed lapp does not set the timers, the recorded `on_timer` calls are replayed instead.
+fn set_timer(mut caller: Caller<Ctx>, name: &str, millis: u64, id: u64, is_interval: bool) -> u32 {
+    if let Some(serialized) = caller.data_mut().replayed_host_call(name) {
+        return serialized.first().copied().unwrap_or_default().into();
+    }
+
+    let is_set = caller
+        .data_mut()
+        .timers
+        .as_mut()
+        .is_some_and(|timers_ctx| timers_ctx.set(id, Duration::from_millis(millis), is_interval));
+    caller.data_mut().record_host_call(name, &[is_set.into()]);
+    is_set.into()
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use borsh::BorshDeserialize;
+use futures::{SinkExt, StreamExt};
+use laplace_common::lapp::HttpSettings;
+use laplace_wasm::ws_client::{Event, Frame, WsError, WsResult};
+use reqwest::Url;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use wasmtime::Caller;
+
+use crate::lapps::wasm_interop::BoxedSendFuture;
+use crate::lapps::Ctx;
+
+/// The limit of the outbound connections open by the instance at once.
+const MAX_CONNECTIONS: usize = 16;
+
+type Connection = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// The outbound WebSocket connections of the instance, the URLs are checked by the `network.http` settings.
+pub struct WsClientCtx {
+    settings: HttpSettings,
+    connections: HashMap<u64, Connection>,
+    next_handle: u64,
+}
+
+impl WsClientCtx {
+    pub fn new(settings: HttpSettings) -> Self {
+        Self {
+            settings,
+            connections: HashMap::new(),
+            next_handle: 0,
+        }
+    }
+
+    pub async fn connect(&mut self, url: String) -> WsResult<u64> {
+        let parsed_url = Url::parse(&url).map_err(|_| WsError::ForbiddenUrl(url.clone()))?;
+        let is_allowed = matches!(parsed_url.scheme(), "ws" | "wss")
+            && self.settings.is_url_allowed(
+                parsed_url.scheme(),
+                parsed_url.host_str().unwrap_or(""),
+                parsed_url.port(),
+                parsed_url.path(),
+            );
+        if !is_allowed {
+            return Err(WsError::ForbiddenUrl(url));
+        }
+        if self.connections.len() >= MAX_CONNECTIONS {
+            return Err(WsError::TooManyConnections);
+        }
+
+        let timeout = Duration::from_millis(self.settings.timeout_ms);
+        let (connection, _) = tokio::time::timeout(timeout, tokio_tungstenite::connect_async(url.as_str()))
+            .await
+            .map_err(|_| WsError::FailConnect("connection timed out".into()))?
+            .map_err(|err| WsError::FailConnect(err.to_string()))?;
+
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.connections.insert(handle, connection);
+        Ok(handle)
+    }
+
+    pub async fn send(&mut self, handle: u64, frame: Frame) -> WsResult<()> {
+        let connection = self
+            .connections
+            .get_mut(&handle)
+            .ok_or(WsError::ConnectionNotFound(handle))?;
+        let message = match frame {
+            Frame::Text(text) => Message::Text(text),
+            Frame::Binary(bytes) => Message::Binary(bytes),
+        };
+        connection
+            .send(message)
+            .await
+            .map_err(|err| WsError::FailSend(err.to_string()))
+    }
+
+    /// Waits for the next frame or the close of the connection, the closed connection is dropped.
+    pub async fn receive(&mut self, handle: u64, timeout: Duration) -> WsResult<Option<Event>> {
+        let connection = self
+            .connections
+            .get_mut(&handle)
+            .ok_or(WsError::ConnectionNotFound(handle))?;
+
+        let next_event = async {
+            loop {
+                match connection.next().await {
+                    Some(Ok(Message::Text(text))) => return Ok(Event::Frame(Frame::Text(text))),
+                    Some(Ok(Message::Binary(bytes))) => return Ok(Event::Frame(Frame::Binary(bytes))),
+                    Some(Ok(Message::Close(close_frame))) => {
+                        return Ok(Event::Closed {
+                            code: close_frame.as_ref().map(|close_frame| close_frame.code.into()),
+                            reason: close_frame
+                                .map(|close_frame| close_frame.reason.into_owned())
+                                .unwrap_or_default(),
+                        })
+                    },
+                    // The pings are answered by the connection itself
+                    Some(Ok(_)) => continue,
+                    Some(Err(err)) => return Err(WsError::FailReceive(err.to_string())),
+                    None => {
+                        return Ok(Event::Closed {
+                            code: None,
+                            reason: String::new(),
+                        })
+                    },
+                }
+            }
+        };
+
+        match tokio::time::timeout(timeout, next_event).await {
+            Ok(Ok(Event::Frame(frame))) => Ok(Some(Event::Frame(frame))),
+            Ok(closed_or_err) => {
+                self.connections.remove(&handle);
+                closed_or_err.map(Some)
+            },
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub async fn close(&mut self, handle: u64) -> WsResult<()> {
+        let mut connection = self
+            .connections
+            .remove(&handle)
+            .ok_or(WsError::ConnectionNotFound(handle))?;
+        connection
+            .close(None)
+            .await
+            .map_err(|err| WsError::FailSend(err.to_string()))
+    }
+}
+
+pub fn ws_connect(caller: Caller<Ctx>, url_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(ws_connect_async(caller, url_slice))
+}
+
+pub async fn ws_connect_async(mut caller: Caller<'_, Ctx>, url_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let url = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_string(url_slice)
+        .await
+        .map_err(|_| WsError::CanNotReadWasmData);
+
+    let serialized = match caller.data_mut().replayed_host_call("ws_connect") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match caller.data_mut().ws_client.as_mut() {
+                Some(ws_client_ctx) => match url {
+                    Ok(url) => ws_client_ctx.connect(url).await,
+                    Err(err) => Err(err),
+                },
+                None => Err(WsError::EmptyContext),
+            };
+            record_result(&mut caller, "ws_connect", &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+pub fn ws_send(caller: Caller<Ctx>, handle: u64, frame_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(ws_send_async(caller, handle, frame_slice))
+}
+
+pub async fn ws_send_async(mut caller: Caller<'_, Ctx>, handle: u64, frame_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let frame_bytes = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_vec(frame_slice)
+        .await
+        .map_err(|_| WsError::CanNotReadWasmData);
+
+    let serialized = match caller.data_mut().replayed_host_call("ws_send") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match caller.data_mut().ws_client.as_mut() {
+                Some(ws_client_ctx) => match frame_bytes
+                    .and_then(|bytes| Frame::try_from_slice(&bytes).map_err(|_| WsError::FailDeserializeFrame))
+                {
+                    Ok(frame) => ws_client_ctx.send(handle, frame).await,
+                    Err(err) => Err(err),
+                },
+                None => Err(WsError::EmptyContext),
+            };
+            record_result(&mut caller, "ws_send", &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+pub fn ws_receive(caller: Caller<Ctx>, handle: u64, timeout_ms: u64) -> BoxedSendFuture<u64> {
+    Box::new(ws_receive_async(caller, handle, timeout_ms))
+}
+
+pub async fn ws_receive_async(mut caller: Caller<'_, Ctx>, handle: u64, timeout_ms: u64) -> u64 {
+    let serialized = match caller.data_mut().replayed_host_call("ws_receive") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match caller.data_mut().ws_client.as_mut() {
+                Some(ws_client_ctx) => ws_client_ctx.receive(handle, Duration::from_millis(timeout_ms)).await,
+                None => Err(WsError::EmptyContext),
+            };
+            record_result(&mut caller, "ws_receive", &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+pub fn ws_close(caller: Caller<Ctx>, handle: u64) -> BoxedSendFuture<u64> {
+    Box::new(ws_close_async(caller, handle))
+}
+
+pub async fn ws_close_async(mut caller: Caller<'_, Ctx>, handle: u64) -> u64 {
+    let serialized = match caller.data_mut().replayed_host_call("ws_close") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match caller.data_mut().ws_client.as_mut() {
+                Some(ws_client_ctx) => ws_client_ctx.close(handle).await,
+                None => Err(WsError::EmptyContext),
+            };
+            record_result(&mut caller, "ws_close", &result)
+        },
+    };
+    move_to_wasm(caller, &serialized).await
+}
+
+fn record_result<T: borsh::BorshSerialize>(caller: &mut Caller<'_, Ctx>, name: &str, result: &WsResult<T>) -> Vec<u8> {
+    let serialized = borsh::to_vec(result).expect("Result should be serializable");
+    caller.data_mut().record_host_call(name, &serialized);
+    serialized
+}
+
+async fn move_to_wasm(mut caller: Caller<'_, Ctx>, serialized: &[u8]) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(serialized)
+        .await
+        .expect("Result should be to move to WASM")
+        .into()
+}
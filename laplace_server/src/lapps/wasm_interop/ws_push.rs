@@ -0,0 +1,55 @@
+use borsh::BorshDeserialize;
+use laplace_wasm::route::websocket::Message;
+use wasmtime::Caller;
+
+use crate::lapps::wasm_interop::BoxedSendFuture;
+use crate::lapps::Ctx;
+
+/// Returns the id of the client whose message is routed, `0` outside of the `route_ws` call.
+pub fn ws_current_client(mut caller: Caller<Ctx>) -> u64 {
+    if let Some(serialized) = caller.data_mut().replayed_host_call("ws_current_client") {
+        let bytes = serialized.try_into().unwrap_or_default();
+        return u64::from_le_bytes(bytes);
+    }
+
+    let client_id = caller.data().ws_current_client.unwrap_or_default();
+    caller
+        .data_mut()
+        .record_host_call("ws_current_client", &client_id.to_le_bytes());
+    client_id
+}
+
+pub fn ws_push(caller: Caller<Ctx>, client_id: u64, msg_slice: u64) -> BoxedSendFuture<u64> {
+    Box::new(ws_push_async(caller, client_id, msg_slice))
+}
+
+pub async fn ws_push_async(mut caller: Caller<'_, Ctx>, client_id: u64, msg_slice: u64) -> u64 {
+    let memory_data = caller.data().memory_data().clone();
+
+    let msg = memory_data
+        .to_manager(&mut caller)
+        .wasm_slice_to_vec(msg_slice)
+        .await
+        .map_err(|err| format!("{err:?}"))
+        .and_then(|bytes| Message::try_from_slice(&bytes).map_err(|err| format!("{err:?}")));
+
+    let serialized = match caller.data_mut().replayed_host_call("ws_push") {
+        Some(serialized) => serialized,
+        None => {
+            let result = match caller.data().ws_push.as_ref() {
+                Some(ws_clients) => msg.and_then(|msg| ws_clients.push(client_id, msg)),
+                None => Err("WebSocket push context is empty".to_string()),
+            };
+
+            let serialized = borsh::to_vec(&result).expect("Result should be serializable");
+            caller.data_mut().record_host_call("ws_push", &serialized);
+            serialized
+        },
+    };
+    memory_data
+        .to_manager(&mut caller)
+        .bytes_to_wasm_slice(&serialized)
+        .await
+        .expect("Result should be to move to WASM")
+        .into()
+}
@@ -1,38 +1,80 @@
-use std::net::{IpAddr, SocketAddr};
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpListener};
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
 use axum::extract::DefaultBodyLimit;
-use axum::http::{HeaderName, HeaderValue};
+use axum::http::{HeaderName, HeaderValue, StatusCode};
 use axum::response::Redirect;
-use axum::routing::get;
-use axum::{middleware, Router};
+use axum::routing::{any, get};
+use axum::{middleware, Router, ServiceExt};
 use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
 use const_format::concatcp;
 use flexi_logger::{Age, Cleanup, Criterion, Duplicate, FileSpec, Logger, LoggerHandle, Naming};
+use futures::{future, FutureExt};
+use laplace_common::lapp::settings::SecurityHeadersSettings;
 use rustls::ServerConfig;
-use tower::ServiceBuilder;
+use tower::{Layer, ServiceBuilder};
 use tower_http::compression::CompressionLayer;
 use tower_http::normalize_path::NormalizePathLayer;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 use tower_http::services::{ServeDir, ServeFile};
 use tower_http::set_header::SetResponseHeaderLayer;
 use truba::Context;
 
-use crate::error::AppResult;
-use crate::lapps::{Lapp, LappsProvider};
+use crate::access_log::AccessLog;
+use crate::auth::users::Users;
+use crate::deploy::Deployer;
+use crate::error::{AppError, AppResult};
+use crate::lapps::{Lapp, LappsProvider, ModuleCacheKey, PermissionAudit};
+use crate::mail::Mailer;
+use crate::mqtt::MqttBridge;
+use crate::registry::Registry;
+use crate::scheduler::Scheduler;
 use crate::service::Addr;
-use crate::settings::{LoggerSettings, Settings};
+use crate::settings::{HttpSettings, LoggerSettings, Settings};
+use crate::settings_editor::SettingsEditor;
+use crate::watcher::LappsWatcher;
 
+pub mod access_log;
 pub mod auth;
+pub mod circuit_breaker;
 pub mod convert;
+pub mod deploy;
+pub mod dump;
 pub mod error;
+pub mod graphql;
+pub mod lapp_calls;
 pub mod lapps;
+pub mod mail;
+pub mod mqtt;
+pub mod permission_requests;
+pub mod rate_limit;
+pub mod registry;
+pub mod replication;
+pub mod scheduler;
+pub mod security_headers;
 pub mod service;
 pub mod settings;
+pub mod settings_editor;
+pub mod tasks;
+#[cfg(feature = "test-utils")]
+pub mod test;
+pub mod vhost;
+pub mod watcher;
 pub mod web_api;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// The maximum time the stopping HTTP server waits for the open connections to close.
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The handle of the started logger, it is used to change the log spec on the config reload.
+static LOGGER: OnceLock<LoggerHandle> = OnceLock::new();
+
 pub fn init_logger(settings: &LoggerSettings) -> AppResult<LoggerHandle> {
     let mut logger = Logger::try_with_env_or_str(&settings.spec)?;
     if let Some(path) = &settings.path {
@@ -54,13 +96,64 @@ pub fn init_logger(settings: &LoggerSettings) -> AppResult<LoggerHandle> {
         .use_utc()
         .format(flexi_logger::colored_detailed_format)
         .start()?;
+    LOGGER.set(handle.clone()).ok();
 
     Ok(handle)
 }
 
+/// Runs the server until the `SIGINT` or `SIGTERM` signal.
 pub async fn run(settings: Settings) -> AppResult<()> {
+    run_with_shutdown(settings, shutdown_signal()).await
+}
+
+/// Completes on the first `SIGINT` (Ctrl+C) or, on Unix, `SIGTERM` of the process.
+pub async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            log::error!("Cannot listen to SIGINT: {err}");
+            future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            },
+            Err(err) => {
+                log::error!("Cannot listen to SIGTERM: {err}");
+                future::pending::<()>().await;
+            },
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = future::pending::<()>();
+
+    tokio::select! {
+        () = interrupt => log::info!("Received SIGINT, shutting down"),
+        () = terminate => log::info!("Received SIGTERM, shutting down"),
+    }
+}
+
+/// Runs the server until the `shutdown` future completes, then gracefully stops it.
+pub async fn run_with_shutdown(
+    settings: Settings,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> AppResult<()> {
+    let http_listener = bind_http_listener(&settings.http)?;
+    run_on_listener(settings, http_listener, shutdown).await
+}
+
+/// Runs the server on the already bound HTTP listener until the `shutdown` future completes. Then the server stops
+/// accepting the connections, waits for the open ones to close, drains the lapp services and flushes the logs.
+pub async fn run_on_listener(
+    settings: Settings,
+    http_listener: TcpListener,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> AppResult<()> {
     let web_root = settings.http.web_root.clone();
-    let laplace_access_token = auth::prepare_access_token(settings.http.access_token.clone())?;
+    let laplace_access_token = auth::prepare_access_token(settings.http.access_token.clone(), &settings.paths.state)?;
     let upload_file_limit = settings.http.upload_file_limit;
     let ctx = Context::<Addr>::default();
     let lapps_provider = LappsProvider::new(&settings.lapps, ctx.clone())
@@ -72,52 +165,213 @@ pub async fn run(settings: Settings) -> AppResult<()> {
             )
         });
 
+    let http_server_addr = http_listener.local_addr()?;
+    let laplace_url = laplace_url(&settings, http_server_addr.port(), laplace_access_token);
+
     if settings.http.print_url {
-        let access_query = (!laplace_access_token.is_empty())
-            .then(|| format!("?access_token={laplace_access_token}"))
-            .unwrap_or_default();
+        log::info!("Laplace URL: {laplace_url}");
+    }
 
-        log::info!(
-            "Laplace URL: {schema}://{host}:{port}/{access_query}",
-            schema = if settings.ssl.enabled { "https" } else { "http" },
-            host = settings.http.host,
-            port = settings.http.port,
-        );
+    if settings.http.open_browser {
+        if let Err(err) = open::that_detached(&laplace_url) {
+            log::warn!("Cannot open Laplace URL in browser: {err}");
+        }
     }
 
+    lapps_provider
+        .write_manager()
+        .await
+        .set_permission_audit(PermissionAudit::new(&settings.paths.state));
+
+    if settings.lapps.module_cache {
+        match ModuleCacheKey::load_or_generate(&settings.paths.state) {
+            Ok(key) => lapps_provider.write_manager().await.set_module_cache_key(key),
+            Err(err) => log::error!("Module cache key is not loaded, the lapp modules are not cached: {err}"),
+        }
+    }
+
+    if settings.mqtt.enabled {
+        let mqtt_bridge = MqttBridge::new(&settings.mqtt);
+        lapps_provider
+            .write_manager()
+            .await
+            .set_mqtt_client(mqtt_bridge.client().clone());
+        mqtt_bridge.run(lapps_provider.clone());
+    }
+
+    if settings.smtp.enabled {
+        match Mailer::new(&settings.smtp) {
+            Ok(mailer) => lapps_provider.write_manager().await.set_mailer(mailer),
+            Err(err) => log::error!("SMTP mailer is not created: {err}"),
+        }
+    }
+
+    lapps_provider
+        .read_manager()
+        .await
+        .circuit_breaker()
+        .run(lapps_provider.clone());
+
+    lapps_provider
+        .read_manager()
+        .await
+        .permission_requests()
+        .run(lapps_provider.clone());
+
+    lapps_provider
+        .read_manager()
+        .await
+        .lapp_calls()
+        .run(lapps_provider.clone());
+
+    lapps_provider
+        .read_manager()
+        .await
+        .lapp_bus()
+        .run(lapps_provider.clone());
+
     log::info!("Load lapps");
     lapps_provider.read_manager().await.autoload_lapps().await;
 
+    if settings.lapps.watch {
+        match LappsWatcher::new(&settings.lapps.path) {
+            Ok(watcher) => watcher.run(lapps_provider.clone()),
+            Err(err) => log::error!("Lapps watcher is not started: {err}"),
+        }
+    }
+
+    let deployer = Deployer::new(lapps_provider.clone(), &settings);
+    if !settings.lapps.read_only {
+        deployer.run();
+    }
+
+    let scheduler = Scheduler::new(lapps_provider.clone());
+    scheduler.run();
+
+    let settings_editor = SettingsEditor::new(lapps_provider.clone(), &settings);
+    let users = Users::load(&settings.paths.state).map_err(|err| AppError::UsersLoadingFail(err.to_string()))?;
+
     log::info!("Create HTTP server");
     let static_dir = web_root.join(Lapp::static_dir_name());
     let laplace_uri = concatcp!("/", Lapp::main_name());
 
-    let router = Router::new()
-        .route("/", get(|| async { Redirect::to(laplace_uri) }))
+    // The management UI and API are served on the main listener and on the designated additional ones only
+    let mut lapps_router = Router::new()
         .route_service("/favicon.ico", ServeFile::new(static_dir.join("favicon.ico")))
         .nest_service(&Lapp::main_static_uri(), ServeDir::new(&static_dir))
         .fallback_service(ServeFile::new(Lapp::index_file_name()))
-        .merge(web_api::laplace::router(laplace_uri, &static_dir, &settings.lapps.path))
-        .merge(web_api::lapp::router())
-        .route_layer(middleware::from_fn_with_state(
-            (lapps_provider.clone(), laplace_access_token),
-            auth::middleware::check_access,
+        .merge(web_api::webdav::router())
+        .merge(web_api::lapp::router());
+
+    if settings.graphql.enabled {
+        lapps_router = lapps_router.merge(web_api::graphql::router(laplace_access_token));
+    }
+
+    let mut management_router = Router::new()
+        .route("/", get(|| async { Redirect::to(laplace_uri) }))
+        .merge(web_api::laplace::router(
+            laplace_uri,
+            &static_dir,
+            &settings.lapps.path,
+            laplace_url,
         ))
-        .layer(
-            ServiceBuilder::new()
-                .layer(NormalizePathLayer::trim_trailing_slash())
-                .layer(DefaultBodyLimit::max(upload_file_limit))
-                .layer(CompressionLayer::new())
-                .layer(SetResponseHeaderLayer::if_not_present(
-                    HeaderName::from_static("x-version"),
-                    HeaderValue::from_static(VERSION),
-                )),
-        )
-        .with_state(lapps_provider);
-
-    log::info!("Run HTTP server");
-    let http_server_addr = SocketAddr::new(IpAddr::from_str(&settings.http.host)?, settings.http.port);
-    if settings.ssl.enabled {
+        .merge(web_api::jobs::router(scheduler))
+        .merge(web_api::p2p::router())
+        .merge(web_api::settings::router(settings_editor))
+        .merge(auth::users::router(users.clone()))
+        .merge(web_api::tasks::router());
+
+    if !settings.lapps.git.is_empty() {
+        management_router = management_router.merge(web_api::deploy::router(deployer));
+    }
+
+    if let Some(registry_url) = &settings.lapps.registry_url {
+        match Registry::new(registry_url) {
+            Ok(registry) => management_router = management_router.merge(web_api::registry::router(registry)),
+            Err(err) => log::error!("Lapps registry is not available: {err}"),
+        }
+    }
+
+    if settings.replication.enabled {
+        let manager = lapps_provider.read_manager().await;
+        if !manager.is_read_only() {
+            replication::enable_lapps_tracking(&manager);
+        }
+        drop(manager);
+
+        management_router = management_router.merge(web_api::replication::router(settings.replication.batch_size));
+    }
+
+    let access_log = AccessLog::new(&settings.access_log)?.map(Arc::new);
+    let security_headers = Arc::new(SecurityHeadersSettings::recommended().merge(&settings.http.security_headers));
+    let lapps_domain: Option<Arc<str>> = settings.http.lapps_domain.as_deref().map(Arc::from);
+    let into_app = |router: Router<LappsProvider>| {
+        let mut router = router
+            .route_layer(middleware::from_fn_with_state(
+                (
+                    lapps_provider.clone(),
+                    laplace_access_token,
+                    users.clone(),
+                    settings.graphql.enabled,
+                ),
+                auth::middleware::check_access,
+            ))
+            .layer(
+                ServiceBuilder::new()
+                    .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                    .layer(PropagateRequestIdLayer::x_request_id())
+                    .layer(NormalizePathLayer::trim_trailing_slash())
+                    .layer(DefaultBodyLimit::max(upload_file_limit))
+                    .layer(CompressionLayer::new())
+                    .layer(SetResponseHeaderLayer::if_not_present(
+                        HeaderName::from_static("x-version"),
+                        HeaderValue::from_static(VERSION),
+                    ))
+                    .layer(middleware::from_fn_with_state(
+                        (
+                            lapps_provider.clone(),
+                            Arc::clone(&security_headers),
+                            settings.ssl.enabled,
+                        ),
+                        security_headers::add_security_headers,
+                    )),
+            );
+
+        if let Some(access_log) = &access_log {
+            router = router.layer(middleware::from_fn_with_state(
+                (Arc::clone(access_log), lapps_provider.clone()),
+                access_log::log_request,
+            ));
+        }
+        let router = router.with_state(lapps_provider.clone());
+
+        // The subdomain of the lapp is rewritten before the routing, so the layer wraps the whole router
+        middleware::from_fn_with_state(lapps_domain.clone(), vhost::route_lapp_host).layer(router)
+    };
+
+    let full_app = into_app(lapps_router.clone().merge(management_router));
+    // The main lapp is the management UI, so it is not routed as a lapp on the other listeners
+    let lapps_app = into_app(
+        lapps_router
+            .route(laplace_uri, any(|| async { StatusCode::NOT_FOUND }))
+            .route(
+                concatcp!("/", Lapp::main_name(), "/*tail"),
+                any(|| async { StatusCode::NOT_FOUND }),
+            ),
+    );
+
+    let mut listeners = vec![(http_listener, full_app.clone())];
+    for listener_settings in &settings.http.listeners {
+        let listener = bind_listener(&listener_settings.host, listener_settings.port)?;
+        let app = if listener_settings.management {
+            full_app.clone()
+        } else {
+            lapps_app.clone()
+        };
+        listeners.push((listener, app));
+    }
+
+    let tls_config = if settings.ssl.enabled {
         let (certificates, private_key) = auth::prepare_certificates(
             &settings.ssl.certificate_path,
             &settings.ssl.private_key_path,
@@ -128,18 +382,106 @@ pub async fn run(settings: Settings) -> AppResult<()> {
             .with_safe_defaults()
             .with_no_client_auth()
             .with_single_cert(certificates, private_key)?;
-
-        axum_server::bind_rustls(http_server_addr, RustlsConfig::from_config(Arc::new(config)))
-            .serve(router.into_make_service())
-            .await?
+        Some(RustlsConfig::from_config(Arc::new(config)))
     } else {
-        axum::Server::bind(&http_server_addr)
-            .serve(router.into_make_service())
-            .await?
+        None
     };
 
+    let shutdown = shutdown.shared();
+    let servers = listeners.into_iter().map(|(listener, app)| {
+        let tls_config = tls_config.clone();
+        let shutdown = shutdown.clone();
+
+        async move {
+            log::info!("Run HTTP server on {}", listener.local_addr()?);
+            match tls_config {
+                Some(tls_config) => {
+                    let handle = Handle::new();
+                    tokio::spawn({
+                        let handle = handle.clone();
+                        async move {
+                            shutdown.await;
+                            handle.graceful_shutdown(Some(SHUTDOWN_DRAIN_TIMEOUT));
+                        }
+                    });
+
+                    axum_server::from_tcp_rustls(listener, tls_config)
+                        .handle(handle)
+                        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                        .await?
+                },
+                None => {
+                    let drain_timeout = shutdown.clone().then(|()| tokio::time::sleep(SHUTDOWN_DRAIN_TIMEOUT));
+                    let server = axum::Server::from_tcp(listener)?
+                        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                        .with_graceful_shutdown(shutdown);
+
+                    tokio::select! {
+                        result = server => result?,
+                        () = drain_timeout => log::warn!("HTTP server is stopped before its connections are closed"),
+                    }
+                },
+            }
+            AppResult::Ok(())
+        }
+    });
+    future::try_join_all(servers).await?;
+
+    log::info!("Unload the lapps");
+    let unload_lapps_fut = lapps_provider.read_manager().await.unload_lapps();
+    unload_lapps_fut.await;
+
     log::info!("Shutdown the context");
     ctx.shutdown().await;
 
+    if let Some(access_log) = access_log {
+        access_log.flush();
+    }
+    if let Some(logger) = LOGGER.get() {
+        logger.flush();
+    }
+
     Ok(())
 }
+
+fn bind_listener(host: &str, port: u16) -> AppResult<TcpListener> {
+    let ip = IpAddr::from_str(host)?;
+    Ok(TcpListener::bind(SocketAddr::new(ip, port))?)
+}
+
+fn bind_http_listener(settings: &HttpSettings) -> AppResult<TcpListener> {
+    let ip = IpAddr::from_str(&settings.host)?;
+
+    match TcpListener::bind(SocketAddr::new(ip, settings.port)) {
+        Ok(listener) => Ok(listener),
+        Err(err) if err.kind() == io::ErrorKind::AddrInUse => {
+            let Some(fallback_ports) = settings.fallback_ports.clone() else {
+                return Err(err.into());
+            };
+            log::warn!(
+                "Port {} is busy, try fallback ports {}-{}",
+                settings.port,
+                fallback_ports.start(),
+                fallback_ports.end()
+            );
+
+            fallback_ports
+                .filter_map(|port| TcpListener::bind(SocketAddr::new(ip, port)).ok())
+                .next()
+                .ok_or_else(|| err.into())
+        },
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn laplace_url(settings: &Settings, port: u16, access_token: &str) -> String {
+    let access_query = (!access_token.is_empty())
+        .then(|| format!("?access_token={access_token}"))
+        .unwrap_or_default();
+
+    format!(
+        "{schema}://{host}:{port}/{access_query}",
+        schema = if settings.ssl.enabled { "https" } else { "http" },
+        host = settings.http.host,
+    )
+}
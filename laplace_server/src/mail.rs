@@ -0,0 +1,69 @@
+//! Sending of the lapp notifications by the server SMTP relay.
+//!
+//! With `enabled = true` in the `[smtp]` section the lapps with the `mail` permission send plain text mails by the
+//! `send_mail` host call. The credentials are kept in the server config, so the lapps do not embed them.
+
+use laplace_wasm::mail::Mail;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::settings::{SmtpSettings, SmtpTls};
+
+#[derive(Clone)]
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn new(settings: &SmtpSettings) -> Result<Self, String> {
+        let from = settings
+            .from
+            .parse::<Mailbox>()
+            .map_err(|err| format!("wrong sender '{}': {err}", settings.from))?;
+
+        let builder = match settings.tls {
+            SmtpTls::Tls => {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host).map_err(|err| err.to_string())?
+            },
+            SmtpTls::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host).map_err(|err| err.to_string())?
+            },
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host),
+        };
+        let mut builder = builder.port(settings.port);
+        if let Some(username) = &settings.username {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                settings.password.clone().unwrap_or_default(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+
+    pub async fn send(&self, mail: Mail) -> Result<(), String> {
+        let Mail { to, subject, body } = mail;
+        let to = to
+            .parse::<Mailbox>()
+            .map_err(|err| format!("wrong recipient '{to}': {err}"))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)
+            .map_err(|err| err.to_string())?;
+
+        self.transport
+            .send(message)
+            .await
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
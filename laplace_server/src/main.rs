@@ -1,13 +1,155 @@
+use std::fs::{self, File};
+use std::future::Future;
+use std::io::{self, BufReader};
+use std::path::Path;
+
 use clap::Parser;
-use laplace_server::settings::Settings;
+use laplace_server::dump;
+use laplace_server::error::ServerResult;
+use laplace_server::settings::{self, Settings};
 
 mod cli;
+#[cfg(unix)]
+mod daemon;
+#[cfg(windows)]
+mod win_service;
 
-#[tokio::main]
-async fn main() {
+fn main() {
     let opts: cli::Opts = cli::Opts::parse();
-    let settings = Settings::new(&opts.config).expect("Settings should be configured");
 
-    laplace_server::init_logger(&settings.log).expect("Logger should be configured");
-    laplace_server::run(settings).await.expect("Laplace running error")
+    #[cfg(windows)]
+    {
+        if opts.install_service {
+            win_service::install(opts.config.as_deref()).expect("Laplace service should be installed");
+            return;
+        }
+
+        if opts.uninstall_service {
+            win_service::uninstall().expect("Laplace service should be uninstalled");
+            return;
+        }
+
+        if opts.service {
+            win_service::run().expect("Laplace service should be started");
+            return;
+        }
+    }
+
+    if let (Some(package_path), Some(signing_key)) = (&opts.sign_package, &opts.signing_key) {
+        sign_package(package_path, signing_key).expect("Lapp package should be signed");
+        return;
+    }
+
+    let settings = load_settings(&opts);
+
+    if let Some(lapp_name) = &opts.export_db {
+        export_database(&settings, lapp_name, &opts).expect("Lapp database should be exported");
+        return;
+    }
+
+    if let Some(lapp_name) = &opts.import_db {
+        import_database(&settings, lapp_name, &opts).expect("Lapp database should be imported");
+        return;
+    }
+
+    if let Some(lapp_name) = &opts.replay_trace {
+        replay_trace(&settings, lapp_name, &opts).expect("Lapp trace should be replayed");
+        return;
+    }
+
+    // The process must be forked before the async runtime threads are started
+    #[cfg(unix)]
+    if opts.daemon {
+        daemon::daemonize(opts.pid_file.as_deref()).expect("Laplace should be daemonized");
+    }
+
+    run(settings, laplace_server::shutdown_signal())
+}
+
+fn load_settings(opts: &cli::Opts) -> Settings {
+    let mut settings = match &opts.config {
+        Some(config_path) => Settings::new(config_path),
+        None => Settings::new_or_default(settings::default_config_path()),
+    }
+    .expect("Settings should be configured");
+    if let Some(log_file) = &opts.log_file {
+        settings.log.path = Some(log_file.clone());
+    }
+
+    // The daemon has no terminal, so logs are written to the state dir by default
+    #[cfg(unix)]
+    if opts.daemon && settings.log.path.is_none() {
+        settings.log.path = Some(settings.paths.state.join("laplace.log"));
+    }
+
+    settings
+}
+
+fn export_database(settings: &Settings, lapp_name: &str, opts: &cli::Opts) -> ServerResult<()> {
+    let database_path = dump::lapp_database_path(&settings.lapps.path, lapp_name)?;
+    let connection = rusqlite::Connection::open(database_path)?;
+
+    let result = match &opts.dump_file {
+        Some(dump_file) => dump::export(&connection, io::BufWriter::new(File::create(dump_file)?))?,
+        None => dump::export(&connection, io::stdout().lock())?,
+    };
+    eprintln!("Exported {} tables, {} rows", result.tables, result.rows);
+
+    Ok(())
+}
+
+fn import_database(settings: &Settings, lapp_name: &str, opts: &cli::Opts) -> ServerResult<()> {
+    let database_path = dump::lapp_database_path(&settings.lapps.path, lapp_name)?;
+    let mut connection = rusqlite::Connection::open(database_path)?;
+
+    let result = match &opts.dump_file {
+        Some(dump_file) => dump::import(&mut connection, BufReader::new(File::open(dump_file)?))?,
+        None => dump::import(&mut connection, io::stdin().lock())?,
+    };
+    eprintln!("Imported {} tables, {} rows", result.tables, result.rows);
+
+    Ok(())
+}
+
+fn replay_trace(settings: &Settings, lapp_name: &str, opts: &cli::Opts) -> ServerResult<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let result = runtime.block_on(async {
+        laplace_server::init_logger(&settings.log).expect("Logger should be configured");
+        laplace_server::lapps::replay_trace(&settings.lapps.path, lapp_name, opts.trace_file.clone()).await
+    })?;
+    eprintln!(
+        "Replayed {} HTTP requests, {} WS messages, {} gossipsub messages, {} GraphQL requests, {} MQTT messages, {} \
+         error pages, {} scheduled jobs, {} timers, {} background jobs, {} lapp messages",
+        result.http,
+        result.ws,
+        result.gossipsub,
+        result.graphql,
+        result.mqtt,
+        result.error_page,
+        result.schedule,
+        result.timer,
+        result.job,
+        result.lapp_message
+    );
+
+    Ok(())
+}
+
+fn sign_package(package_path: &Path, signing_key: &Path) -> ServerResult<()> {
+    let signing_key = fs::read_to_string(signing_key)?;
+    let public_key = laplace_server::lapps::sign_package(package_path, &signing_key)?;
+    eprintln!("Signed package '{}'", package_path.display());
+    println!("{public_key}");
+
+    Ok(())
+}
+
+fn run(settings: Settings, shutdown: impl Future<Output = ()> + Send + 'static) {
+    let runtime = tokio::runtime::Runtime::new().expect("Tokio runtime should be created");
+    runtime.block_on(async move {
+        laplace_server::init_logger(&settings.log).expect("Logger should be configured");
+        laplace_server::run_with_shutdown(settings, shutdown)
+            .await
+            .expect("Laplace running error")
+    })
 }
@@ -0,0 +1,134 @@
+//! Bridge between the MQTT broker and the lapps.
+//!
+//! The bridge subscribes to the `network.mqtt.subscribe` topic filters of the enabled lapps with the allowed
+//! `mqtt` permission and sends the received messages to the `handle_mqtt` export of the matching lapps. The lapps
+//! publish messages by the `mqtt_publish` host call to the topics of their `network.mqtt.publish` filters.
+//! The subscriptions are made on every connection to the broker, so the lapps enabled later are subscribed
+//! after the reconnection or the server restart.
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use laplace_common::lapp::Permission;
+use laplace_wasm::mqtt;
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, Publish, QoS};
+
+use crate::lapps::{Lapp, LappsProvider};
+use crate::settings::MqttSettings;
+
+/// The capacity of the client requests channel.
+const REQUESTS_CAPACITY: usize = 64;
+
+/// The delay before the reconnection to the broker after the connection error.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+pub struct MqttBridge {
+    client: AsyncClient,
+    event_loop: EventLoop,
+}
+
+impl MqttBridge {
+    pub fn new(settings: &MqttSettings) -> Self {
+        let mut options = MqttOptions::new(&settings.client_id, &settings.host, settings.port);
+        options.set_keep_alive(Duration::from_secs(settings.keep_alive_sec));
+        if let Some(username) = &settings.username {
+            options.set_credentials(username, settings.password.as_deref().unwrap_or_default());
+        }
+
+        let (client, event_loop) = AsyncClient::new(options, REQUESTS_CAPACITY);
+        Self { client, event_loop }
+    }
+
+    pub fn client(&self) -> &AsyncClient {
+        &self.client
+    }
+
+    /// Polls the broker connection and dispatches the received messages to the lapps.
+    pub fn run(self, lapps_provider: LappsProvider) {
+        let Self { client, mut event_loop } = self;
+
+        tokio::spawn(async move {
+            loop {
+                match event_loop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        log::info!("MQTT bridge is connected");
+                        subscribe_lapps(&client, &lapps_provider).await;
+                    },
+                    Ok(Event::Incoming(Packet::Publish(publish))) => dispatch(&lapps_provider, publish).await,
+                    Ok(_) => (),
+                    Err(err) => {
+                        log::warn!("MQTT bridge connection error: {err}");
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    },
+                }
+            }
+        });
+    }
+}
+
+async fn subscribe_lapps(client: &AsyncClient, lapps_provider: &LappsProvider) {
+    let filters: BTreeSet<String> = lapps_provider
+        .read_manager()
+        .await
+        .lapp_settings_iter()
+        .filter(|(name, settings)| {
+            !Lapp::is_main(name) && settings.enabled() && settings.permissions.is_allowed(Permission::Mqtt)
+        })
+        .flat_map(|(_, settings)| settings.network().mqtt().subscribe.iter().cloned())
+        .collect();
+
+    for filter in filters {
+        if !rumqttc::valid_filter(&filter) {
+            log::warn!("Wrong MQTT topic filter \"{filter}\"");
+            continue;
+        }
+
+        if let Err(err) = client.subscribe(&filter, QoS::AtLeastOnce).await {
+            log::error!("MQTT subscribe to \"{filter}\" error: {err}");
+        }
+    }
+}
+
+/// Sends the message to every lapp with the matching subscribe filter.
+async fn dispatch(lapps_provider: &LappsProvider, publish: Publish) {
+    let message = mqtt::Message {
+        topic: publish.topic,
+        payload: publish.payload.to_vec(),
+        retain: publish.retain,
+    };
+
+    let manager = lapps_provider.read_manager().await;
+    let lapp_names: Vec<String> = manager
+        .lapp_settings_iter()
+        .filter(|(name, settings)| {
+            !Lapp::is_main(name)
+                && settings.enabled()
+                && settings.permissions.is_allowed(Permission::Mqtt)
+                && settings
+                    .network()
+                    .mqtt()
+                    .subscribe
+                    .iter()
+                    .any(|filter| rumqttc::valid_filter(filter) && rumqttc::matches(&message.topic, filter))
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    let sends: Vec<_> = lapp_names
+        .into_iter()
+        .map(|lapp_name| {
+            let send = manager.send_mqtt(lapp_name.clone(), message.clone());
+            async move { (lapp_name, send.await) }
+        })
+        .collect();
+    drop(manager);
+
+    for (lapp_name, result) in futures::future::join_all(sends).await {
+        if let Err(err) = result {
+            log::error!(
+                "Send MQTT message of topic \"{}\" to lapp \"{lapp_name}\" error: {err}",
+                message.topic
+            );
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Permissions requested by the running lapps.
+//!
+//! The lapp asks for a permission with the `request_permission` host function. The permission that is not required by
+//! the lapp yet is added to its required and pending permissions, so the admin approves or rejects it as the pending
+//! permission of the updated lapp. The running lapp polls the request status, the instance answers from the settings
+//! it was created with, and the admin decision restarts the lapp with the updated settings.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use laplace_common::lapp::Permission;
+use tokio::sync::mpsc;
+
+use crate::lapps::LappsProvider;
+
+#[derive(Clone)]
+pub struct PermissionRequests {
+    requested: Arc<Mutex<HashSet<(String, Permission)>>>,
+    requests_in: mpsc::UnboundedSender<(String, Permission)>,
+    requests: Arc<Mutex<Option<mpsc::UnboundedReceiver<(String, Permission)>>>>,
+}
+
+impl PermissionRequests {
+    pub fn new() -> Self {
+        let (requests_in, requests) = mpsc::unbounded_channel();
+
+        Self {
+            requested: Default::default(),
+            requests_in,
+            requests: Arc::new(Mutex::new(Some(requests))),
+        }
+    }
+
+    /// Queues the requested permissions for the admin approval.
+    pub fn run(&self, lapps_provider: LappsProvider) {
+        let Some(mut requests) = self
+            .requests
+            .lock()
+            .expect("Permission requests lock should not be poisoned")
+            .take()
+        else {
+            log::warn!("Permission requests are already run");
+            return;
+        };
+
+        tokio::spawn(async move {
+            while let Some((lapp_name, permission)) = requests.recv().await {
+                let result = lapps_provider
+                    .write_manager()
+                    .await
+                    .request_permission(&lapp_name, permission);
+                if let Err(err) = result {
+                    log::error!(
+                        "Request permission '{}' of lapp '{lapp_name}' error: {err}",
+                        permission.as_str()
+                    );
+                }
+            }
+        });
+    }
+
+    /// Sends the request of the lapp permission once, the repeated requests are polls of the status.
+    pub fn request(&self, lapp_name: &str, permission: Permission) {
+        if lock_requested(&self.requested).insert((lapp_name.into(), permission)) {
+            self.requests_in.send((lapp_name.into(), permission)).ok();
+        }
+    }
+}
+
+impl Default for PermissionRequests {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn lock_requested(requested: &Mutex<HashSet<(String, Permission)>>) -> MutexGuard<'_, HashSet<(String, Permission)>> {
+    requested
+        .lock()
+        .expect("Permission requests lock should not be poisoned")
+}
@@ -0,0 +1,146 @@
+//! Per-route rate limits of the lapps.
+//!
+//! The rules of the `rate_limits` lapp setting are checked before the request reaches the lapp server module.
+//! Every rule is a token bucket of the rule burst size refilled with the rule rate. The `per client` rules keep the
+//! bucket per the client IP address, so behind a reverse proxy all clients share the proxy bucket.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use laplace_common::rate_limit::{RateLimitError, RateLimitRule};
+
+/// The number of the buckets after which the full ones are dropped.
+const MAX_BUCKETS: usize = 10_000;
+
+type BucketKey = (String, String, Option<IpAddr>);
+
+struct Bucket {
+    tokens: f64,
+    capacity: f64,
+    tokens_per_sec: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(rule: &RateLimitRule, now: Instant) -> Self {
+        let capacity = f64::from(rule.capacity());
+        Self {
+            tokens: capacity,
+            capacity,
+            tokens_per_sec: f64::from(rule.requests) / rule.period.as_secs_f64(),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.tokens_per_sec).min(self.capacity);
+        self.updated = now;
+    }
+
+    fn wait_time(&self) -> Duration {
+        Duration::from_secs_f64(((1.0 - self.tokens) / self.tokens_per_sec).max(0.0))
+    }
+}
+
+/// The exceeded limit with the time until the next allowed request.
+#[derive(Debug)]
+pub struct RateLimitExceeded {
+    pub route: String,
+    pub retry_after: Duration,
+}
+
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<HashMap<BucketKey, Bucket>>>,
+    rules: Arc<Mutex<HashMap<String, Result<RateLimitRule, RateLimitError>>>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the request from every matching rule of the lapp. If any of the limits is exceeded, nothing is taken.
+    pub fn check(
+        &self,
+        lapp_name: &str,
+        rules: &[String],
+        method: &str,
+        path: &str,
+        client: IpAddr,
+    ) -> Result<(), RateLimitExceeded> {
+        let matched: Vec<_> = rules
+            .iter()
+            .filter_map(|rule_str| {
+                self.rule(lapp_name, rule_str)
+                    .filter(|rule| rule.matches(method, path))
+                    .map(|rule| (rule_str, rule))
+            })
+            .collect();
+        if matched.is_empty() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut buckets = self
+            .buckets
+            .lock()
+            .expect("Rate limit buckets lock should not be poisoned");
+        if buckets.len() > MAX_BUCKETS {
+            buckets.retain(|_, bucket| {
+                bucket.refill(now);
+                bucket.tokens < bucket.capacity
+            });
+        }
+
+        let keys: Vec<_> = matched
+            .iter()
+            .map(|(rule_str, rule)| {
+                let key = (
+                    lapp_name.to_string(),
+                    rule_str.to_string(),
+                    rule.per_client.then_some(client),
+                );
+                let bucket = buckets.entry(key.clone()).or_insert_with(|| Bucket::new(rule, now));
+                bucket.refill(now);
+                key
+            })
+            .collect();
+
+        let exceeded = matched
+            .iter()
+            .zip(&keys)
+            .filter(|(_, key)| buckets[*key].tokens < 1.0)
+            .map(|((_, rule), key)| RateLimitExceeded {
+                route: rule.route(),
+                retry_after: buckets[key].wait_time(),
+            })
+            .max_by_key(|exceeded| exceeded.retry_after);
+        if let Some(exceeded) = exceeded {
+            return Err(exceeded);
+        }
+
+        for key in &keys {
+            if let Some(bucket) = buckets.get_mut(key) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses the rule once, the wrong rules are logged and skipped.
+    fn rule(&self, lapp_name: &str, rule_str: &str) -> Option<RateLimitRule> {
+        let mut rules = self.rules.lock().expect("Rate limit rules lock should not be poisoned");
+        let rule = rules.entry(rule_str.to_string()).or_insert_with(|| {
+            let rule = rule_str.parse();
+            if let Err(err) = &rule {
+                log::error!("{err} of lapp '{lapp_name}'");
+            }
+            rule
+        });
+        rule.as_ref().ok().cloned()
+    }
+}
@@ -0,0 +1,99 @@
+//! Client of the remote lapps registry.
+//!
+//! The registry is the JSON index served over HTTPS at `lapps.registry_url`. It lists the available lapps with
+//! their versions and package URLs, the URLs may be relative to the index. The downloaded package is checked by
+//! the SHA-256 from the index, then it is installed as the new lapp or staged as the upgrade of the installed one.
+
+use std::io::Write;
+
+use laplace_common::api::{RegistryIndex, RegistryLapp};
+use reqwest::{Client, Url};
+use ring::digest;
+use tempfile::NamedTempFile;
+
+use crate::error::{ServerError, ServerResult};
+
+#[derive(Clone)]
+pub struct Registry {
+    index_url: Url,
+    http_client: Client,
+}
+
+impl Registry {
+    pub fn new(index_url: &str) -> ServerResult<Self> {
+        let index_url =
+            Url::parse(index_url).map_err(|err| ServerError::RegistryError(format!("wrong index URL: {err}")))?;
+        check_https(&index_url)?;
+
+        Ok(Self {
+            index_url,
+            http_client: Client::new(),
+        })
+    }
+
+    pub async fn index(&self) -> ServerResult<RegistryIndex> {
+        let body = self.fetch(self.index_url.clone()).await?;
+        serde_json::from_slice(&body).map_err(|err| ServerError::RegistryError(format!("wrong index: {err}")))
+    }
+
+    pub async fn lapp(&self, lapp_name: &str) -> ServerResult<RegistryLapp> {
+        self.index()
+            .await?
+            .lapps
+            .into_iter()
+            .find(|lapp| lapp.name == lapp_name)
+            .ok_or_else(|| ServerError::RegistryLappNotFound(lapp_name.into()))
+    }
+
+    /// Downloads the package of the given version of the lapp or of the latest one.
+    pub async fn download(&self, lapp_name: &str, version: Option<&str>) -> ServerResult<NamedTempFile> {
+        let lapp = self.lapp(lapp_name).await?;
+        let registry_version = lapp.version(version).ok_or_else(|| {
+            ServerError::RegistryVersionNotFound(lapp_name.into(), version.unwrap_or("latest").into())
+        })?;
+
+        let url = self.index_url.join(&registry_version.url).map_err(|err| {
+            ServerError::RegistryError(format!("wrong package URL '{}': {err}", registry_version.url))
+        })?;
+        check_https(&url)?;
+        let package_bytes = self.fetch(url).await?;
+
+        if let Some(sha256) = &registry_version.sha256 {
+            let hash = hex::encode(digest::digest(&digest::SHA256, &package_bytes));
+            if !hash.eq_ignore_ascii_case(sha256) {
+                return Err(ServerError::RegistryError(format!(
+                    "SHA-256 of package of lapp '{lapp_name}' version '{}' does not match the index",
+                    registry_version.version
+                )));
+            }
+        }
+
+        let mut package = NamedTempFile::new()?;
+        package.write_all(&package_bytes)?;
+        Ok(package)
+    }
+
+    async fn fetch(&self, url: Url) -> ServerResult<Vec<u8>> {
+        let response = self
+            .http_client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| ServerError::RegistryError(err.to_string()))?;
+
+        let body = response
+            .bytes()
+            .await
+            .map_err(|err| ServerError::RegistryError(err.to_string()))?;
+        Ok(body.to_vec())
+    }
+}
+
+fn check_https(url: &Url) -> ServerResult<()> {
+    if url.scheme() == "https" {
+        Ok(())
+    } else {
+        Err(ServerError::RegistryError(format!("URL '{url}' is not HTTPS")))
+    }
+}
@@ -0,0 +1,463 @@
+//! CouchDB-style replication of lapps data.
+//!
+//! Every change of a lapp database row is recorded by triggers into the changes table as a document with
+//! the table name, the row id and the revision. The changes feed is exposed to the other Laplace instances,
+//! which apply documents with the greater revisions. Tables without rowid and blob values are not replicated.
+//!
+//! The tracking is enabled for the lapps databases at the server start and on every pull, push and applying of the
+//! documents, so the tables created later are tracked from then on. The changes feed only reads the database.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use reqwest::{header, Client};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::{ServerError, ServerResult};
+use crate::lapps::{Lapp, LappsManager, Permission};
+
+const CHANGES_TABLE: &str = "_laplace_changes";
+const CHECKPOINTS_TABLE: &str = "_laplace_checkpoints";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Change {
+    pub seq: u64,
+    pub table: String,
+    pub id: i64,
+    pub rev: u64,
+
+    /// The row values, `None` for the deleted row.
+    pub doc: Option<Map<String, Value>>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Changes {
+    pub results: Vec<Change>,
+    pub last_seq: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BulkDocs {
+    pub docs: Vec<Change>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BulkDocsResult {
+    pub applied: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Peer {
+    /// The base URL of the other Laplace instance, e.g. `https://example.com:8080`.
+    pub url: String,
+    pub access_token: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ReplicationResult {
+    pub docs: usize,
+    pub last_seq: u64,
+}
+
+pub fn open(database_path: impl AsRef<Path>) -> ServerResult<Connection> {
+    let connection = Connection::open(database_path)?;
+    enable_tracking(&connection)?;
+    Ok(connection)
+}
+
+/// Enables the tracking of the existing databases of the lapps with the database permission.
+pub fn enable_lapps_tracking(manager: &LappsManager) {
+    for (lapp_name, lapp_settings) in manager.lapp_settings_iter() {
+        if Lapp::is_main(lapp_name) || !lapp_settings.permissions.is_allowed(Permission::Database) {
+            continue;
+        }
+
+        let result = manager.lapp_database_path(lapp_name).and_then(|database_path| {
+            if database_path.exists() {
+                open(database_path)?;
+            }
+            Ok(())
+        });
+        if let Err(err) = result {
+            log::error!("Cannot enable replication tracking of lapp '{lapp_name}': {err}");
+        }
+    }
+}
+
+/// Opens the database for reading the changes feed, the tracking is not enabled.
+pub fn open_read_only(database_path: impl AsRef<Path>) -> ServerResult<Connection> {
+    let connection = Connection::open_with_flags(
+        database_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    Ok(connection)
+}
+
+/// Creates the changes table and the tracking triggers for every user table. Rows that were written before
+/// the tracking is enabled are recorded with the first revision.
+pub fn enable_tracking(connection: &Connection) -> ServerResult<()> {
+    connection.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS {CHANGES_TABLE} (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            tbl TEXT NOT NULL,
+            id INTEGER NOT NULL,
+            rev INTEGER NOT NULL,
+            doc TEXT
+        );
+        CREATE INDEX IF NOT EXISTS {CHANGES_TABLE}_doc ON {CHANGES_TABLE} (tbl, id);
+        CREATE TABLE IF NOT EXISTS {CHECKPOINTS_TABLE} (
+            peer TEXT PRIMARY KEY,
+            pull_seq INTEGER NOT NULL DEFAULT 0,
+            push_seq INTEGER NOT NULL DEFAULT 0
+        );"
+    ))?;
+
+    for table in user_tables(connection)? {
+        let columns = table_columns(connection, &table)?;
+        let new_doc = json_object_sql("NEW", &columns);
+        let table_name = quote_literal(&table);
+        let next_rev = format!(
+            "(SELECT COALESCE(MAX(rev), 0) + 1 FROM {CHANGES_TABLE} WHERE tbl = {table_name} AND id = {{row}}.rowid)"
+        );
+        let trigger = |event: &str| quote_identifier(&format!("_laplace_{table}_{event}"));
+
+        connection.execute_batch(&format!(
+            r#"DROP TRIGGER IF EXISTS {insert_trigger};
+            DROP TRIGGER IF EXISTS {update_trigger};
+            DROP TRIGGER IF EXISTS {delete_trigger};
+            CREATE TRIGGER {insert_trigger} AFTER INSERT ON {table_ident} BEGIN
+                INSERT INTO {CHANGES_TABLE} (tbl, id, rev, doc) VALUES ({table_name}, NEW.rowid, {insert_rev}, {new_doc});
+            END;
+            CREATE TRIGGER {update_trigger} AFTER UPDATE ON {table_ident} BEGIN
+                INSERT INTO {CHANGES_TABLE} (tbl, id, rev, doc) VALUES ({table_name}, NEW.rowid, {insert_rev}, {new_doc});
+            END;
+            CREATE TRIGGER {delete_trigger} AFTER DELETE ON {table_ident} BEGIN
+                INSERT INTO {CHANGES_TABLE} (tbl, id, rev, doc) VALUES ({table_name}, OLD.rowid, {delete_rev}, NULL);
+            END;
+            INSERT INTO {CHANGES_TABLE} (tbl, id, rev, doc)
+                SELECT {table_name}, rowid, 1, {row_doc} FROM {table_ident}
+                WHERE rowid NOT IN (SELECT id FROM {CHANGES_TABLE} WHERE tbl = {table_name});"#,
+            insert_trigger = trigger("insert"),
+            update_trigger = trigger("update"),
+            delete_trigger = trigger("delete"),
+            table_ident = quote_identifier(&table),
+            insert_rev = next_rev.replace("{row}", "NEW"),
+            delete_rev = next_rev.replace("{row}", "OLD"),
+            row_doc = json_object_sql(&quote_identifier(&table), &columns),
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Returns the latest changes of documents after the `since` sequence number, no changes if the tracking is not
+/// enabled yet.
+pub fn changes(connection: &Connection, since: u64, limit: usize) -> ServerResult<Changes> {
+    let is_tracked = connection
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [CHANGES_TABLE],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if !is_tracked {
+        return Ok(Changes {
+            results: Vec::new(),
+            last_seq: since,
+        });
+    }
+
+    let mut stmt = connection.prepare(&format!(
+        "SELECT seq, tbl, id, rev, doc FROM {CHANGES_TABLE}
+        WHERE seq > ?1 AND seq IN (SELECT MAX(seq) FROM {CHANGES_TABLE} GROUP BY tbl, id)
+        ORDER BY seq LIMIT ?2"
+    ))?;
+
+    let results = stmt
+        .query_map(params![since, limit], |row| {
+            Ok((
+                row.get::<_, u64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, u64>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })?
+        .map(|row| {
+            let (seq, table, id, rev, doc) = row?;
+            let doc = doc.map(|doc| serde_json::from_str(&doc)).transpose()?;
+            Ok(Change {
+                seq,
+                table,
+                id,
+                rev,
+                doc,
+            })
+        })
+        .collect::<ServerResult<Vec<_>>>()?;
+
+    let last_seq = results.last().map(|change| change.seq).unwrap_or(since);
+    Ok(Changes { results, last_seq })
+}
+
+/// Applies the documents which have greater revisions than the local ones, returns the number of applied
+/// documents. The documents with the columns missing in the local table are rejected.
+pub fn apply(connection: &mut Connection, docs: &[Change]) -> ServerResult<usize> {
+    let mut tables = HashMap::new();
+    for table in user_tables(connection)? {
+        let columns = table_columns(connection, &table)?;
+        tables.insert(table, columns);
+    }
+
+    let transaction = connection.transaction()?;
+    let mut applied = 0;
+
+    for change in docs {
+        let Some(columns) = tables.get(&change.table) else {
+            log::warn!("Skip replication of unknown table '{}'", change.table);
+            continue;
+        };
+        if let Some(doc) = &change.doc {
+            if let Some(column) = doc.keys().find(|&column| !columns.contains(column)) {
+                return Err(ServerError::ReplicationError(format!(
+                    "unknown column '{column}' of table '{}'",
+                    change.table
+                )));
+            }
+        }
+
+        let local_rev: u64 = transaction.query_row(
+            &format!("SELECT COALESCE(MAX(rev), 0) FROM {CHANGES_TABLE} WHERE tbl = ?1 AND id = ?2"),
+            params![change.table, change.id],
+            |row| row.get(0),
+        )?;
+        if change.rev <= local_rev {
+            continue;
+        }
+
+        let last_seq: u64 = transaction.query_row(
+            &format!("SELECT COALESCE(MAX(seq), 0) FROM {CHANGES_TABLE}"),
+            [],
+            |row| row.get(0),
+        )?;
+
+        match &change.doc {
+            Some(doc) => {
+                let columns: Vec<_> = doc.keys().map(|column| quote_identifier(column)).collect();
+                let placeholders: Vec<_> = (2..=doc.len() + 1).map(|idx| format!("?{idx}")).collect();
+                let values = [SqlValue::Integer(change.id)]
+                    .into_iter()
+                    .chain(doc.values().map(to_sql_value));
+
+                transaction.execute(
+                    &format!(
+                        "INSERT OR REPLACE INTO {table} (rowid, {columns}) VALUES (?1, {placeholders})",
+                        table = quote_identifier(&change.table),
+                        columns = columns.join(", "),
+                        placeholders = placeholders.join(", "),
+                    ),
+                    params_from_iter(values),
+                )?;
+            },
+            None => {
+                transaction.execute(
+                    &format!("DELETE FROM {} WHERE rowid = ?1", quote_identifier(&change.table)),
+                    [change.id],
+                )?;
+            },
+        }
+
+        // Keep the revision of the applied document, so it will not be sent back as a new change
+        let updated = transaction.execute(
+            &format!("UPDATE {CHANGES_TABLE} SET rev = ?1 WHERE tbl = ?2 AND id = ?3 AND seq > ?4"),
+            params![change.rev, change.table, change.id, last_seq],
+        )?;
+        if updated == 0 {
+            transaction.execute(
+                &format!("INSERT INTO {CHANGES_TABLE} (tbl, id, rev, doc) VALUES (?1, ?2, ?3, ?4)"),
+                params![
+                    change.table,
+                    change.id,
+                    change.rev,
+                    change.doc.as_ref().map(serde_json::to_string).transpose()?
+                ],
+            )?;
+        }
+
+        applied += 1;
+    }
+
+    transaction.commit()?;
+    Ok(applied)
+}
+
+/// Pulls the lapp changes from the peer since the last checkpoint.
+pub async fn pull(
+    client: &Client,
+    database_path: impl AsRef<Path>,
+    lapp_name: &str,
+    peer: &Peer,
+    batch_size: usize,
+) -> ServerResult<ReplicationResult> {
+    let mut connection = open(database_path)?;
+    let mut result = ReplicationResult {
+        docs: 0,
+        last_seq: checkpoint(&connection, &peer.url)?.0,
+    };
+
+    loop {
+        let url = format!(
+            "{}?since={}&limit={batch_size}",
+            replication_url(peer, lapp_name, "changes"),
+            result.last_seq
+        );
+        let changes: Changes = send(client.get(url), peer).await?;
+        if changes.results.is_empty() {
+            break;
+        }
+
+        result.docs += apply(&mut connection, &changes.results)?;
+        result.last_seq = changes.last_seq;
+        connection.execute(
+            &format!(
+                "INSERT INTO {CHECKPOINTS_TABLE} (peer, pull_seq) VALUES (?1, ?2)
+                ON CONFLICT (peer) DO UPDATE SET pull_seq = excluded.pull_seq"
+            ),
+            params![peer.url, result.last_seq],
+        )?;
+    }
+
+    Ok(result)
+}
+
+/// Pushes the lapp changes to the peer since the last checkpoint.
+pub async fn push(
+    client: &Client,
+    database_path: impl AsRef<Path>,
+    lapp_name: &str,
+    peer: &Peer,
+    batch_size: usize,
+) -> ServerResult<ReplicationResult> {
+    let connection = open(database_path)?;
+    let mut result = ReplicationResult {
+        docs: 0,
+        last_seq: checkpoint(&connection, &peer.url)?.1,
+    };
+
+    loop {
+        let Changes { results, last_seq } = changes(&connection, result.last_seq, batch_size)?;
+        if results.is_empty() {
+            break;
+        }
+
+        let url = replication_url(peer, lapp_name, "bulk_docs");
+        let body = serde_json::to_vec(&BulkDocs { docs: results })?;
+        let bulk_result: BulkDocsResult = send(
+            client
+                .post(url)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(body),
+            peer,
+        )
+        .await?;
+
+        result.docs += bulk_result.applied;
+        result.last_seq = last_seq;
+        connection.execute(
+            &format!(
+                "INSERT INTO {CHECKPOINTS_TABLE} (peer, push_seq) VALUES (?1, ?2)
+                ON CONFLICT (peer) DO UPDATE SET push_seq = excluded.push_seq"
+            ),
+            params![peer.url, result.last_seq],
+        )?;
+    }
+
+    Ok(result)
+}
+
+fn checkpoint(connection: &Connection, peer_url: &str) -> ServerResult<(u64, u64)> {
+    let checkpoint = connection
+        .query_row(
+            &format!("SELECT pull_seq, push_seq FROM {CHECKPOINTS_TABLE} WHERE peer = ?1"),
+            [peer_url],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    Ok(checkpoint.unwrap_or_default())
+}
+
+fn replication_url(peer: &Peer, lapp_name: &str, tail: &str) -> String {
+    format!(
+        "{}{}",
+        peer.url.trim_end_matches('/'),
+        Lapp::main_uri(format!("replication/{lapp_name}/{tail}"))
+    )
+}
+
+async fn send<T: for<'de> Deserialize<'de>>(request: reqwest::RequestBuilder, peer: &Peer) -> ServerResult<T> {
+    let request = match &peer.access_token {
+        Some(access_token) => request.header(header::COOKIE, format!("access_token={access_token}")),
+        None => request,
+    };
+
+    let response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| ServerError::ReplicationError(format!("request to peer {} failed: {err}", peer.url)))?;
+    let body = response
+        .bytes()
+        .await
+        .map_err(|err| ServerError::ReplicationError(format!("read response of peer {} failed: {err}", peer.url)))?;
+
+    serde_json::from_slice(&body).map_err(Into::into)
+}
+
+fn user_tables(connection: &Connection) -> ServerResult<Vec<String>> {
+    let mut stmt = connection.prepare(
+        "SELECT name FROM sqlite_master
+        WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name NOT LIKE '\\_laplace\\_%' ESCAPE '\\'
+        AND sql NOT LIKE '%WITHOUT ROWID%'",
+    )?;
+    let tables = stmt.query_map([], |row| row.get(0))?.collect::<Result<_, _>>()?;
+    Ok(tables)
+}
+
+fn table_columns(connection: &Connection, table: &str) -> ServerResult<Vec<String>> {
+    let mut stmt = connection.prepare(&format!("PRAGMA table_info({})", quote_identifier(table)))?;
+    let columns = stmt.query_map([], |row| row.get(1))?.collect::<Result<_, _>>()?;
+    Ok(columns)
+}
+
+fn json_object_sql(row: &str, columns: &[String]) -> String {
+    let fields: Vec<_> = columns
+        .iter()
+        .map(|column| format!("{}, {row}.{}", quote_literal(column), quote_identifier(column)))
+        .collect();
+    format!("json_object({})", fields.join(", "))
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!(r#""{}""#, name.replace('"', r#""""#))
+}
+
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn to_sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(value) => SqlValue::Integer(*value as _),
+        Value::Number(number) => match number.as_i64() {
+            Some(number) => SqlValue::Integer(number),
+            None => SqlValue::Real(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(value) => SqlValue::Text(value.clone()),
+        value => SqlValue::Text(value.to_string()),
+    }
+}
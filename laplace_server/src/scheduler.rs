@@ -0,0 +1,251 @@
+//! Scheduler of the lapp jobs.
+//!
+//! The jobs are declared in the lapp settings with cron schedules in UTC. On each run the scheduler sends
+//! the POST request to the job path of the lapp, or calls the `on_schedule` export of the lapp server module
+//! for the job without the path, and keeps the result to show it in the admin UI.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Datelike, Duration, DurationRound, Timelike, Utc};
+use laplace_common::api::{JobInfo, JobResult};
+use laplace_common::cron::{CronError, CronSchedule};
+use laplace_common::lapp::JobSettings;
+use laplace_wasm::http::{self, HeaderValue, Method};
+use laplace_wasm::schedule::ScheduleResult;
+use tokio::sync::Mutex;
+
+use crate::error::{ServerError, ServerResult};
+use crate::lapps::{Lapp, LappsProvider};
+
+pub const JOB_HEADER: &str = "x-laplace-job";
+
+/// The size of the response body kept as the job result message.
+const MAX_RESULT_MESSAGE_LEN: usize = 1024;
+
+type JobKey = (String, String);
+
+struct JobState {
+    settings: JobSettings,
+    schedule: Result<CronSchedule, CronError>,
+    last_run: Option<DateTime<Utc>>,
+    last_result: Option<JobResult>,
+}
+
+#[derive(Clone)]
+pub struct Scheduler {
+    lapps_provider: LappsProvider,
+    jobs: Arc<Mutex<HashMap<JobKey, JobState>>>,
+}
+
+impl Scheduler {
+    pub fn new(lapps_provider: LappsProvider) -> Self {
+        Self {
+            lapps_provider,
+            jobs: Default::default(),
+        }
+    }
+
+    /// Checks the job schedules at the start of every minute and runs the due jobs.
+    pub fn run(&self) {
+        let scheduler = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let now = Utc::now();
+                let next_minute = start_of_minute(now) + Duration::minutes(1);
+                tokio::time::sleep((next_minute - now).to_std().unwrap_or_default()).await;
+
+                scheduler.sync_jobs().await;
+                for (lapp_name, job_name) in scheduler.due_jobs(next_minute).await {
+                    let scheduler = scheduler.clone();
+                    tokio::spawn(async move { scheduler.run_job(&lapp_name, &job_name).await });
+                }
+            }
+        });
+    }
+
+    pub async fn jobs(&self) -> Vec<JobInfo> {
+        self.sync_jobs().await;
+
+        let now = Utc::now();
+        let jobs = self.jobs.lock().await;
+        let mut infos: Vec<_> = jobs
+            .iter()
+            .map(|((lapp_name, _), job)| JobInfo {
+                lapp_name: lapp_name.clone(),
+                name: job.settings.name.clone(),
+                schedule: job.settings.schedule.clone(),
+                path: job.settings.path.clone(),
+                next_run: job
+                    .schedule
+                    .as_ref()
+                    .ok()
+                    .and_then(|schedule| next_run(schedule, now))
+                    .map(|time| time.to_rfc3339()),
+                last_run: job.last_run.map(|time| time.to_rfc3339()),
+                last_result: job.last_result.clone().or_else(|| {
+                    job.schedule.as_ref().err().map(|err| JobResult {
+                        success: false,
+                        message: err.to_string(),
+                    })
+                }),
+            })
+            .collect();
+        infos.sort_unstable_by(|a, b| (&a.lapp_name, &a.name).cmp(&(&b.lapp_name, &b.name)));
+
+        infos
+    }
+
+    pub async fn run_job(&self, lapp_name: &str, job_name: &str) -> ServerResult<JobResult> {
+        let key = (lapp_name.to_string(), job_name.to_string());
+        let path = self
+            .jobs
+            .lock()
+            .await
+            .get(&key)
+            .map(|job| job.settings.path.clone())
+            .ok_or_else(|| ServerError::JobNotFound(lapp_name.into(), job_name.into()))?;
+
+        log::info!("Run job '{job_name}' of lapp '{lapp_name}'");
+        let started_at = Utc::now();
+        let result = match path {
+            Some(path) => self
+                .request_lapp(lapp_name, job_name, &path)
+                .await
+                .map(|response| JobResult {
+                    success: response.status.is_success(),
+                    message: response_message(&response),
+                }),
+            None => self.call_lapp(lapp_name, job_name).await.map(|result| JobResult {
+                success: result.is_ok(),
+                message: result.err().unwrap_or_default(),
+            }),
+        };
+        let result = result.unwrap_or_else(|err| {
+            log::error!("Job '{job_name}' of lapp '{lapp_name}' error: {err}");
+            JobResult {
+                success: false,
+                message: err.to_string(),
+            }
+        });
+
+        if let Some(job) = self.jobs.lock().await.get_mut(&key) {
+            job.last_run = Some(started_at);
+            job.last_result = Some(result.clone());
+        }
+        Ok(result)
+    }
+
+    async fn request_lapp(&self, lapp_name: &str, job_name: &str, path: &str) -> ServerResult<http::Response> {
+        let mut request = http::Request::new(Vec::new());
+        request.method = Method::POST;
+        request.uri = format!("/{lapp_name}/{}", path.trim_start_matches('/'))
+            .parse()
+            .map_err(|err| ServerError::JobFail(format!("wrong job path '{path}': {err}")))?;
+        if let Ok(job_name) = HeaderValue::from_str(job_name) {
+            request.headers.insert(JOB_HEADER, job_name);
+        }
+
+        let manager = self.lapps_provider.read_manager().await;
+        manager.check_enabled_and_allow_permissions(lapp_name, &[])?;
+        let process_http_fut = manager.process_http(lapp_name, request);
+        drop(manager);
+
+        process_http_fut.await
+    }
+
+    async fn call_lapp(&self, lapp_name: &str, job_name: &str) -> ServerResult<ScheduleResult> {
+        let manager = self.lapps_provider.read_manager().await;
+        manager.check_enabled_and_allow_permissions(lapp_name, &[])?;
+        let run_schedule_fut = manager.run_schedule(lapp_name, job_name);
+        drop(manager);
+
+        run_schedule_fut
+            .await?
+            .ok_or_else(|| ServerError::JobFail("the lapp does not export 'on_schedule'".into()))
+    }
+
+    /// Updates the jobs from the settings of the enabled lapps, keeping the state of the existing jobs.
+    async fn sync_jobs(&self) {
+        let manager = self.lapps_provider.read_manager().await;
+        let mut jobs = self.jobs.lock().await;
+        let mut actual_keys = Vec::new();
+
+        for (lapp_name, lapp_settings) in manager.lapp_settings_iter() {
+            if Lapp::is_main(lapp_name) || !lapp_settings.enabled() {
+                continue;
+            }
+
+            for settings in lapp_settings.jobs() {
+                let key = (lapp_name.clone(), settings.name.clone());
+                let schedule = settings.schedule.parse();
+                match jobs.get_mut(&key) {
+                    Some(job) => {
+                        job.settings = settings.clone();
+                        job.schedule = schedule;
+                    },
+                    None => {
+                        jobs.insert(key.clone(), JobState {
+                            settings: settings.clone(),
+                            schedule,
+                            last_run: None,
+                            last_result: None,
+                        });
+                    },
+                }
+                actual_keys.push(key);
+            }
+        }
+
+        jobs.retain(|key, _| actual_keys.contains(key));
+    }
+
+    async fn due_jobs(&self, time: DateTime<Utc>) -> Vec<JobKey> {
+        self.jobs
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, job)| matches!(&job.schedule, Ok(schedule) if is_scheduled(schedule, time)))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+}
+
+fn start_of_minute(time: DateTime<Utc>) -> DateTime<Utc> {
+    time.duration_trunc(Duration::minutes(1)).unwrap_or(time)
+}
+
+fn is_scheduled(schedule: &CronSchedule, time: DateTime<Utc>) -> bool {
+    schedule.matches(
+        time.minute(),
+        time.hour(),
+        time.day(),
+        time.month(),
+        time.weekday().num_days_from_sunday(),
+    )
+}
+
+/// Finds the next run time within a year after the `time`.
+fn next_run(schedule: &CronSchedule, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let mut next = start_of_minute(time) + Duration::minutes(1);
+    let limit = next + Duration::days(366);
+
+    while next < limit {
+        if is_scheduled(schedule, next) {
+            return Some(next);
+        }
+        next += Duration::minutes(1);
+    }
+    None
+}
+
+fn response_message(response: &http::Response) -> String {
+    let body = String::from_utf8_lossy(&response.body);
+    let message = match body.char_indices().nth(MAX_RESULT_MESSAGE_LEN) {
+        Some((idx, _)) => &body[..idx],
+        None => &body,
+    };
+
+    format!("{}: {message}", response.status)
+}
@@ -0,0 +1,50 @@
+//! The protective headers of the responses.
+//!
+//! The recommended headers are overridden by the `http.security_headers` server settings, which are overridden by the
+//! `security_headers` settings of the lapp for the responses of its routes. The headers already set by the handler,
+//! e.g. by the proxied upstream, are kept.
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::{HeaderName, HeaderValue, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use laplace_common::lapp::settings::SecurityHeadersSettings;
+
+use crate::lapps::LappsProvider;
+
+pub async fn add_security_headers<B>(
+    State((lapps_provider, server_headers, is_tls)): State<(LappsProvider, Arc<SecurityHeadersSettings>, bool)>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let lapp_headers = match request.uri().path().split('/').find(|chunk| !chunk.is_empty()) {
+        Some(lapp_name) => {
+            let manager = lapps_provider.read_manager().await;
+            manager
+                .lapp_settings(lapp_name)
+                .ok()
+                .and_then(|settings| settings.security_headers())
+                .map(|lapp_headers| server_headers.merge(lapp_headers))
+        },
+        None => None,
+    };
+
+    let mut response = next.run(request).await;
+
+    let headers = lapp_headers.as_ref().unwrap_or(&server_headers);
+    for (name, value) in headers.headers(is_tls) {
+        let name = HeaderName::from_static(name);
+        if response.headers().contains_key(&name) {
+            continue;
+        }
+        match HeaderValue::from_str(value) {
+            Ok(value) => {
+                response.headers_mut().insert(name, value);
+            },
+            Err(err) => log::warn!("Invalid value of security header '{name}': {err}"),
+        }
+    }
+    response
+}
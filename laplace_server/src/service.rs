@@ -4,6 +4,7 @@ pub use self::gossipsub::GossipsubService;
 pub use self::lapp::LappService;
 pub use self::websocket::WebSocketService;
 
+pub mod bus;
 pub mod gossipsub;
 pub mod lapp;
 pub mod websocket;
@@ -12,12 +13,16 @@ pub mod websocket;
 pub enum Addr {
     #[display(fmt = "Lapp({})", _0)]
     Lapp(String),
+
+    /// The WebSocket session of the lapp client.
+    #[display(fmt = "WebSocket({}, {})", _0, _1)]
+    WebSocket(String, u64),
 }
 
 impl Addr {
     pub fn as_lapp_name(&self) -> &str {
         match self {
-            Addr::Lapp(name) => name.as_str(),
+            Addr::Lapp(name) | Addr::WebSocket(name, _) => name.as_str(),
         }
     }
 
@@ -29,7 +34,7 @@ impl Addr {
 impl From<Addr> for String {
     fn from(addr: Addr) -> Self {
         match addr {
-            Addr::Lapp(value) => value,
+            Addr::Lapp(value) | Addr::WebSocket(value, _) => value,
         }
     }
 }
@@ -0,0 +1,123 @@
+//! The message bus of the lapps.
+//!
+//! Unlike the lapp call, the message has no response: the `lapp_send` host call returns when the message is queued
+//! to the service of the target lapp, which passes it to the `on_lapp_message` export. The sending lapp needs the
+//! `lapps_outgoing` permission and the target lapp in its `lapp_requests` settings, the target lapp needs the
+//! `lapps_incoming` permission. The sender name is set by the server, so the target lapp can trust it.
+
+use std::sync::{Arc, Mutex};
+
+use laplace_common::lapp::Permission;
+use laplace_wasm::lapps::{CallError, CallResult};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::lapps::{LappsManager, LappsProvider};
+
+pub struct BusMessage {
+    pub sender: String,
+    pub lapp_name: String,
+    pub payload: Vec<u8>,
+    pub result_out: oneshot::Sender<CallResult<()>>,
+}
+
+#[derive(Clone)]
+pub struct LappBus {
+    messages_in: mpsc::UnboundedSender<BusMessage>,
+    messages: Arc<Mutex<Option<mpsc::UnboundedReceiver<BusMessage>>>>,
+}
+
+impl LappBus {
+    pub fn new() -> Self {
+        let (messages_in, messages) = mpsc::unbounded_channel();
+
+        Self {
+            messages_in,
+            messages: Arc::new(Mutex::new(Some(messages))),
+        }
+    }
+
+    /// Delivers the messages to the services of the target lapps.
+    pub fn run(&self, lapps_provider: LappsProvider) {
+        let Some(mut messages) = self
+            .messages
+            .lock()
+            .expect("Lapp bus lock should not be poisoned")
+            .take()
+        else {
+            log::warn!("Lapp bus is already run");
+            return;
+        };
+
+        tokio::spawn(async move {
+            while let Some(message) = messages.recv().await {
+                let lapps_provider = lapps_provider.clone();
+
+                tokio::spawn(async move {
+                    let BusMessage {
+                        sender,
+                        lapp_name,
+                        payload,
+                        result_out,
+                    } = message;
+
+                    let result = deliver(lapps_provider, sender, lapp_name, payload).await;
+                    result_out.send(result).ok();
+                });
+            }
+        });
+    }
+
+    pub async fn send(&self, sender: &str, lapp_name: String, payload: Vec<u8>) -> CallResult<()> {
+        let (result_out, result_in) = oneshot::channel();
+        let message = BusMessage {
+            sender: sender.into(),
+            lapp_name,
+            payload,
+            result_out,
+        };
+
+        self.messages_in
+            .send(message)
+            .map_err(|_| CallError::FailCall("lapp bus is not run".into()))?;
+        result_in
+            .await
+            .map_err(|_| CallError::FailCall("lapp message is dropped".into()))?
+    }
+}
+
+impl Default for LappBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn deliver(lapps_provider: LappsProvider, sender: String, lapp_name: String, payload: Vec<u8>) -> CallResult<()> {
+    let manager = lapps_provider.read_manager().await;
+    check_message(&manager, &sender, &lapp_name)?;
+
+    let send_fut = manager.send_lapp_message(lapp_name, sender, payload);
+    drop(manager);
+
+    send_fut.await.map_err(|err| CallError::FailCall(err.to_string()))
+}
+
+fn check_message(manager: &LappsManager, sender: &str, lapp_name: &str) -> CallResult<()> {
+    let forbidden_lapp = || CallError::ForbiddenLapp(lapp_name.into());
+    if sender == lapp_name {
+        return Err(forbidden_lapp());
+    }
+
+    manager
+        .check_enabled_and_allow_permissions(sender, &[Permission::LappsOutgoing])
+        .map_err(|_| forbidden_lapp())?;
+    manager
+        .check_enabled_and_allow_permissions(lapp_name, &[Permission::LappsIncoming])
+        .map_err(|_| forbidden_lapp())?;
+
+    manager
+        .lapp_settings(sender)
+        .ok()
+        .and_then(|settings| settings.lapp_requests_of(lapp_name))
+        .map(|_| ())
+        .ok_or_else(forbidden_lapp)
+}
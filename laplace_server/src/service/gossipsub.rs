@@ -1,10 +1,11 @@
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::ops::ControlFlow;
 use std::str::FromStr;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use laplace_common::api::{P2pAction, P2pStats, PeerStats, TopicStats};
 pub use laplace_wasm::route::gossipsub::{Message, MessageIn, MessageOut};
 use libp2p::futures::StreamExt;
 use libp2p::gossipsub::{self, IdentTopic as Topic, MessageAuthenticity, MessageId, ValidationMode};
@@ -12,6 +13,7 @@ use libp2p::identity::Keypair;
 use libp2p::multiaddr::Protocol;
 use libp2p::swarm::{NetworkBehaviour, SwarmEvent};
 use libp2p::{mdns, noise, tcp, yamux, Multiaddr, PeerId, Swarm, SwarmBuilder};
+use tokio::sync::oneshot;
 use truba::{Context, Sender, UnboundedMpscChannel};
 
 pub use crate::service::gossipsub::error::{Error, GossipsubResult};
@@ -27,6 +29,17 @@ impl truba::Message for GossipsubServiceMessage {
     type Channel = UnboundedMpscChannel<Self>;
 }
 
+/// The admin requests to the lapp P2P node.
+#[derive(Debug)]
+pub enum GossipsubAdminMessage {
+    Stats(oneshot::Sender<P2pStats>),
+    Action(P2pAction, oneshot::Sender<GossipsubResult>),
+}
+
+impl truba::Message for GossipsubAdminMessage {
+    type Channel = UnboundedMpscChannel<Self>;
+}
+
 #[derive(NetworkBehaviour)]
 struct GossipsubServiceBehaviour {
     gossipsub: gossipsub::Behaviour,
@@ -34,11 +47,17 @@ struct GossipsubServiceBehaviour {
 }
 
 pub struct GossipsubService {
+    lapp_name: String,
     swarm: Swarm<GossipsubServiceBehaviour>,
     dial_ports: Vec<u16>,
     topic: Topic,
     lapp_service_sender: Sender<LappServiceMessage>,
     peers: HashMap<PeerId, Vec<Multiaddr>>,
+    banned: HashSet<PeerId>,
+    labels: HashMap<PeerId, String>,
+    started_at: Instant,
+    messages_in: u64,
+    messages_out: u64,
 }
 
 impl GossipsubService {
@@ -94,13 +113,20 @@ impl GossipsubService {
 
         swarm.listen_on(address)?;
 
-        let mut service_message_in = ctx.actor_receiver::<GossipsubServiceMessage>(actor_id);
+        let mut service_message_in = ctx.actor_receiver::<GossipsubServiceMessage>(actor_id.clone());
+        let mut admin_message_in = ctx.actor_receiver::<GossipsubAdminMessage>(actor_id.clone());
         let mut service = Self {
+            lapp_name: actor_id.into_lapp_name(),
             swarm,
             dial_ports,
             topic,
             lapp_service_sender,
             peers: Default::default(),
+            banned: Default::default(),
+            labels: Default::default(),
+            started_at: Instant::now(),
+            messages_in: 0,
+            messages_out: 0,
         };
 
         truba::spawn_event_loop!(ctx, {
@@ -131,6 +157,7 @@ impl GossipsubService {
 
                 if is_break { break }
             },
+            Some(message) = admin_message_in.recv() => service.handle_admin(message),
         });
 
         Ok(())
@@ -169,6 +196,7 @@ impl GossipsubService {
             let text = String::from_utf8_lossy(&message.data); // todo: catch error
             log::debug!("Got message: {text} with id: {message_id} from peer: {peer_id:?}");
             if message.topic == self.topic.hash() {
+                self.messages_in += 1;
                 self.send_to_lapp(MessageIn::Text {
                     peer_id: peer_id.to_base58(),
                     msg: text.to_string(),
@@ -186,32 +214,32 @@ impl GossipsubService {
                     .behaviour_mut()
                     .gossipsub
                     .publish(topic, msg)
-                    .map(drop)
-                    .map(ControlFlow::Continue)
+                    .map(|_| {
+                        self.messages_out += 1;
+                        ControlFlow::Continue(())
+                    })
                     .map_err(Error::GossipsubPublishError)
             },
             Message::Dial(peer_id) => {
                 log::debug!("Dial peer: {peer_id}");
-                PeerId::from_str(&peer_id)
-                    .map_err(|err| Error::ParsePeerIdError(err.to_string()))
-                    .and_then(|peer_id| {
-                        if let Some(mut address) = self
-                            .peers
-                            .get(&peer_id)
-                            .and_then(|addresses| addresses.first())
-                            .cloned()
-                        {
-                            for port in self.dial_ports.clone() {
-                                address.pop();
-                                address.push(Protocol::Tcp(port));
-                                log::debug!("Dial address: {address}");
-                                self.swarm.dial(address.clone()).map_err(Error::DialError)?;
-                            }
-                            Ok(ControlFlow::Continue(()))
-                        } else {
-                            Err(Error::DialError(libp2p::swarm::DialError::NoAddresses))
+                parse_peer_id(&peer_id).and_then(|peer_id| {
+                    if let Some(mut address) = self
+                        .peers
+                        .get(&peer_id)
+                        .and_then(|addresses| addresses.first())
+                        .cloned()
+                    {
+                        for port in self.dial_ports.clone() {
+                            address.pop();
+                            address.push(Protocol::Tcp(port));
+                            log::debug!("Dial address: {address}");
+                            self.swarm.dial(address.clone()).map_err(Error::DialError)?;
                         }
-                    })
+                        Ok(ControlFlow::Continue(()))
+                    } else {
+                        Err(Error::DialError(libp2p::swarm::DialError::NoAddresses))
+                    }
+                })
             },
             Message::AddAddress(address) => {
                 log::debug!("Add address: {address}");
@@ -227,6 +255,104 @@ impl GossipsubService {
         }
     }
 
+    fn handle_admin(&mut self, message: GossipsubAdminMessage) {
+        match message {
+            GossipsubAdminMessage::Stats(stats_out) => {
+                stats_out.send(self.stats()).ok();
+            },
+            GossipsubAdminMessage::Action(action, result_out) => {
+                let result = self.handle_action(action);
+                if let Err(err) = &result {
+                    log::error!("P2P admin action error for topic \"{}\": {err:?}", self.topic);
+                }
+                result_out.send(result).ok();
+            },
+        }
+    }
+
+    fn handle_action(&mut self, action: P2pAction) -> GossipsubResult {
+        match action {
+            P2pAction::Dial { address } => {
+                let msg = if PeerId::from_str(&address).is_ok() {
+                    Message::Dial(address)
+                } else {
+                    Message::AddAddress(address)
+                };
+                self.handle_p2p(msg).map(drop)
+            },
+            P2pAction::Ban { peer_id } => {
+                let peer_id = parse_peer_id(&peer_id)?;
+                log::info!("Ban peer: {peer_id}");
+
+                self.swarm.behaviour_mut().gossipsub.blacklist_peer(&peer_id);
+                self.swarm.disconnect_peer_id(peer_id).ok();
+                self.banned.insert(peer_id);
+                Ok(())
+            },
+            P2pAction::Unban { peer_id } => {
+                let peer_id = parse_peer_id(&peer_id)?;
+                log::info!("Unban peer: {peer_id}");
+
+                self.swarm.behaviour_mut().gossipsub.remove_blacklisted_peer(&peer_id);
+                self.banned.remove(&peer_id);
+                Ok(())
+            },
+            P2pAction::Label { peer_id, label } => {
+                let peer_id = parse_peer_id(&peer_id)?;
+                match label.filter(|label| !label.is_empty()) {
+                    Some(label) => self.labels.insert(peer_id, label),
+                    None => self.labels.remove(&peer_id),
+                };
+                Ok(())
+            },
+        }
+    }
+
+    fn stats(&self) -> P2pStats {
+        let uptime = self.started_at.elapsed();
+        let per_min = |count: u64| count as f64 * 60.0 / uptime.as_secs_f64().max(1.0);
+        let connected: HashSet<_> = self.swarm.connected_peers().copied().collect();
+
+        let mut peer_ids: Vec<_> = self
+            .peers
+            .keys()
+            .chain(&connected)
+            .chain(&self.banned)
+            .chain(self.labels.keys())
+            .copied()
+            .collect();
+        peer_ids.sort_unstable();
+        peer_ids.dedup();
+
+        P2pStats {
+            lapp_name: self.lapp_name.clone(),
+            peer_id: self.swarm.local_peer_id().to_base58(),
+            listen_addresses: self.swarm.listeners().map(ToString::to_string).collect(),
+            uptime_sec: uptime.as_secs(),
+            topics: vec![TopicStats {
+                topic: self.topic.to_string(),
+                messages_in: self.messages_in,
+                messages_out: self.messages_out,
+                messages_in_per_min: per_min(self.messages_in),
+                messages_out_per_min: per_min(self.messages_out),
+            }],
+            peers: peer_ids
+                .into_iter()
+                .map(|peer_id| PeerStats {
+                    peer_id: peer_id.to_base58(),
+                    addresses: self
+                        .peers
+                        .get(&peer_id)
+                        .map(|addresses| addresses.iter().map(ToString::to_string).collect())
+                        .unwrap_or_default(),
+                    connected: connected.contains(&peer_id),
+                    banned: self.banned.contains(&peer_id),
+                    label: self.labels.get(&peer_id).cloned(),
+                })
+                .collect(),
+        }
+    }
+
     fn send_to_lapp(&self, msg: MessageIn) {
         if let Err(err) = self.lapp_service_sender.send(LappServiceMessage::Gossipsub(msg)) {
             log::error!("Error occurs when send to lapp service: {err:?}");
@@ -238,6 +364,10 @@ pub fn decode_keypair(bytes: &mut [u8]) -> GossipsubResult<Keypair> {
     Ok(Keypair::from_protobuf_encoding(bytes)?)
 }
 
+fn parse_peer_id(peer_id: &str) -> GossipsubResult<PeerId> {
+    PeerId::from_str(peer_id).map_err(|err| Error::ParsePeerIdError(err.to_string()))
+}
+
 pub fn decode_peer_id(bytes: &[u8]) -> GossipsubResult<PeerId> {
     PeerId::from_bytes(bytes).map_err(|err| Error::ParsePeerIdError(err.to_string()))
 }
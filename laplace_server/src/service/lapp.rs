@@ -1,21 +1,34 @@
 use std::future::Future;
 use std::io;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::Duration;
 
 use derive_more::From;
 use futures::FutureExt;
-use laplace_wasm::http::{Request, Response};
-use laplace_wasm::Route;
+use laplace_common::lapp::{Permission, RestartPolicy};
+use laplace_wasm::http::{ErrorPageRequest, Request, Response};
+use laplace_wasm::schedule::ScheduleResult;
+use laplace_wasm::{graphql, mqtt, Route};
 use reqwest::Client;
 use tokio::runtime::Handle;
-use tokio::sync::oneshot;
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
 use truba::{Context, Message, Sender, UnboundedMpscChannel};
 
+use crate::circuit_breaker::CircuitBreaker;
 use crate::error::{ServerError, ServerResult};
-use crate::lapps::{Lapp, LappInstanceError};
+use crate::lapps::{Job, JobQueue, Lapp, LappInstanceError, PooledInstance};
 use crate::service::gossipsub::GossipsubServiceMessage;
 use crate::service::websocket::WsServiceMessage;
 use crate::service::{gossipsub, websocket, Addr};
 
+/// The maximum delay before the restart of the failed lapp instance.
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The number of the background jobs run in a row before the next messages are handled.
+const JOBS_BATCH: usize = 16;
+
 #[derive(Debug, From)]
 pub enum Error {
     Instance(LappInstanceError),
@@ -26,15 +39,48 @@ pub enum Error {
 pub enum LappServiceMessage {
     Stop,
 
+    /// Stops the service after the queued messages and the in-flight pooled requests are processed.
+    Drain(oneshot::Sender<()>),
+
+    /// Switches the read-only mode of the lapp, the lapp is re-instantiated in the new mode.
+    SetReadOnly(bool),
+
     Http(HttpMessage),
+    ErrorPage(ErrorPageMessage),
+
+    // GraphQL
+    GraphqlSchema(oneshot::Sender<ServerResult<Option<String>>>),
+    Graphql(GraphqlMessage),
 
     // WebSocket
-    NewWebSocket(Sender<WsServiceMessage>),
-    WebSocket(websocket::MessageIn),
+    NewWebSocket {
+        client_id: u64,
+        sender: Sender<WsServiceMessage>,
+    },
+    WebSocket {
+        client_id: u64,
+        msg: websocket::MessageIn,
+    },
+    WebSocketClosed(u64),
 
     // Gossipsub
     NewGossipsub(Sender<GossipsubServiceMessage>),
     Gossipsub(gossipsub::MessageIn),
+
+    // MQTT
+    Mqtt(mqtt::Message),
+
+    /// The message of the bus from another lapp.
+    LappMessage(LappMessage),
+
+    // Scheduled jobs
+    Schedule(ScheduleMessage),
+
+    /// The timer set by the lapp instance is fired.
+    Timer(u64),
+
+    /// The background jobs of the lapp may be due.
+    Jobs,
 }
 
 impl Message for LappServiceMessage {
@@ -51,6 +97,35 @@ impl LappServiceMessage {
 
         (message, response_in)
     }
+
+    pub fn new_error_page(request: ErrorPageRequest) -> (Self, oneshot::Receiver<ServerResult<Option<String>>>) {
+        let (page_out, page_in) = oneshot::channel();
+        (Self::ErrorPage(ErrorPageMessage { request, page_out }), page_in)
+    }
+
+    pub fn new_graphql_schema() -> (Self, oneshot::Receiver<ServerResult<Option<String>>>) {
+        let (schema_out, schema_in) = oneshot::channel();
+        (Self::GraphqlSchema(schema_out), schema_in)
+    }
+
+    pub fn new_graphql(request: graphql::Request) -> (Self, oneshot::Receiver<ServerResult<String>>) {
+        let (response_out, response_in) = oneshot::channel();
+        let message = Self::Graphql(GraphqlMessage { request, response_out });
+
+        (message, response_in)
+    }
+
+    pub fn new_schedule(
+        job_name: impl Into<String>,
+    ) -> (Self, oneshot::Receiver<ServerResult<Option<ScheduleResult>>>) {
+        let (result_out, result_in) = oneshot::channel();
+        let message = Self::Schedule(ScheduleMessage {
+            job_name: job_name.into(),
+            result_out,
+        });
+
+        (message, result_in)
+    }
 }
 
 #[derive(Debug)]
@@ -59,24 +134,76 @@ pub struct HttpMessage {
     pub response_out: oneshot::Sender<ServerResult<Response>>,
 }
 
+#[derive(Debug)]
+pub struct ErrorPageMessage {
+    pub request: ErrorPageRequest,
+    pub page_out: oneshot::Sender<ServerResult<Option<String>>>,
+}
+
+#[derive(Debug)]
+pub struct GraphqlMessage {
+    pub request: graphql::Request,
+    pub response_out: oneshot::Sender<ServerResult<String>>,
+}
+
+#[derive(Debug)]
+pub struct LappMessage {
+    pub sender: String,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct ScheduleMessage {
+    pub job_name: String,
+    pub result_out: oneshot::Sender<ServerResult<Option<ScheduleResult>>>,
+}
+
 pub struct LappService {
     lapp: Lapp,
+    circuit_breaker: CircuitBreaker,
     gossipsub_sender: Option<Sender<GossipsubServiceMessage>>,
-    websocket_sender: Option<Sender<WsServiceMessage>>,
+
+    /// The client the WebSocket routes are sent to: the sender of the handled message or the last connected client.
+    websocket_client: Option<u64>,
+
+    /// The GraphQL schema exported by the lapp module, it is requested once per the service run.
+    graphql_schema: Option<Option<String>>,
+
+    /// The number of the restarts of the failed instance in a row.
+    restarts: u32,
+
+    /// The number of the HTTP requests in flight on the pooled instances.
+    pooled_requests: Arc<watch::Sender<usize>>,
+
+    /// The queue of the background jobs, it is opened when the lapp enqueues the first job.
+    job_queue: Option<JobQueue>,
+
+    /// The wake-up at the time of the next postponed job.
+    jobs_retry: Option<JoinHandle<()>>,
 }
 
 impl LappService {
-    pub fn new(lapp: Lapp) -> Self {
+    pub fn new(lapp: Lapp, circuit_breaker: CircuitBreaker) -> Self {
         Self {
             lapp,
+            circuit_breaker,
             gossipsub_sender: None,
-            websocket_sender: None,
+            websocket_client: None,
+            graphql_schema: None,
+            restarts: 0,
+            pooled_requests: Arc::new(watch::channel(0).0),
+            job_queue: None,
+            jobs_retry: None,
         }
     }
 
     pub fn run(mut self, ctx: Context<Addr>, http_client: Client) -> impl Future<Output = ServerResult<()>> {
         let lapp_name = self.lapp.name().to_owned();
         let (instantiate_sender, instantiate_receiver) = oneshot::channel();
+        let mut timers_in = self.lapp.timers_receiver();
+        // The jobs persisted before the service run are checked at the start
+        let jobs_notify = self.lapp.jobs_notify();
+        jobs_notify.notify_one();
 
         log::info!("Run lapp service for lapp \"{lapp_name}\"");
 
@@ -84,8 +211,11 @@ impl LappService {
         std::thread::spawn(move || {
             handle.block_on(async move {
                 let mut messages_in = ctx.actor_receiver::<LappServiceMessage>(Addr::Lapp(self.lapp.name().to_owned()));
-                let instantiate_result = self.lapp.instantiate(http_client).await;
+                let instantiate_result = self.lapp.instantiate(http_client.clone()).await;
                 let is_instantiated = instantiate_result.is_ok();
+                if !is_instantiated {
+                    self.circuit_breaker.record_failure(self.lapp.name());
+                }
 
                 if let Err(instantiate_result) = instantiate_sender.send(instantiate_result) {
                     log::error!("Instantiate receiver dropped, instantiate result: {instantiate_result:?}");
@@ -95,18 +225,29 @@ impl LappService {
                     truba::event_loop!(ctx, {
                         Some(msg) = messages_in.recv() => {
                             match msg {
-                                LappServiceMessage::Http(msg) => self.handle_http(msg).await,
-
-                                LappServiceMessage::NewWebSocket(sender) => self.handle_new_websocket(sender),
-                                LappServiceMessage::WebSocket(msg) => self.handle_websocket(msg).await,
-
-                                LappServiceMessage::NewGossipsub(sender) => self.handle_new_gossipsub(sender),
-                                LappServiceMessage::Gossipsub(msg) => self.handle_gossipsub(msg).await,
-
                                 LappServiceMessage::Stop => break,
+                                LappServiceMessage::Drain(drained_out) => {
+                                    self.wait_pooled_requests().await;
+                                    drained_out.send(()).ok();
+                                    break;
+                                },
+                                LappServiceMessage::SetReadOnly(read_only) => {
+                                    self.handle_set_read_only(read_only, &http_client).await;
+                                },
+                                msg => self.handle_supervised(msg, &http_client).await,
                             }
                         }
+                        Some(id) = timers_in.recv() => {
+                            self.handle_supervised(LappServiceMessage::Timer(id), &http_client).await;
+                        }
+                        _ = jobs_notify.notified() => {
+                            self.handle_supervised(LappServiceMessage::Jobs, &http_client).await;
+                        }
                     });
+
+                    // The sessions are bound to the stopped service, e.g. revoking the `websocket` permission
+                    // re-instantiates the lapp and cuts off its open connections
+                    self.lapp.ws_clients().close_all();
                 }
             });
         });
@@ -130,27 +271,228 @@ impl LappService {
         }
     }
 
+    /// Stops the service after its queued and in-flight requests are processed, the returned receiver is notified
+    /// when the service is stopped. The new messages are not delivered to the stopping service.
+    pub fn drain(ctx: &Context<Addr>, service_actor_id: &Addr) -> Option<oneshot::Receiver<()>> {
+        let sender = ctx.get_actor_sender::<LappServiceMessage>(service_actor_id)?;
+        let (drained_out, drained_in) = oneshot::channel();
+        if let Err(err) = sender.send(LappServiceMessage::Drain(drained_out)) {
+            log::error!("Cannot drain lapp service '{service_actor_id}': {err}");
+        }
+        drop(ctx.extract_actor_channel::<LappServiceMessage>(service_actor_id));
+        Some(drained_in)
+    }
+
+    /// Switches the read-only mode of the running service, it is ignored if the service is not run.
+    pub fn set_read_only(ctx: &Context<Addr>, service_actor_id: &Addr, read_only: bool) {
+        if let Some(sender) = ctx.get_actor_sender::<LappServiceMessage>(service_actor_id) {
+            if let Err(err) = sender.send(LappServiceMessage::SetReadOnly(read_only)) {
+                log::error!("Cannot switch read-only mode of lapp service '{service_actor_id}': {err}");
+            }
+        }
+    }
+
+    async fn wait_pooled_requests(&self) {
+        let mut pooled_requests = self.pooled_requests.subscribe();
+        pooled_requests.wait_for(|&count| count == 0).await.ok();
+    }
+
+    /// Handles the message and restarts the instance that trapped or panicked according to the lapp restart policy.
+    async fn handle_supervised(&mut self, msg: LappServiceMessage, http_client: &Client) {
+        let is_panicked = AssertUnwindSafe(self.handle(msg)).catch_unwind().await.is_err();
+        if is_panicked {
+            log::error!(
+                "Lapp '{}' service panicked while handling the message",
+                self.lapp.name()
+            );
+        }
+
+        if !self.lapp.take_instance_failure() && !is_panicked {
+            self.circuit_breaker.reset(self.lapp.name());
+            self.restarts = 0;
+            return;
+        }
+        self.circuit_breaker.record_failure(self.lapp.name());
+
+        let restart = self.lapp.settings().restart().clone();
+        if restart.policy == RestartPolicy::Never {
+            return;
+        }
+        if self.restarts >= restart.max_retries {
+            if self.restarts == restart.max_retries {
+                log::error!(
+                    "Lapp '{}' is not restarted after {} failed restarts",
+                    self.lapp.name(),
+                    self.restarts
+                );
+                self.restarts += 1;
+            }
+            return;
+        }
+
+        let backoff = Duration::from_millis(restart.backoff_ms)
+            .saturating_mul(2_u32.saturating_pow(self.restarts))
+            .min(MAX_RESTART_BACKOFF);
+        self.restarts += 1;
+        log::warn!(
+            "Restart lapp '{}' in {backoff:?}, restart {} of {}",
+            self.lapp.name(),
+            self.restarts,
+            restart.max_retries
+        );
+        tokio::time::sleep(backoff).await;
+
+        match self.lapp.instantiate(http_client.clone()).await {
+            Ok(()) => self.graphql_schema = None,
+            Err(err) => {
+                log::error!("Restart lapp '{}' error: {err}", self.lapp.name());
+                self.circuit_breaker.record_failure(self.lapp.name());
+            },
+        }
+    }
+
+    async fn handle(&mut self, msg: LappServiceMessage) {
+        match msg {
+            LappServiceMessage::Http(msg) => self.handle_http(msg).await,
+            LappServiceMessage::ErrorPage(msg) => self.handle_error_page(msg).await,
+
+            LappServiceMessage::GraphqlSchema(schema_out) => self.handle_graphql_schema(schema_out).await,
+            LappServiceMessage::Graphql(msg) => self.handle_graphql(msg).await,
+
+            LappServiceMessage::NewWebSocket { client_id, sender } => self.handle_new_websocket(client_id, sender),
+            LappServiceMessage::WebSocket { client_id, msg } => self.handle_websocket(client_id, msg).await,
+            LappServiceMessage::WebSocketClosed(client_id) => self.handle_websocket_closed(client_id),
+
+            LappServiceMessage::NewGossipsub(sender) => self.handle_new_gossipsub(sender),
+            LappServiceMessage::Gossipsub(msg) => self.handle_gossipsub(msg).await,
+
+            LappServiceMessage::Mqtt(msg) => self.handle_mqtt(msg).await,
+            LappServiceMessage::LappMessage(msg) => self.handle_lapp_message(msg).await,
+
+            LappServiceMessage::Schedule(msg) => self.handle_schedule(msg).await,
+            LappServiceMessage::Timer(id) => self.handle_timer(id).await,
+            LappServiceMessage::Jobs => self.handle_jobs().await,
+
+            LappServiceMessage::Stop | LappServiceMessage::Drain(_) | LappServiceMessage::SetReadOnly(_) => (),
+        }
+    }
+
     async fn handle_http(&mut self, msg: HttpMessage) {
         let HttpMessage { request, response_out } = msg;
 
+        if let Some(instance) = self.lapp.pool().try_acquire() {
+            self.spawn_pooled_http(instance, *request, response_out);
+            return;
+        }
+
         let result = self.lapp.process_http(*request).await;
         if let Err(err) = response_out.send(result) {
             log::error!("Cannot process HTTP for lapp '{}': {err:?}", self.lapp.name());
         }
     }
 
-    fn handle_new_websocket(&mut self, sender: Sender<WsServiceMessage>) {
-        self.websocket_sender.replace(sender);
+    /// Processes the HTTP request by the pooled instance concurrently with the next messages.
+    fn spawn_pooled_http(
+        &self,
+        mut instance: PooledInstance,
+        request: Request,
+        response_out: oneshot::Sender<ServerResult<Response>>,
+    ) {
+        let lapp_name = self.lapp.name().to_owned();
+        let is_debug = self.lapp.is_debug();
+        let circuit_breaker = self.circuit_breaker.clone();
+        let pooled_requests = Arc::clone(&self.pooled_requests);
+        pooled_requests.send_modify(|count| *count += 1);
+
+        tokio::spawn(async move {
+            let result: ServerResult<Response> = instance
+                .process_http(request)
+                .await
+                .map_err(|err| Lapp::log_instance_error(&lapp_name, is_debug, err).into());
+
+            if instance.is_failed {
+                log::warn!("Failed pooled instance of lapp '{lapp_name}' is dropped");
+                circuit_breaker.record_failure(&lapp_name);
+            } else {
+                circuit_breaker.reset(&lapp_name);
+            }
+            drop(instance);
+
+            if let Err(err) = response_out.send(result) {
+                log::error!("Cannot process HTTP for lapp '{lapp_name}': {err:?}");
+            }
+            pooled_requests.send_modify(|count| *count -= 1);
+        });
+    }
+
+    async fn handle_error_page(&mut self, msg: ErrorPageMessage) {
+        let ErrorPageMessage { request, page_out } = msg;
+
+        let result = self.lapp.error_page(request).await;
+        if let Err(err) = page_out.send(result) {
+            log::error!("Cannot render error page of lapp '{}': {err:?}", self.lapp.name());
+        }
+    }
+
+    async fn handle_graphql_schema(&mut self, schema_out: oneshot::Sender<ServerResult<Option<String>>>) {
+        let result = match &self.graphql_schema {
+            Some(schema) => Ok(schema.clone()),
+            None => self.lapp.graphql_schema().await.map(|schema| {
+                self.graphql_schema = Some(schema.clone());
+                schema
+            }),
+        };
+
+        if let Err(err) = schema_out.send(result) {
+            log::error!("Cannot get GraphQL schema of lapp '{}': {err:?}", self.lapp.name());
+        }
+    }
+
+    async fn handle_graphql(&mut self, msg: GraphqlMessage) {
+        let GraphqlMessage { request, response_out } = msg;
+
+        let result = self.lapp.graphql_resolve(request).await;
+        if let Err(err) = response_out.send(result) {
+            log::error!("Cannot process GraphQL for lapp '{}': {err:?}", self.lapp.name());
+        }
+    }
+
+    /// The WebSocket messages are forwarded in both directions only while the lapp has the `websocket` permission.
+    fn is_websocket_allowed(&self) -> bool {
+        let is_allowed = self.lapp.is_allowed_permission(Permission::Websocket);
+        if !is_allowed {
+            log::warn!("Websocket is not allowed for lapp {}", self.lapp.name());
+        }
+        is_allowed
+    }
+
+    fn handle_new_websocket(&mut self, client_id: u64, sender: Sender<WsServiceMessage>) {
+        if self.is_websocket_allowed() {
+            self.lapp.ws_clients().insert(client_id, sender);
+            self.websocket_client = Some(client_id);
+        }
     }
 
-    async fn handle_websocket(&mut self, msg: websocket::MessageIn) {
+    async fn handle_websocket(&mut self, client_id: u64, msg: websocket::MessageIn) {
+        if !self.is_websocket_allowed() {
+            return;
+        }
+        self.websocket_client = Some(client_id);
         let Some(instance) = self.lapp.instance_mut() else {
             log::warn!("Handle websocket: instance not found for lapp {}", self.lapp.name());
             return;
         };
-        match instance.route_ws(&msg).await {
+        match instance.route_ws(Some(client_id), &msg).await {
             Ok(routes) => self.process_routes(routes),
-            Err(err) => log::error!("Handle websocket error: {err:?}"),
+            Err(err) => log::error!("Handle websocket error: {}", self.lapp.instance_error(err)),
+        }
+    }
+
+    fn handle_websocket_closed(&mut self, client_id: u64) {
+        let ws_clients = self.lapp.ws_clients();
+        ws_clients.remove(client_id);
+        if self.websocket_client == Some(client_id) {
+            self.websocket_client = ws_clients.last();
         }
     }
 
@@ -165,14 +507,149 @@ impl LappService {
         };
         match instance.route_gossipsub(&msg).await {
             Ok(routes) => self.process_routes(routes),
-            Err(err) => log::error!("Handle gossipsub error: {err:?}"),
+            Err(err) => log::error!("Handle gossipsub error: {}", self.lapp.instance_error(err)),
+        }
+    }
+
+    async fn handle_mqtt(&mut self, msg: mqtt::Message) {
+        let Some(instance) = self.lapp.instance_mut() else {
+            log::warn!("Handle MQTT: instance not found for lapp {}", self.lapp.name());
+            return;
+        };
+        match instance.handle_mqtt(&msg).await {
+            Ok(routes) => self.process_routes(routes),
+            Err(err) => log::error!("Handle MQTT error: {}", self.lapp.instance_error(err)),
+        }
+    }
+
+    async fn handle_lapp_message(&mut self, msg: LappMessage) {
+        let LappMessage { sender, payload } = msg;
+
+        if let Err(err) = self.lapp.on_lapp_message(&sender, &payload).await {
+            log::error!(
+                "Handle message of lapp '{sender}' by lapp '{}' error: {err}",
+                self.lapp.name()
+            );
+        }
+    }
+
+    async fn handle_schedule(&mut self, msg: ScheduleMessage) {
+        let ScheduleMessage { job_name, result_out } = msg;
+
+        let result = self.lapp.on_schedule(&job_name).await;
+        if let Err(err) = result_out.send(result) {
+            log::error!("Cannot run job '{job_name}' of lapp '{}': {err:?}", self.lapp.name());
+        }
+    }
+
+    async fn handle_timer(&mut self, id: u64) {
+        if let Err(err) = self.lapp.on_timer(id).await {
+            log::error!("Handle timer {id} of lapp '{}' error: {err}", self.lapp.name());
+        }
+    }
+
+    /// The instance and its database connections are created with the write access of the mode, so the lapp is
+    /// re-instantiated after the switch.
+    async fn handle_set_read_only(&mut self, read_only: bool, http_client: &Client) {
+        if self.lapp.is_read_only() == read_only {
+            return;
+        }
+
+        self.lapp.set_read_only(read_only);
+        self.job_queue = None;
+        match self.lapp.instantiate(http_client.clone()).await {
+            Ok(()) => {
+                self.graphql_schema = None;
+                log::info!("Lapp '{}' read-only mode is switched to {read_only}", self.lapp.name());
+
+                // The jobs kept in the read-only mode are run after the switch
+                if !read_only {
+                    self.lapp.jobs_notify().notify_one();
+                }
+            },
+            Err(err) => {
+                log::error!("Re-instantiate lapp '{}' error: {err}", self.lapp.name());
+                self.circuit_breaker.record_failure(self.lapp.name());
+            },
+        }
+    }
+
+    /// Runs the due background jobs by the batch, then wakes the service for the rest of them. The jobs are kept in
+    /// the queue of the read-only lapp, since their completion is written to the database.
+    async fn handle_jobs(&mut self) {
+        if self.lapp.is_read_only() {
+            return;
+        }
+
+        if self.job_queue.is_none() {
+            let database_path = Lapp::database_path(self.lapp.root_dir(), self.lapp.settings());
+            match JobQueue::open(database_path, false) {
+                Ok(job_queue) => self.job_queue = job_queue,
+                Err(err) => {
+                    log::error!("Open jobs queue of lapp '{}' error: {err}", self.lapp.name());
+                    return;
+                },
+            }
+        }
+        let Some(job_queue) = &self.job_queue else {
+            return;
+        };
+
+        for _ in 0..JOBS_BATCH {
+            let job = match job_queue.next_due() {
+                Ok(Some(job)) => job,
+                Ok(None) => break,
+                Err(err) => {
+                    log::error!("Get job of lapp '{}' error: {err}", self.lapp.name());
+                    return;
+                },
+            };
+
+            let (result, is_failed_instance) = match self.lapp.on_job(&job.payload).await {
+                Ok(Some(Ok(()))) => (job_queue.complete(&job), false),
+                Ok(Some(Err(err))) => (fail_job(job_queue, &job, self.lapp.name(), &err), false),
+                Ok(None) => {
+                    let reason = "the lapp does not export 'on_job'";
+                    (fail_job(job_queue, &job, self.lapp.name(), reason), false)
+                },
+                Err(err) => (fail_job(job_queue, &job, self.lapp.name(), &err.to_string()), true),
+            };
+            if let Err(err) = result {
+                log::error!("Update job {} of lapp '{}' error: {err}", job.id, self.lapp.name());
+                return;
+            }
+            // The failed instance is restarted before the next jobs
+            if is_failed_instance {
+                break;
+            }
+        }
+
+        match job_queue.next_delay() {
+            Ok(Some(Duration::ZERO)) => self.lapp.jobs_notify().notify_one(),
+            Ok(Some(delay)) => {
+                let jobs_notify = self.lapp.jobs_notify();
+                let jobs_retry = tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    jobs_notify.notify_one();
+                });
+                if let Some(jobs_retry) = self.jobs_retry.replace(jobs_retry) {
+                    jobs_retry.abort();
+                }
+            },
+            Ok(None) => (),
+            Err(err) => log::error!("Get next job of lapp '{}' error: {err}", self.lapp.name()),
         }
     }
 
     fn send_websocket(&self, msg: websocket::MessageOut) {
-        let websocket_sender = self.websocket_sender.clone();
+        if !self.is_websocket_allowed() {
+            return;
+        }
+        let websocket_sender = self
+            .websocket_client
+            .and_then(|client_id| self.lapp.ws_clients().get(client_id));
         if let Some(sender) = websocket_sender {
-            if let Err(err) = sender.send(WsServiceMessage(msg)) {
+            if let Err(err) = sender.send(WsServiceMessage::Route(msg)) {
                 log::error!("Websocket send error: {err:?}");
             }
         } else {
@@ -202,3 +679,20 @@ impl LappService {
         }
     }
 }
+
+/// Postpones the failed job or drops it after the maximum attempts.
+fn fail_job(job_queue: &JobQueue, job: &Job, lapp_name: &str, reason: &str) -> rusqlite::Result<()> {
+    if job_queue.fail(job)? {
+        log::warn!(
+            "Job {} of lapp '{lapp_name}' failed and will be retried: {reason}",
+            job.id
+        );
+    } else {
+        log::error!(
+            "Job {} of lapp '{lapp_name}' is dropped after {} failed attempts: {reason}",
+            job.id,
+            job.attempts + 1
+        );
+    }
+    Ok(())
+}
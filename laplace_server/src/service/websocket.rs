@@ -1,5 +1,8 @@
+use std::collections::HashMap;
 use std::io;
 use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::{Duration, Instant};
 
 use axum::extract::ws;
@@ -21,19 +24,74 @@ enum WsError {
     Io(io::Error),
 }
 
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
 #[derive(Debug)]
-pub struct WsServiceMessage(pub MessageOut);
+pub enum WsServiceMessage {
+    /// The message routed by the lapp, the send result is returned to the lapp as the response.
+    Route(MessageOut),
+
+    /// The message pushed by the `ws_push` host call.
+    Push(Message),
+
+    /// Closes the session when the lapp service is stopped.
+    Close,
+}
 
 impl truba::Message for WsServiceMessage {
     type Channel = UnboundedMpscChannel<Self>;
 }
 
+/// The WebSocket sessions of the lapp by the client ids, the lapp pushes the messages to them.
+#[derive(Debug, Clone, Default)]
+pub struct WsClients(Arc<Mutex<HashMap<u64, Sender<WsServiceMessage>>>>);
+
+impl WsClients {
+    pub fn insert(&self, client_id: u64, sender: Sender<WsServiceMessage>) {
+        self.lock().insert(client_id, sender);
+    }
+
+    pub fn remove(&self, client_id: u64) {
+        self.lock().remove(&client_id);
+    }
+
+    pub fn get(&self, client_id: u64) -> Option<Sender<WsServiceMessage>> {
+        self.lock().get(&client_id).cloned()
+    }
+
+    /// The most recently connected client.
+    pub fn last(&self) -> Option<u64> {
+        self.lock().keys().max().copied()
+    }
+
+    pub fn push(&self, client_id: u64, msg: Message) -> Result<(), String> {
+        let sender = self
+            .get(client_id)
+            .ok_or_else(|| format!("WebSocket client {client_id} is not connected"))?;
+        sender
+            .send(WsServiceMessage::Push(msg))
+            .map_err(|err| format!("WebSocket client {client_id} push error: {err:?}"))
+    }
+
+    /// Closes all sessions, e.g. when the lapp service is stopped.
+    pub fn close_all(&self) {
+        for (_, sender) in self.lock().drain() {
+            sender.send(WsServiceMessage::Close).ok();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<u64, Sender<WsServiceMessage>>> {
+        self.0.lock().expect("WebSocket clients lock should not be poisoned")
+    }
+}
+
 #[derive(Debug)]
 pub struct WebSocketService {
     /// Client must send ping at least once per SETTINGS.ws.client_timeout_sec seconds,
     /// otherwise we drop connection.
     hb: Instant,
 
+    client_id: u64,
     lapp_service_sender: Sender<LappServiceMessage>,
     ws_sender: SplitSink<WebSocket, ws::Message>,
     ws_receiver: SplitStream<WebSocket>,
@@ -46,11 +104,17 @@ impl WebSocketService {
     /// How long before lack of client response causes a timeout
     const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
 
-    pub fn new(web_socket: WebSocket, lapp_service_sender: Sender<LappServiceMessage>) -> Self {
+    /// Returns the unique id of the new client session.
+    pub fn next_client_id() -> u64 {
+        NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn new(web_socket: WebSocket, client_id: u64, lapp_service_sender: Sender<LappServiceMessage>) -> Self {
         let (ws_sender, ws_receiver) = web_socket.split();
 
         Self {
             hb: Instant::now(),
+            client_id,
             lapp_service_sender,
             ws_sender,
             ws_receiver,
@@ -73,7 +137,7 @@ impl WebSocketService {
                         break;
                     }
                 },
-                Some(WsServiceMessage(msg)) = messages_in.recv() => {
+                Some(msg) = messages_in.recv() => {
                     if self.handle_service_message(msg).await.is_break() {
                         break;
                     }
@@ -139,8 +203,12 @@ impl WebSocketService {
         ControlFlow::Continue(())
     }
 
-    async fn handle_service_message(&mut self, MessageOut { id, msg }: MessageOut) -> ControlFlow<(), ()> {
-        let id = Some(id);
+    async fn handle_service_message(&mut self, msg: WsServiceMessage) -> ControlFlow<(), ()> {
+        let (id, msg) = match msg {
+            WsServiceMessage::Route(MessageOut { id, msg }) => (Some(id), msg),
+            WsServiceMessage::Push(msg) => (None, msg),
+            WsServiceMessage::Close => return ControlFlow::Break(()),
+        };
         let sent = match msg {
             Message::Text(text) => self.send_to_ws(id, ws::Message::Text(text)).await,
             Message::Binary(text) => self.send_to_ws(id, ws::Message::Binary(text)).await,
@@ -155,6 +223,12 @@ impl WebSocketService {
 
     async fn close(&mut self) {
         self.ws_sender.send(ws::Message::Close(None)).await.ok();
+        if let Err(err) = self
+            .lapp_service_sender
+            .send(LappServiceMessage::WebSocketClosed(self.client_id))
+        {
+            log::error!("Error occurs when send to lapp service: {err:?}");
+        }
     }
 
     async fn send_to_ws(&mut self, id: Option<String>, msg: ws::Message) -> bool {
@@ -179,7 +253,11 @@ impl WebSocketService {
     }
 
     fn send_to_lapp(&self, msg: MessageIn) {
-        if let Err(err) = self.lapp_service_sender.send(LappServiceMessage::WebSocket(msg)) {
+        let msg = LappServiceMessage::WebSocket {
+            client_id: self.client_id,
+            msg,
+        };
+        if let Err(err) = self.lapp_service_sender.send(msg) {
             log::error!("Error occurs when send to lapp service: {err:?}");
         }
     }
@@ -1,10 +1,64 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 
 pub use config::ConfigError;
-use config::{Config, Environment, File};
+use config::{Config, Environment, File, FileFormat, FileSourceFile};
+use directories::ProjectDirs;
+use laplace_common::lapp::settings::SecurityHeadersSettings;
+use laplace_common::lapp::Permission;
 use serde::{Deserialize, Serialize};
 
+lazy_static::lazy_static! {
+    static ref PROJECT_DIRS: Option<ProjectDirs> = ProjectDirs::from("", "", "laplace");
+}
+
+/// Returns `config.toml` in the working directory if it exists, otherwise the platform config location
+/// (e.g. `$XDG_CONFIG_HOME/laplace/config.toml`).
+pub fn default_config_path() -> PathBuf {
+    let local_config_path = PathBuf::from("config.toml");
+
+    match PROJECT_DIRS.as_ref() {
+        Some(dirs) if !local_config_path.exists() => dirs.config_dir().join(local_config_path),
+        _ => local_config_path,
+    }
+}
+
+/// The platform data location (e.g. `$XDG_DATA_HOME/laplace`), or the working directory.
+pub fn default_data_dir() -> PathBuf {
+    PROJECT_DIRS
+        .as_ref()
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .unwrap_or_default()
+}
+
+/// Returns the path in the working directory if it exists, as the previous versions used it by default, otherwise the
+/// platform location.
+fn legacy_or_default(legacy_path: &str, default_path: impl FnOnce() -> PathBuf) -> PathBuf {
+    let legacy_path = PathBuf::from(legacy_path);
+    if legacy_path.exists() {
+        legacy_path
+    } else {
+        default_path()
+    }
+}
+
+/// The platform cache location (e.g. `$XDG_CACHE_HOME/laplace`), or the `cache` in the working directory.
+pub fn default_cache_dir() -> PathBuf {
+    PROJECT_DIRS
+        .as_ref()
+        .map(|dirs| dirs.cache_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("cache"))
+}
+
+/// The platform state location (e.g. `$XDG_STATE_HOME/laplace`), or the `state` in the working directory.
+pub fn default_state_dir() -> PathBuf {
+    PROJECT_DIRS
+        .as_ref()
+        .map(|dirs| dirs.state_dir().unwrap_or_else(|| dirs.data_local_dir()).to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("state"))
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct HttpSettings {
@@ -14,6 +68,21 @@ pub struct HttpSettings {
     pub access_token: Option<String>,
     pub upload_file_limit: usize,
     pub print_url: bool,
+
+    /// The domain of the lapp subdomains, e.g. `example.com` to serve the `chat` lapp from `chat.example.com`.
+    pub lapps_domain: Option<String>,
+
+    /// Ports to try in order when the configured `port` is busy.
+    pub fallback_ports: Option<RangeInclusive<u16>>,
+
+    /// The additional listeners besides the main `host` and `port` one, e.g. the public listener of the lapps.
+    pub listeners: Vec<ListenerSettings>,
+
+    /// The overrides of the recommended security headers of the responses.
+    pub security_headers: SecurityHeadersSettings,
+
+    /// Open the Laplace URL in the default browser after startup.
+    pub open_browser: bool,
 }
 
 impl Default for HttpSettings {
@@ -25,10 +94,25 @@ impl Default for HttpSettings {
             access_token: None,
             upload_file_limit: 2 * 1024 * 1024 * 1024,
             print_url: true,
+            lapps_domain: None,
+            fallback_ports: None,
+            listeners: Vec::new(),
+            security_headers: SecurityHeadersSettings::default(),
+            open_browser: false,
         }
     }
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ListenerSettings {
+    pub host: String,
+    pub port: u16,
+
+    /// Serve the Laplace management UI and API besides the lapps, the main listener always serves them.
+    #[serde(default)]
+    pub management: bool,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SslSettings {
     #[serde(default)]
@@ -52,11 +136,11 @@ impl Default for SslSettings {
 }
 
 fn private_key_path_default() -> PathBuf {
-    PathBuf::from("key.pem")
+    legacy_or_default("key.pem", || default_data_dir().join("cert").join("key.pem"))
 }
 
 fn certificate_path_default() -> PathBuf {
-    PathBuf::from("cert.pem")
+    legacy_or_default("cert.pem", || default_data_dir().join("cert").join("cert.pem"))
 }
 
 #[derive(Debug, Default, Deserialize, Serialize)]
@@ -98,18 +182,270 @@ const fn default_keep_log_for_days() -> usize {
     7
 }
 
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessLogFormat {
+    /// The combined log format of the common web servers.
+    #[default]
+    Combined,
+    Json,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AccessLogSettings {
+    /// Directory of the access log files, the requests are not logged if missing.
+    pub dir: Option<PathBuf>,
+
+    pub format: AccessLogFormat,
+
+    /// Write the requests of every lapp to its own `{lapp_name}.access.log` file.
+    pub per_lapp: bool,
+
+    #[serde(default = "default_keep_log_for_days")]
+    pub keep_log_for_days: usize,
+}
+
+impl Default for AccessLogSettings {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            format: AccessLogFormat::default(),
+            per_lapp: false,
+            keep_log_for_days: default_keep_log_for_days(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct LappsSettings {
     pub path: PathBuf,
     pub allowed: Option<HashSet<String>>,
+
+    /// Serve lapps in the read-only replica mode: lapps installation, settings changes, file and database
+    /// writes are rejected.
+    pub read_only: bool,
+
+    /// Include the decoded wasm backtraces of the lapp traps into the error responses.
+    pub debug: bool,
+
+    /// Reload the lapps when their server module or config changes on disk, for the lapps development.
+    pub watch: bool,
+
+    /// Lapps deployed from git repositories.
+    pub git: Vec<GitLappSettings>,
+
+    /// The HTTPS URL of the remote lapps registry index.
+    pub registry_url: Option<String>,
+
+    /// The number of the host threads shared by the threads of all lapps, the number of CPUs by default.
+    pub threads_pool_size: usize,
+
+    /// The maximum size in MiB of the linear memory of any lapp, the lapp own limit may only be lower.
+    pub max_memory_mb: Option<u64>,
+
+    /// The maximum time in milliseconds of a single lapp call, the lapp own limit may only be lower.
+    pub call_time_limit_ms: Option<u64>,
+
+    /// The maximum size in MiB of the data directory and database of any lapp, the lapp own quota may only be lower.
+    pub disk_quota_mb: Option<u64>,
+
+    /// The maximum size in MiB of the HTTP request body passed to any lapp, the lapp own limit may only be lower.
+    pub max_body_mb: Option<u64>,
+
+    /// The maximum time in milliseconds any lapp processes an HTTP request, the lapp own timeout may only be lower.
+    pub http_timeout_ms: Option<u64>,
+
+    /// The number of the lapp failures in a row after which the lapp is quarantined, zero disables the quarantine.
+    pub quarantine_threshold: u32,
+
+    /// The absolute host directories outside of the lapp directories that the lapps may mount, along with their
+    /// subdirectories. The lapp mounts are confined to the lapp directory without them.
+    pub mount_paths: Vec<PathBuf>,
+
+    /// Whether the compiled lapp server modules are cached in the lapp directories.
+    pub module_cache: bool,
+
+    /// The hex-encoded Ed25519 public keys of the trusted lapp publishers. When set, only the lapps signed by
+    /// one of the publishers are installed and instantiated.
+    pub publisher_keys: Vec<String>,
+
+    /// Install and instantiate the unsigned lapps even if the publisher keys are set.
+    pub allow_unsigned: bool,
+
+    /// The named sets of the permissions that are allowed to a lapp at once by the update query.
+    pub permission_profiles: BTreeMap<String, Vec<Permission>>,
 }
 
 impl Default for LappsSettings {
     fn default() -> Self {
         Self {
-            path: "lapps".into(),
+            path: legacy_or_default("lapps", || default_data_dir().join("lapps")),
             allowed: None,
+            read_only: false,
+            debug: false,
+            watch: false,
+            git: Vec::new(),
+            registry_url: None,
+            threads_pool_size: std::thread::available_parallelism().map_or(1, usize::from),
+            max_memory_mb: None,
+            call_time_limit_ms: Some(60_000),
+            disk_quota_mb: None,
+            max_body_mb: None,
+            http_timeout_ms: None,
+            quarantine_threshold: 10,
+            mount_paths: Vec::new(),
+            module_cache: true,
+            publisher_keys: Vec::new(),
+            allow_unsigned: false,
+            permission_profiles: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GitLappSettings {
+    /// The lapp name, the repository is deployed into the lapp directory with this name.
+    pub name: String,
+    pub url: String,
+
+    #[serde(default = "default_git_branch")]
+    pub branch: String,
+
+    /// The shell command to build the lapp, it runs in the repository root.
+    pub build: Option<String>,
+
+    /// The path of the lapp package (`.lar` or `.zip` file, or directory) relative to the repository root.
+    /// The repository root is deployed as the lapp directory by default.
+    pub package: Option<PathBuf>,
+
+    /// Check the branch for new commits periodically.
+    pub poll_interval_sec: Option<u64>,
+
+    /// The secret of the `/laplace/deploy/{lapp_name}` webhook, the webhook is disabled without it.
+    pub webhook_secret: Option<String>,
+}
+
+fn default_git_branch() -> String {
+    "main".into()
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReplicationSettings {
+    /// Expose lapps data changes feeds and allow pull/push replication with other Laplace instances.
+    pub enabled: bool,
+
+    #[serde(default = "default_replication_batch_size")]
+    pub batch_size: usize,
+}
+
+impl Default for ReplicationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            batch_size: default_replication_batch_size(),
+        }
+    }
+}
+
+const fn default_replication_batch_size() -> usize {
+    100
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GraphqlSettings {
+    /// Serve the `/graphql` gateway over the schema fragments of lapps with the `graphql` permission.
+    pub enabled: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MqttSettings {
+    /// Connect to the MQTT broker and bridge its messages to lapps with the `mqtt` permission.
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub keep_alive_sec: u64,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".into(),
+            port: 1883,
+            client_id: "laplace".into(),
+            username: None,
+            password: None,
+            keep_alive_sec: 30,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// The TLS connection from the start, usually on the port 465.
+    Tls,
+
+    /// The plain connection upgraded to TLS, usually on the port 587.
+    #[default]
+    Starttls,
+
+    /// The unencrypted connection, e.g. to the local relay.
+    None,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SmtpSettings {
+    /// Send the mails of the lapps with the `mail` permission by the SMTP relay.
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<String>,
+
+    /// The sender address of the lapp mails, e.g. `Laplace <laplace@example.com>`.
+    pub from: String,
+}
+
+impl Default for SmtpSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".into(),
+            port: 587,
+            tls: SmtpTls::default(),
+            username: None,
+            password: None,
+            from: "laplace@localhost".into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PathsSettings {
+    /// Directory for the compiled wasm modules cache.
+    pub cache: PathBuf,
+
+    /// Directory for the runtime state: generated access token, daemon logs.
+    pub state: PathBuf,
+}
+
+impl Default for PathsSettings {
+    fn default() -> Self {
+        Self {
+            cache: default_cache_dir(),
+            state: default_state_dir(),
         }
     }
 }
@@ -117,17 +453,41 @@ impl Default for LappsSettings {
 #[derive(Default, Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct Settings {
+    pub paths: PathsSettings,
     pub http: HttpSettings,
     pub ssl: SslSettings,
     pub p2p: P2pSettings,
     pub log: LoggerSettings,
+    pub access_log: AccessLogSettings,
     pub lapps: LappsSettings,
+    pub replication: ReplicationSettings,
+    pub graphql: GraphqlSettings,
+    pub mqtt: MqttSettings,
+    pub smtp: SmtpSettings,
+
+    /// The path of the loaded config file, the settings editor writes the changes to it.
+    #[serde(skip)]
+    pub config_path: Option<PathBuf>,
 }
 
 impl Settings {
     pub fn new(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::load(File::from(path.as_ref())).map(|settings| settings.with_config_path(path))
+    }
+
+    /// Loads settings from the config file if it exists, otherwise uses the default settings.
+    pub fn new_or_default(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::load(File::from(path.as_ref()).required(false)).map(|settings| settings.with_config_path(path))
+    }
+
+    fn with_config_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.config_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    fn load(file: File<FileSourceFile, FileFormat>) -> Result<Self, ConfigError> {
         let config = Config::builder()
-            .add_source(File::from(path.as_ref()))
+            .add_source(file)
             // Add in settings from the environment (with a prefix of LAPLACE)
             // Eg.. `LAPLACE__HTTP__PORT=8090 laplace_server` would set the `http.port` key
             .add_source(
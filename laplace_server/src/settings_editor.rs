@@ -0,0 +1,149 @@
+//! Editing of the server settings from the admin UI.
+//!
+//! The hot settings are validated and written to the config file, then the config reload reads the file
+//! and applies them to the running server. The other settings are shown read-only and take effect after restart.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::{fs, io};
+
+use flexi_logger::LogSpecification;
+use laplace_common::api::{HotSettings, ServerSettings, SettingsValidation};
+use serde_json::Value;
+use toml_edit::{table, value, Document, Item};
+
+use crate::error::{ServerError, ServerResult};
+use crate::lapps::LappsProvider;
+use crate::settings::Settings;
+
+/// The config keys which values are not shown in the admin UI.
+const SECRET_KEYS: &[&str] = &["access_token", "webhook_secret", "password"];
+
+const REDACTED: &str = "********";
+
+#[derive(Clone)]
+pub struct SettingsEditor {
+    lapps_provider: LappsProvider,
+    config_path: Option<PathBuf>,
+    current: Arc<Mutex<ServerSettings>>,
+}
+
+impl SettingsEditor {
+    pub fn new(lapps_provider: LappsProvider, settings: &Settings) -> Self {
+        Self {
+            lapps_provider,
+            config_path: settings.config_path.clone(),
+            current: Arc::new(Mutex::new(server_settings(settings))),
+        }
+    }
+
+    pub fn settings(&self) -> ServerSettings {
+        self.current.lock().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+
+    pub fn validate(&self, hot: &HotSettings) -> SettingsValidation {
+        let mut validation = SettingsValidation::default();
+        if self.config_path.is_none() {
+            validation
+                .errors
+                .push("the server is started without a config file".into());
+        }
+        if let Err(err) = LogSpecification::parse(&hot.log_spec) {
+            validation
+                .errors
+                .push(format!("wrong log spec '{}': {err}", hot.log_spec));
+        }
+
+        validation
+    }
+
+    /// Writes the hot settings to the config file, keeping the other content and comments of the file.
+    pub fn save(&self, hot: &HotSettings) -> ServerResult<()> {
+        let validation = self.validate(hot);
+        if !validation.is_valid() {
+            return Err(ServerError::SettingsInvalid(validation.errors.join("; ")));
+        }
+        let config_path = self.config_path()?;
+
+        let mut config: Document = match fs::read_to_string(config_path) {
+            Ok(content) => content
+                .parse()
+                .map_err(|err| ServerError::SettingsError(format!("{err}")))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Document::new(),
+            Err(err) => return Err(err.into()),
+        };
+        set_config_item(&mut config, "log", "spec", value(hot.log_spec.as_str()))?;
+        set_config_item(&mut config, "lapps", "read_only", value(hot.read_only))?;
+
+        fs::write(config_path, config.to_string())?;
+        Ok(())
+    }
+
+    /// Reads the config file and applies the hot settings.
+    pub async fn reload(&self) -> ServerResult<ServerSettings> {
+        let settings = Settings::new(self.config_path()?).map_err(|err| ServerError::SettingsError(err.to_string()))?;
+        let server_settings = server_settings(&settings);
+        let hot = &server_settings.hot;
+
+        if let Some(logger) = crate::LOGGER.get() {
+            logger
+                .parse_new_spec(&hot.log_spec)
+                .map_err(|err| ServerError::SettingsError(err.to_string()))?;
+        }
+        self.lapps_provider.write_manager().await.set_read_only(hot.read_only);
+        log::info!("Config is reloaded: {hot:?}");
+
+        *self.current.lock().unwrap_or_else(|err| err.into_inner()) = server_settings.clone();
+        Ok(server_settings)
+    }
+
+    fn config_path(&self) -> ServerResult<&PathBuf> {
+        self.config_path
+            .as_ref()
+            .ok_or_else(|| ServerError::SettingsError("the server is started without a config file".into()))
+    }
+}
+
+fn server_settings(settings: &Settings) -> ServerSettings {
+    let mut config = serde_json::to_value(settings).unwrap_or_default();
+    redact_secrets(&mut config);
+
+    ServerSettings {
+        config_path: settings.config_path.as_ref().map(|path| path.display().to_string()),
+        config,
+        hot: HotSettings {
+            log_spec: settings.log.spec.clone(),
+            read_only: settings.lapps.read_only,
+        },
+    }
+}
+
+fn redact_secrets(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                if SECRET_KEYS.contains(&key.as_str()) && !value.is_null() {
+                    *value = REDACTED.into();
+                } else {
+                    redact_secrets(value);
+                }
+            }
+        },
+        Value::Array(values) => values.iter_mut().for_each(redact_secrets),
+        _ => {},
+    }
+}
+
+fn set_config_item(config: &mut Document, section: &str, key: &str, item: Item) -> ServerResult<()> {
+    if !config.contains_key(section) {
+        config[section] = table();
+    }
+
+    match config[section].as_table_like_mut() {
+        Some(section) => {
+            section.insert(key, item);
+            Ok(())
+        },
+        None => Err(ServerError::SettingsError(format!("config '{section}' is not a table"))),
+    }
+}
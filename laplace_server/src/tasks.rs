@@ -0,0 +1,192 @@
+//! Registry of the long-running server operations.
+//!
+//! Every change of a task is broadcast to the subscribers, the admin UI receives them by the websocket.
+//! Cancellation is cooperative: the operation checks the cancel flag between its steps.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use laplace_common::api::{TaskInfo, TaskKind, TaskState};
+use tokio::sync::broadcast;
+
+use crate::error::{ServerError, ServerResult};
+
+/// The number of the finished tasks kept to show them in the admin UI.
+const MAX_FINISHED_TASKS: usize = 20;
+
+const EVENTS_CAPACITY: usize = 256;
+
+#[derive(Clone)]
+pub struct Tasks {
+    inner: Arc<Mutex<TasksInner>>,
+    events: broadcast::Sender<TaskInfo>,
+}
+
+#[derive(Default)]
+struct TasksInner {
+    next_id: u64,
+    tasks: BTreeMap<u64, TaskEntry>,
+}
+
+struct TaskEntry {
+    info: TaskInfo,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Default for Tasks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tasks {
+    pub fn new() -> Self {
+        Self {
+            inner: Default::default(),
+            events: broadcast::channel(EVENTS_CAPACITY).0,
+        }
+    }
+
+    pub fn start(&self, kind: TaskKind, lapp_name: impl Into<String>, cancellable: bool) -> TaskHandle {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut inner = self.lock();
+        let id = inner.next_id;
+        inner.next_id += 1;
+
+        let info = TaskInfo {
+            id,
+            kind,
+            lapp_name: lapp_name.into(),
+            progress: None,
+            state: TaskState::Running,
+            message: None,
+            cancellable,
+        };
+        inner.tasks.insert(id, TaskEntry {
+            info: info.clone(),
+            cancelled: cancelled.clone(),
+        });
+        drop(inner);
+
+        self.events.send(info).ok();
+        TaskHandle {
+            id,
+            tasks: self.clone(),
+            cancelled,
+        }
+    }
+
+    pub fn list(&self) -> Vec<TaskInfo> {
+        self.lock().tasks.values().map(|task| task.info.clone()).collect()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskInfo> {
+        self.events.subscribe()
+    }
+
+    pub fn cancel(&self, id: u64) -> ServerResult<()> {
+        let inner = self.lock();
+        let task = inner.tasks.get(&id).ok_or(ServerError::TaskNotFound(id))?;
+        if !task.info.cancellable || task.info.state.is_finished() {
+            return Err(ServerError::TaskNotCancellable(id));
+        }
+
+        task.cancelled.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn update(&self, id: u64, update: impl FnOnce(&mut TaskInfo) -> bool) {
+        let mut inner = self.lock();
+        let Some(task) = inner.tasks.get_mut(&id) else {
+            return;
+        };
+        if !update(&mut task.info) {
+            return;
+        }
+
+        let info = task.info.clone();
+        if info.state.is_finished() {
+            let finished: Vec<_> = inner
+                .tasks
+                .iter()
+                .filter(|(_, task)| task.info.state.is_finished())
+                .map(|(&id, _)| id)
+                .collect();
+            for id in finished.iter().take(finished.len().saturating_sub(MAX_FINISHED_TASKS)) {
+                inner.tasks.remove(id);
+            }
+        }
+        drop(inner);
+
+        self.events.send(info).ok();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TasksInner> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// The handle of the running task. The task is marked as failed if the handle is dropped before it is finished.
+pub struct TaskHandle {
+    id: u64,
+    tasks: Tasks,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl TaskHandle {
+    /// Sets the completed part of the task, the changes less than 1% are not sent to the subscribers.
+    pub fn set_progress(&self, progress: f32) {
+        let progress = progress.clamp(0.0, 1.0);
+        self.tasks.update(self.id, |info| {
+            let is_changed = info
+                .progress
+                .map(|current| (progress - current).abs() >= 0.01 || progress == 1.0 && current != 1.0)
+                .unwrap_or(true);
+            if is_changed {
+                info.progress = Some(progress);
+            }
+            is_changed
+        });
+    }
+
+    pub fn check_cancelled(&self) -> ServerResult<()> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            Err(ServerError::TaskCancelled(self.id))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn finish<T>(self, result: ServerResult<T>) -> ServerResult<T> {
+        let (state, message) = match &result {
+            Ok(_) => (TaskState::Completed, None),
+            Err(ServerError::TaskCancelled(_)) => (TaskState::Cancelled, None),
+            Err(err) => (TaskState::Failed, Some(err.to_string())),
+        };
+        self.set_state(state, message);
+
+        result
+    }
+
+    fn set_state(&self, state: TaskState, message: Option<String>) {
+        self.tasks.update(self.id, |info| {
+            if info.state.is_finished() {
+                return false;
+            }
+
+            if state == TaskState::Completed {
+                info.progress = Some(1.0);
+            }
+            info.state = state;
+            info.message = message;
+            true
+        });
+    }
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        self.set_state(TaskState::Failed, Some("the task is interrupted".into()));
+    }
+}
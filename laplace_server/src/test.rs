@@ -0,0 +1,194 @@
+//! In-process Laplace server for the integration tests of Laplace and lapps.
+//!
+//! The server runs on a free local port with the temporary lapps, state and cache directories and the fixed
+//! access token. Fixture lapps (directories with `config.toml`, the server module and static files) are
+//! installed before the start.
+
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use reqwest::header::{self, HeaderMap, HeaderValue};
+use reqwest::Client;
+use tempfile::TempDir;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::error::{AppResult, ServerError, ServerResult};
+use crate::lapps::Lapp;
+use crate::settings::Settings;
+
+/// The access token of the admin UI and API of the test server.
+pub const TEST_ACCESS_TOKEN: &str = "laplace-test-access-token";
+
+type Configure = Box<dyn FnOnce(&mut Settings) + Send>;
+
+#[derive(Default)]
+pub struct TestServerBuilder {
+    lapps: Vec<(String, PathBuf)>,
+    server_modules: Vec<(String, PathBuf)>,
+    configure: Vec<Configure>,
+}
+
+impl TestServerBuilder {
+    /// Installs the fixture lapp from the directory before the server start.
+    pub fn lapp(mut self, lapp_name: impl Into<String>, source_dir: impl Into<PathBuf>) -> Self {
+        self.lapps.push((lapp_name.into(), source_dir.into()));
+        self
+    }
+
+    /// Installs the built server module of the fixture lapp, which sources are not built by the server.
+    pub fn lapp_server_module(mut self, lapp_name: impl Into<String>, module_path: impl Into<PathBuf>) -> Self {
+        self.server_modules.push((lapp_name.into(), module_path.into()));
+        self
+    }
+
+    /// Changes the test server settings, the temporary directories and the HTTP address are already set.
+    pub fn settings(mut self, configure: impl FnOnce(&mut Settings) + Send + 'static) -> Self {
+        self.configure.push(Box::new(configure));
+        self
+    }
+
+    pub async fn start(self) -> AppResult<TestServer> {
+        let root_dir = TempDir::new()?;
+
+        let mut settings = Settings::default();
+        settings.http.host = "127.0.0.1".into();
+        settings.http.port = 0;
+        settings.http.access_token = Some(TEST_ACCESS_TOKEN.into());
+        settings.http.print_url = false;
+        settings.lapps.path = root_dir.path().join("lapps");
+        settings.paths.state = root_dir.path().join("state");
+        settings.paths.cache = root_dir.path().join("cache");
+        for configure in self.configure {
+            configure(&mut settings);
+        }
+
+        fs::create_dir_all(&settings.lapps.path)?;
+        for (lapp_name, source_dir) in &self.lapps {
+            install_lapp(&settings.lapps.path, lapp_name, source_dir)?;
+        }
+        for (lapp_name, module_path) in &self.server_modules {
+            let lapp_dir = settings.lapps.path.join(lapp_name);
+            fs::create_dir_all(&lapp_dir)?;
+            fs::copy(module_path, lapp_dir.join(format!("{lapp_name}_server.wasm")))?;
+        }
+
+        let http_listener = TcpListener::bind((settings.http.host.as_str(), settings.http.port))?;
+        let addr = http_listener.local_addr()?;
+        let lapps_path = settings.lapps.path.clone();
+
+        let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
+        let server = tokio::spawn(crate::run_on_listener(settings, http_listener, async {
+            shutdown_receiver.await.ok();
+        }));
+
+        Ok(TestServer {
+            addr,
+            lapps_path,
+            shutdown: Some(shutdown_sender),
+            server,
+            _root_dir: root_dir,
+        })
+    }
+}
+
+pub struct TestServer {
+    addr: SocketAddr,
+    lapps_path: PathBuf,
+    shutdown: Option<oneshot::Sender<()>>,
+    server: JoinHandle<AppResult<()>>,
+    _root_dir: TempDir,
+}
+
+impl TestServer {
+    pub fn builder() -> TestServerBuilder {
+        TestServerBuilder::default()
+    }
+
+    pub async fn start() -> AppResult<Self> {
+        Self::builder().start().await
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn lapps_path(&self) -> &Path {
+        &self.lapps_path
+    }
+
+    pub fn url(&self, path: impl AsRef<str>) -> String {
+        format!("http://{}{}", self.addr, path.as_ref())
+    }
+
+    pub fn ws_url(&self, path: impl AsRef<str>) -> String {
+        format!("ws://{}{}", self.addr, path.as_ref())
+    }
+
+    /// The HTTP client authorized to the admin UI and API.
+    pub fn client(&self) -> Client {
+        client_with_access_token(TEST_ACCESS_TOKEN)
+    }
+
+    /// The HTTP client authorized to the lapp with its access token.
+    pub fn lapp_client(&self, lapp_name: &str) -> ServerResult<Client> {
+        Ok(client_with_access_token(&self.lapp_access_token(lapp_name)?))
+    }
+
+    pub fn lapp_access_token(&self, lapp_name: &str) -> ServerResult<String> {
+        let settings = Lapp::load_settings(lapp_name, self.lapps_path.join(lapp_name))
+            .ok_or_else(|| ServerError::LappNotFound(lapp_name.into()))?;
+        Ok(settings.application.access_token.unwrap_or_default())
+    }
+
+    /// Gracefully stops the server and returns the server run result.
+    pub async fn stop(mut self) -> AppResult<()> {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+        (&mut self.server)
+            .await
+            .unwrap_or_else(|err| panic!("Test server task failed: {err}"))
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+/// Copies the fixture lapp directory into the lapps directory.
+pub fn install_lapp(lapps_path: impl AsRef<Path>, lapp_name: &str, source_dir: impl AsRef<Path>) -> io::Result<()> {
+    copy_dir(source_dir.as_ref(), &lapps_path.as_ref().join(lapp_name))
+}
+
+fn copy_dir(source: &Path, target: &Path) -> io::Result<()> {
+    fs::create_dir_all(target)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let target = target.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+fn client_with_access_token(access_token: &str) -> Client {
+    let mut headers = HeaderMap::new();
+    if let Ok(cookie) = HeaderValue::from_str(&format!("access_token={access_token}")) {
+        headers.insert(header::COOKIE, cookie);
+    }
+
+    Client::builder()
+        .default_headers(headers)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Test HTTP client should be built")
+}
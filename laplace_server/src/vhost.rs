@@ -0,0 +1,81 @@
+//! Serving of the lapps from their subdomains.
+//!
+//! When `http.lapps_domain` is set, e.g. to `example.com`, the request to `chat.example.com/api/send` is routed as the
+//! request to `/chat/api/send`, so every lapp gets its own origin with the isolated cookies and storage. The paths
+//! already starting with the lapp name, e.g. the absolute URLs of the lapp static files, are not prefixed again. The
+//! lapps are still served by their paths on the main host.
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::uri::PathAndQuery;
+use axum::http::{header, Request, Uri};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::lapps::Lapp;
+
+/// The lapp of the subdomain the request is routed from.
+#[derive(Debug, Clone)]
+pub struct LappHost(pub String);
+
+impl LappHost {
+    /// Returns the path of the request to the subdomain by the routed path.
+    pub fn host_path<'a>(&self, path: &'a str) -> &'a str {
+        match path.strip_prefix(&format!("/{}", self.0)) {
+            Some("") => "/",
+            Some(host_path) if host_path.starts_with('/') => host_path,
+            _ => path,
+        }
+    }
+}
+
+pub async fn route_lapp_host<B>(
+    State(lapps_domain): State<Option<Arc<str>>>,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let lapp_name = lapps_domain
+        .as_deref()
+        .and_then(|lapps_domain| lapp_subdomain(&request, lapps_domain));
+
+    if let Some(lapp_name) = lapp_name {
+        if let Some(uri) = lapp_uri(request.uri(), &lapp_name) {
+            *request.uri_mut() = uri;
+        }
+        request.extensions_mut().insert(LappHost(lapp_name));
+    }
+    next.run(request).await
+}
+
+/// Returns the lapp name of the request host, the main lapp is not served from the subdomain.
+fn lapp_subdomain<B>(request: &Request<B>, lapps_domain: &str) -> Option<String> {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .or_else(|| request.uri().host())?;
+    let host = host.rsplit_once(':').map_or(host, |(host, _port)| host);
+
+    let lapp_name = host
+        .strip_suffix(lapps_domain.trim_start_matches('.'))?
+        .strip_suffix('.')?
+        .to_ascii_lowercase();
+    (!lapp_name.is_empty() && !lapp_name.contains('.') && lapp_name != Lapp::main_name()).then_some(lapp_name)
+}
+
+fn lapp_uri(uri: &Uri, lapp_name: &str) -> Option<Uri> {
+    let prefix = format!("/{lapp_name}");
+    let path = uri.path();
+    if path == prefix || path.starts_with(&format!("{prefix}/")) {
+        return None;
+    }
+
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{prefix}{path}?{query}"),
+        None => format!("{prefix}{path}"),
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(PathAndQuery::try_from(path_and_query).ok()?);
+    Uri::from_parts(parts).ok()
+}
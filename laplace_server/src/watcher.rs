@@ -0,0 +1,134 @@
+//! Hot reload of the lapps for the local development.
+//!
+//! The watcher follows the lapps directory and reloads the lapp when its `{name}_server.wasm` module or config
+//! changes on disk: the settings are reloaded and the running lapp service is restarted with the new module.
+//! The changes are collected for a short time, so the module written by several operations reloads once.
+//! The config saved by the server itself with the unchanged settings does not reload the lapp.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+use crate::lapps::{FileSettings, Lapp, LappSettings, LappsProvider};
+
+/// The time to collect the changes before the reload.
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(300);
+
+pub struct LappsWatcher {
+    lapps_path: PathBuf,
+    watcher: RecommendedWatcher,
+    events: mpsc::UnboundedReceiver<Event>,
+}
+
+impl LappsWatcher {
+    pub fn new(lapps_path: impl Into<PathBuf>) -> notify::Result<Self> {
+        let lapps_path = lapps_path.into();
+        let (events_in, events) = mpsc::unbounded_channel();
+
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+            Ok(event) => {
+                events_in.send(event).ok();
+            },
+            Err(err) => log::warn!("Lapps watcher error: {err}"),
+        })?;
+        watcher.watch(&lapps_path, RecursiveMode::Recursive)?;
+
+        Ok(Self {
+            lapps_path,
+            watcher,
+            events,
+        })
+    }
+
+    pub fn run(self, lapps_provider: LappsProvider) {
+        let Self {
+            lapps_path,
+            watcher,
+            mut events,
+        } = self;
+
+        tokio::spawn(async move {
+            // The watcher stops when it is dropped
+            let _watcher = watcher;
+
+            while let Some(event) = events.recv().await {
+                let mut changed_lapps = HashMap::new();
+                collect_changed_lapps(&lapps_path, &event, &mut changed_lapps);
+
+                if changed_lapps.is_empty() {
+                    continue;
+                }
+
+                tokio::time::sleep(DEBOUNCE_DELAY).await;
+                while let Ok(event) = events.try_recv() {
+                    collect_changed_lapps(&lapps_path, &event, &mut changed_lapps);
+                }
+
+                for (lapp_name, is_module_changed) in changed_lapps {
+                    reload_lapp(&lapps_provider, &lapps_path, &lapp_name, is_module_changed).await;
+                }
+            }
+        });
+    }
+}
+
+/// Collects the names of the changed lapps with the flag of the server module change.
+fn collect_changed_lapps(lapps_path: &Path, event: &Event, changed_lapps: &mut HashMap<String, bool>) {
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        return;
+    }
+
+    for path in &event.paths {
+        let Ok(relative_path) = path.strip_prefix(lapps_path) else {
+            continue;
+        };
+
+        let components: Vec<_> = relative_path.components().collect();
+        let [Component::Normal(lapp_name), Component::Normal(file_name)] = components[..] else {
+            continue;
+        };
+        let (Some(lapp_name), Some(file_name)) = (lapp_name.to_str(), file_name.to_str()) else {
+            continue;
+        };
+
+        if Lapp::is_main(lapp_name) {
+            continue;
+        }
+
+        let is_module = file_name == format!("{lapp_name}_server.wasm");
+        if is_module || file_name == Lapp::config_file_name() {
+            *changed_lapps.entry(lapp_name.to_string()).or_default() |= is_module;
+        }
+    }
+}
+
+async fn reload_lapp(lapps_provider: &LappsProvider, lapps_path: &Path, lapp_name: &str, is_module_changed: bool) {
+    let mut manager = lapps_provider.write_manager().await;
+
+    if let (false, Ok(current)) = (is_module_changed, manager.lapp_settings(lapp_name)) {
+        let settings_path = Lapp::settings_path(lapps_path.join(lapp_name));
+        let is_settings_changed = match LappSettings::load(lapp_name, settings_path) {
+            Ok(settings) => toml::to_string(&settings).ok() != toml::to_string(current).ok(),
+            Err(_) => true,
+        };
+
+        if !is_settings_changed {
+            return;
+        }
+    }
+
+    let result = async {
+        let reload = manager.reload_lapp(lapp_name)?;
+        drop(manager);
+        reload.await
+    }
+    .await;
+
+    match result {
+        Ok(()) => log::info!("Lapp '{lapp_name}' is reloaded after the change on disk"),
+        Err(err) => log::error!("Reload lapp '{lapp_name}' error: {err}"),
+    }
+}
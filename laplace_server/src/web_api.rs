@@ -1,11 +1,27 @@
-use axum::http::StatusCode;
-use axum::Json;
+use std::convert::Infallible;
+
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::{async_trait, Json};
+use serde::Serialize;
 use serde_json::{json, Value};
 
 use crate::error::{ServerError, ServerResult};
+use crate::lapps::ResourceLimitExceeded;
 
+pub mod deploy;
+pub mod graphql;
+pub mod jobs;
 pub mod laplace;
 pub mod lapp;
+pub mod p2p;
+pub mod registry;
+pub mod replication;
+pub mod settings;
+pub mod tasks;
+pub mod webdav;
 
 pub type JsonErrResponse = (StatusCode, Json<Value>);
 pub type ResultResponse<T> = Result<T, JsonErrResponse>;
@@ -25,8 +41,100 @@ impl<T> IntoJsonResponse for ServerResult<T> {
 }
 
 pub fn err_into_json_response(err: ServerError) -> JsonErrResponse {
-    (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        Json(json!({ "error": err.to_string() })),
-    )
+    (err_status_code(&err), Json(json!({ "error": err.to_string() })))
+}
+
+pub fn err_status_code(err: &ServerError) -> StatusCode {
+    match err {
+        ServerError::ReadOnlyMode
+        | ServerError::SqlNotReadOnly
+        | ServerError::LappSignatureInvalid(..)
+        | ServerError::LappMountNotAllowed(..)
+        | ServerError::LappRestorePathNotAllowed(..) => StatusCode::FORBIDDEN,
+        ServerError::LappIconNotFound(_) | ServerError::LappFileNotFound(..) | ServerError::UserNotFound(_) => {
+            StatusCode::NOT_FOUND
+        },
+        ServerError::UserAlreadyExists(_)
+        | ServerError::LappAlreadyExists(_)
+        | ServerError::LappHasDependents(..)
+        | ServerError::LappDependenciesNotSatisfied(..)
+        | ServerError::LappHealthCheckFailed(..) => StatusCode::CONFLICT,
+        ServerError::LappPreviousVersionNotFound(_)
+        | ServerError::PermissionProfileNotFound(_)
+        | ServerError::RegistryLappNotFound(_)
+        | ServerError::RegistryVersionNotFound(..) => StatusCode::NOT_FOUND,
+        ServerError::RegistryError(_) => StatusCode::BAD_GATEWAY,
+        ServerError::SettingsInvalid(_)
+        | ServerError::DumpInvalid(_)
+        | ServerError::WrongUserName(_)
+        | ServerError::UnknownLappName
+        | ServerError::WrongLappPackage(..)
+        | ServerError::LappManifestInvalid(..) => StatusCode::BAD_REQUEST,
+        ServerError::LappRateLimitExceeded(..) => StatusCode::TOO_MANY_REQUESTS,
+        ServerError::LappRequestBodyTooLarge(..) => StatusCode::PAYLOAD_TOO_LARGE,
+        ServerError::LappProxyError(..) => StatusCode::BAD_GATEWAY,
+        ServerError::LappResourceLimit(ResourceLimitExceeded::HttpTimeout { .. }) => StatusCode::GATEWAY_TIMEOUT,
+        ServerError::LappResourceLimit(_) | ServerError::LappUnloading(_) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// The response body format negotiated by the `Accept` request header. JSON is used by default.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+impl ResponseFormat {
+    pub fn from_accept(accept: &str) -> Self {
+        let is_msgpack = accept.split(',').any(|media_type| {
+            let media_type = media_type.split(';').next().unwrap_or_default().trim();
+            media_type.eq_ignore_ascii_case(MSGPACK_CONTENT_TYPE)
+                || media_type.eq_ignore_ascii_case("application/x-msgpack")
+        });
+
+        if is_msgpack {
+            Self::MessagePack
+        } else {
+            Self::Json
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ResponseFormat {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .headers
+            .get(header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .map(Self::from_accept)
+            .unwrap_or_default())
+    }
+}
+
+/// The response that is serialized to the negotiated format.
+pub struct Negotiated<T>(pub ResponseFormat, pub T);
+
+impl<T: Serialize> IntoResponse for Negotiated<T> {
+    fn into_response(self) -> Response {
+        let Self(format, body) = self;
+        match format {
+            ResponseFormat::Json => Json(body).into_response(),
+            ResponseFormat::MessagePack => match rmp_serde::to_vec_named(&body) {
+                Ok(body) => ([(header::CONTENT_TYPE, MSGPACK_CONTENT_TYPE)], body).into_response(),
+                Err(err) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({ "error": format!("MessagePack serialization error: {err}") })),
+                )
+                    .into_response(),
+            },
+        }
+    }
 }
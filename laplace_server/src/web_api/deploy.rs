@@ -0,0 +1,19 @@
+use axum::body::Bytes;
+use axum::extract::Path;
+use axum::http::HeaderMap;
+use axum::routing::post;
+use axum::Router;
+
+use crate::deploy::Deployer;
+use crate::lapps::{Lapp, LappsProvider};
+
+pub mod handler;
+
+pub fn router(deployer: Deployer) -> Router<LappsProvider> {
+    Router::new().route(
+        &Lapp::main_uri("deploy/:lapp_name"),
+        post(move |path: Path<String>, headers: HeaderMap, body: Bytes| {
+            handler::webhook(deployer.clone(), path, headers, body)
+        }),
+    )
+}
@@ -0,0 +1,32 @@
+use axum::body::Bytes;
+use axum::extract::Path;
+use axum::http::{HeaderMap, StatusCode};
+
+use crate::deploy::{self, Deployer};
+use crate::error::ServerError;
+use crate::web_api::{err_into_json_response, ResultResponse};
+
+pub async fn webhook(
+    deployer: Deployer,
+    Path(lapp_name): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> ResultResponse<StatusCode> {
+    let git_lapp = deployer
+        .git_lapp(&lapp_name)
+        .cloned()
+        .ok_or_else(|| err_into_json_response(ServerError::LappNotFound(lapp_name)))?;
+
+    let is_verified = git_lapp
+        .webhook_secret
+        .as_deref()
+        .map(|secret| deploy::verify_webhook(secret, &headers, &body))
+        .unwrap_or(false);
+    if !is_verified {
+        log::warn!("Deploy webhook for lapp '{}' is not verified", git_lapp.name);
+        return Ok(StatusCode::FORBIDDEN);
+    }
+
+    tokio::spawn(async move { deployer.deploy_logged(&git_lapp).await });
+    Ok(StatusCode::ACCEPTED)
+}
@@ -0,0 +1,21 @@
+use axum::extract::State;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+
+use crate::graphql::GraphqlRequest;
+use crate::lapps::LappsProvider;
+
+pub mod handler;
+
+pub const GRAPHQL_PATH: &str = "graphql";
+
+pub fn router(laplace_access_token: &'static str) -> Router<LappsProvider> {
+    Router::new()
+        .route(
+            &format!("/{GRAPHQL_PATH}"),
+            post(move |state: State<LappsProvider>, request: Json<GraphqlRequest>| {
+                handler::execute(state, laplace_access_token, request)
+            }),
+        )
+        .route(&format!("/{GRAPHQL_PATH}/schema"), get(handler::get_schema))
+}
@@ -0,0 +1,18 @@
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::Json;
+
+use crate::graphql::{self, GraphqlRequest};
+use crate::lapps::LappsProvider;
+
+pub async fn execute(
+    State(lapps_provider): State<LappsProvider>,
+    laplace_access_token: &'static str,
+    Json(request): Json<GraphqlRequest>,
+) -> impl IntoResponse {
+    Json(graphql::execute(&lapps_provider, laplace_access_token, request).await)
+}
+
+pub async fn get_schema(State(lapps_provider): State<LappsProvider>) -> impl IntoResponse {
+    graphql::merged_schema(&lapps_provider).await
+}
@@ -0,0 +1,26 @@
+use axum::extract::Path;
+use axum::routing::{get, post};
+use axum::Router;
+
+use crate::lapps::{Lapp, LappsProvider};
+use crate::scheduler::Scheduler;
+use crate::web_api::ResponseFormat;
+
+pub mod handler;
+
+pub fn router(scheduler: Scheduler) -> Router<LappsProvider> {
+    Router::new()
+        .route(
+            &Lapp::main_uri("jobs"),
+            get({
+                let scheduler = scheduler.clone();
+                move |format: ResponseFormat| handler::get_jobs(format, scheduler.clone())
+            }),
+        )
+        .route(
+            &Lapp::main_uri("jobs/:lapp_name/:job_name/run"),
+            post(move |format: ResponseFormat, path: Path<(String, String)>| {
+                handler::run_job(format, scheduler.clone(), path)
+            }),
+        )
+}
@@ -0,0 +1,23 @@
+use axum::extract::Path;
+use axum::response::IntoResponse;
+use laplace_common::api::JobInfo;
+
+use crate::scheduler::Scheduler;
+use crate::web_api::{err_into_json_response, Negotiated, ResponseFormat, ResultResponse};
+
+pub async fn get_jobs(format: ResponseFormat, scheduler: Scheduler) -> impl IntoResponse {
+    Negotiated(format, scheduler.jobs().await)
+}
+
+pub async fn run_job(
+    format: ResponseFormat,
+    scheduler: Scheduler,
+    Path((lapp_name, job_name)): Path<(String, String)>,
+) -> ResultResponse<Negotiated<Vec<JobInfo>>> {
+    scheduler
+        .run_job(&lapp_name, &job_name)
+        .await
+        .map_err(err_into_json_response)?;
+
+    Ok(Negotiated(format, scheduler.jobs().await))
+}
@@ -1,10 +1,13 @@
 use std::path::PathBuf;
 
-use axum::routing::{get, post};
+use axum::routing::{delete, get, post};
 use axum::Router;
+use laplace_common::api::Info;
 use tower_http::services::{ServeDir, ServeFile};
 
 use crate::lapps::{Lapp, LappsProvider};
+use crate::web_api::ResponseFormat;
+use crate::VERSION;
 
 pub mod handler;
 
@@ -12,9 +15,14 @@ pub fn router(
     laplace_uri: &'static str,
     static_dir: impl Into<PathBuf>,
     lapps_dir: impl Into<PathBuf>,
+    laplace_url: impl Into<String>,
 ) -> Router<LappsProvider> {
     let static_dir = static_dir.into();
     let lapps_dir = lapps_dir.into();
+    let info = Info {
+        version: VERSION.into(),
+        url: laplace_url.into(),
+    };
 
     Router::new()
         .route_service(laplace_uri, ServeFile::new(static_dir.join(Lapp::index_file_name())))
@@ -22,7 +30,100 @@ pub fn router(
             &format!("{laplace_uri}/{}", Lapp::static_dir_name()),
             ServeDir::new(lapps_dir.join(Lapp::main_name()).join(Lapp::static_dir_name())),
         )
+        .route(
+            &format!("{laplace_uri}/info"),
+            get(move |format: ResponseFormat| handler::get_info(format, info.clone())),
+        )
         .route(&format!("{laplace_uri}/lapps"), get(handler::get_lapps))
+        .route(
+            &format!("{laplace_uri}/lapps/dependencies"),
+            get(handler::get_dependencies),
+        )
+        .route(&format!("{laplace_uri}/lapps/invalid"), get(handler::get_invalid_lapps))
+        .route(
+            &format!("{laplace_uri}/permission-profiles"),
+            get(handler::get_permission_profiles),
+        )
         .route(&format!("{laplace_uri}/lapp/add"), post(handler::add_lapp))
+        .route(&format!("{laplace_uri}/lapp/install"), post(handler::add_lapp))
         .route(&format!("{laplace_uri}/lapp/update"), post(handler::update_lapp))
+        .route(&format!("{laplace_uri}/lapp/restore"), post(handler::upload_backup))
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name"),
+            delete(handler::uninstall_lapp),
+        )
+        .route(&format!("{laplace_uri}/lapp/:lapp_name/icon"), get(handler::lapp_icon))
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/upgrade"),
+            post(handler::upload_upgrade),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/compare"),
+            get(handler::compare_upgrade),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/upgrade/confirm"),
+            post(handler::confirm_upgrade),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/upgrade/deploy"),
+            post(handler::deploy_upgrade),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/upgrade/cancel"),
+            post(handler::cancel_upgrade),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/duplicate"),
+            post(handler::duplicate_lapp),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/rollback"),
+            post(handler::rollback_lapp),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/disk"),
+            get(handler::get_disk_usage),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/migrations"),
+            get(handler::get_migrations),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/audit"),
+            get(handler::get_permission_audit),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/logs"),
+            get(handler::get_lapp_logs),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/reload-settings"),
+            post(handler::reload_settings),
+        )
+        .route(&format!("{laplace_uri}/lapp/:lapp_name/load"), post(handler::load_lapp))
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/unload"),
+            post(handler::unload_lapp),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/backup"),
+            get(handler::download_backup),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/db/backup"),
+            post(handler::backup_database),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/export"),
+            get(handler::export_database),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/import"),
+            post(handler::import_database),
+        )
+        .route(
+            &format!("{laplace_uri}/lapp/:lapp_name/sql"),
+            post(handler::query_database),
+        )
 }
@@ -1,18 +1,116 @@
-use std::io;
+use std::path::{Component, Path as FsPath};
+use std::{fs, io};
 
-use axum::extract::State;
+use axum::body::{Body, Bytes};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, Request};
 use axum::response::{IntoResponse, Response};
-use axum::Json;
+use axum::{Extension, Json};
 use axum_typed_multipart::{FieldData, TryFromMultipart, TypedMultipart};
+use laplace_common::api::{DatabaseBackup, Info, SqlFormat, SqlQuery, SqlQueryResult, TaskKind};
+use rusqlite::Connection;
+use serde::Deserialize;
 use tempfile::NamedTempFile;
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
 use zip::ZipArchive;
 
+use crate::auth::middleware::Actor;
+use crate::dump;
 use crate::error::{ServerError, ServerResult};
-use crate::lapps::{CommonLappGuard, CommonLappResponse, Lapp, LappUpdateRequest, LappsProvider};
-use crate::web_api::err_into_json_response;
+use crate::lapps::{
+    backup_database, backup_lapp, extract_package, package_settings, restore_lapp, CommonLappGuard, CommonLappResponse,
+    Lapp, LappUpdateRequest, LappsProvider, Permission,
+};
+use crate::tasks::TaskHandle;
+use crate::web_api::graphql::GRAPHQL_PATH;
+use crate::web_api::{err_into_json_response, Negotiated, ResponseFormat};
 
-pub async fn get_lapps(State(lapps_provider): State<LappsProvider>) -> impl IntoResponse {
-    process_get_lapps(lapps_provider).await.map_err(err_into_json_response)
+/// The top-level paths of the server routes, the lapps with these names would be shadowed or bypass the access check.
+const RESERVED_LAPP_NAMES: &[&str] = &[GRAPHQL_PATH, "static", "favicon.ico"];
+
+pub async fn get_info(format: ResponseFormat, info: Info) -> impl IntoResponse {
+    Negotiated(format, info)
+}
+
+pub async fn get_lapps(format: ResponseFormat, State(lapps_provider): State<LappsProvider>) -> impl IntoResponse {
+    process_get_lapps(lapps_provider, format)
+        .await
+        .map_err(err_into_json_response)
+}
+
+pub async fn get_dependencies(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+) -> impl IntoResponse {
+    Negotiated(format, lapps_provider.read_manager().await.dependency_graph())
+}
+
+pub async fn get_disk_usage(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    lapps_provider
+        .read_manager()
+        .await
+        .lapp_disk_usage(lapp_name)
+        .map(|disk_usage| Negotiated(format, disk_usage))
+        .map_err(err_into_json_response)
+}
+
+pub async fn get_migrations(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    lapps_provider
+        .read_manager()
+        .await
+        .lapp_migrations(lapp_name)
+        .map(|migrations| Negotiated(format, migrations))
+        .map_err(err_into_json_response)
+}
+
+pub async fn get_permission_profiles(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+) -> impl IntoResponse {
+    let profiles = lapps_provider.read_manager().await.permission_profiles().clone();
+    Negotiated(format, profiles)
+}
+
+pub async fn get_permission_audit(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    lapps_provider
+        .read_manager()
+        .await
+        .permission_audit(&lapp_name)
+        .map(|entries| Negotiated(format, entries))
+        .map_err(err_into_json_response)
+}
+
+pub async fn get_lapp_logs(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    lapps_provider
+        .read_manager()
+        .await
+        .lapp_logs(&lapp_name)
+        .map(|records| Negotiated(format, records))
+        .map_err(err_into_json_response)
+}
+
+pub async fn get_invalid_lapps(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+) -> impl IntoResponse {
+    Negotiated(format, lapps_provider.read_manager().await.invalid_lapps())
 }
 
 #[derive(TryFromMultipart)]
@@ -23,24 +121,421 @@ pub struct LarUpload {
 }
 
 pub async fn add_lapp(
+    format: ResponseFormat,
     State(lapps_provider): State<LappsProvider>,
     TypedMultipart(form): TypedMultipart<LarUpload>,
 ) -> impl IntoResponse {
-    process_add_lapp(lapps_provider, form.lar)
+    process_add_lapp(lapps_provider, form.lar, format)
+        .await
+        .map_err(err_into_json_response)
+}
+
+#[derive(TryFromMultipart)]
+pub struct BackupUpload {
+    #[form_data(limit = "unlimited")]
+    pub backup: FieldData<NamedTempFile>,
+}
+
+pub async fn upload_backup(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    TypedMultipart(form): TypedMultipart<BackupUpload>,
+) -> impl IntoResponse {
+    process_restore_lapp(lapps_provider, form.backup, format)
         .await
         .map_err(err_into_json_response)
 }
 
+#[derive(Debug, Deserialize)]
+pub struct UninstallQuery {
+    /// Remove the lapp data directory and database too.
+    #[serde(default)]
+    pub purge: bool,
+}
+
+pub async fn uninstall_lapp(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+    Query(query): Query<UninstallQuery>,
+) -> impl IntoResponse {
+    lapps_provider
+        .handle(move |lapps_provider| async move {
+            lapps_provider
+                .write_manager()
+                .await
+                .uninstall_lapp(&lapp_name, query.purge)
+                .await?;
+            process_get_lapps(lapps_provider, format).await
+        })
+        .await
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DuplicateQuery {
+    /// The name of the duplicate lapp.
+    pub name: String,
+}
+
+pub async fn duplicate_lapp(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+    Query(query): Query<DuplicateQuery>,
+) -> impl IntoResponse {
+    lapps_provider
+        .handle(move |lapps_provider| async move {
+            check_lapp_name(&query.name)?;
+            lapps_provider
+                .write_manager()
+                .await
+                .duplicate_lapp(&lapp_name, query.name)?;
+            process_get_lapps(lapps_provider, format).await
+        })
+        .await
+}
+
+pub async fn lapp_icon(
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+    request: Request<Body>,
+) -> impl IntoResponse {
+    lapps_provider
+        .handle(move |lapps_provider| async move {
+            let manager = lapps_provider.read_manager().await;
+            let icon = manager
+                .lapp_settings(&lapp_name)?
+                .application
+                .icon
+                .clone()
+                .filter(|icon| {
+                    icon.components()
+                        .all(|component| matches!(component, Component::Normal(_)))
+                })
+                .ok_or_else(|| ServerError::LappIconNotFound(lapp_name.clone()))?;
+            let icon_path = manager.lapp_dir(&lapp_name).static_dir().join(icon);
+            drop(manager);
+
+            Ok(ServeFile::new(icon_path)
+                .oneshot(request)
+                .await
+                .expect("Infallible call"))
+        })
+        .await
+}
+
 pub async fn update_lapp(
+    format: ResponseFormat,
     State(lapps_provider): State<LappsProvider>,
+    actor: Option<Extension<Actor>>,
     Json(update_request): Json<LappUpdateRequest>,
 ) -> impl IntoResponse {
-    process_update_lapp(lapps_provider, update_request)
+    let actor = actor.map_or_else(|| Lapp::main_name().into(), |Extension(Actor(actor))| actor);
+    process_update_lapp(lapps_provider, update_request, actor, format)
+        .await
+        .map_err(err_into_json_response)
+}
+
+pub async fn upload_upgrade(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+    TypedMultipart(form): TypedMultipart<LarUpload>,
+) -> impl IntoResponse {
+    lapps_provider
+        .write_manager()
+        .await
+        .stage_upgrade(lapp_name, form.lar.contents)
+        .map(|upgrade| Negotiated(format, CommonLappResponse::Upgrade { upgrade }))
+        .map_err(err_into_json_response)
+}
+
+pub async fn compare_upgrade(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    lapps_provider
+        .read_manager()
+        .await
+        .compare_upgrade(lapp_name)
+        .map(|upgrade| Negotiated(format, CommonLappResponse::Upgrade { upgrade }))
+        .map_err(err_into_json_response)
+}
+
+pub async fn confirm_upgrade(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    process_confirm_upgrade(lapps_provider, lapp_name, format)
+        .await
+        .map_err(err_into_json_response)
+}
+
+pub async fn deploy_upgrade(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    process_deploy_upgrade(lapps_provider, lapp_name, format)
+        .await
+        .map_err(err_into_json_response)
+}
+
+pub async fn cancel_upgrade(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    process_cancel_upgrade(lapps_provider, lapp_name, format)
+        .await
+        .map_err(err_into_json_response)
+}
+
+pub async fn rollback_lapp(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    process_rollback_lapp(lapps_provider, lapp_name, format)
+        .await
+        .map_err(err_into_json_response)
+}
+
+pub async fn reload_settings(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    lapps_provider
+        .handle(move |lapps_provider| async move {
+            let (reload, reinstantiate) = lapps_provider.write_manager().await.reload_lapp_settings(lapp_name)?;
+            reinstantiate.await?;
+            Ok(Negotiated(format, reload))
+        })
+        .await
+}
+
+pub async fn load_lapp(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    process_load_lapp(lapps_provider, lapp_name, format)
         .await
         .map_err(err_into_json_response)
 }
 
-async fn process_get_lapps(lapps_provider: LappsProvider) -> ServerResult<Response> {
+pub async fn unload_lapp(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    process_unload_lapp(lapps_provider, lapp_name, format)
+        .await
+        .map_err(err_into_json_response)
+}
+
+pub async fn download_backup(
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    lapps_provider
+        .handle(move |lapps_provider| async move {
+            let manager = lapps_provider.read_manager().await;
+            let settings = manager.lapp_settings(&lapp_name)?.clone();
+            let lapp_dir = manager.lapp_dir(&lapp_name);
+            drop(manager);
+
+            let body = tokio::task::spawn_blocking(move || {
+                let mut body = io::Cursor::new(Vec::new());
+                backup_lapp(&lapp_dir, &settings, &mut body).map(|()| body)
+            })
+            .await
+            .map_err(io::Error::other)??;
+
+            Ok((
+                [
+                    (header::CONTENT_TYPE, "application/zip".to_string()),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        format!(r#"attachment; filename="{lapp_name}.backup.zip""#),
+                    ),
+                ],
+                body.into_inner(),
+            ))
+        })
+        .await
+}
+
+pub async fn export_database(
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    lapps_provider
+        .handle_allowed(
+            &[Permission::Database],
+            lapp_name,
+            move |lapps_provider, lapp_name| async move {
+                let database_path = lapps_provider.read_manager().await.lapp_database_path(&lapp_name)?;
+                let connection = Connection::open(database_path)?;
+
+                let mut body = Vec::new();
+                dump::export(&connection, &mut body)?;
+
+                Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], body))
+            },
+        )
+        .await
+}
+
+pub async fn backup_database(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+) -> impl IntoResponse {
+    lapps_provider
+        .handle_allowed(
+            &[Permission::Database],
+            lapp_name,
+            move |lapps_provider, lapp_name| async move {
+                let manager = lapps_provider.read_manager().await;
+                manager.check_writable()?;
+                let database_path = manager.lapp_database_path(&lapp_name)?;
+                let backup_dir = manager.lapp_database_backup_dir(&lapp_name)?;
+                drop(manager);
+
+                let backup_path = tokio::task::spawn_blocking(move || backup_database(&database_path, &backup_dir))
+                    .await
+                    .map_err(|err| ServerError::DatabaseBackupError(err.to_string()))??;
+                let backup = DatabaseBackup {
+                    lapp_name,
+                    file_name: backup_path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    size_bytes: fs::metadata(&backup_path)?.len(),
+                };
+                Ok(Negotiated(format, backup))
+            },
+        )
+        .await
+}
+
+pub async fn import_database(
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+    body: Bytes,
+) -> impl IntoResponse {
+    lapps_provider
+        .handle_allowed(
+            &[Permission::Database],
+            lapp_name,
+            move |lapps_provider, lapp_name| async move {
+                let manager = lapps_provider.read_manager().await;
+                manager.check_writable()?;
+                let database_path = manager.lapp_database_path(&lapp_name)?;
+                let task = manager.tasks().start(TaskKind::Import, &lapp_name, true);
+                drop(manager);
+
+                let result = Connection::open(database_path)
+                    .map_err(Into::into)
+                    .and_then(|mut connection| {
+                        dump::import_with_progress(&mut connection, body.as_ref(), |read_bytes| {
+                            task.set_progress(read_bytes as f32 / body.len().max(1) as f32);
+                            task.check_cancelled()
+                        })
+                    });
+                task.finish(result).map(Json)
+            },
+        )
+        .await
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SqlFormatQuery {
+    #[serde(default)]
+    pub format: SqlFormat,
+}
+
+pub async fn query_database(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+    Query(query): Query<SqlFormatQuery>,
+    Json(sql_query): Json<SqlQuery>,
+) -> impl IntoResponse {
+    lapps_provider
+        .handle_allowed(
+            &[Permission::Database],
+            lapp_name,
+            move |lapps_provider, lapp_name| async move {
+                let manager = lapps_provider.read_manager().await;
+                let database_path = manager.lapp_database_path(&lapp_name)?;
+                let connection = Connection::open(database_path)?;
+                let mut stmt = connection.prepare(&sql_query.sql)?;
+                if !stmt.readonly() {
+                    if sql_query.read_only {
+                        return Err(ServerError::SqlNotReadOnly);
+                    }
+                    manager.check_writable()?;
+                }
+                drop(manager);
+
+                let result = process_query_database(&mut stmt)?;
+                match query.format {
+                    SqlFormat::Json => Ok(Negotiated(format, result).into_response()),
+                    SqlFormat::Csv => Ok((
+                        [
+                            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                            (
+                                header::CONTENT_DISPOSITION,
+                                format!(r#"attachment; filename="{lapp_name}.csv""#),
+                            ),
+                        ],
+                        query_result_to_csv(&result)?,
+                    )
+                        .into_response()),
+                }
+            },
+        )
+        .await
+}
+
+fn process_query_database(stmt: &mut rusqlite::Statement<'_>) -> ServerResult<SqlQueryResult> {
+    let columns: Vec<String> = stmt.column_names().into_iter().map(Into::into).collect();
+    let mut result = SqlQueryResult {
+        columns,
+        rows: Vec::new(),
+    };
+
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let values = (0..result.columns.len())
+            .map(|idx| row.get(idx).map(dump::to_json_value))
+            .collect::<Result<_, _>>()?;
+        result.rows.push(values);
+    }
+
+    Ok(result)
+}
+
+fn query_result_to_csv(result: &SqlQueryResult) -> ServerResult<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(&result.columns)?;
+    for row in &result.rows {
+        writer.write_record(row.iter().map(|value| match value {
+            serde_json::Value::Null => String::new(),
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        }))?;
+    }
+
+    writer.into_inner().map_err(|err| err.into_error().into())
+}
+
+pub async fn process_get_lapps(lapps_provider: LappsProvider, format: ResponseFormat) -> ServerResult<Response> {
     let manager = lapps_provider.read_manager().await;
 
     let mut lapps = Vec::new();
@@ -51,51 +546,248 @@ async fn process_get_lapps(lapps_provider: LappsProvider) -> ServerResult<Respon
     }
     lapps.sort_unstable_by(|lapp_a, lapp_b| lapp_a.name().cmp(lapp_b.name()));
 
-    Ok(Json(CommonLappResponse::lapps(lapps)).into_response())
+    Ok(Negotiated(format, CommonLappResponse::lapps(lapps)).into_response())
 }
 
-async fn process_add_lapp(lapps_provider: LappsProvider, lar: FieldData<NamedTempFile>) -> ServerResult<Response> {
+async fn process_add_lapp(
+    lapps_provider: LappsProvider,
+    lar: FieldData<NamedTempFile>,
+    format: ResponseFormat,
+) -> ServerResult<Response> {
+    lapps_provider.read_manager().await.check_writable()?;
+
     let file_name = lar.metadata.file_name.ok_or(ServerError::UnknownLappName)?;
-    let lapp_name = file_name
-        .strip_suffix(".zip")
-        .unwrap_or_else(|| file_name.strip_suffix(".lar").unwrap_or(&file_name));
+    let lapp_name = lapp_name_from_file_name(&file_name)?;
+    install_lar(&lapps_provider, lapp_name, lar.contents.as_file()).await?;
+
+    process_get_lapps(lapps_provider, format).await
+}
+
+/// Installs the lapp package as the new lapp.
+pub async fn install_lar(lapps_provider: &LappsProvider, lapp_name: &str, lar: &fs::File) -> ServerResult<()> {
+    let manager = lapps_provider.read_manager().await;
+    let package_verifier = manager.package_verifier().clone();
+    let lapp_dir = manager.lapp_dir(lapp_name);
+    let task = manager.tasks().start(TaskKind::Install, lapp_name, true);
+    drop(manager);
+
+    // The package is verified and extracted off the runtime threads
+    let install = {
+        let lapp_name = lapp_name.to_string();
+        let lar = lar.try_clone()?;
+        move || {
+            let result = ZipArchive::new(lar).map_err(Into::into).and_then(|mut archive| {
+                check_lar_layout(&lapp_name, &mut archive)?;
+                package_verifier.verify_package(&lapp_name, &mut archive)?;
+                extract_lar(&lapp_name, archive, &lapp_dir, &task)
+            });
+            task.finish(result)
+        }
+    };
+    tokio::task::spawn_blocking(install).await.map_err(io::Error::other)??;
+    lapps_provider.write_manager().await.insert_lapp_settings(lapp_name)
+}
+
+async fn process_restore_lapp(
+    lapps_provider: LappsProvider,
+    backup: FieldData<NamedTempFile>,
+    format: ResponseFormat,
+) -> ServerResult<Response> {
+    lapps_provider.read_manager().await.check_writable()?;
+
+    let file_name = backup.metadata.file_name.ok_or(ServerError::UnknownLappName)?;
+    let lapp_name = lapp_name_from_file_name(&file_name)?;
+
+    let manager = lapps_provider.read_manager().await;
+    let lapp_dir = manager.lapp_dir(lapp_name);
+    let package_verifier = manager.package_verifier().clone();
+    let task = manager.tasks().start(TaskKind::Restore, lapp_name, true);
+    drop(manager);
+
+    let restore = {
+        let lapp_name = lapp_name.to_string();
+        move || {
+            let is_dir_exists = lapp_dir.exists();
+            let result = ZipArchive::new(backup.contents.as_file())
+                .map_err(Into::into)
+                .and_then(|mut archive| {
+                    restore_lapp(&lapp_name, &mut archive, &lapp_dir, &package_verifier, |progress| {
+                        task.set_progress(progress);
+                        task.check_cancelled()
+                    })
+                });
+            if result.is_err() && !is_dir_exists {
+                fs::remove_dir_all(&lapp_dir).ok();
+            }
+            task.finish(result)
+        }
+    };
+    tokio::task::spawn_blocking(restore).await.map_err(io::Error::other)??;
+    lapps_provider.write_manager().await.insert_lapp_settings(lapp_name)?;
+
+    process_get_lapps(lapps_provider, format).await
+}
+
+async fn process_confirm_upgrade(
+    lapps_provider: LappsProvider,
+    lapp_name: String,
+    format: ResponseFormat,
+) -> ServerResult<Response> {
+    let task = lapps_provider
+        .read_manager()
+        .await
+        .tasks()
+        .start(TaskKind::Upgrade, &lapp_name, false);
+    let diff = task.finish(lapps_provider.apply_upgrade(&lapp_name).await)?;
+    log::info!("Lapp '{lapp_name}' is upgraded: {diff:?}");
+
+    process_get_lapps(lapps_provider, format).await
+}
+
+async fn process_deploy_upgrade(
+    lapps_provider: LappsProvider,
+    lapp_name: String,
+    format: ResponseFormat,
+) -> ServerResult<Response> {
+    let task = lapps_provider
+        .read_manager()
+        .await
+        .tasks()
+        .start(TaskKind::Upgrade, &lapp_name, false);
+    // The routing is switched to the new version under the write lock only after the smoke test
+    let result = async {
+        let check_health = lapps_provider.read_manager().await.check_upgrade_health(&lapp_name)?;
+        check_health.await?;
+        lapps_provider.apply_upgrade(&lapp_name).await
+    }
+    .await;
+    let diff = task.finish(result)?;
+    log::info!("Lapp '{lapp_name}' is upgraded by the staged deployment: {diff:?}");
+
+    process_get_lapps(lapps_provider, format).await
+}
+
+async fn process_cancel_upgrade(
+    lapps_provider: LappsProvider,
+    lapp_name: String,
+    format: ResponseFormat,
+) -> ServerResult<Response> {
+    lapps_provider.write_manager().await.cancel_upgrade(lapp_name)?;
+    process_get_lapps(lapps_provider, format).await
+}
+
+async fn process_rollback_lapp(
+    lapps_provider: LappsProvider,
+    lapp_name: String,
+    format: ResponseFormat,
+) -> ServerResult<Response> {
+    let rollback = lapps_provider.write_manager().await.rollback_lapp(&lapp_name)?;
+    rollback.await?;
+    process_get_lapps(lapps_provider, format).await
+}
+
+async fn process_load_lapp(
+    lapps_provider: LappsProvider,
+    lapp_name: String,
+    format: ResponseFormat,
+) -> ServerResult<Response> {
+    let load = lapps_provider.read_manager().await.load_lapp(lapp_name);
+    load.await?;
+    process_get_lapps(lapps_provider, format).await
+}
+
+async fn process_unload_lapp(
+    lapps_provider: LappsProvider,
+    lapp_name: String,
+    format: ResponseFormat,
+) -> ServerResult<Response> {
+    let drained = lapps_provider.read_manager().await.unload_lapp(lapp_name)?;
+    drained.await;
+    process_get_lapps(lapps_provider, format).await
+}
+
+/// Takes the lapp name from the name of the uploaded lapp package or backup.
+fn lapp_name_from_file_name(file_name: &str) -> ServerResult<&str> {
+    let lapp_name = [".backup.zip", ".zip", ".lar"]
+        .into_iter()
+        .find_map(|suffix| file_name.strip_suffix(suffix))
+        .unwrap_or(file_name);
+    check_lapp_name(lapp_name)?;
+    Ok(lapp_name)
+}
+
+/// Checks that the lapp name is a single path component and is neither the main lapp name nor a reserved name.
+pub fn check_lapp_name(lapp_name: &str) -> ServerResult<()> {
+    let mut name_components = FsPath::new(lapp_name).components();
+    if !matches!(name_components.next(), Some(Component::Normal(_)))
+        || name_components.next().is_some()
+        || Lapp::is_main(lapp_name)
+        || RESERVED_LAPP_NAMES.contains(&lapp_name)
+    {
+        return Err(ServerError::UnknownLappName);
+    }
+    Ok(())
+}
 
-    extract_lar(&lapps_provider, lapp_name, ZipArchive::new(lar.contents.as_file())?).await?;
-    lapps_provider.write_manager().await.insert_lapp_settings(lapp_name);
+/// Checks that the package contains the valid lapp config and the static directory or the server module of the lapp.
+fn check_lar_layout<R: io::Read + io::Seek>(lapp_name: &str, archive: &mut ZipArchive<R>) -> ServerResult<()> {
+    package_settings(lapp_name, archive)?;
 
-    process_get_lapps(lapps_provider).await
+    let server_module_name = format!("{lapp_name}_server.wasm");
+    match archive
+        .file_names()
+        .find(|name| name.ends_with("_server.wasm") && !name.contains('/') && *name != server_module_name)
+    {
+        Some(name) => Err(ServerError::WrongLappPackage(
+            lapp_name.into(),
+            format!("server module '{name}' does not match the lapp name, expected '{server_module_name}'"),
+        )),
+        None => Ok(()),
+    }
 }
 
-async fn extract_lar<R: io::Read + io::Seek>(
-    lapps_provider: &LappsProvider,
+fn extract_lar<R: io::Read + io::Seek>(
     lapp_name: &str,
     mut archive: ZipArchive<R>,
+    lapp_dir: &FsPath,
+    task: &TaskHandle,
 ) -> ServerResult<()> {
-    let lapp_dir = lapps_provider.read_manager().await.lapp_dir(lapp_name);
-
-    if lapp_dir.exists() {
+    // The directory of the uninstalled lapp may keep its data, the lapp is installed over it
+    let is_dir_exists = lapp_dir.exists();
+    if is_dir_exists {
         if !lapp_dir.is_dir() {
             return Err(ServerError::WrongLappDirectory(lapp_dir.display().to_string()));
         }
 
-        if lapp_dir.read_dir()?.next().is_some() {
+        if Lapp::settings_path(lapp_dir).exists() {
             return Err(ServerError::LappAlreadyExists(lapp_name.into()));
         }
     }
 
-    archive.extract(lapp_dir).map_err(Into::into)
+    // The entries are extracted one by one to report the progress and stop the extraction on cancel
+    let result = extract_package(&mut archive, lapp_dir, |progress| {
+        task.check_cancelled()?;
+        task.set_progress(progress);
+        Ok(())
+    });
+    if result.is_err() && !is_dir_exists {
+        fs::remove_dir_all(lapp_dir).ok();
+    }
+    result
 }
 
 async fn process_update_lapp(
     lapps_provider: LappsProvider,
     update_request: LappUpdateRequest,
+    actor: String,
+    format: ResponseFormat,
 ) -> ServerResult<Response> {
     let update_query = update_request.into_query();
-    let updated = lapps_provider
+    let (updated, reinstantiate) = lapps_provider
         .write_manager()
         .await
-        .update_lapp_settings(update_query)
-        .await?;
+        .update_lapp_settings(update_query, &actor)?;
+    reinstantiate.await?;
 
-    Ok(Json(CommonLappResponse::Updated { updated }).into_response())
+    Ok(Negotiated(format, CommonLappResponse::Updated { updated }).into_response())
 }
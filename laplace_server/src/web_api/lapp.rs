@@ -4,7 +4,9 @@ use const_format::concatcp;
 
 use crate::lapps::{Lapp, LappsProvider};
 
+mod cache;
 pub mod handler;
+mod proxy;
 
 pub fn router() -> Router<LappsProvider> {
     Router::new()
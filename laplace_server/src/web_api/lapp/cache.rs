@@ -0,0 +1,98 @@
+//! The browser caching of the lapp static files.
+//!
+//! Every served file gets the strong ETag of its content and the `Cache-Control` header of the `static_cache` lapp
+//! settings, so the browser revalidates the file by `If-None-Match` and gets `304 Not Modified` while the file is
+//! unchanged. The ETags are cached by the file path, modification time and size, so the file is hashed once.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use axum::body::Body;
+use axum::http::{header, HeaderValue, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+
+/// The number of the cached ETags after which the cache is cleared.
+const MAX_ETAGS: usize = 10_000;
+
+type EtagKey = (PathBuf, SystemTime, u64);
+
+lazy_static::lazy_static! {
+    static ref ETAGS: Mutex<HashMap<EtagKey, String>> = Mutex::new(HashMap::new());
+}
+
+/// Serves the file with the ETag and the cache control, the missing file is returned with the `404` status.
+pub async fn serve_file(file_path: &Path, cache_control: &str, request: Request<Body>) -> Response {
+    let etag = file_etag(file_path).await;
+    let cache_control = HeaderValue::from_str(cache_control).ok();
+
+    if let Some(etag) = &etag {
+        let is_not_modified = request
+            .headers()
+            .get_all(header::IF_NONE_MATCH)
+            .into_iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|tag| {
+                let tag = tag.trim();
+                tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag
+            });
+
+        if is_not_modified {
+            let mut response = StatusCode::NOT_MODIFIED.into_response();
+            set_cache_headers(&mut response, Some(etag), cache_control);
+            return response;
+        }
+    }
+
+    let mut response = ServeFile::new(file_path)
+        .oneshot(request)
+        .await
+        .expect("Infallible call")
+        .into_response();
+    if response.status().is_success() {
+        set_cache_headers(&mut response, etag.as_deref(), cache_control);
+    }
+    response
+}
+
+fn set_cache_headers(response: &mut Response, etag: Option<&str>, cache_control: Option<HeaderValue>) {
+    let headers = response.headers_mut();
+    if let Some(etag) = etag.and_then(|etag| HeaderValue::from_str(etag).ok()) {
+        headers.insert(header::ETAG, etag);
+    }
+    if let Some(cache_control) = cache_control {
+        headers.insert(header::CACHE_CONTROL, cache_control);
+    }
+}
+
+/// The quoted BLAKE3 hash of the file content, `None` if the file is not readable.
+async fn file_etag(file_path: &Path) -> Option<String> {
+    let metadata = tokio::fs::metadata(file_path)
+        .await
+        .ok()
+        .filter(|metadata| metadata.is_file())?;
+    let key = (file_path.to_path_buf(), metadata.modified().ok()?, metadata.len());
+
+    if let Some(etag) = etags().get(&key) {
+        return Some(etag.clone());
+    }
+
+    let content = tokio::fs::read(file_path).await.ok()?;
+    let hash = blake3::hash(&content).to_hex();
+    let etag = format!("\"{}\"", &hash[..32]);
+
+    let mut etags = etags();
+    if etags.len() >= MAX_ETAGS {
+        etags.clear();
+    }
+    etags.insert(key, etag.clone());
+    Some(etag)
+}
+
+fn etags() -> std::sync::MutexGuard<'static, HashMap<EtagKey, String>> {
+    ETAGS.lock().expect("ETags lock should not be poisoned")
+}
@@ -1,59 +1,70 @@
+use std::net::{IpAddr, SocketAddr};
+
 use axum::body::{Body, Bytes, Full};
-use axum::extract::{Path, State, WebSocketUpgrade};
-use axum::http::Request;
-use axum::response::{IntoResponse, Response};
+use axum::extract::{ConnectInfo, Path, State, WebSocketUpgrade};
+use axum::http::{header, Request};
+use axum::response::{Html, IntoResponse, Response};
 use axum::Json;
-use laplace_common::api::Peer;
+use laplace_common::api::{Peer, WsFraming};
 use laplace_common::lapp::settings::GossipsubSettings;
 use laplace_wasm::http;
 use reqwest::StatusCode;
-use tower::ServiceExt;
-use tower_http::services::ServeFile;
+use serde_json::json;
 use truba::{Context, Sender};
 
 use crate::convert;
 use crate::error::{ServerError, ServerResult};
-use crate::lapps::{LappsProvider, Permission};
+use crate::lapp_calls::CALLER_HEADER;
+use crate::lapps::{Lapp, LappsProvider, Permission, ResourceLimitExceeded};
 use crate::service::gossipsub::{self, decode_keypair, decode_peer_id, GossipsubService, GossipsubServiceMessage};
 use crate::service::lapp::LappServiceMessage;
 use crate::service::websocket::{WebSocketService, WsServiceMessage};
 use crate::service::Addr;
+use crate::web_api::lapp::{cache, proxy};
+use crate::web_api::{err_status_code, ResultResponse};
 
 pub async fn index_file(
     State(lapps_provider): State<LappsProvider>,
     Path(lapp_name): Path<String>,
     request: Request<Body>,
-) -> impl IntoResponse {
-    lapps_provider
-        .handle_client_http(lapp_name, move |lapps_provider, lapp_name| async move {
+) -> Response {
+    let accepts_html = accepts_html(&request);
+    let result = lapps_provider
+        .clone()
+        .handle_client_http(lapp_name.clone(), move |lapps_provider, lapp_name| async move {
             let lapp_dir = lapps_provider.read_manager().await.lapp_dir(&lapp_name);
             let index_file = lapp_dir.index_file();
 
-            Ok(ServeFile::new(index_file)
-                .oneshot(request)
-                .await
-                .expect("Infallible call"))
+            // The index page refers to the other files, so it is always revalidated
+            let response = cache::serve_file(&index_file, "no-cache", request).await;
+            if response.status() == StatusCode::NOT_FOUND {
+                return Err(ServerError::LappFileNotFound(lapp_name, Lapp::index_file_name().into()));
+            }
+            Ok(response)
         })
-        .await
+        .await;
+
+    with_error_page(&lapps_provider, &lapp_name, accepts_html, result).await
 }
 
 pub async fn static_file(
     State(lapps_provider): State<LappsProvider>,
     Path((lapp_name, file_path)): Path<(String, String)>,
     request: Request<Body>,
-) -> impl IntoResponse {
-    lapps_provider
-        .handle_client_http(lapp_name, move |lapps_provider, lapp_name| async move {
+) -> Response {
+    let accepts_html = accepts_html(&request);
+    let result = lapps_provider
+        .clone()
+        .handle_client_http(lapp_name.clone(), move |lapps_provider, lapp_name| async move {
             let manager = lapps_provider.read_manager().await;
             let lapp_dir = manager.lapp_dir(&lapp_name);
 
+            let lapp_settings = manager.lapp_settings(&lapp_name)?;
+            let cache_control = lapp_settings.static_cache().cache_control(&file_path);
+
             let mut fs_file_path = lapp_dir.static_dir().join(&file_path);
             if !fs_file_path.exists() {
-                let additional_dirs = manager
-                    .lapp_settings(&lapp_name)?
-                    .application
-                    .additional_static_dirs
-                    .clone();
+                let additional_dirs = lapp_settings.application.additional_static_dirs.clone();
 
                 for additional_dir in additional_dirs {
                     let additional_file_path = lapp_dir.join(additional_dir).join(&file_path);
@@ -63,39 +74,156 @@ pub async fn static_file(
                     }
                 }
             }
+            drop(manager);
 
-            Ok(ServeFile::new(fs_file_path)
-                .oneshot(request)
-                .await
-                .expect("Infallible call"))
+            let response = cache::serve_file(&fs_file_path, &cache_control, request).await;
+            if response.status() == StatusCode::NOT_FOUND {
+                return Err(ServerError::LappFileNotFound(lapp_name, file_path));
+            }
+            Ok(response)
         })
-        .await
+        .await;
+
+    with_error_page(&lapps_provider, &lapp_name, accepts_html, result).await
 }
 
 pub async fn http(
     State(lapps_provider): State<LappsProvider>,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
     Path((lapp_name, _tail)): Path<(String, String)>,
     request: Request<Body>,
-) -> impl IntoResponse {
-    lapps_provider
-        .handle_client_http(lapp_name, move |lapps_provider, lapp_name| {
-            process_http(lapps_provider, lapp_name, request)
+) -> Response {
+    let accepts_html = accepts_html(&request);
+    let result = lapps_provider
+        .clone()
+        .handle_client_http(lapp_name.clone(), move |lapps_provider, lapp_name| {
+            process_http(lapps_provider, lapp_name, client_addr.ip(), request)
         })
-        .await
+        .await;
+
+    with_error_page(&lapps_provider, &lapp_name, accepts_html, result).await
+}
+
+fn accepts_html(request: &Request<Body>) -> bool {
+    request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"))
+}
+
+/// Replaces the not found and the server errors of the browser requests with the lapp error page: the `404.html`
+/// or `50x.html` file of the lapp static directory or the page rendered by the `error_page` export. The responses
+/// of the lapp server module are returned as is.
+async fn with_error_page(
+    lapps_provider: &LappsProvider,
+    lapp_name: &str,
+    accepts_html: bool,
+    result: ResultResponse<impl IntoResponse>,
+) -> Response {
+    let (status, Json(body)) = match result {
+        Ok(response) => return response.into_response(),
+        Err(err) => err,
+    };
+
+    if accepts_html && (status == StatusCode::NOT_FOUND || status.is_server_error()) {
+        let message = body["error"].as_str().unwrap_or_default().to_string();
+        if let Some(page) = error_page(lapps_provider, lapp_name, status, message).await {
+            return (status, Html(page)).into_response();
+        }
+    }
+    (status, Json(body)).into_response()
+}
+
+async fn error_page(
+    lapps_provider: &LappsProvider,
+    lapp_name: &str,
+    status: StatusCode,
+    message: String,
+) -> Option<String> {
+    let manager = lapps_provider.read_manager().await;
+    manager.lapp_settings(lapp_name).ok()?;
+
+    let page_name = if status == StatusCode::NOT_FOUND {
+        Lapp::not_found_page_name()
+    } else {
+        Lapp::server_error_page_name()
+    };
+    let page_path = manager.lapp_dir(lapp_name).static_dir().join(page_name);
+    let render_page = manager.error_page(lapp_name, http::ErrorPageRequest {
+        status: status.as_u16(),
+        message,
+    });
+    drop(manager);
+
+    if let Ok(page) = tokio::fs::read_to_string(page_path).await {
+        return Some(page);
+    }
+
+    render_page.await.unwrap_or_else(|err| {
+        log::error!("Render error page of lapp '{lapp_name}' error: {err}");
+        None
+    })
 }
 
 async fn process_http(
     lapps_provider: LappsProvider,
     lapp_name: String,
-    request: Request<Body>,
-) -> ServerResult<Response<Full<Bytes>>> {
-    let request = convert::to_wasm_http_request(request).await?;
+    client_ip: IpAddr,
+    mut request: Request<Body>,
+) -> ServerResult<Response> {
+    let manager = lapps_provider.read_manager().await;
+    let lapp_settings = manager.lapp_settings(&lapp_name)?;
+    let lapp_path = request
+        .uri()
+        .path()
+        .strip_prefix(&format!("/{lapp_name}"))
+        .unwrap_or_default();
+    let proxy_upstream_url = lapp_settings.proxy_upstream_url(lapp_path);
+    let rate_limit = manager.rate_limiter().check(
+        &lapp_name,
+        lapp_settings.rate_limits(),
+        request.method().as_str(),
+        lapp_path,
+        client_ip,
+    );
+    let max_body_size = manager.lapp_max_body_size(&lapp_name)?;
+    let http_timeout = manager.lapp_http_timeout(&lapp_name)?;
+    if let Err(exceeded) = rate_limit {
+        let err = ServerError::LappRateLimitExceeded(lapp_name, exceeded.route);
+        log::debug!("{err} by {client_ip}");
+
+        let retry_after_secs = exceeded.retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        return Response::builder()
+            .status(err_status_code(&err))
+            .header(header::RETRY_AFTER, retry_after_secs)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Full::from(json!({ "error": err.to_string() }).to_string()))
+            .map(IntoResponse::into_response)
+            .map_err(Into::into);
+    }
+    drop(manager);
+
+    request.headers_mut().remove(CALLER_HEADER);
+    if let Some(upstream_url) = proxy_upstream_url {
+        return proxy::forward(&lapp_name, upstream_url, client_ip, request, max_body_size).await;
+    }
+
+    let request = convert::to_wasm_http_request(&lapp_name, request, max_body_size).await?;
     let process_http_fut = lapps_provider.read_manager().await.process_http(lapp_name, request);
-    let response: http::Response = process_http_fut.await?;
+
+    // The call itself is interrupted at the timeout, this one also limits the wait for a free lapp instance
+    let response: http::Response = match http_timeout {
+        Some(timeout) => tokio::time::timeout(timeout, process_http_fut)
+            .await
+            .map_err(|_| ServerError::LappResourceLimit(ResourceLimitExceeded::HttpTimeout { timeout }))??,
+        None => process_http_fut.await?,
+    };
 
     Response::builder()
         .status(response.status)
         .body(Full::from(response.body))
+        .map(IntoResponse::into_response)
         .map_err(Into::into)
 }
 
@@ -123,20 +251,25 @@ async fn process_ws_start(
     lapp_service_sender: Sender<LappServiceMessage>,
     lapp_name: String,
 ) -> ServerResult<impl IntoResponse> {
-    let ws_service_addr = Addr::Lapp(lapp_name);
+    let client_id = WebSocketService::next_client_id();
+    let ws_service_addr = Addr::WebSocket(lapp_name, client_id);
     let lapp_name = ws_service_addr.as_lapp_name();
     let ws_service_sender = ctx.actor_sender::<WsServiceMessage>(ws_service_addr.clone());
 
     lapp_service_sender
-        .send(LappServiceMessage::NewWebSocket(ws_service_sender))
+        .send(LappServiceMessage::NewWebSocket {
+            client_id,
+            sender: ws_service_sender,
+        })
         .map_err(|err| {
             log::error!("Error occurs when send to lapp service: {err:?}, lapp: {lapp_name}");
             ServerError::LappServiceSendError(lapp_name.into())
         })?;
 
-    Ok(ws.on_upgrade({
+    // Lapps recognize the negotiated framing by the frame type: JSON in text frames, CBOR in binary frames
+    Ok(ws.protocols(WsFraming::PROTOCOLS).on_upgrade({
         move |web_socket| async move {
-            WebSocketService::new(web_socket, lapp_service_sender).run(ctx, ws_service_addr);
+            WebSocketService::new(web_socket, client_id, lapp_service_sender).run(ctx, ws_service_addr);
         }
     }))
 }
@@ -0,0 +1,148 @@
+//! Forwarding of the lapp proxy routes to the upstream HTTP services.
+//!
+//! The request and the response bodies are streamed as is. The upgrade requests, e.g. the websocket handshakes,
+//! keep their `Connection` and `Upgrade` headers, and after the upstream switches the protocols both upgraded
+//! connections are piped to each other.
+
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use axum::body::{self, Body};
+use axum::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use axum::http::{Request, StatusCode, Uri};
+use axum::response::Response;
+use futures::StreamExt;
+use hyper::client::HttpConnector;
+use hyper::Client;
+use lazy_static::lazy_static;
+
+use crate::error::{ServerError, ServerResult};
+
+lazy_static! {
+    static ref CLIENT: Client<HttpConnector> = Client::new();
+}
+
+/// The hop-by-hop headers, which are not forwarded besides the upgrade ones.
+const HOP_HEADERS: [HeaderName; 6] = [
+    header::CONNECTION,
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+];
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+pub async fn forward(
+    lapp_name: &str,
+    upstream_url: String,
+    client_ip: IpAddr,
+    mut request: Request<Body>,
+    max_body_size: Option<u64>,
+) -> ServerResult<Response> {
+    let proxy_error = |err: String| ServerError::LappProxyError(lapp_name.into(), err);
+
+    let mut uri = upstream_url;
+    if let Some(query) = request.uri().query() {
+        uri = format!("{uri}?{query}");
+    }
+    let uri = Uri::from_str(&uri).map_err(|err| proxy_error(err.to_string()))?;
+    let authority = uri
+        .authority()
+        .ok_or_else(|| proxy_error(format!("upstream URL '{uri}' has no host")))?;
+    let host = HeaderValue::from_str(authority.as_str()).map_err(|err| proxy_error(err.to_string()))?;
+
+    let is_upgrade = is_upgrade(request.headers());
+    let client_upgrade = is_upgrade.then(|| hyper::upgrade::on(&mut request));
+
+    let (mut parts, body) = request.into_parts();
+    parts.uri = uri;
+    parts.headers.insert(header::HOST, host);
+    append_forwarded_for(&mut parts.headers, client_ip);
+    if !is_upgrade {
+        remove_hop_headers(&mut parts.headers);
+    }
+
+    let body = match max_body_size {
+        Some(max_body_size) => {
+            let content_length = parts
+                .headers
+                .get(header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok()?.parse::<u64>().ok());
+            if content_length.is_some_and(|content_length| content_length > max_body_size) {
+                return Err(ServerError::LappRequestBodyTooLarge(lapp_name.into(), max_body_size));
+            }
+            limit_body(lapp_name, body, max_body_size)
+        },
+        None => body,
+    };
+
+    let mut response = CLIENT
+        .request(Request::from_parts(parts, body))
+        .await
+        .map_err(|err| proxy_error(err.to_string()))?;
+
+    match client_upgrade {
+        Some(client_upgrade) if response.status() == StatusCode::SWITCHING_PROTOCOLS => {
+            let upstream_upgrade = hyper::upgrade::on(&mut response);
+            let lapp_name = lapp_name.to_string();
+
+            tokio::spawn(async move {
+                match tokio::try_join!(client_upgrade, upstream_upgrade) {
+                    Ok((mut client, mut upstream)) => {
+                        if let Err(err) = tokio::io::copy_bidirectional(&mut client, &mut upstream).await {
+                            log::debug!("Upgraded proxy connection of lapp '{lapp_name}' is closed: {err}");
+                        }
+                    },
+                    Err(err) => log::warn!("Upgrade of proxy connection of lapp '{lapp_name}' failed: {err}"),
+                }
+            });
+        },
+        _ => remove_hop_headers(response.headers_mut()),
+    }
+
+    Ok(response.map(body::boxed))
+}
+
+fn is_upgrade(headers: &HeaderMap) -> bool {
+    headers.contains_key(header::UPGRADE)
+        && headers
+            .get_all(header::CONNECTION)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|option| option.trim().eq_ignore_ascii_case("upgrade"))
+}
+
+fn remove_hop_headers(headers: &mut HeaderMap) {
+    for name in &HOP_HEADERS {
+        headers.remove(name);
+    }
+    headers.remove(header::UPGRADE);
+}
+
+fn append_forwarded_for(headers: &mut HeaderMap, client_ip: IpAddr) {
+    let forwarded_for = match headers.get(X_FORWARDED_FOR).and_then(|value| value.to_str().ok()) {
+        Some(forwarded_for) => format!("{forwarded_for}, {client_ip}"),
+        None => client_ip.to_string(),
+    };
+    if let Ok(value) = HeaderValue::from_str(&forwarded_for) {
+        headers.insert(X_FORWARDED_FOR, value);
+    }
+}
+
+/// Streams the body of `max_body_size` bytes at most, the upstream request is aborted as soon as the limit is exceeded.
+fn limit_body(lapp_name: &str, body: Body, max_body_size: u64) -> Body {
+    let lapp_name = lapp_name.to_string();
+    let mut size = 0_u64;
+
+    Body::wrap_stream(body.map(move |chunk| {
+        let chunk = chunk.map_err(ServerError::from)?;
+        size += chunk.len() as u64;
+        if size > max_body_size {
+            return Err(ServerError::LappRequestBodyTooLarge(lapp_name.clone(), max_body_size));
+        }
+        Ok(chunk)
+    }))
+}
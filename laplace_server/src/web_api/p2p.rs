@@ -0,0 +1,12 @@
+use axum::routing::{get, post};
+use axum::Router;
+
+use crate::lapps::{Lapp, LappsProvider};
+
+pub mod handler;
+
+pub fn router() -> Router<LappsProvider> {
+    Router::new()
+        .route(&Lapp::main_uri("p2p"), get(handler::get_stats))
+        .route(&Lapp::main_uri("p2p/:lapp_name"), post(handler::apply_action))
+}
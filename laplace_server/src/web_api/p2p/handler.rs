@@ -0,0 +1,70 @@
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use axum::Json;
+use laplace_common::api::{P2pAction, P2pStats};
+use tokio::sync::oneshot;
+use truba::Context;
+
+use crate::error::{ServerError, ServerResult};
+use crate::lapps::LappsProvider;
+use crate::service::gossipsub::GossipsubAdminMessage;
+use crate::service::Addr;
+use crate::web_api::{err_into_json_response, Negotiated, ResponseFormat, ResultResponse};
+
+pub async fn get_stats(format: ResponseFormat, State(lapps_provider): State<LappsProvider>) -> impl IntoResponse {
+    Negotiated(format, collect_stats(&lapps_provider).await)
+}
+
+pub async fn apply_action(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+    Json(action): Json<P2pAction>,
+) -> ResultResponse<Negotiated<Vec<P2pStats>>> {
+    let ctx = lapps_provider.read_manager().await.ctx().clone();
+    process_action(&ctx, lapp_name, action)
+        .await
+        .map_err(err_into_json_response)?;
+
+    Ok(Negotiated(format, collect_stats(&lapps_provider).await))
+}
+
+/// Requests the stats of the P2P nodes of all lapps, the lapps without the started P2P node are skipped.
+async fn collect_stats(lapps_provider: &LappsProvider) -> Vec<P2pStats> {
+    let manager = lapps_provider.read_manager().await;
+    let ctx = manager.ctx().clone();
+    let mut lapp_names: Vec<_> = manager.lapp_settings_iter().map(|(name, _)| name.clone()).collect();
+    lapp_names.sort_unstable();
+    drop(manager);
+
+    let mut stats = Vec::new();
+    for lapp_name in lapp_names {
+        let Some(sender) = ctx.get_actor_sender::<GossipsubAdminMessage>(&Addr::Lapp(lapp_name)) else {
+            continue;
+        };
+
+        let (stats_out, stats_in) = oneshot::channel();
+        if sender.send(GossipsubAdminMessage::Stats(stats_out)).is_ok() {
+            if let Ok(lapp_stats) = stats_in.await {
+                stats.push(lapp_stats);
+            }
+        }
+    }
+    stats
+}
+
+async fn process_action(ctx: &Context<Addr>, lapp_name: String, action: P2pAction) -> ServerResult<()> {
+    let sender = ctx
+        .get_actor_sender::<GossipsubAdminMessage>(&Addr::Lapp(lapp_name.clone()))
+        .ok_or_else(|| ServerError::P2pNotStarted(lapp_name.clone()))?;
+
+    let (result_out, result_in) = oneshot::channel();
+    sender
+        .send(GossipsubAdminMessage::Action(action, result_out))
+        .map_err(|_| ServerError::P2pNotStarted(lapp_name.clone()))?;
+
+    result_in
+        .await
+        .map_err(|_| ServerError::P2pNotStarted(lapp_name))?
+        .map_err(Into::into)
+}
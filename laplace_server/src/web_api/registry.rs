@@ -0,0 +1,38 @@
+use axum::extract::{Path, Query, State};
+use axum::routing::{get, post};
+use axum::Router;
+
+use crate::lapps::{Lapp, LappsProvider};
+use crate::registry::Registry;
+use crate::web_api::ResponseFormat;
+
+pub mod handler;
+
+pub fn router(registry: Registry) -> Router<LappsProvider> {
+    Router::new()
+        .route(
+            &Lapp::main_uri("registry"),
+            get({
+                let registry = registry.clone();
+                move |format: ResponseFormat| handler::get_index(format, registry.clone())
+            }),
+        )
+        .route(
+            &Lapp::main_uri("registry/:lapp_name"),
+            get({
+                let registry = registry.clone();
+                move |format: ResponseFormat, path: Path<String>| handler::get_lapp(format, registry.clone(), path)
+            }),
+        )
+        .route(
+            &Lapp::main_uri("registry/:lapp_name/install"),
+            post(
+                move |format: ResponseFormat,
+                      state: State<LappsProvider>,
+                      path: Path<String>,
+                      query: Query<handler::InstallQuery>| {
+                    handler::install_lapp(format, registry.clone(), state, path, query)
+                },
+            ),
+        )
+}
@@ -0,0 +1,67 @@
+use axum::extract::{Path, Query, State};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+
+use crate::error::ServerResult;
+use crate::lapps::{CommonLappResponse, LappsProvider};
+use crate::registry::Registry;
+use crate::web_api::laplace::handler::{check_lapp_name, install_lar, process_get_lapps};
+use crate::web_api::{err_into_json_response, Negotiated, ResponseFormat};
+
+pub async fn get_index(format: ResponseFormat, registry: Registry) -> impl IntoResponse {
+    registry
+        .index()
+        .await
+        .map(|index| Negotiated(format, index))
+        .map_err(err_into_json_response)
+}
+
+pub async fn get_lapp(format: ResponseFormat, registry: Registry, Path(lapp_name): Path<String>) -> impl IntoResponse {
+    registry
+        .lapp(&lapp_name)
+        .await
+        .map(|lapp| Negotiated(format, lapp))
+        .map_err(err_into_json_response)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InstallQuery {
+    /// The version to install, the latest one if missing.
+    pub version: Option<String>,
+}
+
+/// Installs the lapp from the registry, the package of the installed lapp is staged as its upgrade.
+pub async fn install_lapp(
+    format: ResponseFormat,
+    registry: Registry,
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+    Query(query): Query<InstallQuery>,
+) -> impl IntoResponse {
+    process_install_lapp(lapps_provider, registry, lapp_name, query.version, format)
+        .await
+        .map_err(err_into_json_response)
+}
+
+async fn process_install_lapp(
+    lapps_provider: LappsProvider,
+    registry: Registry,
+    lapp_name: String,
+    version: Option<String>,
+    format: ResponseFormat,
+) -> ServerResult<Response> {
+    check_lapp_name(&lapp_name)?;
+    let manager = lapps_provider.read_manager().await;
+    manager.check_writable()?;
+    let is_installed = manager.lapp_settings(&lapp_name).is_ok();
+    drop(manager);
+
+    let package = registry.download(&lapp_name, version.as_deref()).await?;
+    if is_installed {
+        let upgrade = lapps_provider.write_manager().await.stage_upgrade(lapp_name, package)?;
+        return Ok(Negotiated(format, CommonLappResponse::Upgrade { upgrade }).into_response());
+    }
+
+    install_lar(&lapps_provider, &lapp_name, package.as_file()).await?;
+    process_get_lapps(lapps_provider, format).await
+}
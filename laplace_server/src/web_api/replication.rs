@@ -0,0 +1,40 @@
+use axum::extract::{Path, Query, State};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+
+use crate::lapps::{Lapp, LappsProvider};
+use crate::replication::Peer;
+
+pub mod handler;
+
+pub fn router(batch_size: usize) -> Router<LappsProvider> {
+    Router::new()
+        .route(
+            &Lapp::main_uri("replication/:lapp_name/changes"),
+            get(
+                move |state: State<LappsProvider>, path: Path<String>, query: Query<handler::ChangesQuery>| {
+                    handler::changes(state, path, query, batch_size)
+                },
+            ),
+        )
+        .route(
+            &Lapp::main_uri("replication/:lapp_name/bulk_docs"),
+            post(handler::bulk_docs),
+        )
+        .route(
+            &Lapp::main_uri("replication/:lapp_name/pull"),
+            post(
+                move |state: State<LappsProvider>, path: Path<String>, peer: Json<Peer>| {
+                    handler::pull(state, path, peer, batch_size)
+                },
+            ),
+        )
+        .route(
+            &Lapp::main_uri("replication/:lapp_name/push"),
+            post(
+                move |state: State<LappsProvider>, path: Path<String>, peer: Json<Peer>| {
+                    handler::push(state, path, peer, batch_size)
+                },
+            ),
+        )
+}
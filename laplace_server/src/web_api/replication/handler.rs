@@ -0,0 +1,108 @@
+use axum::extract::{Path, Query, State};
+use axum::response::IntoResponse;
+use axum::Json;
+use serde::Deserialize;
+
+use crate::lapps::{LappsProvider, Permission};
+use crate::replication::{self, BulkDocs, BulkDocsResult, Peer};
+
+#[derive(Debug, Deserialize)]
+pub struct ChangesQuery {
+    #[serde(default)]
+    pub since: u64,
+    pub limit: Option<usize>,
+}
+
+pub async fn changes(
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+    Query(query): Query<ChangesQuery>,
+    batch_size: usize,
+) -> impl IntoResponse {
+    lapps_provider
+        .handle_allowed(
+            &[Permission::Database],
+            lapp_name,
+            move |lapps_provider, lapp_name| async move {
+                let database_path = lapps_provider.read_manager().await.lapp_database_path(&lapp_name)?;
+                let connection = replication::open_read_only(database_path)?;
+                let limit = query.limit.map_or(batch_size, |limit| limit.min(batch_size));
+
+                replication::changes(&connection, query.since, limit).map(Json)
+            },
+        )
+        .await
+}
+
+pub async fn bulk_docs(
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+    Json(bulk_docs): Json<BulkDocs>,
+) -> impl IntoResponse {
+    lapps_provider
+        .handle_allowed(
+            &[Permission::Database],
+            lapp_name,
+            move |lapps_provider, lapp_name| async move {
+                let manager = lapps_provider.read_manager().await;
+                manager.check_writable()?;
+                let database_path = manager.lapp_database_path(&lapp_name)?;
+                drop(manager);
+
+                let mut connection = replication::open(database_path)?;
+                let applied = replication::apply(&mut connection, &bulk_docs.docs)?;
+
+                Ok(Json(BulkDocsResult { applied }))
+            },
+        )
+        .await
+}
+
+pub async fn pull(
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+    Json(peer): Json<Peer>,
+    batch_size: usize,
+) -> impl IntoResponse {
+    lapps_provider
+        .handle_allowed(
+            &[Permission::Database],
+            lapp_name,
+            move |lapps_provider, lapp_name| async move {
+                let manager = lapps_provider.read_manager().await;
+                manager.check_writable()?;
+                let database_path = manager.lapp_database_path(&lapp_name)?;
+                let http_client = manager.http_client().clone();
+                drop(manager);
+
+                replication::pull(&http_client, database_path, &lapp_name, &peer, batch_size)
+                    .await
+                    .map(Json)
+            },
+        )
+        .await
+}
+
+pub async fn push(
+    State(lapps_provider): State<LappsProvider>,
+    Path(lapp_name): Path<String>,
+    Json(peer): Json<Peer>,
+    batch_size: usize,
+) -> impl IntoResponse {
+    lapps_provider
+        .handle_allowed(
+            &[Permission::Database],
+            lapp_name,
+            move |lapps_provider, lapp_name| async move {
+                let manager = lapps_provider.read_manager().await;
+                let database_path = manager.lapp_database_path(&lapp_name)?;
+                let http_client = manager.http_client().clone();
+                drop(manager);
+
+                replication::push(&http_client, database_path, &lapp_name, &peer, batch_size)
+                    .await
+                    .map(Json)
+            },
+        )
+        .await
+}
@@ -0,0 +1,39 @@
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use laplace_common::api::HotSettings;
+
+use crate::lapps::{Lapp, LappsProvider};
+use crate::settings_editor::SettingsEditor;
+use crate::web_api::ResponseFormat;
+
+pub mod handler;
+
+pub fn router(editor: SettingsEditor) -> Router<LappsProvider> {
+    Router::new()
+        .route(
+            &Lapp::main_uri("settings"),
+            get({
+                let editor = editor.clone();
+                move |format: ResponseFormat| handler::get_settings(format, editor.clone())
+            })
+            .post({
+                let editor = editor.clone();
+                move |format: ResponseFormat, hot: Json<HotSettings>| {
+                    handler::save_settings(format, editor.clone(), hot)
+                }
+            }),
+        )
+        .route(
+            &Lapp::main_uri("settings/validate"),
+            post({
+                let editor = editor.clone();
+                move |format: ResponseFormat, hot: Json<HotSettings>| {
+                    handler::validate_settings(format, editor.clone(), hot)
+                }
+            }),
+        )
+        .route(
+            &Lapp::main_uri("settings/reload"),
+            post(move |format: ResponseFormat| handler::reload_settings(format, editor.clone())),
+        )
+}
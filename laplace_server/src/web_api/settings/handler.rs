@@ -0,0 +1,39 @@
+use axum::response::IntoResponse;
+use axum::Json;
+use laplace_common::api::{HotSettings, ServerSettings};
+
+use crate::settings_editor::SettingsEditor;
+use crate::web_api::{err_into_json_response, Negotiated, ResponseFormat, ResultResponse};
+
+pub async fn get_settings(format: ResponseFormat, editor: SettingsEditor) -> impl IntoResponse {
+    Negotiated(format, editor.settings())
+}
+
+pub async fn validate_settings(
+    format: ResponseFormat,
+    editor: SettingsEditor,
+    Json(hot): Json<HotSettings>,
+) -> impl IntoResponse {
+    Negotiated(format, editor.validate(&hot))
+}
+
+/// Writes the hot settings to the config file, they are applied by the reload.
+pub async fn save_settings(
+    format: ResponseFormat,
+    editor: SettingsEditor,
+    Json(hot): Json<HotSettings>,
+) -> ResultResponse<Negotiated<ServerSettings>> {
+    editor.save(&hot).map_err(err_into_json_response)?;
+    Ok(Negotiated(format, editor.settings()))
+}
+
+pub async fn reload_settings(
+    format: ResponseFormat,
+    editor: SettingsEditor,
+) -> ResultResponse<Negotiated<ServerSettings>> {
+    editor
+        .reload()
+        .await
+        .map(|settings| Negotiated(format, settings))
+        .map_err(err_into_json_response)
+}
@@ -0,0 +1,13 @@
+use axum::routing::{get, post};
+use axum::Router;
+
+use crate::lapps::{Lapp, LappsProvider};
+
+pub mod handler;
+
+pub fn router() -> Router<LappsProvider> {
+    Router::new()
+        .route(&Lapp::main_uri("tasks"), get(handler::get_tasks))
+        .route(&Lapp::main_uri("tasks/ws"), get(handler::tasks_ws))
+        .route(&Lapp::main_uri("tasks/:id/cancel"), post(handler::cancel_task))
+}
@@ -0,0 +1,58 @@
+use axum::extract::ws::{Message, WebSocket};
+use axum::extract::{Path, State, WebSocketUpgrade};
+use axum::response::IntoResponse;
+use laplace_common::api::TaskInfo;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::lapps::LappsProvider;
+use crate::tasks::Tasks;
+use crate::web_api::{err_into_json_response, Negotiated, ResponseFormat, ResultResponse};
+
+pub async fn get_tasks(format: ResponseFormat, State(lapps_provider): State<LappsProvider>) -> impl IntoResponse {
+    Negotiated(format, lapps_provider.read_manager().await.tasks().list())
+}
+
+pub async fn cancel_task(
+    format: ResponseFormat,
+    State(lapps_provider): State<LappsProvider>,
+    Path(id): Path<u64>,
+) -> ResultResponse<Negotiated<Vec<TaskInfo>>> {
+    let tasks = lapps_provider.read_manager().await.tasks().clone();
+    tasks.cancel(id).map_err(err_into_json_response)?;
+
+    Ok(Negotiated(format, tasks.list()))
+}
+
+pub async fn tasks_ws(ws: WebSocketUpgrade, State(lapps_provider): State<LappsProvider>) -> impl IntoResponse {
+    let tasks = lapps_provider.read_manager().await.tasks().clone();
+    ws.on_upgrade(move |web_socket| send_task_events(web_socket, tasks))
+}
+
+/// Sends the current tasks and then every task change as a JSON text message.
+async fn send_task_events(mut web_socket: WebSocket, tasks: Tasks) {
+    let mut events = tasks.subscribe();
+    let mut pending = tasks.list();
+
+    loop {
+        for task in pending.drain(..) {
+            let Ok(text) = serde_json::to_string(&task) else {
+                continue;
+            };
+            if web_socket.send(Message::Text(text)).await.is_err() {
+                return;
+            }
+        }
+
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(task) => pending.push(task),
+                Err(RecvError::Lagged(_)) => pending = tasks.list(),
+                Err(RecvError::Closed) => return,
+            },
+            msg = web_socket.recv() => match msg {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {},
+            },
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use axum::routing::any;
+use axum::Router;
+
+use crate::lapps::LappsProvider;
+
+pub mod handler;
+
+pub const DAV_PATH: &str = "dav";
+
+pub fn router() -> Router<LappsProvider> {
+    Router::new()
+        .route(&format!("/:lapp_name/{DAV_PATH}"), any(handler::dav))
+        .route(&format!("/:lapp_name/{DAV_PATH}/*path"), any(handler::dav))
+}
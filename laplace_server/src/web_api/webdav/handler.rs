@@ -0,0 +1,456 @@
+//! WebDAV access to the lapp data directory. Locks are not enforced: the `LOCK` method is supported
+//! only to allow the clients that require it to mount the directory in the writable mode.
+//!
+//! The files are read and written asynchronously, the other methods walking the directories are run on the blocking
+//! threads.
+
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Component, Path as FsPath, PathBuf};
+
+use axum::body::{self, Body};
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, Method, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use hyper::body::HttpBody;
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt;
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+
+use crate::auth::generate_token;
+use crate::lapps::{LappsProvider, Permission};
+use crate::web_api::webdav::DAV_PATH;
+
+const ALLOWED_METHODS: &str = "OPTIONS, PROPFIND, PROPPATCH, GET, HEAD, PUT, DELETE, MKCOL, COPY, MOVE, LOCK, UNLOCK";
+const XML_CONTENT_TYPE: &str = "application/xml; charset=utf-8";
+const XML_HEADER: &str = r#"<?xml version="1.0" encoding="utf-8"?>"#;
+
+#[derive(Debug, Deserialize)]
+pub struct DavPath {
+    pub lapp_name: String,
+    pub path: Option<String>,
+}
+
+pub async fn dav(
+    State(lapps_provider): State<LappsProvider>,
+    Path(DavPath { lapp_name, path }): Path<DavPath>,
+    request: Request<Body>,
+) -> impl IntoResponse {
+    let is_write = is_write_method(request.method());
+    let permissions: &[Permission] = if is_write {
+        &[Permission::Webdav, Permission::FileRead, Permission::FileWrite]
+    } else {
+        &[Permission::Webdav, Permission::FileRead]
+    };
+
+    lapps_provider
+        .handle_allowed(permissions, lapp_name, move |lapps_provider, lapp_name| async move {
+            let manager = lapps_provider.read_manager().await;
+            if is_write {
+                manager.check_writable()?;
+            }
+            let data_dir = manager.lapp_data_dir_path(&lapp_name)?;
+            drop(manager);
+
+            let relative_path = match sanitize_path(path.as_deref().unwrap_or_default()) {
+                Some(relative_path) => relative_path,
+                None => return Ok(StatusCode::FORBIDDEN.into_response()),
+            };
+
+            let dav = Dav {
+                base_uri: format!("/{lapp_name}/{DAV_PATH}"),
+                data_dir,
+            };
+            let response = dav
+                .process(relative_path, request, is_write)
+                .await
+                .unwrap_or_else(|err| {
+                    log::debug!("WebDAV error for lapp '{lapp_name}': {err}");
+                    io_error_status(&err).into_response()
+                });
+
+            Ok(response)
+        })
+        .await
+}
+
+fn is_write_method(method: &Method) -> bool {
+    matches!(
+        method.as_str(),
+        "PUT" | "DELETE" | "MKCOL" | "COPY" | "MOVE" | "LOCK" | "UNLOCK" | "PROPPATCH"
+    )
+}
+
+#[derive(Clone)]
+struct Dav {
+    base_uri: String,
+    data_dir: PathBuf,
+}
+
+impl Dav {
+    async fn process(&self, relative_path: PathBuf, request: Request<Body>, is_write: bool) -> io::Result<Response> {
+        if is_write {
+            tokio::fs::create_dir_all(&self.data_dir).await?;
+        }
+
+        let path = self.data_dir.join(&relative_path);
+        let method = request.method().clone();
+        match method.as_str() {
+            "OPTIONS" => Ok(self.options()),
+            "GET" | "HEAD" => self.get(&path, request).await,
+            "PUT" => self.put(&path, request.into_body()).await,
+            _ => {
+                let dav = self.clone();
+                let headers = request.headers().clone();
+
+                tokio::task::spawn_blocking(move || dav.process_blocking(&relative_path, &path, &method, &headers))
+                    .await
+                    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?
+            },
+        }
+    }
+
+    fn process_blocking(
+        &self,
+        relative_path: &FsPath,
+        path: &FsPath,
+        method: &Method,
+        headers: &HeaderMap,
+    ) -> io::Result<Response> {
+        match method.as_str() {
+            "PROPFIND" => self.propfind(relative_path, path, headers),
+            "PROPPATCH" => self.proppatch(relative_path, path),
+            "DELETE" => self.delete(relative_path, path),
+            "MKCOL" => self.mkcol(path),
+            "COPY" => self.transfer(path, headers, false),
+            "MOVE" => self.transfer(path, headers, true),
+            "LOCK" => self.lock(relative_path, path),
+            "UNLOCK" => Ok(StatusCode::NO_CONTENT.into_response()),
+            _ => Ok(StatusCode::METHOD_NOT_ALLOWED.into_response()),
+        }
+    }
+
+    fn options(&self) -> Response {
+        (
+            [
+                (header::ALLOW, ALLOWED_METHODS),
+                (header::HeaderName::from_static("dav"), "1, 2"),
+                (header::HeaderName::from_static("ms-author-via"), "DAV"),
+            ],
+            StatusCode::OK,
+        )
+            .into_response()
+    }
+
+    fn propfind(&self, relative_path: &FsPath, path: &FsPath, headers: &HeaderMap) -> io::Result<Response> {
+        let metadata = fs::metadata(path)?;
+        let depth = headers
+            .get("depth")
+            .and_then(|depth| depth.to_str().ok())
+            .unwrap_or("infinity");
+
+        let mut xml = format!(r#"{XML_HEADER}<D:multistatus xmlns:D="DAV:">"#);
+        self.write_prop_response(&mut xml, relative_path, &metadata);
+
+        if metadata.is_dir() && depth != "0" {
+            for entry in fs::read_dir(path)? {
+                let entry = entry?;
+                self.write_prop_response(&mut xml, &relative_path.join(entry.file_name()), &entry.metadata()?);
+            }
+        }
+        xml.push_str("</D:multistatus>");
+
+        Ok(multi_status(xml))
+    }
+
+    fn proppatch(&self, relative_path: &FsPath, path: &FsPath) -> io::Result<Response> {
+        fs::metadata(path)?;
+
+        // Dead properties are not stored, so the update is reported as successful without changes
+        let xml = format!(
+            r#"{XML_HEADER}<D:multistatus xmlns:D="DAV:"><D:response><D:href>{}</D:href><D:propstat><D:prop/><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response></D:multistatus>"#,
+            escape_xml(&self.href(relative_path)),
+        );
+
+        Ok(multi_status(xml))
+    }
+
+    async fn get(&self, path: &FsPath, request: Request<Body>) -> io::Result<Response> {
+        if tokio::fs::metadata(path).await?.is_dir() {
+            return Ok(StatusCode::METHOD_NOT_ALLOWED.into_response());
+        }
+
+        let response = match ServeFile::new(path).oneshot(request).await {
+            Ok(response) => response,
+            Err(err) => match err {},
+        };
+        Ok(response.map(body::boxed))
+    }
+
+    async fn put(&self, path: &FsPath, mut body: Body) -> io::Result<Response> {
+        let metadata = tokio::fs::metadata(path).await;
+        if metadata.as_ref().is_ok_and(Metadata::is_dir) {
+            return Ok(StatusCode::METHOD_NOT_ALLOWED.into_response());
+        }
+        let parent_metadata = match path.parent() {
+            Some(parent) => tokio::fs::metadata(parent).await,
+            None => Err(io::ErrorKind::NotFound.into()),
+        };
+        if !parent_metadata.is_ok_and(|metadata| metadata.is_dir()) {
+            return Ok(StatusCode::CONFLICT.into_response());
+        }
+
+        let is_created = metadata.is_err();
+        let mut file = tokio::fs::File::create(path).await?;
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+
+        Ok(if is_created {
+            StatusCode::CREATED
+        } else {
+            StatusCode::NO_CONTENT
+        }
+        .into_response())
+    }
+
+    fn delete(&self, relative_path: &FsPath, path: &FsPath) -> io::Result<Response> {
+        if relative_path.as_os_str().is_empty() {
+            return Ok(StatusCode::FORBIDDEN.into_response());
+        }
+
+        remove(path)?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    fn mkcol(&self, path: &FsPath) -> io::Result<Response> {
+        if path.exists() {
+            return Ok(StatusCode::METHOD_NOT_ALLOWED.into_response());
+        }
+        if !is_parent_dir_exists(path) {
+            return Ok(StatusCode::CONFLICT.into_response());
+        }
+
+        fs::create_dir(path)?;
+        Ok(StatusCode::CREATED.into_response())
+    }
+
+    fn transfer(&self, path: &FsPath, headers: &HeaderMap, is_move: bool) -> io::Result<Response> {
+        let destination = match headers
+            .get("destination")
+            .and_then(|destination| destination.to_str().ok())
+            .and_then(|destination| self.destination_path(destination))
+        {
+            Some(destination) => destination,
+            None => return Ok(StatusCode::BAD_REQUEST.into_response()),
+        };
+        let is_overwrite = headers
+            .get("overwrite")
+            .map(|overwrite| overwrite.as_bytes() != b"F")
+            .unwrap_or(true);
+
+        fs::metadata(path)?;
+        if destination == path || destination.starts_with(path) {
+            return Ok(StatusCode::FORBIDDEN.into_response());
+        }
+        if !is_parent_dir_exists(&destination) {
+            return Ok(StatusCode::CONFLICT.into_response());
+        }
+
+        let is_created = !destination.exists();
+        if !is_created {
+            if !is_overwrite {
+                return Ok(StatusCode::PRECONDITION_FAILED.into_response());
+            }
+            remove(&destination)?;
+        }
+
+        if is_move {
+            fs::rename(path, &destination)?;
+        } else {
+            copy(path, &destination)?;
+        }
+
+        Ok(if is_created {
+            StatusCode::CREATED
+        } else {
+            StatusCode::NO_CONTENT
+        }
+        .into_response())
+    }
+
+    fn lock(&self, relative_path: &FsPath, path: &FsPath) -> io::Result<Response> {
+        let mut status = StatusCode::OK;
+        if !path.exists() {
+            if !is_parent_dir_exists(path) {
+                return Ok(StatusCode::CONFLICT.into_response());
+            }
+            fs::File::create(path)?;
+            status = StatusCode::CREATED;
+        }
+
+        let token = generate_token()
+            .map(|token| format!("opaquelocktoken:{token}"))
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+        let xml = format!(
+            r#"{XML_HEADER}<D:prop xmlns:D="DAV:"><D:lockdiscovery><D:activelock><D:locktype><D:write/></D:locktype><D:lockscope><D:exclusive/></D:lockscope><D:depth>infinity</D:depth><D:timeout>Second-3600</D:timeout><D:locktoken><D:href>{token}</D:href></D:locktoken><D:lockroot><D:href>{}</D:href></D:lockroot></D:activelock></D:lockdiscovery></D:prop>"#,
+            escape_xml(&self.href(relative_path)),
+        );
+
+        Ok((
+            status,
+            [
+                (header::CONTENT_TYPE, XML_CONTENT_TYPE.to_string()),
+                (header::HeaderName::from_static("lock-token"), format!("<{token}>")),
+            ],
+            xml,
+        )
+            .into_response())
+    }
+
+    fn write_prop_response(&self, xml: &mut String, relative_path: &FsPath, metadata: &Metadata) {
+        let mut href = self.href(relative_path);
+        if metadata.is_dir() {
+            href.push('/');
+        }
+        let display_name = relative_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let last_modified = metadata.modified().map(httpdate::fmt_http_date).unwrap_or_default();
+
+        xml.push_str(&format!(
+            "<D:response><D:href>{}</D:href><D:propstat><D:prop><D:displayname>{}</D:displayname>\
+            <D:getlastmodified>{last_modified}</D:getlastmodified>",
+            escape_xml(&href),
+            escape_xml(&display_name),
+        ));
+        if metadata.is_dir() {
+            xml.push_str("<D:resourcetype><D:collection/></D:resourcetype>");
+        } else {
+            xml.push_str(&format!(
+                "<D:resourcetype/><D:getcontentlength>{}</D:getcontentlength>",
+                metadata.len()
+            ));
+        }
+        xml.push_str("</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>");
+    }
+
+    fn href(&self, relative_path: &FsPath) -> String {
+        let mut href = self.base_uri.clone();
+        for component in relative_path.components() {
+            href.push('/');
+            href.push_str(&percent_encode(&component.as_os_str().to_string_lossy()));
+        }
+        href
+    }
+
+    /// Converts the `Destination` header URL into the path inside the data directory.
+    fn destination_path(&self, destination: &str) -> Option<PathBuf> {
+        let uri_path = match destination.split_once("://") {
+            Some((_, rest)) => rest.find('/').map(|idx| &rest[idx..]).unwrap_or("/"),
+            None => destination,
+        };
+        let tail = uri_path.strip_prefix(&self.base_uri)?;
+        if !tail.is_empty() && !tail.starts_with('/') {
+            return None;
+        }
+
+        sanitize_path(&percent_decode(tail)?).map(|relative_path| self.data_dir.join(relative_path))
+    }
+}
+
+fn multi_status(xml: String) -> Response {
+    (
+        StatusCode::MULTI_STATUS,
+        [(header::CONTENT_TYPE, XML_CONTENT_TYPE)],
+        xml,
+    )
+        .into_response()
+}
+
+fn io_error_status(err: &io::Error) -> StatusCode {
+    match err.kind() {
+        io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+        io::ErrorKind::AlreadyExists => StatusCode::METHOD_NOT_ALLOWED,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Returns the relative path without the components that can lead out of the data directory.
+fn sanitize_path(path: &str) -> Option<PathBuf> {
+    let mut relative_path = PathBuf::new();
+    for component in FsPath::new(path.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(name) => relative_path.push(name),
+            Component::CurDir => (),
+            _ => return None,
+        }
+    }
+    Some(relative_path)
+}
+
+fn is_parent_dir_exists(path: &FsPath) -> bool {
+    path.parent().map(|parent| parent.is_dir()).unwrap_or(false)
+}
+
+fn remove(path: &FsPath) -> io::Result<()> {
+    if fs::metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+fn copy(from: &FsPath, to: &FsPath) -> io::Result<()> {
+    if from.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' {
+            let hex = text.get(idx + 1..idx + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            idx += 3;
+        } else {
+            decoded.push(bytes[idx]);
+            idx += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
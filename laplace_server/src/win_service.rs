@@ -0,0 +1,132 @@
+use std::ffi::OsString;
+use std::path::Path;
+use std::time::Duration;
+use std::{env, thread};
+
+use clap::Parser;
+use futures::FutureExt;
+use laplace_server::settings;
+use tokio::sync::oneshot;
+use windows_service::service::{
+    ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode, ServiceInfo,
+    ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+use windows_service::{define_windows_service, service_dispatcher};
+
+use crate::cli::Opts;
+
+const SERVICE_NAME: &str = "laplace";
+const SERVICE_DISPLAY_NAME: &str = "Laplace";
+const SERVICE_DESCRIPTION: &str = "The local-first web-application platform";
+const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
+
+define_windows_service!(ffi_service_main, service_main);
+
+pub fn run() -> windows_service::Result<()> {
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+}
+
+pub fn install(config_path: Option<&Path>) -> windows_service::Result<()> {
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )?;
+
+    let config_path = config_path
+        .map(Path::to_path_buf)
+        .unwrap_or_else(settings::default_config_path)
+        .canonicalize()
+        .map_err(windows_service::Error::Winapi)?;
+    let service_info = ServiceInfo {
+        name: SERVICE_NAME.into(),
+        display_name: SERVICE_DISPLAY_NAME.into(),
+        service_type: SERVICE_TYPE,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: env::current_exe().map_err(windows_service::Error::Winapi)?,
+        launch_arguments: vec!["--service".into(), "--config".into(), config_path.into()],
+        dependencies: vec![],
+        account_name: None,
+        account_password: None,
+    };
+
+    let service = manager.create_service(&service_info, ServiceAccess::CHANGE_CONFIG)?;
+    service.set_description(SERVICE_DESCRIPTION)
+}
+
+pub fn uninstall() -> windows_service::Result<()> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+    let service = manager.open_service(
+        SERVICE_NAME,
+        ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+    )?;
+
+    if service.query_status()?.current_state != ServiceState::Stopped {
+        service.stop()?;
+        while service.query_status()?.current_state != ServiceState::Stopped {
+            thread::sleep(Duration::from_millis(500));
+        }
+    }
+
+    service.delete()
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(err) = run_service() {
+        log::error!("Laplace service error: {err}");
+    }
+}
+
+fn run_service() -> windows_service::Result<()> {
+    // The service is launched with the arguments specified at the installation
+    let opts = Opts::parse();
+
+    // The working directory of a service is the system directory, so relative paths from the config
+    // should be resolved against the config directory
+    if let Some(config_dir) = opts.config.as_deref().and_then(Path::parent) {
+        env::set_current_dir(config_dir).map_err(windows_service::Error::Winapi)?;
+    }
+
+    let (shutdown_sender, shutdown_receiver) = oneshot::channel();
+    let mut shutdown_sender = Some(shutdown_sender);
+
+    let status_handle = service_control_handler::register(SERVICE_NAME, move |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            if let Some(sender) = shutdown_sender.take() {
+                sender.send(()).ok();
+            }
+            ServiceControlHandlerResult::NoError
+        },
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })?;
+
+    set_status(
+        &status_handle,
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+    )?;
+
+    let settings = crate::load_settings(&opts);
+    crate::run(settings, shutdown_receiver.map(drop));
+
+    set_status(&status_handle, ServiceState::Stopped, ServiceControlAccept::empty())
+}
+
+fn set_status(
+    status_handle: &ServiceStatusHandle,
+    current_state: ServiceState,
+    controls_accepted: ServiceControlAccept,
+) -> windows_service::Result<()> {
+    status_handle.set_service_status(ServiceStatus {
+        service_type: SERVICE_TYPE,
+        current_state,
+        controls_accepted,
+        exit_code: ServiceExitCode::Win32(0),
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    })
+}
@@ -0,0 +1,89 @@
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use laplace_server::test::TestServer;
+use reqwest::{Client, StatusCode};
+use tokio_tungstenite::tungstenite::Message;
+
+const BENCH_LAPP: &str = "bench";
+
+/// The `bench` test lapp module is built by `cargo make bench_lapp`, or its path is set by `BENCH_LAPP_WASM`.
+fn bench_lapp_module() -> PathBuf {
+    env::var_os("BENCH_LAPP_WASM").map(PathBuf::from).unwrap_or_else(|| {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../target/wasm32-unknown-unknown/release")
+            .join(format!("{BENCH_LAPP}_server.wasm"))
+    })
+}
+
+#[tokio::test]
+async fn laplace_access() {
+    let server = TestServer::start().await.expect("Test server should be started");
+
+    let response = Client::new()
+        .get(server.url("/laplace/lapps"))
+        .send()
+        .await
+        .expect("Fail to get lapps");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = server
+        .client()
+        .get(server.url("/laplace/lapps"))
+        .send()
+        .await
+        .expect("Fail to get lapps");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    server.stop().await.expect("Test server should be stopped");
+}
+
+#[tokio::test]
+async fn lapp_http_and_websocket() {
+    let server = TestServer::builder()
+        .lapp(
+            BENCH_LAPP,
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../tests/lapps/bench"),
+        )
+        .lapp_server_module(BENCH_LAPP, bench_lapp_module())
+        .start()
+        .await
+        .expect("Test server should be started");
+    let client = server.lapp_client(BENCH_LAPP).expect("Lapp client should be created");
+
+    let response = client
+        .post(server.url("/bench/echo"))
+        .body("hello")
+        .send()
+        .await
+        .expect("Fail to post echo");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.expect("Fail to read echo"), "hello");
+
+    // The row is inserted by the lapp init through the database host calls
+    let response = client
+        .get(server.url("/bench/db/row"))
+        .send()
+        .await
+        .expect("Fail to get row");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.bytes().await.expect("Fail to read row").as_ref(), [1]);
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(server.ws_url("/bench/ws"))
+        .await
+        .expect("Websocket should be connected");
+    ws.send(Message::Text("ping".into()))
+        .await
+        .expect("Fail to send websocket message");
+    let message = tokio::time::timeout(Duration::from_secs(10), ws.next())
+        .await
+        .expect("Websocket message should be received in time")
+        .expect("Websocket should not be closed")
+        .expect("Fail to receive websocket message");
+    assert_eq!(message, Message::Text("ping".into()));
+    ws.close(None).await.ok();
+
+    server.stop().await.expect("Test server should be stopped");
+}
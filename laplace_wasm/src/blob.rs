@@ -0,0 +1,36 @@
+use borsh::BorshDeserialize;
+
+use crate::WasmSlice;
+
+extern "C" {
+    fn blob_put(bytes: WasmSlice) -> WasmSlice;
+    fn blob_get(hash: WasmSlice) -> WasmSlice;
+    fn blob_delete(hash: WasmSlice) -> WasmSlice;
+}
+
+/// Checks that the hash is the lowercase hex BLAKE3 hash returned by [`put`].
+pub fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Stores the blob in the lapp data directory, returns its BLAKE3 hash. The same content is stored once.
+pub fn put(bytes: impl Into<Vec<u8>>) -> Result<String, String> {
+    let bytes = unsafe { blob_put(WasmSlice::from(bytes.into())).into_vec_in_wasm() };
+    deserialize(&bytes)
+}
+
+/// Returns the content of the blob, or `None` if there is no blob with the hash.
+pub fn get(hash: impl Into<String>) -> Result<Option<Vec<u8>>, String> {
+    let bytes = unsafe { blob_get(WasmSlice::from(hash.into())).into_vec_in_wasm() };
+    deserialize(&bytes)
+}
+
+/// Deletes the blob, returns `false` if there is no blob with the hash.
+pub fn delete(hash: impl Into<String>) -> Result<bool, String> {
+    let bytes = unsafe { blob_delete(WasmSlice::from(hash.into())).into_vec_in_wasm() };
+    deserialize(&bytes)
+}
+
+fn deserialize<T: BorshDeserialize>(bytes: &[u8]) -> Result<T, String> {
+    BorshDeserialize::try_from_slice(bytes).expect("Blob result should be deserializable")
+}
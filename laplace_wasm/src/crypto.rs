@@ -0,0 +1,151 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use thiserror::Error;
+
+use crate::WasmSlice;
+
+pub type CryptoResult<T> = Result<T, CryptoError>;
+
+#[derive(Debug, Clone, Copy, BorshSerialize, BorshDeserialize)]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+}
+
+#[derive(Debug, Clone, Copy, BorshSerialize, BorshDeserialize)]
+pub enum AeadAlgorithm {
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+/// The crypto operation made by the host, the Ed25519 private key is its 32-byte seed.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub enum CryptoRequest {
+    Hash {
+        algorithm: HashAlgorithm,
+        data: Vec<u8>,
+    },
+    Ed25519PublicKey {
+        seed: Vec<u8>,
+    },
+    Ed25519Sign {
+        seed: Vec<u8>,
+        message: Vec<u8>,
+    },
+    Ed25519Verify {
+        public_key: Vec<u8>,
+        message: Vec<u8>,
+        signature: Vec<u8>,
+    },
+    Seal {
+        algorithm: AeadAlgorithm,
+        key: Vec<u8>,
+        aad: Vec<u8>,
+        plaintext: Vec<u8>,
+    },
+    Open {
+        algorithm: AeadAlgorithm,
+        key: Vec<u8>,
+        aad: Vec<u8>,
+        sealed: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Error, BorshSerialize, BorshDeserialize)]
+pub enum CryptoError {
+    #[error("Read from WASM error")]
+    CanNotReadWasmData,
+
+    #[error("Crypto request deserialization error")]
+    FailDeserializeRequest,
+
+    #[error("Wrong key")]
+    WrongKey,
+
+    #[error("Invalid signature")]
+    InvalidSignature,
+
+    #[error("Sealed data is corrupted or the key is wrong")]
+    FailOpen,
+
+    #[error("Random generation error")]
+    FailRandom,
+}
+
+extern "C" {
+    fn invoke_crypto(request: WasmSlice) -> WasmSlice;
+}
+
+pub fn sha256(data: impl Into<Vec<u8>>) -> [u8; 32] {
+    hash(HashAlgorithm::Sha256, data.into())
+}
+
+pub fn blake3(data: impl Into<Vec<u8>>) -> [u8; 32] {
+    hash(HashAlgorithm::Blake3, data.into())
+}
+
+pub fn ed25519_public_key(seed: impl Into<Vec<u8>>) -> CryptoResult<Vec<u8>> {
+    invoke(CryptoRequest::Ed25519PublicKey { seed: seed.into() })
+}
+
+pub fn ed25519_sign(seed: impl Into<Vec<u8>>, message: impl Into<Vec<u8>>) -> CryptoResult<Vec<u8>> {
+    invoke(CryptoRequest::Ed25519Sign {
+        seed: seed.into(),
+        message: message.into(),
+    })
+}
+
+pub fn ed25519_verify(
+    public_key: impl Into<Vec<u8>>,
+    message: impl Into<Vec<u8>>,
+    signature: impl Into<Vec<u8>>,
+) -> bool {
+    invoke(CryptoRequest::Ed25519Verify {
+        public_key: public_key.into(),
+        message: message.into(),
+        signature: signature.into(),
+    })
+    .is_ok()
+}
+
+/// Encrypts the plaintext with the 32-byte key, the random nonce generated by the host is prepended to the result.
+pub fn seal(
+    algorithm: AeadAlgorithm,
+    key: impl Into<Vec<u8>>,
+    aad: impl Into<Vec<u8>>,
+    plaintext: impl Into<Vec<u8>>,
+) -> CryptoResult<Vec<u8>> {
+    invoke(CryptoRequest::Seal {
+        algorithm,
+        key: key.into(),
+        aad: aad.into(),
+        plaintext: plaintext.into(),
+    })
+}
+
+/// Decrypts the data made by `seal` with the same key and additional data.
+pub fn open(
+    algorithm: AeadAlgorithm,
+    key: impl Into<Vec<u8>>,
+    aad: impl Into<Vec<u8>>,
+    sealed: impl Into<Vec<u8>>,
+) -> CryptoResult<Vec<u8>> {
+    invoke(CryptoRequest::Open {
+        algorithm,
+        key: key.into(),
+        aad: aad.into(),
+        sealed: sealed.into(),
+    })
+}
+
+fn hash(algorithm: HashAlgorithm, data: Vec<u8>) -> [u8; 32] {
+    invoke(CryptoRequest::Hash { algorithm, data })
+        .ok()
+        .and_then(|digest| digest.try_into().ok())
+        .expect("Hash should be 32 bytes")
+}
+
+fn invoke(request: CryptoRequest) -> CryptoResult<Vec<u8>> {
+    let request_bytes = borsh::to_vec(&request).expect("Crypto request should be serializable");
+    let bytes = unsafe { invoke_crypto(WasmSlice::from(request_bytes)).into_vec_in_wasm() };
+    BorshDeserialize::try_from_slice(&bytes).expect("Crypto result should be deserializable")
+}
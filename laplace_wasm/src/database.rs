@@ -6,6 +6,18 @@ extern "C" {
     fn db_execute(sql_query: WasmSlice) -> WasmSlice;
     fn db_query(sql_query: WasmSlice) -> WasmSlice;
     fn db_query_row(sql_query: WasmSlice) -> WasmSlice;
+    fn db_begin() -> WasmSlice;
+    fn db_commit() -> WasmSlice;
+    fn db_rollback() -> WasmSlice;
+    fn db_prepare(sql_query: WasmSlice) -> WasmSlice;
+    fn db_bind(handle: u64, params: WasmSlice) -> WasmSlice;
+    fn db_step(handle: u64) -> WasmSlice;
+    fn db_finalize(handle: u64);
+    fn db_query_open(sql_query: WasmSlice) -> WasmSlice;
+    fn db_query_next(handle: u64) -> WasmSlice;
+    fn db_query_close(handle: u64);
+    fn db_fts_search(fts_query: WasmSlice) -> WasmSlice;
+    fn db_backup() -> WasmSlice;
 }
 
 pub fn execute(sql: impl Into<String>) -> Result<u64, String> {
@@ -23,6 +35,165 @@ pub fn query_row(sql: impl Into<String>) -> Result<Option<Row>, String> {
     BorshDeserialize::try_from_slice(&bytes).expect("Query row result should be deserializable")
 }
 
+/// Opens the cursor reading the query rows one by one, so the large result set is not moved to the lapp at once.
+/// The cursor reads the committed data only, it does not see the changes of the current transaction.
+pub fn query_iter(sql: impl Into<String>) -> Result<Cursor, String> {
+    let bytes = unsafe { db_query_open(WasmSlice::from(sql.into())).into_vec_in_wasm() };
+    let handle: Result<u64, String> =
+        BorshDeserialize::try_from_slice(&bytes).expect("Query open result should be deserializable");
+    handle.map(|handle| Cursor { handle })
+}
+
+/// The rows of the query opened by [`query_iter`], the cursor is closed on drop.
+#[derive(Debug)]
+pub struct Cursor {
+    handle: u64,
+}
+
+impl Iterator for Cursor {
+    type Item = Result<Row, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = unsafe { db_query_next(self.handle).into_vec_in_wasm() };
+        let row: Result<Option<Row>, String> =
+            BorshDeserialize::try_from_slice(&bytes).expect("Query next result should be deserializable");
+        row.transpose()
+    }
+}
+
+impl Drop for Cursor {
+    fn drop(&mut self) {
+        unsafe { db_query_close(self.handle) }
+    }
+}
+
+/// Searches the FTS5 table of the lapp settings, the best matches go first.
+pub fn fts_search(fts_query: &FtsQuery) -> Result<Vec<FtsMatch>, String> {
+    let fts_query_bytes = borsh::to_vec(fts_query).expect("FTS query should be serializable");
+    let bytes = unsafe { db_fts_search(WasmSlice::from(fts_query_bytes)).into_vec_in_wasm() };
+    BorshDeserialize::try_from_slice(&bytes).expect("FTS search result should be deserializable")
+}
+
+/// Copies the database to the backup directory of the lapp by the SQLite online backup, returns the file name of
+/// the backup. The committed data only is backed up.
+pub fn backup() -> Result<String, String> {
+    let bytes = unsafe { db_backup().into_vec_in_wasm() };
+    BorshDeserialize::try_from_slice(&bytes).expect("Backup result should be deserializable")
+}
+
+/// Begins the transaction, the changes are not visible to the other instances until [`commit`]. The transaction
+/// left open at the end of the lapp call is rolled back.
+pub fn begin() -> Result<(), String> {
+    let bytes = unsafe { db_begin().into_vec_in_wasm() };
+    BorshDeserialize::try_from_slice(&bytes).expect("Begin result should be deserializable")
+}
+
+pub fn commit() -> Result<(), String> {
+    let bytes = unsafe { db_commit().into_vec_in_wasm() };
+    BorshDeserialize::try_from_slice(&bytes).expect("Commit result should be deserializable")
+}
+
+pub fn rollback() -> Result<(), String> {
+    let bytes = unsafe { db_rollback().into_vec_in_wasm() };
+    BorshDeserialize::try_from_slice(&bytes).expect("Rollback result should be deserializable")
+}
+
+/// Runs the closure in the transaction, which is committed if the closure succeeds and rolled back otherwise.
+pub fn transaction<T>(fun: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    begin()?;
+    match fun() {
+        Ok(value) => {
+            commit()?;
+            Ok(value)
+        },
+        Err(err) => {
+            rollback()?;
+            Err(err)
+        },
+    }
+}
+
+/// The statement prepared once and run with the different parameters instead of formatting them into the SQL.
+/// The compiled statements are cached by the host, the statement is finalized on drop.
+#[derive(Debug)]
+pub struct Statement {
+    handle: u64,
+}
+
+impl Statement {
+    pub fn prepare(sql: impl Into<String>) -> Result<Self, String> {
+        let bytes = unsafe { db_prepare(WasmSlice::from(sql.into())).into_vec_in_wasm() };
+        let handle: Result<u64, String> =
+            BorshDeserialize::try_from_slice(&bytes).expect("Prepare result should be deserializable");
+        handle.map(|handle| Self { handle })
+    }
+
+    /// Binds the positional parameters `?1`, `?2`, ... and resets the statement, so it runs again on the next step.
+    pub fn bind(&mut self, params: impl IntoIterator<Item = Value>) -> Result<(), String> {
+        let params: Vec<Value> = params.into_iter().collect();
+        let params_bytes = borsh::to_vec(&params).expect("Params should be serializable");
+        let bytes = unsafe { db_bind(self.handle, WasmSlice::from(params_bytes)).into_vec_in_wasm() };
+        BorshDeserialize::try_from_slice(&bytes).expect("Bind result should be deserializable")
+    }
+
+    /// Returns the next row, the first step after the binding runs the statement. The statement without rows,
+    /// e.g. `INSERT`, returns `None` at once.
+    pub fn step(&mut self) -> Result<Option<Row>, String> {
+        let bytes = unsafe { db_step(self.handle).into_vec_in_wasm() };
+        BorshDeserialize::try_from_slice(&bytes).expect("Step result should be deserializable")
+    }
+
+    /// Binds the parameters and returns all rows of the run.
+    pub fn query(&mut self, params: impl IntoIterator<Item = Value>) -> Result<Vec<Row>, String> {
+        self.bind(params)?;
+        let mut rows = Vec::new();
+        while let Some(row) = self.step()? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// Binds the parameters and runs the statement without rows.
+    pub fn execute(&mut self, params: impl IntoIterator<Item = Value>) -> Result<(), String> {
+        self.bind(params)?;
+        self.step().map(|_| ())
+    }
+}
+
+impl Drop for Statement {
+    fn drop(&mut self) {
+        unsafe { db_finalize(self.handle) }
+    }
+}
+
+/// Writes the query rows as CSV text with the header line of column names.
+/// Blob values are written in hex.
+pub fn to_csv<'a>(columns: impl IntoIterator<Item = &'a str>, rows: &[Row]) -> String {
+    let mut csv = String::new();
+    write_csv_line(&mut csv, columns.into_iter().map(Into::into));
+    for row in rows {
+        write_csv_line(&mut csv, row.values.iter().map(Value::to_csv_field));
+    }
+    csv
+}
+
+fn write_csv_line(csv: &mut String, fields: impl Iterator<Item = String>) {
+    for (idx, field) in fields.enumerate() {
+        if idx > 0 {
+            csv.push(',');
+        }
+
+        if field.contains([',', '"', '\n', '\r']) {
+            csv.push('"');
+            csv.push_str(&field.replace('"', "\"\""));
+            csv.push('"');
+        } else {
+            csv.push_str(&field);
+        }
+    }
+    csv.push_str("\r\n");
+}
+
 #[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
 pub enum Value {
     Null,
@@ -32,6 +203,99 @@ pub enum Value {
     Blob(Vec<u8>),
 }
 
+impl Value {
+    pub fn to_csv_field(&self) -> String {
+        match self {
+            Self::Null => String::new(),
+            Self::Integer(value) => value.to_string(),
+            Self::Real(value) => value.to_string(),
+            Self::Text(value) => value.clone(),
+            Self::Blob(value) => value.iter().map(|byte| format!("{byte:02x}")).collect(),
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::Real(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::Text(value.into())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Self::Blob(value)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+/// The full-text query in the FTS5 syntax, e.g. `rust AND wasm*`.
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct FtsQuery {
+    pub table: String,
+    pub query: String,
+    pub limit: u32,
+
+    /// The marks around the matched terms in the snippet, e.g. `("<mark>", "</mark>")`. The snippet text is not
+    /// escaped.
+    pub highlight: Option<(String, String)>,
+}
+
+impl FtsQuery {
+    pub const DEFAULT_LIMIT: u32 = 20;
+
+    pub fn new(table: impl Into<String>, query: impl Into<String>) -> Self {
+        Self {
+            table: table.into(),
+            query: query.into(),
+            limit: Self::DEFAULT_LIMIT,
+            highlight: None,
+        }
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn highlight(mut self, open: impl Into<String>, close: impl Into<String>) -> Self {
+        self.highlight = Some((open.into(), close.into()));
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct FtsMatch {
+    /// The rowid of the FTS table, which is the rowid of the content table for the external content.
+    pub rowid: i64,
+
+    /// The BM25 rank of the match, the lower is better.
+    pub rank: f64,
+    pub snippet: String,
+}
+
 #[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
 pub struct Column {
     name: String,
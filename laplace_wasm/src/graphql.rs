@@ -0,0 +1,26 @@
+//! The GraphQL schema fragment of the lapp, served by the Laplace `/graphql` gateway.
+//!
+//! The lapp with the `graphql` permission exports its schema SDL and the resolver, which executes the operation
+//! with the lapp root fields and returns the GraphQL JSON response:
+//!
+//! ```ignore
+//! #[graphql::schema]
+//! fn schema() -> String {
+//!     "type Query { notes: [Note!]! } type Note { id: ID! title: String! }".into()
+//! }
+//!
+//! #[graphql::resolve]
+//! fn resolve(request: graphql::Request) -> String {
+//!     execute(&request.query, request.operation_name.as_deref(), &request.variables_json)
+//! }
+//! ```
+
+use borsh::{BorshDeserialize, BorshSerialize};
+pub use laplace_wasm_macro::{graphql_resolve as resolve, graphql_schema as schema};
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct Request {
+    pub query: String,
+    pub operation_name: Option<String>,
+    pub variables_json: String,
+}
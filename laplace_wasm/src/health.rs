@@ -0,0 +1,4 @@
+pub use laplace_wasm_macro::health as handler;
+
+/// The result of the smoke test run by the `health` export before the staged upgrade replaces the running version.
+pub type HealthResult = Result<(), String>;
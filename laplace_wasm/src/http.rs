@@ -5,19 +5,28 @@ use borsh::io::Write;
 use borsh::{BorshDeserialize, BorshSerialize};
 pub use http::header::{self, HeaderName};
 pub use http::{self as types, HeaderMap, HeaderValue, Method, StatusCode, Uri, Version};
-pub use laplace_wasm_macro::process_http as process;
+pub use laplace_wasm_macro::{error_page, process_http as process};
 use thiserror::Error;
 
+pub use self::body::*;
 pub use self::request::*;
 pub use self::response::*;
 use crate::WasmSlice;
 
+pub mod body;
 pub mod request;
 pub mod response;
 
 pub type Result<T> = std::result::Result<T, Error>;
 pub type InvokeResult<T> = std::result::Result<T, InvokeError>;
 
+/// The server error of the lapp route, the `error_page` export returns the HTML page for it.
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct ErrorPageRequest {
+    pub status: u16,
+    pub message: String,
+}
+
 #[derive(Debug, Error, BorshDeserialize, BorshSerialize)]
 pub enum InvokeError {
     #[error("HTTP context is empty")]
@@ -40,6 +49,9 @@ pub enum InvokeError {
 
     #[error("HTTP request error: {}, {1}", display_code(.0))]
     FailRequest(Option<u16>, String),
+
+    #[error("HTTP URL \"{0}\" not allowed")]
+    ForbiddenUrl(String),
 }
 
 fn display_code(code: &Option<u16>) -> String {
@@ -0,0 +1,88 @@
+use std::io::{self, Read};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use thiserror::Error;
+
+use super::{InvokeResult, Request, Response, Result};
+use crate::WasmSlice;
+
+/// The handle of the inbound request body streamed with the `application.stream_request_body` lapp setting.
+pub const REQUEST_BODY: u64 = 0;
+
+pub type BodyResult<T> = std::result::Result<T, BodyError>;
+
+#[derive(Debug, Error, BorshDeserialize, BorshSerialize)]
+pub enum BodyError {
+    #[error("Body stream {0} is not found")]
+    StreamNotFound(u64),
+
+    #[error("Too many body streams are open")]
+    TooManyStreams,
+
+    #[error("Body read error: {0}")]
+    FailRead(String),
+}
+
+extern "C" {
+    fn http_read_body(handle: u64, max_len: u32) -> WasmSlice;
+    fn http_write_body(frame: WasmSlice);
+    fn invoke_http_stream(request: WasmSlice) -> WasmSlice;
+}
+
+/// Reads the body kept by the host in frames, so the lapp does not allocate the whole body.
+#[derive(Debug)]
+pub struct BodyReader {
+    handle: u64,
+}
+
+impl BodyReader {
+    /// The reader of the inbound request body, the body of the request passed to `process_http` is empty then.
+    pub fn request() -> Self {
+        Self { handle: REQUEST_BODY }
+    }
+
+    pub fn handle(&self) -> u64 {
+        self.handle
+    }
+
+    /// Reads the next frame of at most `max_len` bytes, the empty frame is the end of the body.
+    pub fn read_frame(&mut self, max_len: u32) -> BodyResult<Vec<u8>> {
+        let bytes = unsafe { http_read_body(self.handle, max_len).into_vec_in_wasm() };
+        BorshDeserialize::try_from_slice(&bytes).expect("Body frame should be deserializable")
+    }
+}
+
+impl Read for BodyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let max_len = u32::try_from(buf.len()).unwrap_or(u32::MAX);
+        let frame = self.read_frame(max_len).map_err(io::Error::other)?;
+        buf[..frame.len()].copy_from_slice(&frame);
+        Ok(frame.len())
+    }
+}
+
+/// The response of the outbound request with the body read in frames.
+#[derive(Debug)]
+pub struct StreamedResponse {
+    /// The status and headers of the response, its body is empty.
+    pub head: Response,
+    pub body: BodyReader,
+}
+
+/// Sends the request like `invoke`, but keeps the response body on the host to read it by frames.
+pub fn invoke_streamed(request: Request) -> Result<StreamedResponse> {
+    let request_bytes = borsh::to_vec(&request).map_err(super::Error::FailSerializeRequest)?;
+    let response_bytes = unsafe { invoke_http_stream(WasmSlice::from(request_bytes)).into_vec_in_wasm() };
+    let response: InvokeResult<(Response, u64)> =
+        BorshDeserialize::try_from_slice(&response_bytes).map_err(super::Error::FailDeserializeResponse)?;
+    let (head, handle) = response.map_err(super::Error::FailInvoke)?;
+    Ok(StreamedResponse {
+        head,
+        body: BodyReader { handle },
+    })
+}
+
+/// Appends the frame to the body of the response returned by `process_http`, the frames follow the response body.
+pub fn write_response_body(frame: &[u8]) {
+    unsafe { http_write_body(WasmSlice::from(frame.to_vec())) }
+}
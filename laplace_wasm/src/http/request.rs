@@ -13,6 +13,9 @@ use super::{
 
 pub type RequestBuilder = http::request::Builder;
 
+/// The header of the id the server assigns to every incoming request, unless the client sets it.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
 #[derive(Default)]
 pub struct Request {
     pub method: Method,
@@ -30,6 +33,11 @@ impl Request {
             ..Default::default()
         }
     }
+
+    /// Returns the id of the request to correlate the lapp logs with the server logs.
+    pub fn request_id(&self) -> Option<&str> {
+        self.headers.get(REQUEST_ID_HEADER)?.to_str().ok()
+    }
 }
 
 impl From<Request> for http::Request<Vec<u8>> {
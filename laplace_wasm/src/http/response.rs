@@ -6,8 +6,8 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use http;
 
 use super::{
-    deserialize_headers, deserialize_version, serialize_headers, serialize_version, HeaderMap, HeaderValue, StatusCode,
-    Version,
+    deserialize_headers, deserialize_version, header, serialize_headers, serialize_version, HeaderMap, HeaderValue,
+    StatusCode, Version,
 };
 
 pub type ResponseBuilder = http::response::Builder;
@@ -28,6 +28,19 @@ impl Response {
             ..Default::default()
         }
     }
+
+    /// Creates the response that is downloaded by browsers as the CSV file.
+    pub fn csv_attachment(file_name: &str, csv: impl Into<Vec<u8>>) -> Self {
+        let mut response = Self::new(csv);
+        response.headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/csv; charset=utf-8"),
+        );
+        if let Ok(disposition) = HeaderValue::from_str(&format!(r#"attachment; filename="{file_name}""#)) {
+            response.headers.insert(header::CONTENT_DISPOSITION, disposition);
+        }
+        response
+    }
 }
 
 impl From<Response> for http::Response<Vec<u8>> {
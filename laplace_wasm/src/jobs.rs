@@ -0,0 +1,18 @@
+use borsh::BorshDeserialize;
+pub use laplace_wasm_macro::job as handler;
+
+use crate::WasmSlice;
+
+/// The result of the job run by the `on_job` export, the failed job is retried later.
+pub type JobResult = Result<(), String>;
+
+extern "C" {
+    fn job_enqueue(payload: WasmSlice) -> WasmSlice;
+}
+
+/// Persists the job in the lapp database, the `on_job` export is called with the payload out of the current request.
+/// Returns the job id, requires the `database` permission.
+pub fn enqueue(payload: impl Into<Vec<u8>>) -> Result<u64, String> {
+    let bytes = unsafe { job_enqueue(WasmSlice::from(payload.into())).into_vec_in_wasm() };
+    BorshDeserialize::try_from_slice(&bytes).expect("Enqueue result should be deserializable")
+}
@@ -0,0 +1,72 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+pub use laplace_wasm_macro::lapp_message as message_handler;
+use thiserror::Error;
+
+use crate::http::{Request, Response};
+use crate::WasmSlice;
+
+pub type CallResult<T> = Result<T, CallError>;
+
+/// The request to the API of another lapp, the request URI is the path relative to the lapp root.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct LappRequest {
+    pub lapp_name: String,
+    pub request: Request,
+}
+
+/// The message to another lapp sent by the bus without the response.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct LappMessage {
+    pub lapp_name: String,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Error, BorshSerialize, BorshDeserialize)]
+pub enum CallError {
+    #[error("Lapps context is empty")]
+    EmptyContext,
+
+    #[error("Read from WASM error")]
+    CanNotReadWasmData,
+
+    #[error("Lapp request deserialization error")]
+    FailDeserializeRequest,
+
+    #[error("Request to lapp \"{0}\" is forbidden")]
+    ForbiddenLapp(String),
+
+    #[error("Request \"{1}\" to lapp \"{0}\" is forbidden")]
+    ForbiddenRequest(String, String),
+
+    #[error("Lapp call error: {0}")]
+    FailCall(String),
+}
+
+extern "C" {
+    fn invoke_lapp(request: WasmSlice) -> WasmSlice;
+    fn lapp_send(message: WasmSlice) -> WasmSlice;
+}
+
+/// Sends the HTTP request to the lapp listed in the `lapp_requests` settings, e.g. the `GET account/1` request to
+/// the `sowa` lapp is processed by its `process_http` export as the `GET /sowa/account/1` request.
+pub fn call(lapp_name: impl Into<String>, request: Request) -> CallResult<Response> {
+    let request = LappRequest {
+        lapp_name: lapp_name.into(),
+        request,
+    };
+    let request_bytes = borsh::to_vec(&request).expect("Lapp request should be serializable");
+    let bytes = unsafe { invoke_lapp(WasmSlice::from(request_bytes)).into_vec_in_wasm() };
+    BorshDeserialize::try_from_slice(&bytes).expect("Lapp call result should be deserializable")
+}
+
+/// Sends the message to the `on_lapp_message` export of the lapp listed in the `lapp_requests` settings. Returns when
+/// the message is queued to the lapp, not when it is handled.
+pub fn send(lapp_name: impl Into<String>, payload: impl Into<Vec<u8>>) -> CallResult<()> {
+    let message = LappMessage {
+        lapp_name: lapp_name.into(),
+        payload: payload.into(),
+    };
+    let message_bytes = borsh::to_vec(&message).expect("Lapp message should be serializable");
+    let bytes = unsafe { lapp_send(WasmSlice::from(message_bytes)).into_vec_in_wasm() };
+    BorshDeserialize::try_from_slice(&bytes).expect("Lapp send result should be deserializable")
+}
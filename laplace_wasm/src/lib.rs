@@ -3,11 +3,28 @@ pub extern crate borsh;
 pub use self::route::Route;
 pub use self::slice::*;
 
+pub mod blob;
+pub mod crypto;
 pub mod database;
+pub mod graphql;
+pub mod health;
 pub mod http;
+pub mod jobs;
+pub mod lapps;
+pub mod log;
+pub mod mail;
+pub mod mqtt;
+pub mod permission;
 pub mod route;
+pub mod schedule;
+pub mod settings;
 pub mod sleep;
 pub mod slice;
+pub mod template;
+pub mod time;
+pub mod timer;
+pub mod websocket;
+pub mod ws_client;
 
 #[no_mangle]
 pub unsafe fn alloc(size: u32) -> u32 {
@@ -0,0 +1,100 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::WasmSlice;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// The log record sent to the log stream of the lapp.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct Record {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+mod host {
+    use crate::WasmSlice;
+
+    extern "C" {
+        pub fn log(record: WasmSlice);
+    }
+}
+
+/// Sends the record to the log stream of the lapp, the server log shows it with the `lapp::{lapp_name}` target.
+pub fn log(level: Level, target: impl Into<String>, message: impl Into<String>) {
+    let record = Record {
+        level,
+        target: target.into(),
+        message: message.into(),
+    };
+    let record_bytes = borsh::to_vec(&record).expect("Log record should be serializable");
+    unsafe { host::log(WasmSlice::from(record_bytes)) }
+}
+
+/// Logs the formatted message with the level, the target is the module path unless it is set by `target:`.
+#[macro_export]
+macro_rules! log {
+    (target: $target:expr, $level:expr, $($arg:tt)+) => {
+        $crate::log::log($level, $target, format!($($arg)+))
+    };
+    ($level:expr, $($arg:tt)+) => {
+        $crate::log::log($level, module_path!(), format!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! error {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::log!(target: $target, $crate::log::Level::Error, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Error, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! warn {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::log!(target: $target, $crate::log::Level::Warn, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Warn, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! info {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::log!(target: $target, $crate::log::Level::Info, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Info, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! debug {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::log!(target: $target, $crate::log::Level::Debug, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Debug, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! trace {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::log!(target: $target, $crate::log::Level::Trace, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Trace, $($arg)+)
+    };
+}
@@ -0,0 +1,26 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::WasmSlice;
+
+/// The plain text mail sent from the server SMTP sender address.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+extern "C" {
+    fn send_mail(mail: WasmSlice) -> WasmSlice;
+}
+
+pub fn send(to: impl Into<String>, subject: impl Into<String>, body: impl Into<String>) -> Result<(), String> {
+    let mail = Mail {
+        to: to.into(),
+        subject: subject.into(),
+        body: body.into(),
+    };
+    let mail_bytes = borsh::to_vec(&mail).expect("Mail should be serializable");
+    let bytes = unsafe { send_mail(WasmSlice::from(mail_bytes)).into_vec_in_wasm() };
+    BorshDeserialize::try_from_slice(&bytes).expect("Send mail result should be deserializable")
+}
@@ -0,0 +1,48 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use thiserror::Error;
+
+use crate::WasmSlice;
+
+pub type PublishResult = Result<(), PublishError>;
+
+/// The MQTT message received by the `handle_mqtt` export or published by the lapp.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct Message {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub retain: bool,
+}
+
+#[derive(Debug, Error, BorshSerialize, BorshDeserialize)]
+pub enum PublishError {
+    #[error("MQTT bridge is not available")]
+    EmptyContext,
+
+    #[error("Read from WASM error")]
+    CanNotReadWasmData,
+
+    #[error("Message deserialization error")]
+    FailDeserializeMessage,
+
+    #[error("Publish to topic \"{0}\" is forbidden")]
+    ForbiddenTopic(String),
+
+    #[error("Publish error: {0}")]
+    FailPublish(String),
+}
+
+extern "C" {
+    fn mqtt_publish(message: WasmSlice) -> WasmSlice;
+}
+
+/// Publishes the message to the topic matching one of the `network.mqtt.publish` filters of the lapp.
+pub fn publish(topic: impl Into<String>, payload: impl Into<Vec<u8>>, retain: bool) -> PublishResult {
+    let message = Message {
+        topic: topic.into(),
+        payload: payload.into(),
+        retain,
+    };
+    let message_bytes = borsh::to_vec(&message).expect("MQTT message should be serializable");
+    let bytes = unsafe { mqtt_publish(WasmSlice::from(message_bytes)).into_vec_in_wasm() };
+    BorshDeserialize::try_from_slice(&bytes).expect("Publish result should be deserializable")
+}
@@ -0,0 +1,38 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use thiserror::Error;
+
+use crate::WasmSlice;
+
+pub type PermissionResult = Result<PermissionStatus, PermissionError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum PermissionStatus {
+    /// The request waits for the admin approval.
+    Pending,
+
+    /// The permission is allowed.
+    Granted,
+
+    /// The permission is denied by the admin or cannot be requested.
+    Denied,
+}
+
+#[derive(Debug, Error, BorshSerialize, BorshDeserialize)]
+pub enum PermissionError {
+    #[error("Read from WASM error")]
+    CanNotReadWasmData,
+
+    #[error("Unknown permission \"{0}\"")]
+    UnknownPermission(String),
+}
+
+extern "C" {
+    fn request_permission(permission: WasmSlice) -> WasmSlice;
+}
+
+/// Requests the permission by its name, e.g. `http`, the repeated requests poll the request status. The granted
+/// permission is available to the lapp after the re-instantiation, the running lapp is restarted by the approval.
+pub fn request(permission: impl Into<String>) -> PermissionResult {
+    let bytes = unsafe { request_permission(WasmSlice::from(permission.into())).into_vec_in_wasm() };
+    BorshDeserialize::try_from_slice(&bytes).expect("Permission result should be deserializable")
+}
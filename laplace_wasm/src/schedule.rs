@@ -0,0 +1,4 @@
+pub use laplace_wasm_macro::schedule as handler;
+
+/// The result of the scheduled job run by the `on_schedule` export, the error message is shown in the admin UI.
+pub type ScheduleResult = Result<(), String>;
@@ -0,0 +1,13 @@
+mod host {
+    use crate::WasmSlice;
+
+    extern "C" {
+        pub fn get_settings_json() -> WasmSlice;
+    }
+}
+
+/// Returns the `application` and `custom` sections of the lapp settings as the JSON object, e.g.
+/// `{"application": {"title": "Notes", ...}, "custom": {"page_size": 20}}`.
+pub fn get_settings_json() -> String {
+    unsafe { host::get_settings_json().into_string_in_wasm() }
+}
@@ -0,0 +1,49 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use thiserror::Error;
+
+use crate::WasmSlice;
+
+pub type RenderResult = Result<String, RenderError>;
+
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct RenderRequest {
+    pub template_name: String,
+    pub context_json: String,
+}
+
+#[derive(Debug, Error, BorshSerialize, BorshDeserialize)]
+pub enum RenderError {
+    #[error("Lapp has no templates")]
+    EmptyContext,
+
+    #[error("Read from WASM error")]
+    CanNotReadWasmData,
+
+    #[error("Render request deserialization error")]
+    FailDeserializeRequest,
+
+    #[error("Template \"{0}\" not found")]
+    TemplateNotFound(String),
+
+    #[error("Wrong template context JSON: {0}")]
+    WrongContext(String),
+
+    #[error("Template render error: {0}")]
+    FailRender(String),
+}
+
+extern "C" {
+    fn render_template(request: WasmSlice) -> WasmSlice;
+}
+
+/// Renders the template from the `templates` directory of the lapp with the context serialized to JSON.
+/// Templates with the `.html` extension escape the substituted values.
+pub fn render(template_name: impl Into<String>, context_json: impl Into<String>) -> RenderResult {
+    let request = RenderRequest {
+        template_name: template_name.into(),
+        context_json: context_json.into(),
+    };
+    let request_bytes = borsh::to_vec(&request).expect("Render request should be serializable");
+    let bytes = unsafe { render_template(WasmSlice::from(request_bytes)).into_vec_in_wasm() };
+    BorshDeserialize::try_from_slice(&bytes).expect("Render result should be deserializable")
+}
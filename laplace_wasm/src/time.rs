@@ -0,0 +1,16 @@
+mod host {
+    extern "C" {
+        pub fn now_millis() -> u64;
+        pub fn monotonic_nanos() -> u64;
+    }
+}
+
+/// The wall-clock time in milliseconds since the Unix epoch.
+pub fn now_millis() -> u64 {
+    unsafe { host::now_millis() }
+}
+
+/// The monotonic time in nanoseconds since an unspecified point, the difference of two values is the elapsed time.
+pub fn monotonic_nanos() -> u64 {
+    unsafe { host::monotonic_nanos() }
+}
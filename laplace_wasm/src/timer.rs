@@ -0,0 +1,24 @@
+pub use laplace_wasm_macro::timer as handler;
+
+mod host {
+    extern "C" {
+        pub fn set_timeout(millis: u64, id: u64) -> u32;
+        pub fn set_interval(millis: u64, id: u64) -> u32;
+        pub fn clear_timer(id: u64);
+    }
+}
+
+/// Calls the `on_timer` export with the timer id once after the delay, the timer with the same id is replaced.
+/// Returns `false` if the timer is not set, e.g. the instance has too many timers or is a pooled one.
+pub fn set_timeout(millis: u64, id: u64) -> bool {
+    unsafe { host::set_timeout(millis, id) != 0 }
+}
+
+/// Calls the `on_timer` export with the timer id every period until the timer is cleared.
+pub fn set_interval(millis: u64, id: u64) -> bool {
+    unsafe { host::set_interval(millis, id) != 0 }
+}
+
+pub fn clear_timer(id: u64) {
+    unsafe { host::clear_timer(id) }
+}
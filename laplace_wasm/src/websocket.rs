@@ -0,0 +1,22 @@
+use borsh::BorshDeserialize;
+
+use crate::route::websocket::Message;
+use crate::WasmSlice;
+
+extern "C" {
+    fn ws_current_client() -> u64;
+    fn ws_push(client_id: u64, msg: WasmSlice) -> WasmSlice;
+}
+
+/// Returns the id of the client session whose message is routed by `route_ws`, `None` outside of the routing.
+pub fn current_client() -> Option<u64> {
+    let client_id = unsafe { ws_current_client() };
+    (client_id != 0).then_some(client_id)
+}
+
+/// Pushes the message to the connected client session, e.g. the client remembered in `route_ws` earlier.
+pub fn push(client_id: u64, msg: Message) -> Result<(), String> {
+    let msg_bytes = borsh::to_vec(&msg).expect("Message should be serializable");
+    let bytes = unsafe { ws_push(client_id, WasmSlice::from(msg_bytes)).into_vec_in_wasm() };
+    BorshDeserialize::try_from_slice(&bytes).expect("Push result should be deserializable")
+}
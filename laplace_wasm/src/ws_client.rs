@@ -0,0 +1,100 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use thiserror::Error;
+
+use crate::WasmSlice;
+
+pub type WsResult<T> = Result<T, WsError>;
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum Frame {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// The event of the outbound connection, the connection is closed after the `Closed` event.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum Event {
+    Frame(Frame),
+    Closed { code: Option<u16>, reason: String },
+}
+
+#[derive(Debug, Error, BorshSerialize, BorshDeserialize)]
+pub enum WsError {
+    #[error("WebSocket client context is empty")]
+    EmptyContext,
+
+    #[error("Read from WASM error")]
+    CanNotReadWasmData,
+
+    #[error("Frame deserialization error")]
+    FailDeserializeFrame,
+
+    #[error("WebSocket URL \"{0}\" not allowed")]
+    ForbiddenUrl(String),
+
+    #[error("WebSocket connection {0} is not found")]
+    ConnectionNotFound(u64),
+
+    #[error("Too many WebSocket connections are open")]
+    TooManyConnections,
+
+    #[error("WebSocket connect error: {0}")]
+    FailConnect(String),
+
+    #[error("WebSocket send error: {0}")]
+    FailSend(String),
+
+    #[error("WebSocket receive error: {0}")]
+    FailReceive(String),
+}
+
+mod host {
+    use crate::WasmSlice;
+
+    extern "C" {
+        pub fn ws_connect(url: WasmSlice) -> WasmSlice;
+        pub fn ws_send(handle: u64, frame: WasmSlice) -> WasmSlice;
+        pub fn ws_receive(handle: u64, timeout_ms: u64) -> WasmSlice;
+        pub fn ws_close(handle: u64) -> WasmSlice;
+    }
+}
+
+/// The outbound WebSocket connection of the lapp server module.
+#[derive(Debug)]
+pub struct Connection {
+    handle: u64,
+}
+
+impl Connection {
+    /// Connects to the `ws://` or `wss://` URL allowed by the `network.http` settings of the lapp.
+    pub fn connect(url: impl Into<String>) -> WsResult<Self> {
+        let bytes = unsafe { host::ws_connect(WasmSlice::from(url.into())).into_vec_in_wasm() };
+        let handle: WsResult<u64> = deserialize(&bytes);
+        handle.map(|handle| Self { handle })
+    }
+
+    pub fn send(&self, frame: Frame) -> WsResult<()> {
+        let frame_bytes = borsh::to_vec(&frame).expect("Frame should be serializable");
+        let bytes = unsafe { host::ws_send(self.handle, WasmSlice::from(frame_bytes)).into_vec_in_wasm() };
+        deserialize(&bytes)
+    }
+
+    pub fn send_text(&self, text: impl Into<String>) -> WsResult<()> {
+        self.send(Frame::Text(text.into()))
+    }
+
+    /// Waits for the next event at most `timeout_ms` milliseconds, returns `None` on the timeout.
+    pub fn receive(&self, timeout_ms: u64) -> WsResult<Option<Event>> {
+        let bytes = unsafe { host::ws_receive(self.handle, timeout_ms).into_vec_in_wasm() };
+        deserialize(&bytes)
+    }
+
+    pub fn close(self) -> WsResult<()> {
+        let bytes = unsafe { host::ws_close(self.handle).into_vec_in_wasm() };
+        deserialize(&bytes)
+    }
+}
+
+fn deserialize<T: BorshDeserialize>(bytes: &[u8]) -> WsResult<T> {
+    BorshDeserialize::try_from_slice(bytes).expect("WebSocket result should be deserializable")
+}
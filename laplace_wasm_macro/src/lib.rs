@@ -6,3 +6,43 @@ mod process;
 pub fn process_http(attrs: TokenStream, input: TokenStream) -> TokenStream {
     process::http(attrs, input)
 }
+
+#[proc_macro_attribute]
+pub fn error_page(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    process::error_page(attrs, input)
+}
+
+#[proc_macro_attribute]
+pub fn graphql_schema(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    process::graphql_schema(attrs, input)
+}
+
+#[proc_macro_attribute]
+pub fn graphql_resolve(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    process::graphql_resolve(attrs, input)
+}
+
+#[proc_macro_attribute]
+pub fn schedule(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    process::schedule(attrs, input)
+}
+
+#[proc_macro_attribute]
+pub fn job(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    process::job(attrs, input)
+}
+
+#[proc_macro_attribute]
+pub fn lapp_message(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    process::lapp_message(attrs, input)
+}
+
+#[proc_macro_attribute]
+pub fn timer(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    process::timer(attrs, input)
+}
+
+#[proc_macro_attribute]
+pub fn health(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    process::health(attrs, input)
+}
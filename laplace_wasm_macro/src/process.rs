@@ -28,3 +28,175 @@ pub fn http(attrs: TokenStream, input: TokenStream) -> TokenStream {
 
     TokenStream::from(expanded)
 }
+
+pub fn error_page(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(input as ItemFn);
+    let function_name = function.sig.ident.clone();
+    let attrs = proc_macro2::TokenStream::from(attrs);
+
+    let expanded = quote! {
+        #[no_mangle]
+        pub unsafe extern "C" fn error_page(request: ::laplace_wasm::WasmSlice) -> ::laplace_wasm::WasmSlice {
+            use ::laplace_wasm::borsh::{BorshDeserialize, to_vec};
+            use ::laplace_wasm::http;
+
+            let request = request.into_vec_in_wasm();
+            let request: http::ErrorPageRequest = BorshDeserialize::try_from_slice(&request)
+                .expect("Error page request should be deserializable");
+            let page: Option<String> = #function_name(request);
+            ::laplace_wasm::WasmSlice::from(to_vec(&page).expect("Error page should be serializable"))
+        }
+
+        #attrs
+        #function
+    };
+
+    TokenStream::from(expanded)
+}
+
+pub fn graphql_schema(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(input as ItemFn);
+    let function_name = function.sig.ident.clone();
+    let attrs = proc_macro2::TokenStream::from(attrs);
+
+    let expanded = quote! {
+        #[no_mangle]
+        pub extern "C" fn graphql_schema() -> ::laplace_wasm::WasmSlice {
+            let schema: String = #function_name().into();
+            ::laplace_wasm::WasmSlice::from(schema)
+        }
+
+        #attrs
+        #function
+    };
+
+    TokenStream::from(expanded)
+}
+
+pub fn graphql_resolve(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(input as ItemFn);
+    let function_name = function.sig.ident.clone();
+    let attrs = proc_macro2::TokenStream::from(attrs);
+
+    let expanded = quote! {
+        #[no_mangle]
+        pub unsafe extern "C" fn graphql_resolve(request: ::laplace_wasm::WasmSlice) -> ::laplace_wasm::WasmSlice {
+            use ::laplace_wasm::borsh::BorshDeserialize;
+            use ::laplace_wasm::graphql;
+
+            let request = request.into_vec_in_wasm();
+            let request: graphql::Request = BorshDeserialize::try_from_slice(&request)
+                .expect("GraphQL request should be deserializable");
+            let response: String = #function_name(request);
+            ::laplace_wasm::WasmSlice::from(response)
+        }
+
+        #attrs
+        #function
+    };
+
+    TokenStream::from(expanded)
+}
+
+pub fn schedule(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(input as ItemFn);
+    let function_name = function.sig.ident.clone();
+    let attrs = proc_macro2::TokenStream::from(attrs);
+
+    let expanded = quote! {
+        #[no_mangle]
+        pub unsafe extern "C" fn on_schedule(job_name: ::laplace_wasm::WasmSlice) -> ::laplace_wasm::WasmSlice {
+            use ::laplace_wasm::borsh::to_vec;
+
+            let job_name = job_name.into_string_in_wasm();
+            let result: ::laplace_wasm::schedule::ScheduleResult = #function_name(job_name);
+            ::laplace_wasm::WasmSlice::from(to_vec(&result).expect("Schedule result should be serializable"))
+        }
+
+        #attrs
+        #function
+    };
+
+    TokenStream::from(expanded)
+}
+
+pub fn job(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(input as ItemFn);
+    let function_name = function.sig.ident.clone();
+    let attrs = proc_macro2::TokenStream::from(attrs);
+
+    let expanded = quote! {
+        #[no_mangle]
+        pub unsafe extern "C" fn on_job(payload: ::laplace_wasm::WasmSlice) -> ::laplace_wasm::WasmSlice {
+            use ::laplace_wasm::borsh::to_vec;
+
+            let payload = payload.into_vec_in_wasm();
+            let result: ::laplace_wasm::jobs::JobResult = #function_name(payload);
+            ::laplace_wasm::WasmSlice::from(to_vec(&result).expect("Job result should be serializable"))
+        }
+
+        #attrs
+        #function
+    };
+
+    TokenStream::from(expanded)
+}
+
+pub fn lapp_message(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(input as ItemFn);
+    let function_name = function.sig.ident.clone();
+    let attrs = proc_macro2::TokenStream::from(attrs);
+
+    let expanded = quote! {
+        #[no_mangle]
+        pub unsafe extern "C" fn on_lapp_message(sender: ::laplace_wasm::WasmSlice, payload: ::laplace_wasm::WasmSlice) {
+            let sender = sender.into_string_in_wasm();
+            let payload = payload.into_vec_in_wasm();
+            #function_name(sender, payload)
+        }
+
+        #attrs
+        #function
+    };
+
+    TokenStream::from(expanded)
+}
+
+pub fn timer(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(input as ItemFn);
+    let function_name = function.sig.ident.clone();
+    let attrs = proc_macro2::TokenStream::from(attrs);
+
+    let expanded = quote! {
+        #[no_mangle]
+        pub extern "C" fn on_timer(id: u64) {
+            #function_name(id)
+        }
+
+        #attrs
+        #function
+    };
+
+    TokenStream::from(expanded)
+}
+
+pub fn health(attrs: TokenStream, input: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(input as ItemFn);
+    let function_name = function.sig.ident.clone();
+    let attrs = proc_macro2::TokenStream::from(attrs);
+
+    let expanded = quote! {
+        #[no_mangle]
+        pub extern "C" fn health() -> ::laplace_wasm::WasmSlice {
+            use ::laplace_wasm::borsh::to_vec;
+
+            let result: ::laplace_wasm::health::HealthResult = #function_name();
+            ::laplace_wasm::WasmSlice::from(to_vec(&result).expect("Health result should be serializable"))
+        }
+
+        #attrs
+        #function
+    };
+
+    TokenStream::from(expanded)
+}
@@ -0,0 +1,61 @@
+use borsh::BorshDeserialize;
+use laplace_wasm::database::{execute, query, query_row};
+use laplace_wasm::http::{self, StatusCode};
+use laplace_wasm::route::websocket;
+pub use laplace_wasm::{alloc, dealloc};
+use laplace_wasm::{Route, WasmSlice};
+
+const ITEMS_COUNT: usize = 1000;
+
+#[no_mangle]
+pub extern "C" fn init() -> WasmSlice {
+    let data = borsh::to_vec(&init_items()).expect("Init result should be serializable");
+    WasmSlice::from(data)
+}
+
+fn init_items() -> Result<(), String> {
+    execute("CREATE TABLE IF NOT EXISTS Items(id INTEGER PRIMARY KEY, value TEXT NOT NULL);")?;
+    if query_row("SELECT id FROM Items LIMIT 1;")?.is_none() {
+        for id in 0..ITEMS_COUNT {
+            execute(format!("INSERT INTO Items(id, value) VALUES({id}, 'item {id}');"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Routes of the benchmarks:
+/// - `/bench/noop` returns the empty response,
+/// - `/bench/echo` returns the request body,
+/// - `/bench/db/row` queries one row,
+/// - `/bench/db/rows` queries all the rows.
+#[http::process]
+fn http(request: http::Request) -> http::Response {
+    let result = match request.uri.path() {
+        "/bench/noop" => Ok(Vec::new()),
+        "/bench/echo" => Ok(request.body),
+        "/bench/db/row" => query_row("SELECT id, value FROM Items WHERE id = 1;").map(|row| vec![row.is_some() as u8]),
+        "/bench/db/rows" => query("SELECT id, value FROM Items;").map(|rows| rows.len().to_le_bytes().to_vec()),
+        path => Err(format!("Unknown path {path}")),
+    };
+
+    match result {
+        Ok(body) => http::Response::new(body),
+        Err(err) => {
+            let mut response = http::Response::new(err.into_bytes());
+            response.status = StatusCode::BAD_REQUEST;
+            response
+        },
+    }
+}
+
+/// Sends the websocket messages back to the client.
+#[no_mangle]
+pub extern "C" fn route_ws(msg: WasmSlice) -> WasmSlice {
+    let routes = match websocket::MessageIn::try_from_slice(&unsafe { msg.into_vec_in_wasm() }) {
+        Ok(websocket::MessageIn::Message(msg)) => {
+            vec![Route::WebSocket(websocket::MessageOut { id: String::new(), msg })]
+        },
+        _ => vec![],
+    };
+    WasmSlice::from(borsh::to_vec(&routes).expect("Routes should be serializable"))
+}